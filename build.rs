@@ -11,20 +11,87 @@ fn main() {
     let path = Path::new(&env::var("OUT_DIR").unwrap()).join("codegen.rs");
     let mut file = BufWriter::new(File::create(&path).unwrap());
 
-    let mut builder = phf_codegen::Map::new();
+    let mut tables = Tables {
+        mnemonics: phf_codegen::Map::new(),
+        help: phf_codegen::Map::new(),
+        names: Vec::new(),
+    };
 
-    mnemonics().build(&mut builder);
+    mnemonics().build(&mut tables);
 
     writeln!(
         &mut file,
         "pub static MNEMONICS: phf::Map<UniCase<&'static str>, Mnemonic> = \n{};\n",
-        builder.build()
+        tables.mnemonics.build()
+    )
+    .unwrap();
+
+    writeln!(
+        &mut file,
+        "pub static MNEMONIC_HELP: phf::Map<UniCase<&'static str>, MnemonicHelp> = \n{};\n",
+        tables.help.build()
+    )
+    .unwrap();
+
+    let name_arms: String = tables
+        .names
+        .iter()
+        .map(|name| format!("            Mnemonic::{name} {{ .. }} => \"{name}\",\n"))
+        .collect();
+
+    writeln!(
+        &mut file,
+        "impl Mnemonic {{\n    /// the mnemonic without any of its flags, e.g. `ADD` for `ADDEQS`\n    pub fn name(&self) -> &'static str {{\n        match self {{\n{name_arms}        }}\n    }}\n}}"
     )
     .unwrap();
 }
 
 type Builder = phf_codegen::Map<UniCase<String>>;
 
+struct Tables {
+    /// every spelling of every mnemonic, including its flags
+    mnemonics: Builder,
+    /// reference information for each mnemonic, without its flags
+    help: Builder,
+    names: Vec<&'static str>,
+}
+
+/// reference information for a mnemonic, shown by editor hovers and `eremius help`
+struct Help {
+    /// the full name of the mnemonic
+    name: &'static str,
+    description: &'static str,
+    syntax: &'static str,
+    operands: &'static [(&'static str, &'static str)],
+    /// how the instruction affects the condition flags
+    flags: &'static str,
+}
+
+impl Help {
+    fn code(&self, mnemonic: &str) -> String {
+        let operands: String = self
+            .operands
+            .iter()
+            .map(|(operand, description)| format!("({operand:?}, {description:?}), "))
+            .collect();
+
+        format!(
+            "MnemonicHelp {{ mnemonic: {mnemonic:?}, name: {:?}, description: {:?}, syntax: {:?}, operands: &[{operands}], flags: {:?} }}",
+            self.name, self.description, self.syntax, self.flags
+        )
+    }
+}
+
+const COND_OPERAND: (&str, &str) = (
+    "<cond>",
+    "the condition under which the instruction is executed, e.g. EQ or NE",
+);
+
+const UNCHANGED_FLAGS: &str = "Doesn't change the condition flags";
+
+const DIRECTIVE_FLAGS: &str =
+    "An assembler directive, which doesn't generate an instruction or change the condition flags";
+
 const CONDITION_FLAG: Flag<18> = Flag::union(
     "condition",
     [
@@ -102,40 +169,651 @@ const LONG_FLAG: Flag<2> = Flag::bool("l");
 fn mnemonics() -> impl MnemonicTableBuilder {
     MnemonicTable
         // branch
-        .entry("B".then(LINK_FLAG).then(CONDITION_FLAG))
+        .entry(
+            "B".then(LINK_FLAG).then(CONDITION_FLAG),
+            Help {
+                name: "Branch",
+                description: "Branches to a target address, optionally storing a return address in the link register (R14)",
+                syntax: "B{L}{<cond>} <target_address>",
+                operands: &[
+                    ("L", "store the address of the next instruction in the link register"),
+                    COND_OPERAND,
+                    ("<target_address>", "the label to branch to"),
+                ],
+                flags: UNCHANGED_FLAGS,
+            },
+        )
+        .entry(
+            "BLX",
+            Help {
+                name: "Branch with Link and Exchange",
+                description: "Branches to Thumb code at a target address, storing a return address in the link register (R14). It can't be conditional",
+                syntax: "BLX <target_address>",
+                operands: &[("<target_address>", "the label of the Thumb code to branch to")],
+                flags: "Sets the T bit to switch to Thumb state",
+            },
+        )
+        .entry(
+            "BX".then(CONDITION_FLAG),
+            Help {
+                name: "Branch and Exchange",
+                description: "Branches to the address in a register, such as `BX LR` to return from a subroutine. Bit 0 of the address chooses Thumb state if it is set, or ARM state if it is clear",
+                syntax: "BX{<cond>} <Rm>",
+                operands: &[
+                    COND_OPERAND,
+                    ("<Rm>", "the register containing the target address"),
+                ],
+                flags: "Sets the T bit to bit 0 of the address",
+            },
+        )
         // data processing
-        .entry("ADD".then(CONDITION_FLAG).then(SET_FLAGS_FLAG))
-        .entry("SUB".then(CONDITION_FLAG).then(SET_FLAGS_FLAG))
-        .entry("CMP".then(CONDITION_FLAG))
-        .entry("MOV".then(CONDITION_FLAG).then(SET_FLAGS_FLAG))
+        .entry(
+            "ADD".then(CONDITION_FLAG).then(SET_FLAGS_FLAG),
+            Help {
+                name: "Add",
+                description: "Adds two values",
+                syntax: "ADD{<cond>}{S} <Rd>, <Rn>, <shifter_operand>",
+                operands: &[
+                    COND_OPERAND,
+                    ("S", "update the condition flags based on the result"),
+                    ("<Rd>", "the destination register"),
+                    ("<Rn>", "the register containing the first operand"),
+                    ("<shifter_operand>", "the second operand"),
+                ],
+                flags: "Updates N, Z, C and V if S is specified",
+            },
+        )
+        .entry(
+            "SUB".then(CONDITION_FLAG).then(SET_FLAGS_FLAG),
+            Help {
+                name: "Subtract",
+                description: "Subtracts one value from another",
+                syntax: "SUB{<cond>}{S} <Rd>, <Rn>, <shifter_operand>",
+                operands: &[
+                    COND_OPERAND,
+                    ("S", "update the condition flags based on the result"),
+                    ("<Rd>", "the destination register"),
+                    ("<Rn>", "the register containing the value to subtract from"),
+                    ("<shifter_operand>", "the value to subtract"),
+                ],
+                flags: "Updates N, Z, C and V if S is specified",
+            },
+        )
+        .entry(
+            "RSB".then(CONDITION_FLAG).then(SET_FLAGS_FLAG),
+            Help {
+                name: "Reverse Subtract",
+                description: "Subtracts a register from a value, e.g. to negate it with `RSB <Rd>, <Rn>, #0`",
+                syntax: "RSB{<cond>}{S} <Rd>, <Rn>, <shifter_operand>",
+                operands: &[
+                    COND_OPERAND,
+                    ("S", "update the condition flags based on the result"),
+                    ("<Rd>", "the destination register"),
+                    ("<Rn>", "the register containing the value to subtract"),
+                    ("<shifter_operand>", "the value to subtract from"),
+                ],
+                flags: "Updates N, Z, C and V if S is specified",
+            },
+        )
+        .entry(
+            "ADC".then(CONDITION_FLAG).then(SET_FLAGS_FLAG),
+            Help {
+                name: "Add with Carry",
+                description: "Adds two values and the carry flag, for adding numbers wider than a word",
+                syntax: "ADC{<cond>}{S} <Rd>, <Rn>, <shifter_operand>",
+                operands: &[
+                    COND_OPERAND,
+                    ("S", "update the condition flags based on the result"),
+                    ("<Rd>", "the destination register"),
+                    ("<Rn>", "the register containing the first operand"),
+                    ("<shifter_operand>", "the second operand"),
+                ],
+                flags: "Updates N, Z, C and V if S is specified",
+            },
+        )
+        .entry(
+            "SBC".then(CONDITION_FLAG).then(SET_FLAGS_FLAG),
+            Help {
+                name: "Subtract with Carry",
+                description: "Subtracts one value, and the inverse of the carry flag, from another, for subtracting numbers wider than a word",
+                syntax: "SBC{<cond>}{S} <Rd>, <Rn>, <shifter_operand>",
+                operands: &[
+                    COND_OPERAND,
+                    ("S", "update the condition flags based on the result"),
+                    ("<Rd>", "the destination register"),
+                    ("<Rn>", "the register containing the value to subtract from"),
+                    ("<shifter_operand>", "the value to subtract"),
+                ],
+                flags: "Updates N, Z, C and V if S is specified",
+            },
+        )
+        .entry(
+            "RSC".then(CONDITION_FLAG).then(SET_FLAGS_FLAG),
+            Help {
+                name: "Reverse Subtract with Carry",
+                description: "Subtracts a register, and the inverse of the carry flag, from a value",
+                syntax: "RSC{<cond>}{S} <Rd>, <Rn>, <shifter_operand>",
+                operands: &[
+                    COND_OPERAND,
+                    ("S", "update the condition flags based on the result"),
+                    ("<Rd>", "the destination register"),
+                    ("<Rn>", "the register containing the value to subtract"),
+                    ("<shifter_operand>", "the value to subtract from"),
+                ],
+                flags: "Updates N, Z, C and V if S is specified",
+            },
+        )
+        .entry(
+            "CMP".then(CONDITION_FLAG),
+            Help {
+                name: "Compare",
+                description: "Compares two values by subtracting the second from the first, discarding the result",
+                syntax: "CMP{<cond>} <Rn>, <shifter_operand>",
+                operands: &[
+                    COND_OPERAND,
+                    ("<Rn>", "the register containing the first operand"),
+                    ("<shifter_operand>", "the second operand"),
+                ],
+                flags: "Always updates N, Z, C and V",
+            },
+        )
+        .entry(
+            "CMN".then(CONDITION_FLAG),
+            Help {
+                name: "Compare Negative",
+                description: "Compares a value with the negative of another by adding them, discarding the result",
+                syntax: "CMN{<cond>} <Rn>, <shifter_operand>",
+                operands: &[
+                    COND_OPERAND,
+                    ("<Rn>", "the register containing the first operand"),
+                    ("<shifter_operand>", "the value to add"),
+                ],
+                flags: "Always updates N, Z, C and V",
+            },
+        )
+        .entry(
+            "TST".then(CONDITION_FLAG),
+            Help {
+                name: "Test",
+                description: "Tests whether any of the bits of a value are set by a bitwise AND of two values, discarding the result",
+                syntax: "TST{<cond>} <Rn>, <shifter_operand>",
+                operands: &[
+                    COND_OPERAND,
+                    ("<Rn>", "the register containing the value to test"),
+                    ("<shifter_operand>", "the bits to test"),
+                ],
+                flags: "Always updates N and Z, and C from the shifter",
+            },
+        )
+        .entry(
+            "TEQ".then(CONDITION_FLAG),
+            Help {
+                name: "Test Equivalence",
+                description: "Tests whether two values are equal by a bitwise exclusive OR of them, discarding the result",
+                syntax: "TEQ{<cond>} <Rn>, <shifter_operand>",
+                operands: &[
+                    COND_OPERAND,
+                    ("<Rn>", "the register containing the first operand"),
+                    ("<shifter_operand>", "the second operand"),
+                ],
+                flags: "Always updates N and Z, and C from the shifter",
+            },
+        )
+        .entry(
+            "MOV".then(CONDITION_FLAG).then(SET_FLAGS_FLAG),
+            Help {
+                name: "Move",
+                description: "Writes a value to a register",
+                syntax: "MOV{<cond>}{S} <Rd>, <shifter_operand>",
+                operands: &[
+                    COND_OPERAND,
+                    ("S", "update the condition flags based on the result"),
+                    ("<Rd>", "the destination register"),
+                    ("<shifter_operand>", "the value to write"),
+                ],
+                flags: "Updates N and Z, and C from the shifter, if S is specified",
+            },
+        )
+        .entry(
+            "MVN".then(CONDITION_FLAG).then(SET_FLAGS_FLAG),
+            Help {
+                name: "Move Not",
+                description: "Writes the bitwise inverse of a value to a register",
+                syntax: "MVN{<cond>}{S} <Rd>, <shifter_operand>",
+                operands: &[
+                    COND_OPERAND,
+                    ("S", "update the condition flags based on the result"),
+                    ("<Rd>", "the destination register"),
+                    ("<shifter_operand>", "the value to invert"),
+                ],
+                flags: "Updates N and Z, and C from the shifter, if S is specified",
+            },
+        )
+        // multiply
+        .entry(
+            "MUL".then(CONDITION_FLAG).then(SET_FLAGS_FLAG),
+            Help {
+                name: "Multiply",
+                description: "Multiplies two registers, keeping the low word of the product",
+                syntax: "MUL{<cond>}{S} <Rd>, <Rm>, <Rs>",
+                operands: &[
+                    COND_OPERAND,
+                    ("S", "update the condition flags based on the result"),
+                    ("<Rd>", "the destination register"),
+                    ("<Rm>", "the register containing the first operand"),
+                    ("<Rs>", "the register containing the second operand"),
+                ],
+                flags: "Updates N and Z if S is specified, leaving C and V unchanged",
+            },
+        )
+        .entry(
+            "MLA".then(CONDITION_FLAG).then(SET_FLAGS_FLAG),
+            Help {
+                name: "Multiply Accumulate",
+                description: "Multiplies two registers and adds a third, keeping the low word of the result",
+                syntax: "MLA{<cond>}{S} <Rd>, <Rm>, <Rs>, <Rn>",
+                operands: &[
+                    COND_OPERAND,
+                    ("S", "update the condition flags based on the result"),
+                    ("<Rd>", "the destination register"),
+                    ("<Rm>", "the register containing the first operand"),
+                    ("<Rs>", "the register containing the second operand"),
+                    ("<Rn>", "the register containing the value to add"),
+                ],
+                flags: "Updates N and Z if S is specified, leaving C and V unchanged",
+            },
+        )
+        .entry(
+            "UMULL".then(CONDITION_FLAG).then(SET_FLAGS_FLAG),
+            Help {
+                name: "Unsigned Multiply Long",
+                description: "Multiplies two unsigned registers into a 64-bit result in a pair of registers",
+                syntax: "UMULL{<cond>}{S} <RdLo>, <RdHi>, <Rm>, <Rs>",
+                operands: &[
+                    COND_OPERAND,
+                    ("S", "update the condition flags based on the result"),
+                    ("<RdLo>", "the destination register for the low word of the result"),
+                    ("<RdHi>", "the destination register for the high word of the result"),
+                    ("<Rm>", "the register containing the first operand"),
+                    ("<Rs>", "the register containing the second operand"),
+                ],
+                flags: "Updates N and Z from the 64-bit result if S is specified, leaving C and V unchanged",
+            },
+        )
+        .entry(
+            "UMLAL".then(CONDITION_FLAG).then(SET_FLAGS_FLAG),
+            Help {
+                name: "Unsigned Multiply Accumulate Long",
+                description: "Multiplies two unsigned registers and adds the product to the 64-bit value in a pair of registers",
+                syntax: "UMLAL{<cond>}{S} <RdLo>, <RdHi>, <Rm>, <Rs>",
+                operands: &[
+                    COND_OPERAND,
+                    ("S", "update the condition flags based on the result"),
+                    ("<RdLo>", "the register to add to and write the low word of the result to"),
+                    ("<RdHi>", "the register to add to and write the high word of the result to"),
+                    ("<Rm>", "the register containing the first operand"),
+                    ("<Rs>", "the register containing the second operand"),
+                ],
+                flags: "Updates N and Z from the 64-bit result if S is specified, leaving C and V unchanged",
+            },
+        )
+        .entry(
+            "SMULL".then(CONDITION_FLAG).then(SET_FLAGS_FLAG),
+            Help {
+                name: "Signed Multiply Long",
+                description: "Multiplies two signed registers into a 64-bit result in a pair of registers",
+                syntax: "SMULL{<cond>}{S} <RdLo>, <RdHi>, <Rm>, <Rs>",
+                operands: &[
+                    COND_OPERAND,
+                    ("S", "update the condition flags based on the result"),
+                    ("<RdLo>", "the destination register for the low word of the result"),
+                    ("<RdHi>", "the destination register for the high word of the result"),
+                    ("<Rm>", "the register containing the first operand"),
+                    ("<Rs>", "the register containing the second operand"),
+                ],
+                flags: "Updates N and Z from the 64-bit result if S is specified, leaving C and V unchanged",
+            },
+        )
+        .entry(
+            "SMLAL".then(CONDITION_FLAG).then(SET_FLAGS_FLAG),
+            Help {
+                name: "Signed Multiply Accumulate Long",
+                description: "Multiplies two signed registers and adds the product to the 64-bit value in a pair of registers",
+                syntax: "SMLAL{<cond>}{S} <RdLo>, <RdHi>, <Rm>, <Rs>",
+                operands: &[
+                    COND_OPERAND,
+                    ("S", "update the condition flags based on the result"),
+                    ("<RdLo>", "the register to add to and write the low word of the result to"),
+                    ("<RdHi>", "the register to add to and write the high word of the result to"),
+                    ("<Rm>", "the register containing the first operand"),
+                    ("<Rs>", "the register containing the second operand"),
+                ],
+                flags: "Updates N and Z from the 64-bit result if S is specified, leaving C and V unchanged",
+            },
+        )
         // load/store
-        .entry("LDR".then(CONDITION_FLAG))
-        .entry("STR".then(CONDITION_FLAG))
-        .entry("LDRB".then(CONDITION_FLAG))
-        .entry("STRB".then(CONDITION_FLAG))
-        .entry("LDM".then(CONDITION_FLAG).then(LDM_MODE_FLAG))
-        .entry("STM".then(CONDITION_FLAG).then(STM_MODE_FLAG))
+        .entry(
+            "LDR".then(CONDITION_FLAG),
+            Help {
+                name: "Load Register",
+                description: "Loads a word from memory into a register, or loads a constant with `LDR <Rd>, =<expression>`",
+                syntax: "LDR{<cond>} <Rd>, <addressing_mode>",
+                operands: &[
+                    COND_OPERAND,
+                    ("<Rd>", "the destination register"),
+                    ("<addressing_mode>", "the address to load from"),
+                ],
+                flags: UNCHANGED_FLAGS,
+            },
+        )
+        .entry(
+            "STR".then(CONDITION_FLAG),
+            Help {
+                name: "Store Register",
+                description: "Stores a word from a register into memory",
+                syntax: "STR{<cond>} <Rd>, <addressing_mode>",
+                operands: &[
+                    COND_OPERAND,
+                    ("<Rd>", "the register containing the value to store"),
+                    ("<addressing_mode>", "the address to store to"),
+                ],
+                flags: UNCHANGED_FLAGS,
+            },
+        )
+        .entry(
+            "LDRB".then(CONDITION_FLAG),
+            Help {
+                name: "Load Register Byte",
+                description: "Loads a byte from memory into the bottom 8 bits of a register, zeroing the rest",
+                syntax: "LDR{<cond>}B <Rd>, <addressing_mode>",
+                operands: &[
+                    COND_OPERAND,
+                    ("<Rd>", "the destination register"),
+                    ("<addressing_mode>", "the address to load from"),
+                ],
+                flags: UNCHANGED_FLAGS,
+            },
+        )
+        .entry(
+            "PLD",
+            Help {
+                name: "Preload Data",
+                description: "Hints that data will soon be loaded from an address, so it can be cached. It can't be conditional",
+                syntax: "PLD <addressing_mode>",
+                operands: &[(
+                    "<addressing_mode>",
+                    "the address that will be loaded from, which can't be pre- or post-indexed",
+                )],
+                flags: UNCHANGED_FLAGS,
+            },
+        )
+        .entry(
+            "STRB".then(CONDITION_FLAG),
+            Help {
+                name: "Store Register Byte",
+                description: "Stores the bottom 8 bits of a register into memory",
+                syntax: "STR{<cond>}B <Rd>, <addressing_mode>",
+                operands: &[
+                    COND_OPERAND,
+                    ("<Rd>", "the register containing the value to store"),
+                    ("<addressing_mode>", "the address to store to"),
+                ],
+                flags: UNCHANGED_FLAGS,
+            },
+        )
+        .entry(
+            "LDM".then(CONDITION_FLAG).then(LDM_MODE_FLAG),
+            Help {
+                name: "Load Multiple",
+                description: "Loads a list of registers from consecutive words of memory, e.g. to pop from a stack",
+                syntax: "LDM{<cond>}<addressing_mode> <Rn>{!}, <registers>",
+                operands: &[
+                    COND_OPERAND,
+                    ("<addressing_mode>", "how the address changes between words: IA, IB, DA or DB, or the stack aliases FD, ED, FA or EA"),
+                    ("<Rn>", "the register containing the start address"),
+                    ("!", "write the final address back to Rn"),
+                    ("<registers>", "the registers to load, e.g. {R0-R3, LR}"),
+                ],
+                flags: UNCHANGED_FLAGS,
+            },
+        )
+        .entry(
+            "STM".then(CONDITION_FLAG).then(STM_MODE_FLAG),
+            Help {
+                name: "Store Multiple",
+                description: "Stores a list of registers into consecutive words of memory, e.g. to push to a stack",
+                syntax: "STM{<cond>}<addressing_mode> <Rn>{!}, <registers>",
+                operands: &[
+                    COND_OPERAND,
+                    ("<addressing_mode>", "how the address changes between words: IA, IB, DA or DB, or the stack aliases FD, ED, FA or EA"),
+                    ("<Rn>", "the register containing the start address"),
+                    ("!", "write the final address back to Rn"),
+                    ("<registers>", "the registers to store, e.g. {R0-R3, LR}"),
+                ],
+                flags: UNCHANGED_FLAGS,
+            },
+        )
         // supervisor call
-        .entry("SVC".then(CONDITION_FLAG))
+        .entry(
+            "SVC".then(CONDITION_FLAG),
+            Help {
+                name: "SuperVisor Call",
+                description: "Calls a system function, e.g. to output a character or halt the program",
+                syntax: "SVC{<cond>} <immed_24>",
+                operands: &[
+                    COND_OPERAND,
+                    ("<immed_24>", "the number of the system function to call"),
+                ],
+                flags: UNCHANGED_FLAGS,
+            },
+        )
+        // status register access
+        .entry(
+            "MRS".then(CONDITION_FLAG),
+            Help {
+                name: "Move Status Register to Register",
+                description: "Copies the CPSR to a register, e.g. to save the flags or read the mode",
+                syntax: "MRS{<cond>} <Rd>, CPSR",
+                operands: &[
+                    COND_OPERAND,
+                    ("<Rd>", "the destination register"),
+                ],
+                flags: UNCHANGED_FLAGS,
+            },
+        )
+        .entry(
+            "MSR".then(CONDITION_FLAG),
+            Help {
+                name: "Move to Status Register",
+                description: "Writes fields of the CPSR from an immediate or a register, e.g. to restore the flags. In user mode only the flags field is written",
+                syntax: "MSR{<cond>} CPSR_<fields>, #<immediate> or MSR{<cond>} CPSR_<fields>, <Rm>",
+                operands: &[
+                    COND_OPERAND,
+                    ("<fields>", "the fields to write, any of f (flags, bits 24-31), s (bits 16-23), x (bits 8-15) and c (control, bits 0-7), e.g. CPSR_fc. CPSR on its own writes the f and c fields"),
+                    ("<immediate>", "the value to write, which must be an 8-bit value rotated right by an even amount"),
+                    ("<Rm>", "the register containing the value to write"),
+                ],
+                flags: "Sets N, Z, C, V and Q from bits 31-27 of the value if the f field is written",
+            },
+        )
+        // miscellaneous arithmetic
+        .entry(
+            "CLZ".then(CONDITION_FLAG),
+            Help {
+                name: "Count Leading Zeros",
+                description: "Counts the zero bits above the highest set bit of a register, or 32 if it is 0, e.g. to normalise a value by shifting it left by the count",
+                syntax: "CLZ{<cond>} <Rd>, <Rm>",
+                operands: &[
+                    COND_OPERAND,
+                    ("<Rd>", "the destination register"),
+                    ("<Rm>", "the register containing the value to count the leading zeros of"),
+                ],
+                flags: UNCHANGED_FLAGS,
+            },
+        )
         // pseudo-instructions
-        .entry("ADR".then(LONG_FLAG).then(CONDITION_FLAG))
+        .entry(
+            "ADR".then(LONG_FLAG).then(CONDITION_FLAG),
+            Help {
+                name: "Address Register",
+                description: "Loads an address into a register, using one data processing instruction, or two with the L flag",
+                syntax: "ADR{L}{<cond>} <Rd>, <target_address>",
+                operands: &[
+                    ("L", "allow two instructions to be generated, for a wider range of addresses"),
+                    COND_OPERAND,
+                    ("<Rd>", "the destination register"),
+                    ("<target_address>", "the label whose address is loaded"),
+                ],
+                flags: UNCHANGED_FLAGS,
+            },
+        )
+        .entry(
+            "MOV32".then(CONDITION_FLAG),
+            Help {
+                name: "Move 32-bit Constant",
+                description: "Builds any 32-bit constant in a register from the fewest MOV, MVN and ORR instructions, without a literal pool",
+                syntax: "MOV32{<cond>} <Rd>, #<constant>",
+                operands: &[
+                    COND_OPERAND,
+                    ("<Rd>", "the destination register"),
+                    ("<constant>", "the value to load, which can only refer to symbols defined before it"),
+                ],
+                flags: UNCHANGED_FLAGS,
+            },
+        )
         // directives
-        .entry("DEFS")
-        .entry("DEFB")
-        .entry("DEFW")
-        .entry("ALIGN")
-        .entry("ORIGIN")
-        .entry("ENTRY")
-        .entry("EQU")
+        .entry(
+            "DEFS",
+            Help {
+                name: "Define Space",
+                description: "Reserves a block of memory, optionally filling each byte with a value",
+                syntax: "DEFS <size>{, <fill>}",
+                operands: &[
+                    ("<size>", "the number of bytes to reserve"),
+                    ("<fill>", "the value to put in each byte, which is 0 if omitted"),
+                ],
+                flags: DIRECTIVE_FLAGS,
+            },
+        )
+        .entry(
+            "DEFB",
+            Help {
+                name: "Define Bytes",
+                description: "Places bytes or strings in memory",
+                syntax: "DEFB <expression>{, ...}",
+                operands: &[("<expression>", "a byte value or string to place in memory")],
+                flags: DIRECTIVE_FLAGS,
+            },
+        )
+        .entry(
+            "DEFW",
+            Help {
+                name: "Define Words",
+                description: "Places words in memory",
+                syntax: "DEFW <expression>{, ...}",
+                operands: &[("<expression>", "a word value to place in memory")],
+                flags: DIRECTIVE_FLAGS,
+            },
+        )
+        .entry(
+            "ALIGN",
+            Help {
+                name: "Align Address",
+                description: "Aligns the following code to the next word boundary",
+                syntax: "ALIGN",
+                operands: &[],
+                flags: DIRECTIVE_FLAGS,
+            },
+        )
+        .entry(
+            "ORIGIN",
+            Help {
+                name: "Set Origin Address",
+                description: "Sets the address of the following code",
+                syntax: "ORIGIN <target_address>",
+                operands: &[("<target_address>", "the address to place the following code at")],
+                flags: DIRECTIVE_FLAGS,
+            },
+        )
+        .entry(
+            "ENTRY",
+            Help {
+                name: "Set Entry Point",
+                description: "Makes the following code the entry point of the program",
+                syntax: "ENTRY",
+                operands: &[],
+                flags: DIRECTIVE_FLAGS,
+            },
+        )
+        .entry(
+            "EQU",
+            Help {
+                name: "Equals",
+                description: "Defines the label on the same line as a name for a value",
+                syntax: "<label> EQU <expression>",
+                operands: &[
+                    ("<label>", "the name to define"),
+                    ("<expression>", "the value of the name"),
+                ],
+                flags: DIRECTIVE_FLAGS,
+            },
+        )
+        .entry(
+            "SECTION",
+            Help {
+                name: "Start Section",
+                description: "Places the following code in a section, at the next free address of the memory region the section is placed in",
+                syntax: "SECTION <name>",
+                operands: &[("<name>", "the name of the section")],
+                flags: DIRECTIVE_FLAGS,
+            },
+        )
+        .entry(
+            "LTORG",
+            Help {
+                name: "Literal Pool",
+                description: "Places the values of the preceding LDR= pseudo-instructions that can't be a MOV here, rather than at the end of the program",
+                syntax: "LTORG",
+                operands: &[],
+                flags: DIRECTIVE_FLAGS,
+            },
+        )
+        .entry(
+            "CHECKSUM",
+            Help {
+                name: "Checksum",
+                description: "Defines a word holding the sum of the bytes from one label up to another, ignoring overflow, so a bootloader can check the image",
+                syntax: "CHECKSUM <start>, <end>",
+                operands: &[
+                    ("<start>", "the label of the first byte to check"),
+                    ("<end>", "the label after the last byte to check"),
+                ],
+                flags: DIRECTIVE_FLAGS,
+            },
+        )
+        .entry(
+            "CRC32",
+            Help {
+                name: "CRC-32",
+                description: "Defines a word holding the CRC-32 of the bytes from one label up to another, as used by zip and Ethernet",
+                syntax: "CRC32 <start>, <end>",
+                operands: &[
+                    ("<start>", "the label of the first byte to check"),
+                    ("<end>", "the label after the last byte to check"),
+                ],
+                flags: DIRECTIVE_FLAGS,
+            },
+        )
 }
 
 trait MnemonicTableBuilder: Sized {
-    fn build(self, builder: &mut Builder);
+    fn build(self, tables: &mut Tables);
 
-    fn entry<M: MnemonicBuilder>(self, mnemonic: M) -> MnemonicEntry<Self, M> {
+    fn entry<M: MnemonicBuilder>(self, mnemonic: M, help: Help) -> MnemonicEntry<Self, M> {
         MnemonicEntry {
             table: self,
             mnemonic,
+            help,
         }
     }
 }
@@ -143,23 +821,35 @@ trait MnemonicTableBuilder: Sized {
 struct MnemonicTable;
 
 impl MnemonicTableBuilder for MnemonicTable {
-    fn build(self, _builder: &mut Builder) {}
+    fn build(self, _tables: &mut Tables) {}
 }
 
 struct MnemonicEntry<T: MnemonicTableBuilder, M: MnemonicBuilder> {
     table: T,
     mnemonic: M,
+    help: Help,
 }
 
 impl<T: MnemonicTableBuilder, M: MnemonicBuilder> MnemonicTableBuilder for MnemonicEntry<T, M> {
-    fn build(self, builder: &mut Builder) {
-        self.table.build(builder);
+    fn build(self, tables: &mut Tables) {
+        self.table.build(tables);
+
+        self.mnemonic
+            .build(&mut tables.mnemonics, "".to_owned(), &vec![]);
 
-        self.mnemonic.build(builder, "".to_owned(), &vec![]);
+        let name = self.mnemonic.name();
+
+        tables
+            .help
+            .entry(UniCase::new(name.to_owned()), &self.help.code(name));
+        tables.names.push(name);
     }
 }
 
 trait MnemonicBuilder: Sized {
+    /// the mnemonic without any flags
+    fn name(&self) -> &'static str;
+
     fn build(
         &self,
         builder: &mut Builder,
@@ -176,6 +866,10 @@ trait MnemonicBuilder: Sized {
 }
 
 impl MnemonicBuilder for &'static str {
+    fn name(&self) -> &'static str {
+        self
+    }
+
     fn build(
         &self,
         builder: &mut Builder,
@@ -205,6 +899,10 @@ struct MnemonicWithFlag<M: MnemonicBuilder, const C: usize> {
 }
 
 impl<M: MnemonicBuilder, const C: usize> MnemonicBuilder for MnemonicWithFlag<M, C> {
+    fn name(&self) -> &'static str {
+        self.mnemonic.name()
+    }
+
     fn build(
         &self,
         builder: &mut Builder,
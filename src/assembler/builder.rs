@@ -0,0 +1,130 @@
+use std::fmt::{Display, Write};
+
+use super::{AssemblyError, AssemblyOptions, Program};
+
+/// writes the source of a program from Rust, for tests, code generators and property-based testing, rather than filling in a template
+///
+/// It is built like `ProgramBuilder::new().label("main").instruction("ADD R0, R1, R2").words(&[1, 2])`, with a line of source for each call.
+/// The source is checked when it is assembled by [`ProgramBuilder::build`], so errors point at the line the call wrote.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ProgramBuilder {
+    source: String,
+}
+
+impl ProgramBuilder {
+    pub fn new() -> ProgramBuilder {
+        ProgramBuilder::default()
+    }
+
+    /// defines a label at the address of whatever comes next
+    pub fn label(self, name: &str) -> ProgramBuilder {
+        self.line(name, "")
+    }
+
+    /// an instruction or pseudo-instruction, written as it would be in source, e.g. `"LDR R0, =table"`
+    pub fn instruction(self, instruction: &str) -> ProgramBuilder {
+        self.line("", instruction)
+    }
+
+    /// a `DEFW` of words
+    pub fn words(self, words: &[u32]) -> ProgramBuilder {
+        let words: Vec<String> = words.iter().map(|word| word.to_string()).collect();
+
+        self.line("", &format!("DEFW {}", words.join(", ")))
+    }
+
+    /// a `DEFB` of bytes
+    pub fn bytes(self, bytes: &[u8]) -> ProgramBuilder {
+        let bytes: Vec<String> = bytes.iter().map(|byte| byte.to_string()).collect();
+
+        self.line("", &format!("DEFB {}", bytes.join(", ")))
+    }
+
+    /// a `DEFB` of a string followed by a zero byte, with printable characters kept in quotes so the source can be read
+    pub fn string(self, text: &str) -> ProgramBuilder {
+        let mut operands: Vec<String> = Vec::new();
+        let mut quoted = String::new();
+
+        for byte in text.bytes().chain([0]) {
+            if (byte.is_ascii_graphic() || byte == b' ') && byte != b'"' && byte != b'\\' {
+                quoted.push(byte as char);
+                continue;
+            }
+
+            if !quoted.is_empty() {
+                operands.push(format!("\"{}\"", std::mem::take(&mut quoted)));
+            }
+            operands.push(byte.to_string());
+        }
+
+        self.line("", &format!("DEFB {}", operands.join(", ")))
+    }
+
+    /// a `DEFS` of `size` zero bytes
+    pub fn space(self, size: u32) -> ProgramBuilder {
+        self.line("", &format!("DEFS {size}"))
+    }
+
+    /// defines a name for a value with `EQU`
+    pub fn constant(self, name: &str, value: u32) -> ProgramBuilder {
+        self.line(name, &format!("EQU {value}"))
+    }
+
+    /// aligns what comes next to a word boundary
+    pub fn align(self) -> ProgramBuilder {
+        self.line("", "ALIGN")
+    }
+
+    /// places what comes next at the start of the program
+    pub fn entry(self) -> ProgramBuilder {
+        self.line("", "ENTRY")
+    }
+
+    /// places what comes next at `address`
+    pub fn origin(self, address: u32) -> ProgramBuilder {
+        self.line("", &format!("ORIGIN {address:#X}"))
+    }
+
+    pub fn section(self, name: &str) -> ProgramBuilder {
+        self.line("", &format!("SECTION {name}"))
+    }
+
+    /// a comment on a line of its own
+    pub fn comment(mut self, text: &str) -> ProgramBuilder {
+        for line in text.lines() {
+            writeln!(self.source, "; {line}").unwrap();
+        }
+        self
+    }
+
+    /// the source written so far
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// assembles the source written
+    pub fn build(&self) -> Result<Program, AssemblyError> {
+        Program::assemble(&self.source)
+    }
+
+    /// assembles the source written, with options such as a runtime or layout
+    pub fn build_with(&self, options: AssemblyOptions) -> Result<Program, AssemblyError> {
+        Program::assemble_with(&self.source, options)
+    }
+
+    fn line(mut self, label: &str, statement: &str) -> ProgramBuilder {
+        match statement {
+            "" => writeln!(self.source, "{label}"),
+            statement => writeln!(self.source, "{label}\t{statement}"),
+        }
+        .unwrap();
+        self
+    }
+}
+
+impl Display for ProgramBuilder {
+    /// writes the source
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
@@ -0,0 +1,79 @@
+use std::ops::Range;
+
+use crate::{ir::ByteOrder, parser::ChecksumKind};
+
+use super::{AssembledStatement, Program};
+
+/// the reversed polynomial of the CRC-32 used by zip and Ethernet
+const CRC32_POLYNOMIAL: u32 = 0xEDB88320;
+
+impl ChecksumKind {
+    /// the checksum of `bytes`
+    pub fn compute(&self, bytes: impl IntoIterator<Item = u8>) -> u32 {
+        match self {
+            ChecksumKind::Sum => bytes
+                .into_iter()
+                .fold(0, |sum: u32, byte| sum.wrapping_add(byte as u32)),
+            ChecksumKind::Crc32 => {
+                let crc = bytes.into_iter().fold(u32::MAX, |crc, byte| {
+                    (0..8).fold(crc ^ byte as u32, |crc, _| match crc & 1 {
+                        1 => (crc >> 1) ^ CRC32_POLYNOMIAL,
+                        _ => crc >> 1,
+                    })
+                });
+
+                !crc
+            }
+        }
+    }
+}
+
+impl Program {
+    /// the checksum of the program's bytes in `range`, e.g. to sign an image or check the word of a CHECKSUM or CRC32 directive
+    ///
+    /// Gaps between statements, such as those left by ORIGIN, are skipped rather than counted as zeroes.
+    pub fn checksum(&self, kind: ChecksumKind, range: Range<usize>) -> u32 {
+        kind.compute(bytes_in(&self.statements, range))
+    }
+}
+
+/// fills in the words of the CHECKSUM and CRC32 directives at the `checksums` indices of `statements`, in `order`
+///
+/// They are filled in in order of address, so a checksum that covers an earlier one includes its value, and the word of a checksum that covers itself is counted as zero.
+pub(super) fn fill(
+    statements: &mut [AssembledStatement],
+    checksums: &[(usize, ChecksumKind, Range<usize>)],
+    order: ByteOrder,
+) {
+    let mut checksums = checksums.to_vec();
+    checksums.sort_by_key(|(index, ..)| statements[*index].address);
+
+    for (index, ..) in &checksums {
+        statements[*index].bytes = [0; 4].into_iter().collect();
+    }
+
+    for (index, kind, range) in checksums {
+        let checksum = kind.compute(bytes_in(statements, range));
+
+        statements[index].bytes = order.word_to_bytes(checksum).into_iter().collect();
+    }
+}
+
+/// the bytes of the statements in `range`, in order of address
+fn bytes_in(statements: &[AssembledStatement], range: Range<usize>) -> Vec<u8> {
+    let mut bytes: Vec<(usize, u8)> = statements
+        .iter()
+        .flat_map(|statement| {
+            statement
+                .bytes
+                .iter()
+                .enumerate()
+                .map(|(offset, byte)| (statement.address + offset, *byte))
+        })
+        .filter(|(address, _)| range.contains(address))
+        .collect();
+
+    bytes.sort_by_key(|(address, _)| *address);
+
+    bytes.into_iter().map(|(_, byte)| byte).collect()
+}
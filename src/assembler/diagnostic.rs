@@ -0,0 +1,320 @@
+use std::fmt::Display;
+
+use crate::{
+    ir::{ImmediateEncoding, Shift},
+    parser::ParseError,
+    preprocessor::PreProcessError,
+    resolver::{ResolveError, UnencodableStatement},
+    span::Span,
+};
+
+use super::{AssemblyError, AssemblyWarning, Program};
+
+/// an error or warning from any phase of assembly, in one shape, so a frontend can present them all the same way
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// the phase of assembly that found the problem
+    pub phase: Phase,
+    /// the source the problem is in, or `None` if it isn't in a single place, e.g. a symbol that isn't defined
+    pub span: Option<Span>,
+    pub message: String,
+    /// a suggestion for fixing the problem
+    pub help: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// the phases of assembly, in the order they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+    /// splitting the source into tokens
+    Lex,
+    /// turning the tokens of each line into a statement
+    Parse,
+    /// giving each statement an address, and expanding pseudo-instructions
+    PreProcess,
+    /// working out the values of symbols and expressions
+    Resolve,
+    /// fitting the values into instructions
+    Encode,
+}
+
+impl Diagnostic {
+    fn error(phase: Phase, span: impl Into<Option<Span>>, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            phase,
+            span: span.into(),
+            message: message.into(),
+            help: None,
+        }
+    }
+
+    fn warning(phase: Phase, span: Span, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            phase,
+            span: Some(span),
+            message: message.into(),
+            help: None,
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    /// formats the diagnostic on one line, e.g. `3:9: error: Invalid Register`, followed by a line for the help if there is any
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(span) = &self.span {
+            write!(f, "{span}: ")?;
+        }
+
+        match self.severity {
+            Severity::Error => write!(f, "error: {}", self.message)?,
+            Severity::Warning => write!(f, "warning: {}", self.message)?,
+        }
+
+        if let Some(help) = &self.help {
+            write!(f, "\nhelp: {help}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Diagnostic {
+        let phase = match error.is_lexical() {
+            true => Phase::Lex,
+            false => Phase::Parse,
+        };
+
+        Diagnostic::error(phase, error.span.clone(), error.message())
+    }
+}
+
+impl From<&PreProcessError> for Diagnostic {
+    fn from(error: &PreProcessError) -> Diagnostic {
+        let (span, message) = match error {
+            PreProcessError::ParseError(error) => return error.into(),
+            PreProcessError::OriginAddressError { span } => (
+                span,
+                "the address of the ORIGIN can't be worked out from the symbols defined before it"
+                    .to_owned(),
+            ),
+            PreProcessError::SpaceExpressionError { span } => (
+                span,
+                "the size or fill value of the DEFS can't be worked out from the symbols defined before it"
+                    .to_owned(),
+            ),
+            PreProcessError::ConstantExpressionError { span } => (
+                span,
+                "the value of the MOV32 can't be worked out from the symbols defined before it"
+                    .to_owned(),
+            ),
+            PreProcessError::SpaceSizeOutOfRange { span, size } => (
+                span,
+                format!("a DEFS of {size} bytes doesn't fit in memory"),
+            ),
+            PreProcessError::SpaceFillOutOfRange { span, fill } => (
+                span,
+                format!("the fill value {fill} of the DEFS doesn't fit in a byte"),
+            ),
+            PreProcessError::UnplacedSection { span, section } => (
+                span,
+                format!("the section `{section}` isn't placed in a region by the layout"),
+            ),
+            PreProcessError::OutsideRegion { span, region } => (
+                span,
+                format!("the statement doesn't fit in the region `{region}`"),
+            ),
+            PreProcessError::MultipleEntryPoints { span, previous } => (
+                span,
+                format!("there is already an ENTRY at {previous}"),
+            ),
+            PreProcessError::EntryPointInData { span } => {
+                (span, "the ENTRY is followed by data".to_owned())
+            }
+            PreProcessError::EntryPointWithoutCode { span } => {
+                (span, "the ENTRY isn't followed by any code".to_owned())
+            }
+        };
+
+        Diagnostic::error(Phase::PreProcess, span.clone(), message)
+    }
+}
+
+impl From<&ResolveError> for Diagnostic {
+    fn from(error: &ResolveError) -> Diagnostic {
+        match error {
+            ResolveError::SymbolNotFound(_) => {
+                Diagnostic::error(Phase::Resolve, None, "a symbol isn't defined")
+            }
+            ResolveError::UnalignedBranchTarget(target) => Diagnostic::error(
+                Phase::Resolve,
+                None,
+                format!("the target of the BLX, {target:#010X}, isn't halfword aligned"),
+            ),
+            ResolveError::ShiftOutOfRange { kind, amount } => Diagnostic::error(
+                Phase::Resolve,
+                None,
+                format!(
+                    "{} can shift by at most {}, not {amount}",
+                    shift_name(*kind),
+                    kind.max_amount()
+                ),
+            ),
+            ResolveError::ChecksumRange { start, end } => Diagnostic::error(
+                Phase::Resolve,
+                None,
+                format!("the checksum ends at {end:#010X}, before it starts at {start:#010X}"),
+            ),
+            ResolveError::UnencodableValue(_) | ResolveError::UnencodableSignedValue(_) => {
+                let (value, encoding) = error.unencodable().unwrap();
+
+                Diagnostic::error(Phase::Encode, None, unencodable_message(value, encoding))
+            }
+        }
+    }
+}
+
+impl From<&UnencodableStatement> for Diagnostic {
+    fn from(statement: &UnencodableStatement) -> Diagnostic {
+        let mut message = unencodable_message(statement.value, statement.encoding);
+
+        if !statement.alternatives.is_empty() {
+            let alternatives: Vec<String> = statement
+                .alternatives
+                .iter()
+                .map(|alternative| match *alternative < statement.value {
+                    true => format!("{alternative} below"),
+                    false => format!("{alternative} above"),
+                })
+                .collect();
+
+            message += &format!(
+                ", the nearest values that fit are {}",
+                alternatives.join(" and ")
+            );
+        }
+
+        let mut diagnostic = Diagnostic::error(Phase::Encode, statement.span.clone(), message);
+
+        // any word can be loaded from a literal pool, which the assembler places itself
+        if statement.encoding == ImmediateEncoding::Rotated {
+            diagnostic.help = Some(format!(
+                "load the value from a literal pool with `LDR Rd, ={}` instead",
+                statement.value
+            ));
+        }
+
+        diagnostic
+    }
+}
+
+impl From<&AssemblyWarning> for Diagnostic {
+    fn from(warning: &AssemblyWarning) -> Diagnostic {
+        match warning {
+            AssemblyWarning::ParseWarning(warning) => {
+                Diagnostic::warning(Phase::Parse, warning.span.clone(), warning.message())
+            }
+            AssemblyWarning::SymbolCaseMismatch {
+                span,
+                reference,
+                definition,
+            } => Diagnostic::warning(
+                Phase::Resolve,
+                span.clone(),
+                format!(
+                    "`{}` is written with different case to its definition `{}`",
+                    reference.0, definition.0
+                ),
+            ),
+            AssemblyWarning::LiteralPoolPlaced { span, address } => Diagnostic::warning(
+                Phase::PreProcess,
+                span.clone(),
+                format!("the literal is out of range of the end of the program, so a literal pool was placed at {address:#010X} with a branch over it"),
+            ),
+            AssemblyWarning::PcLint { span, lint } => Diagnostic {
+                help: Some(lint.explanation().to_owned()),
+                ..Diagnostic::warning(Phase::Parse, span.clone(), lint.message())
+            },
+            AssemblyWarning::JumpTable { span, problem } => {
+                Diagnostic::warning(Phase::Resolve, span.clone(), problem.to_string())
+            }
+        }
+    }
+}
+
+impl AssemblyError {
+    /// the problems the error is made of, in the order they appear in the source
+    ///
+    /// Most errors are a single problem, but every value that can't be encoded is reported at once. Problems that aren't in a single place come last.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = match self {
+            AssemblyError::PreProcessError(error) => vec![error.into()],
+            AssemblyError::ResolveError(error) => vec![error.into()],
+            AssemblyError::NotAnInstruction(span) => vec![Diagnostic::error(
+                Phase::Parse,
+                span.clone(),
+                "expected a single instruction, not a directive",
+            )],
+            AssemblyError::UnmappedAddress(address) => vec![Diagnostic::error(
+                Phase::Encode,
+                None,
+                format!("{address:#010X} isn't part of the program"),
+            )],
+            AssemblyError::Unencodable(statements) => statements.iter().map(Into::into).collect(),
+        };
+
+        sort_by_source(&mut diagnostics);
+
+        diagnostics
+    }
+}
+
+impl Program {
+    /// the program's warnings as diagnostics, in the order they appear in the source
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let mut diagnostics: Vec<Diagnostic> = self.warnings.iter().map(Into::into).collect();
+
+        sort_by_source(&mut diagnostics);
+
+        diagnostics
+    }
+}
+
+/// sorts diagnostics by where they start in the source, keeping the ones without a span at the end
+fn sort_by_source(diagnostics: &mut [Diagnostic]) {
+    diagnostics.sort_by_key(|diagnostic| {
+        diagnostic
+            .span
+            .as_ref()
+            .map_or(usize::MAX, |span| span.byte_range.start)
+    });
+}
+
+fn shift_name(kind: Shift) -> &'static str {
+    match kind {
+        Shift::LogicalShiftLeft => "LSL",
+        Shift::LogicalShiftRight => "LSR",
+        Shift::ArithmeticShiftRight => "ASR",
+        Shift::RotateRight => "ROR",
+    }
+}
+
+fn unencodable_message(value: i64, encoding: ImmediateEncoding) -> String {
+    let encoding = match encoding {
+        ImmediateEncoding::Rotated => {
+            "an 8-bit value rotated right by an even number of bits".to_owned()
+        }
+        ImmediateEncoding::Unsigned { bits } => format!("a {bits}-bit unsigned value"),
+        ImmediateEncoding::Signed { bits } => format!("a {bits}-bit signed value"),
+    };
+
+    format!("{value} can't be encoded as {encoding}")
+}
@@ -0,0 +1,314 @@
+use std::fmt::Display;
+
+use crate::ir::{
+    register_name, BranchKind, Condition, InstructionKind, LoadStoreKind, RegisterList,
+};
+
+use super::{InstructionSite, Program};
+
+/// the stalls of a simple in-order pipeline, in cycles, see [`Program::hazards`]
+///
+/// The default is a five-stage pipeline like the ARM9TDMI's, where a load's result is ready a cycle after the instruction after it needs it,
+/// and the two instructions fetched after an instruction that writes the PC are thrown away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PipelineModel {
+    /// the cycles an instruction waits when it uses the register loaded by the instruction before it
+    pub load_use: u64,
+    /// the cycles lost refilling the pipeline when the PC is written, e.g. by a taken branch
+    pub refill: u64,
+}
+
+/// a place where the pipeline stalls or is refilled, along with how to avoid it if there is a way
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hazard {
+    /// the address of the instruction that stalls, or that writes the PC
+    pub address: usize,
+    pub source_line: usize,
+    pub kind: HazardKind,
+    /// the cycles lost each time the instruction is executed, or its branch is taken
+    pub penalty: u64,
+    pub suggestion: Option<Suggestion>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HazardKind {
+    /// the instruction uses `register` straight after the load on `load_line` loads it
+    LoadUse { register: u8, load_line: usize },
+    /// an instruction other than a branch writes the PC, e.g. `MOV PC, LR` or `LDR PC, [SP], #4`
+    PcWrite,
+    /// a conditional branch forward over a few instructions, which could be conditionally executed instead
+    ShortBranch { skipped: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Suggestion {
+    /// the instruction on `line` doesn't depend on the load or its use, so it can be moved between them to run while the load finishes
+    MoveBetween { line: usize },
+    /// the branch can be removed, and the instructions it skips executed with `condition`, which costs a cycle for each of them rather than a refill when it is taken
+    Conditional { condition: Condition },
+}
+
+/// the most instructions a conditional branch can skip and be suggested as conditional execution, as each of them costs a cycle even when it doesn't execute
+const SHORT_BRANCH: usize = 3;
+
+impl Default for PipelineModel {
+    fn default() -> Self {
+        PipelineModel {
+            load_use: 1,
+            refill: 2,
+        }
+    }
+}
+
+impl PipelineModel {
+    pub fn new() -> PipelineModel {
+        PipelineModel::default()
+    }
+
+    pub fn load_use(mut self, cycles: u64) -> PipelineModel {
+        self.load_use = cycles;
+        self
+    }
+
+    pub fn refill(mut self, cycles: u64) -> PipelineModel {
+        self.refill = cycles;
+        self
+    }
+}
+
+impl Display for Hazard {
+    /// describes the hazard and its suggestion, with one-based line numbers
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let cycles = match self.penalty {
+            1 => "1 cycle".to_owned(),
+            penalty => format!("{penalty} cycles"),
+        };
+
+        write!(f, "line {}: ", self.source_line + 1)?;
+
+        match &self.kind {
+            HazardKind::LoadUse {
+                register,
+                load_line,
+            } => write!(
+                f,
+                "{} is used straight after it is loaded on line {}, which stalls for {cycles}",
+                register_name(*register),
+                load_line + 1
+            )?,
+            HazardKind::PcWrite => {
+                write!(f, "writing the PC refills the pipeline, which takes {cycles}")?
+            }
+            HazardKind::ShortBranch { skipped } => write!(
+                f,
+                "the branch over {skipped} instruction{} refills the pipeline when it is taken, which takes {cycles}",
+                if *skipped == 1 { "" } else { "s" }
+            )?,
+        }
+
+        match &self.suggestion {
+            Some(Suggestion::MoveBetween { line }) => {
+                write!(f, "; move line {} between them", line + 1)
+            }
+            Some(Suggestion::Conditional { condition }) => write!(
+                f,
+                "; remove it and add {condition:?} to the instructions it skips"
+            ),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Program {
+    /// the load-use stalls and pipeline refills of the program under `model`, in order of address, with reorderings that avoid them
+    ///
+    /// Each instruction is compared with the ones after it in memory, rather than along each path through branches, and the penalties are for a single execution.
+    /// A load followed by an instruction that reads the loaded register stalls, and an instruction that could be moved between them is suggested if the instruction after the use doesn't depend on either and isn't branched to.
+    /// Writes to the PC other than branches refill the pipeline, and a conditional branch forward over up to three unconditional instructions that don't set the flags is suggested as conditional execution instead.
+    pub fn hazards(&self, model: &PipelineModel) -> Vec<Hazard> {
+        let sites: Vec<InstructionSite> = self.instructions().collect();
+        let mut hazards = Vec::new();
+
+        for (index, site) in sites.iter().enumerate() {
+            let load_use = next_of(&sites, index)
+                .and_then(|next| self.load_use(model, site, next, next_of(&sites, index + 1)));
+
+            hazards.extend(load_use);
+
+            match site.instruction {
+                InstructionKind::Branch {
+                    kind: BranchKind::Branch,
+                    condition,
+                    ..
+                } => {
+                    if let Some((skipped, condition)) = self.short_branch(&sites, index, *condition)
+                    {
+                        hazards.push(Hazard {
+                            address: site.address,
+                            source_line: site.source_line,
+                            kind: HazardKind::ShortBranch { skipped },
+                            penalty: model.refill,
+                            suggestion: Some(Suggestion::Conditional { condition }),
+                        });
+                    }
+                }
+
+                InstructionKind::Branch { .. }
+                | InstructionKind::BranchWithLinkAndExchange { .. } => {}
+
+                instruction if instruction.writes().contains(15) => hazards.push(Hazard {
+                    address: site.address,
+                    source_line: site.source_line,
+                    kind: HazardKind::PcWrite,
+                    penalty: model.refill,
+                    suggestion: None,
+                }),
+
+                _ => {}
+            }
+        }
+
+        hazards.sort_by_key(|hazard| hazard.address);
+        hazards
+    }
+
+    /// the stall of `next` on the register loaded by `load`, and an instruction after `next` that can fill it
+    fn load_use(
+        &self,
+        model: &PipelineModel,
+        load: &InstructionSite,
+        next: &InstructionSite,
+        candidate: Option<&InstructionSite>,
+    ) -> Option<Hazard> {
+        let InstructionKind::LoadStore {
+            kind: LoadStoreKind::Load,
+            destination,
+            ..
+        } = load.instruction
+        else {
+            return None;
+        };
+
+        if destination.0 == 15 || !next.instruction.reads().contains(destination.0) {
+            return None;
+        }
+
+        let suggestion = candidate
+            .filter(|candidate| self.can_fill(load, next, candidate))
+            .map(|candidate| Suggestion::MoveBetween {
+                line: candidate.source_line,
+            });
+
+        Some(Hazard {
+            address: next.address,
+            source_line: next.source_line,
+            kind: HazardKind::LoadUse {
+                register: destination.0,
+                load_line: load.source_line,
+            },
+            penalty: model.load_use,
+            suggestion,
+        })
+    }
+
+    /// whether `candidate`, which comes after `next`, can be moved between `load` and `next` without changing what the program does
+    fn can_fill(
+        &self,
+        load: &InstructionSite,
+        next: &InstructionSite,
+        candidate: &InstructionSite,
+    ) -> bool {
+        // the candidate must be a whole statement, that isn't a branch target, run whenever the use is
+        let alone = self
+            .statement_at(candidate.address)
+            .is_some_and(|statement| {
+                statement.address == candidate.address && statement.instructions.len() == 1
+            })
+            && self.label_at(candidate.address).is_none()
+            && !next.instruction.writes().contains(15);
+
+        let mut used = RegisterList::default();
+        let mut written = RegisterList::default();
+
+        for instruction in [load.instruction, next.instruction] {
+            instruction
+                .reads()
+                .iter()
+                .for_each(|register| used.insert(register));
+            instruction.writes().iter().for_each(|register| {
+                used.insert(register);
+                written.insert(register);
+            });
+        }
+
+        let independent = candidate
+            .instruction
+            .writes()
+            .iter()
+            .all(|register| !used.contains(register))
+            && candidate
+                .instruction
+                .reads()
+                .iter()
+                .all(|register| !written.contains(register));
+
+        // moving it above the use mustn't change the flags the use sees, or the flags the candidate's condition sees
+        let flags = !candidate.instruction.sets_flags()
+            && (candidate.instruction.condition() == Condition::AL
+                || !next.instruction.sets_flags());
+
+        alone
+            && independent
+            && flags
+            && matches!(
+                candidate.instruction,
+                InstructionKind::DataProcessing { .. }
+            )
+    }
+
+    /// the number of instructions skipped by the conditional branch at `sites[index]`, and the condition they would be executed with, if they can be
+    fn short_branch(
+        &self,
+        sites: &[InstructionSite],
+        index: usize,
+        condition: Condition,
+    ) -> Option<(usize, Condition)> {
+        let site = &sites[index];
+        let inverse = condition.inverse()?;
+        let target = site.instruction.branch_target(site.address as u32)? as usize;
+
+        let skipped = target.checked_sub(site.address + 4)? / 4;
+
+        if skipped == 0 || skipped > SHORT_BRANCH {
+            return None;
+        }
+
+        let skipped_sites = sites.get(index + 1..=index + skipped)?;
+
+        let convertible = skipped_sites.iter().enumerate().all(|(offset, skipped)| {
+            skipped.address == site.address + 4 * (offset + 1)
+                && skipped.instruction.condition() == Condition::AL
+                && !skipped.instruction.sets_flags()
+                && !matches!(
+                    skipped.instruction,
+                    InstructionKind::BranchWithLinkAndExchange { .. }
+                        | InstructionKind::Preload { .. }
+                )
+                && self.label_at(skipped.address).is_none()
+        });
+
+        convertible.then_some((skipped, inverse))
+    }
+}
+
+/// the instruction at `sites[index + 1]`, if it follows on from `sites[index]` in memory
+fn next_of<'a, 'b>(
+    sites: &'b [InstructionSite<'a>],
+    index: usize,
+) -> Option<&'b InstructionSite<'a>> {
+    let site = sites.get(index)?;
+
+    sites
+        .get(index + 1)
+        .filter(|next| next.address == site.address + 4)
+}
@@ -0,0 +1,172 @@
+use crate::{
+    ir::ByteOrder,
+    parser::{DefinitionKind, DirectiveKind, Line, StatementKind},
+};
+
+use super::{checksum, AssembledStatement, Program, Region};
+
+/// the memory written by a program as one block, from its lowest address to its highest, for writing to a binary file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    /// the address of the first byte
+    pub base: usize,
+    pub bytes: Vec<u8>,
+}
+
+/// the number of data bytes in each Intel HEX record
+const HEX_RECORD_LENGTH: usize = 16;
+
+impl Program {
+    /// the program's memory as a flat image, with words in `order`, so it matches the system it is flashed to regardless of how the emulator stores words
+    ///
+    /// Instructions, `DEFW` words, literals and checksums are words, so their bytes are reversed for a little-endian target, but `DEFB` bytes and strings aren't.
+    /// The words of CHECKSUM and CRC32 directives are computed again over the bytes in `order`, so they match the image.
+    /// Gaps between the program's regions (e.g. from `ORIGIN`) are filled with zeros.
+    pub fn image(&self, order: ByteOrder) -> Image {
+        let regions = self.regions();
+
+        let (Some(first), Some(last)) = (regions.first(), regions.last()) else {
+            return Image {
+                base: 0,
+                bytes: Vec::new(),
+            };
+        };
+
+        let base = first.start;
+        let mut bytes = vec![0; last.end - base];
+
+        for (address, statement_bytes) in self.ordered_statements(order) {
+            bytes[address - base..address - base + statement_bytes.len()]
+                .copy_from_slice(&statement_bytes);
+        }
+
+        Image { base, bytes }
+    }
+
+    /// the program's memory in the Intel HEX format, with words in `order`, see [`Program::image`]
+    ///
+    /// Only the memory the program writes is included, rather than the gaps between its regions, and the entry point is given by a start linear address record.
+    pub fn intel_hex(&self, order: ByteOrder) -> String {
+        let image = self.image(order);
+
+        let mut hex = String::new();
+        let mut upper = 0;
+
+        for region in self.regions() {
+            for start in region.clone().step_by(HEX_RECORD_LENGTH) {
+                let end = (start + HEX_RECORD_LENGTH)
+                    .min(region.end)
+                    // a record can't cross a 64 KiB boundary
+                    .min((start & !0xFFFF) + 0x10000);
+
+                if start >> 16 != upper {
+                    upper = start >> 16;
+                    hex_record(&mut hex, 0, 0x04, &(upper as u16).to_be_bytes());
+                }
+
+                let data = &image.bytes[start - image.base..end - image.base];
+
+                hex_record(&mut hex, start as u16, 0x00, data);
+            }
+        }
+
+        hex_record(&mut hex, 0, 0x05, &(self.entry_point as u32).to_be_bytes());
+        hex_record(&mut hex, 0, 0x01, &[]);
+
+        hex
+    }
+
+    /// the bytes of each statement, by address, with its words in `order`
+    fn ordered_statements(&self, order: ByteOrder) -> Vec<(usize, Vec<u8>)> {
+        let runtime = self.runtime_lines();
+
+        let mut statements: Vec<AssembledStatement> = self
+            .statements
+            .iter()
+            .map(|statement| {
+                let mut bytes = statement.bytes.clone();
+
+                if order == ByteOrder::LittleEndian {
+                    if statement.region == Region::Padding {
+                        for (i, byte) in bytes.iter_mut().enumerate() {
+                            *byte = self.options.padding.byte_in(statement.address + i, order);
+                        }
+                    } else if self.is_words(statement, &runtime) {
+                        bytes.chunks_exact_mut(4).for_each(<[u8]>::reverse);
+                    }
+                }
+
+                AssembledStatement {
+                    bytes,
+                    ..statement.clone()
+                }
+            })
+            .collect();
+
+        if order != ByteOrder::BigEndian {
+            checksum::fill(&mut statements, &self.checksums, order);
+        }
+
+        statements
+            .into_iter()
+            .map(|statement| (statement.address, statement.bytes.to_vec()))
+            .collect()
+    }
+
+    /// whether a statement's bytes are a sequence of words, rather than individual bytes
+    fn is_words(&self, statement: &AssembledStatement, runtime: &[Line]) -> bool {
+        if statement.region == Region::Code {
+            return true;
+        }
+
+        if self
+            .literal_pools
+            .iter()
+            .any(|pool| pool.address == statement.address)
+        {
+            return true;
+        }
+
+        let line = match statement.source_line.checked_sub(self.lines.len()) {
+            Some(index) => runtime.get(index),
+            None => self.lines.get(statement.source_line),
+        };
+
+        matches!(
+            line.and_then(|line| line.statement.as_ref())
+                .map(|statement| &statement.kind),
+            Some(StatementKind::Directive {
+                kind: DirectiveKind::Definition {
+                    kind: DefinitionKind::Words { .. }
+                } | DirectiveKind::Checksum { .. }
+            })
+        )
+    }
+
+    /// the parsed lines of the runtime, which are numbered after the source's lines
+    fn runtime_lines(&self) -> Vec<Line> {
+        self.options
+            .runtime
+            .parse(self.lines.len())
+            .map(|(prelude, postlude)| prelude.into_iter().chain(postlude).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// writes an Intel HEX record of `kind` with `data`, followed by a newline
+fn hex_record(hex: &mut String, address: u16, kind: u8, data: &[u8]) {
+    let mut record = vec![data.len() as u8];
+    record.extend(address.to_be_bytes());
+    record.push(kind);
+    record.extend(data);
+
+    let checksum = record
+        .iter()
+        .fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+        .wrapping_neg();
+    record.push(checksum);
+
+    hex.push(':');
+    hex.extend(record.iter().map(|byte| format!("{byte:02X}")));
+    hex.push('\n');
+}
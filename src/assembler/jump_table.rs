@@ -0,0 +1,209 @@
+use std::fmt::Display;
+
+use crate::{
+    ir::{
+        AddressingOffset, AddressingOffsetValue, ComparisonKind, Condition, DataProcessingKind,
+        InstructionKind, LoadStoreAddressCode, LoadStoreKind, LoadStoreQuantity, OffsetMode, Rd,
+        Rm, Rn, Shift, ShiftedRegister, ShifterOperandCode, Sign,
+    },
+    span::Span,
+};
+
+use super::{AssemblyWarning, InstructionSite, Program, Region};
+
+/// a jump through a table of addresses, `LDR PC, [PC, Rn, LSL #2]` with the table's DEFW after the instruction that follows it, see [`Program::jump_tables`]
+///
+/// # Examples
+/// ```text
+///         CMP     R0, #2
+///         LDRLS   PC, [PC, R0, LSL #2]
+///         B       default
+///         DEFW    zero, one, two
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct JumpTable {
+    /// the address of the LDR
+    pub address: usize,
+    pub source_line: usize,
+    /// the register that indexes the table
+    pub index: u8,
+    /// the address of the table, which is the PC the LDR reads, 8 bytes after it
+    pub table: usize,
+    /// the addresses in the table, in order, up to the bound if there is one
+    pub targets: Vec<u32>,
+    /// the number of entries the index is limited to by a `CMP Rn, #<n>` just before an LDRLS, if there is one
+    pub bound: Option<usize>,
+}
+
+/// a jump table entry or bound that can't be right, see [`AssemblyWarning::JumpTable`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum JumpTableProblem {
+    /// entry `entry` of the table is `target`, which isn't the address of an instruction
+    EntryNotCode { entry: usize, target: u32 },
+    /// the CMP allows `bound` entries, but only `entries` words follow the jump
+    TooShort { entries: usize, bound: usize },
+}
+
+impl Display for JumpTableProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JumpTableProblem::EntryNotCode { entry, target } => write!(
+                f,
+                "entry {entry} of the jump table is {target:#010X}, which isn't an instruction"
+            ),
+            JumpTableProblem::TooShort { entries, bound } => write!(
+                f,
+                "the jump table has {entries} entr{}, but the index can be up to {}",
+                if *entries == 1 { "y" } else { "ies" },
+                bound - 1
+            ),
+        }
+    }
+}
+
+impl Program {
+    /// the jumps through tables of addresses, in order of address
+    ///
+    /// A table is the data words from 8 bytes after an `LDR PC, [PC, Rn, LSL #2]`, up to the next label or instruction.
+    /// If the LDR is LS-conditional and straight after a `CMP Rn, #<n>`, the table has `n + 1` entries, so only they are targets.
+    pub fn jump_tables(&self) -> Vec<JumpTable> {
+        let sites: Vec<InstructionSite> = self.instructions().collect();
+
+        sites
+            .iter()
+            .enumerate()
+            .filter_map(|(index, site)| {
+                let register = jump_index(site.instruction)?;
+                let table = site.address + 8;
+
+                let bound = index
+                    .checked_sub(1)
+                    .map(|previous| &sites[previous])
+                    .filter(|previous| previous.address + 4 == site.address)
+                    .filter(|_| site.instruction.condition() == Condition::LS)
+                    .and_then(|previous| compare_bound(previous.instruction, register));
+
+                let mut targets = self.table_words(table);
+
+                if let Some(bound) = bound {
+                    targets.truncate(bound);
+                }
+
+                Some(JumpTable {
+                    address: site.address,
+                    source_line: site.source_line,
+                    index: register,
+                    table,
+                    targets,
+                    bound,
+                })
+            })
+            .collect()
+    }
+
+    /// the problems with the program's jump tables, with the span of the statement each is about
+    pub(super) fn jump_table_warnings(&self) -> Vec<AssemblyWarning> {
+        let span = |address: usize| -> Option<Span> {
+            let line = self.lines.get(self.statement_at(address)?.source_line)?;
+
+            Some(line.statement.as_ref()?.span.clone())
+        };
+
+        let mut warnings = Vec::new();
+
+        for table in self.jump_tables() {
+            for (entry, target) in table.targets.iter().enumerate() {
+                if !self
+                    .instructions()
+                    .any(|site| site.address == *target as usize)
+                {
+                    warnings.extend(span(table.table + 4 * entry).map(|span| {
+                        AssemblyWarning::JumpTable {
+                            span,
+                            problem: JumpTableProblem::EntryNotCode {
+                                entry,
+                                target: *target,
+                            },
+                        }
+                    }));
+                }
+            }
+
+            if let Some(bound) = table.bound.filter(|bound| table.targets.len() < *bound) {
+                warnings.extend(span(table.address).map(|span| AssemblyWarning::JumpTable {
+                    span,
+                    problem: JumpTableProblem::TooShort {
+                        entries: table.targets.len(),
+                        bound,
+                    },
+                }));
+            }
+        }
+
+        warnings
+    }
+
+    /// the words of the data statements from `address`, up to the next label, instruction or gap
+    fn table_words(&self, address: usize) -> Vec<u32> {
+        let mut bytes = Vec::new();
+        let mut next = address;
+
+        while let Some(statement) = self
+            .statement_at(next)
+            .filter(|statement| statement.address == next && statement.region == Region::Data)
+            .filter(|_| next == address || self.label_at(next).is_none())
+        {
+            bytes.extend_from_slice(&statement.bytes);
+            next += statement.bytes.len();
+        }
+
+        bytes
+            .chunks_exact(4)
+            .map(|word| u32::from_be_bytes([word[0], word[1], word[2], word[3]]))
+            .collect()
+    }
+}
+
+/// the register that indexes the table, if the instruction is `LDR PC, [PC, Rn, LSL #2]`
+fn jump_index(instruction: &InstructionKind) -> Option<u8> {
+    match instruction {
+        InstructionKind::LoadStore {
+            kind: LoadStoreKind::Load,
+            quantity: LoadStoreQuantity::Word,
+            destination: Rd(15),
+            address:
+                LoadStoreAddressCode {
+                    base: Rn(15),
+                    offset:
+                        AddressingOffset {
+                            sign: Sign::Positive,
+                            value:
+                                AddressingOffsetValue::ScaledRegister(ShiftedRegister {
+                                    kind: Shift::LogicalShiftLeft,
+                                    amount,
+                                    base: Rm(register),
+                                }),
+                            mode: OffsetMode::Offset,
+                        },
+                },
+            ..
+        } if amount.get() == 2 && *register != 15 => Some(*register),
+        _ => None,
+    }
+}
+
+/// the number of entries allowed by `instruction`, if it is a `CMP` of `register` with an immediate
+fn compare_bound(instruction: &InstructionKind, register: u8) -> Option<usize> {
+    match instruction {
+        InstructionKind::DataProcessing {
+            condition: Condition::AL,
+            kind:
+                DataProcessingKind::Comparison {
+                    kind: ComparisonKind::CMP,
+                    source: Rn(source),
+                    shifter: ShifterOperandCode::Immediate(immediate),
+                },
+        } if *source == register => Some(immediate.get() as usize + 1),
+        _ => None,
+    }
+}
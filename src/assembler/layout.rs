@@ -0,0 +1,96 @@
+use std::{collections::HashMap, ops::Range};
+
+use crate::ir::ByteOrder;
+
+/// where the sections of a program are placed in memory, like a minimal linker script
+///
+/// Memory is split into named regions, such as ROM and RAM, and each section named by a `SECTION` directive is placed in one of them.
+/// Sections placed in the same region share it, in the order their statements appear in the source.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Layout {
+    regions: Vec<MemoryRegion>,
+    /// the name of the region each section is placed in
+    sections: HashMap<String, String>,
+}
+
+/// the bytes that fill the gaps left by ALIGN directives
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Padding {
+    #[default]
+    Zero,
+    /// `MOV R0, R0`, so running into the padding does nothing
+    Nop,
+    /// 0xFF, which is what erased flash memory reads as
+    Ones,
+}
+
+impl Padding {
+    /// the encoding of `MOV R0, R0`
+    const NOP: u32 = 0xE1A00000;
+
+    /// the padding byte at `address`, which for a NOP depends on its position in the word
+    pub fn byte(&self, address: usize) -> u8 {
+        self.byte_in(address, ByteOrder::BigEndian)
+    }
+
+    /// the padding byte at `address` in an image with words in `order`
+    pub fn byte_in(&self, address: usize, order: ByteOrder) -> u8 {
+        match self {
+            Padding::Zero => 0x00,
+            Padding::Nop => order.word_to_bytes(Padding::NOP)[address % 4],
+            Padding::Ones => 0xFF,
+        }
+    }
+}
+
+/// a named block of memory that sections can be placed in
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryRegion {
+    pub name: String,
+    pub base: u32,
+    pub size: u32,
+}
+
+impl MemoryRegion {
+    /// the addresses in the region
+    pub fn range(&self) -> Range<usize> {
+        self.base as usize..self.base as usize + self.size as usize
+    }
+}
+
+impl Layout {
+    /// a layout without any regions, where `SECTION` directives can't be used
+    pub fn new() -> Layout {
+        Layout::default()
+    }
+
+    /// adds a region of `size` bytes starting at `base`, replacing any region with the same name
+    pub fn region(mut self, name: &str, base: u32, size: u32) -> Layout {
+        self.regions.retain(|region| region.name != name);
+        self.regions.push(MemoryRegion {
+            name: name.to_owned(),
+            base,
+            size,
+        });
+
+        self
+    }
+
+    /// places a section in the region with the name `region`
+    pub fn place(mut self, section: &str, region: &str) -> Layout {
+        self.sections.insert(section.to_owned(), region.to_owned());
+
+        self
+    }
+
+    pub fn regions(&self) -> &[MemoryRegion] {
+        &self.regions
+    }
+
+    /// the region a section is placed in, if the section is placed in a region that exists
+    pub fn placement(&self, section: &str) -> Option<&MemoryRegion> {
+        let name = self.sections.get(section)?;
+
+        self.regions.iter().find(|region| &region.name == name)
+    }
+}
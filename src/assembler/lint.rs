@@ -0,0 +1,141 @@
+use crate::{
+    ir::{Rd, Rn},
+    parser::{
+        CalculationKind, DataProcessingKind, InstructionKind, LoadStoreKind, MoveKind, Register,
+        SetFlags, ShifterOperandExpression, ShifterOperandShiftAmount, Statement, StatementKind,
+        WriteBack,
+    },
+    span::Span,
+};
+
+/// a use of the PC that the ARM ARM says is unpredictable, implementation defined or deprecated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PcLint {
+    /// a data processing instruction that sets the flags and writes the PC, other than the exception returns `MOVS PC, LR` and `SUBS PC, LR, #<immediate>`
+    FlagSettingPcWrite,
+    /// an LDM or STM with the PC as its base and write-back
+    PcBaseWriteBack,
+    /// a register shift that shifts the PC, or shifts by the PC
+    PcShiftRegister,
+    /// an STR of the PC
+    StorePc,
+}
+
+impl PcLint {
+    pub fn message(&self) -> &'static str {
+        match self {
+            PcLint::FlagSettingPcWrite => "Setting the flags while writing the PC is unpredictable",
+            PcLint::PcBaseWriteBack => {
+                "Writing back to the PC as the base register is unpredictable"
+            }
+            PcLint::PcShiftRegister => {
+                "Using the PC in a register-specified shift is unpredictable"
+            }
+            PcLint::StorePc => "The value of the PC stored by STR is implementation defined",
+        }
+    }
+
+    /// why the use of the PC is a problem, to show alongside the message
+    pub fn explanation(&self) -> &'static str {
+        match self {
+            PcLint::FlagSettingPcWrite => "With S and the PC as Rd, the instruction copies the SPSR into the CPSR to return from an exception, which is unpredictable in User mode as there is no SPSR. Leave out the S to branch, or use MOVS PC, LR or SUBS PC, LR, #4 in an exception handler",
+            PcLint::PcBaseWriteBack => "The PC changes as the instruction executes, so the address written back to it is unpredictable. Use another register as the base",
+            PcLint::PcShiftRegister => "The value read from the PC by a register-specified shift isn't defined, as the shift takes an extra cycle. Copy the PC to another register first",
+            PcLint::StorePc => "Processors store either the address of the STR plus 8 or plus 12, so code that relies on the value isn't portable. Copy the PC to another register, or use ADR, to store a known address",
+        }
+    }
+
+    /// the lints for a statement, along with the span of the operand each is about
+    pub(super) fn check(statement: &Statement) -> Vec<(PcLint, Span)> {
+        let StatementKind::Instruction { kind } = &statement.kind else {
+            return Vec::new();
+        };
+
+        let operand = |index: usize| {
+            statement
+                .operands
+                .get(index)
+                .unwrap_or(&statement.span)
+                .clone()
+        };
+
+        let mut lints = Vec::new();
+
+        match kind {
+            InstructionKind::DataProcessing { kind, .. } => {
+                let shifter = match kind {
+                    DataProcessingKind::Move {
+                        kind,
+                        set_flags,
+                        destination,
+                        shifter,
+                    } => {
+                        // MOVS PC, LR
+                        let exception_return = *kind == MoveKind::Move
+                            && *shifter == ShifterOperandExpression::Register(Register(14));
+
+                        if *set_flags == SetFlags::Set
+                            && *destination == Rd(15)
+                            && !exception_return
+                        {
+                            lints.push((PcLint::FlagSettingPcWrite, operand(0)));
+                        }
+
+                        shifter
+                    }
+
+                    DataProcessingKind::Calculation {
+                        kind,
+                        set_flags,
+                        destination,
+                        source,
+                        shifter,
+                    } => {
+                        // SUBS PC, LR, #<immediate>
+                        let exception_return = *kind == CalculationKind::SUB
+                            && *source == Rn(14)
+                            && matches!(shifter, ShifterOperandExpression::Immediate(_));
+
+                        if *set_flags == SetFlags::Set
+                            && *destination == Rd(15)
+                            && !exception_return
+                        {
+                            lints.push((PcLint::FlagSettingPcWrite, operand(0)));
+                        }
+
+                        shifter
+                    }
+
+                    DataProcessingKind::Comparison { shifter, .. } => shifter,
+                };
+
+                if let ShifterOperandExpression::ShiftedRegister(shift) = shifter {
+                    if let ShifterOperandShiftAmount::Register(amount) = shift.amount {
+                        if shift.base.0 == 15 || amount.0 == 15 {
+                            lints.push((
+                                PcLint::PcShiftRegister,
+                                operand(statement.operands.len().saturating_sub(1)),
+                            ));
+                        }
+                    }
+                }
+            }
+
+            InstructionKind::LoadStore {
+                kind: LoadStoreKind::Store,
+                destination: Rd(15),
+                ..
+            } => lints.push((PcLint::StorePc, operand(0))),
+
+            InstructionKind::LoadStoreMultiple {
+                base: Rn(15),
+                write_back: WriteBack::WriteBack,
+                ..
+            } => lints.push((PcLint::PcBaseWriteBack, operand(0))),
+
+            _ => {}
+        }
+
+        lints
+    }
+}
@@ -1,9 +1,137 @@
-use crate::{preprocessor::PreProcessError, resolver::ResolveError};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    ops::Range,
+};
+
+use smallvec::SmallVec;
+
+use crate::{
+    ir::{ByteOrder, InstructionKind},
+    parser::{
+        DirectiveKind, Expression, ExpressionKind, Line, ParseError, ParseWarning, Parser,
+        PseudoInstructionKind, Statement, StatementKind, Symbol,
+    },
+    preprocessor::{
+        AddressedStatement, PreProcessError, PreProcessResult, PreProcessedStatement, PreProcessor,
+    },
+    resolver::{ResolveError, ResolvedStatement, SymbolCase, SymbolTable, UnencodableStatement},
+    span::Span,
+};
+
+#[cfg(feature = "disassembler")]
+use crate::disassembler::Annotations;
+
+pub use crate::encoder::{EncodedInstruction, EncodingField};
+pub use crate::parser::ChecksumKind;
+pub use crate::preprocessor::{LiteralPool, PoolPlacement};
+pub use builder::ProgramBuilder;
+pub use diagnostic::{Diagnostic, Phase, Severity};
+pub use hazard::{Hazard, HazardKind, PipelineModel, Suggestion};
+#[cfg(feature = "formats")]
+pub use image::Image;
+pub use jump_table::{JumpTable, JumpTableProblem};
+pub use layout::{Layout, MemoryRegion, Padding};
+pub use lint::PcLint;
+pub use peephole::{Optimization, OptimizationKind};
+pub use query::InstructionSite;
+pub use relocation::{Relocation, RelocationKind};
+pub use runtime::{Runtime, RuntimeLine};
+pub use source_map::SourceMap;
+pub use stack::{RoutineStack, UnbalancedReturn};
+pub use strings::DuplicateString;
+pub use unused::{UnreferencedLabel, UnusedCode, UnusedStatement};
+pub use workspace::{RenameError, SymbolLocation, SymbolOccurrence, WorkspaceIndex};
+pub use xref::{CrossReference, Reference, ReferenceKind, SymbolReferences};
+
+mod builder;
+mod checksum;
+mod diagnostic;
+mod hazard;
+#[cfg(feature = "formats")]
+mod image;
+mod jump_table;
+mod layout;
+mod lint;
+mod peephole;
+mod query;
+mod relocation;
+mod runtime;
+mod source_map;
+mod stack;
+mod strings;
+mod unused;
+mod workspace;
+mod xref;
+
+#[cfg(test)]
+mod tests;
 
 #[derive(Debug)]
 pub enum AssemblyError {
     PreProcessError(PreProcessError),
     ResolveError(ResolveError),
+    /// a single instruction was expected, but the statement was a directive
+    NotAnInstruction(Span),
+    /// a patch would write to an address that isn't part of the program
+    UnmappedAddress(usize),
+    /// values that can't be encoded, in every statement they were found in rather than just the first
+    Unencodable(Vec<UnencodableStatement>),
+}
+
+/// a problem with the source that doesn't stop it being assembled
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssemblyWarning {
+    ParseWarning(ParseWarning),
+    /// a symbol was referred to with different case to its definition, see [`SymbolCase::InsensitiveWithWarnings`]
+    SymbolCaseMismatch {
+        /// the span of the statement containing the reference
+        span: Span,
+        reference: Symbol,
+        definition: Symbol,
+    },
+    /// a literal pool was placed between statements at `address`, with a branch over it, because the literal of the LDR= at `span` would otherwise be out of range
+    LiteralPoolPlaced {
+        span: Span,
+        address: usize,
+    },
+    /// an unpredictable or implementation defined use of the PC, where `span` is the operand it is in
+    PcLint {
+        span: Span,
+        lint: PcLint,
+    },
+    /// a jump table entry that isn't an instruction, where `span` is its DEFW, or a table shorter than its bound, where `span` is the jump, see [`Program::jump_tables`]
+    JumpTable {
+        span: Span,
+        problem: JumpTableProblem,
+    },
+}
+
+/// settings that change how source is assembled
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AssemblyOptions {
+    pub symbol_case: SymbolCase,
+    /// where the sections named by `SECTION` directives are placed
+    pub layout: Layout,
+    /// instructor-provided assembly to place before and after the source
+    pub runtime: Runtime,
+    /// the bytes that fill the gaps left by ALIGN directives
+    pub padding: Padding,
+    /// build LDR= values that are known where they are used with instructions, like MOV32, rather than loading them from a literal pool
+    pub synthesise_constants: bool,
+    /// apply the peephole optimizations to the source before it is assembled, see [`Program::optimizations`]
+    ///
+    /// It is off by default, as the instructions that run no longer match the source, and memory-mapped peripherals may be read twice on purpose.
+    pub optimize: bool,
+    /// replace each labelled DEFB of a string that is identical to an earlier one with an alias of the earlier one's label, see [`Program::duplicate_strings`]
+    ///
+    /// It is off by default, as a program that writes to one of the strings, or walks from one string to the next, would see the other change or disappear.
+    pub deduplicate_strings: bool,
+}
+
+impl From<ParseError> for AssemblyError {
+    fn from(value: ParseError) -> AssemblyError {
+        AssemblyError::PreProcessError(PreProcessError::ParseError(value))
+    }
 }
 
 impl From<PreProcessError> for AssemblyError {
@@ -17,3 +145,689 @@ impl From<ResolveError> for AssemblyError {
         AssemblyError::ResolveError(value)
     }
 }
+
+/// An assembled program, along with the source it was assembled from.
+///
+/// The parsed form of every source line is kept, so that an edit to the source only needs to re-parse the lines it touches (see [`Program::edit`])
+#[derive(Debug)]
+pub struct Program {
+    source: String,
+    /// the byte offset of the start of each source line
+    line_starts: Vec<usize>,
+    /// the parsed form of each source line
+    lines: Vec<Line>,
+    options: AssemblyOptions,
+    pub symbol_table: SymbolTable<u32>,
+    /// symbols defined by other programs, which this program can refer to
+    imports: SymbolTable<u32>,
+    /// the imports that this program refers to without defining, which are in its symbol table
+    imported: HashSet<Symbol>,
+    pub entry_point: usize,
+    /// maps addresses to the source lines that generated them, and source lines to their addresses
+    pub source_map: SourceMap,
+    pub statements: Vec<AssembledStatement>,
+    /// the places that depend on the address of a symbol, for linking the program separately
+    pub relocations: Vec<Relocation>,
+    /// where the values of LDR= pseudo-instructions that can't be a MOV were placed
+    pub literal_pools: Vec<LiteralPool>,
+    /// the values built by MOV32 sequences, by the address of the instruction that finishes them
+    pub synthesised_constants: BTreeMap<usize, u32>,
+    /// the changes made by the peephole optimizer, if [`AssemblyOptions::optimize`] is set, in the order they were made
+    pub optimizations: Vec<Optimization>,
+    /// the strings removed by [`AssemblyOptions::deduplicate_strings`], with the bytes each saved
+    pub duplicate_strings: Vec<DuplicateString>,
+    /// the CHECKSUM and CRC32 directives, by index in `statements`, with the range each covers, so they can be filled in again for another byte order
+    #[cfg(feature = "formats")]
+    checksums: Vec<(usize, ChecksumKind, Range<usize>)>,
+    pub warnings: Vec<AssemblyWarning>,
+}
+
+/// the machine code or data generated by a single source line
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssembledStatement {
+    pub address: usize,
+    pub source_line: usize,
+    // most statements generate at most 2 instructions, so this only allocates for a MOV32 of more than 2
+    pub bytes: SmallVec<[u8; 8]>,
+    /// the instructions encoded in the bytes, which is empty for data and padding
+    pub instructions: SmallVec<[InstructionKind; 2]>,
+    pub region: Region,
+}
+
+/// whether bytes were generated by instructions or by data directives, or fill the gap left by an ALIGN
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Region {
+    Code,
+    Data,
+    Padding,
+}
+
+/// a replacement of a byte range of the source with new text
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+impl Program {
+    pub fn assemble(source: &str) -> Result<Program, AssemblyError> {
+        Program::assemble_with(source, AssemblyOptions::default())
+    }
+
+    pub fn assemble_with(source: &str, options: AssemblyOptions) -> Result<Program, AssemblyError> {
+        Program::assemble_with_imports(source, options, SymbolTable::new())
+    }
+
+    /// assembles a program that can refer to symbols defined by other programs, such as a library loaded at a fixed ORIGIN
+    ///
+    /// A symbol defined by the program hides an import with the same name.
+    pub fn assemble_with_imports(
+        source: &str,
+        options: AssemblyOptions,
+        imports: SymbolTable<u32>,
+    ) -> Result<Program, AssemblyError> {
+        let line_starts = line_starts(source, 0).collect();
+        let lines = parse_lines(source, 0, 0)?;
+
+        Program::build(
+            source.to_owned(),
+            line_starts,
+            lines,
+            options,
+            imports,
+            None,
+            |_| None,
+        )
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// the options the program was assembled with, which are kept when it is edited
+    pub fn options(&self) -> &AssemblyOptions {
+        &self.options
+    }
+
+    /// the symbols defined by the program, excluding its imports
+    pub fn exports(&self) -> impl Iterator<Item = (&Symbol, &u32)> {
+        self.symbol_table
+            .iter()
+            .filter(|(symbol, _)| !self.imported.contains(*symbol))
+    }
+
+    /// Applies an edit to the source and reassembles the program.
+    ///
+    /// Only the lines touched by the edit are re-lexed and re-parsed, and only the statements whose address or referenced symbols changed are re-resolved and re-encoded.
+    /// If [`AssemblyOptions::optimize`] is set, every statement is re-encoded, as the optimizer can change a statement because of the lines after it.
+    ///
+    /// Panics if the range of the edit is out of bounds, or doesn't lie on character boundaries.
+    pub fn edit(&self, edit: &TextEdit) -> Result<Program, AssemblyError> {
+        // the edit is widened to cover whole lines, as lines are the unit of parsing
+        let first_line = self.line_index(edit.range.start);
+        let last_line = self.line_index(edit.range.end);
+
+        let region_start = self.line_starts[first_line];
+        let region_end = self.line_end(last_line);
+
+        let mut source = String::with_capacity(self.source.len() + edit.text.len());
+        source.push_str(&self.source[..edit.range.start]);
+        source.push_str(&edit.text);
+        source.push_str(&self.source[edit.range.end..]);
+
+        let delta = edit.text.len() as isize - edit.range.len() as isize;
+        let region = &source[region_start..(region_end as isize + delta) as usize];
+
+        let region_lines = parse_lines(region, region_start, first_line)?;
+
+        let replaced = last_line - first_line + 1;
+        let inserted = region_lines.len();
+
+        let mut lines = Vec::with_capacity(self.lines.len() - replaced + inserted);
+        lines.extend_from_slice(&self.lines[..first_line]);
+        lines.extend(region_lines);
+        // the lines after the edit are unchanged, but have moved
+        lines.extend(self.lines[last_line + 1..].iter().cloned().map(|mut line| {
+            line.shift(delta, inserted as isize - replaced as isize);
+            line
+        }));
+
+        let mut starts = Vec::with_capacity(lines.len());
+        starts.extend_from_slice(&self.line_starts[..first_line]);
+        starts.extend(line_starts(region, region_start));
+        starts.extend(
+            self.line_starts[last_line + 1..]
+                .iter()
+                .map(|start| (*start as isize + delta) as usize),
+        );
+
+        Program::build(
+            source,
+            starts,
+            lines,
+            self.options.clone(),
+            self.imports.clone(),
+            Some(self),
+            |line| {
+                if line < first_line {
+                    Some(line)
+                } else if line >= first_line + inserted {
+                    Some(line - inserted + replaced)
+                } else {
+                    None
+                }
+            },
+        )
+    }
+
+    /// assembles a single instruction and overwrites the bytes at `address` with it, e.g. to experiment with a fix from a debugger
+    ///
+    /// Labels are resolved using the program's symbol table. The patch only changes the assembled bytes, not the source, and lasts until the patched statements' lines are edited.
+    pub fn patch(&mut self, address: usize, source: &str) -> Result<(), AssemblyError> {
+        let instructions = assemble_instruction(source, &self.symbol_table, address)?;
+        let bytes: SmallVec<[u8; 8]> = instructions
+            .iter()
+            .flat_map(|instruction| instruction.encode().to_bytes(ByteOrder::BigEndian))
+            .collect();
+
+        let end = address + bytes.len();
+
+        // check the whole patch lands in the program before writing any of it
+        let mut covered = address;
+        while covered < end {
+            match self.statement_at(covered) {
+                Some(statement) => covered = statement.address + statement.bytes.len(),
+                None => return Err(AssemblyError::UnmappedAddress(covered)),
+            }
+        }
+
+        for statement in &mut self.statements {
+            let statement_end = statement.address + statement.bytes.len();
+
+            let start = address.max(statement.address);
+            let stop = end.min(statement_end);
+
+            if start < stop {
+                statement.bytes[(start - statement.address)..(stop - statement.address)]
+                    .copy_from_slice(&bytes[(start - address)..(stop - address)]);
+
+                // keep the instructions of code in step with its bytes, when the patch lines up with them
+                for (index, instruction) in statement.instructions.iter_mut().enumerate() {
+                    let word = statement.address + index * 4;
+
+                    if word >= address && word < end && (word - address).is_multiple_of(4) {
+                        *instruction = instructions[(word - address) / 4].clone();
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// names for disassembling the program, such as the names of SVC numbers
+    ///
+    /// An SVC number is named after the EQU constant used for it, e.g. `SVC print_str` names 3 `print_str` if `print_str EQU 3`.
+    /// Only constants used by an SVC are used, so that unrelated constants with the same value (like `cLF EQU 10`) don't name it.
+    #[cfg(feature = "disassembler")]
+    pub fn annotations(&self) -> Annotations {
+        let is_constant = |symbol: &Symbol| {
+            self.lines.iter().any(|line| {
+                line.label.as_ref() == Some(&symbol.0)
+                    && matches!(
+                        line.statement,
+                        Some(Statement {
+                            kind: StatementKind::Directive {
+                                kind: DirectiveKind::Constant { .. }
+                            },
+                            ..
+                        })
+                    )
+            })
+        };
+
+        let mut annotations = Annotations {
+            constants: self
+                .synthesised_constants
+                .iter()
+                .map(|(address, value)| (*address as u32, *value))
+                .collect(),
+            jump_tables: self
+                .jump_tables()
+                .into_iter()
+                .map(|table| (table.address as u32, table.targets))
+                .collect(),
+            ..Annotations::default()
+        };
+
+        for statement in self.lines.iter().flat_map(|line| &line.statement) {
+            if let StatementKind::Instruction {
+                kind: InstructionKind::SuperVisorCall { immediate, .. },
+            } = &statement.kind
+            {
+                // the definition's spelling is used, in case symbols are case-insensitive
+                if let ExpressionKind::Symbol(symbol) = &immediate.kind {
+                    if let Some((definition, number)) = self.symbol_table.definition(symbol) {
+                        if is_constant(definition) {
+                            annotations
+                                .svc_names
+                                .entry(*number)
+                                .or_insert_with(|| definition.0.clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        annotations
+    }
+
+    /// the statement whose bytes include `address`
+    pub fn statement_at(&self, address: usize) -> Option<&AssembledStatement> {
+        self.statements.iter().find(|statement| {
+            statement.address <= address && address < statement.address + statement.bytes.len()
+        })
+    }
+
+    /// the line of the runtime that a line number refers to, if it is numbered after the source's lines (see [`Runtime`])
+    pub fn runtime_line(&self, line: usize) -> Option<RuntimeLine> {
+        self.options
+            .runtime
+            .line(line.checked_sub(self.lines.len())?)
+    }
+
+    /// the ranges of memory the program writes to, in order of address, with adjacent statements merged
+    pub fn regions(&self) -> Vec<Range<usize>> {
+        let mut regions: Vec<Range<usize>> = Vec::new();
+
+        for (range, _) in self.source_map.iter() {
+            match regions.last_mut() {
+                Some(last) if last.end >= range.start => last.end = last.end.max(range.end),
+                _ => regions.push(range),
+            }
+        }
+
+        regions
+    }
+
+    /// whether the byte at `address` was generated by an instruction or a data directive
+    pub fn region(&self, address: usize) -> Option<Region> {
+        self.statement_at(address).map(|statement| statement.region)
+    }
+
+    /// the index of the line containing the byte at `offset`
+    fn line_index(&self, offset: usize) -> usize {
+        self.line_starts.partition_point(|start| *start <= offset) - 1
+    }
+
+    /// the byte offset of the end of a line, excluding its newline character
+    fn line_end(&self, line: usize) -> usize {
+        match self.line_starts.get(line + 1) {
+            Some(next_start) => next_start - 1,
+            None => self.source.len(),
+        }
+    }
+
+    /// assembles the parsed lines, reusing the statements of a previous program where possible
+    ///
+    /// `previous_line` maps the index of a line to the index of the same line in the previous program, if it is unchanged
+    fn build(
+        source: String,
+        line_starts: Vec<usize>,
+        mut lines: Vec<Line>,
+        options: AssemblyOptions,
+        imports: SymbolTable<u32>,
+        previous: Option<&Program>,
+        previous_line: impl Fn(usize) -> Option<usize>,
+    ) -> Result<Program, AssemblyError> {
+        // the runtime's lines are numbered after the source's, so the source's line numbers are unchanged
+        let source_lines = lines.len();
+        let (prelude, postlude) = options.runtime.parse(source_lines)?;
+        let prelude_lines = source_lines..source_lines + prelude.len();
+        lines.extend(prelude);
+        lines.extend(postlude);
+
+        // only the source is optimized, and the lines it removes are kept so the program can still be edited
+        let mut optimized = Vec::new();
+        if options.optimize || options.deduplicate_strings {
+            optimized = lines[..source_lines].to_vec();
+        }
+
+        let optimizations = match options.optimize {
+            true => peephole::optimize(&mut optimized),
+            false => Vec::new(),
+        };
+        // the aliases aren't in `lines`, so references to them are still relocated like references to the string
+        let duplicate_strings = match options.deduplicate_strings {
+            true => strings::deduplicate(&mut optimized),
+            false => Vec::new(),
+        };
+
+        // but the prelude is placed before the source
+        let order = prelude_lines
+            .clone()
+            .chain(0..source_lines)
+            .chain(prelude_lines.end..lines.len());
+
+        let PreProcessResult {
+            statements,
+            mut symbol_table,
+            entry_point,
+            source_map,
+            literal_pools,
+        } = PreProcessor::with_symbol_case(options.symbol_case)
+            .with_layout(options.layout.clone())
+            .with_padding(options.padding)
+            .with_constant_synthesis(options.synthesise_constants)
+            .run_numbered(order.map(|index| {
+                (
+                    index,
+                    Ok(optimized.get(index).unwrap_or(&lines[index]).clone()),
+                )
+            }))?;
+
+        let mut imported = HashSet::new();
+
+        for (symbol, value) in imports.iter() {
+            if symbol_table.get(symbol).is_none() {
+                symbol_table.insert(
+                    symbol.clone(),
+                    Expression::from(ExpressionKind::Number {
+                        base: 16,
+                        n: *value,
+                    }),
+                );
+                imported.insert(symbol.clone());
+            }
+        }
+
+        let symbol_table = symbol_table.resolve()?;
+
+        // the previous program's statements, indexed by source line
+        let previous_statements: HashMap<usize, &AssembledStatement> = previous
+            .iter()
+            .flat_map(|program| program.statements.iter())
+            .map(|statement| (statement.source_line, statement))
+            .collect();
+
+        let mut assembled = Vec::with_capacity(statements.len());
+
+        // labels defined by EQU are constants, so references to them don't need relocating
+        let constants: HashSet<&str> = lines
+            .iter()
+            .filter(|line| {
+                matches!(
+                    line.statement,
+                    Some(Statement {
+                        kind: StatementKind::Directive {
+                            kind: DirectiveKind::Constant { .. }
+                        },
+                        ..
+                    })
+                )
+            })
+            .filter_map(|line| line.label.as_deref())
+            .collect();
+        let is_address = |symbol: &Symbol| {
+            symbol_table
+                .definition(symbol)
+                .is_some_and(|(definition, _)| !constants.contains(definition.0.as_str()))
+        };
+
+        let mut relocations = Vec::new();
+        let mut unencodable = Vec::new();
+        let mut synthesised_constants = BTreeMap::new();
+        let mut checksums = Vec::new();
+
+        for AddressedStatement {
+            address,
+            statement,
+            source_line,
+            span,
+        } in statements
+        {
+            // a statement can be reused if it is at the same address, and all the symbols it references still have the same values
+            // but not when optimizing, as a folded statement also depends on the lines after it, which may have changed
+            let reusable = previous
+                .filter(|_| !options.optimize)
+                .zip(previous_line(source_line))
+                .and_then(|(program, line)| Some((program, *previous_statements.get(&line)?)))
+                .filter(|(program, previous_statement)| {
+                    previous_statement.address == address
+                        && lines[source_line]
+                            .statement
+                            .iter()
+                            .flat_map(Statement::symbols)
+                            .all(|symbol| {
+                                program.symbol_table.get(symbol) == symbol_table.get(symbol)
+                            })
+                });
+
+            let region = match statement {
+                PreProcessedStatement::Data(_)
+                | PreProcessedStatement::Words(_)
+                | PreProcessedStatement::LiteralPool(_)
+                | PreProcessedStatement::Checksum { .. } => Region::Data,
+                PreProcessedStatement::Padding(_) => Region::Padding,
+                PreProcessedStatement::Instruction { .. }
+                | PreProcessedStatement::PseudoInstruction { .. }
+                | PreProcessedStatement::LoadLiteral { .. }
+                | PreProcessedStatement::Skip { .. } => Region::Code,
+            };
+
+            // the value of a MOV32 has already been folded into a number
+            let constant = match &statement {
+                PreProcessedStatement::PseudoInstruction {
+                    kind:
+                        PseudoInstructionKind::MoveConstant {
+                            value:
+                                Expression {
+                                    kind: ExpressionKind::Number { n, .. },
+                                    ..
+                                },
+                            ..
+                        },
+                } => Some(*n),
+                _ => None,
+            };
+
+            // a checksum is filled in once the bytes it covers have been assembled
+            let checksum = match &statement {
+                PreProcessedStatement::Checksum { kind, start, end } => {
+                    Some((*kind, start.clone(), end.clone()))
+                }
+                _ => None,
+            };
+
+            // a literal that is the address of a label is relocated like a DEFW of it
+            if let PreProcessedStatement::LiteralPool(literals) = &statement {
+                relocations.extend(Relocation::of_literals(literals, address, is_address));
+            }
+
+            // literal pools, and the branches over them, aren't the statement of their source line
+            let generated = matches!(
+                statement,
+                PreProcessedStatement::LiteralPool(_) | PreProcessedStatement::Skip { .. }
+            );
+
+            // and they can move without the line changing, along with the address of a literal
+            let reusable = reusable.filter(|_| {
+                !generated && !matches!(statement, PreProcessedStatement::LoadLiteral { .. })
+            });
+
+            let (bytes, instructions) = match reusable {
+                Some((_, previous_statement)) => (
+                    previous_statement.bytes.clone(),
+                    previous_statement.instructions.clone(),
+                ),
+
+                None => match statement.resolve(&symbol_table, address) {
+                    Ok(ResolvedStatement::Instructions(instructions)) => (
+                        instructions
+                            .iter()
+                            .flat_map(|instruction| {
+                                instruction.encode().to_bytes(ByteOrder::BigEndian)
+                            })
+                            .collect(),
+                        instructions,
+                    ),
+                    Ok(ResolvedStatement::Data(data)) => {
+                        (data.into_iter().collect(), SmallVec::new())
+                    }
+
+                    // carry on to find the rest of the unencodable values
+                    Err(error) => match UnencodableStatement::new(&error, source_line, span) {
+                        Some(statement) => {
+                            unencodable.push(statement);
+                            continue;
+                        }
+                        None => return Err(error.into()),
+                    },
+                },
+            };
+
+            if let Some(constant) = constant {
+                synthesised_constants.insert(address + bytes.len() - 4, constant);
+            }
+
+            if let Some((kind, start, end)) = checksum {
+                if let (Some(start), Some(end)) = (symbol_table.get(&start), symbol_table.get(&end))
+                {
+                    checksums.push((assembled.len(), kind, *start as usize..*end as usize));
+                }
+            }
+
+            relocations.extend(
+                lines[source_line]
+                    .statement
+                    .as_ref()
+                    .filter(|_| !generated)
+                    .into_iter()
+                    .flat_map(|statement| Relocation::of(statement, address, is_address)),
+            );
+
+            assembled.push(AssembledStatement {
+                address,
+                source_line,
+                bytes,
+                instructions,
+                region,
+            });
+        }
+
+        if !unencodable.is_empty() {
+            return Err(AssemblyError::Unencodable(unencodable));
+        }
+
+        checksum::fill(&mut assembled, &checksums, ByteOrder::BigEndian);
+
+        let mut warnings: Vec<AssemblyWarning> = lines
+            .iter()
+            .flat_map(|line| &line.warnings)
+            .cloned()
+            .map(AssemblyWarning::ParseWarning)
+            .collect();
+
+        for pool in &literal_pools {
+            if let PoolPlacement::OutOfRange { instruction, .. } = &pool.placement {
+                warnings.push(AssemblyWarning::LiteralPoolPlaced {
+                    span: instruction.clone(),
+                    address: pool.address,
+                });
+            }
+        }
+
+        for statement in lines[..source_lines]
+            .iter()
+            .flat_map(|line| &line.statement)
+        {
+            warnings.extend(
+                PcLint::check(statement)
+                    .into_iter()
+                    .map(|(lint, span)| AssemblyWarning::PcLint { span, lint }),
+            );
+        }
+
+        if options.symbol_case == SymbolCase::InsensitiveWithWarnings {
+            for statement in lines.iter().flat_map(|line| &line.statement) {
+                for reference in statement.symbols() {
+                    if let Some(definition) = symbol_table.case_mismatch(reference) {
+                        warnings.push(AssemblyWarning::SymbolCaseMismatch {
+                            span: statement.span.clone(),
+                            reference: reference.clone(),
+                            definition: definition.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        lines.truncate(source_lines);
+
+        let mut program = Program {
+            source,
+            line_starts,
+            lines,
+            options,
+            symbol_table,
+            imports,
+            imported,
+            entry_point,
+            source_map,
+            statements: assembled,
+            relocations,
+            literal_pools,
+            synthesised_constants,
+            optimizations,
+            duplicate_strings,
+            #[cfg(feature = "formats")]
+            checksums,
+            warnings,
+        };
+
+        program.warnings.extend(program.jump_table_warnings());
+
+        Ok(program)
+    }
+}
+
+/// assembles a single instruction or pseudo-instruction, as if it were at `address`
+pub fn assemble_instruction(
+    source: &str,
+    symbol_table: &SymbolTable<u32>,
+    address: usize,
+) -> Result<SmallVec<[InstructionKind; 2]>, AssemblyError> {
+    let statement = Parser::parse_statement(source)?;
+
+    let statement = match statement.kind {
+        StatementKind::Instruction { kind } => PreProcessedStatement::Instruction { kind },
+        StatementKind::PseudoInstruction { kind } => {
+            PreProcessedStatement::PseudoInstruction { kind }
+        }
+        StatementKind::Directive { .. } => {
+            return Err(AssemblyError::NotAnInstruction(statement.span))
+        }
+    };
+
+    match statement.resolve(symbol_table, address)? {
+        ResolvedStatement::Instructions(instructions) => Ok(instructions),
+        ResolvedStatement::Data(_) => unreachable!("instructions don't resolve to data"),
+    }
+}
+
+/// the byte offsets of the start of each line in `text`, which begins at byte `offset` of its file
+fn line_starts(text: &str, offset: usize) -> impl Iterator<Item = usize> + '_ {
+    std::iter::once(offset).chain(
+        text.match_indices('\n')
+            .map(move |(index, _)| offset + index + 1),
+    )
+}
+
+/// parses each line of `text`, which begins at byte `offset` and line `first_line` of its file
+fn parse_lines(text: &str, offset: usize, first_line: usize) -> Result<Vec<Line>, ParseError> {
+    text.split('\n')
+        .zip(line_starts(text, offset))
+        .enumerate()
+        .map(|(index, (line, start))| Parser::parse_line(line, start, first_line + index))
+        .collect()
+}
@@ -0,0 +1,280 @@
+use std::fmt::Display;
+
+use crate::{
+    ir::{AddressingOffsetValue, Rn, RotatedImm8, ShiftedRegister},
+    parser::{
+        BranchKind, CalculationKind, DataProcessingKind, Expression, ExpressionKind,
+        InstructionKind, Line, LoadStoreAddress, LoadStoreKind, LoadStoreQuantity, MoveKind,
+        OffsetMode, SetFlags, ShifterOperandExpression, StatementInstructionKind, StatementKind,
+    },
+};
+
+/// a change made by the peephole optimizer, see [`super::AssemblyOptions::optimize`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Optimization {
+    /// the zero-based source line that was changed or removed
+    pub source_line: usize,
+    pub kind: OptimizationKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OptimizationKind {
+    /// a MOV of an immediate, followed by an ADD or SUB of an immediate to the same register, was folded into a MOV of `value`, and the ADD or SUB on `removed` was removed
+    FoldedMove { removed: usize, value: u32 },
+    /// a branch to the instruction after it was removed
+    BranchToNext,
+    /// a load of a value that the register already holds, from a load or store of the same address on `previous`, was removed
+    RedundantLoad { previous: usize },
+}
+
+impl Display for Optimization {
+    /// describes the change, with one-based line numbers
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let line = self.source_line + 1;
+
+        match &self.kind {
+            OptimizationKind::FoldedMove { removed, value } => write!(
+                f,
+                "line {line}: folded the immediate on line {} into the MOV, which now moves #{value}",
+                removed + 1
+            ),
+            OptimizationKind::BranchToNext => write!(
+                f,
+                "line {line}: removed a branch to the next instruction, which runs next anyway"
+            ),
+            OptimizationKind::RedundantLoad { previous } => write!(
+                f,
+                "line {line}: removed a load of the value the register already holds from line {}",
+                previous + 1
+            ),
+        }
+    }
+}
+
+/// applies the peephole optimizations to a program's lines, returning the changes made
+///
+/// Only statements next to each other are considered, and a statement with a label is never merged into the one before it, as it can be branched to.
+pub(super) fn optimize(lines: &mut [Line]) -> Vec<Optimization> {
+    let mut optimizations = Vec::new();
+
+    let mut statements: Vec<usize> = (0..lines.len())
+        .filter(|&index| lines[index].statement.is_some())
+        .collect();
+
+    let mut i = 0;
+
+    while i + 1 < statements.len() {
+        let (first, second) = (statements[i], statements[i + 1]);
+
+        // the labels between the statements, including one on the second
+        let labels: Vec<&str> = lines[first + 1..=second]
+            .iter()
+            .filter_map(|line| line.label.as_deref())
+            .collect();
+
+        if let Some(target) = branch_target(&lines[first]) {
+            if labels.contains(&target) {
+                lines[first].statement = None;
+                statements.remove(i);
+                i = i.saturating_sub(1);
+
+                optimizations.push(Optimization {
+                    source_line: first,
+                    kind: OptimizationKind::BranchToNext,
+                });
+                continue;
+            }
+        }
+
+        if !labels.is_empty() {
+            i += 1;
+            continue;
+        }
+
+        let kind = match (instruction(&lines[first]), instruction(&lines[second])) {
+            (Some(a), Some(b)) => {
+                if let Some(value) = folded_move(a, b) {
+                    set_move_immediate(&mut lines[first], value);
+
+                    Some((
+                        first,
+                        OptimizationKind::FoldedMove {
+                            removed: second,
+                            value,
+                        },
+                    ))
+                } else if redundant_load(a, b) {
+                    Some((second, OptimizationKind::RedundantLoad { previous: first }))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+
+        match kind {
+            Some((source_line, kind)) => {
+                // both optimizations remove the second statement, and the first can be merged with the one after it
+                lines[second].statement = None;
+                statements.remove(i + 1);
+
+                optimizations.push(Optimization { source_line, kind });
+            }
+            None => i += 1,
+        }
+    }
+
+    optimizations
+}
+
+fn instruction(line: &Line) -> Option<&StatementInstructionKind> {
+    match &line.statement.as_ref()?.kind {
+        StatementKind::Instruction { kind } => Some(kind),
+        _ => None,
+    }
+}
+
+/// the label a B (without link) branches to
+fn branch_target(line: &Line) -> Option<&str> {
+    match instruction(line)? {
+        InstructionKind::Branch {
+            kind: BranchKind::Branch,
+            target,
+            ..
+        } => Some(&target.0),
+        _ => None,
+    }
+}
+
+/// the value of an immediate written as a number
+fn immediate(shifter: &ShifterOperandExpression) -> Option<u32> {
+    match shifter {
+        ShifterOperandExpression::Immediate(Expression {
+            kind: ExpressionKind::Number { n, .. },
+            ..
+        }) => Some(*n),
+        _ => None,
+    }
+}
+
+/// the value moved by `MOV Rd, #a` followed by `ADD Rd, Rd, #b` or `SUB Rd, Rd, #b`, if it fits in an immediate
+fn folded_move(first: &StatementInstructionKind, second: &StatementInstructionKind) -> Option<u32> {
+    let (
+        InstructionKind::DataProcessing {
+            condition,
+            kind:
+                DataProcessingKind::Move {
+                    kind: MoveKind::Move,
+                    set_flags: SetFlags::DontSet,
+                    destination,
+                    shifter: moved,
+                },
+        },
+        InstructionKind::DataProcessing {
+            condition: second_condition,
+            kind:
+                DataProcessingKind::Calculation {
+                    kind,
+                    set_flags: SetFlags::DontSet,
+                    destination: second_destination,
+                    source,
+                    shifter: operand,
+                },
+        },
+    ) = (first, second)
+    else {
+        return None;
+    };
+
+    if condition != second_condition
+        || destination != second_destination
+        || *source != Rn(destination.0)
+        || destination.0 == 15
+    {
+        return None;
+    }
+
+    let (a, b) = (immediate(moved)?, immediate(operand)?);
+
+    let value = match kind {
+        CalculationKind::ADD => a.wrapping_add(b),
+        CalculationKind::SUB => a.wrapping_sub(b),
+        _ => return None,
+    };
+
+    RotatedImm8::try_from(value).ok().map(|_| value)
+}
+
+fn set_move_immediate(line: &mut Line, value: u32) {
+    if let Some(StatementKind::Instruction {
+        kind:
+            InstructionKind::DataProcessing {
+                kind:
+                    DataProcessingKind::Move {
+                        shifter: ShifterOperandExpression::Immediate(expression),
+                        ..
+                    },
+                ..
+            },
+    }) = line.statement.as_mut().map(|statement| &mut statement.kind)
+    {
+        if let ExpressionKind::Number { n, .. } = &mut expression.kind {
+            *n = value;
+        }
+    }
+}
+
+/// whether `second` loads a register with the value it already holds after `first`, which loads the same register from, or stores it to, the same address
+///
+/// A store followed by a load is only redundant for words, as a byte load clears the rest of the register.
+fn redundant_load(first: &StatementInstructionKind, second: &StatementInstructionKind) -> bool {
+    let (
+        InstructionKind::LoadStore {
+            condition,
+            kind,
+            quantity,
+            destination,
+            address,
+        },
+        InstructionKind::LoadStore {
+            condition: second_condition,
+            kind: LoadStoreKind::Load,
+            quantity: second_quantity,
+            destination: second_destination,
+            address: second_address,
+        },
+    ) = (first, second)
+    else {
+        return false;
+    };
+
+    let same_value = match kind {
+        LoadStoreKind::Load => quantity == second_quantity,
+        LoadStoreKind::Store => {
+            *quantity == LoadStoreQuantity::Word && *second_quantity == LoadStoreQuantity::Word
+        }
+    };
+
+    // the address must not depend on the register, or change between the two
+    let stable_address = match address {
+        LoadStoreAddress::Expression(_) => true,
+        LoadStoreAddress::AddressingMode(code) => {
+            code.offset.mode == OffsetMode::Offset
+                && code.base.0 != destination.0
+                && !matches!(
+                    &code.offset.value,
+                    AddressingOffsetValue::Register(offset)
+                        | AddressingOffsetValue::ScaledRegister(ShiftedRegister { base: offset, .. })
+                        if offset.0 == destination.0
+                )
+        }
+    };
+
+    // expressions are compared as source, as their spans differ
+    same_value
+        && stable_address
+        && condition == second_condition
+        && destination == second_destination
+        && destination.0 != 15
+        && address.to_string() == second_address.to_string()
+}
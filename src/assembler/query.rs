@@ -0,0 +1,90 @@
+use crate::{
+    ir::InstructionKind,
+    parser::{DirectiveKind, Outline, StatementKind, Symbol},
+};
+
+use super::Program;
+
+/// an instruction of an assembled program, along with where it is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InstructionSite<'a> {
+    pub address: usize,
+    pub source_line: usize,
+    pub instruction: &'a InstructionKind,
+}
+
+impl Program {
+    /// every instruction of the program, in the order of the statements that generated them
+    pub fn instructions(&self) -> impl Iterator<Item = InstructionSite<'_>> {
+        self.statements.iter().flat_map(|statement| {
+            statement
+                .instructions
+                .iter()
+                .enumerate()
+                .map(|(index, instruction)| InstructionSite {
+                    address: statement.address + index * 4,
+                    source_line: statement.source_line,
+                    instruction,
+                })
+        })
+    }
+
+    /// the instructions that may write `register`, e.g. 13 for everything that changes the stack pointer
+    pub fn instructions_writing(&self, register: u8) -> Vec<InstructionSite<'_>> {
+        self.instructions()
+            .filter(|site| site.instruction.writes().contains(register))
+            .collect()
+    }
+
+    /// the instructions that read `register`
+    pub fn instructions_reading(&self, register: u8) -> Vec<InstructionSite<'_>> {
+        self.instructions()
+            .filter(|site| site.instruction.reads().contains(register))
+            .collect()
+    }
+
+    /// the branches to the address of `label`, including BLs, which is empty if the label isn't defined
+    pub fn branches_to(&self, label: &str) -> Vec<InstructionSite<'_>> {
+        let Some(target) = self.symbol_table.get(&Symbol(label.to_owned())) else {
+            return Vec::new();
+        };
+
+        self.instructions()
+            .filter(|site| site.instruction.branch_target(site.address as u32) == Some(*target))
+            .collect()
+    }
+
+    /// the SVC instructions, which are where the program calls the environment
+    pub fn svc_sites(&self) -> Vec<InstructionSite<'_>> {
+        self.instructions()
+            .filter(|site| matches!(site.instruction, InstructionKind::SuperVisorCall { .. }))
+            .collect()
+    }
+
+    /// the last label at or before `address`, which names the routine or data it is in, ignoring EQU constants
+    pub fn label_before(&self, address: usize) -> Option<String> {
+        self.lines
+            .iter()
+            .filter(|line| {
+                !matches!(
+                    line.statement.as_ref().map(|statement| &statement.kind),
+                    Some(StatementKind::Directive {
+                        kind: DirectiveKind::Constant { .. }
+                    })
+                )
+            })
+            .filter_map(|line| {
+                let label = line.label.as_ref()?;
+                let value = *self.symbol_table.get(&Symbol(label.clone()))? as usize;
+
+                (value <= address).then_some((value, label))
+            })
+            .max_by_key(|(value, _)| *value)
+            .map(|(_, label)| label.clone())
+    }
+
+    /// the outline of the source's labels and sections, see [`Outline::parse`] for source that doesn't assemble
+    pub fn outline(&self) -> Outline {
+        Outline::new(&self.lines)
+    }
+}
@@ -0,0 +1,128 @@
+use crate::{
+    ir::{BranchKind, Condition, InstructionKind},
+    parser::{
+        DefinitionKind, DirectiveKind, Expression, ExpressionKind, Statement, StatementKind,
+        Symbol, WordDefinition,
+    },
+};
+
+/// a place in the program whose bytes depend on the address of a symbol, which a separate link step has to fix up if it moves the program or the symbol
+#[derive(Debug, Clone, PartialEq)]
+pub struct Relocation {
+    /// the address of the bytes to fix up
+    pub address: usize,
+    pub kind: RelocationKind,
+    /// the symbol as it was written, which may be imported from another program
+    pub symbol: Symbol,
+}
+
+/// how a symbol's address is encoded at a relocation, which matches an ELF relocation type for ARM
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RelocationKind {
+    /// the 24-bit word offset of a B, or a conditional BL (`R_ARM_JUMP24`)
+    Jump24,
+    /// the 24-bit word offset of an unconditional BL or a BLX, which a linker can turn into a call to Thumb code or a veneer (`R_ARM_CALL`)
+    Call,
+    /// the address of a DEFW label, or of a label loaded with `LDR Rd, =label` from a literal pool (`R_ARM_ABS32`)
+    Abs32,
+    /// the offset of a DEFW label from the word, as in `DEFW label - .` (`R_ARM_REL32`)
+    Rel32,
+}
+
+impl RelocationKind {
+    /// the ELF relocation type, for writing objects that GNU ld can link
+    pub fn elf_type(&self) -> u32 {
+        match self {
+            RelocationKind::Abs32 => 2,
+            RelocationKind::Rel32 => 3,
+            RelocationKind::Call => 28,
+            RelocationKind::Jump24 => 29,
+        }
+    }
+}
+
+impl Relocation {
+    /// the relocations for a statement at `address`, for each symbol it refers to that `is_address` says is an address, rather than a constant
+    pub(super) fn of(
+        statement: &Statement,
+        address: usize,
+        is_address: impl Fn(&Symbol) -> bool,
+    ) -> Vec<Relocation> {
+        let relocation = |address, kind, symbol: &Symbol| {
+            is_address(symbol).then(|| Relocation {
+                address,
+                kind,
+                symbol: symbol.clone(),
+            })
+        };
+
+        let (kind, target) = match &statement.kind {
+            StatementKind::Instruction {
+                kind:
+                    InstructionKind::Branch {
+                        condition: Condition::AL,
+                        kind: BranchKind::BranchWithLink,
+                        target,
+                    },
+            }
+            | StatementKind::Instruction {
+                kind: InstructionKind::BranchWithLinkAndExchange { target, .. },
+            } => (RelocationKind::Call, target),
+
+            StatementKind::Instruction {
+                kind: InstructionKind::Branch { target, .. },
+            } => (RelocationKind::Jump24, target),
+
+            // each word of a DEFW is relocated separately
+            StatementKind::Directive {
+                kind:
+                    DirectiveKind::Definition {
+                        kind: DefinitionKind::Words { words },
+                    },
+            } => {
+                return words
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, word)| {
+                        let (kind, symbol) = match word {
+                            // like a literal, an expression is only relocated if it is just a label
+                            WordDefinition::Expression(Expression {
+                                kind: ExpressionKind::Symbol(symbol),
+                                ..
+                            }) => (RelocationKind::Abs32, symbol),
+                            WordDefinition::Word(_) | WordDefinition::Expression(_) => return None,
+                            WordDefinition::Address(symbol) => (RelocationKind::Abs32, symbol),
+                            WordDefinition::Offset(symbol) => (RelocationKind::Rel32, symbol),
+                        };
+
+                        relocation(address + 4 * index, kind, symbol)
+                    })
+                    .collect();
+            }
+
+            _ => return Vec::new(),
+        };
+
+        relocation(address, kind, target).into_iter().collect()
+    }
+
+    /// the relocations for the literals of a pool at `address`, for each that is the address of a label
+    pub(super) fn of_literals(
+        literals: &[Expression],
+        address: usize,
+        is_address: impl Fn(&Symbol) -> bool,
+    ) -> Vec<Relocation> {
+        literals
+            .iter()
+            .enumerate()
+            .filter_map(|(index, literal)| match &literal.kind {
+                ExpressionKind::Symbol(symbol) if is_address(symbol) => Some(Relocation {
+                    address: address + 4 * index,
+                    kind: RelocationKind::Abs32,
+                    symbol: symbol.clone(),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,59 @@
+use crate::parser::{Line, ParseError};
+
+use super::parse_lines;
+
+/// assembly provided by an instructor that is assembled along with a student's source, such as stack setup, SVC wrappers and an exit trampoline
+///
+/// The prelude is placed before the source and the postlude after it, and they share the source's symbols, so e.g. the prelude can `BL main`.
+/// Their lines are numbered after the source's lines, so the source map and diagnostics keep the student's line numbers (see [`Program::runtime_line`](super::Program::runtime_line)).
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Runtime {
+    pub prelude: String,
+    pub postlude: String,
+}
+
+/// a line of a [`Runtime`], numbered from 0 within its part
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RuntimeLine {
+    Prelude(usize),
+    Postlude(usize),
+}
+
+impl Runtime {
+    /// parses the prelude and postlude, numbering their lines from `first_line`
+    pub(super) fn parse(&self, first_line: usize) -> Result<(Vec<Line>, Vec<Line>), ParseError> {
+        let prelude = parse_part(&self.prelude, first_line)?;
+        let postlude = parse_part(&self.postlude, first_line + prelude.len())?;
+
+        Ok((prelude, postlude))
+    }
+
+    /// the runtime line with the given index, counting from the start of the prelude
+    pub(super) fn line(&self, index: usize) -> Option<RuntimeLine> {
+        let prelude = line_count(&self.prelude);
+
+        if index < prelude {
+            Some(RuntimeLine::Prelude(index))
+        } else if index - prelude < line_count(&self.postlude) {
+            Some(RuntimeLine::Postlude(index - prelude))
+        } else {
+            None
+        }
+    }
+}
+
+fn parse_part(text: &str, first_line: usize) -> Result<Vec<Line>, ParseError> {
+    if text.is_empty() {
+        Ok(Vec::new())
+    } else {
+        parse_lines(text, 0, first_line)
+    }
+}
+
+fn line_count(text: &str) -> usize {
+    if text.is_empty() {
+        0
+    } else {
+        text.split('\n').count()
+    }
+}
@@ -0,0 +1,56 @@
+use std::{collections::BTreeMap, ops::Range};
+
+/// maps between addresses and the source lines that generated them
+///
+/// Every byte generated by a statement maps to its line, including the second instruction of an ADRL and the bytes of data directives.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SourceMap {
+    /// the end address and source line of each statement, by start address
+    statements: BTreeMap<usize, (usize, usize)>,
+    /// the address range generated by each source line
+    lines: BTreeMap<usize, Range<usize>>,
+}
+
+impl SourceMap {
+    pub fn new() -> SourceMap {
+        SourceMap::default()
+    }
+
+    /// records that a source line generated the bytes in `range`
+    ///
+    /// Statements that don't generate any bytes (e.g. `DEFS 0`) aren't recorded.
+    pub fn insert(&mut self, range: Range<usize>, line: usize) {
+        if range.is_empty() {
+            return;
+        }
+
+        self.statements.insert(range.start, (range.end, line));
+        self.lines.insert(line, range);
+    }
+
+    /// records that the bytes in `range` were generated on behalf of a source line, such as a literal pool placed by an LTORG or before a statement, without making them the line's addresses
+    pub fn insert_generated(&mut self, range: Range<usize>, line: usize) {
+        if !range.is_empty() {
+            self.statements.insert(range.start, (range.end, line));
+        }
+    }
+
+    /// the source line that generated the byte at `address`
+    pub fn line(&self, address: usize) -> Option<usize> {
+        let (_, (end, line)) = self.statements.range(..=address).next_back()?;
+
+        (address < *end).then_some(*line)
+    }
+
+    /// the address range generated by a source line, if it generated any bytes
+    pub fn addresses(&self, line: usize) -> Option<Range<usize>> {
+        self.lines.get(&line).cloned()
+    }
+
+    /// the address range and source line of each statement, in order of address
+    pub fn iter(&self) -> impl Iterator<Item = (Range<usize>, usize)> + '_ {
+        self.statements
+            .iter()
+            .map(|(start, (end, line))| (*start..*end, *line))
+    }
+}
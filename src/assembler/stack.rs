@@ -0,0 +1,279 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    ops::Range,
+};
+
+use crate::{
+    ir::{
+        AddressingOffset, AddressingOffsetValue, BranchKind, CalculationKind, DataProcessingKind,
+        InstructionKind, LoadStoreAddressCode, MultipleAddressingMode, OffsetMode, Rd, Rn,
+        ShifterOperandCode, Sign, WriteBack,
+    },
+    parser::{DirectiveKind, StatementKind, Symbol},
+};
+
+use super::Program;
+
+/// the stack used by a routine, worked out from the instructions that move SP, see [`Program::stack_usage`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoutineStack {
+    /// the address of the routine's first instruction
+    pub address: usize,
+    /// the label at the start of the routine, if there is one
+    pub label: Option<String>,
+    /// the most bytes the routine has on the stack at once, not counting the routines it calls
+    pub frame: u32,
+    /// the most bytes on the stack during a call to the routine, including the routines it calls, or `None` if it can call itself
+    pub total: Option<u32>,
+    /// the length of the longest chain of calls the routine makes, e.g. 0 for a routine that doesn't call any, or `None` if it can call itself
+    pub call_depth: Option<usize>,
+    /// the addresses of the routines it calls with BL
+    pub calls: BTreeSet<usize>,
+    /// the returns where the bytes pushed and popped by the routine don't balance
+    pub unbalanced: Vec<UnbalancedReturn>,
+}
+
+impl RoutineStack {
+    pub fn is_balanced(&self) -> bool {
+        self.unbalanced.is_empty()
+    }
+}
+
+/// a return from a routine that leaves SP somewhere other than where it was when the routine was called
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnbalancedReturn {
+    /// the address of the returning instruction
+    pub address: usize,
+    pub source_line: usize,
+    /// the bytes left on the stack, which is negative if more was popped than pushed
+    pub offset: i64,
+}
+
+/// a routine's own use of the stack, before the routines it calls are taken into account
+struct Frame {
+    size: u32,
+    /// the bytes on the stack at each BL, and the address it calls
+    calls: Vec<(u32, usize)>,
+    unbalanced: Vec<UnbalancedReturn>,
+}
+
+impl Program {
+    /// the stack used by each routine, in order of address, to check that every routine pops what it pushes
+    ///
+    /// The routines are the entry point and the targets of BLs, and each runs until the next one starts.
+    /// Their instructions are followed in order, rather than along each path through branches, so this is an estimate for routines with several returns.
+    /// STM and LDM, and LDR and STR, with write-back to SP are counted, as are ADD and SUB of an immediate to SP, and a routine returns when it writes the PC with anything but a branch.
+    pub fn stack_usage(&self) -> Vec<RoutineStack> {
+        let starts = self.routine_starts();
+
+        let frames: BTreeMap<usize, Frame> = starts
+            .iter()
+            .map(|&start| {
+                let end = starts
+                    .range(start + 1..)
+                    .next()
+                    .copied()
+                    .unwrap_or(usize::MAX);
+
+                (start, self.frame(start..end))
+            })
+            .collect();
+
+        let mut totals = BTreeMap::new();
+
+        frames
+            .iter()
+            .map(|(&address, frame)| {
+                let total = total_stack(address, &frames, &mut totals, &mut HashSet::new());
+
+                RoutineStack {
+                    address,
+                    label: self.label_at(address),
+                    frame: frame.size,
+                    total: total.map(|(total, _)| total),
+                    call_depth: total.map(|(_, depth)| depth),
+                    calls: frame.calls.iter().map(|(_, target)| *target).collect(),
+                    unbalanced: frame.unbalanced.clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// the addresses of the routines, which are the entry point and the targets of BLs
+    pub(crate) fn routine_starts(&self) -> BTreeSet<usize> {
+        let mut starts: BTreeSet<usize> = self
+            .instructions()
+            .filter(|site| {
+                matches!(
+                    site.instruction,
+                    InstructionKind::Branch {
+                        kind: BranchKind::BranchWithLink,
+                        ..
+                    }
+                )
+            })
+            .filter_map(|site| site.instruction.branch_target(site.address as u32))
+            .map(|target| target as usize)
+            .collect();
+        starts.insert(self.entry_point);
+
+        starts
+    }
+
+    /// follows the instructions in `range`, keeping track of the bytes on the stack
+    fn frame(&self, range: Range<usize>) -> Frame {
+        let mut frame = Frame {
+            size: 0,
+            calls: Vec::new(),
+            unbalanced: Vec::new(),
+        };
+        let mut offset: i64 = 0;
+
+        for site in self
+            .instructions()
+            .filter(|site| range.contains(&site.address))
+        {
+            offset += pushed(site.instruction);
+            frame.size = frame.size.max(offset.clamp(0, u32::MAX.into()) as u32);
+
+            match site.instruction {
+                InstructionKind::Branch {
+                    kind: BranchKind::BranchWithLink,
+                    ..
+                } => {
+                    if let Some(target) = site.instruction.branch_target(site.address as u32) {
+                        frame
+                            .calls
+                            .push((offset.clamp(0, u32::MAX.into()) as u32, target as usize));
+                    }
+                }
+
+                InstructionKind::Branch { .. } => {}
+
+                instruction if instruction.writes().contains(15) && offset != 0 => {
+                    frame.unbalanced.push(UnbalancedReturn {
+                        address: site.address,
+                        source_line: site.source_line,
+                        offset,
+                    });
+                }
+
+                _ => {}
+            }
+        }
+
+        frame
+    }
+
+    /// the first label defined at `address`, ignoring EQU constants
+    pub(crate) fn label_at(&self, address: usize) -> Option<String> {
+        self.lines
+            .iter()
+            .filter(|line| {
+                !matches!(
+                    line.statement.as_ref().map(|statement| &statement.kind),
+                    Some(StatementKind::Directive {
+                        kind: DirectiveKind::Constant { .. }
+                    })
+                )
+            })
+            .filter_map(|line| line.label.as_ref())
+            .find(|label| {
+                self.symbol_table.get(&Symbol((*label).clone())) == Some(&(address as u32))
+            })
+            .cloned()
+    }
+}
+
+/// the most bytes on the stack during a call to the routine at `address`, and the length of its longest chain of calls, or `None` if it can call itself
+fn total_stack(
+    address: usize,
+    frames: &BTreeMap<usize, Frame>,
+    totals: &mut BTreeMap<usize, Option<(u32, usize)>>,
+    calling: &mut HashSet<usize>,
+) -> Option<(u32, usize)> {
+    if let Some(total) = totals.get(&address) {
+        return *total;
+    }
+
+    // the routine is already being called, so it is recursive
+    if !calling.insert(address) {
+        return None;
+    }
+
+    let frame = &frames[&address];
+
+    let mut total = Some((frame.size, 0));
+
+    for (offset, target) in &frame.calls {
+        total = match (total, total_stack(*target, frames, totals, calling)) {
+            (Some((size, depth)), Some((callee, callee_depth))) => {
+                Some((size.max(offset + callee), depth.max(callee_depth + 1)))
+            }
+            _ => None,
+        };
+    }
+
+    calling.remove(&address);
+    totals.insert(address, total);
+
+    total
+}
+
+/// the bytes the instruction pushes onto the stack, which is negative if it pops them
+fn pushed(instruction: &InstructionKind) -> i64 {
+    match instruction {
+        InstructionKind::LoadStoreMultiple {
+            mode,
+            base: Rn(13),
+            write_back: WriteBack::WriteBack,
+            register_list,
+            ..
+        } => {
+            let bytes = 4 * register_list.count() as i64;
+
+            match mode {
+                MultipleAddressingMode::DecrementAfter
+                | MultipleAddressingMode::DecrementBefore => bytes,
+                MultipleAddressingMode::IncrementAfter
+                | MultipleAddressingMode::IncrementBefore => -bytes,
+            }
+        }
+
+        InstructionKind::LoadStore {
+            address:
+                LoadStoreAddressCode {
+                    base: Rn(13),
+                    offset:
+                        AddressingOffset {
+                            sign,
+                            value: AddressingOffsetValue::Immediate(immediate),
+                            mode: OffsetMode::PreIndexed | OffsetMode::PostIndexed,
+                        },
+                },
+            ..
+        } => match sign {
+            Sign::Negative => immediate.get().into(),
+            Sign::Positive => -i64::from(immediate.get()),
+        },
+
+        InstructionKind::DataProcessing {
+            kind:
+                DataProcessingKind::Calculation {
+                    kind,
+                    destination: Rd(13),
+                    source: Rn(13),
+                    shifter: ShifterOperandCode::Immediate(immediate),
+                    ..
+                },
+            ..
+        } => match kind {
+            CalculationKind::SUB => immediate.get().into(),
+            CalculationKind::ADD => -i64::from(immediate.get()),
+            // other calculations aren't used to move SP
+            _ => 0,
+        },
+
+        _ => 0,
+    }
+}
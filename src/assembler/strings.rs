@@ -0,0 +1,98 @@
+use std::fmt::Display;
+
+use crate::parser::{
+    BytesDefinition, DefinitionKind, DirectiveKind, Expression, ExpressionKind, Line,
+    StatementKind, Symbol,
+};
+
+/// a string definition that was replaced by an alias of an identical one, see [`super::AssemblyOptions::deduplicate_strings`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateString {
+    /// the zero-based source line of the DEFB that was removed
+    pub source_line: usize,
+    /// the zero-based source line of the DEFB that is kept
+    pub original: usize,
+    /// the label of the kept DEFB, which the removed DEFB's labels now refer to
+    pub alias: String,
+    /// the bytes of the removed DEFB
+    pub saved: usize,
+}
+
+impl Display for DuplicateString {
+    /// describes the change, with one-based line numbers
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}: the string is the same as the one on line {}, so it now refers to {}, saving {} byte{}",
+            self.source_line + 1,
+            self.original + 1,
+            self.alias,
+            self.saved,
+            if self.saved == 1 { "" } else { "s" }
+        )
+    }
+}
+
+/// replaces each labelled DEFB of a string that is identical to an earlier labelled one with an EQU of the earlier one's label, returning the replacements made
+///
+/// The labels on the lines without statements before a DEFB are its labels too, so they are aliased along with it.
+pub(super) fn deduplicate(lines: &mut [Line]) -> Vec<DuplicateString> {
+    let mut duplicates = Vec::new();
+    // the bytes of each string kept so far, with its line and the label to alias it by
+    let mut originals: Vec<(Vec<u8>, usize, String)> = Vec::new();
+    let mut label = None;
+
+    for (index, line) in lines.iter_mut().enumerate() {
+        if let Some(own) = &line.label {
+            label = Some(own.clone());
+        }
+
+        let Some(statement) = &mut line.statement else {
+            continue;
+        };
+
+        let Some(label) = label.take() else {
+            continue;
+        };
+
+        let StatementKind::Directive {
+            kind:
+                DirectiveKind::Definition {
+                    kind: DefinitionKind::Bytes { bytes },
+                },
+        } = &statement.kind
+        else {
+            continue;
+        };
+
+        if !bytes
+            .iter()
+            .any(|byte| matches!(byte, BytesDefinition::String(_)))
+        {
+            continue;
+        }
+
+        // strings are compared by their bytes, so `"Hi", 0` is the same as `"H", "i", 0`
+        let bytes: Vec<u8> = bytes.iter().cloned().flatten().collect();
+
+        match originals.iter().find(|(original, ..)| *original == bytes) {
+            Some((_, original, alias)) => {
+                duplicates.push(DuplicateString {
+                    source_line: index,
+                    original: *original,
+                    alias: alias.clone(),
+                    saved: bytes.len(),
+                });
+
+                statement.kind = StatementKind::Directive {
+                    kind: DirectiveKind::Constant {
+                        value: Expression::from(ExpressionKind::Symbol(Symbol(alias.clone()))),
+                    },
+                };
+            }
+            None => originals.push((bytes, index, label)),
+        }
+    }
+
+    duplicates
+}
@@ -0,0 +1,1563 @@
+#[cfg(feature = "emulator")]
+use crate::emulator::Emulator;
+use crate::{
+    assembler::{
+        assemble_instruction, AssemblyError, AssemblyOptions, AssemblyWarning, ChecksumKind,
+        DuplicateString, Hazard, HazardKind, InstructionSite, JumpTable, JumpTableProblem, Layout,
+        LiteralPool, Optimization, OptimizationKind, Padding, PcLint, Phase, PipelineModel,
+        PoolPlacement, Program, ProgramBuilder, ReferenceKind, Region, Relocation, RelocationKind,
+        RenameError, Runtime, RuntimeLine, Severity, Suggestion, TextEdit, UnbalancedReturn,
+        UnreferencedLabel, WorkspaceIndex,
+    },
+    ir::{
+        ByteOrder, Condition, DataProcessingKind, ImmediateEncoding, InstructionKind, Shift,
+        ShifterOperandCode,
+    },
+    parser::Symbol,
+    preprocessor::PreProcessError,
+    resolver::{ResolveError, SymbolCase, SymbolTable},
+    span::Span,
+};
+
+const PROGRAM: &str = "	B main
+hello	DEFB	\"Hello\\0\"
+	ALIGN
+main	ADR	R0, hello
+	SVC	3
+loop	SUB	R1, R1, #1
+	CMP	R1, #0
+	BNE	loop
+	SVC	2";
+
+/// checks that editing a program gives the same result as assembling the edited source from scratch
+fn assert_edit_matches_assemble(range: std::ops::Range<usize>, text: &str) {
+    let program = Program::assemble(PROGRAM).unwrap();
+
+    let edited = program
+        .edit(&TextEdit {
+            range,
+            text: text.to_owned(),
+        })
+        .unwrap();
+
+    let assembled = Program::assemble(edited.source()).unwrap();
+
+    assert_eq!(edited.statements, assembled.statements);
+    assert_eq!(edited.source_map, assembled.source_map);
+    assert_eq!(edited.entry_point, assembled.entry_point);
+    assert_eq!(edited.line_starts, assembled.line_starts);
+    assert_eq!(edited.lines, assembled.lines);
+}
+
+#[test]
+fn test_edit_within_line() {
+    let start = PROGRAM.find("#1").unwrap() + 1;
+
+    assert_edit_matches_assemble(start..start + 1, "2");
+}
+
+#[test]
+fn test_edit_shifting_addresses() {
+    // lengthening the string moves every label after it
+    let start = PROGRAM.find("Hello").unwrap();
+
+    assert_edit_matches_assemble(start..start + 5, "Hello, World\\n");
+}
+
+#[test]
+fn test_edit_inserting_and_removing_lines() {
+    let start = PROGRAM.find("	SVC	3").unwrap();
+    let end = PROGRAM.find("	CMP").unwrap();
+
+    assert_edit_matches_assemble(
+        start..end,
+        "	MOV	R1, #10\n	ADD	R1, R1, R1\n\nloop	SUB	R1, R1, #2\n",
+    );
+}
+
+#[test]
+fn test_patch() {
+    let mut program = Program::assemble(PROGRAM).unwrap();
+    let address = program
+        .symbol_table
+        .get(&Symbol("loop".to_owned()))
+        .copied()
+        .unwrap() as usize;
+
+    program.patch(address, "SUB R1, R1, #2").unwrap();
+    program.patch(address + 8, "BEQ main").unwrap();
+
+    let expected = Program::assemble(
+        &PROGRAM
+            .replace("#1", "#2")
+            .replace("BNE\tloop", "BEQ\tmain"),
+    )
+    .unwrap();
+
+    assert_eq!(program.statements, expected.statements);
+
+    assert!(matches!(
+        program.patch(address, "ALIGN"),
+        Err(AssemblyError::NotAnInstruction(_))
+    ));
+    assert!(matches!(
+        program.patch(address + 16, "MOV R0, #0"),
+        Err(AssemblyError::UnmappedAddress(_))
+    ));
+}
+
+#[test]
+fn test_symbol_case() {
+    let source = PROGRAM.replace("BNE\tloop", "BNE\tLoop");
+
+    let assemble = |symbol_case| {
+        Program::assemble_with(
+            &source,
+            AssemblyOptions {
+                symbol_case,
+                ..AssemblyOptions::default()
+            },
+        )
+    };
+
+    assert!(matches!(
+        assemble(SymbolCase::Sensitive),
+        Err(AssemblyError::ResolveError(ResolveError::SymbolNotFound(_)))
+    ));
+
+    let insensitive = assemble(SymbolCase::Insensitive).unwrap();
+    assert_eq!(
+        insensitive.symbol_table.get(&Symbol("LOOP".to_owned())),
+        Some(&20)
+    );
+    assert_eq!(insensitive.warnings, []);
+
+    // the bytes are the same as referring to the label with the right case
+    let expected = Program::assemble(PROGRAM).unwrap();
+    assert_eq!(insensitive.statements, expected.statements);
+
+    let warned = assemble(SymbolCase::InsensitiveWithWarnings).unwrap();
+    assert!(matches!(
+        &warned.warnings[..],
+        [AssemblyWarning::SymbolCaseMismatch { reference, definition, .. }]
+            if reference.0 == "Loop" && definition.0 == "loop"
+    ));
+
+    // the options are kept when the program is edited
+    let edited = warned
+        .edit(&TextEdit {
+            range: 0..0,
+            text: "\n".to_owned(),
+        })
+        .unwrap();
+    assert_eq!(edited.options(), warned.options());
+    assert_eq!(edited.warnings.len(), 1);
+}
+
+#[test]
+fn test_parse_warnings() {
+    let program = Program::assemble("\tSTMFD SP!, {R0-R2, SP}\n\tLDMFD SP!, {R2-R0}\n").unwrap();
+
+    let lines: Vec<_> = program
+        .warnings
+        .iter()
+        .map(|warning| match warning {
+            AssemblyWarning::ParseWarning(warning) => warning.span.line,
+            warning => panic!("unexpected warning {warning:?}"),
+        })
+        .collect();
+    assert_eq!(lines, [0, 1]);
+}
+
+#[test]
+fn test_branch_with_link_and_exchange() {
+    // the Thumb code starts at a halfword, so the H bit is set
+    let program = Program::assemble("\tBLX\tthumb\n\tDEFB\t0, 0\nthumb\tDEFB\t0, 0\n").unwrap();
+    assert_eq!(program.statements[0].bytes[..], 0xFB000001u32.to_be_bytes());
+
+    assert!(matches!(
+        Program::assemble("\tBLX\tthumb\n\tDEFB\t0\nthumb\tDEFB\t0\n"),
+        Err(AssemblyError::ResolveError(
+            ResolveError::UnalignedBranchTarget(5)
+        ))
+    ));
+}
+
+#[test]
+fn test_preload() {
+    let program = Program::assemble("\tPLD\t[R1, #4]\n").unwrap();
+    assert_eq!(program.statements[0].bytes[..], 0xF5D1F004u32.to_be_bytes());
+
+    let error = Program::assemble("\tPLD\t[R1, #4]!\n").unwrap_err();
+    assert!(matches!(
+        error,
+        AssemblyError::PreProcessError(PreProcessError::ParseError(error))
+            if error.message() == "PLD can't use pre- or post-indexed addressing"
+    ));
+}
+
+#[test]
+fn test_source_map() {
+    let program =
+        Program::assemble("\tADRL\tR0, data\ndata\tDEFB\t1, 2, 3\n\tDEFS\t0\n\tSVC\t2").unwrap();
+
+    // the second instruction of the ADRL
+    assert_eq!(program.source_map.line(4), Some(0));
+    // the last byte of the data
+    assert_eq!(program.source_map.line(10), Some(1));
+    assert_eq!(program.source_map.line(11), Some(3));
+    assert_eq!(program.source_map.line(15), None);
+
+    assert_eq!(program.source_map.addresses(1), Some(8..11));
+    assert_eq!(program.source_map.addresses(2), None);
+
+    assert_eq!(
+        program.source_map.iter().collect::<Vec<_>>(),
+        [(0..8, 0), (8..11, 1), (11..15, 3)]
+    );
+}
+
+#[test]
+fn test_regions() {
+    let program = Program::assemble(PROGRAM).unwrap();
+
+    assert_eq!(program.region(0), Some(Region::Code));
+    assert_eq!(program.region(4), Some(Region::Data));
+    assert_eq!(program.region(10), Some(Region::Data));
+    // the padding from the ALIGN
+    assert_eq!(program.region(11), Some(Region::Padding));
+    assert_eq!(program.region(12), Some(Region::Code));
+}
+
+#[test]
+fn test_padding() {
+    let assemble = |source, padding| {
+        Program::assemble_with(
+            source,
+            AssemblyOptions {
+                padding,
+                ..AssemblyOptions::default()
+            },
+        )
+        .unwrap()
+    };
+
+    let program = assemble("\tDEFB\t1\n\tALIGN\nnext\tSVC\t2", Padding::Ones);
+    assert_eq!(program.statements[1].address, 1);
+    assert_eq!(program.statements[1].bytes[..], [0xFF, 0xFF, 0xFF]);
+    // the label before the code is after the padding
+    assert_eq!(
+        program.symbol_table.get(&Symbol("next".to_owned())),
+        Some(&4)
+    );
+
+    // a NOP is split across the bytes of the word
+    let program = assemble("\tDEFB\t1, 2\n\tALIGN", Padding::Nop);
+    assert_eq!(program.statements[1].bytes[..], [0x00, 0x00]);
+    let program = assemble("\tDEFB\t1\n\tALIGN", Padding::Nop);
+    assert_eq!(program.statements[1].bytes[..], [0xA0, 0x00, 0x00]);
+
+    // an aligned address isn't padded
+    let program = assemble("\tDEFW\t1\n\tALIGN\n\tSVC\t2", Padding::Ones);
+    assert_eq!(program.regions(), [0..8]);
+    assert_eq!(program.statements[2].address, 4);
+}
+
+#[test]
+fn test_layout() {
+    let source = "\tSECTION\tcode\nstart\tMOV\tR0, #1\n\tSECTION\tdata\ncount\tDEFW\t0\n\tSECTION\tcode\n\tSVC\t2\n";
+
+    let assemble = |source: &str, layout: &Layout| {
+        Program::assemble_with(
+            source,
+            AssemblyOptions {
+                layout: layout.clone(),
+                ..AssemblyOptions::default()
+            },
+        )
+    };
+
+    let layout = Layout::new()
+        .region("ROM", 0, 0x100)
+        .region("RAM", 0x8000, 4)
+        .place("code", "ROM")
+        .place("data", "RAM");
+
+    // returning to a section carries on where it left off
+    let program = assemble(source, &layout).unwrap();
+    assert_eq!(
+        program
+            .statements
+            .iter()
+            .map(|statement| statement.address)
+            .collect::<Vec<_>>(),
+        [0, 0x8000, 4]
+    );
+    assert_eq!(
+        program.symbol_table.get(&Symbol("count".to_owned())),
+        Some(&0x8000)
+    );
+
+    // the second word doesn't fit in RAM
+    assert!(matches!(
+        assemble(&format!("{source}\tSECTION\tdata\n\tDEFW\t1\n"), &layout),
+        Err(AssemblyError::PreProcessError(PreProcessError::OutsideRegion { region, .. })) if region == "RAM"
+    ));
+
+    // sections have to be placed by the layout
+    assert!(matches!(
+        assemble(source, &Layout::new()),
+        Err(AssemblyError::PreProcessError(PreProcessError::UnplacedSection { section, .. })) if section == "code"
+    ));
+}
+
+#[test]
+fn test_relocations() {
+    let mut imports = SymbolTable::new();
+    imports.insert(Symbol("print".to_owned()), 0x40);
+
+    let program = Program::assemble_with_imports(
+        "reset\tEQU\t0\nloop\tBL\tprint\n\tBLEQ\tprint\n\tB\tloop\n\tB\treset\n",
+        AssemblyOptions::default(),
+        imports,
+    )
+    .unwrap();
+
+    let relocation = |address, kind, symbol: &str| Relocation {
+        address,
+        kind,
+        symbol: Symbol(symbol.to_owned()),
+    };
+
+    // the branch to the EQU constant doesn't depend on where the program is placed
+    assert_eq!(
+        program.relocations,
+        [
+            relocation(0, RelocationKind::Call, "print"),
+            relocation(4, RelocationKind::Jump24, "print"),
+            relocation(8, RelocationKind::Jump24, "loop"),
+        ]
+    );
+    assert_eq!(RelocationKind::Call.elf_type(), 28);
+
+    // the literal of a label is its address, but the literal of a constant isn't
+    let program = Program::assemble(
+        "big\tEQU\t0x12345678\n\tLDR\tR0, =data\n\tLDR\tR1, =big\n\tSVC\t2\ndata\tDEFW\t1\n",
+    )
+    .unwrap();
+
+    assert_eq!(program.literal_pools[0].address, 16);
+    assert_eq!(
+        program.relocations,
+        [relocation(16, RelocationKind::Abs32, "data")]
+    );
+}
+
+#[test]
+fn test_runtime() {
+    let options = AssemblyOptions {
+        runtime: Runtime {
+            prelude: "\tMOV\tR13, #0x100\n\tBL\tmain\n\tSVC\t2".to_owned(),
+            postlude: "exit\tSVC\t2".to_owned(),
+        },
+        ..AssemblyOptions::default()
+    };
+
+    let program =
+        Program::assemble_with("main\tMOV\tR0, #1\n\tMOV\tPC, LR", options.clone()).unwrap();
+
+    // the prelude is placed before the source, and can refer to its labels
+    assert_eq!(
+        program.symbol_table.get(&Symbol("main".to_owned())),
+        Some(&12)
+    );
+    assert_eq!(
+        program.symbol_table.get(&Symbol("exit".to_owned())),
+        Some(&20)
+    );
+
+    // but the source keeps its line numbers
+    assert_eq!(program.source_map.line(12), Some(0));
+    assert_eq!(program.source_map.line(16), Some(1));
+    assert_eq!(program.runtime_line(1), None);
+
+    assert_eq!(program.source_map.line(0), Some(2));
+    assert_eq!(program.runtime_line(2), Some(RuntimeLine::Prelude(0)));
+    assert_eq!(program.source_map.line(20), Some(5));
+    assert_eq!(program.runtime_line(5), Some(RuntimeLine::Postlude(0)));
+
+    // the runtime is kept when the program is edited
+    let edited = program
+        .edit(&TextEdit {
+            range: 0..0,
+            text: "\tMOV\tR1, #2\n".to_owned(),
+        })
+        .unwrap();
+    let expected =
+        Program::assemble_with("\tMOV\tR1, #2\nmain\tMOV\tR0, #1\n\tMOV\tPC, LR", options).unwrap();
+    assert_eq!(edited.statements, expected.statements);
+    assert_eq!(edited.runtime_line(3), Some(RuntimeLine::Prelude(0)));
+}
+
+#[test]
+fn test_literal_pools() {
+    let program = Program::assemble(
+        "\tLDR\tR0, =value\n\tLDR\tR1, =4\n\tSVC\t2\n\tLTORG\nvalue\tEQU\t0x12345678\n\tSVC\t2",
+    )
+    .unwrap();
+
+    // the value isn't known yet, so it is loaded from the pool, but a small constant is a MOV
+    assert_eq!(program.statements[0].bytes[..], [0xE5, 0x9F, 0x00, 0x0C]);
+    assert_eq!(
+        program.statements[1].bytes,
+        Program::assemble("\tMOV\tR1, #4").unwrap().statements[0].bytes
+    );
+    assert_eq!(
+        program.literal_pools,
+        [LiteralPool {
+            address: 12,
+            literals: 1,
+            placement: PoolPlacement::Directive { source_line: 3 },
+        }]
+    );
+    assert_eq!(program.statements[3].bytes[..], [0x12, 0x34, 0x56, 0x78]);
+    assert_eq!(program.region(12), Some(Region::Data));
+    assert_eq!(program.statements[4].address, 16);
+
+    // without an LTORG, the pool goes at the end, mapped to the last LDR=
+    let program = Program::assemble("\tLDR\tR0, =0x12345678\n\tSVC\t2").unwrap();
+    assert_eq!(program.literal_pools[0].address, 8);
+    assert_eq!(program.literal_pools[0].placement, PoolPlacement::End);
+    assert_eq!(program.source_map.line(8), Some(0));
+    assert_eq!(program.source_map.addresses(0), Some(0..4));
+
+    // the block would put the literal out of range, so the pool is placed before it, with a branch over it
+    let program =
+        Program::assemble("\tLDR\tR0, =0x12345678\nbuffer\tDEFS\t4096\n\tSVC\t2").unwrap();
+    assert!(matches!(
+        program.literal_pools.as_slice(),
+        [LiteralPool {
+            address: 8,
+            literals: 1,
+            placement: PoolPlacement::OutOfRange {
+                source_line: 1,
+                instruction_line: 0,
+                instruction,
+            },
+        }] if instruction.byte_range == (1..20)
+    ));
+    assert_eq!(program.statements[1].bytes[..], [0xEA, 0x00, 0x00, 0x02]);
+    assert_eq!(program.statements[0].bytes[..], [0xE5, 0x9F, 0x00, 0x08]);
+    assert_eq!(
+        program.symbol_table.get(&Symbol("buffer".to_owned())),
+        Some(&12)
+    );
+    assert!(matches!(
+        program.warnings.as_slice(),
+        [AssemblyWarning::LiteralPoolPlaced { span, address: 8 }] if span.byte_range == (1..20)
+    ));
+}
+
+#[test]
+fn test_unencodable_values() {
+    let Err(AssemblyError::Unencodable(unencodable)) = Program::assemble(
+        "\tMOV\tR0, #0x101\n\tMOV\tR1, #2\n\tSVC\t0x1000000\n\tADD\tR0, R0, #255",
+    ) else {
+        panic!("expected the unencodable values to be reported");
+    };
+
+    // every statement with an unencodable value is reported, along with the nearest values that can be encoded
+    assert_eq!(
+        unencodable
+            .iter()
+            .map(|statement| (
+                statement.source_line,
+                statement.encoding,
+                statement.alternatives.clone()
+            ))
+            .collect::<Vec<_>>(),
+        [
+            (0, ImmediateEncoding::Rotated, vec![0x100, 0x104]),
+            (2, ImmediateEncoding::Unsigned { bits: 24 }, vec![0xFFFFFF]),
+        ]
+    );
+    assert_eq!(unencodable[0].value, 0x101);
+    assert_eq!(unencodable[1].span.line, 2);
+
+    assert_eq!(
+        ImmediateEncoding::Signed { bits: 24 }.nearest(-(1 << 24)),
+        [-(1 << 23)]
+    );
+}
+
+#[test]
+fn test_move_constant() {
+    // runs the assembled instructions of a MOV32
+    #[cfg(feature = "emulator")]
+    {
+        let synthesise = |value: u32| {
+            let instructions =
+                assemble_instruction(&format!("MOV32 R0, #{value}"), &SymbolTable::new(), 0)
+                    .unwrap();
+
+            let mut emulator = Emulator::new();
+            emulator
+                .assemble(&format!("\tMOV32\tR0, #{value}\n"))
+                .unwrap();
+            emulator.set_register(0, 0xDEADBEEF);
+
+            for _ in &instructions {
+                emulator.step().unwrap();
+            }
+
+            (emulator.register(0), instructions.len())
+        };
+
+        assert_eq!(synthesise(0), (0, 1));
+        assert_eq!(synthesise(0xFFFFFF00), (0xFFFFFF00, 1));
+        assert_eq!(synthesise(0xF000000F), (0xF000000F, 1));
+        assert_eq!(synthesise(0x00FF00FF), (0x00FF00FF, 2));
+        assert_eq!(synthesise(0xFFFF00FE), (0xFFFF00FE, 2));
+        assert_eq!(synthesise(0x12345678), (0x12345678, 4));
+    }
+
+    // the size of the sequence is known before the labels after it
+    let program = Program::assemble("\tMOV32\tR0, #0x00FF00FF\nnext\tSVC\t2").unwrap();
+    assert_eq!(
+        program.symbol_table.get(&Symbol("next".to_owned())),
+        Some(&8)
+    );
+    assert_eq!(program.synthesised_constants.get(&4), Some(&0x00FF00FF));
+
+    assert!(matches!(
+        Program::assemble("\tMOV32\tR0, #later\nlater\tSVC\t2"),
+        Err(AssemblyError::PreProcessError(
+            PreProcessError::ConstantExpressionError { .. }
+        ))
+    ));
+
+    // LDR= can build known values the same way, rather than using a literal pool
+    let program = Program::assemble_with(
+        "\tLDR\tR0, =0x00FF00FF\n\tSVC\t2",
+        AssemblyOptions {
+            synthesise_constants: true,
+            ..AssemblyOptions::default()
+        },
+    )
+    .unwrap();
+    assert!(program.literal_pools.is_empty());
+    assert_eq!(program.regions(), [0..12]);
+}
+
+#[test]
+fn test_queries() {
+    let program = Program::assemble(
+        "\tSTMFD\tSP!, {R4, LR}
+\tBL\tcount
+\tSVC\t2
+count\tMOV\tR0, #3
+loop\tSUBS\tR0, R0, #1
+\tBNE\tloop
+\tSTR\tR0, [SP, #-4]!
+\tSVC\t1
+\tMOV\tPC, LR",
+    )
+    .unwrap();
+
+    let lines = |sites: Vec<InstructionSite>| -> Vec<usize> {
+        sites.iter().map(|site| site.source_line).collect()
+    };
+
+    assert_eq!(lines(program.instructions_writing(13)), [0, 6]);
+    assert_eq!(lines(program.instructions_reading(14)), [0, 8]);
+    assert_eq!(lines(program.branches_to("loop")), [5]);
+    assert_eq!(lines(program.branches_to("count")), [1]);
+    assert_eq!(lines(program.branches_to("missing")), []);
+    assert_eq!(lines(program.svc_sites()), [2, 7]);
+
+    assert_eq!(program.instructions().count(), 9);
+    assert_eq!(program.instructions().nth(3).unwrap().address, 12);
+}
+
+#[test]
+#[cfg(feature = "formats")]
+fn test_image_byte_order() {
+    let program = Program::assemble(
+        "\tSVC\t3\nvalue\tDEFW\t287454020 ; 0x11223344\nbytes\tDEFB\t\"ab\"\n\tORIGIN\t0x10010\n\tSVC\t2\n",
+    )
+    .unwrap();
+
+    let big = program.image(ByteOrder::BigEndian);
+    assert_eq!(big.base, 0);
+    assert_eq!(
+        big.bytes[..10],
+        [0xEF, 0x00, 0x00, 0x03, 0x11, 0x22, 0x33, 0x44, b'a', b'b']
+    );
+    assert_eq!(big.bytes.len(), 0x10014);
+
+    // the words are reversed, but not the string
+    let little = program.image(ByteOrder::LittleEndian);
+    assert_eq!(
+        little.bytes[..10],
+        [0x03, 0x00, 0x00, 0xEF, 0x44, 0x33, 0x22, 0x11, b'a', b'b']
+    );
+    assert_eq!(little.bytes[0x10010..], [0x02, 0x00, 0x00, 0xEF]);
+
+    assert_eq!(
+        program.intel_hex(ByteOrder::LittleEndian),
+        "\
+:0A000000030000EF44332211616297
+:020000040001F9
+:04001000020000EFFB
+:0400000500000000F7
+:00000001FF
+"
+    );
+}
+
+#[test]
+fn test_diagnostics() {
+    let phases = |source: &str| {
+        Program::assemble(source)
+            .unwrap_err()
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| (diagnostic.phase, diagnostic.span.clone().unwrap().line))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(phases("\tADD\tR0, R0, $1"), [(Phase::Lex, 0)]);
+    assert_eq!(phases("\tSVC\t0\n\tFOO\tR0"), [(Phase::Parse, 1)]);
+    assert_eq!(
+        phases("\tENTRY\n\tSVC\t0\n\tENTRY\n\tSVC\t0"),
+        [(Phase::PreProcess, 2)]
+    );
+    assert_eq!(
+        phases("\tMOV\tR0, #0x101\n\tMOV\tR1, #2\n\tSVC\t0x1000000"),
+        [(Phase::Encode, 0), (Phase::Encode, 2)]
+    );
+
+    let diagnostics = Program::assemble("\tMOV\tR0, #0x101")
+        .unwrap_err()
+        .diagnostics();
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+    assert_eq!(
+        diagnostics[0].to_string(),
+        "1:2: error: 257 can't be encoded as an 8-bit value rotated right by an even number of bits, the nearest values that fit are 256 below and 260 above\nhelp: load the value from a literal pool with `LDR Rd, =257` instead"
+    );
+
+    // only rotated immediates can be replaced with a literal
+    let diagnostics = Program::assemble("\tSVC\t0x1000000")
+        .unwrap_err()
+        .diagnostics();
+    assert_eq!(diagnostics[0].help, None);
+    assert_eq!(
+        diagnostics[0].message,
+        "16777216 can't be encoded as a 24-bit unsigned value, the nearest values that fit are 16777215 below"
+    );
+
+    // symbols that aren't defined aren't in a single place
+    let diagnostics = Program::assemble("\tB\tnowhere").unwrap_err().diagnostics();
+    assert_eq!(diagnostics[0].phase, Phase::Resolve);
+    assert_eq!(diagnostics[0].span, None);
+
+    let program = Program::assemble("\tSTMFD\tSP!, {R2-R0}").unwrap();
+    assert_eq!(
+        program
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| (diagnostic.severity, diagnostic.phase))
+            .collect::<Vec<_>>(),
+        [(Severity::Warning, Phase::Parse)]
+    );
+}
+
+#[test]
+fn test_shift_validation() {
+    let error = |source: &str| Program::assemble(source).unwrap_err().diagnostics()[0].to_string();
+
+    assert_eq!(
+        error("\tADD\tR0, R1, R2, LSL #32"),
+        "1:23: error: Shift amount is too large, LSL and ROR can shift by at most 31"
+    );
+    assert_eq!(
+        error("\tLDR\tR0, [R1, R2, ASR #33]"),
+        "1:24: error: Shift amount is too large, LSR and ASR can shift by at most 32"
+    );
+    assert_eq!(
+        error("\tLDR\tR0, [R1, R2, RRX]"),
+        "1:19: error: RRX can't be used in a scaled register offset"
+    );
+
+    // amounts given by symbols are checked once they are resolved
+    assert!(matches!(
+        Program::assemble("amount\tEQU\t40\n\tADD\tR0, R1, R2, LSR #amount"),
+        Err(AssemblyError::ResolveError(ResolveError::ShiftOutOfRange {
+            kind: Shift::LogicalShiftRight,
+            amount: 40
+        }))
+    ));
+
+    // LSR #32 is encoded as LSR #0, so a shift by 0 is encoded as LSL #0
+    let shifts = |source: &str| {
+        let program = Program::assemble(source).unwrap();
+
+        let InstructionKind::DataProcessing {
+            kind:
+                DataProcessingKind::Calculation {
+                    shifter: ShifterOperandCode::ImmediateShift(shift),
+                    ..
+                },
+            ..
+        } = &program.statements[0].instructions[0]
+        else {
+            panic!("expected a shifted register");
+        };
+
+        (shift.kind, shift.amount.get())
+    };
+
+    assert_eq!(
+        shifts("\tADD\tR0, R1, R2, LSR #32"),
+        (Shift::LogicalShiftRight, 0)
+    );
+    assert_eq!(
+        shifts("\tADD\tR0, R1, R2, ASR #0"),
+        (Shift::LogicalShiftLeft, 0)
+    );
+
+    for source in ["\tADD\tR0, R1, PC, LSL R2", "\tADD\tR0, R1, R2, LSL PC"] {
+        let program = Program::assemble(source).unwrap();
+
+        assert_eq!(
+            program.diagnostics()[0].message,
+            "Using the PC in a register-specified shift is unpredictable"
+        );
+    }
+}
+
+#[test]
+fn test_pc_lints() {
+    let lints = |source: &str| {
+        Program::assemble(source)
+            .unwrap()
+            .warnings
+            .into_iter()
+            .filter_map(|warning| match warning {
+                AssemblyWarning::PcLint { span, lint } => Some((lint, span.column)),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        lints("\tADDS\tPC, R0, R1\n\tMOVS\tPC, R0"),
+        [
+            (PcLint::FlagSettingPcWrite, 6),
+            (PcLint::FlagSettingPcWrite, 6)
+        ]
+    );
+    assert_eq!(
+        lints("\tLDMFD\tPC!, {R0, R1}"),
+        [(PcLint::PcBaseWriteBack, 7)]
+    );
+    assert_eq!(
+        lints("\tADD\tR0, R1, R2, LSL PC\n\tADD\tR0, R1, PC, LSL R2"),
+        [(PcLint::PcShiftRegister, 17), (PcLint::PcShiftRegister, 17)]
+    );
+    assert_eq!(lints("\tSTR\tPC, [R0]"), [(PcLint::StorePc, 5)]);
+
+    // exception returns, branches and shifts by an immediate are fine
+    assert_eq!(
+        lints("\tMOVS\tPC, LR\n\tSUBS\tPC, LR, #4\n\tMOV\tPC, R0\n\tADD\tR0, R1, PC, LSL #2\n\tLDR\tPC, [R0]"),
+        []
+    );
+
+    let diagnostic = &Program::assemble("\tSTR\tPC, [R0]").unwrap().diagnostics()[0];
+    assert_eq!(diagnostic.severity, Severity::Warning);
+    assert_eq!(
+        diagnostic.help,
+        Some(PcLint::StorePc.explanation().to_owned())
+    );
+}
+
+#[test]
+fn test_stack_usage() {
+    let program = Program::assemble(
+        "main\tSTMFD\tSP!, {R4, LR}
+\tBL\touter
+\tLDMFD\tSP!, {R4, PC}
+outer\tSTMFD\tSP!, {R0-R2, LR}
+\tBL\tinner
+\tLDMFD\tSP!, {R0-R2, PC}
+inner\tSUB\tSP, SP, #8
+\tADD\tSP, SP, #4
+\tMOV\tPC, LR
+",
+    )
+    .unwrap();
+
+    let usage = program.stack_usage();
+
+    assert_eq!(
+        usage
+            .iter()
+            .map(|routine| (
+                routine.label.as_deref(),
+                routine.frame,
+                routine.total,
+                routine.call_depth
+            ))
+            .collect::<Vec<_>>(),
+        [
+            (Some("main"), 8, Some(32), Some(2)),
+            (Some("outer"), 16, Some(24), Some(1)),
+            (Some("inner"), 8, Some(8), Some(0)),
+        ]
+    );
+    assert!(usage[0].is_balanced() && usage[1].is_balanced());
+    assert_eq!(
+        usage[2].unbalanced,
+        [UnbalancedReturn {
+            address: 32,
+            source_line: 8,
+            offset: 4
+        }]
+    );
+
+    // a recursive routine has no bound on its stack
+    let usage = Program::assemble("count\tSTMFD\tSP!, {LR}\n\tBL\tcount\n\tLDMFD\tSP!, {PC}")
+        .unwrap()
+        .stack_usage();
+    assert_eq!(usage[0].frame, 4);
+    assert_eq!(usage[0].total, None);
+    assert_eq!(usage[0].calls, [0].into());
+}
+
+#[test]
+fn test_cross_reference() {
+    let program = Program::assemble(
+        "SIZE\tEQU\t4
+main\tADR\tR0, table
+loop\tLDR\tR1, table
+\tSVC\tSIZE
+\tBNE\tloop
+\tBL\tloop
+table\tDEFS\tSIZE
+",
+    )
+    .unwrap();
+
+    let xref = program.cross_reference();
+
+    assert_eq!(
+        xref.symbols
+            .iter()
+            .map(|symbol| (
+                symbol.symbol.0.as_str(),
+                symbol.definition,
+                symbol
+                    .references
+                    .iter()
+                    .map(|reference| (reference.source_line, reference.kind))
+                    .collect::<Vec<_>>()
+            ))
+            .collect::<Vec<_>>(),
+        [
+            (
+                "SIZE",
+                Some(0),
+                vec![(3, ReferenceKind::Operand), (6, ReferenceKind::Directive)]
+            ),
+            (
+                "loop",
+                Some(2),
+                vec![(4, ReferenceKind::Branch), (5, ReferenceKind::Branch)]
+            ),
+            ("main", Some(1), vec![]),
+            (
+                "table",
+                Some(6),
+                vec![(1, ReferenceKind::Address), (2, ReferenceKind::Address)]
+            ),
+        ]
+    );
+
+    assert_eq!(
+        xref.to_string().lines().take(3).collect::<Vec<_>>(),
+        [
+            "SYMBOL  VALUE       DEFINED  REFERENCED",
+            "SIZE    0x00000004  1        4 (operand), 7 (directive)",
+            "loop    0x00000004  3        5 (branch), 6 (branch)",
+        ]
+    );
+}
+
+#[test]
+fn test_unused_code() {
+    let program = Program::assemble(
+        "main\tLDR\tR0, used
+\tBL\tdone
+\tB\tmain
+dead\tMOV\tR1, #2
+\tMOV\tR2, #3
+done\tMOV\tPC, LR
+\tMOV\tR3, #4
+used\tDEFW\t3
+table\tDEFW\t1
+\tDEFW\t2
+",
+    )
+    .unwrap();
+
+    let unused = program.unused_code();
+
+    assert_eq!(
+        unused
+            .unreachable
+            .iter()
+            .map(|statement| statement.source_line)
+            .collect::<Vec<_>>(),
+        [3, 4, 6]
+    );
+    assert_eq!(
+        unused
+            .unused_data
+            .iter()
+            .map(|statement| statement.addresses.clone())
+            .collect::<Vec<_>>(),
+        [32..36, 36..40]
+    );
+    assert_eq!(
+        unused.unreferenced_labels,
+        [
+            UnreferencedLabel {
+                label: "dead".to_owned(),
+                source_line: 3
+            },
+            UnreferencedLabel {
+                label: "table".to_owned(),
+                source_line: 8
+            },
+        ]
+    );
+
+    // the unused data at the end is left out, and the unreachable code is zeroed
+    #[cfg(feature = "formats")]
+    {
+        let image = program.stripped_image(ByteOrder::BigEndian);
+        let full = program.image(ByteOrder::BigEndian);
+
+        assert_eq!(image.base, 0);
+        assert_eq!(image.bytes.len(), 32);
+        assert_eq!(image.bytes[..12], full.bytes[..12]);
+        assert_eq!(image.bytes[12..20], [0; 8]);
+        assert_eq!(image.bytes[24..28], [0; 4]);
+        assert_eq!(image.bytes[28..], full.bytes[28..32]);
+    }
+
+    assert!(Program::assemble("main\tB\tmain\n")
+        .unwrap()
+        .unused_code()
+        .is_empty());
+}
+
+#[test]
+fn test_rename_symbol() {
+    let library = "SIZE\tEQU\t4\nprint\tSVC\t1\n\tMOV\tPC, LR\n";
+    let main = "main\tMOV\tR0, #SIZE\n\tBL\tprint ; print it\n\tBL\tprint\nprintAll\tB\tmain\n";
+
+    let mut index = WorkspaceIndex::new(SymbolCase::Sensitive);
+    index.update("library.s", library);
+    index.update("main.s", "broken\tMOV\tR0,\n");
+    index.update("main.s", main);
+
+    assert_eq!(index.definitions("print")[0].file, "library.s");
+    assert_eq!(index.references("print").len(), 2);
+    assert_eq!(
+        index
+            .symbol_at("main.s", main.find("SIZE").unwrap() + 2)
+            .map(|occurrence| occurrence.name.as_str()),
+        Some("SIZE")
+    );
+    assert!(index.definitions("broken").is_empty());
+
+    let edits = index.rename_symbol("print", "output").unwrap();
+    assert_eq!(
+        edits["library.s"],
+        [TextEdit {
+            range: 11..16,
+            text: "output".to_owned()
+        }]
+    );
+    assert_eq!(
+        edits["main.s"]
+            .iter()
+            .map(|edit| &main[edit.range.clone()])
+            .collect::<Vec<_>>(),
+        ["print", "print"]
+    );
+
+    assert_eq!(
+        index.rename_symbol("print", "main"),
+        Err(RenameError::AlreadyDefined {
+            file: "main.s".to_owned(),
+            span: Span::new(0..4, 0, 0)
+        })
+    );
+    assert_eq!(
+        index.rename_symbol("print", "R1"),
+        Err(RenameError::InvalidName)
+    );
+    assert_eq!(
+        index.rename_symbol("print", "a b"),
+        Err(RenameError::InvalidName)
+    );
+    assert_eq!(
+        index.rename_symbol("missing", "other"),
+        Err(RenameError::UnknownSymbol)
+    );
+
+    // with case-insensitive symbols, every spelling is renamed
+    let mut index = WorkspaceIndex::new(SymbolCase::Insensitive);
+    index.update("main.s", "Loop\tB\tLOOP\n");
+    assert_eq!(
+        index.rename_symbol("loop", "again").unwrap()["main.s"].len(),
+        2
+    );
+}
+
+#[test]
+fn test_program_builder() {
+    let builder = ProgramBuilder::new()
+        .comment("adds up a table")
+        .constant("count", 3)
+        .label("main")
+        .instruction("ADR R0, table")
+        .instruction("LDMIA R0, {R1, R2, R3}")
+        .instruction("SVC 2")
+        .label("table")
+        .words(&[1, 2, 0xFFFF])
+        .label("name")
+        .string("Hi \"you\"\n")
+        .align()
+        .bytes(&[7, 8])
+        .space(2);
+
+    assert_eq!(
+        builder.source(),
+        "; adds up a table
+count\tEQU 3
+main
+\tADR R0, table
+\tLDMIA R0, {R1, R2, R3}
+\tSVC 2
+table
+\tDEFW 1, 2, 65535
+name
+\tDEFB \"Hi \", 34, \"you\", 34, 10, 0
+\tALIGN
+\tDEFB 7, 8
+\tDEFS 2
+"
+    );
+    assert_eq!(builder.to_string(), builder.source());
+
+    let program = builder.build().unwrap();
+    assert_eq!(
+        program.symbol_table.get(&Symbol("main".to_owned())),
+        Some(&0)
+    );
+    assert_eq!(
+        program.symbol_table.get(&Symbol("table".to_owned())),
+        Some(&12)
+    );
+    assert_eq!(
+        program.symbol_table.get(&Symbol("name".to_owned())),
+        Some(&24)
+    );
+    assert_eq!(
+        program.symbol_table.get(&Symbol("count".to_owned())),
+        Some(&3)
+    );
+
+    let table = program.statement_at(12).unwrap();
+    assert_eq!(
+        table.bytes.as_slice(),
+        [0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0xFF, 0xFF]
+    );
+    assert_eq!(
+        program.statement_at(24).unwrap().bytes.as_slice(),
+        b"Hi \"you\"\n\0"
+    );
+
+    // errors point at the line that was written
+    let error = ProgramBuilder::new()
+        .instruction("MOV R0, #1")
+        .instruction("ADD R0, R0")
+        .build()
+        .unwrap_err();
+    assert_eq!(error.diagnostics()[0].span.as_ref().unwrap().line, 1);
+}
+
+#[test]
+fn test_peephole_optimizations() {
+    let source = "\tMOV\tR0, #12
+\tADD\tR0, R0, #4
+\tSUB\tR0, R0, #1
+\tB\tnext
+; the branch skips this comment
+next\tLDR\tR1, [R2, #4]
+\tLDR\tR1, [R2, #4]
+\tSTR\tR3, value
+\tLDR\tR3, value
+\tLDR\tR2, [R2]
+\tLDR\tR2, [R2]
+\tMOV\tR4, #1
+again\tADD\tR4, R4, #1
+\tSVC\t2
+value\tDEFW\t0";
+
+    let optimize = |source| {
+        Program::assemble_with(
+            source,
+            AssemblyOptions {
+                optimize: true,
+                ..AssemblyOptions::default()
+            },
+        )
+        .unwrap()
+    };
+
+    let program = optimize(source);
+
+    assert_eq!(
+        program.optimizations,
+        [
+            Optimization {
+                source_line: 0,
+                kind: OptimizationKind::FoldedMove {
+                    removed: 1,
+                    value: 16
+                },
+            },
+            Optimization {
+                source_line: 0,
+                kind: OptimizationKind::FoldedMove {
+                    removed: 2,
+                    value: 15
+                },
+            },
+            Optimization {
+                source_line: 3,
+                kind: OptimizationKind::BranchToNext,
+            },
+            Optimization {
+                source_line: 6,
+                kind: OptimizationKind::RedundantLoad { previous: 5 },
+            },
+            Optimization {
+                source_line: 8,
+                kind: OptimizationKind::RedundantLoad { previous: 7 },
+            },
+        ]
+    );
+    assert_eq!(
+        program.optimizations[0].to_string(),
+        "line 1: folded the immediate on line 2 into the MOV, which now moves #16"
+    );
+
+    // the loaded register is the base, and the labelled ADD can be branched to, so neither is removed
+    assert_eq!(program.statements.len(), 9);
+    assert_eq!(
+        program.statements[0].bytes,
+        Program::assemble("\tMOV\tR0, #15").unwrap().statements[0].bytes
+    );
+
+    // a sum that doesn't fit in an immediate isn't folded
+    assert!(optimize("\tMOV\tR0, #255\n\tADD\tR0, R0, #2")
+        .optimizations
+        .is_empty());
+
+    // the optimizer is opt-in
+    assert!(Program::assemble(source).unwrap().optimizations.is_empty());
+
+    // editing the ADD changes the folded MOV on the line before it
+    let source = "\tMOV\tR0, #1\n\tADD\tR0, R0, #2\n\tSVC\t2";
+    let start = source.find("#2").unwrap();
+    let edited = optimize(source)
+        .edit(&TextEdit {
+            range: start..start + 2,
+            text: "#8".to_owned(),
+        })
+        .unwrap();
+
+    assert_eq!(edited.statements, optimize(edited.source()).statements);
+    assert_eq!(
+        edited.statements[0].bytes,
+        Program::assemble("\tMOV\tR0, #9").unwrap().statements[0].bytes
+    );
+}
+
+#[test]
+fn test_hazards() {
+    let program = Program::assemble(
+        "\tLDR\tR1, [R0]
+\tADD\tR2, R1, #1
+\tMOV\tR3, #5
+\tLDR\tR4, [R0, #4]
+\tADD\tR4, R4, R3
+\tMOV\tR5, R4
+\tCMP\tR5, #0
+\tBEQ\tskip
+\tADD\tR5, R5, #1
+\tSUB\tR6, R6, #1
+skip\tMOV\tPC, LR",
+    )
+    .unwrap();
+
+    let hazards = program.hazards(&PipelineModel::default());
+
+    assert_eq!(
+        hazards,
+        [
+            Hazard {
+                address: 4,
+                source_line: 1,
+                kind: HazardKind::LoadUse {
+                    register: 1,
+                    load_line: 0
+                },
+                penalty: 1,
+                suggestion: Some(Suggestion::MoveBetween { line: 2 }),
+            },
+            // the instruction after the use reads the loaded register, so it can't fill the stall
+            Hazard {
+                address: 16,
+                source_line: 4,
+                kind: HazardKind::LoadUse {
+                    register: 4,
+                    load_line: 3
+                },
+                penalty: 1,
+                suggestion: None,
+            },
+            Hazard {
+                address: 28,
+                source_line: 7,
+                kind: HazardKind::ShortBranch { skipped: 2 },
+                penalty: 2,
+                suggestion: Some(Suggestion::Conditional {
+                    condition: Condition::NE
+                }),
+            },
+            Hazard {
+                address: 40,
+                source_line: 10,
+                kind: HazardKind::PcWrite,
+                penalty: 2,
+                suggestion: None,
+            },
+        ]
+    );
+
+    assert_eq!(
+        hazards[0].to_string(),
+        "line 2: R1 is used straight after it is loaded on line 1, which stalls for 1 cycle; move line 3 between them"
+    );
+    assert_eq!(
+        hazards[2].to_string(),
+        "line 8: the branch over 2 instructions refills the pipeline when it is taken, which takes 2 cycles; remove it and add NE to the instructions it skips"
+    );
+
+    // the penalties come from the model
+    let slow = program.hazards(&PipelineModel::new().load_use(2).refill(4));
+    assert_eq!(slow[1].penalty, 2);
+    assert_eq!(slow[3].penalty, 4);
+
+    // a skipped instruction that sets the flags can't be made conditional, and a labelled instruction can be branched to
+    let program = Program::assemble(
+        "\tLDR\tR1, [R0]\n\tADD\tR2, R1, #1\nnext\tMOV\tR3, #5\n\tBNE\tskip\n\tADDS\tR5, R5, #1\nskip\tSVC\t2",
+    )
+    .unwrap();
+    let hazards = program.hazards(&PipelineModel::default());
+
+    assert_eq!(hazards.len(), 1);
+    assert_eq!(hazards[0].suggestion, None);
+}
+
+#[test]
+fn test_label_before() {
+    let program =
+        Program::assemble("\tSVC\t2\nstart\tSVC\t2\n\tSVC\t2\nSIZE\tEQU\t8\nvalue\tDEFW\t0")
+            .unwrap();
+
+    assert_eq!(program.label_before(0), None);
+    assert_eq!(program.label_before(4).as_deref(), Some("start"));
+    // EQU constants aren't labels, even when their value is an address
+    assert_eq!(program.label_before(8).as_deref(), Some("start"));
+    assert_eq!(program.label_before(14).as_deref(), Some("value"));
+}
+
+#[test]
+fn test_deduplicate_strings() {
+    let source = "\tADR\tR0, bye
+\tADR\tR1, farewell
+\tSVC\t2
+hello\tDEFB\t\"Hi\", 0
+bye\tDEFB\t\"Bye\", 0
+ciao
+farewell\tDEFB\t\"Bye\", 0
+\tDEFB\t\"Bye\", 0
+again\tDEFB\t\"H\", \"i\", 0";
+
+    let assemble = |deduplicate_strings| {
+        Program::assemble_with(
+            source,
+            AssemblyOptions {
+                deduplicate_strings,
+                ..AssemblyOptions::default()
+            },
+        )
+        .unwrap()
+    };
+
+    let program = assemble(true);
+
+    // the unlabelled string may be reached from the one before it, so it is kept
+    assert_eq!(
+        program.duplicate_strings,
+        [
+            DuplicateString {
+                source_line: 6,
+                original: 4,
+                alias: "bye".to_owned(),
+                saved: 4,
+            },
+            DuplicateString {
+                source_line: 8,
+                original: 3,
+                alias: "hello".to_owned(),
+                saved: 3,
+            },
+        ]
+    );
+    assert_eq!(
+        program.duplicate_strings[0].to_string(),
+        "line 7: the string is the same as the one on line 5, so it now refers to bye, saving 4 bytes"
+    );
+
+    let bye = program.symbol_table.get(&Symbol("bye".to_owned()));
+    assert_eq!(
+        program.symbol_table.get(&Symbol("farewell".to_owned())),
+        bye
+    );
+    assert_eq!(program.symbol_table.get(&Symbol("ciao".to_owned())), bye);
+    assert_eq!(
+        program.symbol_table.get(&Symbol("again".to_owned())),
+        program.symbol_table.get(&Symbol("hello".to_owned()))
+    );
+
+    let size = |program: &Program| program.regions().last().unwrap().end;
+    assert_eq!(size(&assemble(false)) - size(&program), 7);
+    assert!(assemble(false).duplicate_strings.is_empty());
+}
+
+#[test]
+fn test_checksums() {
+    let program = Program::assemble(
+        "start\tDEFB\t\"123456789\"
+end\tCRC32\tstart, end
+\tCHECKSUM\tstart, end
+whole\tCHECKSUM\tstart, whole
+itself\tCHECKSUM\titself, after
+\tDEFB\t1
+after\tDEFB\t2",
+    )
+    .unwrap();
+
+    let word = |address: usize| {
+        let bytes = &program.statement_at(address).unwrap().bytes;
+        u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    };
+
+    assert_eq!(word(9), 0xCBF43926);
+    assert_eq!(word(13), 477);
+    // the CRC-32 and the sum are included in the checksum that covers them
+    assert_eq!(word(17), 477 + (0xCB + 0xF4 + 0x39 + 0x26) + (0x01 + 0xDD));
+    // and a checksum that covers itself counts as zero
+    assert_eq!(word(21), 1);
+
+    assert_eq!(program.checksum(ChecksumKind::Crc32, 0..9), 0xCBF43926);
+    assert_eq!(program.checksum(ChecksumKind::Sum, 21..27), 4);
+
+    // a little-endian image has the checksums of its own bytes, in little-endian order
+    #[cfg(feature = "formats")]
+    {
+        let program =
+            Program::assemble("start\tDEFW\t258\nend\tCHECKSUM\tstart, end\n\tCRC32\tstart, end")
+                .unwrap();
+        let big = program.image(ByteOrder::BigEndian);
+        let little = program.image(ByteOrder::LittleEndian);
+
+        assert_eq!(big.bytes[..8], [0, 0, 1, 2, 0, 0, 0, 3]);
+        assert_eq!(
+            big.bytes[8..],
+            ChecksumKind::Crc32.compute([0, 0, 1, 2]).to_be_bytes()
+        );
+        assert_eq!(little.bytes[..8], [2, 1, 0, 0, 3, 0, 0, 0]);
+        assert_eq!(
+            little.bytes[8..],
+            ChecksumKind::Crc32.compute([2, 1, 0, 0]).to_le_bytes()
+        );
+    }
+
+    assert!(matches!(
+        Program::assemble("start\tCHECKSUM\tend, start\nend\tDEFB\t1"),
+        Err(AssemblyError::ResolveError(ResolveError::ChecksumRange {
+            start: 4,
+            end: 0
+        }))
+    ));
+}
+
+#[test]
+fn test_word_labels() {
+    let program = Program::assemble(
+        "SIZE\tEQU\t7
+table\tDEFW\tfirst, second - ., SIZE, 3
+first\tMOV\tR0, #1
+second\tMOV\tR0, #2
+\tDEFW\ttable - .",
+    )
+    .unwrap();
+
+    let words = |address: usize| -> Vec<u32> {
+        program
+            .statement_at(address)
+            .unwrap()
+            .bytes
+            .chunks(4)
+            .map(|word| u32::from_be_bytes([word[0], word[1], word[2], word[3]]))
+            .collect()
+    };
+
+    assert_eq!(words(0), [16, 20 - 4, 7, 3]);
+    assert_eq!(words(24), [(-24i32) as u32]);
+
+    let relocation = |address, kind, symbol: &str| Relocation {
+        address,
+        kind,
+        symbol: Symbol(symbol.to_owned()),
+    };
+
+    // the EQU constant doesn't depend on where the program is placed
+    assert_eq!(
+        program.relocations,
+        [
+            relocation(0, RelocationKind::Abs32, "first"),
+            relocation(4, RelocationKind::Rel32, "second"),
+            relocation(24, RelocationKind::Rel32, "table"),
+        ]
+    );
+    assert_eq!(RelocationKind::Abs32.elf_type(), 2);
+
+    // other numbers and expressions are resolved like operands, and a label plus an offset isn't relocated
+    let program = Program::assemble(
+        "\tDEFW\t0x11223344, 2_101, first + 4, second - first\nfirst\tMOV\tR0, #1\nsecond\tMOV\tR0, #2",
+    )
+    .unwrap();
+
+    let words: Vec<u32> = program
+        .statement_at(0)
+        .unwrap()
+        .bytes
+        .chunks(4)
+        .map(|word| u32::from_be_bytes([word[0], word[1], word[2], word[3]]))
+        .collect();
+
+    assert_eq!(words, [0x11223344, 5, 20, 4]);
+    assert!(program.relocations.is_empty());
+
+    for source in [
+        "\tDEFW\t1 2",
+        "\tDEFW\t1, #2",
+        "\tDEFW\t1,",
+        "\tDEFW\tfirst -\nfirst\tMOV\tR0, #1",
+    ] {
+        assert!(matches!(
+            Program::assemble(source),
+            Err(AssemblyError::PreProcessError(PreProcessError::ParseError(
+                _
+            )))
+        ));
+    }
+}
+
+#[test]
+fn test_jump_tables() {
+    let program = Program::assemble(
+        "\tCMP\tR0, #2
+\tLDRLS\tPC, [PC, R0, LSL #2]
+\tB\tdefault
+\tDEFW\tzero, one, two
+zero\tMOV\tR1, #0
+one\tMOV\tR1, #1
+two\tMOV\tR1, #2
+default\tSVC\t2",
+    )
+    .unwrap();
+
+    assert_eq!(
+        program.jump_tables(),
+        [JumpTable {
+            address: 4,
+            source_line: 1,
+            index: 0,
+            table: 12,
+            targets: vec![24, 28, 32],
+            bound: Some(3),
+        }]
+    );
+    assert!(program.warnings.is_empty());
+    assert!(program.unused_code().unreachable.is_empty());
+
+    let program = Program::assemble(
+        "\tCMP\tR0, #2
+\tLDRLS\tPC, [PC, R0, LSL #2]
+\tB\tdefault
+\tDEFW\tdefault, value
+default\tMOV\tR1, #0
+value\tDEFW\t5",
+    )
+    .unwrap();
+
+    let problems: Vec<_> = program
+        .warnings
+        .iter()
+        .filter_map(|warning| match warning {
+            AssemblyWarning::JumpTable { span, problem } => Some((span.line, problem.clone())),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(
+        problems,
+        [
+            (
+                3,
+                JumpTableProblem::EntryNotCode {
+                    entry: 1,
+                    target: 24
+                }
+            ),
+            (
+                1,
+                JumpTableProblem::TooShort {
+                    entries: 2,
+                    bound: 3
+                }
+            ),
+        ]
+    );
+}
@@ -0,0 +1,280 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::Range,
+};
+
+#[cfg(feature = "formats")]
+use crate::ir::ByteOrder;
+use crate::{
+    ir::{BranchKind, Condition, InstructionKind},
+    parser::{DirectiveKind, StatementKind},
+};
+
+#[cfg(feature = "formats")]
+use super::Image;
+use super::{Program, ReferenceKind, Region};
+
+/// the code and data of a program that nothing uses, see [`Program::unused_code`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnusedCode {
+    /// the statements whose instructions can't be reached from the entry point, in order of address
+    pub unreachable: Vec<UnusedStatement>,
+    /// the data statements after a label that nothing refers to, up to the next label, in order of address
+    pub unused_data: Vec<UnusedStatement>,
+    /// the labels that nothing refers to, other than the ones at the entry point, in source order
+    pub unreferenced_labels: Vec<UnreferencedLabel>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnusedStatement {
+    /// the addresses of the statement's bytes
+    pub addresses: Range<usize>,
+    pub source_line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnreferencedLabel {
+    pub label: String,
+    /// the source line the label is defined on
+    pub source_line: usize,
+}
+
+impl UnusedCode {
+    /// whether every instruction can be reached and every label is referred to
+    pub fn is_empty(&self) -> bool {
+        self.unreachable.is_empty()
+            && self.unused_data.is_empty()
+            && self.unreferenced_labels.is_empty()
+    }
+}
+
+impl Program {
+    /// the instructions that can't be reached, and the labels and data that nothing refers to, to find dead code left behind by changes to a program
+    ///
+    /// Instructions are reached by following branches and falling through from the entry point, and from every label whose address is taken, e.g. by `ADR` or `DEFW`, as it may be jumped to through a register.
+    /// A BL is followed by both its target and the next instruction, a jump through a table (see [`Program::jump_tables`]) by each of the table's targets,
+    /// and any other write to the PC, e.g. `MOV PC, LR`, ends the path unless it is conditional.
+    /// Only the source's references are counted, so a label that is only used by the runtime, or by another program, is reported as unreferenced.
+    pub fn unused_code(&self) -> UnusedCode {
+        let cross_reference = self.cross_reference();
+
+        let mut roots = vec![self.entry_point];
+        roots.extend(
+            cross_reference
+                .symbols
+                .iter()
+                .filter(|symbol| {
+                    symbol
+                        .references
+                        .iter()
+                        .any(|reference| reference.kind != ReferenceKind::Branch)
+                })
+                .map(|symbol| symbol.value as usize),
+        );
+
+        let reachable = self.reachable(roots);
+
+        let unreachable = self
+            .statements
+            .iter()
+            .filter(|statement| {
+                statement.region == Region::Code
+                    && statement.source_line < self.lines.len()
+                    && !(0..statement.instructions.len())
+                        .any(|i| reachable.contains(&(statement.address + 4 * i)))
+            })
+            .map(|statement| UnusedStatement {
+                addresses: statement.address..statement.address + statement.bytes.len(),
+                source_line: statement.source_line,
+            })
+            .collect();
+
+        let unreferenced_labels: Vec<UnreferencedLabel> = cross_reference
+            .symbols
+            .iter()
+            .filter(|symbol| {
+                symbol.references.is_empty() && symbol.value as usize != self.entry_point
+            })
+            .filter_map(|symbol| {
+                let source_line = symbol.definition?;
+
+                // constants from EQU aren't labels of code or data
+                let constant = matches!(
+                    self.lines[source_line]
+                        .statement
+                        .as_ref()
+                        .map(|statement| &statement.kind),
+                    Some(StatementKind::Directive {
+                        kind: DirectiveKind::Constant { .. }
+                    })
+                );
+
+                (!constant).then(|| {
+                    (
+                        source_line,
+                        UnreferencedLabel {
+                            label: symbol.symbol.0.clone(),
+                            source_line,
+                        },
+                    )
+                })
+            })
+            .collect::<BTreeMap<usize, UnreferencedLabel>>()
+            .into_values()
+            .collect();
+
+        let unused_data = self.unused_data(&unreferenced_labels);
+
+        UnusedCode {
+            unreachable,
+            unused_data,
+            unreferenced_labels,
+        }
+    }
+
+    /// the program's image with its unused code and data left out, like linking with `--gc-sections`, see [`Program::unused_code`]
+    ///
+    /// Statements keep their addresses, so the bytes of unused statements between used ones are zeros, and the image only shrinks when they are at its start or end.
+    #[cfg(feature = "formats")]
+    pub fn stripped_image(&self, order: ByteOrder) -> Image {
+        let unused = self.unused_code();
+        let removed: Vec<&Range<usize>> = unused
+            .unreachable
+            .iter()
+            .chain(&unused.unused_data)
+            .map(|statement| &statement.addresses)
+            .collect();
+
+        let image = self.image(order);
+
+        let kept = self.statements.iter().filter(|statement| {
+            statement.region != Region::Padding
+                && !statement.bytes.is_empty()
+                && !removed.iter().any(|range| range.start == statement.address)
+        });
+
+        let (Some(start), Some(end)) = (
+            kept.clone().map(|statement| statement.address).min(),
+            kept.map(|statement| statement.address + statement.bytes.len())
+                .max(),
+        ) else {
+            return Image {
+                base: 0,
+                bytes: Vec::new(),
+            };
+        };
+
+        let mut bytes = image.bytes[start - image.base..end - image.base].to_vec();
+
+        for range in removed {
+            for address in range.start.max(start)..range.end.min(end) {
+                bytes[address - start] = 0;
+            }
+        }
+
+        Image { base: start, bytes }
+    }
+
+    /// the addresses of the instructions that can be reached from `roots`
+    fn reachable(&self, roots: Vec<usize>) -> BTreeSet<usize> {
+        let instructions: BTreeMap<usize, &InstructionKind> = self
+            .instructions()
+            .map(|site| (site.address, site.instruction))
+            .collect();
+        let jump_tables: BTreeMap<usize, Vec<u32>> = self
+            .jump_tables()
+            .into_iter()
+            .map(|table| (table.address, table.targets))
+            .collect();
+
+        let mut reachable = BTreeSet::new();
+        let mut pending = roots;
+
+        while let Some(address) = pending.pop() {
+            let Some(instruction) = instructions.get(&address) else {
+                continue;
+            };
+
+            if !reachable.insert(address) {
+                continue;
+            }
+
+            let next = address + 4;
+            let conditional = instruction.condition() != Condition::AL;
+            let target = instruction
+                .branch_target(address as u32)
+                .map(|target| target as usize);
+
+            match instruction {
+                InstructionKind::Branch {
+                    kind: BranchKind::BranchWithLink,
+                    ..
+                }
+                | InstructionKind::BranchWithLinkAndExchange { .. } => {
+                    pending.extend(target);
+                    pending.push(next);
+                }
+
+                InstructionKind::Branch { .. } => {
+                    pending.extend(target);
+                    if conditional {
+                        pending.push(next);
+                    }
+                }
+
+                // a jump through a table, which can go to any of its targets
+                _ if jump_tables.contains_key(&address) => {
+                    pending.extend(jump_tables[&address].iter().map(|target| *target as usize));
+                    if conditional {
+                        pending.push(next);
+                    }
+                }
+
+                // a return, or a jump through a register, which can't be followed
+                instruction if instruction.writes().contains(15) => {
+                    if conditional {
+                        pending.push(next);
+                    }
+                }
+
+                _ => pending.push(next),
+            }
+        }
+
+        reachable
+    }
+
+    /// the data statements of the source after each unreferenced label, up to the next label
+    fn unused_data(&self, unreferenced_labels: &[UnreferencedLabel]) -> Vec<UnusedStatement> {
+        let mut unused_lines = BTreeSet::new();
+        let mut unreferenced = false;
+
+        for (index, line) in self.lines.iter().enumerate() {
+            if line.label.is_some() {
+                unreferenced = unreferenced_labels
+                    .iter()
+                    .any(|label| label.source_line == index);
+            }
+
+            if unreferenced {
+                unused_lines.insert(index);
+            }
+        }
+
+        self.statements
+            .iter()
+            .filter(|statement| {
+                statement.region == Region::Data
+                    && unused_lines.contains(&statement.source_line)
+                    && !self
+                        .literal_pools
+                        .iter()
+                        .any(|pool| pool.address == statement.address)
+            })
+            .map(|statement| UnusedStatement {
+                addresses: statement.address..statement.address + statement.bytes.len(),
+                source_line: statement.source_line,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    ir::register_number,
+    lexer::{TokenKind, Tokens},
+    parser::{Line, Parser},
+    resolver::SymbolCase,
+    span::Span,
+};
+
+use super::TextEdit;
+
+/// the definitions and references of the symbols in a set of files that are linked together, for renaming a symbol across all of them
+///
+/// Linked programs refer to each other's symbols by name (see [`Program::assemble_with_imports`](super::Program::assemble_with_imports)), so a symbol is the same wherever it is written.
+/// Each file is indexed on its own, so only a file that changes needs to be indexed again.
+#[derive(Debug, Default, Clone)]
+pub struct WorkspaceIndex {
+    case: SymbolCase,
+    /// the symbols written in each file, by the file's name
+    files: BTreeMap<String, Vec<SymbolOccurrence>>,
+}
+
+/// a place a symbol is written
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolOccurrence {
+    /// the symbol as it is written
+    pub name: String,
+    /// the span of the symbol's name
+    pub span: Span,
+    /// whether the symbol is defined here, as a label or EQU, rather than referred to
+    pub definition: bool,
+}
+
+/// a place a symbol is written, along with the file it is in
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolLocation<'a> {
+    pub file: &'a str,
+    pub occurrence: &'a SymbolOccurrence,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RenameError {
+    /// the symbol isn't written anywhere in the workspace
+    UnknownSymbol,
+    /// the new name isn't a single identifier, or is the name of a register
+    InvalidName,
+    /// a symbol with the new name is already defined
+    AlreadyDefined { file: String, span: Span },
+}
+
+impl WorkspaceIndex {
+    /// an empty index, which matches symbols with `case` like the assembler does
+    pub fn new(case: SymbolCase) -> WorkspaceIndex {
+        WorkspaceIndex {
+            case,
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// indexes the source of a file, replacing what was indexed for it before
+    ///
+    /// Lines that don't parse are left out, so the index can be kept up to date while a file is being edited.
+    pub fn update(&mut self, file: &str, source: &str) {
+        let mut occurrences = Vec::new();
+        let mut offset = 0;
+
+        for (index, text) in source.split('\n').enumerate() {
+            if let Ok(line) = Parser::parse_line(text, offset, index) {
+                occurrences.extend(self.occurrences(&line, text, offset, index));
+            }
+
+            offset += text.len() + 1;
+        }
+
+        self.files.insert(file.to_owned(), occurrences);
+    }
+
+    /// removes a file from the index
+    pub fn remove(&mut self, file: &str) {
+        self.files.remove(file);
+    }
+
+    /// the symbol written at a byte `offset` of a file, such as where an editor's cursor is
+    pub fn symbol_at(&self, file: &str, offset: usize) -> Option<&SymbolOccurrence> {
+        self.files.get(file)?.iter().find(|occurrence| {
+            occurrence.span.byte_range.start <= offset && offset <= occurrence.span.byte_range.end
+        })
+    }
+
+    /// the places a symbol is defined, which is more than one if it is defined by several files
+    pub fn definitions(&self, symbol: &str) -> Vec<SymbolLocation<'_>> {
+        self.locations(symbol)
+            .into_iter()
+            .filter(|location| location.occurrence.definition)
+            .collect()
+    }
+
+    /// the places a symbol is referred to, in order of file and then position
+    pub fn references(&self, symbol: &str) -> Vec<SymbolLocation<'_>> {
+        self.locations(symbol)
+            .into_iter()
+            .filter(|location| !location.occurrence.definition)
+            .collect()
+    }
+
+    /// the edits to each file that rename a symbol everywhere it is defined or referred to
+    pub fn rename_symbol(
+        &self,
+        symbol: &str,
+        new_name: &str,
+    ) -> Result<BTreeMap<String, Vec<TextEdit>>, RenameError> {
+        if !is_symbol_name(new_name) {
+            return Err(RenameError::InvalidName);
+        }
+
+        // renaming to the same name with different case is allowed, even when case doesn't matter
+        if !self.matches(symbol, new_name) {
+            if let Some(location) = self.definitions(new_name).first() {
+                return Err(RenameError::AlreadyDefined {
+                    file: location.file.to_owned(),
+                    span: location.occurrence.span.clone(),
+                });
+            }
+        }
+
+        let mut edits: BTreeMap<String, Vec<TextEdit>> = BTreeMap::new();
+
+        for location in self.locations(symbol) {
+            edits
+                .entry(location.file.to_owned())
+                .or_default()
+                .push(TextEdit {
+                    range: location.occurrence.span.byte_range.clone(),
+                    text: new_name.to_owned(),
+                });
+        }
+
+        if edits.is_empty() {
+            return Err(RenameError::UnknownSymbol);
+        }
+
+        Ok(edits)
+    }
+
+    fn locations(&self, symbol: &str) -> Vec<SymbolLocation<'_>> {
+        self.files
+            .iter()
+            .flat_map(|(file, occurrences)| {
+                occurrences
+                    .iter()
+                    .filter(|occurrence| self.matches(&occurrence.name, symbol))
+                    .map(move |occurrence| SymbolLocation { file, occurrence })
+            })
+            .collect()
+    }
+
+    /// whether two names are the same symbol
+    fn matches(&self, a: &str, b: &str) -> bool {
+        match self.case {
+            SymbolCase::Sensitive => a == b,
+            SymbolCase::Insensitive | SymbolCase::InsensitiveWithWarnings => {
+                a.to_lowercase() == b.to_lowercase()
+            }
+        }
+    }
+
+    /// the symbols written on a line, found by matching the line's identifiers against the symbols its statement refers to
+    fn occurrences(
+        &self,
+        line: &Line,
+        text: &str,
+        offset: usize,
+        index: usize,
+    ) -> Vec<SymbolOccurrence> {
+        let mut identifiers = Tokens::with_offset(text, offset, index)
+            .filter(|token| !token.kind.is_trivia() && token.kind != TokenKind::NewLine);
+
+        let mut occurrences = Vec::new();
+
+        if let Some(label) = &line.label {
+            if let Some(token) = identifiers.next() {
+                occurrences.push(SymbolOccurrence {
+                    name: label.clone(),
+                    span: token.span,
+                    definition: true,
+                });
+            }
+        }
+
+        let Some(statement) = &line.statement else {
+            return occurrences;
+        };
+
+        // the mnemonic
+        identifiers.next();
+
+        let symbols = statement.symbols();
+
+        for token in identifiers.filter(|token| token.kind == TokenKind::Identifier) {
+            if symbols
+                .iter()
+                .any(|symbol| self.matches(&symbol.0, token.text))
+            {
+                occurrences.push(SymbolOccurrence {
+                    name: token.text.to_owned(),
+                    span: token.span,
+                    definition: false,
+                });
+            }
+        }
+
+        occurrences
+    }
+}
+
+/// whether `name` can be used as a symbol, i.e. it is a single identifier that isn't a register
+fn is_symbol_name(name: &str) -> bool {
+    let mut tokens = Tokens::with_offset(name, 0, 0);
+
+    matches!(
+        (tokens.next(), tokens.next()),
+        (Some(token), None) if token.kind == TokenKind::Identifier && token.text == name
+    ) && register_number(name).is_none()
+}
@@ -0,0 +1,171 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use crate::{
+    parser::{InstructionKind, Statement, StatementKind, Symbol},
+    span::Span,
+};
+
+use super::Program;
+
+/// every symbol of a program, where it is defined and every line that refers to it, see [`Program::cross_reference`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrossReference {
+    /// the symbols, in alphabetical order
+    pub symbols: Vec<SymbolReferences>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolReferences {
+    pub symbol: Symbol,
+    pub value: u32,
+    /// the source line the symbol is defined on, or `None` if it is imported or defined by the runtime
+    pub definition: Option<usize>,
+    /// the references to the symbol, in source order
+    pub references: Vec<Reference>,
+}
+
+/// a statement that refers to a symbol
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub source_line: usize,
+    /// the span of the referring statement
+    pub span: Span,
+    pub kind: ReferenceKind,
+}
+
+/// how a statement uses a symbol
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceKind {
+    /// a branch to the symbol, including BL and BLX
+    Branch,
+    /// a memory access or address of the symbol, e.g. `LDR R0, value`, `ADR R0, table` or `LDR R0, =table`
+    Address,
+    /// the symbol as the value of an operand, e.g. `MOV R0, #SIZE` or `SVC exit`
+    Operand,
+    /// the symbol in a directive, e.g. `ORIGIN base` or `DEFS SIZE`
+    Directive,
+}
+
+impl ReferenceKind {
+    /// the kind of reference a statement makes to its symbols
+    fn of(statement: &Statement) -> ReferenceKind {
+        match &statement.kind {
+            StatementKind::Instruction { kind } => match kind {
+                InstructionKind::Branch { .. }
+                | InstructionKind::BranchWithLinkAndExchange { .. }
+                | InstructionKind::BranchAndExchange { .. } => ReferenceKind::Branch,
+                InstructionKind::LoadStore { .. }
+                | InstructionKind::Preload { .. }
+                | InstructionKind::LoadStoreMultiple { .. } => ReferenceKind::Address,
+                InstructionKind::DataProcessing { .. }
+                | InstructionKind::Multiply { .. }
+                | InstructionKind::MultiplyLong { .. }
+                | InstructionKind::SuperVisorCall { .. }
+                | InstructionKind::MoveStatusToRegister { .. }
+                | InstructionKind::MoveToStatusRegister { .. }
+                | InstructionKind::CountLeadingZeros { .. } => ReferenceKind::Operand,
+            },
+            StatementKind::PseudoInstruction { .. } => ReferenceKind::Address,
+            StatementKind::Directive { .. } => ReferenceKind::Directive,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            ReferenceKind::Branch => "branch",
+            ReferenceKind::Address => "address",
+            ReferenceKind::Operand => "operand",
+            ReferenceKind::Directive => "directive",
+        }
+    }
+}
+
+impl Program {
+    /// a cross-reference of the program's symbols, for finding where a label is used in a larger program
+    pub fn cross_reference(&self) -> CrossReference {
+        let mut symbols: BTreeMap<&str, SymbolReferences> = self
+            .symbol_table
+            .iter()
+            .map(|(symbol, value)| {
+                let definition = self
+                    .lines
+                    .iter()
+                    .position(|line| line.label.as_ref() == Some(&symbol.0));
+
+                (
+                    symbol.0.as_str(),
+                    SymbolReferences {
+                        symbol: symbol.clone(),
+                        value: *value,
+                        definition,
+                        references: Vec::new(),
+                    },
+                )
+            })
+            .collect();
+
+        for (source_line, statement) in self
+            .lines
+            .iter()
+            .enumerate()
+            .filter_map(|(index, line)| Some((index, line.statement.as_ref()?)))
+        {
+            for reference in statement.symbols() {
+                let Some((symbol, _)) = self.symbol_table.definition(reference) else {
+                    continue;
+                };
+
+                if let Some(references) = symbols.get_mut(symbol.0.as_str()) {
+                    references.references.push(Reference {
+                        source_line,
+                        span: statement.span.clone(),
+                        kind: ReferenceKind::of(statement),
+                    });
+                }
+            }
+        }
+
+        CrossReference {
+            symbols: symbols.into_values().collect(),
+        }
+    }
+}
+
+impl Display for CrossReference {
+    /// formats the cross-reference as a table, with one-based line numbers, e.g. `loop  0x00000004  3  5 (branch), 9 (branch)`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let width = self
+            .symbols
+            .iter()
+            .map(|symbol| symbol.symbol.0.len())
+            .chain(["SYMBOL".len()])
+            .max()
+            .unwrap_or_default();
+
+        writeln!(f, "{:width$}  VALUE       DEFINED  REFERENCED", "SYMBOL")?;
+
+        for symbol in &self.symbols {
+            let definition = symbol
+                .definition
+                .map_or("-".to_owned(), |line| (line + 1).to_string());
+
+            let references: Vec<String> = symbol
+                .references
+                .iter()
+                .map(|reference| {
+                    format!("{} ({})", reference.source_line + 1, reference.kind.name())
+                })
+                .collect();
+
+            writeln!(
+                f,
+                "{:width$}  {:#010X}  {definition:7}  {}",
+                symbol.symbol.0,
+                symbol.value,
+                references.join(", ")
+            )?;
+        }
+
+        Ok(())
+    }
+}
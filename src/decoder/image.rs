@@ -0,0 +1,63 @@
+use crate::ir::{ByteOrder, InstructionKind};
+
+use super::{Bits, EncodingSpace};
+
+/// a word of a memory image, see [`decode_image`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodedItem {
+    Instruction {
+        word: u32,
+        instruction: InstructionKind,
+    },
+    /// bytes that aren't an instruction, in the order they are in the image
+    ///
+    /// These are whole words, except for the bytes left at the end of an image whose length isn't a multiple of 4.
+    Data(Vec<u8>),
+}
+
+impl DecodedItem {
+    pub fn instruction(&self) -> Option<&InstructionKind> {
+        match self {
+            DecodedItem::Instruction { instruction, .. } => Some(instruction),
+            DecodedItem::Data(_) => None,
+        }
+    }
+}
+
+/// decodes each word of a big-endian image loaded at `base_address`, for disassembling it or showing it in a debugger, see [`decode_image_with`]
+pub fn decode_image(bytes: &[u8], base_address: u32) -> Vec<(u32, DecodedItem)> {
+    decode_image_with(bytes, base_address, ByteOrder::BigEndian)
+}
+
+/// decodes each word of an image loaded at `base_address`, with its address
+///
+/// Words that don't decode, and words with the NV condition that aren't one of the unconditional instructions, are data, as they are never executed.
+/// An image's data can't always be told apart from its instructions like this, as many data words are also valid instructions.
+pub fn decode_image_with(
+    bytes: &[u8],
+    base_address: u32,
+    order: ByteOrder,
+) -> Vec<(u32, DecodedItem)> {
+    bytes
+        .chunks(4)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let address = base_address.wrapping_add(4 * i as u32);
+
+            let Ok(word) = chunk.try_into().map(|word| order.word_from_bytes(word)) else {
+                return (address, DecodedItem::Data(chunk.to_vec()));
+            };
+
+            let bits = Bits(word);
+
+            let item = match InstructionKind::decode(&bits) {
+                Ok(instruction) if bits.space() != EncodingSpace::Never => {
+                    DecodedItem::Instruction { word, instruction }
+                }
+                _ => DecodedItem::Data(chunk.to_vec()),
+            };
+
+            (address, item)
+        })
+        .collect()
+}
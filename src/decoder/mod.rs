@@ -1,17 +1,16 @@
-use crate::{
-    ir::{
-        Condition, Imm, MultipleAddressingMode, Rd, Rm, Rn, RotatedImm8, Rs, ShiftedRegister,
-        ShifterOperandCode, SignedImm,
-    },
-    parser::{
-        AddressingOffset, AddressingOffsetValue, BranchKind, CalculationKind, DataProcessingKind,
-        InstructionKind, LoadStoreAddressCode, LoadStoreKind, LoadStoreQuantity, OffsetMode,
-        RegisterList, SetFlags, Shift, Sign, WriteBack,
-    },
+use crate::ir::{
+    AddressingOffset, AddressingOffsetValue, BranchKind, CalculationKind, ComparisonKind,
+    Condition, DataProcessingKind, Imm, InstructionKind, LoadStoreAddressCode, LoadStoreKind,
+    LoadStoreQuantity, MoveKind, MultipleAddressingMode, MultiplyLongKind, OffsetMode, Rd,
+    RegisterList, RegisterPair, Rm, Rn, RotatedImm8, Rs, SetFlags, Shift, ShiftedRegister,
+    ShifterOperandCode, Sign, SignedImm, StatusFields, WriteBack,
 };
 
 use std::ops::{Deref, Index, RangeInclusive};
 
+pub use image::{decode_image, decode_image_with, DecodedItem};
+
+mod image;
 mod tests;
 
 pub struct Bits(pub u32);
@@ -45,18 +44,134 @@ impl Deref for Bits {
 #[derive(Debug)]
 pub struct InvalidInstructionError;
 
+/// how an instruction word is interpreted, which depends on its condition field
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EncodingSpace {
+    /// the instruction is executed if its condition passes
+    Conditional(Condition),
+    /// an ARMv5 instruction that is always executed, which is either BLX (immediate) or PLD
+    Unconditional,
+    /// a word with the [`Condition::NV`] condition outside of the unconditional space
+    ///
+    /// These are never executed on ARMv4 and earlier, and are unpredictable from ARMv5.
+    Never,
+}
+
+impl Bits {
+    /// classifies the word by its condition field
+    pub fn space(&self) -> EncodingSpace {
+        match Condition::decode(self) {
+            Condition::NV => {
+                // BLX (immediate): 1111 101H
+                let branch = self.range(25..=27) == 0b101;
+                // PLD: 1111 01I1 U101 Rn 1111
+                let preload = self.range(26..=27) == 0b01
+                    && self[24] == 1
+                    && self.range(20..=22) == 0b101
+                    && self.range(12..=15) == 0b1111;
+
+                if branch || preload {
+                    EncodingSpace::Unconditional
+                } else {
+                    EncodingSpace::Never
+                }
+            }
+            condition => EncodingSpace::Conditional(condition),
+        }
+    }
+}
+
 impl InstructionKind {
     pub fn decode(bits: &Bits) -> Result<Self, InvalidInstructionError> {
         match bits.range(25..=27) {
+            // branch and exchange, where data processing would be a TEQ without the S bit
+            0b000 if bits.range(4..=27) == 0x12_FFF1 => Ok(InstructionKind::BranchAndExchange {
+                condition: Condition::decode(bits),
+                target: Rm::decode(bits),
+            }),
+
+            // move status register to a register, where data processing would be a TST without the S bit
+            0b000 if bits.range(16..=27) == 0x10F && bits.range(0..=11) == 0 => {
+                Ok(InstructionKind::MoveStatusToRegister {
+                    condition: Condition::decode(bits),
+                    destination: Rd::decode(bits),
+                })
+            }
+
+            // move to status register, where data processing would be a TEQ without the S bit
+            0b000
+                if bits.range(20..=27) == 0x12
+                    && bits.range(12..=15) == 0xF
+                    && bits.range(4..=11) == 0 =>
+            {
+                Ok(InstructionKind::MoveToStatusRegister {
+                    condition: Condition::decode(bits),
+                    fields: StatusFields::decode(bits),
+                    source: ShifterOperandCode::decode(bits),
+                })
+            }
+            0b001 if bits.range(20..=24) == 0b10010 && bits.range(12..=15) == 0xF => {
+                Ok(InstructionKind::MoveToStatusRegister {
+                    condition: Condition::decode(bits),
+                    fields: StatusFields::decode(bits),
+                    source: ShifterOperandCode::decode(bits),
+                })
+            }
+
+            // count leading zeros, where data processing would be a CMN without the S bit
+            0b000
+                if bits.range(16..=27) == 0x16F
+                    && bits.range(8..=11) == 0xF
+                    && bits.range(4..=7) == 0b0001 =>
+            {
+                Ok(InstructionKind::CountLeadingZeros {
+                    condition: Condition::decode(bits),
+                    destination: Rd::decode(bits),
+                    source: Rm::decode(bits),
+                })
+            }
+
+            // multiply, where data processing would have an illegal register shift with bit 7 set
+            0b000 if bits.range(22..=24) == 0 && bits.range(4..=7) == 0b1001 => {
+                Ok(InstructionKind::Multiply {
+                    condition: Condition::decode(bits),
+                    set_flags: SetFlags::decode(bits),
+                    // Rd and Rn are the other way round to data processing
+                    destination: Rd(bits.range(16..=19) as u8),
+                    multiplicand: Rm::decode(bits),
+                    multiplier: Rs::decode(bits),
+                    accumulate: (bits[21] == 1).then(|| Rn(bits.range(12..=15) as u8)),
+                })
+            }
+
+            // long multiply
+            0b000 if bits.range(23..=24) == 0b01 && bits.range(4..=7) == 0b1001 => {
+                Ok(InstructionKind::MultiplyLong {
+                    condition: Condition::decode(bits),
+                    kind: MultiplyLongKind::decode(bits),
+                    set_flags: SetFlags::decode(bits),
+                    destination: RegisterPair::decode(bits),
+                    multiplicand: Rm::decode(bits),
+                    multiplier: Rs::decode(bits),
+                })
+            }
+
             // data processing
             0b000 | 0b001 => Ok(InstructionKind::DataProcessing {
-                condition: Condition::decode(bits)?,
+                condition: Condition::decode(bits),
                 kind: DataProcessingKind::decode(bits)?,
             }),
 
+            // preload, in the unconditional space
+            0b010 | 0b011 if bits.space() == EncodingSpace::Unconditional => {
+                Ok(InstructionKind::Preload {
+                    address: LoadStoreAddressCode::decode(bits)?,
+                })
+            }
+
             // load store
             0b010 | 0b011 => Ok(InstructionKind::LoadStore {
-                condition: Condition::decode(bits)?,
+                condition: Condition::decode(bits),
                 kind: LoadStoreKind::decode(bits),
                 quantity: LoadStoreQuantity::decode(bits),
                 destination: Rd::decode(bits),
@@ -65,50 +180,62 @@ impl InstructionKind {
 
             // load store multiple
             0b100 => Ok(InstructionKind::LoadStoreMultiple {
-                condition: Condition::decode(bits)?,
+                condition: Condition::decode(bits),
                 kind: LoadStoreKind::decode(bits),
-                mode: MultipleAddressingMode::decode(bits)?,
+                mode: MultipleAddressingMode::decode(bits),
                 base: Rn::decode(bits),
                 write_back: WriteBack::decode(bits),
                 register_list: RegisterList::decode(bits),
             }),
 
+            // branch with link and exchange, in the unconditional space
+            0b101 if bits.space() == EncodingSpace::Unconditional => {
+                Ok(InstructionKind::BranchWithLinkAndExchange {
+                    target: SignedImm::decode(bits),
+                    halfword: bits[24] == 1,
+                })
+            }
+
             // branch
             0b101 => Ok(InstructionKind::Branch {
-                condition: Condition::decode(bits)?,
+                condition: Condition::decode(bits),
                 kind: BranchKind::decode(bits),
                 target: SignedImm::decode(bits),
             }),
 
             // supervisor call
             0b111 => Ok(InstructionKind::SuperVisorCall {
-                condition: Condition::decode(bits)?,
+                condition: Condition::decode(bits),
                 immediate: Imm::decode(bits),
             }),
 
-            _ => todo!(),
+            // coprocessor instructions aren't supported
+            _ => Err(InvalidInstructionError),
         }
     }
 }
 
 impl Condition {
-    fn decode(bits: &Bits) -> Result<Self, InvalidInstructionError> {
+    /// all 16 values of the condition field are valid, but [`Condition::NV`] changes how the rest of the word is interpreted from ARMv5 (see [`Bits::space`])
+    fn decode(bits: &Bits) -> Self {
         match bits.range(28..=31) {
-            0b0000 => Ok(Self::EQ),
-            0b0001 => Ok(Self::NE),
-            0b0010 => Ok(Self::CS),
-            0b0011 => Ok(Self::CC),
-            0b0100 => Ok(Self::MI),
-            0b0110 => Ok(Self::VS),
-            0b0111 => Ok(Self::VC),
-            0b1000 => Ok(Self::HI),
-            0b1001 => Ok(Self::LS),
-            0b1010 => Ok(Self::GE),
-            0b1011 => Ok(Self::LT),
-            0b1100 => Ok(Self::GT),
-            0b1101 => Ok(Self::LE),
-            0b1110 => Ok(Self::AL),
-            _ => Err(InvalidInstructionError),
+            0b0000 => Self::EQ,
+            0b0001 => Self::NE,
+            0b0010 => Self::CS,
+            0b0011 => Self::CC,
+            0b0100 => Self::MI,
+            0b0101 => Self::PL,
+            0b0110 => Self::VS,
+            0b0111 => Self::VC,
+            0b1000 => Self::HI,
+            0b1001 => Self::LS,
+            0b1010 => Self::GE,
+            0b1011 => Self::LT,
+            0b1100 => Self::GT,
+            0b1101 => Self::LE,
+            0b1110 => Self::AL,
+            0b1111 => Self::NV,
+            _ => unreachable!(),
         }
     }
 }
@@ -117,7 +244,7 @@ impl DataProcessingKind {
     fn decode(bits: &Bits) -> Result<Self, InvalidInstructionError> {
         match bits.range(21..=24) {
             // Data Processing
-            0b0100 | 0b0010 => Ok(DataProcessingKind::Calculation {
+            0b0010..=0b0111 => Ok(DataProcessingKind::Calculation {
                 kind: CalculationKind::decode(bits)?,
                 set_flags: SetFlags::decode(bits),
                 destination: Rd::decode(bits),
@@ -125,6 +252,21 @@ impl DataProcessingKind {
                 shifter: ShifterOperandCode::decode(bits),
             }),
 
+            // Comparison, which always sets the flags, as the encodings without S are other instructions
+            0b1000..=0b1011 if bits[20] == 1 => Ok(DataProcessingKind::Comparison {
+                kind: ComparisonKind::decode(bits),
+                source: Rn::decode(bits),
+                shifter: ShifterOperandCode::decode(bits),
+            }),
+
+            // Move
+            0b1101 | 0b1111 => Ok(DataProcessingKind::Move {
+                kind: MoveKind::decode(bits),
+                set_flags: SetFlags::decode(bits),
+                destination: Rd::decode(bits),
+                shifter: ShifterOperandCode::decode(bits),
+            }),
+
             //
             _ => Err(InvalidInstructionError),
         }
@@ -134,13 +276,51 @@ impl DataProcessingKind {
 impl CalculationKind {
     fn decode(bits: &Bits) -> Result<Self, InvalidInstructionError> {
         match bits.range(21..=24) {
-            0b0100 => Ok(Self::ADD),
             0b0010 => Ok(Self::SUB),
+            0b0011 => Ok(Self::RSB),
+            0b0100 => Ok(Self::ADD),
+            0b0101 => Ok(Self::ADC),
+            0b0110 => Ok(Self::SBC),
+            0b0111 => Ok(Self::RSC),
             _ => Err(InvalidInstructionError),
         }
     }
 }
 
+impl ComparisonKind {
+    fn decode(bits: &Bits) -> Self {
+        match bits.range(21..=22) {
+            0b00 => Self::TST,
+            0b01 => Self::TEQ,
+            0b10 => Self::CMP,
+            0b11 => Self::CMN,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl MultiplyLongKind {
+    fn decode(bits: &Bits) -> Self {
+        match bits.range(21..=22) {
+            0b00 => Self::UMULL,
+            0b01 => Self::UMLAL,
+            0b10 => Self::SMULL,
+            0b11 => Self::SMLAL,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl MoveKind {
+    fn decode(bits: &Bits) -> Self {
+        match bits[22] {
+            0b0 => Self::Move,
+            0b1 => Self::MoveNot,
+            _ => unreachable!(),
+        }
+    }
+}
+
 impl SetFlags {
     fn decode(bits: &Bits) -> Self {
         match bits[20] {
@@ -163,6 +343,15 @@ impl Rd {
     }
 }
 
+impl RegisterPair {
+    fn decode(bits: &Bits) -> Self {
+        Self {
+            low: Rd::decode(bits),
+            high: Rn::decode(bits),
+        }
+    }
+}
+
 impl Rs {
     fn decode(bits: &Bits) -> Self {
         Self(bits.range(8..=11) as u8)
@@ -175,6 +364,17 @@ impl Rm {
     }
 }
 
+impl StatusFields {
+    fn decode(bits: &Bits) -> Self {
+        Self {
+            flags: bits[19] == 1,
+            status: bits[18] == 1,
+            extension: bits[17] == 1,
+            control: bits[16] == 1,
+        }
+    }
+}
+
 impl ShifterOperandCode<RotatedImm8, Imm<5>> {
     fn decode(bits: &Bits) -> Self {
         match bits[25] {
@@ -298,15 +498,13 @@ impl OffsetMode {
 }
 
 impl MultipleAddressingMode {
-    fn decode(bits: &Bits) -> Result<Self, InvalidInstructionError> {
-        let (p, u, l) = (bits[20], bits[23], bits[24]);
-
-        match (l, p, u) {
-            (1, 0, 0) => Ok(MultipleAddressingMode::DecrementAfter),
-            (1, 0, 1) => Ok(MultipleAddressingMode::IncrementAfter),
-            (1, 1, 0) => Ok(MultipleAddressingMode::DecrementBefore),
-            (1, 1, 1) => Ok(MultipleAddressingMode::IncrementBefore),
-            _ => Err(InvalidInstructionError),
+    fn decode(bits: &Bits) -> Self {
+        match (bits[24], bits[23]) {
+            (0, 0) => MultipleAddressingMode::DecrementAfter,
+            (0, 1) => MultipleAddressingMode::IncrementAfter,
+            (1, 0) => MultipleAddressingMode::DecrementBefore,
+            (1, 1) => MultipleAddressingMode::IncrementBefore,
+            _ => unreachable!(),
         }
     }
 }
@@ -322,13 +520,15 @@ impl WriteBack {
 
 impl RegisterList {
     fn decode(bits: &Bits) -> Self {
-        let mut registers = [false; 16];
+        let mut list = RegisterList::default();
 
-        for i in 0..16 {
-            registers[i] = bits[i] == 1;
+        for register in 0..16 {
+            if bits[register as usize] == 1 {
+                list.insert(register);
+            }
         }
 
-        RegisterList { registers }
+        list
     }
 }
 
@@ -1,10 +1,11 @@
 use crate::{
-    decoder::Bits,
-    ir::{Condition, Imm, Rd, Rm, Rn, ShiftedRegister, ShifterOperandCode, SignedImm},
-    parser::{
-        AddressingOffset, AddressingOffsetValue, BranchKind, CalculationKind, DataProcessingKind,
-        InstructionKind, LoadStoreAddressCode, LoadStoreKind, LoadStoreQuantity, OffsetMode,
-        SetFlags, Shift, Sign,
+    decoder::{decode_image, decode_image_with, Bits, DecodedItem, EncodingSpace},
+    ir::{
+        AddressingOffset, AddressingOffsetValue, BranchKind, ByteOrder, CalculationKind,
+        ComparisonKind, Condition, DataProcessingKind, Imm, InstructionKind, LoadStoreAddressCode,
+        LoadStoreKind, LoadStoreQuantity, MoveKind, MultiplyLongKind, OffsetMode, Rd, RegisterPair,
+        Rm, Rn, RotatedImm8, Rs, SetFlags, Shift, ShiftedRegister, ShifterOperandCode, Sign,
+        SignedImm, StatusFields,
     },
 };
 
@@ -45,6 +46,305 @@ fn test_data_processing_decode() {
     )
 }
 
+#[test]
+fn test_move_decode() {
+    let instruction = InstructionKind::DataProcessing {
+        condition: Condition::AL,
+        kind: DataProcessingKind::Move {
+            kind: MoveKind::MoveNot,
+            set_flags: SetFlags::Set,
+            destination: Rd(0),
+            shifter: ShifterOperandCode::ImmediateShift(ShiftedRegister {
+                kind: Shift::LogicalShiftLeft,
+                amount: Imm::new(0),
+                base: Rm(1),
+            }),
+        },
+    };
+
+    // MVNS R0, R1
+    assert_eq!(
+        instruction,
+        InstructionKind::decode(&Bits(0b11100001111100000000000000000001)).unwrap()
+    );
+    #[cfg(feature = "assembler")]
+    assert_eq!(
+        InstructionKind::decode(&Bits(instruction.encode().word())).unwrap(),
+        instruction
+    );
+}
+
+#[test]
+fn test_calculation_decode() {
+    #[cfg(feature = "assembler")]
+    for kind in [
+        CalculationKind::ADD,
+        CalculationKind::SUB,
+        CalculationKind::RSB,
+        CalculationKind::ADC,
+        CalculationKind::SBC,
+        CalculationKind::RSC,
+    ] {
+        let instruction = InstructionKind::DataProcessing {
+            condition: Condition::AL,
+            kind: DataProcessingKind::Calculation {
+                kind,
+                set_flags: SetFlags::Set,
+                destination: Rd(0),
+                source: Rn(1),
+                shifter: ShifterOperandCode::ImmediateShift(ShiftedRegister {
+                    kind: Shift::LogicalShiftLeft,
+                    amount: Imm::new(0),
+                    base: Rm(2),
+                }),
+            },
+        };
+
+        assert_eq!(
+            InstructionKind::decode(&Bits(instruction.encode().word())).unwrap(),
+            instruction
+        );
+    }
+
+    // SBCS R0, R1, R2
+    assert_eq!(
+        InstructionKind::decode(&Bits(0b11100000110100010000000000000010)).unwrap(),
+        InstructionKind::DataProcessing {
+            condition: Condition::AL,
+            kind: DataProcessingKind::Calculation {
+                kind: CalculationKind::SBC,
+                set_flags: SetFlags::Set,
+                destination: Rd(0),
+                source: Rn(1),
+                shifter: ShifterOperandCode::ImmediateShift(ShiftedRegister {
+                    kind: Shift::LogicalShiftLeft,
+                    amount: Imm::new(0),
+                    base: Rm(2),
+                }),
+            },
+        }
+    );
+}
+
+#[test]
+fn test_multiply_decode() {
+    // MLAS R3, R1, R2, R4
+    assert_eq!(
+        InstructionKind::decode(&Bits(0xE0334291)).unwrap(),
+        InstructionKind::Multiply {
+            condition: Condition::AL,
+            set_flags: SetFlags::Set,
+            destination: Rd(3),
+            multiplicand: Rm(1),
+            multiplier: Rs(2),
+            accumulate: Some(Rn(4)),
+        }
+    );
+
+    // MULEQ R0, R1, R2
+    let instruction = InstructionKind::Multiply {
+        condition: Condition::EQ,
+        set_flags: SetFlags::DontSet,
+        destination: Rd(0),
+        multiplicand: Rm(1),
+        multiplier: Rs(2),
+        accumulate: None,
+    };
+
+    #[cfg(feature = "assembler")]
+    assert_eq!(instruction.encode().word(), 0x00000291);
+    assert_eq!(
+        InstructionKind::decode(&Bits(0x00000291)).unwrap(),
+        instruction
+    );
+}
+
+#[test]
+fn test_multiply_long_decode() {
+    // UMULL R0, R1, R2, R3
+    assert_eq!(
+        InstructionKind::decode(&Bits(0xE0810392)).unwrap(),
+        InstructionKind::MultiplyLong {
+            condition: Condition::AL,
+            kind: MultiplyLongKind::UMULL,
+            set_flags: SetFlags::DontSet,
+            destination: RegisterPair {
+                low: Rd(0),
+                high: Rn(1),
+            },
+            multiplicand: Rm(2),
+            multiplier: Rs(3),
+        }
+    );
+
+    #[cfg(feature = "assembler")]
+    for kind in [
+        MultiplyLongKind::UMULL,
+        MultiplyLongKind::UMLAL,
+        MultiplyLongKind::SMULL,
+        MultiplyLongKind::SMLAL,
+    ] {
+        let instruction = InstructionKind::MultiplyLong {
+            condition: Condition::NE,
+            kind,
+            set_flags: SetFlags::Set,
+            destination: RegisterPair {
+                low: Rd(4),
+                high: Rn(5),
+            },
+            multiplicand: Rm(6),
+            multiplier: Rs(7),
+        };
+
+        assert_eq!(
+            InstructionKind::decode(&Bits(instruction.encode().word())).unwrap(),
+            instruction
+        );
+    }
+}
+
+#[test]
+fn test_branch_and_exchange_decode() {
+    // BX LR
+    assert_eq!(
+        InstructionKind::decode(&Bits(0xE12FFF1E)).unwrap(),
+        InstructionKind::BranchAndExchange {
+            condition: Condition::AL,
+            target: Rm(14),
+        }
+    );
+
+    // BXNE R3
+    let instruction = InstructionKind::BranchAndExchange {
+        condition: Condition::NE,
+        target: Rm(3),
+    };
+
+    #[cfg(feature = "assembler")]
+    assert_eq!(instruction.encode().word(), 0x112FFF13);
+    assert_eq!(
+        InstructionKind::decode(&Bits(0x112FFF13)).unwrap(),
+        instruction
+    );
+}
+
+#[test]
+fn test_status_register_decode() {
+    // MRS R0, CPSR
+    assert_eq!(
+        InstructionKind::decode(&Bits(0xE10F0000)).unwrap(),
+        InstructionKind::MoveStatusToRegister {
+            condition: Condition::AL,
+            destination: Rd(0),
+        }
+    );
+
+    // MSR CPSR_fc, R1
+    let instruction = InstructionKind::MoveToStatusRegister {
+        condition: Condition::AL,
+        fields: StatusFields::DEFAULT,
+        source: ShifterOperandCode::ImmediateShift(ShiftedRegister {
+            kind: Shift::LogicalShiftLeft,
+            amount: Imm::try_from(0).unwrap(),
+            base: Rm(1),
+        }),
+    };
+
+    #[cfg(feature = "assembler")]
+    assert_eq!(instruction.encode().word(), 0xE129F001);
+    assert_eq!(
+        InstructionKind::decode(&Bits(0xE129F001)).unwrap(),
+        instruction
+    );
+
+    // MSRNE CPSR_f, #0xF0000000
+    let instruction = InstructionKind::MoveToStatusRegister {
+        condition: Condition::NE,
+        fields: StatusFields {
+            flags: true,
+            status: false,
+            extension: false,
+            control: false,
+        },
+        source: ShifterOperandCode::Immediate(RotatedImm8::new(0xF0, 4)),
+    };
+
+    #[cfg(feature = "assembler")]
+    assert_eq!(instruction.encode().word(), 0x1328F4F0);
+    assert_eq!(
+        InstructionKind::decode(&Bits(0x1328F4F0)).unwrap(),
+        instruction
+    );
+}
+
+#[test]
+fn test_count_leading_zeros_decode() {
+    // CLZ R0, R1
+    let instruction = InstructionKind::CountLeadingZeros {
+        condition: Condition::AL,
+        destination: Rd(0),
+        source: Rm(1),
+    };
+
+    #[cfg(feature = "assembler")]
+    assert_eq!(instruction.encode().word(), 0xE16F0F11);
+    assert_eq!(
+        InstructionKind::decode(&Bits(0xE16F0F11)).unwrap(),
+        instruction
+    );
+}
+
+#[test]
+fn test_comparison_decode() {
+    let instruction = InstructionKind::DataProcessing {
+        condition: Condition::NE,
+        kind: DataProcessingKind::Comparison {
+            kind: ComparisonKind::TEQ,
+            source: Rn(2),
+            shifter: ShifterOperandCode::ImmediateShift(ShiftedRegister {
+                kind: Shift::LogicalShiftLeft,
+                amount: Imm::new(0),
+                base: Rm(3),
+            }),
+        },
+    };
+
+    // TEQNE R2, R3
+    assert_eq!(
+        instruction,
+        InstructionKind::decode(&Bits(0b00010001001100100000000000000011)).unwrap()
+    );
+
+    #[cfg(feature = "assembler")]
+    for kind in [
+        ComparisonKind::CMP,
+        ComparisonKind::CMN,
+        ComparisonKind::TST,
+        ComparisonKind::TEQ,
+    ] {
+        let instruction = InstructionKind::DataProcessing {
+            condition: Condition::AL,
+            kind: DataProcessingKind::Comparison {
+                kind,
+                source: Rn(0),
+                shifter: ShifterOperandCode::ImmediateShift(ShiftedRegister {
+                    kind: Shift::LogicalShiftLeft,
+                    amount: Imm::new(0),
+                    base: Rm(1),
+                }),
+            },
+        };
+
+        assert_eq!(
+            InstructionKind::decode(&Bits(instruction.encode().word())).unwrap(),
+            instruction
+        );
+    }
+
+    // the comparison opcodes without S are other instructions
+    assert!(InstructionKind::decode(&Bits(0b11100001001000000000000000000001)).is_err());
+}
+
 #[test]
 fn test_load_store_decode() {
     let instruction = InstructionKind::LoadStore {
@@ -67,3 +367,103 @@ fn test_load_store_decode() {
         InstructionKind::decode(&Bits(0b11100101001100010000000000001010)).unwrap()
     )
 }
+
+#[test]
+fn test_condition_space() {
+    // ADDPL R0, R1, R2
+    assert_eq!(
+        Bits(0x50810002).space(),
+        EncodingSpace::Conditional(Condition::PL)
+    );
+    assert_eq!(
+        InstructionKind::decode(&Bits(0x50810002))
+            .unwrap()
+            .condition(),
+        Condition::PL
+    );
+
+    // BLX #0
+    assert_eq!(Bits(0xFA000000).space(), EncodingSpace::Unconditional);
+    // PLD [R1, #4]
+    assert_eq!(Bits(0xF5D1F004).space(), EncodingSpace::Unconditional);
+
+    // ADDNV R0, R1, R2, which was never executed before ARMv5
+    assert_eq!(Bits(0xF0810002).space(), EncodingSpace::Never);
+    assert_eq!(
+        InstructionKind::decode(&Bits(0xF0810002))
+            .unwrap()
+            .condition(),
+        Condition::NV
+    );
+}
+
+#[test]
+fn test_branch_with_link_and_exchange_decode() {
+    let instruction = InstructionKind::BranchWithLinkAndExchange {
+        target: SignedImm::new(-2),
+        halfword: true,
+    };
+
+    assert_eq!(
+        instruction,
+        InstructionKind::decode(&Bits(0b11111011111111111111111111111110)).unwrap()
+    );
+    assert_eq!(instruction.condition(), Condition::AL);
+}
+
+#[test]
+fn test_preload_decode() {
+    let instruction = InstructionKind::Preload {
+        address: LoadStoreAddressCode {
+            base: Rn(1),
+            offset: AddressingOffset {
+                sign: Sign::Positive,
+                mode: OffsetMode::Offset,
+                value: AddressingOffsetValue::Immediate(Imm::new(4)),
+            },
+        },
+    };
+
+    assert_eq!(
+        instruction,
+        InstructionKind::decode(&Bits(0xF5D1F004)).unwrap()
+    );
+}
+
+#[test]
+fn test_decode_image() {
+    let mut image = Vec::new();
+    for word in [0xEAFFFFF6u32, 0xEC000000, 0xF0000000, 0xE0810002] {
+        image.extend(word.to_be_bytes());
+    }
+    image.extend([1, 2]);
+
+    let items = decode_image(&image, 0x8000);
+    let addresses: Vec<u32> = items.iter().map(|(address, _)| *address).collect();
+    assert_eq!(addresses, [0x8000, 0x8004, 0x8008, 0x800C, 0x8010]);
+
+    assert_eq!(
+        items[0].1,
+        DecodedItem::Instruction {
+            word: 0xEAFFFFF6,
+            instruction: InstructionKind::Branch {
+                condition: Condition::AL,
+                kind: BranchKind::Branch,
+                target: SignedImm::new(-10),
+            }
+        }
+    );
+
+    // a coprocessor instruction, and a word that is never executed
+    assert_eq!(items[1].1, DecodedItem::Data(vec![0xEC, 0, 0, 0]));
+    assert_eq!(items[2].1, DecodedItem::Data(vec![0xF0, 0, 0, 0]));
+
+    assert!(matches!(
+        items[3].1.instruction(),
+        Some(InstructionKind::DataProcessing { .. })
+    ));
+    assert_eq!(items[4].1, DecodedItem::Data(vec![1, 2]));
+
+    let little = decode_image_with(&0xE0810002u32.to_le_bytes(), 0, ByteOrder::LittleEndian);
+    assert_eq!(little[0].1.instruction(), items[3].1.instruction());
+}
@@ -0,0 +1,479 @@
+use std::collections::BTreeMap;
+
+use crate::ir::{
+    AddressingOffset, AddressingOffsetValue, BranchKind, CalculationKind, ComparisonKind,
+    DataProcessingKind, Imm, InstructionKind, LoadStoreAddressCode, LoadStoreKind,
+    LoadStoreQuantity, MoveKind, MultipleAddressingMode, MultiplyLongKind, OffsetMode,
+    RegisterIdentifier, SetFlags, Shift, ShiftedRegister, ShifterOperandCode, Sign, WriteBack,
+};
+
+mod style;
+#[cfg(all(test, feature = "assembler"))]
+mod tests;
+
+pub use style::{CarryConditions, DisassemblyStyle, Radix, RegisterNames};
+
+/// names that make disassembled instructions easier to read
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Annotations {
+    /// the names of SVC numbers, such as `print_str` for `SVC 3`
+    pub svc_names: BTreeMap<u32, String>,
+    /// the values built by MOV32 sequences, by the address of the instruction that finishes them, which is followed by a comment with the value
+    pub constants: BTreeMap<u32, u32>,
+    /// the targets of jump tables, by the address of the `LDR PC, [PC, Rn, LSL #2]` that jumps through them, which is followed by a comment with the targets
+    pub jump_tables: BTreeMap<u32, Vec<u32>>,
+}
+
+impl InstructionKind {
+    /// writes the instruction as assembly, as if it were at `address`
+    ///
+    /// Branch targets are written as addresses, and SVCs with a name in `annotations` are followed by a comment with the name, e.g. `SVC 3 ; print_str`.
+    /// The instruction that finishes a MOV32 is followed by a comment with the value it built, and a jump through a known table by a comment with its targets.
+    pub fn disassemble(&self, address: u32, annotations: &Annotations) -> String {
+        self.disassemble_with(address, annotations, &DisassemblyStyle::default())
+    }
+
+    /// writes the instruction as assembly in the given style, as if it were at `address`
+    pub fn disassemble_with(
+        &self,
+        address: u32,
+        annotations: &Annotations,
+        style: &DisassemblyStyle,
+    ) -> String {
+        let text = self.disassemble_instruction(address, annotations, style);
+
+        let jump_table = annotations.jump_tables.get(&address).map(|targets| {
+            let targets: Vec<String> = targets
+                .iter()
+                .map(|target| format!("{target:#010X}"))
+                .collect();
+
+            targets.join(", ")
+        });
+
+        style.case(match (annotations.constants.get(&address), jump_table) {
+            (Some(value), _) => format!("{text} ; MOV32 {value:#010X}"),
+            (None, Some(targets)) => format!("{text} ; jump table {targets}"),
+            (None, None) => text,
+        })
+    }
+
+    fn disassemble_instruction(
+        &self,
+        address: u32,
+        annotations: &Annotations,
+        style: &DisassemblyStyle,
+    ) -> String {
+        match self {
+            InstructionKind::Branch {
+                condition,
+                kind,
+                target,
+            } => {
+                let link = match kind {
+                    BranchKind::Branch => "",
+                    BranchKind::BranchWithLink => "L",
+                };
+
+                let target = address.wrapping_add((target.get() << 2) as u32);
+
+                format!("B{link}{} {target:#010X}", style.condition(condition))
+            }
+
+            InstructionKind::BranchWithLinkAndExchange { target, halfword } => {
+                let offset = target.get() << 2 | (*halfword as i32) << 1;
+
+                format!("BLX {:#010X}", address.wrapping_add(offset as u32))
+            }
+
+            InstructionKind::BranchAndExchange { condition, target } => format!(
+                "BX{} {}",
+                style.condition(condition),
+                register(style, target)
+            ),
+
+            InstructionKind::DataProcessing { condition, kind } => match kind {
+                DataProcessingKind::Move {
+                    kind: MoveKind::Move,
+                    set_flags,
+                    destination,
+                    shifter,
+                } if style.shift_aliases => match shifter_alias(style, shifter) {
+                    Some((mnemonic, operands)) => format!(
+                        "{mnemonic}{}{} {}, {operands}",
+                        style.condition(condition),
+                        set_flags_suffix(set_flags),
+                        register(style, destination),
+                    ),
+                    None => format!(
+                        "MOV{}{} {}, {}",
+                        style.condition(condition),
+                        set_flags_suffix(set_flags),
+                        register(style, destination),
+                        shifter_operand(style, shifter)
+                    ),
+                },
+
+                DataProcessingKind::Move {
+                    kind,
+                    set_flags,
+                    destination,
+                    shifter,
+                } => {
+                    let mnemonic = match kind {
+                        MoveKind::Move => "MOV",
+                        MoveKind::MoveNot => "MVN",
+                    };
+
+                    format!(
+                        "{mnemonic}{}{} {}, {}",
+                        style.condition(condition),
+                        set_flags_suffix(set_flags),
+                        register(style, destination),
+                        shifter_operand(style, shifter)
+                    )
+                }
+
+                DataProcessingKind::Comparison {
+                    kind,
+                    source,
+                    shifter,
+                } => {
+                    let mnemonic = match kind {
+                        ComparisonKind::CMP => "CMP",
+                        ComparisonKind::CMN => "CMN",
+                        ComparisonKind::TST => "TST",
+                        ComparisonKind::TEQ => "TEQ",
+                    };
+
+                    format!(
+                        "{mnemonic}{} {}, {}",
+                        style.condition(condition),
+                        register(style, source),
+                        shifter_operand(style, shifter)
+                    )
+                }
+
+                DataProcessingKind::Calculation {
+                    kind,
+                    set_flags,
+                    destination,
+                    source,
+                    shifter,
+                } => {
+                    let mnemonic = match kind {
+                        CalculationKind::ADD => "ADD",
+                        CalculationKind::SUB => "SUB",
+                        CalculationKind::RSB => "RSB",
+                        CalculationKind::ADC => "ADC",
+                        CalculationKind::SBC => "SBC",
+                        CalculationKind::RSC => "RSC",
+                    };
+
+                    format!(
+                        "{mnemonic}{}{} {}, {}, {}",
+                        style.condition(condition),
+                        set_flags_suffix(set_flags),
+                        register(style, destination),
+                        register(style, source),
+                        shifter_operand(style, shifter)
+                    )
+                }
+            },
+
+            InstructionKind::Multiply {
+                condition,
+                set_flags,
+                destination,
+                multiplicand,
+                multiplier,
+                accumulate,
+            } => {
+                let operands = format!(
+                    "{}, {}, {}",
+                    register(style, destination),
+                    register(style, multiplicand),
+                    register(style, multiplier)
+                );
+
+                match accumulate {
+                    Some(accumulate) => format!(
+                        "MLA{}{} {operands}, {}",
+                        style.condition(condition),
+                        set_flags_suffix(set_flags),
+                        register(style, accumulate)
+                    ),
+                    None => format!(
+                        "MUL{}{} {operands}",
+                        style.condition(condition),
+                        set_flags_suffix(set_flags)
+                    ),
+                }
+            }
+
+            InstructionKind::MultiplyLong {
+                condition,
+                kind,
+                set_flags,
+                destination,
+                multiplicand,
+                multiplier,
+            } => {
+                let mnemonic = match kind {
+                    MultiplyLongKind::UMULL => "UMULL",
+                    MultiplyLongKind::UMLAL => "UMLAL",
+                    MultiplyLongKind::SMULL => "SMULL",
+                    MultiplyLongKind::SMLAL => "SMLAL",
+                };
+
+                format!(
+                    "{mnemonic}{}{} {}, {}, {}, {}",
+                    style.condition(condition),
+                    set_flags_suffix(set_flags),
+                    register(style, &destination.low),
+                    register(style, &destination.high),
+                    register(style, multiplicand),
+                    register(style, multiplier)
+                )
+            }
+
+            InstructionKind::LoadStore {
+                condition,
+                kind,
+                quantity,
+                destination,
+                address,
+            } => {
+                let mnemonic = match kind {
+                    LoadStoreKind::Load => "LDR",
+                    LoadStoreKind::Store => "STR",
+                };
+
+                let byte = match quantity {
+                    LoadStoreQuantity::Word => "",
+                    LoadStoreQuantity::Byte => "B",
+                };
+
+                format!(
+                    "{mnemonic}{byte}{} {}, {}",
+                    style.condition(condition),
+                    register(style, destination),
+                    load_store_address(style, address)
+                )
+            }
+
+            InstructionKind::Preload { address } => {
+                format!("PLD {}", load_store_address(style, address))
+            }
+
+            InstructionKind::LoadStoreMultiple {
+                condition,
+                kind,
+                mode,
+                base,
+                write_back,
+                register_list,
+            } => {
+                let mnemonic = match kind {
+                    LoadStoreKind::Load => "LDM",
+                    LoadStoreKind::Store => "STM",
+                };
+
+                let mode = match mode {
+                    MultipleAddressingMode::IncrementAfter => "IA",
+                    MultipleAddressingMode::IncrementBefore => "IB",
+                    MultipleAddressingMode::DecrementAfter => "DA",
+                    MultipleAddressingMode::DecrementBefore => "DB",
+                };
+
+                let write_back = match write_back {
+                    WriteBack::WriteBack => "!",
+                    WriteBack::NoWriteBack => "",
+                };
+
+                format!(
+                    "{mnemonic}{}{mode} {}{write_back}, {}",
+                    style.condition(condition),
+                    register(style, base),
+                    register_list.format(|number| style.register(number))
+                )
+            }
+
+            InstructionKind::SuperVisorCall {
+                condition,
+                immediate,
+            } => {
+                let number = immediate.get();
+                let text = style.number(number);
+
+                match annotations.svc_names.get(&number) {
+                    Some(name) => format!("SVC{} {text} ; {name}", style.condition(condition)),
+                    None => format!("SVC{} {text}", style.condition(condition)),
+                }
+            }
+
+            InstructionKind::MoveStatusToRegister {
+                condition,
+                destination,
+            } => format!(
+                "MRS{} {}, CPSR",
+                style.condition(condition),
+                register(style, destination)
+            ),
+
+            InstructionKind::MoveToStatusRegister {
+                condition,
+                fields,
+                source,
+            } => format!(
+                "MSR{} CPSR_{fields}, {}",
+                style.condition(condition),
+                shifter_operand(style, source)
+            ),
+
+            InstructionKind::CountLeadingZeros {
+                condition,
+                destination,
+                source,
+            } => format!(
+                "CLZ{} {}, {}",
+                style.condition(condition),
+                register(style, destination),
+                register(style, source)
+            ),
+        }
+    }
+}
+
+/// writes data as a DEFB directive, rather than disassembling it as instructions
+///
+/// Printable text is written as a string, e.g. `DEFB "Hell"`, and other bytes are written in hex.
+pub fn disassemble_data(bytes: &[u8]) -> String {
+    disassemble_data_with(bytes, &DisassemblyStyle::default())
+}
+
+/// writes data as a DEFB directive in the given style, which only changes the case of the directive
+pub fn disassemble_data_with(bytes: &[u8], style: &DisassemblyStyle) -> String {
+    let directive = if style.lowercase { "defb" } else { "DEFB" };
+
+    if !bytes.is_empty()
+        && bytes
+            .iter()
+            .all(|byte| byte.is_ascii_graphic() || *byte == b' ')
+        && !bytes.contains(&b'"')
+    {
+        format!("{directive} \"{}\"", String::from_utf8_lossy(bytes))
+    } else {
+        let bytes: Vec<String> = bytes.iter().map(|byte| format!("{byte:#04X}")).collect();
+
+        format!("{directive} {}", bytes.join(", "))
+    }
+}
+
+fn set_flags_suffix(set_flags: &SetFlags) -> &'static str {
+    match set_flags {
+        SetFlags::Set => "S",
+        SetFlags::DontSet => "",
+    }
+}
+
+fn register(style: &DisassemblyStyle, register: &impl RegisterIdentifier) -> String {
+    style.register(register.number())
+}
+
+fn shift_name(shift: &Shift) -> &'static str {
+    match shift {
+        Shift::LogicalShiftLeft => "LSL",
+        Shift::LogicalShiftRight => "LSR",
+        Shift::ArithmeticShiftRight => "ASR",
+        Shift::RotateRight => "ROR",
+    }
+}
+
+/// writes a register shifted by an immediate, where an amount of 0 has a special meaning for every shift but LSL
+fn immediate_shift(style: &DisassemblyStyle, shift: &ShiftedRegister<Imm<5>>) -> String {
+    let base = register(style, &shift.base);
+
+    match (shift.kind, shift.amount.get()) {
+        (Shift::LogicalShiftLeft, 0) => base,
+        (Shift::RotateRight, 0) => format!("{base}, RRX"),
+        (kind, 0) => format!("{base}, {} #32", shift_name(&kind)),
+        (kind, amount) => format!("{base}, {} #{amount}", shift_name(&kind)),
+    }
+}
+
+/// the shift a MOV of a shifted register is an alias of, and its operands, e.g. `LSL` and `R1, #2` for `MOV R0, R1, LSL #2`
+fn shifter_alias(
+    style: &DisassemblyStyle,
+    shifter: &ShifterOperandCode,
+) -> Option<(&'static str, String)> {
+    match shifter {
+        ShifterOperandCode::Immediate(_) => None,
+        ShifterOperandCode::ImmediateShift(shift) => {
+            let base = register(style, &shift.base);
+
+            match (shift.kind, shift.amount.get()) {
+                // a plain register move
+                (Shift::LogicalShiftLeft, 0) => None,
+                (Shift::RotateRight, 0) => Some(("RRX", base)),
+                (kind, 0) => Some((shift_name(&kind), format!("{base}, #32"))),
+                (kind, amount) => Some((shift_name(&kind), format!("{base}, #{amount}"))),
+            }
+        }
+        ShifterOperandCode::RegisterShift(shift) => Some((
+            shift_name(&shift.kind),
+            format!(
+                "{}, {}",
+                register(style, &shift.base),
+                register(style, &shift.amount)
+            ),
+        )),
+    }
+}
+
+fn shifter_operand(style: &DisassemblyStyle, shifter: &ShifterOperandCode) -> String {
+    match shifter {
+        ShifterOperandCode::Immediate(immediate) => format!("#{}", style.number(immediate.get())),
+        ShifterOperandCode::ImmediateShift(shift) => immediate_shift(style, shift),
+        ShifterOperandCode::RegisterShift(shift) => format!(
+            "{}, {} {}",
+            register(style, &shift.base),
+            shift_name(&shift.kind),
+            register(style, &shift.amount)
+        ),
+    }
+}
+
+fn load_store_address(
+    style: &DisassemblyStyle,
+    LoadStoreAddressCode { base, offset }: &LoadStoreAddressCode<Imm<12>, Imm<5>>,
+) -> String {
+    let AddressingOffset { sign, value, mode } = offset;
+
+    let sign = match sign {
+        Sign::Positive => "",
+        Sign::Negative => "-",
+    };
+
+    let value = match value {
+        // a zero offset is left out, e.g. `[R0]`
+        AddressingOffsetValue::Immediate(immediate)
+            if immediate.get() == 0 && matches!(mode, OffsetMode::Offset) =>
+        {
+            return format!("[{}]", register(style, base))
+        }
+        AddressingOffsetValue::Immediate(immediate) => {
+            format!("#{sign}{}", style.number(immediate.get()))
+        }
+        AddressingOffsetValue::Register(offset) => format!("{sign}{}", register(style, offset)),
+        AddressingOffsetValue::ScaledRegister(shift) => {
+            format!("{sign}{}", immediate_shift(style, shift))
+        }
+    };
+
+    match mode {
+        OffsetMode::Offset => format!("[{}, {value}]", register(style, base)),
+        OffsetMode::PreIndexed => format!("[{}, {value}]!", register(style, base)),
+        OffsetMode::PostIndexed => format!("[{}], {value}", register(style, base)),
+    }
+}
@@ -0,0 +1,98 @@
+use crate::ir::{register_name, Condition};
+
+/// how disassembled instructions are written, so the output can match the reference a course uses
+///
+/// The default style is the one the assembler's documentation uses, e.g. `MOV R0, SP, LSL #2`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct DisassemblyStyle {
+    pub registers: RegisterNames,
+    pub carry_conditions: CarryConditions,
+    /// the radix of immediates and offsets, which doesn't change branch targets, as they are always addresses in hex
+    pub immediates: Radix,
+    /// whether mnemonics, registers and shifts are written in lowercase, e.g. `mov r0, sp`
+    pub lowercase: bool,
+    /// whether a MOV of a shifted register is written as the shift, e.g. `LSL R0, R1, #2` rather than `MOV R0, R1, LSL #2`
+    pub shift_aliases: bool,
+}
+
+/// how R13 to R15 are written
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum RegisterNames {
+    /// `SP`, `LR` and `PC`
+    #[default]
+    Aliases,
+    /// `R13`, `R14` and `R15`
+    Numbers,
+}
+
+/// how the carry conditions are written
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum CarryConditions {
+    /// `CS` and `CC`, for carry set and carry clear
+    #[default]
+    Carry,
+    /// `HS` and `LO`, for unsigned higher or same and lower
+    Unsigned,
+}
+
+/// the base immediates are written in
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Radix {
+    #[default]
+    Decimal,
+    /// with a `0x` prefix
+    Hexadecimal,
+}
+
+impl DisassemblyStyle {
+    pub(super) fn register(&self, register: u8) -> String {
+        match self.registers {
+            RegisterNames::Aliases => register_name(register),
+            RegisterNames::Numbers => format!("R{register}"),
+        }
+    }
+
+    /// the mnemonic suffix for a condition, which is empty for [`Condition::AL`]
+    pub(super) fn condition(&self, condition: &Condition) -> &'static str {
+        match (condition, self.carry_conditions) {
+            (Condition::CS, CarryConditions::Unsigned) => "HS",
+            (Condition::CC, CarryConditions::Unsigned) => "LO",
+            (Condition::EQ, _) => "EQ",
+            (Condition::NE, _) => "NE",
+            (Condition::CS, _) => "CS",
+            (Condition::CC, _) => "CC",
+            (Condition::MI, _) => "MI",
+            (Condition::PL, _) => "PL",
+            (Condition::VS, _) => "VS",
+            (Condition::VC, _) => "VC",
+            (Condition::HI, _) => "HI",
+            (Condition::LS, _) => "LS",
+            (Condition::GE, _) => "GE",
+            (Condition::LT, _) => "LT",
+            (Condition::GT, _) => "GT",
+            (Condition::LE, _) => "LE",
+            (Condition::AL, _) => "",
+            (Condition::NV, _) => "NV",
+        }
+    }
+
+    /// writes an immediate or offset in the radix, without the `#`
+    pub(super) fn number(&self, value: u32) -> String {
+        match self.immediates {
+            Radix::Decimal => format!("{value}"),
+            Radix::Hexadecimal => format!("{value:#X}"),
+        }
+    }
+
+    /// applies the case to an instruction, leaving any comment after it, such as the name of an SVC, as it is
+    pub(super) fn case(&self, text: String) -> String {
+        if !self.lowercase {
+            return text;
+        }
+
+        match text.split_once(" ; ") {
+            Some((instruction, comment)) => format!("{} ; {comment}", instruction.to_lowercase()),
+            None => text.to_lowercase(),
+        }
+    }
+}
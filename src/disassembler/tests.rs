@@ -0,0 +1,193 @@
+use crate::{
+    assembler::{assemble_instruction, Program},
+    decoder::Bits,
+    disassembler::{Annotations, CarryConditions, DisassemblyStyle, Radix, RegisterNames},
+    ir::{
+        BranchKind, Condition, Imm, InstructionKind, LoadStoreKind, MultipleAddressingMode,
+        RegisterList, Rn, SignedImm, WriteBack,
+    },
+    resolver::SymbolTable,
+};
+
+fn disassemble(word: u32) -> String {
+    InstructionKind::decode(&Bits(word))
+        .unwrap()
+        .disassemble(0, &Annotations::default())
+}
+
+#[test]
+fn test_disassemble() {
+    for (word, expected) in [
+        (0xE0810002, "ADD R0, R1, R2"),
+        (0xE0510102, "SUBS R0, R1, R2, LSL #2"),
+        (0x00810352, "ADDEQ R0, R1, R2, ASR R3"),
+        (0xE5910000, "LDR R0, [R1]"),
+        (0xE5310004, "LDR R0, [R1, #-4]!"),
+        (0xE4C10001, "STRB R0, [R1], #1"),
+        (0xE7110002, "LDR R0, [R1, -R2]"),
+        (0xF5D1F004, "PLD [R1, #4]"),
+        (0xE92D4007, "STMDB SP!, {R0-R2, LR}"),
+        (0xE8900030, "LDMIA R0, {R4, R5}"),
+        (0x1F000003, "SVCNE 3"),
+    ] {
+        assert_eq!(disassemble(word), expected);
+    }
+}
+
+#[test]
+fn test_disassemble_branch() {
+    let branch = InstructionKind::Branch {
+        condition: Condition::LE,
+        kind: BranchKind::BranchWithLink,
+        target: SignedImm::new(-2),
+    };
+
+    assert_eq!(
+        branch.disassemble(0x100, &Annotations::default()),
+        "BLLE 0x000000F8"
+    );
+
+    let exchange = InstructionKind::BranchWithLinkAndExchange {
+        target: SignedImm::new(1),
+        halfword: true,
+    };
+
+    assert_eq!(
+        exchange.disassemble(0x100, &Annotations::default()),
+        "BLX 0x00000106"
+    );
+}
+
+#[test]
+fn test_svc_names() {
+    let program = Program::assemble(
+        "print_char\tequ\t0\nprint_str\tequ\t3\ncLF\tequ\t10\n\tSVC\tprint_str\n\tSVC\t0\n\tSVC\t10\n",
+    )
+    .unwrap();
+
+    let annotations = program.annotations();
+
+    // only constants used for SVCs name them
+    assert_eq!(
+        annotations.svc_names.into_iter().collect::<Vec<_>>(),
+        [(3, "print_str".to_owned())]
+    );
+
+    let svc = InstructionKind::SuperVisorCall {
+        condition: Condition::AL,
+        immediate: Imm::new(3),
+    };
+    assert_eq!(
+        svc.disassemble(0, &program.annotations()),
+        "SVC 3 ; print_str"
+    );
+
+    let store = InstructionKind::LoadStoreMultiple {
+        condition: Condition::AL,
+        kind: LoadStoreKind::Store,
+        mode: MultipleAddressingMode::DecrementBefore,
+        base: Rn(13),
+        write_back: WriteBack::WriteBack,
+        register_list: RegisterList::from_slice(&[0]),
+    };
+    assert_eq!(
+        store.disassemble(0, &program.annotations()),
+        "STMDB SP!, {R0}"
+    );
+}
+
+#[test]
+fn test_move_constant_comment() {
+    let program = Program::assemble("\tMOV32\tR0, #0x00FF00FF\n").unwrap();
+
+    let instructions =
+        assemble_instruction("MOV32 R0, #0x00FF00FF", &program.symbol_table, 0).unwrap();
+
+    // the last instruction of the sequence says which value it built
+    assert_eq!(
+        instructions[0].disassemble(0, &program.annotations()),
+        "MOV R0, #255"
+    );
+    assert_eq!(
+        instructions[1].disassemble(4, &program.annotations()),
+        "ADD R0, R0, #16711680 ; MOV32 0x00FF00FF"
+    );
+}
+
+#[test]
+fn test_jump_table_comment() {
+    let program = Program::assemble(
+        "\tCMP\tR0, #1\n\tLDRLS\tPC, [PC, R0, LSL #2]\n\tB\tzero\n\tDEFW\tzero, one\nzero\tMOV\tR1, #0\none\tMOV\tR1, #1\n",
+    )
+    .unwrap();
+
+    let jump = &program.statement_at(4).unwrap().instructions[0];
+
+    assert_eq!(
+        jump.disassemble(4, &program.annotations()),
+        "LDRLS PC, [PC, R0, LSL #2] ; jump table 0x00000014, 0x00000018"
+    );
+}
+
+#[test]
+fn test_disassembly_style() {
+    let disassemble = |source: &str, style: &DisassemblyStyle| {
+        assemble_instruction(source, &SymbolTable::default(), 0).unwrap()[0].disassemble_with(
+            0,
+            &Annotations::default(),
+            style,
+        )
+    };
+
+    let numbers = DisassemblyStyle {
+        registers: RegisterNames::Numbers,
+        lowercase: true,
+        ..Default::default()
+    };
+
+    assert_eq!(disassemble("MOV R0, SP", &numbers), "mov r0, r13");
+    assert_eq!(
+        disassemble("STMDB SP!, {R0-R2, LR}", &numbers),
+        "stmdb r13!, {r0-r2, r14}"
+    );
+
+    let unsigned = DisassemblyStyle {
+        carry_conditions: CarryConditions::Unsigned,
+        immediates: Radix::Hexadecimal,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        disassemble("ADDCS R0, R1, R2", &unsigned),
+        "ADDHS R0, R1, R2"
+    );
+    assert_eq!(
+        disassemble("SUBCC R0, R1, R2", &unsigned),
+        "SUBLO R0, R1, R2"
+    );
+    assert_eq!(
+        disassemble("LDR R0, [R1, #-16]", &unsigned),
+        "LDR R0, [R1, #-0x10]"
+    );
+    assert_eq!(disassemble("SVC 255", &unsigned), "SVC 0xFF");
+
+    let aliases = DisassemblyStyle {
+        shift_aliases: true,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        disassemble("MOV R0, R1, LSL #2", &aliases),
+        "LSL R0, R1, #2"
+    );
+    assert_eq!(
+        disassemble("MOVS R0, R1, ASR R2", &aliases),
+        "ASRS R0, R1, R2"
+    );
+    assert_eq!(disassemble("MOV R0, R1, RRX", &aliases), "RRX R0, R1");
+    assert_eq!(disassemble("MOV R0, R1", &aliases), "MOV R0, R1");
+    assert_eq!(
+        disassemble("ADD R0, R1, R2, LSL #2", &aliases),
+        "ADD R0, R1, R2, LSL #2"
+    );
+}
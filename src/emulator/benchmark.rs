@@ -0,0 +1,195 @@
+use std::{fmt::Display, sync::Arc};
+
+use crate::assembler::Program;
+
+use super::{Emulator, ExecutionError, RunOutcome, RunStatus};
+
+/// inputs to run the same program with, measuring each run, e.g. to see how a sort's instruction count grows with the length of the list, see [`Benchmark::run`]
+///
+/// It is built like `Benchmark::new(10_000).sizes(0, [10, 100, 1000])`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Benchmark {
+    inputs: Vec<BenchmarkInput>,
+    /// the most instructions a run can execute, so a program stuck in a loop still stops
+    limit: u64,
+}
+
+/// the state a run of a [`Benchmark`] starts from, on top of the loaded program
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BenchmarkInput {
+    /// the name of the run in the summary, e.g. the size of the problem
+    pub label: String,
+    /// the registers to set, in order
+    pub registers: Vec<(u8, u32)>,
+    /// the bytes to write to memory from each address, in order, e.g. a list to sort
+    pub memory: Vec<(u32, Vec<u8>)>,
+    /// text for the program to read, which needs a console, see [`Emulator::mount_console`]
+    pub input: String,
+}
+
+/// the measurements of a run of a [`Benchmark`]
+#[derive(Debug)]
+pub struct BenchmarkRun {
+    pub label: String,
+    /// how the run finished, or the error that stopped it
+    pub outcome: Result<RunOutcome, ExecutionError>,
+    /// the number of instructions executed
+    pub instructions: u64,
+    /// the total cost of the instructions, if the emulator has a cost table, see [`Emulator::set_cost_table`]
+    pub cost: Option<u64>,
+}
+
+/// the runs of a [`Benchmark`], in the order of its inputs
+#[derive(Debug)]
+pub struct BenchmarkReport {
+    pub runs: Vec<BenchmarkRun>,
+}
+
+impl Benchmark {
+    /// a benchmark with no inputs, where each run executes at most `limit` instructions
+    pub fn new(limit: u64) -> Benchmark {
+        Benchmark {
+            inputs: Vec::new(),
+            limit,
+        }
+    }
+
+    pub fn input(mut self, input: BenchmarkInput) -> Benchmark {
+        self.inputs.push(input);
+        self
+    }
+
+    /// adds a run for each size, with the size in `register`, labelled like `R0=100`
+    pub fn sizes(mut self, register: u8, sizes: impl IntoIterator<Item = u32>) -> Benchmark {
+        self.inputs.extend(sizes.into_iter().map(|size| {
+            BenchmarkInput::new(&format!("R{register}={size}")).register(register, size)
+        }));
+        self
+    }
+
+    pub fn inputs(&self) -> &[BenchmarkInput] {
+        &self.inputs
+    }
+
+    /// runs `program` once for each input, each on a new emulator made by `setup`, until it finishes, has to wait for input, or reaches the limit
+    ///
+    /// `setup` mounts what the program needs, such as a console or a stack, and can set a cost table to measure the runs by.
+    pub fn run(
+        &self,
+        program: &Arc<Program>,
+        mut setup: impl FnMut() -> Emulator,
+    ) -> BenchmarkReport {
+        let runs = self
+            .inputs
+            .iter()
+            .map(|input| {
+                let mut emulator = setup();
+
+                emulator.load(program.clone());
+
+                for (register, value) in &input.registers {
+                    emulator.registers[*register] = *value;
+                }
+
+                for (address, bytes) in &input.memory {
+                    emulator.write_memory(*address as usize, bytes);
+                }
+
+                emulator.send_input(input.input.as_bytes());
+
+                let start = emulator.cycles;
+                let outcome = emulator.run(self.limit);
+
+                BenchmarkRun {
+                    label: input.label.clone(),
+                    outcome,
+                    instructions: emulator.cycles - start,
+                    cost: emulator.cost_report().map(|report| report.total),
+                }
+            })
+            .collect();
+
+        BenchmarkReport { runs }
+    }
+}
+
+impl BenchmarkInput {
+    pub fn new(label: &str) -> BenchmarkInput {
+        BenchmarkInput {
+            label: label.to_owned(),
+            ..BenchmarkInput::default()
+        }
+    }
+
+    pub fn register(mut self, register: u8, value: u32) -> BenchmarkInput {
+        self.registers.push((register, value));
+        self
+    }
+
+    pub fn memory(mut self, address: u32, bytes: &[u8]) -> BenchmarkInput {
+        self.memory.push((address, bytes.to_vec()));
+        self
+    }
+
+    pub fn input(mut self, input: &str) -> BenchmarkInput {
+        self.input.push_str(input);
+        self
+    }
+}
+
+impl BenchmarkRun {
+    /// how the run ended, e.g. `exited with 0`, for the summary
+    fn result(&self) -> String {
+        match &self.outcome {
+            Ok(outcome) => match outcome.status {
+                RunStatus::Exited(code) => format!("exited with {code}"),
+                RunStatus::Returned => format!("returned {}", outcome.r0),
+                RunStatus::Waiting => "waiting for input".to_owned(),
+                RunStatus::LimitReached => "reached the limit".to_owned(),
+            },
+            Err(_) => "stopped with an error".to_owned(),
+        }
+    }
+}
+
+impl Display for BenchmarkReport {
+    /// writes a table with a row for each run, giving its label, instruction count, cost if there is a cost table, and how it ended
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let costs = self.runs.iter().any(|run| run.cost.is_some());
+
+        let rows: Vec<[String; 4]> = self
+            .runs
+            .iter()
+            .map(|run| {
+                [
+                    run.label.clone(),
+                    run.instructions.to_string(),
+                    run.cost.map_or("-".to_owned(), |cost| cost.to_string()),
+                    run.result(),
+                ]
+            })
+            .collect();
+
+        let header = ["run", "instructions", "cost", "result"].map(str::to_owned);
+        let width = |column: usize| {
+            rows.iter()
+                .chain([&header])
+                .map(|row| row[column].len())
+                .max()
+                .unwrap_or(0)
+        };
+        let (label, instructions, cost) = (width(0), width(1), width(2));
+
+        for row in [&header].into_iter().chain(&rows) {
+            write!(f, "{:<label$}  {:>instructions$}", row[0], row[1])?;
+
+            if costs {
+                write!(f, "  {:>cost$}", row[2])?;
+            }
+
+            writeln!(f, "  {}", row[3])?;
+        }
+
+        Ok(())
+    }
+}
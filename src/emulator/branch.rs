@@ -0,0 +1,50 @@
+use crate::ir::{Condition, InstructionKind};
+
+use super::{Emulator, Psr};
+
+/// why a stepped branch was or wasn't taken, see [`Emulator::why_branch`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BranchDecision {
+    /// the address of the branch
+    pub address: u32,
+    pub condition: Condition,
+    pub taken: bool,
+    /// the CPSR the condition was checked against
+    pub flags: Psr,
+    /// the address of the instruction that last set the flags before the branch, or `None` if no stepped instruction has set them
+    pub flags_set_by: Option<u32>,
+}
+
+impl Emulator {
+    /// why the last branch that was stepped was or wasn't taken: the flags its condition was checked against, and the instruction that set them
+    ///
+    /// For example, a `BEQ` that wasn't taken had Z clear, and `flags_set_by` points at the `CMP` that cleared it.
+    pub fn why_branch(&self) -> Option<&BranchDecision> {
+        self.last_branch.as_ref()
+    }
+
+    /// the address of the last stepped instruction that set the flags
+    pub fn flags_set_by(&self) -> Option<u32> {
+        self.flags_set_by
+    }
+
+    /// records the decision of a branch, or the address of an instruction that sets the flags, before it is executed
+    pub(super) fn record_branch(&mut self, address: u32, instruction: &InstructionKind) {
+        let condition = instruction.condition();
+        let passed = self.cpsr.condition_passed(condition);
+
+        if let InstructionKind::Branch { .. } = instruction {
+            self.last_branch = Some(BranchDecision {
+                address,
+                condition,
+                taken: passed,
+                flags: self.cpsr,
+                flags_set_by: self.flags_set_by,
+            });
+        }
+
+        if passed && instruction.sets_flags() {
+            self.flags_set_by = Some(address);
+        }
+    }
+}
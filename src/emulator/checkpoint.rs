@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+
+use super::{Emulator, ExecutionError, Psr};
+
+/// the size of the blocks of memory saved by a checkpoint before they are first written
+pub(super) const PAGE_SIZE: usize = 4096;
+
+/// the state of the machine after a number of instructions, see [`Emulator::set_checkpoint_interval`]
+///
+/// Rather than copying all of memory, a checkpoint keeps the old contents of each page the first time it is written after the checkpoint.
+struct Checkpoint {
+    cycles: u64,
+    registers: [u32; 16],
+    cpsr: Psr,
+    waiting: bool,
+    jumped_from: Option<u32>,
+    exit_code: Option<u32>,
+    irq_raised: Option<u64>,
+    /// the contents of the pages written since the checkpoint, by page number, as they were when it was taken
+    pages: HashMap<usize, Box<[u8]>>,
+}
+
+pub(super) struct Checkpoints {
+    interval: u64,
+    /// in order of cycles, starting with the checkpoint taken when they were turned on
+    checkpoints: Vec<Checkpoint>,
+}
+
+impl Emulator {
+    /// sets how many instructions are executed between each checkpoint, or `None` to stop taking them, which is the default
+    ///
+    /// A checkpoint is taken straight away, and then whenever the number of instructions executed is a multiple of `interval`, so that [`Emulator::run_to`] and [`Emulator::step_back`] can go back to any instruction since then by restoring the checkpoint before it and stepping forwards.
+    /// Each checkpoint only keeps the registers and the memory written after it, so long runs can be stepped back through without recording every step.
+    ///
+    /// Edits made with [`Emulator::edit_memory`] and [`Emulator::edit_register`] are rewound and made again along with the program's own changes.
+    /// Only the machine is rewound: the state of SVC handlers, such as input already read or files written, and diagnostics like [`Emulator::memory_heatmap`] aren't.
+    /// Loading a program starts the checkpoints again.
+    pub fn set_checkpoint_interval(&mut self, interval: Option<u64>) {
+        self.checkpoints = interval.map(|interval| Checkpoints {
+            interval: interval.max(1),
+            checkpoints: Vec::new(),
+        });
+
+        self.take_checkpoint();
+    }
+
+    /// the numbers of instructions executed at each checkpoint that is kept, in order
+    pub fn checkpoints(&self) -> Vec<u64> {
+        self.checkpoints
+            .iter()
+            .flat_map(|checkpoints| &checkpoints.checkpoints)
+            .map(|checkpoint| checkpoint.cycles)
+            .collect()
+    }
+
+    /// steps until `cycles` instructions have been executed, going back to the latest checkpoint before it first if it is in the past
+    ///
+    /// Going back fails with an [`ExecutionError::NoCheckpoint`] if there is no checkpoint at or before `cycles`.
+    /// Stepping stops early if an SVC has to wait, as it would need the host to provide input, or if the program halts.
+    pub fn run_to(&mut self, cycles: u64) -> Result<(), ExecutionError> {
+        if cycles < self.cycles {
+            let index = self
+                .checkpoints
+                .as_ref()
+                .and_then(|checkpoints| {
+                    checkpoints
+                        .checkpoints
+                        .iter()
+                        .rposition(|checkpoint| checkpoint.cycles <= cycles)
+                })
+                .ok_or(ExecutionError::NoCheckpoint { cycles })?;
+
+            self.restore_checkpoint(index);
+        }
+
+        while self.cycles < cycles {
+            if self.is_halted() {
+                break;
+            }
+
+            self.step()?;
+
+            if self.waiting {
+                break;
+            }
+        }
+
+        self.replay_edits();
+
+        Ok(())
+    }
+
+    /// undoes the last instruction executed, by going back to the checkpoint before it and stepping to the instruction before, see [`Emulator::run_to`]
+    pub fn step_back(&mut self) -> Result<(), ExecutionError> {
+        let cycles = self
+            .cycles
+            .checked_sub(1)
+            .ok_or(ExecutionError::NoCheckpoint { cycles: 0 })?;
+
+        self.run_to(cycles)
+    }
+
+    /// takes a checkpoint if one is due after a step
+    pub(super) fn checkpoint_step(&mut self) {
+        if let Some(checkpoints) = &self.checkpoints {
+            let due = self.cycles.is_multiple_of(checkpoints.interval)
+                && checkpoints
+                    .checkpoints
+                    .last()
+                    .is_none_or(|checkpoint| checkpoint.cycles < self.cycles);
+
+            if due {
+                self.take_checkpoint();
+            }
+        }
+    }
+
+    /// replaces the checkpoints with one of the current state and forgets the manual edits, e.g. after loading a program
+    pub(super) fn restart_checkpoints(&mut self) {
+        self.clear_edits();
+
+        if let Some(checkpoints) = &mut self.checkpoints {
+            checkpoints.checkpoints.clear();
+        }
+
+        self.take_checkpoint();
+    }
+
+    /// saves the contents of the pages from `address` to `address + length` before they are first written after the last checkpoint
+    pub(super) fn save_pages(&mut self, address: usize, length: usize) {
+        let Some(checkpoint) = self
+            .checkpoints
+            .as_mut()
+            .and_then(|checkpoints| checkpoints.checkpoints.last_mut())
+        else {
+            return;
+        };
+
+        if length == 0 {
+            return;
+        }
+
+        for page in address / PAGE_SIZE..=(address + length - 1) / PAGE_SIZE {
+            checkpoint.pages.entry(page).or_insert_with(|| {
+                let start = page * PAGE_SIZE;
+                let end = (start + PAGE_SIZE).min(self.memory.len());
+
+                self.memory[start..end].into()
+            });
+        }
+    }
+
+    fn take_checkpoint(&mut self) {
+        if let Some(checkpoints) = &mut self.checkpoints {
+            checkpoints.checkpoints.push(Checkpoint {
+                cycles: self.cycles,
+                registers: self.registers.0,
+                cpsr: self.cpsr,
+                waiting: self.waiting,
+                jumped_from: self.jumped_from,
+                exit_code: self.exit_code,
+                irq_raised: self.irq_raised,
+                pages: HashMap::new(),
+            });
+        }
+    }
+
+    /// puts the machine back in the state of the checkpoint at `index`, and drops the checkpoints after it
+    fn restore_checkpoint(&mut self, index: usize) {
+        let Some(checkpoints) = &mut self.checkpoints else {
+            return;
+        };
+
+        // the pages are put back newest first, so each ends up as it was at the checkpoint
+        for checkpoint in checkpoints.checkpoints.drain(index + 1..).rev() {
+            restore_pages(&mut self.memory, checkpoint.pages);
+        }
+
+        let checkpoint = &mut checkpoints.checkpoints[index];
+
+        restore_pages(&mut self.memory, std::mem::take(&mut checkpoint.pages));
+
+        self.cycles = checkpoint.cycles;
+        self.registers.0 = checkpoint.registers;
+        self.cpsr = checkpoint.cpsr;
+        self.waiting = checkpoint.waiting;
+        self.jumped_from = checkpoint.jumped_from;
+        self.exit_code = checkpoint.exit_code;
+        self.irq_raised = checkpoint.irq_raised;
+
+        self.rewind_edits(self.cycles);
+        self.decoded.clear();
+        self.publish();
+    }
+}
+
+fn restore_pages(memory: &mut [u8], pages: HashMap<usize, Box<[u8]>>) {
+    for (page, contents) in pages {
+        let start = page * PAGE_SIZE;
+
+        memory[start..start + contents.len()].copy_from_slice(&contents);
+    }
+}
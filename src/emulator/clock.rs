@@ -0,0 +1,66 @@
+use std::time::Instant;
+
+use super::Emulator;
+
+/// where the time returned by the clock SVC comes from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Clock {
+    /// time is derived from the cycle counter at a fixed frequency, so every run of a program sees the same times
+    Cycles { hertz: u64 },
+    /// the host's monotonic clock, measured from the given instant
+    Host(Instant),
+}
+
+impl Default for Clock {
+    /// a deterministic 1 MHz clock, so each cycle is a microsecond
+    fn default() -> Self {
+        Clock::Cycles { hertz: 1_000_000 }
+    }
+}
+
+impl Clock {
+    /// a host clock, starting now
+    pub fn host() -> Clock {
+        Clock::Host(Instant::now())
+    }
+
+    /// the time in microseconds, given the number of cycles executed
+    pub fn microseconds(&self, cycles: u64) -> u64 {
+        match self {
+            Clock::Cycles { hertz } => (cycles as u128 * 1_000_000 / *hertz as u128) as u64,
+            Clock::Host(start) => start.elapsed().as_micros() as u64,
+        }
+    }
+}
+
+impl Emulator {
+    /// the number of cycles executed, where each instruction currently takes one cycle
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// gives the program access to the cycle counter and a clock through two SVCs, numbered from `first_svc`
+    ///
+    /// | SVC             | Result |
+    /// | --------------- | ------ |
+    /// | `first_svc`     | the number of cycles executed before the SVC, with the low word in R0 and high word in R1 |
+    /// | `first_svc + 1` | the time in microseconds, with the low word in R0 and high word in R1 |
+    ///
+    /// Both are monotonic. Use [`Clock::Cycles`] (the default) for reproducible runs, e.g. when marking.
+    pub fn mount_clock(&mut self, clock: Clock, first_svc: u32) {
+        self.register_svc(first_svc, |emulator, _| {
+            // the SVC itself has already been counted
+            emulator.set_double_word(emulator.cycles - 1);
+        });
+
+        self.register_svc(first_svc + 1, move |emulator, _| {
+            emulator.set_double_word(clock.microseconds(emulator.cycles - 1));
+        });
+    }
+
+    /// returns a 64-bit value in R0 and R1
+    fn set_double_word(&mut self, value: u64) {
+        self.registers[0] = value as u32;
+        self.registers[1] = (value >> 32) as u32;
+    }
+}
@@ -0,0 +1,346 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    panic::{self, AssertUnwindSafe},
+};
+
+use super::{parse_hex, Emulator, ExecutionError, Psr};
+
+/// the address instructions are executed at, unless a vector gives another
+const DEFAULT_ADDRESS: u32 = 0x8000;
+
+/// vectors that each execute one instruction from an initial state and check the state after it, to validate the emulator against vectors derived from the architecture's pseudocode
+///
+/// A file of vectors has a block of lines for each vector:
+///
+/// ```text
+/// ; ADDS that carries out
+/// vector adds_carry
+/// instruction 0xE0910002
+/// set r1 0xFFFFFFFF
+/// set r2 0x00000001
+/// set cpsr 0x00000010
+/// set memory 0x1000 0x00000007
+/// expect r0 0x00000000
+/// expect cpsr 0x60000010
+/// ```
+///
+/// The instruction is executed at `0x8000`, or the address given by an `address` line, which the PC is set to.
+/// Only the registers, CPSR and words of memory that are given are set and checked, and the rest start as zero.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ConformanceSuite {
+    pub vectors: Vec<ConformanceVector>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConformanceVector {
+    pub name: String,
+    /// the one-based line of the vector in its file
+    pub line: usize,
+    pub instruction: u32,
+    pub address: u32,
+    pub initial: VectorState,
+    pub expected: VectorState,
+}
+
+/// part of the state of the machine
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct VectorState {
+    pub registers: BTreeMap<u8, u32>,
+    pub cpsr: Option<u32>,
+    /// words of memory, by address
+    pub memory: BTreeMap<u32, u32>,
+}
+
+/// a problem reading a file of vectors
+#[derive(Debug, Clone, PartialEq)]
+pub enum VectorError {
+    /// a line isn't part of a vector, with a one-based line number
+    Malformed { line: usize },
+    /// a vector has no instruction line
+    MissingInstruction { vector: String },
+}
+
+/// the results of running a suite
+#[derive(Debug, Default)]
+pub struct ConformanceReport {
+    pub passed: usize,
+    pub failures: Vec<VectorFailure>,
+}
+
+#[derive(Debug)]
+pub struct VectorFailure {
+    pub name: String,
+    pub line: usize,
+    pub kind: VectorFailureKind,
+}
+
+#[derive(Debug)]
+pub enum VectorFailureKind {
+    /// the state after the instruction isn't the expected state
+    Mismatches(Vec<Mismatch>),
+    /// the instruction stopped the emulator with an error
+    Error(ExecutionError),
+    /// the emulator panicked, e.g. because the instruction isn't emulated yet, with the panic's message
+    Panicked(String),
+}
+
+/// a value that differs from the one a vector expects
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mismatch {
+    pub location: StateLocation,
+    pub expected: u32,
+    pub actual: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateLocation {
+    Register(u8),
+    Cpsr,
+    /// a word of memory
+    Memory(u32),
+}
+
+impl ConformanceSuite {
+    /// reads a file of vectors, where lines starting with `;` are comments
+    pub fn parse(text: &str) -> Result<ConformanceSuite, VectorError> {
+        let mut suite = ConformanceSuite::default();
+        // the vector being read, which has an instruction once its instruction line is read
+        let mut current: Option<(ConformanceVector, bool)> = None;
+
+        for (index, line) in text.lines().enumerate() {
+            let malformed = VectorError::Malformed { line: index + 1 };
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+
+            if let ["vector", name] = fields.as_slice() {
+                suite.finish(current.take())?;
+
+                current = Some((
+                    ConformanceVector {
+                        name: name.to_string(),
+                        line: index + 1,
+                        instruction: 0,
+                        address: DEFAULT_ADDRESS,
+                        initial: VectorState::default(),
+                        expected: VectorState::default(),
+                    },
+                    false,
+                ));
+                continue;
+            }
+
+            if fields.is_empty() || fields[0].starts_with(';') {
+                continue;
+            }
+
+            let (vector, has_instruction) = current.as_mut().ok_or(malformed.clone())?;
+
+            match fields.as_slice() {
+                ["instruction", word] => {
+                    vector.instruction = parse_hex(word).ok_or(malformed)?;
+                    *has_instruction = true;
+                }
+
+                ["address", address] => {
+                    vector.address = parse_hex(address).ok_or(malformed)?;
+                }
+
+                ["set", location @ ..] => {
+                    // the PC is set from the vector's address
+                    if matches!(location, ["r15", ..]) {
+                        return Err(malformed);
+                    }
+
+                    vector.initial.parse(location).ok_or(malformed)?;
+                }
+
+                ["expect", location @ ..] => vector.expected.parse(location).ok_or(malformed)?,
+
+                _ => return Err(malformed),
+            }
+        }
+
+        suite.finish(current)?;
+
+        Ok(suite)
+    }
+
+    /// runs every vector, each on a new emulator
+    pub fn run(&self) -> ConformanceReport {
+        let mut report = ConformanceReport::default();
+
+        for vector in &self.vectors {
+            match vector.run() {
+                Ok(()) => report.passed += 1,
+                Err(kind) => report.failures.push(VectorFailure {
+                    name: vector.name.clone(),
+                    line: vector.line,
+                    kind,
+                }),
+            }
+        }
+
+        report
+    }
+
+    fn finish(&mut self, vector: Option<(ConformanceVector, bool)>) -> Result<(), VectorError> {
+        match vector {
+            Some((vector, true)) => self.vectors.push(vector),
+            Some((vector, false)) => {
+                return Err(VectorError::MissingInstruction {
+                    vector: vector.name,
+                })
+            }
+            None => {}
+        }
+
+        Ok(())
+    }
+}
+
+impl ConformanceVector {
+    /// executes the instruction on a new emulator, and checks the state after it
+    pub fn run(&self) -> Result<(), VectorFailureKind> {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut emulator = Emulator::new();
+
+            emulator.write_word(self.address as usize, self.instruction);
+            emulator.registers[15] = self.address;
+
+            for (register, value) in &self.initial.registers {
+                emulator.registers[*register] = *value;
+            }
+
+            if let Some(cpsr) = self.initial.cpsr {
+                emulator.cpsr = Psr::from_bits(cpsr);
+            }
+
+            for (address, value) in &self.initial.memory {
+                emulator.write_word(*address as usize, *value);
+            }
+
+            emulator.step()?;
+
+            Ok(self.mismatches(&emulator))
+        }));
+
+        match result {
+            Ok(Ok(mismatches)) if mismatches.is_empty() => Ok(()),
+            Ok(Ok(mismatches)) => Err(VectorFailureKind::Mismatches(mismatches)),
+            Ok(Err(error)) => Err(VectorFailureKind::Error(error)),
+            Err(payload) => Err(VectorFailureKind::Panicked(
+                payload
+                    .downcast_ref::<&str>()
+                    .map(|message| message.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_default(),
+            )),
+        }
+    }
+
+    /// the values in the emulator that differ from the expected state
+    fn mismatches(&self, emulator: &Emulator) -> Vec<Mismatch> {
+        let registers = self.expected.registers.iter().map(|(register, value)| {
+            (
+                StateLocation::Register(*register),
+                *value,
+                emulator.registers[*register],
+            )
+        });
+
+        let cpsr = self
+            .expected
+            .cpsr
+            .map(|value| (StateLocation::Cpsr, value, emulator.cpsr.bits()));
+
+        let memory = self.expected.memory.iter().map(|(address, value)| {
+            (
+                StateLocation::Memory(*address),
+                *value,
+                emulator.read_word(*address as usize),
+            )
+        });
+
+        registers
+            .chain(cpsr)
+            .chain(memory)
+            .filter(|(_, expected, actual)| expected != actual)
+            .map(|(location, expected, actual)| Mismatch {
+                location,
+                expected,
+                actual,
+            })
+            .collect()
+    }
+}
+
+impl VectorState {
+    /// reads a location and its value, like `r0 0x00000001` or `memory 0x1000 0x00000007`
+    fn parse(&mut self, fields: &[&str]) -> Option<()> {
+        match fields {
+            ["cpsr", value] => self.cpsr = Some(parse_hex(value)?),
+            ["memory", address, value] => {
+                self.memory.insert(parse_hex(address)?, parse_hex(value)?);
+            }
+            [register, value] => {
+                let register = register.strip_prefix('r')?.parse().ok()?;
+
+                if register > 15 {
+                    return None;
+                }
+
+                self.registers.insert(register, parse_hex(value)?);
+            }
+            _ => return None,
+        }
+
+        Some(())
+    }
+}
+
+impl ConformanceReport {
+    /// whether every vector passed
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl Display for ConformanceReport {
+    /// writes each failure, then the number of vectors that passed
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for failure in &self.failures {
+            write!(f, "{} (line {}): ", failure.name, failure.line)?;
+
+            match &failure.kind {
+                VectorFailureKind::Mismatches(mismatches) => {
+                    writeln!(f, "mismatched state")?;
+
+                    for mismatch in mismatches {
+                        writeln!(f, "    {mismatch}")?;
+                    }
+                }
+                VectorFailureKind::Error(error) => writeln!(f, "stopped with {error:?}")?,
+                VectorFailureKind::Panicked(message) => writeln!(f, "panicked: {message}")?,
+            }
+        }
+
+        write!(f, "{} passed, {} failed", self.passed, self.failures.len())
+    }
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.location {
+            StateLocation::Register(register) => write!(f, "r{register}")?,
+            StateLocation::Cpsr => write!(f, "cpsr")?,
+            StateLocation::Memory(address) => write!(f, "memory {address:#010X}")?,
+        }
+
+        write!(
+            f,
+            ": expected {:#010X}, got {:#010X}",
+            self.expected, self.actual
+        )
+    }
+}
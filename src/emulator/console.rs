@@ -0,0 +1,193 @@
+use std::{collections::VecDeque, io::Write, ops::Range};
+
+use super::Emulator;
+
+/// where the console SVCs write, along with everything they have written, see [`Emulator::mount_console`]
+pub(super) struct Console {
+    sink: Box<dyn Write + Send>,
+    output: ConsoleOutput,
+    /// the input sent by the host that the program hasn't read yet
+    input: VecDeque<u8>,
+}
+
+/// everything printed by the program, with the instruction count at which each piece was printed
+///
+/// This lets tests check the order of the output, e.g. that a prompt was printed before the answer.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ConsoleOutput {
+    bytes: Vec<u8>,
+    chunks: Vec<OutputChunk>,
+}
+
+/// the bytes printed by one SVC
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputChunk {
+    /// the offsets of the bytes in [`ConsoleOutput::bytes`]
+    pub bytes: Range<usize>,
+    /// the number of instructions executed when they were printed, including the SVC that printed them
+    pub cycles: u64,
+}
+
+impl ConsoleOutput {
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// the output as text, with any invalid UTF-8 replaced
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.bytes).into_owned()
+    }
+
+    /// the pieces of output, in the order they were printed
+    pub fn chunks(&self) -> &[OutputChunk] {
+        &self.chunks
+    }
+
+    /// the number of instructions executed when the byte at `offset` was printed
+    pub fn cycles_at(&self, offset: usize) -> Option<u64> {
+        let index = self
+            .chunks
+            .partition_point(|chunk| chunk.bytes.end <= offset);
+
+        self.chunks
+            .get(index)
+            .filter(|chunk| chunk.bytes.contains(&offset))
+            .map(|chunk| chunk.cycles)
+    }
+
+    /// the offset of the first occurrence of `text` in the output, even if it was printed by several SVCs
+    pub fn find(&self, text: &str) -> Option<usize> {
+        self.find_from(0, text)
+    }
+
+    /// the offset of the first occurrence of `text` that starts at or after `offset`
+    pub fn find_from(&self, offset: usize, text: &str) -> Option<usize> {
+        let text = text.as_bytes();
+        let bytes = self.bytes.get(offset..)?;
+
+        if text.is_empty() {
+            return Some(offset);
+        }
+
+        bytes
+            .windows(text.len())
+            .position(|window| window == text)
+            .map(|position| offset + position)
+    }
+
+    /// whether `first` was printed before `second` started, which is false if either wasn't printed
+    pub fn printed_before(&self, first: &str, second: &str) -> bool {
+        match (self.find(first), self.find(second)) {
+            (Some(first_offset), Some(second_offset)) => {
+                first_offset + first.len() <= second_offset
+            }
+            _ => false,
+        }
+    }
+
+    fn push(&mut self, bytes: &[u8], cycles: u64) {
+        let start = self.bytes.len();
+
+        self.bytes.extend_from_slice(bytes);
+        self.chunks.push(OutputChunk {
+            bytes: start..self.bytes.len(),
+            cycles,
+        });
+    }
+}
+
+impl Emulator {
+    /// gives the program the input and output SVCs from the table of system functions, which print to `sink` (e.g. [`std::io::stdout`]) and are also kept in [`Emulator::output`]
+    ///
+    /// | SVC | Operation |
+    /// | --- | --------- |
+    /// | `0` | prints the character in R0 |
+    /// | `1` | reads a character sent by [`Emulator::send_input`] into R0, waiting if there isn't one |
+    /// | `3` | prints the null-terminated string at the address in R0 |
+    /// | `4` | prints the signed number in R0, in decimal |
+    ///
+    /// Use [`std::io::sink`] to only keep the output. Errors writing to `sink` are ignored, as the program can't handle them, but the output is still kept.
+    pub fn mount_console(&mut self, sink: impl Write + Send + 'static) {
+        self.console = Some(Console {
+            sink: Box::new(sink),
+            output: ConsoleOutput::default(),
+            input: VecDeque::new(),
+        });
+
+        self.register_svc(0, |emulator, _| {
+            emulator.print(&[emulator.registers[0] as u8]);
+        });
+
+        self.register_svc(1, |emulator, _| match emulator.read_input() {
+            Some(byte) => emulator.registers[0] = byte as u32,
+            None => emulator.wait(),
+        });
+
+        self.register_svc(3, |emulator, _| {
+            let string = emulator.c_string(emulator.registers[0]);
+
+            emulator.print(&string);
+        });
+
+        self.register_svc(4, |emulator, _| {
+            let number = emulator.registers[0] as i32;
+
+            emulator.print(number.to_string().as_bytes());
+        });
+    }
+
+    /// takes the next byte of input sent to the console, if there is one
+    pub(super) fn read_input(&mut self) -> Option<u8> {
+        self.console
+            .as_mut()
+            .and_then(|console| console.input.pop_front())
+    }
+
+    /// the bytes of the null-terminated string at `address`, excluding the null
+    pub(super) fn c_string(&self, address: u32) -> Vec<u8> {
+        let start = address as usize;
+        let length = self.memory[start..]
+            .iter()
+            .position(|byte| *byte == 0)
+            .unwrap_or(self.memory.len() - start);
+
+        self.memory[start..start + length].to_vec()
+    }
+
+    /// everything printed since [`Emulator::mount_console`] was called
+    pub fn output(&self) -> Option<&ConsoleOutput> {
+        self.console.as_ref().map(|console| &console.output)
+    }
+
+    /// queues input for the program to read through the console, see [`Emulator::mount_console`]
+    ///
+    /// Nothing is queued if the console isn't mounted.
+    pub fn send_input(&mut self, bytes: &[u8]) {
+        if let Some(console) = &mut self.console {
+            console.input.extend(bytes);
+        }
+    }
+
+    /// whether there is input that the program hasn't read yet
+    pub(super) fn has_input(&self) -> bool {
+        self.pending_input() > 0
+    }
+
+    /// the number of bytes of input that the program hasn't read yet
+    pub(super) fn pending_input(&self) -> usize {
+        self.console
+            .as_ref()
+            .map_or(0, |console| console.input.len())
+    }
+
+    /// prints through the console, so that SVC handlers registered by the host are captured in [`Emulator::output`] too
+    ///
+    /// Nothing is printed if the console isn't mounted.
+    pub fn print(&mut self, bytes: &[u8]) {
+        if let Some(console) = &mut self.console {
+            let _ = console.sink.write_all(bytes);
+
+            console.output.push(bytes, self.cycles);
+        }
+    }
+}
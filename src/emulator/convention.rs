@@ -0,0 +1,174 @@
+use std::fmt::Display;
+
+use crate::ir::{register_name, BranchKind, InstructionKind, RegisterList};
+
+use super::Emulator;
+
+/// the registers a called routine must leave as it found them, see [`Emulator::set_calling_convention`]
+///
+/// The default is the AAPCS, where a routine can change R0-R3 and R12 (and LR, to call other routines), but must restore R4-R11 and SP before it returns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallingConvention {
+    pub preserved: RegisterList,
+}
+
+/// a return from a routine that didn't restore all the registers the calling convention says it must
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConventionViolation {
+    /// the address of the BL that called the routine
+    pub call: u32,
+    /// the address of the routine
+    pub routine: u32,
+    /// the label at the start of the routine, if it is part of a loaded module
+    #[cfg(feature = "assembler")]
+    pub label: Option<String>,
+    /// each register that wasn't restored, with its value before the call and after the return, in order
+    pub registers: Vec<(u8, u32, u32)>,
+}
+
+/// a routine that has been called and hasn't returned yet
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct CallFrame {
+    call: u32,
+    routine: u32,
+    /// the values of the preserved registers when it was called
+    saved: Vec<(u8, u32)>,
+}
+
+impl Default for CallingConvention {
+    fn default() -> Self {
+        let mut preserved = RegisterList::from_range(4..=11);
+        preserved.insert(13);
+
+        CallingConvention { preserved }
+    }
+}
+
+impl CallingConvention {
+    pub fn new() -> CallingConvention {
+        CallingConvention::default()
+    }
+
+    /// a convention where routines must restore `preserved`, e.g. to also check R0-R3 for a course that doesn't allow routines to change their arguments
+    pub fn preserving(preserved: RegisterList) -> CallingConvention {
+        CallingConvention { preserved }
+    }
+}
+
+impl Display for ConventionViolation {
+    /// describes the registers that weren't restored, e.g. `sum (called from 0x00000004) didn't restore R4, which changed from 0x00000001 to 0x00000005`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        #[cfg(feature = "assembler")]
+        let routine = self
+            .label
+            .clone()
+            .unwrap_or_else(|| format!("{:#010X}", self.routine));
+        #[cfg(not(feature = "assembler"))]
+        let routine = format!("{:#010X}", self.routine);
+
+        let registers: Vec<String> = self
+            .registers
+            .iter()
+            .map(|(register, before, after)| {
+                format!(
+                    "{}, which changed from {before:#010X} to {after:#010X}",
+                    register_name(*register)
+                )
+            })
+            .collect();
+
+        write!(
+            f,
+            "{routine} (called from {:#010X}) didn't restore {}",
+            self.call,
+            registers.join(", or ")
+        )
+    }
+}
+
+impl Emulator {
+    /// starts checking that each routine called with BL or BLX restores the registers `convention` says it must before it returns, or stops if it is `None`
+    ///
+    /// A routine returns when the PC reaches the instruction after its call, so the check works however it returns, e.g. `MOV PC, LR`, `BX LR` or `LDMFD SP!, {R4, PC}`.
+    /// Setting it forgets the calls that haven't returned, and the violations found so far.
+    pub fn set_calling_convention(&mut self, convention: Option<CallingConvention>) {
+        self.calling_convention = convention.map(|convention| (convention, Vec::new()));
+        self.convention_violations.clear();
+    }
+
+    /// the returns that didn't restore the registers of the calling convention, in the order they happened
+    pub fn convention_violations(&self) -> &[ConventionViolation] {
+        &self.convention_violations
+    }
+
+    /// saves the preserved registers if the instruction at `address` calls a routine, before it is executed
+    pub(super) fn record_call(&mut self, address: u32, instruction: &InstructionKind) {
+        let Some((convention, frames)) = &mut self.calling_convention else {
+            return;
+        };
+
+        let calls = match instruction {
+            InstructionKind::Branch {
+                condition,
+                kind: BranchKind::BranchWithLink,
+                ..
+            } => self.cpsr.condition_passed(*condition),
+            InstructionKind::BranchWithLinkAndExchange { .. } => true,
+            _ => false,
+        };
+
+        let Some(routine) = instruction.branch_target(address).filter(|_| calls) else {
+            return;
+        };
+
+        frames.push(CallFrame {
+            call: address,
+            routine,
+            saved: convention
+                .preserved
+                .iter()
+                .map(|register| (register, self.registers[register]))
+                .collect(),
+        });
+    }
+
+    /// checks the preserved registers of the routine that has just returned, if the PC is back after its call
+    ///
+    /// The routine's frame may not be the latest, as a routine that never returned to its caller, e.g. because it ended with a tail call, leaves its frame behind, so those above it are dropped.
+    pub(super) fn check_return(&mut self) {
+        let Some((_, frames)) = &mut self.calling_convention else {
+            return;
+        };
+
+        let pc = self.registers[15];
+
+        let Some(index) = frames.iter().rposition(|frame| frame.call + 4 == pc) else {
+            return;
+        };
+
+        let frame = frames.swap_remove(index);
+        frames.truncate(index);
+
+        let registers: Vec<(u8, u32, u32)> = frame
+            .saved
+            .iter()
+            .filter(|(register, value)| self.registers[*register] != *value)
+            .map(|(register, value)| (*register, *value, self.registers[*register]))
+            .collect();
+
+        if registers.is_empty() {
+            return;
+        }
+
+        self.convention_violations.push(ConventionViolation {
+            call: frame.call,
+            routine: frame.routine,
+            #[cfg(feature = "assembler")]
+            label: self
+                .modules
+                .iter()
+                .find_map(|program| program.label_at(frame.routine as usize)),
+            registers,
+        });
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+
+#[cfg(feature = "assembler")]
+use crate::assembler::Program;
+use crate::ir::InstructionKind;
+
+use super::Emulator;
+
+/// the kinds of instruction that a [`CostTable`] gives a cost to, written in snake case in a cost file, e.g. `supervisor_call`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum InstructionClass {
+    /// e.g. `MOV`, `ADD` and `CMP`, along with `MRS`, `MSR` and `CLZ`
+    DataProcessing,
+    /// `MUL`, `MLA` and the long multiplies, e.g. `UMULL`
+    Multiply,
+    /// a load or store, which for `LDM` and `STM` is charged once for each register
+    Memory,
+    /// `B`, `BL`, `BLX` and `BX`
+    Branch,
+    SupervisorCall,
+}
+
+/// the cost of each class of instruction, for coursework that asks students to optimise a program against a cost function, see [`Emulator::set_cost_table`]
+///
+/// Every class costs a unit unless it is given another cost, so the default table counts instructions.
+/// A table can be read from a file with a line for each class, like `memory 3`, with [`CostTable::parse`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CostTable {
+    costs: BTreeMap<InstructionClass, u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CostTableError {
+    /// a line of a cost file isn't a class followed by its cost, with a one-based line number
+    Malformed { line: usize },
+    /// a line of a cost file names a class that doesn't exist
+    UnknownClass { line: usize },
+}
+
+/// the cost of the instructions stepped since the cost table was set
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CostReport {
+    pub total: u64,
+    pub by_class: BTreeMap<InstructionClass, u64>,
+    /// the cost of the instructions at each address
+    pub by_address: BTreeMap<u32, u64>,
+}
+
+/// the cost of the instructions in a routine, see [`CostReport::routines`]
+#[cfg(feature = "assembler")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoutineCost {
+    /// the address of the routine's first instruction
+    pub address: usize,
+    /// the label at the start of the routine, if there is one
+    pub label: Option<String>,
+    pub cost: u64,
+}
+
+impl InstructionClass {
+    pub fn of(instruction: &InstructionKind) -> InstructionClass {
+        match instruction {
+            InstructionKind::DataProcessing { .. }
+            | InstructionKind::MoveStatusToRegister { .. }
+            | InstructionKind::MoveToStatusRegister { .. }
+            | InstructionKind::CountLeadingZeros { .. } => InstructionClass::DataProcessing,
+            InstructionKind::Multiply { .. } | InstructionKind::MultiplyLong { .. } => {
+                InstructionClass::Multiply
+            }
+            InstructionKind::LoadStore { .. }
+            | InstructionKind::Preload { .. }
+            | InstructionKind::LoadStoreMultiple { .. } => InstructionClass::Memory,
+            InstructionKind::Branch { .. }
+            | InstructionKind::BranchWithLinkAndExchange { .. }
+            | InstructionKind::BranchAndExchange { .. } => InstructionClass::Branch,
+            InstructionKind::SuperVisorCall { .. } => InstructionClass::SupervisorCall,
+        }
+    }
+
+    fn parse(name: &str) -> Option<InstructionClass> {
+        match name {
+            "data_processing" => Some(InstructionClass::DataProcessing),
+            "multiply" => Some(InstructionClass::Multiply),
+            "memory" => Some(InstructionClass::Memory),
+            "branch" => Some(InstructionClass::Branch),
+            "supervisor_call" => Some(InstructionClass::SupervisorCall),
+            _ => None,
+        }
+    }
+}
+
+impl CostTable {
+    /// a table where every class costs a unit
+    pub fn new() -> CostTable {
+        CostTable::default()
+    }
+
+    pub fn cost(mut self, class: InstructionClass, units: u64) -> CostTable {
+        self.costs.insert(class, units);
+        self
+    }
+
+    /// the cost of an instruction of `class`
+    pub fn get(&self, class: InstructionClass) -> u64 {
+        self.costs.get(&class).copied().unwrap_or(1)
+    }
+
+    /// the cost of executing `instruction`, which is the same whether or not its condition passes
+    pub fn instruction(&self, instruction: &InstructionKind) -> u64 {
+        let cost = self.get(InstructionClass::of(instruction));
+
+        match instruction {
+            InstructionKind::LoadStoreMultiple { register_list, .. } => {
+                cost * register_list.count().max(1) as u64
+            }
+            _ => cost,
+        }
+    }
+
+    /// reads a cost file, where lines starting with `;` are comments
+    pub fn parse(text: &str) -> Result<CostTable, CostTableError> {
+        let mut table = CostTable::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let fields: Vec<&str> = line.split_whitespace().collect();
+
+            match fields.as_slice() {
+                [] => {}
+                [comment, ..] if comment.starts_with(';') => {}
+
+                [class, units] => {
+                    let class = InstructionClass::parse(class)
+                        .ok_or(CostTableError::UnknownClass { line: line_number })?;
+                    let units = units
+                        .parse()
+                        .map_err(|_| CostTableError::Malformed { line: line_number })?;
+
+                    table.costs.insert(class, units);
+                }
+
+                _ => return Err(CostTableError::Malformed { line: line_number }),
+            }
+        }
+
+        Ok(table)
+    }
+}
+
+impl CostReport {
+    /// the cost of each routine of a program, in order of address
+    ///
+    /// The routines are the entry point and the targets of BLs, and each runs until the next one starts, like [`Program::stack_usage`].
+    /// A routine's cost doesn't include the routines it calls.
+    #[cfg(feature = "assembler")]
+    pub fn routines(&self, program: &Program) -> Vec<RoutineCost> {
+        let starts = program.routine_starts();
+
+        starts
+            .iter()
+            .map(|&start| {
+                let end = starts
+                    .range(start + 1..)
+                    .next()
+                    .copied()
+                    .unwrap_or(usize::MAX);
+
+                RoutineCost {
+                    address: start,
+                    label: program.label_at(start),
+                    cost: self
+                        .by_address
+                        .range(start as u32..)
+                        .take_while(|(address, _)| (**address as usize) < end)
+                        .map(|(_, cost)| cost)
+                        .sum(),
+                }
+            })
+            .collect()
+    }
+}
+
+impl Emulator {
+    /// sets the cost of each class of instruction, and starts adding up the cost of the instructions stepped, or stops if it is `None`
+    ///
+    /// Setting it clears the costs so far, so each run can be measured on its own.
+    pub fn set_cost_table(&mut self, table: Option<CostTable>) {
+        self.cost = table.map(|table| (table, CostReport::default()));
+    }
+
+    /// the cost of the instructions stepped, if [`Emulator::set_cost_table`] is on
+    pub fn cost_report(&self) -> Option<&CostReport> {
+        self.cost.as_ref().map(|(_, report)| report)
+    }
+
+    /// adds the cost of the instruction at `address`, before it is executed
+    pub(super) fn record_cost(&mut self, address: u32, instruction: &InstructionKind) {
+        let Some((table, report)) = &mut self.cost else {
+            return;
+        };
+
+        let cost = table.instruction(instruction);
+
+        report.total += cost;
+        *report
+            .by_class
+            .entry(InstructionClass::of(instruction))
+            .or_default() += cost;
+        *report.by_address.entry(address).or_default() += cost;
+    }
+}
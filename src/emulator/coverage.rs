@@ -0,0 +1,66 @@
+use std::collections::BTreeMap;
+
+use crate::ir::{Condition, InstructionKind};
+
+use super::Emulator;
+
+/// how often the condition of a conditional instruction passed and failed
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ConditionCoverage {
+    pub passed: u64,
+    pub failed: u64,
+}
+
+impl ConditionCoverage {
+    /// whether both outcomes of the condition have been run
+    pub fn is_complete(&self) -> bool {
+        self.passed > 0 && self.failed > 0
+    }
+}
+
+impl Emulator {
+    /// sets whether to count how often the condition of each conditional instruction passes and fails, which is off by default
+    ///
+    /// Turning it on or off clears the counts.
+    pub fn set_condition_coverage(&mut self, track: bool) {
+        self.condition_coverage = track.then(BTreeMap::new);
+    }
+
+    /// the counts of each conditional instruction that has been stepped, by address, if [`Emulator::set_condition_coverage`] is on
+    pub fn condition_coverage(&self) -> Option<&BTreeMap<u32, ConditionCoverage>> {
+        self.condition_coverage.as_ref()
+    }
+
+    /// the addresses of the conditional instructions that have been stepped, but whose condition never passed, such as a branch that was never taken
+    ///
+    /// These are the comparisons that still need a test for their other outcome.
+    pub fn never_taken(&self) -> Vec<u32> {
+        self.condition_coverage
+            .iter()
+            .flatten()
+            .filter(|(_, coverage)| coverage.passed == 0)
+            .map(|(address, _)| *address)
+            .collect()
+    }
+
+    /// counts whether the condition of the instruction at `address` passes, before it is executed
+    pub(super) fn record_condition(&mut self, address: u32, instruction: &InstructionKind) {
+        let Some(coverage) = &mut self.condition_coverage else {
+            return;
+        };
+
+        let condition = instruction.condition();
+
+        if matches!(condition, Condition::AL | Condition::NV) {
+            return;
+        }
+
+        let counts = coverage.entry(address).or_default();
+
+        if self.cpsr.condition_passed(condition) {
+            counts.passed += 1;
+        } else {
+            counts.failed += 1;
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use std::{collections::BTreeSet, fmt::Display};
+
+use crate::ir::register_name;
+
+use super::{Emulator, ExecutionError, RunStatus, StateLocation, PAGE_SIZE};
+
+/// the first difference between two emulators running the same program in lockstep, see [`Emulator::compare_with`]
+#[derive(Debug)]
+pub struct StateDivergence {
+    /// the number of instructions each had executed in the comparison when they differed
+    pub steps: u64,
+    /// the address of the instruction that made them differ, or `None` if they differed before the first one
+    pub address: Option<u32>,
+    pub kind: DivergenceKind,
+}
+
+#[derive(Debug)]
+pub enum DivergenceKind {
+    /// a register, the CPSR or a word of memory has a different value on each emulator
+    Value {
+        location: StateLocation,
+        first: u32,
+        second: u32,
+    },
+    /// one emulator finished or had to wait for input and the other didn't, or they finished differently, with `None` for one that is still running
+    Status {
+        first: Option<RunStatus>,
+        second: Option<RunStatus>,
+    },
+    /// one or both emulators stopped with an error, so they can't be stepped any further
+    Error {
+        first: Option<ExecutionError>,
+        second: Option<ExecutionError>,
+    },
+}
+
+impl Display for StateDivergence {
+    /// describes the difference, e.g. `after 3 instructions, the instruction at 0x00000008 left R0 as 0x00000005 on the first emulator and 0x00000004 on the second`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.address {
+            Some(address) => write!(
+                f,
+                "after {} instruction{}, the instruction at {address:#010X} ",
+                self.steps,
+                if self.steps == 1 { "" } else { "s" }
+            )?,
+            None => write!(f, "before the first instruction, ")?,
+        }
+
+        match &self.kind {
+            DivergenceKind::Value {
+                location,
+                first,
+                second,
+            } => {
+                let location = match location {
+                    StateLocation::Register(register) => register_name(*register),
+                    StateLocation::Cpsr => "CPSR".to_owned(),
+                    StateLocation::Memory(address) => format!("the word at {address:#010X}"),
+                };
+
+                if self.address.is_some() {
+                    write!(f, "left ")?;
+                }
+
+                write!(
+                    f,
+                    "{location} {} {first:#010X} on the first emulator and {second:#010X} on the second",
+                    if self.address.is_some() { "as" } else { "is" }
+                )
+            }
+            DivergenceKind::Status { first, second } => {
+                if self.address.is_some() {
+                    write!(f, "was the last, as ")?;
+                }
+
+                write!(
+                    f,
+                    "the first emulator {} and the second {}",
+                    status(first),
+                    status(second)
+                )
+            }
+            DivergenceKind::Error { first, second } => write!(
+                f,
+                "stopped the first emulator with {first:?} and the second with {second:?}"
+            ),
+        }
+    }
+}
+
+impl Emulator {
+    /// steps this emulator and `other` together, comparing their registers, CPSR and written memory before the first instruction and after each one, and returns the first difference
+    ///
+    /// Both should have the same program loaded, but can be set up differently, e.g. with different stacks, SVC handlers or register behaviours, to see how the setup changes a run.
+    /// This returns `None` if they agree until both finish or have to wait for input, or for `limit` instructions.
+    pub fn compare_with(&mut self, other: &mut Emulator, limit: u64) -> Option<StateDivergence> {
+        let mut steps = 0;
+        let mut address = None;
+
+        loop {
+            if let Some(kind) = self.state_difference(other) {
+                return Some(StateDivergence {
+                    steps,
+                    address,
+                    kind,
+                });
+            }
+
+            let (first, second) = (self.stop_status(), other.stop_status());
+
+            if first != second {
+                return Some(StateDivergence {
+                    steps,
+                    address,
+                    kind: DivergenceKind::Status { first, second },
+                });
+            }
+
+            if first.is_some() || steps >= limit {
+                return None;
+            }
+
+            address = Some(self.registers[15]);
+
+            let (first, second) = (self.step().err(), other.step().err());
+            steps += 1;
+
+            if first.is_some() || second.is_some() {
+                return Some(StateDivergence {
+                    steps,
+                    address,
+                    kind: DivergenceKind::Error { first, second },
+                });
+            }
+        }
+    }
+
+    /// how the program has stopped, or `None` if it can still be stepped
+    fn stop_status(&self) -> Option<RunStatus> {
+        if let Some(code) = self.exit_code {
+            Some(RunStatus::Exited(code))
+        } else if self.returned() {
+            Some(RunStatus::Returned)
+        } else if self.waiting {
+            Some(RunStatus::Waiting)
+        } else {
+            None
+        }
+    }
+
+    /// the first register, then the CPSR, then the first word of the memory written on either emulator, that differs
+    fn state_difference(&self, other: &Emulator) -> Option<DivergenceKind> {
+        let value = |location, first, second| {
+            (first != second).then_some(DivergenceKind::Value {
+                location,
+                first,
+                second,
+            })
+        };
+
+        let registers = (0..16).find_map(|register| {
+            value(
+                StateLocation::Register(register),
+                self.registers[register],
+                other.registers[register],
+            )
+        });
+
+        let pages: BTreeSet<usize> = self
+            .written_pages
+            .union(&other.written_pages)
+            .copied()
+            .collect();
+
+        registers
+            .or_else(|| value(StateLocation::Cpsr, self.cpsr.bits(), other.cpsr.bits()))
+            .or_else(|| {
+                pages.into_iter().find_map(|page| {
+                    let start = page * PAGE_SIZE;
+                    let end = (start + PAGE_SIZE).min(self.memory.len());
+
+                    let offset = self.memory[start..end]
+                        .iter()
+                        .zip(&other.memory[start..end])
+                        .position(|(first, second)| first != second)?;
+                    let address = (start + offset) & !0b11;
+
+                    value(
+                        StateLocation::Memory(address as u32),
+                        word_at(&self.memory, address),
+                        word_at(&other.memory, address),
+                    )
+                })
+            })
+    }
+}
+
+/// the big-endian word at `address`, with any bytes past the end of memory read as zero
+fn word_at(memory: &[u8], address: usize) -> u32 {
+    let mut word = [0; 4];
+
+    for (byte, value) in word.iter_mut().zip(&memory[address.min(memory.len())..]) {
+        *byte = *value;
+    }
+
+    u32::from_be_bytes(word)
+}
+
+/// how an emulator stopped, for the description of a divergence
+fn status(status: &Option<RunStatus>) -> String {
+    match status {
+        Some(RunStatus::Exited(code)) => format!("exited with {code}"),
+        Some(RunStatus::Returned) => "returned".to_owned(),
+        Some(RunStatus::Waiting) => "was waiting for input".to_owned(),
+        Some(RunStatus::LimitReached) | None => "was still running".to_owned(),
+    }
+}
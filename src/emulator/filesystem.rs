@@ -0,0 +1,359 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{self, ErrorKind, Read, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use super::Emulator;
+
+/// the value an SVC returns in R0 when a file operation fails
+const FAILURE: u32 = u32::MAX;
+
+/// a sandboxed filesystem for the emulated program, which can only access the files inside it
+///
+/// Files are kept in memory by default, or a directory on the host can be used with [`FileSystem::host`].
+pub struct FileSystem {
+    storage: Storage,
+    /// the files opened by the program, by handle
+    open_files: HashMap<u32, OpenFile>,
+    next_handle: u32,
+}
+
+enum Storage {
+    /// the contents of each file, by path
+    Memory(HashMap<String, Vec<u8>>),
+    /// the directory on the host that contains the files
+    Host(PathBuf),
+}
+
+enum OpenFile {
+    Memory {
+        path: String,
+        mode: FileMode,
+        /// the offset of the next byte to read or write
+        position: usize,
+    },
+    Host {
+        file: File,
+        mode: FileMode,
+    },
+}
+
+/// how a file is opened, which is passed to the open SVC in R1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileMode {
+    /// read an existing file from the start
+    Read = 0,
+    /// create a file, or empty an existing one, and write from the start
+    Write = 1,
+    /// create a file if it doesn't exist, and write to the end
+    Append = 2,
+}
+
+impl TryFrom<u32> for FileMode {
+    type Error = io::Error;
+
+    fn try_from(value: u32) -> io::Result<FileMode> {
+        match value {
+            0 => Ok(FileMode::Read),
+            1 => Ok(FileMode::Write),
+            2 => Ok(FileMode::Append),
+            _ => Err(ErrorKind::InvalidInput.into()),
+        }
+    }
+}
+
+/// the operations on a [`FileSystem`], in the order of their SVC numbers
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FileCall {
+    Open,
+    Read,
+    Write,
+    Close,
+}
+
+impl Default for FileSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileSystem {
+    /// an empty filesystem, kept in memory
+    pub fn new() -> FileSystem {
+        FileSystem {
+            storage: Storage::Memory(HashMap::new()),
+            open_files: HashMap::new(),
+            next_handle: 0,
+        }
+    }
+
+    /// a filesystem of the files in a directory on the host
+    pub fn host(root: impl Into<PathBuf>) -> FileSystem {
+        FileSystem {
+            storage: Storage::Host(root.into()),
+            ..FileSystem::new()
+        }
+    }
+
+    /// the contents of a file, e.g. to check the output of a program
+    pub fn read_file(&self, path: &str) -> io::Result<Vec<u8>> {
+        match &self.storage {
+            Storage::Memory(files) => files
+                .get(sandboxed(path)?)
+                .cloned()
+                .ok_or(ErrorKind::NotFound.into()),
+            Storage::Host(root) => fs::read(root.join(sandboxed(path)?)),
+        }
+    }
+
+    /// creates or replaces a file, e.g. to provide the input of a program
+    pub fn write_file(&mut self, path: &str, contents: &[u8]) -> io::Result<()> {
+        match &mut self.storage {
+            Storage::Memory(files) => {
+                files.insert(sandboxed(path)?.to_owned(), contents.to_vec());
+
+                Ok(())
+            }
+            Storage::Host(root) => fs::write(root.join(sandboxed(path)?), contents),
+        }
+    }
+
+    /// opens a file, returning its handle
+    fn open(&mut self, path: &str, mode: FileMode) -> io::Result<u32> {
+        let path = sandboxed(path)?;
+
+        let file = match &mut self.storage {
+            Storage::Memory(files) => {
+                let position = match mode {
+                    FileMode::Read => {
+                        if !files.contains_key(path) {
+                            return Err(ErrorKind::NotFound.into());
+                        }
+
+                        0
+                    }
+                    FileMode::Write => {
+                        files.insert(path.to_owned(), Vec::new());
+
+                        0
+                    }
+                    FileMode::Append => files.entry(path.to_owned()).or_default().len(),
+                };
+
+                OpenFile::Memory {
+                    path: path.to_owned(),
+                    mode,
+                    position,
+                }
+            }
+
+            Storage::Host(root) => {
+                let file = match mode {
+                    FileMode::Read => File::open(root.join(path))?,
+                    FileMode::Write => File::create(root.join(path))?,
+                    FileMode::Append => OpenOptions::new()
+                        .append(true)
+                        .create(true)
+                        .open(root.join(path))?,
+                };
+
+                OpenFile::Host { file, mode }
+            }
+        };
+
+        let handle = self.next_handle;
+        self.next_handle += 1;
+
+        self.open_files.insert(handle, file);
+
+        Ok(handle)
+    }
+
+    /// reads up to `length` bytes from an open file
+    fn read(&mut self, handle: u32, length: usize) -> io::Result<Vec<u8>> {
+        match self.open_files.get_mut(&handle) {
+            Some(OpenFile::Memory {
+                path,
+                mode: FileMode::Read,
+                position,
+            }) => {
+                let contents = match &self.storage {
+                    Storage::Memory(files) => files.get(path.as_str()),
+                    Storage::Host(_) => None,
+                }
+                .ok_or(ErrorKind::NotFound)?;
+
+                let start = (*position).min(contents.len());
+                let end = (start + length).min(contents.len());
+
+                *position = end;
+
+                Ok(contents[start..end].to_vec())
+            }
+
+            Some(OpenFile::Host {
+                file,
+                mode: FileMode::Read,
+            }) => {
+                let mut buffer = Vec::with_capacity(length);
+                file.take(length as u64).read_to_end(&mut buffer)?;
+
+                Ok(buffer)
+            }
+
+            Some(_) => Err(ErrorKind::PermissionDenied.into()),
+            None => Err(ErrorKind::NotFound.into()),
+        }
+    }
+
+    /// writes bytes to an open file
+    fn write(&mut self, handle: u32, bytes: &[u8]) -> io::Result<()> {
+        match self.open_files.get_mut(&handle) {
+            Some(OpenFile::Memory {
+                mode: FileMode::Read,
+                ..
+            })
+            | Some(OpenFile::Host {
+                mode: FileMode::Read,
+                ..
+            }) => Err(ErrorKind::PermissionDenied.into()),
+
+            Some(OpenFile::Memory {
+                path,
+                mode,
+                position,
+            }) => {
+                let contents = match &mut self.storage {
+                    Storage::Memory(files) => files.get_mut(path.as_str()),
+                    Storage::Host(_) => None,
+                }
+                .ok_or(ErrorKind::NotFound)?;
+
+                // the file could have been changed through another handle since the last write
+                let start = match mode {
+                    FileMode::Append => contents.len(),
+                    _ => (*position).min(contents.len()),
+                };
+                let end = start + bytes.len();
+
+                if contents.len() < end {
+                    contents.resize(end, 0);
+                }
+                contents[start..end].copy_from_slice(bytes);
+
+                *position = end;
+
+                Ok(())
+            }
+
+            Some(OpenFile::Host { file, .. }) => file.write_all(bytes),
+
+            None => Err(ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn close(&mut self, handle: u32) -> io::Result<()> {
+        match self.open_files.remove(&handle) {
+            Some(_) => Ok(()),
+            None => Err(ErrorKind::NotFound.into()),
+        }
+    }
+}
+
+/// checks that a path stays inside the filesystem, i.e. it is relative and doesn't contain `..`
+fn sandboxed(path: &str) -> io::Result<&str> {
+    let valid = !path.is_empty()
+        && Path::new(path)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+
+    if valid {
+        Ok(path)
+    } else {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+}
+
+impl Emulator {
+    /// gives the program access to a filesystem through four SVCs, numbered from `first_svc`
+    ///
+    /// | SVC             | Operation | Arguments | Result in R0 |
+    /// | --------------- | --------- | --------- | ------------ |
+    /// | `first_svc`     | open      | R0: address of a null-terminated path, R1: [`FileMode`] | the handle |
+    /// | `first_svc + 1` | read      | R0: handle, R1: buffer address, R2: maximum length | the number of bytes read, which is 0 at the end of the file |
+    /// | `first_svc + 2` | write     | R0: handle, R1: buffer address, R2: length | the number of bytes written |
+    /// | `first_svc + 3` | close     | R0: handle | 0 |
+    ///
+    /// If an operation fails, R0 is set to -1.
+    pub fn mount(&mut self, filesystem: FileSystem, first_svc: u32) {
+        self.filesystem = Some(filesystem);
+
+        for (number, call) in (first_svc..).zip([
+            FileCall::Open,
+            FileCall::Read,
+            FileCall::Write,
+            FileCall::Close,
+        ]) {
+            self.register_svc(number, move |emulator, _| {
+                emulator.registers[0] = emulator.file_call(call).unwrap_or(FAILURE);
+            });
+        }
+    }
+
+    /// the mounted filesystem, see [`Emulator::mount`]
+    pub fn filesystem(&self) -> Option<&FileSystem> {
+        self.filesystem.as_ref()
+    }
+
+    pub fn filesystem_mut(&mut self) -> Option<&mut FileSystem> {
+        self.filesystem.as_mut()
+    }
+
+    /// performs a file operation with the arguments in the registers, returning the result for R0
+    fn file_call(&mut self, call: FileCall) -> io::Result<u32> {
+        let filesystem = self.filesystem.as_mut().ok_or(ErrorKind::NotFound)?;
+
+        let [r0, r1, r2] = [self.registers[0], self.registers[1], self.registers[2]];
+
+        match call {
+            FileCall::Open => {
+                let path = &self.memory[r0 as usize..];
+                let length = path
+                    .iter()
+                    .position(|byte| *byte == 0)
+                    .ok_or(ErrorKind::InvalidInput)?;
+                let path =
+                    std::str::from_utf8(&path[..length]).map_err(|_| ErrorKind::InvalidInput)?;
+
+                filesystem.open(path, FileMode::try_from(r1)?)
+            }
+
+            FileCall::Read => {
+                let bytes = filesystem.read(r0, r2 as usize)?;
+
+                if (r1 as usize).saturating_add(bytes.len()) > self.memory.len() {
+                    return Err(ErrorKind::InvalidInput.into());
+                }
+
+                self.write_memory(r1 as usize, &bytes);
+
+                Ok(bytes.len() as u32)
+            }
+
+            FileCall::Write => {
+                let bytes = self
+                    .memory
+                    .get(r1 as usize..r1 as usize + r2 as usize)
+                    .ok_or(ErrorKind::InvalidInput)?;
+
+                filesystem.write(r0, bytes)?;
+
+                Ok(r2)
+            }
+
+            FileCall::Close => filesystem.close(r0).map(|_| 0),
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use std::fmt::Display;
+
+use super::Emulator;
+
+/// a condition flag of the CPSR
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flag {
+    N,
+    Z,
+    C,
+    V,
+}
+
+/// a flag set by the last instruction, along with why it has its value, see [`Emulator::set_explain_flags`]
+///
+/// It is written like `C set: borrow did not occur in SUB 0x00000005 - 0x00000003`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlagChange {
+    pub flag: Flag,
+    pub value: bool,
+    /// whether the instruction changed the flag, rather than setting it to the value it already had
+    pub changed: bool,
+    pub reason: String,
+}
+
+/// whether an arithmetic instruction adds or subtracts, which decides what its C and V flags mean
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Arithmetic {
+    Add,
+    Subtract,
+}
+
+impl Arithmetic {
+    /// adds `b` and the carry to `a`, or subtracts `b` and the borrow (the inverse of the carry) from `a`
+    pub(super) fn calculate(self, (a, b): (u32, u32), carry_in: bool) -> u32 {
+        match self {
+            Arithmetic::Add => a.wrapping_add(b).wrapping_add(carry_in as u32),
+            Arithmetic::Subtract => a.wrapping_sub(b).wrapping_sub(!carry_in as u32),
+        }
+    }
+}
+
+impl Display for FlagChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = if self.value { "set" } else { "clear" };
+
+        write!(f, "{:?} {value}: {}", self.flag, self.reason)
+    }
+}
+
+impl Emulator {
+    /// sets whether each step records why it set the flags, for a tracer that teaches condition codes, which is off by default
+    pub fn set_explain_flags(&mut self, explain: bool) {
+        self.explain_flags = explain;
+        self.flag_changes.clear();
+    }
+
+    /// why the last step set each flag it set, in the order N, Z, C, V, if [`Emulator::set_explain_flags`] is on
+    pub fn flag_changes(&self) -> &[FlagChange] {
+        &self.flag_changes
+    }
+
+    /// sets the flags from the result of a calculation or comparison of `a` and `b`, with the carry in that [`Arithmetic::calculate`] was given
+    pub(super) fn set_arithmetic_flags(
+        &mut self,
+        mnemonic: &str,
+        arithmetic: Arithmetic,
+        (a, b): (u32, u32),
+        carry_in: bool,
+        result: u32,
+    ) {
+        let (unsigned, signed) = match arithmetic {
+            Arithmetic::Add => (
+                a as i64 + b as i64 + carry_in as i64,
+                a as i32 as i64 + b as i32 as i64 + carry_in as i64,
+            ),
+            Arithmetic::Subtract => (
+                a as i64 - b as i64 - !carry_in as i64,
+                a as i32 as i64 - b as i32 as i64 - !carry_in as i64,
+            ),
+        };
+
+        // the carry is set when an addition overflows, or a subtraction doesn't borrow
+        let carry = match arithmetic {
+            Arithmetic::Add => unsigned > u32::MAX as i64,
+            Arithmetic::Subtract => unsigned >= 0,
+        };
+        let overflow = i32::try_from(signed).is_err();
+
+        // the carry or borrow taken in, if it changes the result
+        let carried = match (arithmetic, carry_in) {
+            (Arithmetic::Add, true) => " + 1",
+            (Arithmetic::Subtract, false) => " - 1",
+            _ => "",
+        };
+
+        self.set_result_flags(result);
+        self.set_flag(Flag::C, carry, || {
+            let operation = format!(
+                "{mnemonic} {a:#010X} {} {b:#010X}{carried}",
+                match arithmetic {
+                    Arithmetic::Add => "+",
+                    Arithmetic::Subtract => "-",
+                }
+            );
+
+            match (arithmetic, carry) {
+                (Arithmetic::Add, true) => format!("unsigned overflow in {operation}"),
+                (Arithmetic::Add, false) => format!("no unsigned overflow in {operation}"),
+                (Arithmetic::Subtract, true) => format!("borrow did not occur in {operation}"),
+                (Arithmetic::Subtract, false) => format!("borrow occurred in {operation}"),
+            }
+        });
+        self.set_flag(Flag::V, overflow, || {
+            let (verb, preposition) = match arithmetic {
+                Arithmetic::Add => ("adding", "+"),
+                Arithmetic::Subtract => ("subtracting", "-"),
+            };
+            let overflow = if overflow {
+                "signed overflow"
+            } else {
+                "no signed overflow"
+            };
+
+            format!("{overflow} {verb} {a:#010X} {preposition} {b:#010X}{carried} in {mnemonic}")
+        });
+    }
+
+    /// sets the flags from the result of a MOV, MVN, TST or TEQ, where C is the carry out of the shifter operand
+    pub(super) fn set_logical_flags(&mut self, result: u32, shifter_carry: bool) {
+        self.set_result_flags(result);
+        self.set_flag(Flag::C, shifter_carry, || {
+            format!(
+                "the carry out of the shifter operand was {}",
+                shifter_carry as u8
+            )
+        });
+    }
+
+    /// sets N and Z from a result, which is all a MULS or MLAS sets
+    pub(super) fn set_result_flags(&mut self, result: u32) {
+        let negative = (result as i32) < 0;
+
+        self.set_flag(Flag::N, negative, || match negative {
+            true => format!("bit 31 of the result {result:#010X} is set, so it is negative"),
+            false => format!("bit 31 of the result {result:#010X} is clear"),
+        });
+        self.set_flag(Flag::Z, result == 0, || match result {
+            0 => "the result is zero".to_owned(),
+            _ => format!("the result {result:#010X} isn't zero"),
+        });
+    }
+
+    /// sets N and Z from the 64-bit result of a long multiply, like UMULLS
+    pub(super) fn set_long_result_flags(&mut self, result: u64) {
+        let negative = (result as i64) < 0;
+
+        self.set_flag(Flag::N, negative, || match negative {
+            true => format!("bit 63 of the result {result:#018X} is set, so it is negative"),
+            false => format!("bit 63 of the result {result:#018X} is clear"),
+        });
+        self.set_flag(Flag::Z, result == 0, || match result {
+            0 => "the result is zero".to_owned(),
+            _ => format!("the result {result:#018X} isn't zero"),
+        });
+    }
+
+    fn set_flag(&mut self, flag: Flag, value: bool, reason: impl FnOnce() -> String) {
+        let previous = match flag {
+            Flag::N => self.cpsr.n(),
+            Flag::Z => self.cpsr.z(),
+            Flag::C => self.cpsr.c(),
+            Flag::V => self.cpsr.v(),
+        };
+
+        match flag {
+            Flag::N => self.cpsr.set_n(value),
+            Flag::Z => self.cpsr.set_z(value),
+            Flag::C => self.cpsr.set_c(value),
+            Flag::V => self.cpsr.set_v(value),
+        }
+
+        if self.explain_flags {
+            self.flag_changes.push(FlagChange {
+                flag,
+                value,
+                changed: value != previous,
+                reason: reason(),
+            });
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use std::{collections::BTreeMap, ops::Range};
+
+#[cfg(feature = "assembler")]
+use crate::assembler::Program;
+
+use super::Emulator;
+
+/// the value the free SVC returns in R0 when the address wasn't allocated
+const FAILURE: u32 = u32::MAX;
+
+/// a region of memory for the allocation SVCs, so programs can build linked lists and trees without writing an allocator first
+///
+/// Blocks are allocated first-fit from the gaps between the allocated blocks, and are word aligned.
+/// Freeing a block leaves a gap that later allocations can reuse.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heap {
+    region: Range<u32>,
+    /// the size of each allocated block, by address
+    allocated: BTreeMap<u32, u32>,
+}
+
+impl Heap {
+    /// a heap that allocates from `region`, which is shrunk to whole words
+    pub fn new(region: Range<u32>) -> Heap {
+        let start = region.start.next_multiple_of(4);
+
+        Heap {
+            region: start..(region.end & !0b11).max(start),
+            allocated: BTreeMap::new(),
+        }
+    }
+
+    /// a heap of `size` bytes starting at the first word after the program's last byte, after its data
+    #[cfg(feature = "assembler")]
+    pub fn after(program: &Program, size: u32) -> Heap {
+        let end = program
+            .regions()
+            .last()
+            .map_or(0, |region| region.end as u32);
+        let start = end.next_multiple_of(4);
+
+        Heap::new(start..start.saturating_add(size))
+    }
+
+    /// the memory the heap allocates from
+    pub fn region(&self) -> Range<u32> {
+        self.region.clone()
+    }
+
+    /// the address and size of each block that hasn't been freed, in order of address, e.g. to check for leaks at the end of a run
+    pub fn allocations(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        self.allocated
+            .iter()
+            .map(|(address, size)| (*address, *size))
+    }
+
+    /// the bytes allocated and not yet freed, not counting the padding to whole words
+    pub fn in_use(&self) -> u32 {
+        self.allocated.values().sum()
+    }
+
+    /// allocates a block of at least `size` bytes, returning its address, or `None` if there is no gap big enough or `size` is 0
+    pub fn allocate(&mut self, size: u32) -> Option<u32> {
+        if size == 0 {
+            return None;
+        }
+
+        let words = size.checked_next_multiple_of(4)?;
+        let mut start = self.region.start;
+
+        for (address, size) in self
+            .allocated
+            .iter()
+            .map(|(address, size)| (*address, *size))
+            .chain([(self.region.end, 0)])
+        {
+            if address - start >= words {
+                break;
+            }
+
+            start = address + size.next_multiple_of(4);
+        }
+
+        if self.region.end - start < words {
+            return None;
+        }
+
+        self.allocated.insert(start, size);
+
+        Some(start)
+    }
+
+    /// frees the block at `address`, returning false if no block was allocated there
+    pub fn free(&mut self, address: u32) -> bool {
+        self.allocated.remove(&address).is_some()
+    }
+}
+
+impl Emulator {
+    /// gives the program a heap through two SVCs, numbered from `first_svc`
+    ///
+    /// | SVC             | Operation | Arguments | Result in R0 |
+    /// | --------------- | --------- | --------- | ------------ |
+    /// | `first_svc`     | allocate  | R0: size in bytes | the address of the block, or 0 if there isn't room |
+    /// | `first_svc + 1` | free      | R0: address of a block, or 0 to do nothing | 0, or -1 if the address isn't an allocated block |
+    ///
+    /// Allocated memory isn't cleared, so it holds whatever was there before.
+    pub fn mount_heap(&mut self, heap: Heap, first_svc: u32) {
+        self.heap = Some(heap);
+
+        self.register_svc(first_svc, |emulator, _| {
+            let size = emulator.registers[0];
+
+            emulator.registers[0] = emulator
+                .heap
+                .as_mut()
+                .and_then(|heap| heap.allocate(size))
+                .unwrap_or(0);
+        });
+
+        self.register_svc(first_svc + 1, |emulator, _| {
+            let address = emulator.registers[0];
+
+            let freed = address == 0
+                || emulator
+                    .heap
+                    .as_mut()
+                    .is_some_and(|heap| heap.free(address));
+
+            emulator.registers[0] = if freed { 0 } else { FAILURE };
+        });
+    }
+
+    /// the mounted heap, see [`Emulator::mount_heap`]
+    pub fn heap(&self) -> Option<&Heap> {
+        self.heap.as_ref()
+    }
+}
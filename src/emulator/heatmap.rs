@@ -0,0 +1,94 @@
+use std::{collections::BTreeMap, ops::Range};
+
+use crate::ir::LoadStoreKind;
+
+use super::Emulator;
+
+/// how many times each address was read and written by the program, see [`Emulator::set_memory_heatmap`]
+///
+/// Only loads and stores made by instructions are counted, not fetches or memory written by the host, such as loading a program.
+/// A word access is counted at the address of the word.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MemoryHeatmap {
+    counts: BTreeMap<u32, AccessCounts>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AccessCounts {
+    pub reads: u64,
+    pub writes: u64,
+}
+
+/// the accesses to a block of addresses, for drawing one cell of a heatmap
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeatmapBucket {
+    pub addresses: Range<u32>,
+    pub counts: AccessCounts,
+}
+
+impl MemoryHeatmap {
+    /// the accesses to `address`
+    pub fn get(&self, address: u32) -> AccessCounts {
+        self.counts.get(&address).copied().unwrap_or_default()
+    }
+
+    /// the addresses that have been accessed, in order, and their accesses
+    pub fn iter(&self) -> impl Iterator<Item = (u32, AccessCounts)> + '_ {
+        self.counts
+            .iter()
+            .map(|(address, counts)| (*address, *counts))
+    }
+
+    /// splits `addresses` into buckets of `size` bytes, totalling the accesses in each, e.g. to draw a heatmap over a memory view with a cell per 16 bytes
+    ///
+    /// The last bucket is smaller if the addresses aren't a multiple of `size`.
+    pub fn buckets(&self, addresses: Range<u32>, size: u32) -> Vec<HeatmapBucket> {
+        let mut buckets: Vec<HeatmapBucket> = addresses
+            .clone()
+            .step_by(size as usize)
+            .map(|start| HeatmapBucket {
+                addresses: start..start.saturating_add(size).min(addresses.end),
+                counts: AccessCounts::default(),
+            })
+            .collect();
+
+        for (address, counts) in self.counts.range(addresses.clone()) {
+            let bucket = &mut buckets[((address - addresses.start) / size) as usize];
+
+            bucket.counts.reads += counts.reads;
+            bucket.counts.writes += counts.writes;
+        }
+
+        buckets
+    }
+
+    fn record(&mut self, address: u32, kind: &LoadStoreKind) {
+        let counts = self.counts.entry(address).or_default();
+
+        match kind {
+            LoadStoreKind::Load => counts.reads += 1,
+            LoadStoreKind::Store => counts.writes += 1,
+        }
+    }
+}
+
+impl Emulator {
+    /// sets whether to count the loads and stores to each address, which is off by default
+    ///
+    /// Turning it on or off clears the counts.
+    pub fn set_memory_heatmap(&mut self, track: bool) {
+        self.memory_heatmap = track.then(MemoryHeatmap::default);
+    }
+
+    /// the loads and stores to each address, if [`Emulator::set_memory_heatmap`] is on
+    pub fn memory_heatmap(&self) -> Option<&MemoryHeatmap> {
+        self.memory_heatmap.as_ref()
+    }
+
+    /// counts a load or store by an instruction
+    pub(super) fn record_access(&mut self, address: usize, kind: &LoadStoreKind) {
+        if let Some(heatmap) = &mut self.memory_heatmap {
+            heatmap.record(address as u32, kind);
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use super::Emulator;
+
+/// the address the PC is set to when an IRQ is taken
+const IRQ_VECTOR: u32 = 0x18;
+
+/// an IRQ that was taken, for measuring how long the program took to respond to it, see [`Emulator::raise_irq`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interrupt {
+    /// the number of instructions executed when the IRQ was raised
+    pub raised: u64,
+    /// the number of instructions executed when the exception was taken
+    pub taken: u64,
+    /// the address of the instruction that was interrupted, which the handler returns to
+    pub address: u32,
+}
+
+impl Interrupt {
+    /// the number of instructions executed between the IRQ being raised and taken
+    pub fn latency(&self) -> u64 {
+        self.taken - self.raised
+    }
+}
+
+impl Emulator {
+    /// sets how many instructions are executed after an IRQ is raised before the exception can be taken, which is 0 by default
+    ///
+    /// This models the time a device takes to signal the processor, so interrupt exercises can compare the response time of polling and interrupts.
+    pub fn set_irq_latency(&mut self, cycles: u64) {
+        self.irq_latency = cycles;
+    }
+
+    /// raises the IRQ line, e.g. from a timer or UART, unless it is already raised
+    ///
+    /// Once the latency has passed, and the I bit of the CPSR is clear, the IRQ exception is taken before the next instruction: LR is set to the address of that instruction plus 4, and the PC to the vector at `0x18`.
+    /// Processor modes aren't emulated, so there is no banked LR or SPSR, and the handler runs in the same mode.
+    pub fn raise_irq(&mut self) {
+        self.irq_raised.get_or_insert(self.cycles);
+    }
+
+    /// whether an IRQ has been raised, but not taken yet
+    pub fn irq_pending(&self) -> bool {
+        self.irq_raised.is_some()
+    }
+
+    /// the IRQs taken, in order
+    pub fn interrupts(&self) -> &[Interrupt] {
+        &self.interrupts
+    }
+
+    /// takes the IRQ exception before a step, if one is due
+    pub(super) fn take_irq(&mut self) {
+        let Some(raised) = self.irq_raised else {
+            return;
+        };
+
+        if self.cpsr.i() || self.cycles < raised + self.irq_latency {
+            return;
+        }
+
+        let address = self.registers[15];
+
+        self.registers[14] = address.wrapping_add(4);
+        self.registers[15] = IRQ_VECTOR;
+        self.jumped_from = None;
+        self.irq_raised = None;
+
+        self.interrupts.push(Interrupt {
+            raised,
+            taken: self.cycles,
+            address,
+        });
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+
+use crate::ir::RegisterList;
+
+use super::Emulator;
+
+/// a change the user made to the machine while debugging, rather than one made by the program, see [`Emulator::edit_memory`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManualEdit {
+    /// the number of instructions executed when it was made
+    pub cycles: u64,
+    pub kind: EditKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditKind {
+    /// bytes written from `address`, with their values before and after
+    Memory {
+        address: u32,
+        old: Vec<u8>,
+        new: Vec<u8>,
+    },
+    Register {
+        register: u8,
+        old: u32,
+        new: u32,
+    },
+}
+
+/// the manual edits, in the order they were made
+#[derive(Debug, Default)]
+pub(super) struct Journal {
+    edits: Vec<ManualEdit>,
+    /// the number of edits made to the machine as it is now, the rest are after the current instruction count and are made again when it is reached
+    applied: usize,
+}
+
+impl Emulator {
+    /// writes `bytes` to memory from `address` for the user, e.g. when they change a cell of a memory view, and journals the edit
+    ///
+    /// The bytes past the end of memory are ignored.
+    /// Edits are part of the run: going back before one with [`Emulator::run_to`] or [`Emulator::step_back`] undoes it, and going forwards again makes it again at the same instruction count.
+    /// Making an edit after going back forgets the edits that were made later, as the run has changed.
+    pub fn edit_memory(&mut self, address: u32, bytes: &[u8]) {
+        let start = (address as usize).min(self.memory.len());
+        let end = (start + bytes.len()).min(self.memory.len());
+
+        self.edit(EditKind::Memory {
+            address,
+            old: self.memory[start..end].to_vec(),
+            new: bytes[..end - start].to_vec(),
+        });
+    }
+
+    /// sets a register for the user, and journals the edit, see [`Emulator::edit_memory`]
+    pub fn edit_register(&mut self, register: u8, value: u32) {
+        self.edit(EditKind::Register {
+            register,
+            old: self.registers[register],
+            new: value,
+        });
+    }
+
+    /// the edits made by the user, in the order they were made, including those after the current instruction count that will be made again when it is reached
+    pub fn manual_edits(&self) -> &[ManualEdit] {
+        &self.journal.edits
+    }
+
+    /// the addresses of the bytes that still hold the value the user last gave them, by address, so a frontend can mark the cells the user changed
+    pub fn edited_memory(&self) -> BTreeMap<u32, u8> {
+        let mut edited = BTreeMap::new();
+
+        for edit in self.applied_edits() {
+            if let EditKind::Memory { address, new, .. } = &edit.kind {
+                edited.extend((*address..).zip(new.iter().copied()));
+            }
+        }
+
+        edited.retain(|address, value| self.memory[*address as usize] == *value);
+
+        edited
+    }
+
+    /// the registers that still hold the value the user last gave them, see [`Emulator::edited_memory`]
+    pub fn edited_registers(&self) -> RegisterList {
+        let mut edited = BTreeMap::new();
+
+        for edit in self.applied_edits() {
+            if let EditKind::Register { register, new, .. } = &edit.kind {
+                edited.insert(*register, *new);
+            }
+        }
+
+        let mut registers = RegisterList::default();
+
+        for (register, value) in edited {
+            if self.registers[register] == value {
+                registers.insert(register);
+            }
+        }
+
+        registers
+    }
+
+    /// makes the edits due at the current instruction count again, after going back to a checkpoint before them
+    pub(super) fn replay_edits(&mut self) {
+        while let Some(edit) = self
+            .journal
+            .edits
+            .get(self.journal.applied)
+            .filter(|edit| edit.cycles <= self.cycles)
+        {
+            let kind = edit.kind.clone();
+
+            self.apply_edit(&kind);
+            self.journal.applied += 1;
+        }
+    }
+
+    /// marks the edits after `cycles` as not made, after going back to a checkpoint at `cycles`
+    ///
+    /// Edits at `cycles` are made again too, as they may have been made after the checkpoint was taken, and making one again doesn't change anything.
+    pub(super) fn rewind_edits(&mut self, cycles: u64) {
+        self.journal.applied = self
+            .journal
+            .edits
+            .partition_point(|edit| edit.cycles < cycles);
+    }
+
+    /// forgets the edits, e.g. after loading a program
+    pub(super) fn clear_edits(&mut self) {
+        self.journal = Journal::default();
+    }
+
+    fn edit(&mut self, kind: EditKind) {
+        self.replay_edits();
+
+        self.journal.edits.truncate(self.journal.applied);
+        self.journal.edits.push(ManualEdit {
+            cycles: self.cycles,
+            kind: kind.clone(),
+        });
+        self.journal.applied += 1;
+
+        self.apply_edit(&kind);
+    }
+
+    fn apply_edit(&mut self, kind: &EditKind) {
+        match kind {
+            EditKind::Memory { address, new, .. } => self.write_memory(*address as usize, new),
+            EditKind::Register { register, new, .. } => {
+                self.registers[*register] = *new;
+
+                if *register == 15 {
+                    self.jumped_from = None;
+                }
+            }
+        }
+
+        self.publish();
+    }
+
+    fn applied_edits(&self) -> &[ManualEdit] {
+        &self.journal.edits[..self.journal.applied]
+    }
+}
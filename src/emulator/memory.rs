@@ -0,0 +1,86 @@
+use std::ops::Range;
+
+use crate::ir::ByteOrder;
+
+use super::Emulator;
+
+/// a read-only view of a range of memory as bytes, halfwords or words, for memory tables and hexdumps in frontends, see [`Emulator::memory`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MemoryView<'a> {
+    /// the address of the first byte
+    start: u32,
+    bytes: &'a [u8],
+    order: ByteOrder,
+}
+
+impl<'a> MemoryView<'a> {
+    /// reads halfwords and words in `order`, rather than the emulator's big-endian order, e.g. to show memory as a little-endian target would see it
+    pub fn with_byte_order(self, order: ByteOrder) -> MemoryView<'a> {
+        MemoryView { order, ..self }
+    }
+
+    /// the addresses in the view
+    pub fn range(&self) -> Range<u32> {
+        self.start..self.start + self.bytes.len() as u32
+    }
+
+    pub fn as_slice(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// each byte, along with its address
+    pub fn bytes(&self) -> impl Iterator<Item = (u32, u8)> + 'a {
+        self.range().zip(self.bytes.iter().copied())
+    }
+
+    /// each halfword that is entirely in the view, along with its address, starting at the first halfword aligned address
+    pub fn halfwords(&self) -> impl Iterator<Item = (u32, u16)> + 'a {
+        let order = self.order;
+
+        self.aligned(2)
+            .map(move |(address, bytes)| (address, order.halfword_from_bytes([bytes[0], bytes[1]])))
+    }
+
+    /// each word that is entirely in the view, along with its address, starting at the first word aligned address
+    pub fn words(&self) -> impl Iterator<Item = (u32, u32)> + 'a {
+        let order = self.order;
+
+        self.aligned(4).map(move |(address, bytes)| {
+            (address, order.word_from_bytes(bytes.try_into().unwrap()))
+        })
+    }
+
+    /// the view split into rows of `width` bytes, along with the address of each, for a hexdump
+    ///
+    /// The last row is shorter if the view isn't a multiple of `width`.
+    pub fn rows(&self, width: usize) -> impl Iterator<Item = (u32, &'a [u8])> + 'a {
+        self.range().step_by(width).zip(self.bytes.chunks(width))
+    }
+
+    /// the chunks of `size` bytes that start at addresses aligned to `size`
+    fn aligned(&self, size: u32) -> impl Iterator<Item = (u32, &'a [u8])> + 'a {
+        let skip = (size - self.start % size) % size;
+        let first = self.start.saturating_add(skip);
+
+        (first..self.range().end).step_by(size as usize).zip(
+            self.bytes
+                .get(skip as usize..)
+                .unwrap_or_default()
+                .chunks_exact(size as usize),
+        )
+    }
+}
+
+impl Emulator {
+    /// a view of the memory in `range`, which is cut short if it goes past the end of memory
+    pub fn memory(&self, range: Range<u32>) -> MemoryView<'_> {
+        let end = (range.end as usize).min(self.memory.len());
+        let start = (range.start as usize).min(end);
+
+        MemoryView {
+            start: start as u32,
+            bytes: &self.memory[start..end],
+            order: ByteOrder::BigEndian,
+        }
+    }
+}
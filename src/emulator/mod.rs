@@ -1,23 +1,111 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap},
     ops::{Index, IndexMut},
 };
 
+#[cfg(feature = "assembler")]
+use std::{ops::Range, sync::Arc};
+
 use crate::{
-    assembler::AssemblyError,
     decoder::{Bits, InvalidInstructionError},
-    encoder::Encode,
-    ir::{Condition, Imm, RegisterIdentifier, Rs, ShiftedRegister, ShifterOperandCode},
-    lexer::Lexer,
-    parser::{
-        AddressingOffset, AddressingOffsetValue, BranchKind, CalculationKind, ComparisonKind,
-        DataProcessingKind, InstructionKind, LoadStoreAddressCode, LoadStoreKind,
-        LoadStoreQuantity, MoveKind, Register, SetFlags, Shift,
+    ir::{
+        AddressingOffsetValue, BranchKind, CalculationKind, ComparisonKind, DataProcessingKind,
+        InstructionKind, LoadStoreKind, LoadStoreQuantity, MoveKind, MultipleAddressingMode,
+        OffsetMode, RegisterIdentifier, SetFlags, Shift, ShifterOperandCode, Sign, WriteBack,
     },
-    preprocessor::PreProcessResult,
-    resolver::{ResolvedStatement, SymbolTable},
 };
 
+#[cfg(feature = "disassembler")]
+use crate::disassembler::{Annotations, DisassemblyStyle};
+
+#[cfg(all(feature = "assembler", feature = "disassembler"))]
+use crate::disassembler::disassemble_data_with;
+
+#[cfg(feature = "assembler")]
+use crate::{
+    assembler::{assemble_instruction, AssemblyError, AssemblyWarning, Program, Region},
+    ir::ByteOrder,
+};
+
+#[cfg(feature = "assembler")]
+pub use benchmark::{Benchmark, BenchmarkInput, BenchmarkReport, BenchmarkRun};
+pub use branch::BranchDecision;
+use checkpoint::{Checkpoints, PAGE_SIZE};
+pub use clock::Clock;
+pub use conformance::{
+    ConformanceReport, ConformanceSuite, ConformanceVector, Mismatch, StateLocation, VectorError,
+    VectorFailure, VectorFailureKind, VectorState,
+};
+use console::Console;
+pub use console::{ConsoleOutput, OutputChunk};
+use convention::CallFrame;
+pub use convention::{CallingConvention, ConventionViolation};
+#[cfg(feature = "assembler")]
+pub use cost::RoutineCost;
+pub use cost::{CostReport, CostTable, CostTableError, InstructionClass};
+pub use coverage::ConditionCoverage;
+pub use differential::{DivergenceKind, StateDivergence};
+pub use filesystem::{FileMode, FileSystem};
+use flags::Arithmetic;
+pub use flags::{Flag, FlagChange};
+pub use heap::Heap;
+pub use heatmap::{AccessCounts, HeatmapBucket, MemoryHeatmap};
+pub use interrupt::Interrupt;
+use journal::Journal;
+pub use journal::{EditKind, ManualEdit};
+pub use memory::MemoryView;
+#[cfg(feature = "assembler")]
+pub use modules::{LoadError, SourceLine};
+pub use outcome::{RunOutcome, RunStatus};
+use peripheral::MappedRegister;
+pub use peripheral::{Board, Peripheral, PeripheralError, PeripheralRegister, RegisterBehavior};
+pub use psr::{ProcessorMode, Psr};
+pub use random::Random;
+pub use script::{Script, ScriptError, ScriptStep, Transcript, TranscriptEntry};
+#[cfg(feature = "assembler")]
+pub use session::{SavedWatch, Session, SessionError, Snapshot};
+pub use stack::{StackFill, StackPointer, StackSetup, HALT_ADDRESS};
+pub use svc_spec::{SvcBehavior, SvcDefinition, SvcSpec, SvcSpecError};
+pub use tick::TickHandler;
+pub use trace::{Divergence, Trace, TraceError, TraceStep};
+pub use view::MachineView;
+pub use watch::{WatchAction, WatchHandler, WatchHit};
+
+#[cfg(feature = "assembler")]
+mod benchmark;
+mod branch;
+mod checkpoint;
+mod clock;
+mod conformance;
+mod console;
+mod convention;
+mod cost;
+mod coverage;
+mod differential;
+mod filesystem;
+mod flags;
+mod heap;
+mod heatmap;
+mod interrupt;
+mod journal;
+mod memory;
+#[cfg(feature = "assembler")]
+mod modules;
+mod outcome;
+mod peripheral;
+mod psr;
+mod random;
+mod script;
+#[cfg(feature = "assembler")]
+mod session;
+mod stack;
+mod svc_spec;
+mod tick;
+mod trace;
+mod view;
+mod watch;
+
+#[cfg(feature = "assembler")]
 mod tests;
 
 struct Registers([u32; 16]);
@@ -42,96 +130,639 @@ impl<R: RegisterIdentifier> IndexMut<R> for Registers {
     }
 }
 
-struct Emulator {
+/// handles an SVC, given the emulator and the SVC number
+///
+/// Handlers must be [`Send`], so that the emulator can be moved to a worker thread.
+pub type SvcHandler = Box<dyn FnMut(&mut Emulator, u32) + Send>;
+
+/// the address the PC is set to when an SVC raises the SWI exception
+const SWI_VECTOR: u32 = 0x08;
+
+/// what happens when an SVC is executed with a number that has no registered handler
+#[derive(Default)]
+pub enum UndefinedSvc {
+    /// raise the SWI exception as the processor would, setting LR to the address of the next instruction and jumping to the vector at 0x08
+    ///
+    /// Processor modes aren't emulated, so there is no banked LR or SPSR, and the handler runs in the same mode.
+    RaiseException,
+    /// stop with an [`ExecutionError::UndefinedSvc`], leaving the PC at the SVC
+    #[default]
+    Halt,
+    /// call a handler for every number without its own
+    Fallback(SvcHandler),
+}
+
+/// a problem that stops the emulator executing an instruction
+#[derive(Debug)]
+pub enum ExecutionError {
+    InvalidInstruction(InvalidInstructionError),
+    /// an SVC with no registered handler was executed, and [`UndefinedSvc::Halt`] is set
+    UndefinedSvc {
+        address: u32,
+        number: u32,
+    },
+    #[cfg(feature = "assembler")]
+    AssemblyError(AssemblyError),
+    /// the PC is in bytes generated by a data directive, and executing data is set to fault
+    #[cfg(feature = "assembler")]
+    ExecutedData {
+        address: u32,
+        /// the jump that moved the PC there, or `None` if execution ran into the data from the instruction before it
+        jump: Option<Jump>,
+    },
+    /// an instruction stored to bytes generated by an instruction of the loaded program, and [`SelfModifyingCode::Fault`] is set
+    #[cfg(feature = "assembler")]
+    SelfModifyingCode {
+        /// the address of the storing instruction
+        address: u32,
+        /// the source line of the storing instruction, if it is part of a loaded module
+        source_line: Option<SourceLine>,
+        /// the address of the modified instruction
+        target: u32,
+        /// the last label at or before the modified instruction, e.g. the routine it is in
+        label: Option<String>,
+    },
+    /// the PC isn't word aligned, so it can't be the address of an instruction
+    MisalignedPc {
+        address: u32,
+        /// the jump that moved the PC there, or `None` if the PC was set directly
+        jump: Option<Jump>,
+    },
+    /// an instruction stored to a word watched with [`WatchAction::Halt`]
+    Watchpoint(WatchHit),
+    /// there is no checkpoint to go back to the instruction count `cycles` from, see [`Emulator::set_checkpoint_interval`]
+    NoCheckpoint {
+        cycles: u64,
+    },
+}
+
+/// a summary of a program loaded into the emulator, e.g. to show to the user
+#[cfg(feature = "assembler")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadReport {
+    pub entry_point: u32,
+    /// the ranges of memory written, in order of address
+    pub regions: Vec<Range<usize>>,
+    /// the number of labels and constants defined
+    pub symbol_count: usize,
+    pub warnings: Vec<AssemblyWarning>,
+}
+
+#[cfg(feature = "assembler")]
+impl LoadReport {
+    fn new(program: &Program) -> LoadReport {
+        LoadReport {
+            entry_point: program.entry_point as u32,
+            regions: program.regions(),
+            symbol_count: program.exports().count(),
+            warnings: program.warnings.clone(),
+        }
+    }
+}
+
+/// what happens when an instruction stores to bytes generated by an instruction of the loaded program
+#[cfg(feature = "assembler")]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum SelfModifyingCode {
+    /// the store happens, and the modified instructions are decoded again before they are executed
+    #[default]
+    Allow,
+    /// treat the program's instructions as read-only, stopping with an [`ExecutionError::SelfModifyingCode`] before storing, to catch accidental code overwrites
+    Fault,
+}
+
+/// an instruction that moved the PC somewhere other than the next instruction, e.g. a branch, which is used to explain how the PC reached a bad address
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Jump {
+    pub address: u32,
+    /// the source line of the instruction, if it is part of a loaded module
+    #[cfg(feature = "assembler")]
+    pub source_line: Option<SourceLine>,
+}
+
+impl From<InvalidInstructionError> for ExecutionError {
+    fn from(value: InvalidInstructionError) -> ExecutionError {
+        ExecutionError::InvalidInstruction(value)
+    }
+}
+
+#[cfg(feature = "assembler")]
+impl From<AssemblyError> for ExecutionError {
+    fn from(value: AssemblyError) -> ExecutionError {
+        ExecutionError::AssemblyError(value)
+    }
+}
+
+/// The state of the emulated machine, along with the program loaded into it.
+///
+/// The emulator is [`Send`], so it can be stepped by a worker thread. Its registers can be read from other threads through a [`MachineView`], and the loaded [`Program`] is shared rather than copied.
+pub struct Emulator {
     memory: Vec<u8>,
     registers: Registers,
-    cpsr: CPSR,
+    cpsr: Psr,
     entry_point: u32,
-    symbol_table: SymbolTable<u32>,
-    source_map: HashMap<usize, usize>,
+    /// the loaded programs, which aren't changed by running them, see [`Emulator::load_module`]
+    #[cfg(feature = "assembler")]
+    modules: Vec<Arc<Program>>,
+    /// names for disassembling the loaded program
+    #[cfg(feature = "disassembler")]
+    annotations: Annotations,
+    #[cfg(feature = "disassembler")]
+    disassembly_style: DisassemblyStyle,
+    /// the handlers for each SVC number
+    svc_handlers: HashMap<u32, SvcHandler>,
+    undefined_svc: UndefinedSvc,
+    /// the filesystem used by the file SVCs, see [`Emulator::mount`]
+    filesystem: Option<FileSystem>,
+    /// the heap used by the allocation SVCs, see [`Emulator::mount_heap`]
+    heap: Option<Heap>,
+    /// the number of instructions executed
+    cycles: u64,
+    /// whether the last SVC is waiting to be retried, see [`Emulator::wait`]
+    waiting: bool,
+    view: MachineView,
+    /// whether to stop if the PC is in bytes generated by a data directive
+    #[cfg(feature = "assembler")]
+    fault_on_data: bool,
+    /// the address of the last instruction executed, if it moved the PC somewhere other than the next instruction
+    jumped_from: Option<u32>,
+    /// the instructions that have already been decoded, by address, which are removed when their memory is written to
+    decoded: HashMap<u32, InstructionKind>,
+    #[cfg(feature = "assembler")]
+    self_modifying_code: SelfModifyingCode,
+    /// whether to record why each step sets the flags
+    explain_flags: bool,
+    flag_changes: Vec<FlagChange>,
+    /// how often each conditional instruction's condition passed and failed, if it is being tracked
+    condition_coverage: Option<BTreeMap<u32, ConditionCoverage>>,
+    /// the last branch stepped, and why it was or wasn't taken
+    last_branch: Option<BranchDecision>,
+    /// the address of the last stepped instruction that set the flags
+    flags_set_by: Option<u32>,
+    /// the loads and stores to each address, if they are being counted
+    memory_heatmap: Option<MemoryHeatmap>,
+    /// the cost of each class of instruction and of the instructions stepped, if it is being added up
+    cost: Option<(CostTable, CostReport)>,
+    /// the registers called routines must restore, and the calls that haven't returned yet, if they are being checked
+    calling_convention: Option<(CallingConvention, Vec<CallFrame>)>,
+    convention_violations: Vec<ConventionViolation>,
+    /// the checkpoints to go back to, if they are being taken
+    checkpoints: Option<Checkpoints>,
+    /// the changes made by the user while debugging
+    journal: Journal,
+    /// how SP and LR are set up when a program is loaded
+    stack: Option<StackSetup>,
+    /// the code the program exited with, once it has called the exit SVC
+    exit_code: Option<u32>,
+    /// where the output SVCs print, see [`Emulator::mount_console`]
+    console: Option<Console>,
+    /// the actions for stores to watched words, by word address
+    watchpoints: HashMap<u32, WatchAction>,
+    watch_log: Vec<WatchHit>,
+    /// a watchpoint that halted during the instruction being executed
+    watch_halt: Option<WatchHit>,
+    /// the number of instructions executed after an IRQ is raised before it can be taken
+    irq_latency: u64,
+    /// the number of instructions executed when the pending IRQ was raised
+    irq_raised: Option<u64>,
+    interrupts: Vec<Interrupt>,
+    /// the pages of memory that have been written, which a snapshot saves
+    written_pages: BTreeSet<usize>,
+    /// the memory-mapped registers of the mounted peripherals, by address
+    mapped: BTreeMap<u32, MappedRegister>,
+    tickers: Vec<TickHandler>,
+}
+
+/// why [`Emulator::poll_step`] returned control to the host
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Poll {
+    /// the instruction budget was used up
+    Yielded,
+    /// an SVC is waiting for something from the host, such as input, and will be retried by the next step
+    Waiting,
+    /// the program has finished, see [`Emulator::is_halted`]
+    Halted,
 }
 
-struct CPSR {
-    n: bool,
-    z: bool,
-    c: bool,
-    v: bool,
+impl Default for Emulator {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Emulator {
-    fn new() -> Emulator {
+    pub fn new() -> Emulator {
         Emulator {
             memory: vec![0; 0xFFFFFFFF],
             registers: Registers([0; 16]),
-            cpsr: CPSR { n: false, z: false, c: false, v: false },
+            cpsr: Psr::default(),
             entry_point: 0,
-            symbol_table: SymbolTable::new(),
-            source_map: HashMap::new(),
+            #[cfg(feature = "assembler")]
+            modules: Vec::new(),
+            #[cfg(feature = "disassembler")]
+            annotations: Annotations::default(),
+            #[cfg(feature = "disassembler")]
+            disassembly_style: DisassemblyStyle::default(),
+            svc_handlers: HashMap::new(),
+            undefined_svc: UndefinedSvc::default(),
+            filesystem: None,
+            heap: None,
+            cycles: 0,
+            waiting: false,
+            view: MachineView::new(),
+            #[cfg(feature = "assembler")]
+            fault_on_data: false,
+            jumped_from: None,
+            decoded: HashMap::new(),
+            #[cfg(feature = "assembler")]
+            self_modifying_code: SelfModifyingCode::default(),
+            explain_flags: false,
+            flag_changes: Vec::new(),
+            condition_coverage: None,
+            last_branch: None,
+            flags_set_by: None,
+            memory_heatmap: None,
+            cost: None,
+            calling_convention: None,
+            convention_violations: Vec::new(),
+            checkpoints: None,
+            journal: Journal::default(),
+            stack: None,
+            exit_code: None,
+            console: None,
+            watchpoints: HashMap::new(),
+            watch_log: Vec::new(),
+            watch_halt: None,
+            irq_latency: 0,
+            irq_raised: None,
+            interrupts: Vec::new(),
+            written_pages: BTreeSet::new(),
+            mapped: BTreeMap::new(),
+            tickers: Vec::new(),
         }
     }
 
-    pub fn assemble(&mut self, input: &str) -> Result<(), AssemblyError> {
-        let PreProcessResult {
-            entry_point,
-            statements,
-            symbol_table,
-            source_map,
-        } = Lexer::new(input).parse().preprocess()?;
+    /// the value of a register, where 13 is SP, 14 is LR and 15 is PC
+    pub fn register(&self, register: u8) -> u32 {
+        self.registers[register]
+    }
 
-        let symbol_table = symbol_table.resolve()?;
+    pub fn cpsr(&self) -> Psr {
+        self.cpsr
+    }
 
-        // write the statements to memory
-        for (mut address, statement) in statements {
-            let statement = statement.resolve(&symbol_table, address)?;
+    /// replaces the CPSR, e.g. to set the flags before stepping an instruction
+    pub fn set_cpsr(&mut self, cpsr: Psr) {
+        self.cpsr = cpsr;
 
-            match statement {
-                ResolvedStatement::Instructions(instructions) => {
-                    for instruction in instructions {
-                        for byte in instruction.encode().to_be_bytes() {
-                            self.memory[address] = byte;
-                            address += 1;
-                        }
-                    }
-                }
+        self.publish();
+    }
 
-                ResolvedStatement::Data(data) => {
-                    for byte in data {
-                        self.memory[address] = byte;
-                        address += 1;
-                    }
-                }
-            }
+    pub fn set_register(&mut self, register: u8, value: u32) {
+        self.registers[register] = value;
+
+        if register == 15 {
+            self.jumped_from = None;
+        }
+
+        self.publish();
+    }
+
+    /// calls `handler` when an SVC with `number` is executed, replacing any previous handler for it
+    pub fn register_svc(
+        &mut self,
+        number: u32,
+        handler: impl FnMut(&mut Emulator, u32) + Send + 'static,
+    ) {
+        self.svc_handlers.insert(number, Box::new(handler));
+    }
+
+    /// sets what happens when an SVC is executed with a number that has no registered handler, which halts by default
+    pub fn set_undefined_svc(&mut self, policy: UndefinedSvc) {
+        self.undefined_svc = policy;
+    }
+
+    /// writes a memory image (e.g. produced by a separate assembler) to memory starting at `address`, and sets the PC to the entry point
+    pub fn load_image(&mut self, address: usize, image: &[u8], entry_point: u32) {
+        self.write_memory(address, image);
+
+        self.registers[15] = entry_point;
+        self.jumped_from = None;
+        self.exit_code = None;
+
+        self.apply_stack();
+        self.restart_checkpoints();
+        self.publish();
+    }
+
+    /// assembles source and loads it, see [`Emulator::load`]
+    #[cfg(feature = "assembler")]
+    pub fn assemble(&mut self, input: &str) -> Result<LoadReport, AssemblyError> {
+        let program = Program::assemble(input)?;
+
+        Ok(self.load(program))
+    }
+
+    /// writes an assembled program to memory in place of any loaded modules, and sets the PC to its entry point
+    ///
+    /// The program can be shared with other threads by passing an [`Arc`], as the emulator only needs to read it.
+    /// The program's symbol table and source map stay available through [`Emulator::program`].
+    #[cfg(feature = "assembler")]
+    pub fn load(&mut self, program: impl Into<Arc<Program>>) -> LoadReport {
+        let program = program.into();
+
+        for statement in &program.statements {
+            let address = statement.address;
+
+            self.write_memory(address, &statement.bytes);
+        }
+
+        #[cfg(feature = "disassembler")]
+        {
+            self.annotations = program.annotations();
         }
 
         // set the PC to the entry point
-        self.registers[15] = entry_point as u32;
+        self.registers[15] = program.entry_point as u32;
+        self.jumped_from = None;
+        self.exit_code = None;
+
+        let report = LoadReport::new(&program);
+
+        self.modules = vec![program];
+
+        self.apply_stack();
+        self.restart_checkpoints();
+        self.publish();
+
+        report
+    }
+
+    /// the loaded program, which is the first module if there are several
+    #[cfg(feature = "assembler")]
+    pub fn program(&self) -> Option<&Arc<Program>> {
+        self.modules.first()
+    }
+
+    /// sets whether stepping stops with an [`ExecutionError::ExecutedData`] if the PC is in bytes generated by a data directive of the loaded program, rather than decoding the data as instructions
+    ///
+    /// This is off by default, so data is run as it would be on a processor, but turning it on catches a common mistake, e.g. forgetting to halt at the end of a program.
+    #[cfg(feature = "assembler")]
+    pub fn set_fault_on_data(&mut self, fault: bool) {
+        self.fault_on_data = fault;
+    }
+
+    /// sets what happens when an instruction stores to bytes generated by an instruction of the loaded program, which is allowed by default
+    #[cfg(feature = "assembler")]
+    pub fn set_self_modifying_code(&mut self, policy: SelfModifyingCode) {
+        self.self_modifying_code = policy;
+    }
+
+    /// sets the style of [`Emulator::disassemble`] and traces, e.g. to match the register names a course uses
+    #[cfg(feature = "disassembler")]
+    pub fn set_disassembly_style(&mut self, style: DisassemblyStyle) {
+        self.disassembly_style = style;
+    }
+
+    /// assembles and executes a single instruction, such as one typed into a REPL, without writing it to memory
+    ///
+    /// The instruction is executed as if it were at the address in the PC, and labels are resolved using the symbol table of the loaded program.
+    /// Unless the instruction writes to the PC (e.g. a branch), the PC is left unchanged afterwards, so a paused program can carry on where it was.
+    #[cfg(feature = "assembler")]
+    pub fn execute_source_line(&mut self, line: &str) -> Result<(), ExecutionError> {
+        let address = self.registers[15];
+
+        let instructions = assemble_instruction(line, &self.symbol_table(), address as usize)?;
+
+        for (i, instruction) in instructions.into_iter().enumerate() {
+            let instruction_address = address + 4 * i as u32;
+
+            // the PC is incremented before executing, as it is by step
+            let next_address = instruction_address + 4;
+            self.registers[15] = next_address;
+
+            let result = self.execute(instruction).and(self.watch_halted());
+
+            self.publish();
+
+            result?;
+
+            // the instruction branched, so the PC shouldn't be restored
+            if self.registers[15] != next_address {
+                return Ok(());
+            }
+        }
+
+        self.registers[15] = address;
+
+        self.publish();
+
+        Ok(())
+    }
+
+    /// assembles a single instruction and writes it to memory at `address`, overwriting whatever was there
+    ///
+    /// Labels are resolved using the symbol table of the loaded program, so the patched instruction can refer to them.
+    #[cfg(feature = "assembler")]
+    pub fn patch_instruction(&mut self, address: usize, source: &str) -> Result<(), AssemblyError> {
+        let bytes: Vec<u8> = assemble_instruction(source, &self.symbol_table(), address)?
+            .iter()
+            .flat_map(|instruction| instruction.encode().to_bytes(ByteOrder::BigEndian))
+            .collect();
+
+        self.write_memory(address, &bytes);
 
         Ok(())
     }
 
-    pub fn step(&mut self) -> Result<(), InvalidInstructionError> {
+    /// disassembles the instruction at `address`, annotating it with names from the loaded program, such as for a trace or listing
+    ///
+    /// Bytes generated by a data directive are written as data, e.g. `DEFB "Hell"`, rather than disassembled.
+    #[cfg(feature = "disassembler")]
+    pub fn disassemble(&self, address: usize) -> Result<String, InvalidInstructionError> {
+        #[cfg(feature = "assembler")]
+        if let Some(statement) = self
+            .statement_at(address)
+            .filter(|statement| statement.region == Region::Data)
+        {
+            let end = (address + 4).min(statement.address + statement.bytes.len());
+
+            return Ok(disassemble_data_with(
+                &self.memory[address..end],
+                &self.disassembly_style,
+            ));
+        }
+
+        Ok(self.fetch(address)?.disassemble_with(
+            address as u32,
+            &self.annotations,
+            &self.disassembly_style,
+        ))
+    }
+
+    /// executes the next instruction like [`Emulator::step`], returning its address and disassembly for a trace
+    ///
+    /// With [`Emulator::set_explain_flags`] on, [`Emulator::flag_changes`] then says why the instruction set each flag.
+    #[cfg(feature = "disassembler")]
+    pub fn trace_step(&mut self) -> Result<(u32, String), ExecutionError> {
+        let address = self.registers[15];
+        let disassembly = self.disassemble(address as usize)?;
+
+        self.step()?;
+
+        Ok((address, disassembly))
+    }
+
+    /// fetches and decodes the instruction at `address`
+    fn fetch(&self, address: usize) -> Result<InstructionKind, InvalidInstructionError> {
+        let instruction: [u8; 4] = self.memory[address..address + 4].try_into().unwrap(); // try_into converts the slice to a fixed size 4-byte array
+
+        InstructionKind::decode(&Bits(u32::from_be_bytes(instruction)))
+    }
+
+    pub fn step(&mut self) -> Result<(), ExecutionError> {
+        self.replay_edits();
+
+        if self.is_halted() {
+            return Ok(());
+        }
+
+        self.take_irq();
+
         // get the address from the PC
         let address = self.registers[15] as usize;
 
-        // fetch the instruction
-        let instruction: [u8; 4] = self.memory[address..address + 4].try_into().unwrap(); // try_into converts the slice to a fixed size 4-byte array
+        if !address.is_multiple_of(4) {
+            return Err(ExecutionError::MisalignedPc {
+                address: address as u32,
+                jump: self.last_jump(),
+            });
+        }
+
+        #[cfg(feature = "assembler")]
+        if self.fault_on_data && self.region(address) == Some(Region::Data) {
+            return Err(ExecutionError::ExecutedData {
+                address: address as u32,
+                jump: self.last_jump(),
+            });
+        }
+
+        // fetch and decode the instruction, unless it has already been decoded
+        let instruction = match self.decoded.get(&(address as u32)) {
+            Some(instruction) => Ok(instruction.clone()),
+            None => self.fetch(address).inspect(|instruction| {
+                self.decoded.insert(address as u32, instruction.clone());
+            }),
+        };
+
+        if let Ok(instruction) = &instruction {
+            self.record_condition(address as u32, instruction);
+            self.record_cost(address as u32, instruction);
+            self.record_branch(address as u32, instruction);
+            self.record_call(address as u32, instruction);
+        }
 
         // increment the PC
         // TODO: check branch implementation for this?
         self.registers[15] += 4;
 
-        // decode the instruction
-        let instruction = InstructionKind::decode(&Bits(u32::from_be_bytes(instruction)))?;
+        self.waiting = false;
+
+        let start = self.cycles;
 
         // execute the instruction
-        self.execute(instruction);
+        let result = instruction
+            .map_err(ExecutionError::from)
+            .and_then(|instruction| self.execute(instruction))
+            .and(self.watch_halted());
+
+        self.run_peripherals(start);
+        self.check_return();
 
         // check docs for cpsr etc, to get correct behaviours
 
+        self.jumped_from = (self.registers[15] as usize != address + 4).then_some(address as u32);
+
+        self.checkpoint_step();
+
+        // the view is updated even if the instruction failed, as the PC has moved
+        self.publish();
+
+        result
+    }
+
+    /// the last instruction executed, if it moved the PC somewhere other than the next instruction
+    fn last_jump(&self) -> Option<Jump> {
+        let address = self.jumped_from?;
+
+        Some(Jump {
+            address,
+            #[cfg(feature = "assembler")]
+            source_line: self.source_line(address as usize),
+        })
+    }
+
+    /// executes all the instructions generated by the source statement at the PC, such as both instructions of an ADRL, for source-level debugging
+    ///
+    /// Stepping stops when the PC leaves the statement, or returns to its start (e.g. a statement that branches to itself), or an SVC has to wait.
+    /// If the PC isn't in a statement of the loaded program, a single instruction is executed.
+    #[cfg(feature = "assembler")]
+    pub fn step_statement(&mut self) -> Result<(), ExecutionError> {
+        let start = self.registers[15] as usize;
+
+        let statement = self
+            .statement_at(start)
+            .map(|statement| statement.address..statement.address + statement.bytes.len());
+
+        self.step()?;
+
+        if let Some(statement) = statement {
+            while !self.waiting && !self.is_halted() {
+                let address = self.registers[15] as usize;
+
+                if address == statement.start || !statement.contains(&address) {
+                    break;
+                }
+
+                self.step()?;
+            }
+        }
+
         Ok(())
     }
 
-    fn execute(&mut self, instruction: InstructionKind) {
+    /// executes up to `budget` instructions, returning early if an SVC has to wait or the program has halted
+    ///
+    /// This lets hosts with an event loop, like browsers and GUIs, run long programs in slices without becoming unresponsive.
+    pub fn poll_step(&mut self, budget: usize) -> Result<Poll, ExecutionError> {
+        for _ in 0..budget {
+            if self.is_halted() {
+                return Ok(Poll::Halted);
+            }
+
+            self.step()?;
+
+            if self.waiting {
+                return Ok(Poll::Waiting);
+            }
+        }
+
+        Ok(Poll::Yielded)
+    }
+
+    /// called by an SVC handler that can't complete yet, e.g. because no input is available
+    ///
+    /// The PC is moved back to the SVC, so that it is executed again by the next step, and [`Emulator::poll_step`] returns [`Poll::Waiting`].
+    pub fn wait(&mut self) {
+        self.registers[15] -= 4;
+        self.waiting = true;
+    }
+
+    fn execute(&mut self, instruction: InstructionKind) -> Result<(), ExecutionError> {
+        // an instruction takes a cycle even if its condition fails
+        self.cycles += 1;
+        self.flag_changes.clear();
+
         match instruction {
             InstructionKind::Branch {
                 condition,
@@ -152,6 +783,28 @@ impl Emulator {
                 }
             }
 
+            InstructionKind::BranchWithLinkAndExchange { target, halfword } => {
+                self.registers[14] = self.registers[15];
+
+                let offset = target.get() << 2 | (halfword as i32) << 1;
+
+                self.registers[15] = ((self.registers[15] as i32) + offset) as u32;
+                self.cpsr.set_t(true);
+            }
+
+            InstructionKind::BranchAndExchange { condition, target } => {
+                if self.cpsr.condition_passed(condition) {
+                    let address = self.registers[target];
+
+                    // bit 0 of the address chooses the state rather than being part of it
+                    self.registers[15] = address & !1;
+                    self.cpsr.set_t(address & 1 == 1);
+                }
+            }
+
+            // there is no cache to load into
+            InstructionKind::Preload { .. } => (),
+
             InstructionKind::DataProcessing { condition, kind } => {
                 if self.cpsr.condition_passed(condition) {
                     match kind {
@@ -161,70 +814,76 @@ impl Emulator {
                             destination,
                             source,
                             shifter,
-                        } => match kind {
-                            CalculationKind::ADD => {
-                                let register_operand = self.registers[source];
-                                let (shifter_operand, _) = self.calculate_shifter(&shifter);
-                                let (result, carry) =
-                                    u32::carrying_add(register_operand, shifter_operand, false);
-
-                                self.registers[destination] = result;
-
-                                if let SetFlags::Set = set_flags {
-                                    self.cpsr.n = (result as i32) < 0;
-                                    self.cpsr.z = result == 0;
-                                    self.cpsr.c = carry;
-                                    self.cpsr.v =
-                                        match u32::checked_add(register_operand, shifter_operand) {
-                                            Some(_) => false,
-                                            None => true,
-                                        }
-                                }
-                            }
+                        } => {
+                            let register_operand = self.registers[source];
+                            let (shifter_operand, _) = self.calculate_shifter(&shifter);
+                            let carry = self.cpsr.c();
 
-                            CalculationKind::SUB => {
-                                let register_operand = self.registers[source];
-                                let (shifter_operand, _) = self.calculate_shifter(&shifter);
-                                let (result, borrow) =
-                                    u32::borrowing_sub(register_operand, shifter_operand, false);
-
-                                self.registers[destination] = result;
-
-                                if let SetFlags::Set = set_flags {
-                                    self.cpsr.n = (result as i32) < 0;
-                                    self.cpsr.z = result == 0;
-                                    self.cpsr.c = !borrow;
-                                    self.cpsr.v =
-                                        match u32::checked_sub(register_operand, shifter_operand) {
-                                            Some(_) => false,
-                                            None => true,
-                                        }
+                            let forward = (register_operand, shifter_operand);
+                            let reverse = (shifter_operand, register_operand);
+
+                            // the operands in the order they are calculated with, and the carry in, which is the inverse of the borrow for a subtraction
+                            let (mnemonic, arithmetic, operands, carry_in) = match kind {
+                                CalculationKind::ADD => ("ADD", Arithmetic::Add, forward, false),
+                                CalculationKind::ADC => ("ADC", Arithmetic::Add, forward, carry),
+                                CalculationKind::SUB => {
+                                    ("SUB", Arithmetic::Subtract, forward, true)
+                                }
+                                CalculationKind::SBC => {
+                                    ("SBC", Arithmetic::Subtract, forward, carry)
+                                }
+                                CalculationKind::RSB => {
+                                    ("RSB", Arithmetic::Subtract, reverse, true)
                                 }
+                                CalculationKind::RSC => {
+                                    ("RSC", Arithmetic::Subtract, reverse, carry)
+                                }
+                            };
+
+                            let result = arithmetic.calculate(operands, carry_in);
+
+                            self.registers[destination] = result;
+
+                            if let SetFlags::Set = set_flags {
+                                self.set_arithmetic_flags(
+                                    mnemonic, arithmetic, operands, carry_in, result,
+                                );
                             }
-                        },
+                        }
 
                         DataProcessingKind::Comparison {
                             kind,
                             source,
                             shifter,
-                        } => match kind {
-                            ComparisonKind::CMP => {
-                                let register_operand = self.registers[source];
-                                let (shifter_operand, shifter_carry) =
-                                    self.calculate_shifter(&shifter);
-                                let (result, borrow) =
-                                    u32::borrowing_sub(register_operand, shifter_operand, false);
-
-                                self.cpsr.n = (result as i32) < 0;
-                                self.cpsr.z = result == 0;
-                                self.cpsr.c = !borrow;
-                                self.cpsr.v =
-                                    match u32::checked_sub(register_operand, shifter_operand) {
-                                        Some(_) => false,
-                                        None => true,
-                                    }
+                        } => {
+                            let register_operand = self.registers[source];
+                            let (shifter_operand, shifter_carry) = self.calculate_shifter(&shifter);
+
+                            match kind {
+                                ComparisonKind::CMP => self.set_arithmetic_flags(
+                                    "CMP",
+                                    Arithmetic::Subtract,
+                                    (register_operand, shifter_operand),
+                                    true,
+                                    register_operand.wrapping_sub(shifter_operand),
+                                ),
+                                ComparisonKind::CMN => self.set_arithmetic_flags(
+                                    "CMN",
+                                    Arithmetic::Add,
+                                    (register_operand, shifter_operand),
+                                    false,
+                                    register_operand.wrapping_add(shifter_operand),
+                                ),
+                                ComparisonKind::TST => self.set_logical_flags(
+                                    register_operand & shifter_operand,
+                                    shifter_carry,
+                                ),
+                                ComparisonKind::TEQ => self.set_logical_flags(
+                                    register_operand ^ shifter_operand,
+                                    shifter_carry,
+                                ),
                             }
-                        },
+                        }
 
                         DataProcessingKind::Move {
                             kind,
@@ -242,17 +901,373 @@ impl Emulator {
                             self.registers[destination] = result;
 
                             if let SetFlags::Set = set_flags {
-                                self.cpsr.n = (result as i32) < 0;
-                                self.cpsr.z = result == 0;
-                                self.cpsr.c = shifter_carry;
+                                self.set_logical_flags(result, shifter_carry);
                             }
                         }
                     }
                 }
             }
 
-            _ => todo!(),
+            InstructionKind::Multiply {
+                condition,
+                set_flags,
+                destination,
+                multiplicand,
+                multiplier,
+                accumulate,
+            } => {
+                if self.cpsr.condition_passed(condition) {
+                    let product =
+                        self.registers[multiplicand].wrapping_mul(self.registers[multiplier]);
+
+                    let result = match accumulate {
+                        Some(accumulate) => product.wrapping_add(self.registers[accumulate]),
+                        None => product,
+                    };
+
+                    self.registers[destination] = result;
+
+                    // C and V are left alone, as they are meaningless for a multiply
+                    if let SetFlags::Set = set_flags {
+                        self.set_result_flags(result);
+                    }
+                }
+            }
+
+            InstructionKind::MultiplyLong {
+                condition,
+                kind,
+                set_flags,
+                destination,
+                multiplicand,
+                multiplier,
+            } => {
+                if self.cpsr.condition_passed(condition) {
+                    let (multiplicand, multiplier) =
+                        (self.registers[multiplicand], self.registers[multiplier]);
+
+                    // the low words of the signed and unsigned products are the same, but the high words aren't
+                    let product = match kind.signed() {
+                        true => (multiplicand as i32 as i64 * multiplier as i32 as i64) as u64,
+                        false => multiplicand as u64 * multiplier as u64,
+                    };
+
+                    let result = match kind.accumulates() {
+                        true => product.wrapping_add(
+                            (self.registers[destination.high] as u64) << 32
+                                | self.registers[destination.low] as u64,
+                        ),
+                        false => product,
+                    };
+
+                    self.registers[destination.low] = result as u32;
+                    self.registers[destination.high] = (result >> 32) as u32;
+
+                    if let SetFlags::Set = set_flags {
+                        self.set_long_result_flags(result);
+                    }
+                }
+            }
+
+            InstructionKind::LoadStoreMultiple {
+                condition,
+                kind,
+                mode,
+                base,
+                write_back,
+                register_list,
+            } => {
+                if self.cpsr.condition_passed(condition) {
+                    let base_address = self.registers[base];
+                    let size = 4 * register_list.count() as u32;
+
+                    // the registers are always transferred to ascending addresses, lowest register first
+                    let start_address = match mode {
+                        MultipleAddressingMode::IncrementAfter => base_address,
+                        MultipleAddressingMode::IncrementBefore => base_address.wrapping_add(4),
+                        MultipleAddressingMode::DecrementAfter => {
+                            base_address.wrapping_sub(size).wrapping_add(4)
+                        }
+                        MultipleAddressingMode::DecrementBefore => base_address.wrapping_sub(size),
+                    };
+
+                    #[cfg(feature = "assembler")]
+                    if let LoadStoreKind::Store = kind {
+                        self.check_store(start_address..start_address.wrapping_add(size))?;
+                    }
+
+                    // the base is written back before loading, so a loaded base register keeps the loaded value
+                    if let WriteBack::WriteBack = write_back {
+                        self.registers[base] = match mode {
+                            MultipleAddressingMode::IncrementAfter
+                            | MultipleAddressingMode::IncrementBefore => {
+                                base_address.wrapping_add(size)
+                            }
+                            MultipleAddressingMode::DecrementAfter
+                            | MultipleAddressingMode::DecrementBefore => {
+                                base_address.wrapping_sub(size)
+                            }
+                        };
+                    }
+
+                    for (i, register) in register_list.iter().enumerate() {
+                        let address = start_address.wrapping_add(4 * i as u32) as usize;
+
+                        self.record_access(address, &kind);
+
+                        match kind {
+                            LoadStoreKind::Load => {
+                                let value = self.load_word(address);
+
+                                // a loaded PC is word aligned, as there is no Thumb state to switch to
+                                self.registers[register] =
+                                    if register == 15 { value & !0b11 } else { value };
+                            }
+
+                            LoadStoreKind::Store => {
+                                // the PC is already incremented, so this stores the address of the instruction plus 8
+                                let value = if register == 15 {
+                                    self.registers[15] + 4
+                                } else {
+                                    self.registers[register]
+                                };
+                                let value = self.watch_store(address as u32, value);
+
+                                self.store_word(address, value);
+                            }
+                        }
+                    }
+                }
+            }
+
+            InstructionKind::SuperVisorCall {
+                condition,
+                immediate,
+            } => {
+                if self.cpsr.condition_passed(condition) {
+                    self.supervisor_call(immediate.get())?;
+                }
+            }
+
+            InstructionKind::MoveStatusToRegister {
+                condition,
+                destination,
+            } => {
+                if self.cpsr.condition_passed(condition) {
+                    self.registers[destination] = self.cpsr.bits();
+                }
+            }
+
+            InstructionKind::MoveToStatusRegister {
+                condition,
+                fields,
+                source,
+            } => {
+                if self.cpsr.condition_passed(condition) {
+                    let (value, _) = self.calculate_shifter(&source);
+
+                    self.cpsr.write_fields(value, fields);
+                }
+            }
+
+            InstructionKind::CountLeadingZeros {
+                condition,
+                destination,
+                source,
+            } => {
+                if self.cpsr.condition_passed(condition) {
+                    self.registers[destination] = self.registers[source].leading_zeros();
+                }
+            }
+
+            InstructionKind::LoadStore {
+                condition,
+                kind,
+                quantity,
+                destination,
+                address,
+            } => {
+                if self.cpsr.condition_passed(condition) {
+                    let base_address = self.registers[address.base];
+
+                    let offset = match &address.offset.value {
+                        AddressingOffsetValue::Immediate(offset) => offset.get(),
+                        AddressingOffsetValue::Register(offset) => self.registers[*offset],
+                        AddressingOffsetValue::ScaledRegister(shift) => {
+                            self.calculate_shifter(&ShifterOperandCode::ImmediateShift(
+                                shift.clone(),
+                            ))
+                            .0
+                        }
+                    };
+
+                    let offset_address = match address.offset.sign {
+                        Sign::Positive => base_address.wrapping_add(offset),
+                        Sign::Negative => base_address.wrapping_sub(offset),
+                    };
+
+                    let target = match address.offset.mode {
+                        OffsetMode::Offset | OffsetMode::PreIndexed => offset_address,
+                        OffsetMode::PostIndexed => base_address,
+                    };
+
+                    // words are accessed at the word containing the address
+                    let (target, size) = match quantity {
+                        LoadStoreQuantity::Word => (target & !0b11, 4),
+                        LoadStoreQuantity::Byte => (target, 1),
+                    };
+
+                    #[cfg(feature = "assembler")]
+                    if let LoadStoreKind::Store = kind {
+                        self.check_store(target..target.wrapping_add(size))?;
+                    }
+
+                    // the base is written back before loading, so a loaded base register keeps the loaded value
+                    if address.offset.mode != OffsetMode::Offset {
+                        self.registers[address.base] = offset_address;
+                    }
+
+                    self.record_access(target as usize, &kind);
+
+                    match (kind, quantity) {
+                        (LoadStoreKind::Load, LoadStoreQuantity::Word) => {
+                            let value = self.load_word(target as usize);
+
+                            // a loaded PC is word aligned, as there is no Thumb state to switch to
+                            self.registers[destination] = if destination.0 == 15 {
+                                value & !0b11
+                            } else {
+                                value
+                            };
+                        }
+
+                        (LoadStoreKind::Load, LoadStoreQuantity::Byte) => {
+                            self.registers[destination] = self.memory[target as usize].into();
+                        }
+
+                        (LoadStoreKind::Store, quantity) => {
+                            // the PC is already incremented, so this stores the address of the instruction plus 8
+                            let value = if destination.0 == 15 {
+                                self.registers[15] + 4
+                            } else {
+                                self.registers[destination]
+                            };
+
+                            match quantity {
+                                LoadStoreQuantity::Word => {
+                                    let value = self.watch_store(target, value);
+
+                                    self.store_word(target as usize, value);
+                                }
+                                LoadStoreQuantity::Byte => {
+                                    self.write_memory(target as usize, &[value as u8])
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// calls the handler for an SVC number, or applies the [`UndefinedSvc`] policy if there isn't one
+    fn supervisor_call(&mut self, number: u32) -> Result<(), ExecutionError> {
+        // the handler is taken out of the emulator while it runs, so that it can borrow the emulator mutably
+        if let Some(mut handler) = self.svc_handlers.remove(&number) {
+            handler(self, number);
+
+            // keep any replacement the handler registered for itself
+            self.svc_handlers.entry(number).or_insert(handler);
+
+            return Ok(());
+        }
+
+        match self.undefined_svc {
+            UndefinedSvc::RaiseException => {
+                // the PC is already incremented, so it holds the return address
+                self.registers[14] = self.registers[15];
+                self.registers[15] = SWI_VECTOR;
+                self.cpsr.set_t(false);
+            }
+
+            UndefinedSvc::Halt => {
+                let address = self.registers[15] - 4;
+                self.registers[15] = address;
+
+                return Err(ExecutionError::UndefinedSvc { address, number });
+            }
+
+            UndefinedSvc::Fallback(_) => {
+                if let UndefinedSvc::Fallback(mut fallback) =
+                    std::mem::take(&mut self.undefined_svc)
+                {
+                    fallback(self, number);
+
+                    self.undefined_svc = UndefinedSvc::Fallback(fallback);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// reads a big-endian word from memory
+    fn read_word(&self, address: usize) -> u32 {
+        u32::from_be_bytes(self.memory[address..address + 4].try_into().unwrap())
+    }
+
+    /// writes a big-endian word to memory
+    fn write_word(&mut self, address: usize, value: u32) {
+        self.write_memory(address, &value.to_be_bytes());
+    }
+
+    /// writes bytes to memory, so that any instructions they overwrite are decoded again
+    fn write_memory(&mut self, address: usize, bytes: &[u8]) {
+        self.save_pages(address, bytes.len());
+        self.written_pages
+            .extend(address / PAGE_SIZE..(address + bytes.len()).div_ceil(PAGE_SIZE));
+
+        self.memory[address..address + bytes.len()].copy_from_slice(bytes);
+
+        let start = address & !0b11;
+        let end = address + bytes.len();
+
+        // loading a program writes a lot of memory, so it's quicker to start again
+        if end - start > 4 * self.decoded.len() {
+            self.decoded.clear();
+        } else {
+            for word in (start..end).step_by(4) {
+                self.decoded.remove(&(word as u32));
+            }
+        }
+    }
+
+    /// checks whether the current instruction can store to `range`, see [`SelfModifyingCode`]
+    #[cfg(feature = "assembler")]
+    fn check_store(&self, range: Range<u32>) -> Result<(), ExecutionError> {
+        if self.self_modifying_code == SelfModifyingCode::Fault {
+            if let Some(target) = range
+                .clone()
+                .find(|address| self.region(*address as usize) == Some(Region::Code))
+            {
+                let address = self.registers[15] - 4;
+
+                return Err(ExecutionError::SelfModifyingCode {
+                    address,
+                    source_line: self.source_line(address as usize),
+                    target,
+                    label: self
+                        .modules
+                        .iter()
+                        .find(|program| program.statement_at(target as usize).is_some())
+                        .and_then(|program| program.label_before(target as usize)),
+                });
+            }
         }
+
+        Ok(())
     }
 
     fn calculate_shifter(&mut self, shifter: &ShifterOperandCode) -> (u32, bool) {
@@ -263,7 +1278,7 @@ impl Emulator {
                 (
                     shifter_operand,
                     if shifter_operand == 0 {
-                        self.cpsr.c
+                        self.cpsr.c()
                     } else {
                         (shifter_operand as i32) < 0
                     },
@@ -281,7 +1296,12 @@ impl Emulator {
                     let base = self.registers[shift.base];
                     let amount = shift.amount.get();
 
-                    (base >> amount, Bits(base)[(amount as usize - 1) % 32] == 1)
+                    // an amount of 0 encodes LSR #32
+                    if amount == 0 {
+                        (0, Bits(base)[31] == 1)
+                    } else {
+                        (base >> amount, Bits(base)[amount as usize - 1] == 1)
+                    }
                 }
 
                 Shift::ArithmeticShiftRight => {
@@ -310,7 +1330,7 @@ impl Emulator {
                         // Rotate right with extend
                         (
                             // (C Flag Logical_Shift_Left 31) OR (Rm Logical_Shift_Right 1)
-                            if self.cpsr.c { 1 } else { 0 } << 31 | base >> 1,
+                            if self.cpsr.c() { 1 } else { 0 } << 31 | base >> 1,
                             // Rm[0]
                             Bits(base)[0] == 1,
                         )
@@ -328,7 +1348,7 @@ impl Emulator {
                     let amount = Bits(self.registers[shift.amount]).range(0..=7);
 
                     match amount {
-                        0 => (base, self.cpsr.c),
+                        0 => (base, self.cpsr.c()),
                         1..=31 => (base << amount, Bits(base)[32 - amount as usize] == 1),
                         32 => (base, Bits(base)[0] == 1),
                         _ => (0, false),
@@ -340,7 +1360,7 @@ impl Emulator {
                     let amount = Bits(self.registers[shift.amount]).range(0..=7);
 
                     match amount {
-                        0 => (base, self.cpsr.c),
+                        0 => (base, self.cpsr.c()),
                         1..=31 => (base >> amount, Bits(base)[amount as usize - 1] == 1),
                         32 => (base, Bits(base)[0] == 1),
                         _ => (0, false),
@@ -352,7 +1372,7 @@ impl Emulator {
                     let amount = Bits(self.registers[shift.amount]).range(0..=7);
 
                     match amount {
-                        0 => (base, self.cpsr.c),
+                        0 => (base, self.cpsr.c()),
                         1..=31 => (
                             ((base as i32) >> amount) as u32,
                             Bits(base)[amount as usize - 1] == 1,
@@ -372,7 +1392,7 @@ impl Emulator {
                     let amount = Bits(self.registers[shift.amount]).range(0..=7);
 
                     if amount == 0 {
-                        (base, self.cpsr.c)
+                        (base, self.cpsr.c())
                     } else {
                         let amount = Bits(self.registers[shift.amount]).range(0..=4);
 
@@ -391,37 +1411,6 @@ impl Emulator {
     }
 }
 
-impl CPSR {
-    fn condition_passed(&self, condition: Condition) -> bool {
-        match condition {
-            Condition::EQ => self.z,
-            Condition::NE => !self.z,
-            Condition::CS => self.c,
-            Condition::CC => !self.c,
-            Condition::MI => self.n,
-            Condition::PL => !self.n,
-            Condition::VS => self.v,
-            Condition::VC => !self.v,
-            Condition::HI => self.c && !self.z,
-            Condition::LS => !self.c || self.z,
-            Condition::GE => self.n == self.v,
-            Condition::LT => self.n != self.v,
-            Condition::GT => !self.z && (self.n == self.v),
-            Condition::LE => self.z || (self.n != self.v),
-            Condition::AL => true,
-        }
-    }
-}
-
-impl CalculationKind {
-    fn calculate(&self, a: u32, b: u32) -> u32 {
-        match self {
-            CalculationKind::ADD => a + b,
-            CalculationKind::SUB => a - b,
-        }
-    }
-}
-
 impl Shift {
     fn apply(&self, base: u32, amount: u32) -> u32 {
         match self {
@@ -432,3 +1421,13 @@ impl Shift {
         }
     }
 }
+
+/// parses a number written like `0x0000002A`, as the emulator's text formats write them
+fn parse_hex(text: &str) -> Option<u32> {
+    u32::from_str_radix(text.strip_prefix("0x")?, 16).ok()
+}
+
+/// parses a number like `16` or `0x10`, for files written by hand
+fn parse_number(text: &str) -> Option<u32> {
+    parse_hex(text).or_else(|| text.parse().ok())
+}
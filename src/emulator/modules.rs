@@ -0,0 +1,165 @@
+use std::{borrow::Cow, collections::HashMap, sync::Arc};
+
+use crate::{
+    assembler::{AssembledStatement, AssemblyError, AssemblyOptions, Program, Region},
+    parser::Symbol,
+    resolver::SymbolTable,
+};
+
+use super::{Emulator, LoadReport};
+
+/// why a program couldn't be loaded alongside the modules already in the emulator
+#[derive(Debug)]
+pub enum LoadError {
+    AssemblyError(AssemblyError),
+    /// the program defines a symbol that is also defined by the module with this index
+    SymbolCollision {
+        symbol: Symbol,
+        module: usize,
+    },
+    /// the program generates bytes at an address that is also used by the module with this index
+    Overlap {
+        address: usize,
+        module: usize,
+    },
+}
+
+impl From<AssemblyError> for LoadError {
+    fn from(value: AssemblyError) -> LoadError {
+        LoadError::AssemblyError(value)
+    }
+}
+
+/// a line of the source of one of the loaded modules
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SourceLine {
+    /// the index of the module, in the order they were loaded
+    pub module: usize,
+    pub line: usize,
+}
+
+impl Emulator {
+    /// assembles source that can refer to the symbols of the loaded modules, and loads it alongside them, see [`Emulator::load_module`]
+    pub fn assemble_module(&mut self, input: &str) -> Result<LoadReport, LoadError> {
+        let program =
+            Program::assemble_with_imports(input, AssemblyOptions::default(), self.exports())?;
+
+        self.load_module(program)
+    }
+
+    /// writes a program to memory alongside the loaded modules, e.g. a student's program and a provided library at a fixed ORIGIN
+    ///
+    /// Unlike [`Emulator::load`], the other modules are kept and the PC isn't changed, so the report's entry point has to be jumped to if the module is the one to run.
+    /// The program is rejected if it defines a symbol that another module defines, or generates bytes at an address that another module uses.
+    /// Each module keeps its own source map, so source lines are reported along with their module (see [`SourceLine`]).
+    pub fn load_module(
+        &mut self,
+        program: impl Into<Arc<Program>>,
+    ) -> Result<LoadReport, LoadError> {
+        let program = program.into();
+
+        let exported: HashMap<&Symbol, usize> = self
+            .modules
+            .iter()
+            .enumerate()
+            .flat_map(|(module, other)| other.exports().map(move |(symbol, _)| (symbol, module)))
+            .collect();
+
+        if let Some((symbol, module)) = program
+            .exports()
+            .find_map(|(symbol, _)| Some((symbol, *exported.get(symbol)?)))
+        {
+            return Err(LoadError::SymbolCollision {
+                symbol: symbol.clone(),
+                module,
+            });
+        }
+
+        let regions = program.regions();
+
+        for (module, other) in self.modules.iter().enumerate() {
+            for other_region in other.regions() {
+                if let Some(region) = regions.iter().find(|region| {
+                    region.start < other_region.end && other_region.start < region.end
+                }) {
+                    return Err(LoadError::Overlap {
+                        address: region.start.max(other_region.start),
+                        module,
+                    });
+                }
+            }
+        }
+
+        for statement in &program.statements {
+            self.write_memory(statement.address, &statement.bytes);
+        }
+
+        #[cfg(feature = "disassembler")]
+        {
+            let annotations = program.annotations();
+
+            for (number, name) in annotations.svc_names {
+                self.annotations.svc_names.entry(number).or_insert(name);
+            }
+            self.annotations.constants.extend(annotations.constants);
+            self.annotations.jump_tables.extend(annotations.jump_tables);
+        }
+
+        let report = LoadReport::new(&program);
+
+        self.modules.push(program);
+
+        self.restart_checkpoints();
+        self.publish();
+
+        Ok(report)
+    }
+
+    /// the loaded programs, in the order they were loaded
+    pub fn modules(&self) -> &[Arc<Program>] {
+        &self.modules
+    }
+
+    /// the source line that generated the byte at `address`, and the module it is in
+    pub fn source_line(&self, address: usize) -> Option<SourceLine> {
+        self.modules
+            .iter()
+            .enumerate()
+            .find_map(|(module, program)| {
+                Some(SourceLine {
+                    module,
+                    line: program.source_map.line(address)?,
+                })
+            })
+    }
+
+    /// the statement of any loaded module that generated the byte at `address`
+    pub(super) fn statement_at(&self, address: usize) -> Option<&AssembledStatement> {
+        self.modules
+            .iter()
+            .find_map(|program| program.statement_at(address))
+    }
+
+    pub(super) fn region(&self, address: usize) -> Option<Region> {
+        self.statement_at(address).map(|statement| statement.region)
+    }
+
+    /// the symbols defined by the loaded modules
+    pub(super) fn symbol_table(&self) -> Cow<'_, SymbolTable<u32>> {
+        match self.modules.as_slice() {
+            [program] => Cow::Borrowed(&program.symbol_table),
+            _ => Cow::Owned(self.exports()),
+        }
+    }
+
+    /// the symbols defined by the loaded modules, excluding the ones they import from each other
+    fn exports(&self) -> SymbolTable<u32> {
+        let mut symbol_table = SymbolTable::new();
+
+        for (symbol, value) in self.modules.iter().flat_map(|program| program.exports()) {
+            symbol_table.insert(symbol.clone(), *value);
+        }
+
+        symbol_table
+    }
+}
@@ -0,0 +1,86 @@
+use super::{Emulator, ExecutionError};
+
+/// how a program finished running, see [`Emulator::run`]
+///
+/// Graders can assert on [`RunOutcome::exit_code`] and the final registers, rather than only the program's output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunOutcome {
+    pub status: RunStatus,
+    /// the value of R0 when the run stopped
+    pub r0: u32,
+    /// the number of instructions executed since the emulator was created
+    pub cycles: u64,
+}
+
+/// why [`Emulator::run`] stopped
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RunStatus {
+    /// the program called the exit SVC, see [`Emulator::mount_exit`]
+    Exited(u32),
+    /// the program returned from its entry point to the address LR was set to by [`Emulator::set_stack`]
+    Returned,
+    /// an SVC is waiting for something from the host, such as input
+    Waiting,
+    /// the instruction limit was reached before the program finished
+    LimitReached,
+}
+
+impl RunOutcome {
+    /// the program's exit code: the code passed to the exit SVC, or R0 if it returned from its entry point, as `main` does in C
+    ///
+    /// This is `None` if the program hasn't finished.
+    pub fn exit_code(&self) -> Option<u32> {
+        match self.status {
+            RunStatus::Exited(code) => Some(code),
+            RunStatus::Returned => Some(self.r0),
+            RunStatus::Waiting | RunStatus::LimitReached => None,
+        }
+    }
+}
+
+impl Emulator {
+    /// gives the program an SVC that exits with the code in R0, halting the emulator
+    pub fn mount_exit(&mut self, svc: u32) {
+        self.register_svc(svc, |emulator, _| {
+            emulator.exit_code = Some(emulator.registers[0]);
+        });
+    }
+
+    /// whether the program has finished, by calling the exit SVC or returning from its entry point, see [`Emulator::mount_exit`] and [`Emulator::set_stack`]
+    ///
+    /// Stepping a halted emulator does nothing, and [`Emulator::poll_step`] returns [`super::Poll::Halted`].
+    pub fn is_halted(&self) -> bool {
+        self.exit_code.is_some() || self.returned()
+    }
+
+    /// steps until the program finishes or an SVC has to wait, executing at most `limit` instructions so that a program stuck in a loop still stops
+    pub fn run(&mut self, limit: u64) -> Result<RunOutcome, ExecutionError> {
+        let start = self.cycles;
+
+        let status = loop {
+            if let Some(code) = self.exit_code {
+                break RunStatus::Exited(code);
+            }
+
+            if self.returned() {
+                break RunStatus::Returned;
+            }
+
+            if self.cycles - start >= limit {
+                break RunStatus::LimitReached;
+            }
+
+            self.step()?;
+
+            if self.waiting {
+                break RunStatus::Waiting;
+            }
+        };
+
+        Ok(RunOutcome {
+            status,
+            r0: self.registers[0],
+            cycles: self.cycles,
+        })
+    }
+}
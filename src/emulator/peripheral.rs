@@ -0,0 +1,271 @@
+use super::{parse_number, Emulator};
+
+/// the peripherals of a lab's board, which can be read from a file with [`Board::parse`], see [`Emulator::mount_board`]
+///
+/// The file has a line for each peripheral, followed by a line for each of its registers:
+///
+/// ```text
+/// peripheral uart 0x10000000
+/// register data 0x0 fifo
+/// register status 0x4 fifo_status
+/// register id 0x8 constant 0x41
+/// register control 0xC latch 0x0
+/// register timer 0x10 counter 0x0 1
+/// register tick 0x14 timer 1000
+/// ```
+///
+/// Lines starting with `;` are comments.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Board {
+    pub peripherals: Vec<Peripheral>,
+}
+
+/// a block of memory-mapped registers, built like `Peripheral::new("uart", 0x10000000).register("data", 0, RegisterBehavior::Fifo)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Peripheral {
+    pub name: String,
+    /// the address of the first register
+    pub base: u32,
+    pub registers: Vec<PeripheralRegister>,
+}
+
+/// a word-sized register of a peripheral
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeripheralRegister {
+    pub name: String,
+    /// the register's offset from the base of its peripheral, which is word aligned
+    pub offset: u32,
+    pub behavior: RegisterBehavior,
+}
+
+/// what reading and writing a memory-mapped register does, written in snake case in a board file, e.g. `fifo_status`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegisterBehavior {
+    /// reads as the value and ignores writes, e.g. an ID register
+    Constant(u32),
+    /// reads as the last value written, which starts as the value
+    Latch(u32),
+    /// counts up by `step` every cycle from `start`, and counts on from the value written
+    Counter { start: u32, step: u32 },
+    /// reads take a byte of the console's input, or 0 if there isn't any, and writes print the low byte to the console
+    Fifo,
+    /// reads as the number of bytes of input waiting to be read from a FIFO, and ignores writes
+    FifoStatus,
+    /// raises an IRQ every `period` cycles, reads as the cycles until it next does, and restarts with the period written, or stops if it is 0
+    Timer { period: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PeripheralError {
+    /// a line of a board file isn't a peripheral, register or comment, with a one-based line number
+    Malformed { line: usize },
+    /// a register isn't word aligned
+    UnalignedRegister { address: u32 },
+    /// a register is at the same address as another that is already mounted
+    Overlap { address: u32 },
+}
+
+/// the state of a mounted register
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct MappedRegister {
+    behavior: RegisterBehavior,
+    /// the value last written, or the starting value
+    value: u32,
+    /// the cycle the value was set at, which a counter counts from
+    set_at: u64,
+}
+
+impl Board {
+    pub fn new() -> Board {
+        Board::default()
+    }
+
+    pub fn peripheral(mut self, peripheral: Peripheral) -> Board {
+        self.peripherals.push(peripheral);
+        self
+    }
+
+    /// reads a board file
+    pub fn parse(text: &str) -> Result<Board, PeripheralError> {
+        let mut board = Board::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let malformed = PeripheralError::Malformed { line: index + 1 };
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+
+            match fields.as_slice() {
+                [] => {}
+                [comment, ..] if comment.starts_with(';') => {}
+
+                ["peripheral", name, base] => {
+                    let base = parse_number(base).ok_or(malformed)?;
+
+                    board.peripherals.push(Peripheral::new(name, base));
+                }
+
+                ["register", name, offset, behavior @ ..] => {
+                    let offset = parse_number(offset).ok_or(malformed)?;
+
+                    let behavior = match behavior {
+                        ["constant", value] => {
+                            RegisterBehavior::Constant(parse_number(value).ok_or(malformed)?)
+                        }
+                        ["latch", value] => {
+                            RegisterBehavior::Latch(parse_number(value).ok_or(malformed)?)
+                        }
+                        ["counter", start, step] => RegisterBehavior::Counter {
+                            start: parse_number(start).ok_or(malformed)?,
+                            step: parse_number(step).ok_or(malformed)?,
+                        },
+                        ["fifo"] => RegisterBehavior::Fifo,
+                        ["fifo_status"] => RegisterBehavior::FifoStatus,
+                        ["timer", period] => RegisterBehavior::Timer {
+                            period: parse_number(period).ok_or(malformed)?,
+                        },
+                        _ => return Err(malformed),
+                    };
+
+                    board
+                        .peripherals
+                        .last_mut()
+                        .ok_or(malformed)?
+                        .registers
+                        .push(PeripheralRegister {
+                            name: name.to_string(),
+                            offset,
+                            behavior,
+                        });
+                }
+
+                _ => return Err(malformed),
+            }
+        }
+
+        Ok(board)
+    }
+}
+
+impl Peripheral {
+    pub fn new(name: &str, base: u32) -> Peripheral {
+        Peripheral {
+            name: name.to_owned(),
+            base,
+            registers: Vec::new(),
+        }
+    }
+
+    pub fn register(mut self, name: &str, offset: u32, behavior: RegisterBehavior) -> Peripheral {
+        self.registers.push(PeripheralRegister {
+            name: name.to_owned(),
+            offset,
+            behavior,
+        });
+        self
+    }
+}
+
+impl Emulator {
+    /// maps the registers of every peripheral of a board into memory, see [`Emulator::mount_peripheral`]
+    pub fn mount_board(&mut self, board: &Board) -> Result<(), PeripheralError> {
+        board
+            .peripherals
+            .iter()
+            .try_for_each(|peripheral| self.mount_peripheral(peripheral))
+    }
+
+    /// maps a peripheral's registers into memory, so loads and stores to them have the register's behavior rather than accessing memory
+    ///
+    /// Nothing is mounted if a register isn't word aligned or is already mapped. The FIFO behaviors go through the console, so it should be mounted with [`Emulator::mount_console`] first.
+    pub fn mount_peripheral(&mut self, peripheral: &Peripheral) -> Result<(), PeripheralError> {
+        let addresses: Vec<u32> = peripheral
+            .registers
+            .iter()
+            .map(|register| peripheral.base.wrapping_add(register.offset))
+            .collect();
+
+        for (i, address) in addresses.iter().enumerate() {
+            if address % 4 != 0 {
+                return Err(PeripheralError::UnalignedRegister { address: *address });
+            }
+
+            if self.mapped.contains_key(address) || addresses[..i].contains(address) {
+                return Err(PeripheralError::Overlap { address: *address });
+            }
+        }
+
+        for (register, address) in peripheral.registers.iter().zip(addresses) {
+            let value = match register.behavior {
+                RegisterBehavior::Constant(value) | RegisterBehavior::Latch(value) => value,
+                RegisterBehavior::Counter { start, .. } => start,
+                RegisterBehavior::Timer { period } => period,
+                RegisterBehavior::Fifo | RegisterBehavior::FifoStatus => 0,
+            };
+
+            self.mapped.insert(
+                address,
+                MappedRegister {
+                    behavior: register.behavior,
+                    value,
+                    set_at: self.cycles,
+                },
+            );
+        }
+
+        Ok(())
+    }
+
+    /// loads a word, from a memory-mapped register if one is at `address`
+    pub(super) fn load_word(&mut self, address: usize) -> u32 {
+        let Some(register) = self.mapped.get(&(address as u32)) else {
+            return self.read_word(address);
+        };
+
+        match register.behavior {
+            RegisterBehavior::Constant(_) | RegisterBehavior::Latch(_) => register.value,
+            RegisterBehavior::Counter { step, .. } => register
+                .value
+                .wrapping_add(step.wrapping_mul((self.cycles - register.set_at) as u32)),
+            RegisterBehavior::Fifo => self.read_input().unwrap_or(0).into(),
+            RegisterBehavior::FifoStatus => self.pending_input() as u32,
+            RegisterBehavior::Timer { .. } => match register.value {
+                0 => 0,
+                period => period - ((self.cycles - register.set_at) % u64::from(period)) as u32,
+            },
+        }
+    }
+
+    /// stores a word, to a memory-mapped register if one is at `address`
+    pub(super) fn store_word(&mut self, address: usize, value: u32) {
+        let cycles = self.cycles;
+
+        let Some(register) = self.mapped.get_mut(&(address as u32)) else {
+            return self.write_word(address, value);
+        };
+
+        match register.behavior {
+            RegisterBehavior::Constant(_) | RegisterBehavior::FifoStatus => {}
+            RegisterBehavior::Latch(_)
+            | RegisterBehavior::Counter { .. }
+            | RegisterBehavior::Timer { .. } => {
+                register.value = value;
+                register.set_at = cycles;
+            }
+            RegisterBehavior::Fifo => self.print(&[value as u8]),
+        }
+    }
+
+    /// raises an IRQ if a timer fires at the current cycle, see [`RegisterBehavior::Timer`]
+    pub(super) fn tick_timers(&mut self) {
+        let fired = self.mapped.values().any(|register| {
+            matches!(register.behavior, RegisterBehavior::Timer { .. })
+                && register.value != 0
+                && self.cycles > register.set_at
+                && (self.cycles - register.set_at).is_multiple_of(u64::from(register.value))
+        });
+
+        if fired {
+            self.raise_irq();
+        }
+    }
+}
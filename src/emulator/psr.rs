@@ -0,0 +1,241 @@
+use std::fmt::Display;
+
+use crate::ir::{Condition, StatusFields};
+
+/// a program status register, such as the CPSR, in its 32-bit layout
+///
+/// ```text
+///  3 3 2 2 2 2
+///  1 0 9 8 7 6                                     7 6 5 4 3 2 1 0
+/// |N|Z|C|V|Q|                                     |I|F|T|mode     |
+/// ```
+///
+/// It is written like `nzCvq ift USR`, with a capital for each bit that is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Psr(u32);
+
+/// the processor mode in bits 0 to 4 of a [`Psr`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessorMode {
+    User = 0b10000,
+    Fiq = 0b10001,
+    Irq = 0b10010,
+    Supervisor = 0b10011,
+    Abort = 0b10111,
+    Undefined = 0b11011,
+    System = 0b11111,
+}
+
+impl ProcessorMode {
+    /// the mode with the given mode bits, if they are a valid mode
+    pub fn from_bits(bits: u32) -> Option<ProcessorMode> {
+        [
+            ProcessorMode::User,
+            ProcessorMode::Fiq,
+            ProcessorMode::Irq,
+            ProcessorMode::Supervisor,
+            ProcessorMode::Abort,
+            ProcessorMode::Undefined,
+            ProcessorMode::System,
+        ]
+        .into_iter()
+        .find(|mode| *mode as u32 == bits)
+    }
+
+    /// the abbreviation used for the mode, e.g. `SVC`
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProcessorMode::User => "USR",
+            ProcessorMode::Fiq => "FIQ",
+            ProcessorMode::Irq => "IRQ",
+            ProcessorMode::Supervisor => "SVC",
+            ProcessorMode::Abort => "ABT",
+            ProcessorMode::Undefined => "UND",
+            ProcessorMode::System => "SYS",
+        }
+    }
+}
+
+impl Default for Psr {
+    /// user mode, with every flag clear
+    fn default() -> Self {
+        Psr(ProcessorMode::User as u32)
+    }
+}
+
+impl Psr {
+    const N: u32 = 31;
+    const Z: u32 = 30;
+    const C: u32 = 29;
+    const V: u32 = 28;
+    const Q: u32 = 27;
+    const I: u32 = 7;
+    const F: u32 = 6;
+    const T: u32 = 5;
+    const MODE: u32 = 0b11111;
+
+    pub fn from_bits(bits: u32) -> Psr {
+        Psr(bits)
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    fn get(&self, bit: u32) -> bool {
+        self.0 & (1 << bit) != 0
+    }
+
+    fn set(&mut self, bit: u32, value: bool) {
+        self.0 = self.0 & !(1 << bit) | (value as u32) << bit;
+    }
+
+    /// negative
+    pub fn n(&self) -> bool {
+        self.get(Psr::N)
+    }
+
+    /// zero
+    pub fn z(&self) -> bool {
+        self.get(Psr::Z)
+    }
+
+    /// carry
+    pub fn c(&self) -> bool {
+        self.get(Psr::C)
+    }
+
+    /// overflow
+    pub fn v(&self) -> bool {
+        self.get(Psr::V)
+    }
+
+    /// saturation
+    pub fn q(&self) -> bool {
+        self.get(Psr::Q)
+    }
+
+    /// whether IRQ interrupts are disabled
+    pub fn i(&self) -> bool {
+        self.get(Psr::I)
+    }
+
+    /// whether FIQ interrupts are disabled
+    pub fn f(&self) -> bool {
+        self.get(Psr::F)
+    }
+
+    /// whether the processor is in Thumb state
+    pub fn t(&self) -> bool {
+        self.get(Psr::T)
+    }
+
+    /// the processor mode, or `None` if the mode bits aren't a valid mode
+    pub fn mode(&self) -> Option<ProcessorMode> {
+        ProcessorMode::from_bits(self.0 & Psr::MODE)
+    }
+
+    pub fn set_n(&mut self, value: bool) {
+        self.set(Psr::N, value)
+    }
+
+    pub fn set_z(&mut self, value: bool) {
+        self.set(Psr::Z, value)
+    }
+
+    pub fn set_c(&mut self, value: bool) {
+        self.set(Psr::C, value)
+    }
+
+    pub fn set_v(&mut self, value: bool) {
+        self.set(Psr::V, value)
+    }
+
+    pub fn set_q(&mut self, value: bool) {
+        self.set(Psr::Q, value)
+    }
+
+    pub fn set_i(&mut self, value: bool) {
+        self.set(Psr::I, value)
+    }
+
+    pub fn set_f(&mut self, value: bool) {
+        self.set(Psr::F, value)
+    }
+
+    pub fn set_t(&mut self, value: bool) {
+        self.set(Psr::T, value)
+    }
+
+    pub fn set_mode(&mut self, mode: ProcessorMode) {
+        self.0 = self.0 & !Psr::MODE | mode as u32;
+    }
+
+    /// writes the bytes of `value` in `fields`, as an MSR does
+    ///
+    /// In user mode only the flags field can be written, and the T bit is never written, as switching state takes a BX.
+    pub fn write_fields(&mut self, value: u32, fields: StatusFields) {
+        let mut mask = fields.mask() & !(1 << Psr::T);
+
+        if self.mode() == Some(ProcessorMode::User) {
+            mask &= StatusFields::FLAGS;
+        }
+
+        self.0 = self.0 & !mask | value & mask;
+    }
+
+    /// whether an instruction with `condition` is executed with these flags
+    pub fn condition_passed(&self, condition: Condition) -> bool {
+        let (n, z, c, v) = (self.n(), self.z(), self.c(), self.v());
+
+        match condition {
+            Condition::EQ => z,
+            Condition::NE => !z,
+            Condition::CS => c,
+            Condition::CC => !c,
+            Condition::MI => n,
+            Condition::PL => !n,
+            Condition::VS => v,
+            Condition::VC => !v,
+            Condition::HI => c && !z,
+            Condition::LS => !c || z,
+            Condition::GE => n == v,
+            Condition::LT => n != v,
+            Condition::GT => !z && (n == v),
+            Condition::LE => z || (n != v),
+            Condition::AL => true,
+            Condition::NV => false,
+        }
+    }
+}
+
+impl Display for Psr {
+    /// writes the bits like `nzCvq ift USR`, with a capital for each bit that is set
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let letter = |set: bool, letter: char| match set {
+            true => letter.to_ascii_uppercase(),
+            false => letter,
+        };
+
+        let flags: String = [
+            (self.n(), 'n'),
+            (self.z(), 'z'),
+            (self.c(), 'c'),
+            (self.v(), 'v'),
+            (self.q(), 'q'),
+        ]
+        .into_iter()
+        .map(|(set, flag)| letter(set, flag))
+        .collect();
+
+        let control: String = [(self.i(), 'i'), (self.f(), 'f'), (self.t(), 't')]
+            .into_iter()
+            .map(|(set, bit)| letter(set, bit))
+            .collect();
+
+        match self.mode() {
+            Some(mode) => write!(f, "{flags} {control} {}", mode.name()),
+            None => write!(f, "{flags} {control} {:#07b}", self.0 & Psr::MODE),
+        }
+    }
+}
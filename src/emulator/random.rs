@@ -0,0 +1,57 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::Emulator;
+
+/// a pseudo-random number generator for the random SVC, using SplitMix64
+///
+/// The same seed always produces the same numbers, so programs that use randomness can still be marked reproducibly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        Random { state: seed }
+    }
+
+    /// a generator seeded from the host's clock, for runs that don't need to be reproducible
+    pub fn from_time() -> Random {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+
+        Random::new(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// a number less than `bound`, or any 32-bit number if `bound` is 0
+    pub fn below(&mut self, bound: u32) -> u32 {
+        let n = self.next_u64() >> 32;
+
+        match bound {
+            0 => n as u32,
+            // scaling the number avoids the bias of taking the remainder
+            _ => ((n * bound as u64) >> 32) as u32,
+        }
+    }
+}
+
+impl Emulator {
+    /// gives the program random numbers through an SVC, which takes a bound in R0 and returns a number less than it in R0
+    ///
+    /// If the bound is 0, any 32-bit number can be returned.
+    pub fn mount_random(&mut self, mut random: Random, svc: u32) {
+        self.register_svc(svc, move |emulator, _| {
+            emulator.registers[0] = random.below(emulator.registers[0]);
+        });
+    }
+}
@@ -0,0 +1,162 @@
+use std::fmt::Display;
+
+use super::{Emulator, ExecutionError, RunOutcome};
+
+/// input to send to a program and the output to expect back, like expect(1), see [`Emulator::run_script`]
+///
+/// It is built like `Script::new().expect("name?").send("Ada\n").expect("hello, Ada")`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Script {
+    steps: Vec<ScriptStep>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptStep {
+    /// queue input for the program to read
+    Send(String),
+    /// run until the output printed since the last expectation contains the text
+    Expect(String),
+}
+
+/// why a script failed
+#[derive(Debug)]
+pub enum ScriptError {
+    Execution(ExecutionError),
+    /// the program halted, waited for input that wasn't sent, or reached the instruction limit, before printing the expected text
+    Unmatched {
+        expected: String,
+        /// the input sent and output printed while running the script
+        transcript: Transcript,
+    },
+}
+
+/// the input sent to a program and the output it printed, in order
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Transcript {
+    pub entries: Vec<TranscriptEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TranscriptEntry {
+    Input(String),
+    Output(String),
+}
+
+impl Script {
+    pub fn new() -> Script {
+        Script::default()
+    }
+
+    pub fn send(mut self, input: &str) -> Script {
+        self.steps.push(ScriptStep::Send(input.to_owned()));
+        self
+    }
+
+    pub fn expect(mut self, output: &str) -> Script {
+        self.steps.push(ScriptStep::Expect(output.to_owned()));
+        self
+    }
+
+    pub fn steps(&self) -> &[ScriptStep] {
+        &self.steps
+    }
+}
+
+impl Display for Transcript {
+    /// writes the output as it was printed, with the input shown like `[sent "A\n"]`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for entry in &self.entries {
+            match entry {
+                TranscriptEntry::Input(input) => write!(f, "[sent {input:?}]")?,
+                TranscriptEntry::Output(output) => write!(f, "{output}")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl From<ExecutionError> for ScriptError {
+    fn from(value: ExecutionError) -> ScriptError {
+        ScriptError::Execution(value)
+    }
+}
+
+impl Emulator {
+    /// runs the program through the console, sending the script's input and checking for its expected output in order, and then runs it to the end
+    ///
+    /// Each expectation only matches output printed after the text matched by the one before.
+    /// The console must be mounted with [`Emulator::mount_console`], and at most `limit` instructions are executed, so a program stuck in a loop fails rather than running forever.
+    pub fn run_script(&mut self, script: &Script, limit: u64) -> Result<RunOutcome, ScriptError> {
+        let end = self.cycles + limit;
+
+        let start = self.output_length();
+        let mut cursor = start;
+        // the input sent, and the length of the output when it was sent
+        let mut sent = Vec::new();
+
+        for step in &script.steps {
+            match step {
+                ScriptStep::Send(input) => {
+                    sent.push((self.output_length(), input.clone()));
+                    self.send_input(input.as_bytes());
+                }
+
+                ScriptStep::Expect(expected) => loop {
+                    if let Some(offset) = self
+                        .output()
+                        .and_then(|output| output.find_from(cursor, expected))
+                    {
+                        cursor = offset + expected.len();
+                        break;
+                    }
+
+                    let stuck = self.waiting && !self.has_input();
+
+                    if self.is_halted() || stuck || self.cycles >= end {
+                        return Err(ScriptError::Unmatched {
+                            expected: expected.clone(),
+                            transcript: self.transcript(start, &sent),
+                        });
+                    }
+
+                    self.step()?;
+                },
+            }
+        }
+
+        Ok(self.run(end.saturating_sub(self.cycles))?)
+    }
+
+    /// the number of bytes printed through the console
+    fn output_length(&self) -> usize {
+        self.output().map_or(0, |output| output.bytes().len())
+    }
+
+    /// the output from `start`, with the input sent at each length of the output
+    fn transcript(&self, start: usize, sent: &[(usize, String)]) -> Transcript {
+        let bytes = self.output().map_or(&[][..], |output| output.bytes());
+
+        let mut entries = Vec::new();
+        let mut printed = start;
+
+        for (length, input) in sent {
+            if *length > printed {
+                entries.push(TranscriptEntry::Output(
+                    String::from_utf8_lossy(&bytes[printed..*length]).into_owned(),
+                ));
+                printed = *length;
+            }
+
+            entries.push(TranscriptEntry::Input(input.clone()));
+        }
+
+        if bytes.len() > printed {
+            entries.push(TranscriptEntry::Output(
+                String::from_utf8_lossy(&bytes[printed..]).into_owned(),
+            ));
+        }
+
+        Transcript { entries }
+    }
+}
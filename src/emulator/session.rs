@@ -0,0 +1,289 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+};
+
+use crate::assembler::{AssemblyError, AssemblyOptions, Program};
+
+use super::{checkpoint::PAGE_SIZE, parse_hex, Emulator, LoadReport, Psr, WatchAction};
+
+/// the first line of a session file, which includes the version of the format
+const HEADER: &str = "eremius session 1\n";
+
+/// a debugging session that can be saved to a file and resumed later, see [`Emulator::save_session`]
+///
+/// The file is text, with a line for each breakpoint, watchpoint, register and page of memory, so it can be read and diffed.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Session {
+    /// the source of the loaded program, which is assembled again when the session is restored
+    pub source: String,
+    /// the breakpoints set by the frontend, which the emulator doesn't use itself
+    pub breakpoints: BTreeSet<u32>,
+    pub watchpoints: BTreeMap<u32, SavedWatch>,
+    /// the state of the machine, to carry on exactly where the session left off
+    pub snapshot: Option<Snapshot>,
+}
+
+/// a watchpoint whose action can be saved, which excludes [`WatchAction::Call`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SavedWatch {
+    Halt,
+    Log,
+    Inject(u32),
+}
+
+/// the registers, CPSR and written memory of the machine
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Snapshot {
+    pub registers: [u32; 16],
+    pub cpsr: Psr,
+    pub cycles: u64,
+    /// the contents of each page of memory that has been written, by the address of the page
+    pub pages: BTreeMap<u32, Vec<u8>>,
+}
+
+/// a problem reading a session file
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionError {
+    /// the file doesn't start with the header of a session file of this version
+    UnknownFormat,
+    Malformed {
+        line: usize,
+    },
+}
+
+impl Session {
+    /// reads a session file
+    pub fn parse(text: &str) -> Result<Session, SessionError> {
+        let mut rest = text
+            .strip_prefix(HEADER)
+            .ok_or(SessionError::UnknownFormat)?;
+
+        let mut session = Session::default();
+        let mut line_number = 1;
+
+        while !rest.is_empty() {
+            line_number += 1;
+
+            let malformed = SessionError::Malformed { line: line_number };
+
+            let (line, after) = rest.split_once('\n').unwrap_or((rest, ""));
+            rest = after;
+
+            let fields: Vec<&str> = line.split(' ').collect();
+
+            match fields.as_slice() {
+                ["source", length] => {
+                    let length = length.parse().map_err(|_| malformed.clone())?;
+                    let source = rest.get(..length).ok_or(malformed.clone())?;
+
+                    line_number += source.matches('\n').count();
+                    session.source = source.to_owned();
+
+                    rest = rest[length..].strip_prefix('\n').ok_or(malformed)?;
+                }
+
+                ["breakpoint", address] => {
+                    session
+                        .breakpoints
+                        .insert(parse_hex(address).ok_or(malformed)?);
+                }
+
+                ["watch", address, action @ ..] => {
+                    let address = parse_hex(address).ok_or(malformed.clone())?;
+
+                    let action = match action {
+                        ["halt"] => SavedWatch::Halt,
+                        ["log"] => SavedWatch::Log,
+                        ["inject", value] => SavedWatch::Inject(parse_hex(value).ok_or(malformed)?),
+                        _ => return Err(malformed),
+                    };
+
+                    session.watchpoints.insert(address, action);
+                }
+
+                ["register", number, value] => {
+                    let number: usize = number.parse().map_err(|_| malformed.clone())?;
+                    let value = parse_hex(value).ok_or(malformed.clone())?;
+
+                    *session
+                        .snapshot
+                        .get_or_insert_default()
+                        .registers
+                        .get_mut(number)
+                        .ok_or(malformed)? = value;
+                }
+
+                ["cpsr", value] => {
+                    session.snapshot.get_or_insert_default().cpsr =
+                        Psr::from_bits(parse_hex(value).ok_or(malformed)?);
+                }
+
+                ["cycles", cycles] => {
+                    session.snapshot.get_or_insert_default().cycles =
+                        cycles.parse().map_err(|_| malformed)?;
+                }
+
+                ["page", address, bytes] => {
+                    let address = parse_hex(address).ok_or(malformed.clone())?;
+                    let bytes = (0..bytes.len())
+                        .step_by(2)
+                        .map(|i| {
+                            bytes
+                                .get(i..i + 2)
+                                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                        })
+                        .collect::<Option<Vec<u8>>>()
+                        .ok_or(malformed)?;
+
+                    session
+                        .snapshot
+                        .get_or_insert_default()
+                        .pages
+                        .insert(address, bytes);
+                }
+
+                [""] => {}
+
+                _ => return Err(malformed),
+            }
+        }
+
+        Ok(session)
+    }
+}
+
+impl Display for Session {
+    /// writes the session file
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{HEADER}")?;
+        writeln!(f, "source {}\n{}", self.source.len(), self.source)?;
+
+        for address in &self.breakpoints {
+            writeln!(f, "breakpoint {address:#010X}")?;
+        }
+
+        for (address, action) in &self.watchpoints {
+            match action {
+                SavedWatch::Halt => writeln!(f, "watch {address:#010X} halt")?,
+                SavedWatch::Log => writeln!(f, "watch {address:#010X} log")?,
+                SavedWatch::Inject(value) => {
+                    writeln!(f, "watch {address:#010X} inject {value:#010X}")?
+                }
+            }
+        }
+
+        if let Some(snapshot) = &self.snapshot {
+            for (number, value) in snapshot.registers.iter().enumerate() {
+                writeln!(f, "register {number} {value:#010X}")?;
+            }
+
+            writeln!(f, "cpsr {:#010X}", snapshot.cpsr.bits())?;
+            writeln!(f, "cycles {}", snapshot.cycles)?;
+
+            for (address, bytes) in &snapshot.pages {
+                write!(f, "page {address:#010X} ")?;
+                for byte in bytes {
+                    write!(f, "{byte:02X}")?;
+                }
+                writeln!(f)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Emulator {
+    /// the current session, for saving to a file with [`Session::to_string`]
+    ///
+    /// The breakpoints are kept by the frontend, so it passes them in. Watchpoints that call a closure can't be saved, so they are left out.
+    /// With `snapshot`, the registers and every page of memory that has been written are saved, so the session resumes exactly where it left off, rather than from the start of the program.
+    pub fn save_session(&self, breakpoints: &BTreeSet<u32>, snapshot: bool) -> Session {
+        let watchpoints = self
+            .watchpoints
+            .iter()
+            .filter_map(|(address, action)| {
+                let saved = match action {
+                    WatchAction::Halt => SavedWatch::Halt,
+                    WatchAction::Log => SavedWatch::Log,
+                    WatchAction::Inject(value) => SavedWatch::Inject(*value),
+                    WatchAction::Call(_) => return None,
+                };
+
+                Some((*address, saved))
+            })
+            .collect();
+
+        Session {
+            source: self
+                .program()
+                .map(|program| program.source().to_owned())
+                .unwrap_or_default(),
+            breakpoints: breakpoints.clone(),
+            watchpoints,
+            snapshot: snapshot.then(|| self.snapshot()),
+        }
+    }
+
+    /// assembles the session's source with `options` and loads it, then sets its watchpoints and restores its snapshot, if it has one
+    ///
+    /// This is meant for a new emulator, as memory that the session didn't write isn't cleared.
+    pub fn restore_session(
+        &mut self,
+        session: &Session,
+        options: AssemblyOptions,
+    ) -> Result<LoadReport, AssemblyError> {
+        let report = self.load(Program::assemble_with(&session.source, options)?);
+
+        for (address, action) in &session.watchpoints {
+            let action = match action {
+                SavedWatch::Halt => WatchAction::Halt,
+                SavedWatch::Log => WatchAction::Log,
+                SavedWatch::Inject(value) => WatchAction::Inject(*value),
+            };
+
+            self.watch(*address, action);
+        }
+
+        if let Some(snapshot) = &session.snapshot {
+            self.restore_snapshot(snapshot);
+        }
+
+        Ok(report)
+    }
+
+    /// the registers, CPSR and written memory of the machine
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            registers: self.registers.0,
+            cpsr: self.cpsr,
+            cycles: self.cycles,
+            pages: self
+                .written_pages
+                .iter()
+                .map(|page| {
+                    let start = page * PAGE_SIZE;
+                    let end = (start + PAGE_SIZE).min(self.memory.len());
+
+                    (start as u32, self.memory[start..end].to_vec())
+                })
+                .collect(),
+        }
+    }
+
+    /// puts the machine back in the state of a snapshot
+    pub fn restore_snapshot(&mut self, snapshot: &Snapshot) {
+        for (address, bytes) in &snapshot.pages {
+            self.write_memory(*address as usize, bytes);
+        }
+
+        self.registers.0 = snapshot.registers;
+        self.cpsr = snapshot.cpsr;
+        self.cycles = snapshot.cycles;
+        self.jumped_from = None;
+
+        self.restart_checkpoints();
+        self.publish();
+    }
+}
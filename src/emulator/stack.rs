@@ -0,0 +1,103 @@
+#[cfg(feature = "assembler")]
+use crate::parser::Symbol;
+
+use super::Emulator;
+
+/// the address LR is set to by default, which halts the emulator when the program returns to it
+pub const HALT_ADDRESS: u32 = 0xFFFF_FFF0;
+
+/// how SP and LR are set up when a program is loaded, in place of the boilerplate at the start of each program, see [`Emulator::set_stack`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackSetup {
+    /// where SP starts, or `None` to leave it alone
+    pub pointer: Option<StackPointer>,
+    /// the address LR starts at, so that returning from the program's entry point (e.g. `MOV PC, LR`) halts the emulator, or `None` to leave LR alone
+    pub return_address: Option<u32>,
+    /// fills the stack with a pattern, so that reading a slot that was never pushed stands out
+    pub fill: Option<StackFill>,
+}
+
+/// where SP starts
+#[derive(Debug, Clone, PartialEq)]
+pub enum StackPointer {
+    Address(u32),
+    /// the value of a symbol defined by the loaded program, e.g. `_stack`
+    ///
+    /// If the program doesn't define it, SP is left alone.
+    #[cfg(feature = "assembler")]
+    Symbol(String),
+}
+
+/// a word repeated through the `size` bytes below the initial SP
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StackFill {
+    pub size: u32,
+    pub pattern: u32,
+}
+
+impl Default for StackSetup {
+    /// leaves SP alone, and sets LR to [`HALT_ADDRESS`]
+    fn default() -> Self {
+        StackSetup {
+            pointer: None,
+            return_address: Some(HALT_ADDRESS),
+            fill: None,
+        }
+    }
+}
+
+impl Emulator {
+    /// sets up SP, LR and the stack now, and again whenever a program is loaded with [`Emulator::load`] or [`Emulator::load_image`]
+    ///
+    /// Loading a module with [`Emulator::load_module`] doesn't set them up again, as it doesn't start a new program.
+    pub fn set_stack(&mut self, setup: StackSetup) {
+        self.stack = Some(setup);
+
+        self.apply_stack();
+        self.publish();
+    }
+
+    /// whether the PC is at the return address set by [`Emulator::set_stack`], so the program has returned from its entry point
+    pub(super) fn returned(&self) -> bool {
+        self.stack
+            .as_ref()
+            .and_then(|stack| stack.return_address)
+            .is_some_and(|address| address == self.registers[15])
+    }
+
+    /// sets SP and LR, and fills the stack, as set by [`Emulator::set_stack`]
+    pub(super) fn apply_stack(&mut self) {
+        let Some(stack) = self.stack.clone() else {
+            return;
+        };
+
+        match &stack.pointer {
+            Some(StackPointer::Address(address)) => self.registers[13] = *address,
+            #[cfg(feature = "assembler")]
+            Some(StackPointer::Symbol(name)) => {
+                if let Some(value) = self.symbol_table().get(&Symbol(name.clone())) {
+                    self.registers[13] = *value;
+                }
+            }
+            None => {}
+        }
+
+        if let Some(address) = stack.return_address {
+            self.registers[14] = address;
+        }
+
+        if let Some(StackFill { size, pattern }) = stack.fill {
+            let top = self.registers[13] as usize;
+            let bottom = top.saturating_sub(size as usize);
+
+            let bytes: Vec<u8> = pattern
+                .to_be_bytes()
+                .into_iter()
+                .cycle()
+                .take(top - bottom)
+                .collect();
+
+            self.write_memory(bottom, &bytes);
+        }
+    }
+}
@@ -0,0 +1,399 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+use crate::ir::register_number;
+
+use super::{parse_number, Emulator, SvcHandler};
+
+/// the SVCs a program can call, so that another institution's SVC conventions can be emulated, see [`Emulator::mount_svcs`]
+///
+/// It is built like `SvcSpec::new().define(SvcDefinition::new(0, "putc", SvcBehavior::PrintChar).arguments(&[1]))`,
+/// or read from a TOML file with [`SvcSpec::parse`], with a `[[svc]]` table for each SVC:
+///
+/// ```toml
+/// [[svc]]
+/// number = 0
+/// name = "putc"
+/// behavior = "print_char"
+/// arguments = ["R1"]
+/// ```
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SvcSpec {
+    pub svcs: Vec<SvcDefinition>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvcDefinition {
+    pub number: u32,
+    pub name: String,
+    pub behavior: SvcBehavior,
+    /// the registers the SVC takes its arguments from, in order, which is R0 for the behaviors that take an argument by default
+    pub arguments: Vec<u8>,
+    /// the register the SVC writes its result to, which is R0 for [`SvcBehavior::ReadChar`] by default
+    pub result: Option<u8>,
+}
+
+/// what an SVC does, written in snake case in a spec, e.g. `print_char`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SvcBehavior {
+    /// prints the character in the first argument
+    PrintChar,
+    /// prints the null-terminated string at the address in the first argument
+    PrintString,
+    /// prints the signed number in the first argument, in decimal
+    PrintDecimal,
+    /// prints the number in the first argument as 8 hexadecimal digits
+    PrintHex,
+    /// reads a character of input into the result, waiting if there isn't one
+    ReadChar,
+    /// halts the program, with an exit code of 0
+    Halt,
+    /// exits with the code in the first argument
+    Exit,
+    /// calls the host's handler registered under the SVC's name, which is given the SVC's number like any [`SvcHandler`]
+    Custom,
+}
+
+/// a problem reading a spec, or mounting it, with one-based line numbers
+#[derive(Debug, Clone, PartialEq)]
+pub enum SvcSpecError {
+    /// a line isn't a `[[svc]]` header, a key and value, or a comment
+    Malformed {
+        line: usize,
+    },
+    /// a key that isn't a field of an SVC, or a value of the wrong type for it
+    InvalidField {
+        line: usize,
+        key: String,
+    },
+    /// an SVC doesn't have its number, name or behavior, with the line of its header
+    MissingField {
+        line: usize,
+        key: &'static str,
+    },
+    DuplicateNumber {
+        number: u32,
+    },
+    /// a custom SVC has no handler with its name
+    MissingCallback {
+        name: String,
+    },
+}
+
+/// the fields of an SVC in a spec, by key, along with the line each is on
+type Fields = HashMap<String, (usize, Value)>;
+
+/// a value in a spec
+enum Value {
+    Integer(u32),
+    String(String),
+    Array(Vec<String>),
+}
+
+impl SvcSpec {
+    pub fn new() -> SvcSpec {
+        SvcSpec::default()
+    }
+
+    /// the system functions described in the README, which are the ones [`Emulator::mount_console`] and [`Emulator::mount_exit`] give
+    pub fn standard() -> SvcSpec {
+        SvcSpec::new()
+            .define(SvcDefinition::new(0, "print_char", SvcBehavior::PrintChar))
+            .define(SvcDefinition::new(1, "read_char", SvcBehavior::ReadChar))
+            .define(SvcDefinition::new(2, "halt", SvcBehavior::Halt))
+            .define(SvcDefinition::new(
+                3,
+                "print_string",
+                SvcBehavior::PrintString,
+            ))
+            .define(SvcDefinition::new(
+                4,
+                "print_decimal",
+                SvcBehavior::PrintDecimal,
+            ))
+    }
+
+    pub fn define(mut self, svc: SvcDefinition) -> SvcSpec {
+        self.svcs.push(svc);
+        self
+    }
+
+    /// the SVC with `number`, e.g. to show its name alongside the instruction that calls it
+    pub fn get(&self, number: u32) -> Option<&SvcDefinition> {
+        self.svcs.iter().find(|svc| svc.number == number)
+    }
+
+    /// reads a spec from TOML, which can only use the parts of TOML a spec needs: `[[svc]]` headers, comments, and keys whose values are numbers, strings or arrays of strings on one line
+    pub fn parse(text: &str) -> Result<SvcSpec, SvcSpecError> {
+        // the fields of each SVC, along with the line of its header
+        let mut tables: Vec<(usize, Fields)> = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = strip_comment(line).trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            if line == "[[svc]]" {
+                tables.push((line_number, HashMap::new()));
+                continue;
+            }
+
+            let malformed = SvcSpecError::Malformed { line: line_number };
+
+            let (key, value) = line.split_once('=').ok_or(malformed.clone())?;
+            let value = parse_value(value.trim()).ok_or(malformed.clone())?;
+
+            tables
+                .last_mut()
+                .ok_or(malformed)?
+                .1
+                .insert(key.trim().to_owned(), (line_number, value));
+        }
+
+        let mut spec = SvcSpec::new();
+        let mut numbers = HashSet::new();
+
+        for (header, mut fields) in tables {
+            let number = match fields.remove("number") {
+                Some((_, Value::Integer(number))) => number,
+                Some((line, _)) => return Err(invalid(line, "number")),
+                None => return Err(missing(header, "number")),
+            };
+
+            let name = match fields.remove("name") {
+                Some((_, Value::String(name))) => name,
+                Some((line, _)) => return Err(invalid(line, "name")),
+                None => return Err(missing(header, "name")),
+            };
+
+            let behavior = match fields.remove("behavior") {
+                Some((line, Value::String(behavior))) => {
+                    SvcBehavior::from_name(&behavior).ok_or(invalid(line, "behavior"))?
+                }
+                Some((line, _)) => return Err(invalid(line, "behavior")),
+                None => return Err(missing(header, "behavior")),
+            };
+
+            let mut svc = SvcDefinition::new(number, &name, behavior);
+
+            if let Some((line, value)) = fields.remove("arguments") {
+                let Value::Array(registers) = value else {
+                    return Err(invalid(line, "arguments"));
+                };
+
+                svc.arguments = registers
+                    .iter()
+                    .map(|register| register_number(register))
+                    .collect::<Option<_>>()
+                    .ok_or(invalid(line, "arguments"))?;
+            }
+
+            if let Some((line, value)) = fields.remove("result") {
+                svc.result = match value {
+                    Value::String(register) => register_number(&register),
+                    _ => None,
+                };
+
+                if svc.result.is_none() {
+                    return Err(invalid(line, "result"));
+                }
+            }
+
+            if let Some((key, (line, _))) = fields.into_iter().next() {
+                return Err(SvcSpecError::InvalidField { line, key });
+            }
+
+            if !numbers.insert(number) {
+                return Err(SvcSpecError::DuplicateNumber { number });
+            }
+
+            spec.svcs.push(svc);
+        }
+
+        Ok(spec)
+    }
+}
+
+impl SvcDefinition {
+    /// an SVC that takes its argument from, and writes its result to, R0
+    pub fn new(number: u32, name: &str, behavior: SvcBehavior) -> SvcDefinition {
+        let (arguments, result) = match behavior {
+            SvcBehavior::PrintChar
+            | SvcBehavior::PrintString
+            | SvcBehavior::PrintDecimal
+            | SvcBehavior::PrintHex
+            | SvcBehavior::Exit => (vec![0], None),
+            SvcBehavior::ReadChar => (Vec::new(), Some(0)),
+            SvcBehavior::Halt | SvcBehavior::Custom => (Vec::new(), None),
+        };
+
+        SvcDefinition {
+            number,
+            name: name.to_owned(),
+            behavior,
+            arguments,
+            result,
+        }
+    }
+
+    pub fn arguments(mut self, registers: &[u8]) -> SvcDefinition {
+        self.arguments = registers.to_vec();
+        self
+    }
+
+    pub fn result(mut self, register: u8) -> SvcDefinition {
+        self.result = Some(register);
+        self
+    }
+}
+
+impl SvcBehavior {
+    fn from_name(name: &str) -> Option<SvcBehavior> {
+        Some(match name {
+            "print_char" => SvcBehavior::PrintChar,
+            "print_string" => SvcBehavior::PrintString,
+            "print_decimal" => SvcBehavior::PrintDecimal,
+            "print_hex" => SvcBehavior::PrintHex,
+            "read_char" => SvcBehavior::ReadChar,
+            "halt" => SvcBehavior::Halt,
+            "exit" => SvcBehavior::Exit,
+            "custom" => SvcBehavior::Custom,
+            _ => return None,
+        })
+    }
+}
+
+impl Emulator {
+    /// registers a handler for each SVC of a spec, replacing any handlers for the same numbers
+    ///
+    /// The print and read behaviors go through the console, so it should be mounted with [`Emulator::mount_console`] first.
+    /// Each custom SVC calls the handler in `callbacks` with its name, which is shared by all the custom SVCs with that name, and nothing is registered if one is missing.
+    pub fn mount_svcs(
+        &mut self,
+        spec: &SvcSpec,
+        callbacks: HashMap<String, SvcHandler>,
+    ) -> Result<(), SvcSpecError> {
+        if let Some(svc) = spec
+            .svcs
+            .iter()
+            .find(|svc| svc.behavior == SvcBehavior::Custom && !callbacks.contains_key(&svc.name))
+        {
+            return Err(SvcSpecError::MissingCallback {
+                name: svc.name.clone(),
+            });
+        }
+
+        let callbacks: HashMap<String, Arc<Mutex<SvcHandler>>> = callbacks
+            .into_iter()
+            .map(|(name, callback)| (name, Arc::new(Mutex::new(callback))))
+            .collect();
+
+        for svc in &spec.svcs {
+            let argument = svc.arguments.first().copied().unwrap_or(0);
+            let result = svc.result.unwrap_or(0);
+
+            let handler: SvcHandler = match svc.behavior {
+                SvcBehavior::PrintChar => Box::new(move |emulator: &mut Emulator, _| {
+                    emulator.print(&[emulator.registers[argument] as u8]);
+                }),
+                SvcBehavior::PrintString => Box::new(move |emulator: &mut Emulator, _| {
+                    let string = emulator.c_string(emulator.registers[argument]);
+
+                    emulator.print(&string);
+                }),
+                SvcBehavior::PrintDecimal => Box::new(move |emulator: &mut Emulator, _| {
+                    let number = emulator.registers[argument] as i32;
+
+                    emulator.print(number.to_string().as_bytes());
+                }),
+                SvcBehavior::PrintHex => Box::new(move |emulator: &mut Emulator, _| {
+                    let number = emulator.registers[argument];
+
+                    emulator.print(format!("{number:08X}").as_bytes());
+                }),
+                SvcBehavior::ReadChar => Box::new(move |emulator: &mut Emulator, _| match emulator
+                    .read_input()
+                {
+                    Some(byte) => emulator.registers[result] = byte as u32,
+                    None => emulator.wait(),
+                }),
+                SvcBehavior::Halt => Box::new(|emulator: &mut Emulator, _| {
+                    emulator.exit_code = Some(0);
+                }),
+                SvcBehavior::Exit => Box::new(move |emulator: &mut Emulator, _| {
+                    emulator.exit_code = Some(emulator.registers[argument]);
+                }),
+                SvcBehavior::Custom => {
+                    let callback = Arc::clone(&callbacks[&svc.name]);
+
+                    Box::new(move |emulator: &mut Emulator, number| {
+                        callback.lock().unwrap()(emulator, number)
+                    })
+                }
+            };
+
+            self.svc_handlers.insert(svc.number, handler);
+        }
+
+        Ok(())
+    }
+}
+
+fn invalid(line: usize, key: &str) -> SvcSpecError {
+    SvcSpecError::InvalidField {
+        line,
+        key: key.to_owned(),
+    }
+}
+
+fn missing(line: usize, key: &'static str) -> SvcSpecError {
+    SvcSpecError::MissingField { line, key }
+}
+
+/// parses a number like `4` or `0x100`, a string like `"putc"`, or an array of strings like `["R0", "R1"]`
+fn parse_value(text: &str) -> Option<Value> {
+    if let Some(items) = text
+        .strip_prefix('[')
+        .and_then(|text| text.strip_suffix(']'))
+    {
+        return items
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(parse_string)
+            .collect::<Option<_>>()
+            .map(Value::Array);
+    }
+
+    if text.starts_with('"') {
+        return parse_string(text).map(Value::String);
+    }
+
+    parse_number(text).map(Value::Integer)
+}
+
+/// the part of a line before a `#` that starts a comment, ignoring any inside strings
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+
+    for (index, character) in line.char_indices() {
+        match character {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..index],
+            _ => {}
+        }
+    }
+
+    line
+}
+
+fn parse_string(text: &str) -> Option<String> {
+    let string = text.strip_prefix('"')?.strip_suffix('"')?;
+
+    (!string.contains('"')).then(|| string.to_owned())
+}
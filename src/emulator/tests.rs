@@ -3,7 +3,27 @@ use std::fs;
 extern crate test;
 use test::{black_box, Bencher};
 
-use super::Emulator;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use super::{
+    AccessCounts, Benchmark, BenchmarkInput, Board, BranchDecision, CallingConvention, Clock,
+    ConditionCoverage, ConformanceSuite, ConventionViolation, CostReport, CostTable,
+    CostTableError, DivergenceKind, EditKind, Emulator, ExecutionError, FileSystem, Flag, Heap,
+    InstructionClass, Interrupt, Jump, LoadError, MachineView, ManualEdit, Mismatch, OutputChunk,
+    Peripheral, PeripheralError, Poll, ProcessorMode, Psr, Random, RegisterBehavior, RunStatus,
+    SavedWatch, Script, ScriptError, SelfModifyingCode, Session, SessionError, SourceLine,
+    StackFill, StackPointer, StackSetup, StateLocation, SvcHandler, SvcSpec, SvcSpecError, Trace,
+    TraceError, UndefinedSvc, VectorError, VectorFailureKind, WatchAction, WatchHit, HALT_ADDRESS,
+};
+use crate::{
+    assembler::{AssemblyError, AssemblyWarning, Program},
+    ir::{ByteOrder, Condition, RegisterList},
+    parser::Symbol,
+};
 
 #[bench]
 fn test_large_file(b: &mut Bencher) {
@@ -54,4 +74,2207 @@ fn test_large_file_with_pseudo_instructions(b: &mut Bencher) {
 
         black_box(emulator);
     });
-}
\ No newline at end of file
+}
+#[test]
+fn test_execute_source_line() {
+    let mut emulator = Emulator::new();
+
+    emulator
+        .assemble("start\tMOV R0, #1\n\tB start\nend\tDEFW 0\n")
+        .unwrap();
+
+    emulator.execute_source_line("ADD R0, R0, #41").unwrap();
+    assert_eq!(emulator.registers[0], 41);
+    assert_eq!(emulator.registers[15], 0);
+
+    emulator.execute_source_line("ADD R1, R0, #end").unwrap();
+    assert_eq!(emulator.registers[1], 41 + 8);
+
+    emulator.execute_source_line("MOVS R2, #0").unwrap();
+    assert!(emulator.cpsr.z());
+
+    assert!(matches!(
+        emulator.execute_source_line("DEFW 1"),
+        Err(ExecutionError::AssemblyError(
+            AssemblyError::NotAnInstruction(_)
+        ))
+    ));
+    assert!(emulator
+        .execute_source_line("ADD R0, R0, #missing")
+        .is_err());
+}
+
+#[test]
+fn test_patch_instruction() {
+    let mut emulator = Emulator::new();
+
+    emulator
+        .assemble("\tADD R0, R0, #1\nend\tADD R1, R1, #2\n")
+        .unwrap();
+
+    emulator.patch_instruction(4, "SUB R1, R0, #end").unwrap();
+
+    let expected = Program::assemble("\tADD R0, R0, #1\nend\tSUB R1, R0, #end\n").unwrap();
+
+    assert_eq!(emulator.memory[4..8], expected.statements[1].bytes[..]);
+}
+
+#[test]
+fn test_load_store_multiple() {
+    let mut emulator = Emulator::new();
+
+    emulator
+        .assemble("\tSTMFD SP!, {R0-R2}\n\tLDMFD SP!, {R3-R5}\n")
+        .unwrap();
+
+    emulator.registers[0] = 1;
+    emulator.registers[1] = 2;
+    emulator.registers[2] = 3;
+    emulator.registers[13] = 0x1000;
+
+    emulator.step().unwrap();
+    assert_eq!(emulator.registers[13], 0x1000 - 12);
+    assert_eq!(
+        emulator.memory[0x1000 - 12..0x1000],
+        [0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 3]
+    );
+
+    emulator.step().unwrap();
+    assert_eq!(emulator.registers[13], 0x1000);
+    assert_eq!(emulator.registers.0[3..=5], [1, 2, 3]);
+}
+
+#[test]
+fn test_never_condition() {
+    let mut emulator = Emulator::new();
+
+    // ADDNV R0, R0, R1
+    emulator.load_image(0, &0xF0800001u32.to_be_bytes(), 0);
+    emulator.registers[1] = 1;
+
+    emulator.step().unwrap();
+    assert_eq!(emulator.registers[0], 0);
+    assert_eq!(emulator.registers[15], 4);
+}
+
+#[test]
+fn test_branch_with_link_and_exchange() {
+    let mut emulator = Emulator::new();
+
+    // BLX with an offset of 6 bytes
+    emulator.load_image(0, &0xFB000001u32.to_be_bytes(), 0);
+
+    emulator.step().unwrap();
+    assert_eq!(emulator.registers[14], 4);
+    // the offset is added to the address of the next instruction, as it is for B
+    assert_eq!(emulator.registers[15], 4 + 6);
+    assert!(emulator.cpsr.t());
+}
+
+#[test]
+fn test_branch_and_exchange() {
+    let mut emulator = Emulator::new();
+    emulator
+        .assemble("\tBX\tLR\n\tMOV\tR0, #1\n\tBXEQ\tR1\n")
+        .unwrap();
+    emulator.registers[1] = 0x101;
+    emulator.registers[14] = 8;
+
+    emulator.step().unwrap();
+    assert_eq!(emulator.registers[15], 8);
+    assert!(!emulator.cpsr.t());
+
+    // it is conditional, unlike BLX
+    emulator.step().unwrap();
+    assert_eq!(emulator.registers[15], 12);
+
+    // and bit 0 of the address switches to Thumb state
+    emulator.cpsr.set_z(true);
+    emulator.registers[15] = 8;
+    emulator.step().unwrap();
+    assert_eq!(emulator.registers[15], 0x100);
+    assert!(emulator.cpsr.t());
+}
+
+#[test]
+fn test_calling_convention() {
+    let mut emulator = Emulator::new();
+    emulator
+        .assemble(
+            "\tBL\tclobber\n\tBL\tkeep\n\tSVC\t2\n\
+             clobber\tMOV\tR0, R0\n\tMOV\tR4, R5\n\tMOV\tPC, LR\n\
+             keep\tMOV\tR0, R0\n\tMOV\tR0, R5\n\tMOV\tPC, LR\n",
+        )
+        .unwrap();
+    emulator.set_calling_convention(Some(CallingConvention::new()));
+    emulator.registers[4] = 1;
+    emulator.registers[5] = 5;
+
+    // the routine has returned once the PC is back after the BL, however it gets there
+    for _ in 0..3 {
+        emulator.step().unwrap();
+    }
+    assert_eq!(emulator.registers[15], 4);
+    assert_eq!(
+        emulator.convention_violations(),
+        [ConventionViolation {
+            call: 0,
+            routine: 12,
+            label: Some("clobber".to_owned()),
+            registers: vec![(4, 1, 5)],
+        }]
+    );
+    assert_eq!(
+        emulator.convention_violations()[0].to_string(),
+        "clobber (called from 0x00000000) didn't restore R4, which changed from 0x00000001 to 0x00000005"
+    );
+
+    // R0 isn't preserved by the AAPCS, so changing it is fine
+    for _ in 0..3 {
+        emulator.step().unwrap();
+    }
+    assert_eq!(emulator.registers[15], 8);
+    assert_eq!(emulator.convention_violations().len(), 1);
+
+    // lost never returns to its caller, which doesn't hide the return from outer
+    emulator
+        .assemble(
+            "\tBL\touter\n\tSVC\t2\n\
+             outer\tMOV\tR0, R0\n\tMOV\tR12, LR\n\tMOV\tR4, R5\n\tBL\tlost\n\
+             back\tMOV\tR0, R0\n\tMOV\tPC, R12\n\
+             lost\tMOV\tR0, R0\n\tB\tback\n",
+        )
+        .unwrap();
+    emulator.set_calling_convention(Some(CallingConvention::new()));
+    emulator.registers[4] = 1;
+
+    for _ in 0..6 {
+        emulator.step().unwrap();
+    }
+    assert_eq!(emulator.registers[15], 4);
+    assert_eq!(
+        emulator.convention_violations(),
+        [ConventionViolation {
+            call: 0,
+            routine: 8,
+            label: Some("outer".to_owned()),
+            registers: vec![(4, 1, 5)],
+        }]
+    );
+}
+
+#[test]
+fn test_preload() {
+    let mut emulator = Emulator::new();
+
+    emulator.assemble("\tPLD\t[R1, #4]\n").unwrap();
+
+    emulator.step().unwrap();
+    assert_eq!(emulator.registers.0[..15], [0; 15]);
+    assert_eq!(emulator.registers[15], 4);
+}
+
+#[test]
+#[cfg(feature = "disassembler")]
+fn test_trace_step() {
+    let mut emulator = Emulator::new();
+
+    emulator
+        .assemble("print_str\tEQU\t3\n\tPLD\t[R1]\n\tSVC\tprint_str\n")
+        .unwrap();
+
+    assert_eq!(emulator.trace_step().unwrap(), (0, "PLD [R1]".to_owned()));
+    assert_eq!(emulator.disassemble(4).unwrap(), "SVC 3 ; print_str");
+}
+
+#[test]
+fn test_supervisor_call() {
+    let mut emulator = Emulator::new();
+
+    emulator.assemble("\tSVC\t0\n\tSVC\t1\n").unwrap();
+
+    emulator.register_svc(0, |emulator, _| {
+        emulator.set_register(0, emulator.register(0) + 1)
+    });
+
+    emulator.step().unwrap();
+    assert_eq!(emulator.register(0), 1);
+
+    // undefined SVCs halt by default
+    assert!(matches!(
+        emulator.step(),
+        Err(ExecutionError::UndefinedSvc {
+            address: 4,
+            number: 1
+        })
+    ));
+    assert_eq!(emulator.register(15), 4);
+
+    let numbers = Arc::new(Mutex::new(Vec::new()));
+    let fallback_numbers = numbers.clone();
+    emulator.set_undefined_svc(UndefinedSvc::Fallback(Box::new(move |_, number| {
+        fallback_numbers.lock().unwrap().push(number)
+    })));
+
+    emulator.step().unwrap();
+    assert_eq!(*numbers.lock().unwrap(), [1]);
+    assert_eq!(emulator.register(15), 8);
+
+    emulator.set_undefined_svc(UndefinedSvc::RaiseException);
+    emulator.set_register(15, 4);
+
+    emulator.step().unwrap();
+    assert_eq!(emulator.register(14), 8);
+    assert_eq!(emulator.register(15), 0x08);
+}
+
+#[test]
+fn test_filesystem() {
+    let mut emulator = Emulator::new();
+
+    let mut filesystem = FileSystem::new();
+    filesystem.write_file("in.txt", b"hello").unwrap();
+    emulator.mount(filesystem, 16);
+
+    emulator.memory[0x100..0x107].copy_from_slice(b"in.txt\0");
+    emulator.memory[0x110..0x118].copy_from_slice(b"out.txt\0");
+    emulator.memory[0x120..0x125].copy_from_slice(b"../x\0");
+
+    // each call sets the arguments, then executes the SVC
+    let mut call = |number: u32, arguments: [u32; 3]| {
+        emulator.registers.0[..3].copy_from_slice(&arguments);
+        emulator
+            .execute_source_line(&format!("SVC {}", number))
+            .unwrap();
+        emulator.registers[0]
+    };
+
+    let input = call(16, [0x100, 0, 0]);
+    assert_eq!(call(17, [input, 0x200, 3]), 3);
+    assert_eq!(call(17, [input, 0x203, 10]), 2);
+    assert_eq!(call(17, [input, 0x205, 10]), 0);
+    // the file was opened for reading
+    assert_eq!(call(18, [input, 0x200, 5]), u32::MAX);
+    assert_eq!(call(19, [input, 0, 0]), 0);
+    assert_eq!(call(19, [input, 0, 0]), u32::MAX);
+
+    let output = call(16, [0x110, 1, 0]);
+    assert_eq!(call(18, [output, 0x200, 5]), 5);
+    assert_eq!(call(19, [output, 0, 0]), 0);
+
+    let output = call(16, [0x110, 2, 0]);
+    assert_eq!(call(18, [output, 0x200, 1]), 1);
+
+    // paths can't leave the filesystem
+    assert_eq!(call(16, [0x120, 1, 0]), u32::MAX);
+    // the file doesn't exist
+    assert_eq!(call(16, [0x110 + 4, 0, 0]), u32::MAX);
+
+    assert_eq!(
+        emulator.filesystem().unwrap().read_file("out.txt").unwrap(),
+        b"helloh"
+    );
+}
+
+#[test]
+fn test_clock() {
+    let mut emulator = Emulator::new();
+
+    emulator.mount_clock(Clock::Cycles { hertz: 500_000 }, 5);
+    emulator
+        .assemble("\tPLD\t[R0]\n\tSVC\t5\n\tSVC\t6\n")
+        .unwrap();
+
+    emulator.step().unwrap();
+    emulator.step().unwrap();
+    assert_eq!((emulator.registers[0], emulator.registers[1]), (1, 0));
+
+    // each cycle takes 2 microseconds at 500 kHz
+    emulator.step().unwrap();
+    assert_eq!((emulator.registers[0], emulator.registers[1]), (4, 0));
+    assert_eq!(emulator.cycles(), 3);
+}
+
+#[test]
+fn test_random() {
+    let run = |seed: u64| {
+        let mut emulator = Emulator::new();
+
+        emulator.mount_random(Random::new(seed), 7);
+        emulator.assemble(&"\tSVC\t7\n".repeat(8)).unwrap();
+
+        (0..8)
+            .map(|_| {
+                emulator.registers[0] = 6;
+                emulator.step().unwrap();
+                emulator.registers[0]
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // the same seed gives the same numbers
+    assert_eq!(run(42), run(42));
+    assert_ne!(run(42), run(43));
+    assert!(run(42).iter().all(|n| *n < 6));
+}
+
+#[test]
+fn test_poll_step() {
+    let mut emulator = Emulator::new();
+
+    let input = Arc::new(Mutex::new(VecDeque::new()));
+    let handler_input = input.clone();
+    emulator.register_svc(1, move |emulator, _| {
+        match handler_input.lock().unwrap().pop_front() {
+            Some(char) => emulator.registers[0] = char as u32,
+            None => emulator.wait(),
+        }
+    });
+
+    emulator
+        .assemble(&format!(
+            "{}\tSVC\t1\n\tPLD\t[R0]\n",
+            "\tPLD\t[R0]\n".repeat(3)
+        ))
+        .unwrap();
+
+    assert_eq!(emulator.poll_step(2).unwrap(), Poll::Yielded);
+    assert_eq!(emulator.registers[15], 8);
+
+    assert_eq!(emulator.poll_step(100).unwrap(), Poll::Waiting);
+    assert_eq!(emulator.registers[15], 12);
+    assert_eq!(emulator.poll_step(100).unwrap(), Poll::Waiting);
+
+    input.lock().unwrap().push_back(b'a');
+    assert_eq!(emulator.poll_step(2).unwrap(), Poll::Yielded);
+    assert_eq!(emulator.registers[0], b'a' as u32);
+    assert_eq!(emulator.registers[15], 20);
+}
+
+#[test]
+fn test_threads() {
+    fn assert_send<T: Send>() {}
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send::<Emulator>();
+    assert_send_sync::<MachineView>();
+    assert_send_sync::<Program>();
+
+    let program = Arc::new(Program::assemble("\tSVC\t0\n\tPLD\t[R0]\n").unwrap());
+
+    let mut emulator = Emulator::new();
+    emulator.register_svc(0, |emulator, _| emulator.set_register(0, 7));
+    emulator.load(program.clone());
+
+    // the program is shared, not copied
+    assert!(Arc::ptr_eq(emulator.program().unwrap(), &program));
+
+    let view = emulator.view();
+    assert_eq!(view.register(15), 0);
+
+    thread::spawn(move || {
+        emulator.step().unwrap();
+        emulator.step().unwrap();
+    })
+    .join()
+    .unwrap();
+
+    assert_eq!(view.register(0), 7);
+    assert_eq!(view.register(15), 8);
+    assert_eq!(view.cycles(), 2);
+    assert_eq!(view.cpsr().mode(), Some(ProcessorMode::User));
+}
+
+#[test]
+fn test_flag_explanations() {
+    let mut emulator = Emulator::new();
+
+    emulator.execute_source_line("ADDS R1, R0, #1").unwrap();
+    assert!(emulator.flag_changes().is_empty());
+
+    emulator.set_explain_flags(true);
+    emulator.set_register(0, 0x7FFFFFFF);
+    emulator.execute_source_line("ADDS R1, R0, #1").unwrap();
+
+    let explanations: Vec<String> = emulator
+        .flag_changes()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+
+    assert_eq!(
+        explanations,
+        [
+            "N set: bit 31 of the result 0x80000000 is set, so it is negative",
+            "Z clear: the result 0x80000000 isn't zero",
+            "C clear: no unsigned overflow in ADD 0x7FFFFFFF + 0x00000001",
+            "V set: signed overflow adding 0x7FFFFFFF + 0x00000001 in ADD",
+        ]
+    );
+
+    emulator.execute_source_line("CMP R0, R0").unwrap();
+
+    let carry = &emulator.flag_changes()[2];
+    assert_eq!(carry.flag, Flag::C);
+    assert!(carry.changed);
+    assert_eq!(
+        carry.to_string(),
+        "C set: borrow did not occur in CMP 0x7FFFFFFF - 0x7FFFFFFF"
+    );
+    assert!(emulator.cpsr().z());
+    assert!(!emulator.cpsr().v());
+
+    // instructions that don't set flags don't explain any
+    emulator.execute_source_line("ADD R1, R0, #1").unwrap();
+    assert!(emulator.flag_changes().is_empty());
+}
+
+#[test]
+fn test_condition_coverage() {
+    let mut emulator = Emulator::new();
+    emulator
+        .assemble("\tPLD\t[R0]\n\tBEQ\tskip\nskip\tBNE\tskip\n")
+        .unwrap();
+
+    emulator.step().unwrap();
+    assert_eq!(emulator.condition_coverage(), None);
+
+    emulator.set_condition_coverage(true);
+
+    // Z is clear, so the BEQ falls through and the BNE branches
+    emulator.step().unwrap();
+    emulator.step().unwrap();
+
+    let coverage = emulator.condition_coverage().unwrap();
+    assert_eq!(
+        coverage[&4],
+        ConditionCoverage {
+            passed: 0,
+            failed: 1
+        }
+    );
+    assert_eq!(
+        coverage[&8],
+        ConditionCoverage {
+            passed: 1,
+            failed: 0
+        }
+    );
+    // the PLD isn't conditional, so it isn't counted
+    assert_eq!(coverage.len(), 2);
+    assert_eq!(emulator.never_taken(), [4]);
+
+    let mut cpsr = emulator.cpsr();
+    cpsr.set_z(true);
+    emulator.set_cpsr(cpsr);
+    emulator.set_register(15, 4);
+    emulator.step().unwrap();
+
+    assert!(emulator.condition_coverage().unwrap()[&4].is_complete());
+    assert!(emulator.never_taken().is_empty());
+}
+
+#[test]
+fn test_why_branch() {
+    let mut emulator = Emulator::new();
+    emulator
+        .assemble("\tSUBS\tR0, R0, R1\n\tPLD\t[R0]\n\tBEQ\tdone\ndone\tPLD\t[R0]\n")
+        .unwrap();
+
+    assert_eq!(emulator.why_branch(), None);
+
+    emulator.set_register(0, 1);
+    emulator.step().unwrap();
+    emulator.step().unwrap();
+    emulator.step().unwrap();
+
+    // the SUBS cleared Z, so the BEQ wasn't taken
+    let decision = emulator.why_branch().unwrap();
+    assert_eq!(
+        decision,
+        &BranchDecision {
+            address: 8,
+            condition: Condition::EQ,
+            taken: false,
+            flags: decision.flags,
+            flags_set_by: Some(0),
+        }
+    );
+    assert!(!decision.flags.z());
+
+    emulator.set_register(0, 0);
+    emulator.set_register(15, 0);
+    emulator.step().unwrap();
+    emulator.step().unwrap();
+    emulator.step().unwrap();
+
+    assert!(emulator.why_branch().unwrap().taken);
+    assert_eq!(emulator.flags_set_by(), Some(0));
+}
+
+#[test]
+fn test_memory_heatmap() {
+    let mut emulator = Emulator::new();
+
+    emulator
+        .assemble("\tSTMFD SP!, {R0-R2}\n\tLDMFD SP!, {R3-R4}\n")
+        .unwrap();
+
+    emulator.set_memory_heatmap(true);
+    emulator.registers[13] = 0x1000;
+
+    emulator.step().unwrap();
+    emulator.step().unwrap();
+
+    let heatmap = emulator.memory_heatmap().unwrap();
+    assert_eq!(
+        heatmap.get(0x1000 - 12),
+        AccessCounts {
+            reads: 1,
+            writes: 1
+        }
+    );
+    assert_eq!(
+        heatmap.get(0x1000 - 4),
+        AccessCounts {
+            reads: 0,
+            writes: 1
+        }
+    );
+    // fetching the instructions isn't counted
+    assert_eq!(heatmap.get(0), AccessCounts::default());
+    assert_eq!(heatmap.iter().count(), 3);
+
+    let buckets = heatmap.buckets(0xFF0..0x1000, 8);
+    assert_eq!(buckets.len(), 2);
+    assert_eq!(buckets[0].addresses, 0xFF0..0xFF8);
+    assert_eq!(
+        buckets[0].counts,
+        AccessCounts {
+            reads: 1,
+            writes: 1
+        }
+    );
+    assert_eq!(
+        buckets[1].counts,
+        AccessCounts {
+            reads: 1,
+            writes: 2
+        }
+    );
+}
+
+#[test]
+fn test_checkpoints() {
+    let mut emulator = Emulator::new();
+
+    emulator
+        .assemble(
+            "\tSTMFD SP!, {R0-R1}\n\tSUBS R0, R0, R1\n\tSTMFD SP!, {R0}\n\tLDMFD SP!, {R2-R3}\n\tADDS R1, R2, R3\n\tSTMFD SP!, {R1-R3}\n",
+        )
+        .unwrap();
+
+    emulator.registers[0] = 5;
+    emulator.registers[1] = 3;
+    emulator.registers[13] = 0x1000;
+    emulator.set_checkpoint_interval(Some(2));
+
+    let snapshot = |emulator: &Emulator| {
+        (
+            emulator.registers.0,
+            emulator.cpsr(),
+            emulator.memory[0xFE0..0x1000].to_vec(),
+        )
+    };
+
+    let mut snapshots = vec![snapshot(&emulator)];
+    for _ in 0..6 {
+        emulator.step().unwrap();
+        snapshots.push(snapshot(&emulator));
+    }
+
+    assert_eq!(emulator.checkpoints(), vec![0, 2, 4, 6]);
+
+    emulator.step_back().unwrap();
+    assert_eq!(emulator.cycles(), 5);
+    assert_eq!(snapshot(&emulator), snapshots[5]);
+    // the checkpoints after the one restored are dropped
+    assert_eq!(emulator.checkpoints(), vec![0, 2, 4]);
+
+    emulator.run_to(1).unwrap();
+    assert_eq!(snapshot(&emulator), snapshots[1]);
+
+    emulator.run_to(6).unwrap();
+    assert_eq!(snapshot(&emulator), snapshots[6]);
+
+    emulator.run_to(0).unwrap();
+    assert_eq!(snapshot(&emulator), snapshots[0]);
+
+    emulator.set_checkpoint_interval(None);
+    emulator.step().unwrap();
+    assert!(matches!(
+        emulator.step_back(),
+        Err(ExecutionError::NoCheckpoint { cycles: 0 })
+    ));
+}
+
+#[test]
+fn test_manual_edits() {
+    let mut emulator = Emulator::new();
+
+    emulator
+        .assemble("\tADD R0, R0, R1\n\tSTR R0, [R2]\n\tLDR R3, [R2]\n\tADD R0, R0, R1\n")
+        .unwrap();
+
+    emulator.registers[1] = 1;
+    emulator.registers[2] = 0x1000;
+    emulator.set_checkpoint_interval(Some(2));
+
+    emulator.step().unwrap();
+    emulator.edit_register(1, 10);
+    emulator.edit_memory(0x1000, &[9]);
+    emulator.step().unwrap();
+    emulator.edit_memory(0x1003, &[7]);
+    emulator.step().unwrap();
+    emulator.step().unwrap();
+
+    assert_eq!(emulator.registers[0], 11);
+    assert_eq!(emulator.registers[3], 7);
+    assert_eq!(
+        emulator.manual_edits()[0],
+        ManualEdit {
+            cycles: 1,
+            kind: EditKind::Register {
+                register: 1,
+                old: 1,
+                new: 10
+            }
+        }
+    );
+    // the STR overwrote the first edited byte
+    assert_eq!(emulator.edited_memory(), BTreeMap::from([(0x1003, 7)]));
+    assert_eq!(emulator.edited_registers(), RegisterList::from_slice(&[1]));
+
+    // going back undoes the edits, and going forwards makes them again
+    emulator.step_back().unwrap();
+    assert_eq!(emulator.registers[3], 7);
+    emulator.run_to(0).unwrap();
+    assert_eq!(emulator.registers[1], 1);
+    assert!(emulator.edited_memory().is_empty());
+    emulator.run_to(4).unwrap();
+    assert_eq!(emulator.registers[0], 11);
+    assert_eq!(emulator.registers[3], 7);
+
+    // an edit after going back forgets the later ones
+    emulator.run_to(1).unwrap();
+    emulator.edit_register(1, 2);
+    assert_eq!(emulator.manual_edits().len(), 3);
+    emulator.run_to(4).unwrap();
+    assert_eq!(emulator.registers[0], 3);
+    assert_eq!(emulator.registers[3], 1);
+
+    // and traces don't count them as changes made by the instructions
+    emulator.run_to(0).unwrap();
+    let trace = emulator.record_trace(4).unwrap();
+    assert_eq!(trace.steps[1].writes, []);
+    assert_eq!(trace.steps[3].writes, [(0, 3)]);
+}
+
+#[test]
+fn test_stack_setup() {
+    let mut emulator = Emulator::new();
+
+    emulator.set_stack(StackSetup {
+        pointer: Some(StackPointer::Symbol("_stack".to_owned())),
+        fill: Some(StackFill {
+            size: 8,
+            pattern: 0xDEADBEEF,
+        }),
+        ..StackSetup::default()
+    });
+
+    emulator
+        .assemble("_stack EQU 0x4000\n\tSTMFD SP!, {R0, LR}\n\tLDMFD SP!, {R0, PC}\n")
+        .unwrap();
+
+    assert_eq!(emulator.register(13), 0x4000);
+    assert_eq!(emulator.register(14), HALT_ADDRESS);
+    assert_eq!(
+        emulator.memory[0x3FF8..0x4000],
+        [0xDE, 0xAD, 0xBE, 0xEF, 0xDE, 0xAD, 0xBE, 0xEF]
+    );
+
+    // returning through the pushed LR halts
+    assert_eq!(emulator.poll_step(10).unwrap(), Poll::Halted);
+    assert!(emulator.is_halted());
+    assert_eq!(emulator.cycles(), 2);
+
+    emulator.step().unwrap();
+    assert_eq!(emulator.cycles(), 2);
+}
+
+#[test]
+fn test_run_outcome() {
+    let mut emulator = Emulator::new();
+
+    emulator.mount_exit(0);
+    emulator
+        .assemble("\tADDS R0, R1, R2\n\tSVC 0\n\tPLD [R0]\n")
+        .unwrap();
+    emulator.registers[1] = 10;
+    emulator.registers[2] = 3;
+
+    let outcome = emulator.run(100).unwrap();
+    assert_eq!(outcome.status, RunStatus::Exited(13));
+    assert_eq!(outcome.exit_code(), Some(13));
+    assert_eq!(outcome.cycles, 2);
+    assert!(emulator.is_halted());
+
+    // loading a program clears the exit code
+    emulator.set_stack(StackSetup::default());
+    emulator
+        .assemble("\tSTMFD SP!, {LR}\n\tADDS R0, R1, R2\n\tLDMFD SP!, {PC}\n")
+        .unwrap();
+    emulator.registers[13] = 0x1000;
+    assert!(!emulator.is_halted());
+
+    let outcome = emulator.run(100).unwrap();
+    assert_eq!(outcome.status, RunStatus::Returned);
+    assert_eq!(outcome.exit_code(), Some(13));
+
+    emulator
+        .assemble("\tPLD [R0]\n\tPLD [R0]\n\tPLD [R0]\n")
+        .unwrap();
+
+    let outcome = emulator.run(2).unwrap();
+    assert_eq!(outcome.status, RunStatus::LimitReached);
+    assert_eq!(outcome.exit_code(), None);
+    assert_eq!(emulator.register(15), 8);
+}
+
+#[test]
+fn test_compare_with() {
+    let program = Arc::new(Program::assemble("\tADDS R0, R1, R2\n\tSVC 0\n").unwrap());
+    let setup = || {
+        let mut emulator = Emulator::new();
+        emulator.load(program.clone());
+        emulator.registers[1] = 10;
+        emulator.registers[2] = 3;
+        emulator
+    };
+
+    let (mut first, mut second) = (setup(), setup());
+    first.mount_exit(0);
+    second.mount_exit(0);
+    assert!(first.compare_with(&mut second, 100).is_none());
+    assert_eq!(first.exit_code, Some(13));
+
+    // the second emulator's SVC 0 doesn't exit, and changes R1
+    let (mut first, mut second) = (setup(), setup());
+    first.mount_exit(0);
+    second.register_svc(0, |emulator, _| emulator.registers[1] = 9);
+
+    let divergence = first.compare_with(&mut second, 100).unwrap();
+    assert_eq!(divergence.steps, 2);
+    assert_eq!(divergence.address, Some(4));
+    assert!(matches!(
+        divergence.kind,
+        DivergenceKind::Value {
+            location: StateLocation::Register(1),
+            first: 10,
+            second: 9,
+        }
+    ));
+    assert_eq!(
+        divergence.to_string(),
+        "after 2 instructions, the instruction at 0x00000004 left R1 as 0x0000000A on the first emulator and 0x00000009 on the second"
+    );
+
+    let (mut first, mut second) = (setup(), setup());
+    first.mount_exit(0);
+    second.register_svc(0, |_, _| ());
+
+    let divergence = first.compare_with(&mut second, 100).unwrap();
+    assert!(matches!(
+        divergence.kind,
+        DivergenceKind::Status {
+            first: Some(RunStatus::Exited(13)),
+            second: None,
+        }
+    ));
+
+    // memory is compared before the first instruction too
+    let (mut first, mut second) = (setup(), setup());
+    second.edit_memory(0x1002, &[1]);
+
+    let divergence = first.compare_with(&mut second, 100).unwrap();
+    assert_eq!(divergence.steps, 0);
+    assert_eq!(
+        divergence.to_string(),
+        "before the first instruction, the word at 0x00001000 is 0x00000000 on the first emulator and 0x00000100 on the second"
+    );
+}
+
+#[test]
+fn test_benchmark() {
+    let program = Arc::new(
+        Program::assemble("\tMOVS R1, R0\n\tSVCEQ 0\n\tPLD [R0]\n\tPLD [R0]\n\tSVC 0\n").unwrap(),
+    );
+
+    let benchmark = Benchmark::new(4)
+        .sizes(0, [0, 3])
+        .input(BenchmarkInput::new("stored").register(2, 7));
+    assert_eq!(benchmark.inputs()[1].registers, vec![(0, 3)]);
+
+    let report = benchmark.run(&program, || {
+        let mut emulator = Emulator::new();
+        emulator.mount_exit(0);
+        emulator
+    });
+
+    // each run starts on a new emulator, so R0 is 0 again for the last one
+    let instructions: Vec<u64> = report.runs.iter().map(|run| run.instructions).collect();
+    assert_eq!(instructions, vec![2, 4, 2]);
+    assert_eq!(
+        report.runs[0].outcome.as_ref().unwrap().status,
+        RunStatus::Exited(0)
+    );
+    assert_eq!(
+        report.runs[1].outcome.as_ref().unwrap().status,
+        RunStatus::LimitReached
+    );
+    assert_eq!(
+        report.to_string(),
+        "run     instructions  result\n\
+         R0=0               2  exited with 0\n\
+         R0=3               4  reached the limit\n\
+         stored             2  exited with 0\n"
+    );
+
+    let report = Benchmark::new(10).sizes(0, [3]).run(&program, || {
+        let mut emulator = Emulator::new();
+        emulator.mount_exit(0);
+        emulator.set_cost_table(Some(CostTable::new().cost(InstructionClass::Memory, 3)));
+        emulator
+    });
+    assert_eq!(report.runs[0].cost, Some(1 + 1 + 2 * 3 + 1));
+    assert_eq!(
+        report.to_string(),
+        "run   instructions  cost  result\n\
+         R0=3             5     9  exited with 3\n"
+    );
+}
+
+#[test]
+fn test_console_output() {
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut emulator = Emulator::new();
+
+    let sink = SharedBuffer::default();
+    emulator.mount_console(sink.clone());
+    emulator.mount_exit(2);
+
+    emulator
+        .assemble("\tSVC 3\n\tADDS R0, R1, R2\n\tSVC 4\n\tSVC 2\nprompt\tDEFB \"Hi: \", 0\n")
+        .unwrap();
+    emulator.registers[0] = 16;
+    emulator.registers[1] = -5i32 as u32;
+    emulator.registers[2] = 2;
+
+    emulator.run(100).unwrap();
+
+    assert_eq!(sink.0.lock().unwrap().as_slice(), b"Hi: -3");
+
+    let output = emulator.output().unwrap();
+    assert_eq!(output.text(), "Hi: -3");
+    assert_eq!(
+        output.chunks(),
+        [
+            OutputChunk {
+                bytes: 0..4,
+                cycles: 1
+            },
+            OutputChunk {
+                bytes: 4..6,
+                cycles: 3
+            }
+        ]
+    );
+    assert_eq!(output.cycles_at(5), Some(3));
+    assert_eq!(output.cycles_at(6), None);
+    assert!(output.printed_before("Hi", "-3"));
+    assert!(!output.printed_before("-3", "Hi"));
+}
+
+#[test]
+fn test_script() {
+    let program = "\tSVC 3\n\tSVC 1\n\tSVC 0\n\tSVC 2\nprompt\tDEFB \"name? \", 0\n";
+
+    let run = |script: Script| {
+        let mut emulator = Emulator::new();
+
+        emulator.mount_console(std::io::sink());
+        emulator.mount_exit(2);
+        emulator.assemble(program).unwrap();
+        emulator.registers[0] = 16;
+
+        emulator.run_script(&script, 100)
+    };
+
+    let outcome = run(Script::new().expect("name?").send("A").expect("A")).unwrap();
+    assert_eq!(outcome.status, RunStatus::Exited(b'A' as u32));
+
+    // the program waits for input that is never sent
+    let Err(ScriptError::Unmatched {
+        expected,
+        transcript,
+    }) = run(Script::new().expect("name?").expect("hello"))
+    else {
+        panic!("the script should fail");
+    };
+    assert_eq!(expected, "hello");
+    assert_eq!(transcript.to_string(), "name? ");
+
+    // the program halts without printing the expected text
+    let Err(ScriptError::Unmatched { transcript, .. }) =
+        run(Script::new().send("B").expect("you are"))
+    else {
+        panic!("the script should fail");
+    };
+    assert_eq!(transcript.to_string(), "[sent \"B\"]name? B");
+}
+
+#[test]
+fn test_watchpoints() {
+    let mut emulator = Emulator::new();
+
+    emulator
+        .assemble("\tSTMFD SP!, {R0-R2}\n\tSTMFD SP!, {R0-R2}\n")
+        .unwrap();
+    emulator.registers[0] = 1;
+    emulator.registers[1] = 2;
+    emulator.registers[2] = 3;
+    emulator.registers[13] = 0x1000;
+
+    emulator.watch(0xFF4, WatchAction::Log);
+    emulator.watch(0xFF8, WatchAction::Inject(99));
+    emulator.watch(
+        0xFFC,
+        WatchAction::Call(Box::new(|emulator, hit| {
+            emulator.set_register(5, hit.value)
+        })),
+    );
+    emulator.watch(0xFE8, WatchAction::Halt);
+
+    emulator.step().unwrap();
+
+    assert_eq!(
+        emulator.watch_log(),
+        [WatchHit {
+            address: 0xFF4,
+            value: 1,
+            pc: 0,
+            cycles: 1
+        }]
+    );
+    assert_eq!(emulator.read_word(0xFF8), 99);
+    assert_eq!(emulator.register(5), 3);
+
+    // the instruction finishes before halting
+    assert!(matches!(
+        emulator.step(),
+        Err(ExecutionError::Watchpoint(WatchHit {
+            address: 0xFE8,
+            pc: 4,
+            ..
+        }))
+    ));
+    assert_eq!(emulator.read_word(0xFF0), 3);
+}
+
+#[test]
+fn test_irq_latency() {
+    let mut emulator = Emulator::new();
+
+    emulator.assemble(&"\tPLD [R0]\n".repeat(12)).unwrap();
+    emulator.set_irq_latency(2);
+
+    emulator.step().unwrap();
+    emulator.raise_irq();
+    emulator.step().unwrap();
+    emulator.step().unwrap();
+    assert!(emulator.irq_pending());
+    assert_eq!(emulator.register(15), 12);
+
+    // the exception is taken before the next instruction, which is the first of the handler
+    emulator.step().unwrap();
+    assert!(!emulator.irq_pending());
+    assert_eq!(emulator.register(14), 16);
+    assert_eq!(emulator.register(15), 0x1C);
+    assert_eq!(
+        emulator.interrupts(),
+        [Interrupt {
+            raised: 1,
+            taken: 3,
+            address: 12
+        }]
+    );
+    assert_eq!(emulator.interrupts()[0].latency(), 2);
+
+    // masked IRQs wait until the I bit is cleared
+    let mut cpsr = emulator.cpsr();
+    cpsr.set_i(true);
+    emulator.set_cpsr(cpsr);
+    emulator.raise_irq();
+    emulator.step().unwrap();
+    emulator.step().unwrap();
+    emulator.step().unwrap();
+    assert!(emulator.irq_pending());
+}
+
+#[test]
+fn test_memory_view() {
+    let mut emulator = Emulator::new();
+
+    emulator
+        .assemble("\tDEFB 1, 2, 3, 4, 5, 6, 7, 8\n")
+        .unwrap();
+
+    let view = emulator.memory(1..8);
+    assert_eq!(view.range(), 1..8);
+    assert_eq!(view.bytes().next(), Some((1, 2)));
+    assert_eq!(
+        view.halfwords().collect::<Vec<_>>(),
+        [(2, 0x0304), (4, 0x0506), (6, 0x0708)]
+    );
+    assert_eq!(view.words().collect::<Vec<_>>(), [(4, 0x05060708)]);
+    assert_eq!(
+        view.with_byte_order(ByteOrder::LittleEndian)
+            .words()
+            .collect::<Vec<_>>(),
+        [(4, 0x08070605)]
+    );
+    assert_eq!(
+        view.rows(4).collect::<Vec<_>>(),
+        [(1, &[2, 3, 4, 5][..]), (5, &[6, 7, 8][..])]
+    );
+
+    // the view stops at the end of memory
+    assert_eq!(emulator.memory(0xFFFFFFF0..u32::MAX).words().count(), 3);
+}
+
+#[test]
+fn test_session() {
+    let mut emulator = Emulator::new();
+
+    emulator
+        .assemble("\tSTMFD SP!, {R0-R2}\n\tSTMFD SP!, {R0-R2}\n")
+        .unwrap();
+    emulator.registers[0] = 1;
+    emulator.registers[1] = 2;
+    emulator.registers[2] = 3;
+    emulator.registers[13] = 0x1000;
+
+    emulator.watch(0xFE8, WatchAction::Log);
+    emulator.watch(0xFEC, WatchAction::Inject(42));
+    emulator.watch(0xFF0, WatchAction::Call(Box::new(|_, _| {})));
+
+    emulator.step().unwrap();
+
+    let session = emulator.save_session(&BTreeSet::from([4]), true);
+
+    assert_eq!(
+        session.watchpoints,
+        BTreeMap::from([(0xFE8, SavedWatch::Log), (0xFEC, SavedWatch::Inject(42))])
+    );
+
+    let file = session.to_string();
+    assert_eq!(Session::parse(&file), Ok(session.clone()));
+    assert_eq!(
+        Session::parse("not a session"),
+        Err(SessionError::UnknownFormat)
+    );
+
+    let mut resumed = Emulator::new();
+    resumed
+        .restore_session(&Session::parse(&file).unwrap(), Default::default())
+        .unwrap();
+
+    assert_eq!(resumed.register(13), 0xFF4);
+    assert_eq!(resumed.register(15), 4);
+    assert_eq!(resumed.read_word(0xFF8), 2);
+
+    // the session carries on where it left off, with its watchpoints
+    resumed.step().unwrap();
+    assert_eq!(resumed.read_word(0xFEC), 42);
+    assert_eq!(resumed.watch_log().len(), 1);
+    assert_eq!(
+        resumed.save_session(&BTreeSet::new(), false).source,
+        session.source
+    );
+}
+
+#[test]
+fn test_shift_by_32() {
+    let mut emulator = Emulator::new();
+
+    emulator
+        .assemble("\tADD\tR0, R1, R2, LSR #32\n\tADD\tR3, R1, R2, ASR #32\n")
+        .unwrap();
+    emulator.registers[1] = 5;
+    emulator.registers[2] = 0x80000000;
+
+    emulator.step().unwrap();
+    emulator.step().unwrap();
+
+    assert_eq!(emulator.register(0), 5);
+    assert_eq!(emulator.register(3), 4);
+}
+
+#[test]
+fn test_svc_spec() {
+    let spec = SvcSpec::parse(
+        "# the SVCs of another course
+[[svc]]
+number = 0x10
+name = \"putc\"
+behavior = \"print_char\"
+arguments = [\"R1\"]
+
+[[svc]]
+number = 0x11
+name = \"getc\"
+behavior = \"read_char\"
+result = \"r2\"
+
+[[svc]]
+number = 0x12
+name = \"beep\"
+behavior = \"custom\"
+
+[[svc]]
+number = 0x14
+name = \"beep\" # shares the handler of 0x12
+behavior = \"custom\"
+
+[[svc]]
+number = 0x13
+name = \"exit\"
+behavior = \"exit\"
+arguments = [\"R3\"]
+",
+    )
+    .unwrap();
+
+    assert_eq!(spec.get(0x11).unwrap().result, Some(2));
+
+    let mut emulator = Emulator::new();
+    emulator.mount_console(std::io::sink());
+
+    assert_eq!(
+        emulator.mount_svcs(&spec, HashMap::new()),
+        Err(SvcSpecError::MissingCallback {
+            name: "beep".to_owned()
+        })
+    );
+
+    let beep: SvcHandler =
+        Box::new(|emulator: &mut Emulator, number| emulator.registers[4] += number);
+    emulator
+        .mount_svcs(&spec, HashMap::from([("beep".to_owned(), beep)]))
+        .unwrap();
+
+    emulator
+        .assemble("\tSVC 0x11\n\tSVC 0x10\n\tSVC 0x12\n\tSVC 0x14\n\tSVC 0x13\n")
+        .unwrap();
+    emulator.registers[1] = 'A' as u32;
+    emulator.registers[3] = 7;
+    emulator.send_input(b"z");
+
+    let outcome = emulator.run(100).unwrap();
+
+    assert_eq!(outcome.exit_code(), Some(7));
+    assert_eq!(emulator.output().unwrap().text(), "A");
+    assert_eq!(emulator.registers[2], 'z' as u32);
+    assert_eq!(emulator.registers[4], 0x12 + 0x14);
+
+    assert_eq!(
+        SvcSpec::parse("[[svc]]\nnumber = 1\nname = \"a#b\" # c\nbehavior = \"halt\"\n")
+            .unwrap()
+            .svcs[0]
+            .name,
+        "a#b"
+    );
+
+    assert_eq!(
+        SvcSpec::parse("[[svc]]\nnumber = 1\nbehavior = \"halt\"\n"),
+        Err(SvcSpecError::MissingField {
+            line: 1,
+            key: "name"
+        })
+    );
+    assert_eq!(
+        SvcSpec::parse(
+            "[[svc]]\nnumber = 1\nname = \"a\"\nbehavior = \"print_char\"\narguments = [\"R16\"]\n"
+        ),
+        Err(SvcSpecError::InvalidField {
+            line: 5,
+            key: "arguments".to_owned()
+        })
+    );
+    assert_eq!(
+        SvcSpec::parse("number = 1\n"),
+        Err(SvcSpecError::Malformed { line: 1 })
+    );
+    assert_eq!(
+        SvcSpec::parse("[[svc]]\nnumber = 1\nname = \"a\"\nbehavior = \"halt\"\n[[svc]]\nnumber = 1\nname = \"b\"\nbehavior = \"halt\"\n"),
+        Err(SvcSpecError::DuplicateNumber { number: 1 })
+    );
+}
+
+#[test]
+fn test_peripherals() {
+    let board = Board::parse(
+        "; the lab's UART
+peripheral uart 0x10000000
+register data 0x0 fifo
+register status 0x4 fifo_status
+register id 0x8 constant 0x41
+register control 0xC latch 7
+register timer 0x10 counter 100 2
+",
+    )
+    .unwrap();
+
+    let mut emulator = Emulator::new();
+    emulator.mount_console(std::io::sink());
+    emulator.mount_board(&board).unwrap();
+
+    assert_eq!(
+        emulator.mount_peripheral(&Peripheral::new("timer", 0x10000010).register(
+            "count",
+            0,
+            RegisterBehavior::Constant(0)
+        )),
+        Err(PeripheralError::Overlap {
+            address: 0x10000010
+        })
+    );
+
+    emulator
+        .assemble(
+            "\tLDMIA R0, {R1-R5}\n\tSTMIA R8, {R6, R7}\n\tLDMIA R8, {R9, R10}\n\tSTMIA R0, {R11}\n",
+        )
+        .unwrap();
+    emulator.registers[0] = 0x10000000;
+    emulator.registers[8] = 0x1000000C;
+    emulator.registers[6] = 12;
+    emulator.registers[7] = 50;
+    emulator.registers[11] = '!' as u32;
+    emulator.send_input(b"hi");
+
+    for _ in 0..4 {
+        emulator.step().unwrap();
+    }
+
+    assert_eq!(emulator.registers[1], 'h' as u32);
+    assert_eq!(emulator.registers[2], 1);
+    assert_eq!(emulator.registers[3], 0x41);
+    assert_eq!(emulator.registers[4], 7);
+    assert_eq!(emulator.registers[5], 102);
+    // the counter counts on from the value written a cycle before
+    assert_eq!(emulator.registers[9], 12);
+    assert_eq!(emulator.registers[10], 52);
+    assert_eq!(emulator.output().unwrap().text(), "!");
+
+    assert_eq!(
+        Board::parse("register data 0x0 fifo\n"),
+        Err(PeripheralError::Malformed { line: 1 })
+    );
+}
+
+#[test]
+fn test_tick() {
+    let mut emulator = Emulator::new();
+    emulator
+        .mount_peripheral(&Peripheral::new("timer", 0x1000).register(
+            "tick",
+            0,
+            RegisterBehavior::Timer { period: 3 },
+        ))
+        .unwrap();
+
+    let ticks = Arc::new(Mutex::new(Vec::new()));
+    let recorded = ticks.clone();
+    emulator.add_ticker(move |emulator| recorded.lock().unwrap().push(emulator.cycles));
+
+    emulator
+        .assemble("\tLDMIA R0, {R1}\n\tLDMIA R0, {R2}\n\tLDMIA R0, {R3}\n")
+        .unwrap();
+    emulator.registers[0] = 0x1000;
+
+    // IRQs are masked, so the pending IRQ can be seen
+    let mut cpsr = emulator.cpsr();
+    cpsr.set_i(true);
+    emulator.set_cpsr(cpsr);
+
+    assert_eq!(emulator.tick(2).unwrap(), Poll::Yielded);
+    assert_eq!(emulator.registers[1], 2);
+    assert!(!emulator.irq_pending());
+
+    assert_eq!(emulator.tick(1).unwrap(), Poll::Yielded);
+    assert!(emulator.irq_pending());
+    assert_eq!(*ticks.lock().unwrap(), [1, 2, 3]);
+
+    emulator.mount_exit(0);
+    emulator.assemble("\tSVC 0\n").unwrap();
+    assert_eq!(emulator.tick(5).unwrap(), Poll::Halted);
+}
+
+#[test]
+fn test_cost_table() {
+    let table = CostTable::parse("; a memory access costs 3 units\nmemory 3\nbranch 2\n").unwrap();
+    assert_eq!(
+        table,
+        CostTable::new()
+            .cost(InstructionClass::Memory, 3)
+            .cost(InstructionClass::Branch, 2)
+    );
+    assert_eq!(table.get(InstructionClass::DataProcessing), 1);
+
+    assert_eq!(
+        CostTable::parse("multiply 4\n"),
+        Ok(CostTable::new().cost(InstructionClass::Multiply, 4))
+    );
+    assert_eq!(
+        CostTable::parse("memory 3\ndivide 4\n"),
+        Err(CostTableError::UnknownClass { line: 2 })
+    );
+    assert_eq!(
+        CostTable::parse("memory three\n"),
+        Err(CostTableError::Malformed { line: 1 })
+    );
+
+    let mut emulator = Emulator::new();
+    emulator.mount_exit(0);
+    emulator.set_cost_table(Some(table));
+
+    emulator
+        .assemble("\tLDMIA R0, {R1, R2}\n\tADD R0, R1, R2\n\tSVC 0\n")
+        .unwrap();
+    emulator.registers[0] = 0x1000;
+    emulator.write_memory(0x1000, &[0, 0, 0, 2, 0, 0, 0, 3]);
+
+    assert_eq!(emulator.run(100).unwrap().status, RunStatus::Exited(5));
+
+    // the LDM costs 3 units for each of its two registers
+    let report = emulator.cost_report().unwrap();
+    assert_eq!(report.total, 6 + 1 + 1);
+    assert_eq!(report.by_class[&InstructionClass::Memory], 6);
+    assert_eq!(report.by_address[&0], 6);
+
+    let program =
+        Program::assemble("start\tBL\tsum\n\tSVC 0\nsum\tLDMIA R0, {R1, R2}\n\tMOV PC, LR\n")
+            .unwrap();
+    let report = CostReport {
+        total: 10,
+        by_address: BTreeMap::from([(0, 2), (4, 1), (8, 6), (12, 1)]),
+        ..CostReport::default()
+    };
+
+    let routines = report.routines(&program);
+    assert_eq!(routines.len(), 2);
+    assert_eq!(routines[0].label.as_deref(), Some("start"));
+    assert_eq!(routines[0].cost, 3);
+    assert_eq!(routines[1].label.as_deref(), Some("sum"));
+    assert_eq!(routines[1].cost, 7);
+
+    emulator.set_cost_table(None);
+    assert_eq!(emulator.cost_report(), None);
+}
+
+#[test]
+fn test_golden_trace() {
+    let run = || {
+        let mut emulator = Emulator::new();
+        emulator.mount_exit(0);
+        emulator
+            .assemble("\tADDS R0, R1, R2\n\tLDMIA R3, {R4}\n\tSVC 0\n")
+            .unwrap();
+        emulator.registers[1] = 10;
+        emulator.registers[2] = 3;
+        emulator.registers[3] = 0x1000;
+        emulator.write_memory(0x1000, &[0, 0, 0, 7]);
+        emulator
+    };
+
+    let trace = run().record_trace(100).unwrap();
+    assert_eq!(trace.steps.len(), 3);
+    assert_eq!(trace.steps[0].writes, [(0, 13)]);
+    assert_eq!(trace.steps[1].to_string(), "0x00000004 r4=0x00000007");
+    assert_eq!(trace.steps[2].cpsr, None);
+
+    let text = trace.to_string();
+    assert!(text.starts_with("eremius trace 1\n0x00000000 r0=0x0000000D"));
+
+    let golden = Trace::parse(&text).unwrap();
+    assert_eq!(golden, trace);
+    assert_eq!(run().compare_trace(&golden).unwrap(), None);
+
+    // a golden trace where the load gave a different value
+    let mut changed = golden.clone();
+    changed.steps[1].writes = vec![(4, 8)];
+
+    let divergence = run().compare_trace(&changed).unwrap().unwrap();
+    assert_eq!(divergence.step, 1);
+    assert_eq!(divergence.context, golden.steps[..1]);
+    assert_eq!(divergence.actual, Some(golden.steps[1].clone()));
+    assert!(divergence
+        .to_string()
+        .contains("expected:  0x00000004 r4=0x00000008\nactual:    0x00000004 r4=0x00000007"));
+
+    // a golden trace that carries on after the program halts
+    let mut longer = golden.clone();
+    longer.steps.push(golden.steps[0].clone());
+
+    let divergence = run().compare_trace(&longer).unwrap().unwrap();
+    assert_eq!(divergence.step, 3);
+    assert_eq!(divergence.actual, None);
+
+    assert_eq!(Trace::parse("0x00000000\n"), Err(TraceError::UnknownFormat));
+    assert_eq!(
+        Trace::parse("eremius trace 1\n0x00000000 r0=13\n"),
+        Err(TraceError::Malformed { line: 2 })
+    );
+}
+
+#[test]
+fn test_conformance_vectors() {
+    let suite = ConformanceSuite::parse(
+        "; ADDS that carries out
+vector adds_carry
+instruction 0xE0910002
+set r1 0xFFFFFFFF
+set r2 0x00000001
+set cpsr 0x00000010
+expect r0 0x00000000
+expect r15 0x00008004
+expect cpsr 0x60000010
+
+vector ldm
+instruction 0xE8930010
+address 0x100
+set r3 0x00001000
+set memory 0x1000 0x00000007
+expect r4 0x00000007
+
+vector add_wrong
+instruction 0xE0810002
+set r1 0x00000002
+set r2 0x00000003
+expect r0 0x00000006
+expect memory 0x1000 0x00000000
+
+vector undefined_svc
+instruction 0xEF000005
+",
+    )
+    .unwrap();
+
+    assert_eq!(suite.vectors.len(), 4);
+    assert_eq!(suite.vectors[1].address, 0x100);
+    assert_eq!(suite.vectors[1].initial.memory[&0x1000], 7);
+    assert_eq!(suite.vectors[2].line, 18);
+
+    let report = suite.run();
+    assert_eq!(report.passed, 2);
+    assert!(!report.is_success());
+
+    assert_eq!(report.failures[0].name, "add_wrong");
+    assert!(matches!(
+        &report.failures[0].kind,
+        VectorFailureKind::Mismatches(mismatches) if mismatches == &[Mismatch {
+            location: StateLocation::Register(0),
+            expected: 6,
+            actual: 5,
+        }]
+    ));
+    assert!(matches!(
+        report.failures[1].kind,
+        VectorFailureKind::Error(ExecutionError::UndefinedSvc { number: 5, .. })
+    ));
+    assert!(report.to_string().starts_with(
+        "add_wrong (line 18): mismatched state\n    r0: expected 0x00000006, got 0x00000005\n"
+    ));
+    assert!(report.to_string().ends_with("2 passed, 2 failed"));
+
+    assert_eq!(
+        ConformanceSuite::parse("vector empty\nset r0 0x1\n"),
+        Err(VectorError::MissingInstruction {
+            vector: "empty".to_owned()
+        })
+    );
+    assert_eq!(
+        ConformanceSuite::parse("vector pc\ninstruction 0xE0810002\nset r15 0x0\n"),
+        Err(VectorError::Malformed { line: 3 })
+    );
+    assert_eq!(
+        ConformanceSuite::parse("instruction 0xE0810002\n"),
+        Err(VectorError::Malformed { line: 1 })
+    );
+}
+
+#[test]
+fn test_move_not() {
+    let mut emulator = Emulator::new();
+    emulator
+        .assemble("\tMVN R0, R1\n\tMVNS R2, R3\n\tMVNEQ R4, R1\n\tMVN R5, #0\n\tMVN R6, #0xFF00\n")
+        .unwrap();
+    emulator.registers[1] = 0x0000FF00;
+    emulator.registers[3] = 0x7FFFFFFF;
+
+    emulator.step().unwrap();
+    assert_eq!(emulator.registers[0], 0xFFFF00FF);
+
+    emulator.step().unwrap();
+    assert_eq!(emulator.registers[2], 0x80000000);
+    assert!(emulator.cpsr.n());
+    assert!(!emulator.cpsr.z());
+
+    // the condition fails, so R4 is left alone
+    emulator.step().unwrap();
+    assert_eq!(emulator.registers[4], 0);
+
+    // an immediate is negated itself, rather than the register with its number
+    emulator.step().unwrap();
+    assert_eq!(emulator.registers[5], 0xFFFFFFFF);
+
+    emulator.step().unwrap();
+    assert_eq!(emulator.registers[6], 0xFFFF00FF);
+}
+
+#[test]
+fn test_comparisons() {
+    let mut emulator = Emulator::new();
+    emulator
+        .assemble("\tCMN R0, R1\n\tTST R2, R3\n\tTEQ R4, R5\n\tCMP R0, R1\n")
+        .unwrap();
+    emulator.registers[0] = 1;
+    emulator.registers[1] = 0xFFFFFFFF;
+    emulator.registers[2] = 0b1010;
+    emulator.registers[3] = 0b0101;
+    emulator.registers[4] = 0x80000001;
+    emulator.registers[5] = 0x00000001;
+
+    // 1 + -1 is zero with a carry out
+    emulator.step().unwrap();
+    assert_eq!(emulator.cpsr().to_string(), "nZCvq ift USR");
+
+    // no bits in common, and V is left alone
+    emulator.cpsr.set_v(true);
+    emulator.step().unwrap();
+    assert!(emulator.cpsr.z());
+    assert!(!emulator.cpsr.n());
+    assert!(emulator.cpsr.v());
+
+    emulator.step().unwrap();
+    assert!(!emulator.cpsr.z());
+    assert!(emulator.cpsr.n());
+
+    // none of them change a register
+    emulator.step().unwrap();
+    assert!(!emulator.cpsr.c());
+    assert_eq!(emulator.registers[0], 1);
+    assert_eq!(emulator.registers[2], 0b1010);
+    assert_eq!(emulator.registers[4], 0x80000001);
+
+    // immediates are compared themselves, rather than the registers with their numbers
+    let mut emulator = Emulator::new();
+    emulator
+        .assemble("\tCMN R0, #1\n\tTST R2, #1\n\tTEQ R4, #3\n\tCMP R4, #3\n")
+        .unwrap();
+    emulator.registers[0] = 0xFFFFFFFF;
+    emulator.registers[1] = 2;
+    emulator.registers[2] = 0b1010;
+    emulator.registers[3] = 0b1010;
+    emulator.registers[4] = 3;
+
+    emulator.step().unwrap();
+    assert_eq!(emulator.cpsr().to_string(), "nZCvq ift USR");
+
+    emulator.step().unwrap();
+    assert!(emulator.cpsr.z());
+
+    emulator.cpsr.set_z(false);
+    emulator.step().unwrap();
+    assert!(emulator.cpsr.z());
+
+    emulator.cpsr.set_z(false);
+    emulator.step().unwrap();
+    assert_eq!(emulator.cpsr().to_string(), "nZCvq ift USR");
+}
+
+#[test]
+fn test_carry_arithmetic() {
+    let mut emulator = Emulator::new();
+    emulator
+        .assemble(
+            "\tADDS R4, R0, R2
+\tADC R5, R1, R3
+\tSUBS R6, R0, R2
+\tSBC R7, R1, R3
+\tRSB R8, R2, R3
+\tSUBS R9, R2, R3
+\tRSCS R10, R2, R3
+",
+        )
+        .unwrap();
+
+    // the 64-bit values 0x1_FFFFFFFF and 0x2_00000001, low word first
+    emulator.registers[0] = 0xFFFFFFFF;
+    emulator.registers[1] = 1;
+    emulator.registers[2] = 1;
+    emulator.registers[3] = 2;
+
+    // the carry out of the low words is added to the high words
+    emulator.step().unwrap();
+    emulator.step().unwrap();
+    assert_eq!((emulator.registers[4], emulator.registers[5]), (0, 4));
+
+    // the low words don't borrow, so neither do the high words
+    emulator.step().unwrap();
+    emulator.step().unwrap();
+    assert_eq!(
+        (emulator.registers[6], emulator.registers[7]),
+        (0xFFFFFFFE, 0xFFFFFFFF)
+    );
+
+    emulator.step().unwrap();
+    assert_eq!(emulator.registers[8], 1);
+
+    // 1 - 2 borrows, which RSC takes from 2 - 1
+    emulator.step().unwrap();
+    assert!(!emulator.cpsr.c());
+
+    emulator.set_explain_flags(true);
+    emulator.step().unwrap();
+    assert_eq!(emulator.registers[10], 0);
+    assert_eq!(emulator.cpsr().to_string(), "nZCvq ift USR");
+    assert_eq!(
+        emulator.flag_changes()[2].to_string(),
+        "C set: borrow did not occur in RSC 0x00000002 - 0x00000001 - 1"
+    );
+
+    // the same with immediate operands
+    let mut emulator = Emulator::new();
+    emulator
+        .assemble(
+            "\tRSB R0, R0, #0
+\tADDS R1, R1, #1
+\tADC R2, R2, #0
+\tSUBS R3, R3, #1
+\tSBC R4, R4, #0
+\tRSC R5, R5, #10
+",
+        )
+        .unwrap();
+    emulator.registers[0] = 5;
+    emulator.registers[1] = 0xFFFFFFFF;
+    emulator.registers[2] = 7;
+    emulator.registers[4] = 7;
+    emulator.registers[5] = 3;
+
+    emulator.step().unwrap();
+    assert_eq!(emulator.registers[0], (-5i32) as u32);
+
+    emulator.step().unwrap();
+    emulator.step().unwrap();
+    assert_eq!((emulator.registers[1], emulator.registers[2]), (0, 8));
+
+    // 0 - 1 borrows, which SBC and RSC take from their immediates
+    emulator.step().unwrap();
+    emulator.step().unwrap();
+    assert_eq!(
+        (emulator.registers[3], emulator.registers[4]),
+        (0xFFFFFFFF, 6)
+    );
+
+    emulator.step().unwrap();
+    assert_eq!(emulator.registers[5], 6);
+}
+
+#[test]
+fn test_heap() {
+    let mut heap = Heap::new(2..32);
+    assert_eq!(heap.region(), 4..32);
+
+    // sizes are rounded up to whole words, and blocks that don't fit aren't allocated
+    assert_eq!(heap.allocate(5), Some(4));
+    assert_eq!(heap.allocate(8), Some(12));
+    assert_eq!(heap.allocate(16), None);
+    assert_eq!(heap.allocate(0), None);
+
+    // a freed gap is reused by the first allocation that fits
+    assert!(heap.free(4));
+    assert!(!heap.free(4));
+    assert_eq!(heap.allocate(12), Some(20));
+    assert_eq!(heap.allocate(4), Some(4));
+    assert_eq!(heap.in_use(), 24);
+
+    let mut emulator = Emulator::new();
+    emulator
+        .assemble(
+            "\tSVC\t256
+\tMOV\tR5, R0
+\tMOV\tR0, R4
+\tSVC\t256
+\tMOV\tR6, R0
+\tMOV\tR0, R5
+\tSVC\t257
+\tMOV\tR0, R4
+\tSVC\t256
+\tSTR\tR4, [R0]
+\tSVC\t257
+value\tDEFB\t1
+",
+        )
+        .unwrap();
+
+    // the heap starts at the first word after the data
+    let heap = Heap::after(&emulator.modules()[0], 64);
+    assert_eq!(heap.region(), 48..112);
+
+    emulator.mount_heap(heap, 256);
+    emulator.registers[0] = 12;
+    emulator.registers[4] = 8;
+
+    for _ in 0..10 {
+        emulator.step().unwrap();
+    }
+
+    assert_eq!((emulator.registers[5], emulator.registers[6]), (48, 60));
+    // the first block was freed, so the last allocation reuses it
+    assert_eq!(emulator.registers[0], 48);
+    assert_eq!(emulator.memory[51], 8);
+    assert_eq!(
+        emulator.heap().unwrap().allocations().collect::<Vec<_>>(),
+        [(48, 8), (60, 8)]
+    );
+
+    // freeing an address that isn't a block fails
+    emulator.registers[0] = 52;
+    emulator.step().unwrap();
+    assert_eq!(emulator.registers[0], u32::MAX);
+}
+
+#[test]
+fn test_multiply() {
+    let mut emulator = Emulator::new();
+    emulator
+        .assemble(
+            "\tMUL R2, R0, R1
+\tMLAS R3, R0, R1, R2
+\tMULS R4, R0, R5
+",
+        )
+        .unwrap();
+
+    emulator.registers[0] = 0x10001;
+    emulator.registers[1] = 0x10000;
+    emulator.registers[5] = 0;
+    emulator.cpsr.set_c(true);
+
+    // only the low word of the product is kept
+    emulator.step().unwrap();
+    assert_eq!(emulator.registers[2], 0x10000);
+
+    emulator.step().unwrap();
+    assert_eq!(emulator.registers[3], 0x20000);
+
+    // the carry is left alone
+    emulator.step().unwrap();
+    assert_eq!(emulator.registers[4], 0);
+    assert!(emulator.cpsr.z() && emulator.cpsr.c());
+}
+
+#[test]
+fn test_multiply_long() {
+    let mut emulator = Emulator::new();
+    emulator
+        .assemble(
+            "\tUMULL R2, R3, R0, R1
+\tSMULL R4, R5, R0, R1
+\tUMLAL R2, R3, R0, R1
+\tSMLALS R4, R5, R6, R6
+",
+        )
+        .unwrap();
+
+    emulator.registers[0] = 0xFFFFFFFF;
+    emulator.registers[1] = 2;
+    emulator.registers[6] = 1;
+
+    // 0xFFFFFFFF is 4294967295 unsigned, but -1 signed
+    emulator.step().unwrap();
+    assert_eq!(
+        (emulator.registers[2], emulator.registers[3]),
+        (0xFFFFFFFE, 1)
+    );
+
+    emulator.step().unwrap();
+    assert_eq!(
+        (emulator.registers[4], emulator.registers[5]),
+        (0xFFFFFFFE, 0xFFFFFFFF)
+    );
+
+    // the carry out of the low word goes into the high word
+    emulator.step().unwrap();
+    assert_eq!(
+        (emulator.registers[2], emulator.registers[3]),
+        (0xFFFFFFFC, 3)
+    );
+
+    // -2 + 1 is -1, which is negative across the whole 64 bits
+    emulator.step().unwrap();
+    assert_eq!(
+        (emulator.registers[4], emulator.registers[5]),
+        (0xFFFFFFFF, 0xFFFFFFFF)
+    );
+    assert!(emulator.cpsr.n() && !emulator.cpsr.z());
+}
+
+#[test]
+fn test_psr() {
+    let mut psr = Psr::default();
+    assert_eq!(psr.to_string(), "nzcvq ift USR");
+
+    psr.set_c(true);
+    psr.set_z(true);
+    psr.set_i(true);
+    psr.set_mode(ProcessorMode::Supervisor);
+
+    assert_eq!(psr.to_string(), "nZCvq Ift SVC");
+    assert_eq!(psr.bits(), 0x60000093);
+    assert!(psr.condition_passed(Condition::LS));
+    assert!(!psr.condition_passed(Condition::HI));
+
+    psr.set_z(false);
+    assert!(psr.condition_passed(Condition::HI));
+    assert_eq!(Psr::from_bits(psr.bits()), psr);
+
+    // mode bits that aren't a mode are written in binary
+    assert_eq!(Psr::from_bits(0x80000000).to_string(), "Nzcvq ift 0b00000");
+    assert_eq!(Psr::from_bits(0).mode(), None);
+
+    let mut emulator = Emulator::new();
+    emulator.execute_source_line("MOVS R0, #0").unwrap();
+    assert_eq!(emulator.cpsr().to_string(), "nZcvq ift USR");
+}
+
+#[test]
+fn test_status_register_access() {
+    let mut emulator = Emulator::new();
+    emulator
+        .assemble(
+            "\tMRS\tR0, CPSR\n\tMSR\tCPSR_f, R1\n\tMSR\tCPSR_c, R2\n\tMSR\tCPSR, #0x10\n\tMSR\tCPSR_c, R2\n",
+        )
+        .unwrap();
+    emulator.registers[1] = 0xA000_0000;
+    emulator.registers[2] = 0x9F;
+    emulator.cpsr.set_z(true);
+    #[cfg(feature = "disassembler")]
+    assert_eq!(emulator.disassemble(4).unwrap(), "MSR CPSR_f, R1");
+    #[cfg(feature = "disassembler")]
+    assert_eq!(emulator.disassemble(12).unwrap(), "MSR CPSR_fc, #16");
+
+    emulator.step().unwrap();
+    assert_eq!(emulator.registers[0], 0x4000_0010);
+
+    emulator.step().unwrap();
+    assert_eq!(emulator.cpsr().to_string(), "NzCvq ift USR");
+
+    // the control field can only be written in a privileged mode
+    emulator.cpsr.set_mode(ProcessorMode::Supervisor);
+    emulator.step().unwrap();
+    assert_eq!(emulator.cpsr().to_string(), "NzCvq Ift SYS");
+
+    // CPSR on its own writes the flags and control fields
+    emulator.step().unwrap();
+    assert_eq!(emulator.cpsr().bits(), 0x10);
+
+    emulator.step().unwrap();
+    assert_eq!(emulator.cpsr().bits(), 0x10);
+}
+
+#[test]
+fn test_count_leading_zeros() {
+    let mut emulator = Emulator::new();
+    emulator
+        .assemble("\tCLZ\tR0, R3\n\tCLZ\tR1, R4\n\tCLZ\tR2, R5\n\tCLZNE\tR6, R3\n")
+        .unwrap();
+    emulator.registers[3] = 0x0001_0000;
+    emulator.registers[4] = 0x8000_0000;
+    emulator.registers[6] = 7;
+    emulator.cpsr.set_z(true);
+    #[cfg(feature = "disassembler")]
+    assert_eq!(emulator.disassemble(0).unwrap(), "CLZ R0, R3");
+
+    for _ in 0..4 {
+        emulator.step().unwrap();
+    }
+
+    assert_eq!(emulator.registers[0], 15);
+    assert_eq!(emulator.registers[1], 0);
+    // a value of 0 has 32 leading zeros
+    assert_eq!(emulator.registers[2], 32);
+    assert_eq!(emulator.registers[6], 7);
+}
+
+#[test]
+fn test_step_statement() {
+    let mut emulator = Emulator::new();
+
+    emulator
+        .assemble("\tADRL\tR0, end\nend\tPLD\t[R0]\n")
+        .unwrap();
+
+    // both instructions of the ADRL are executed
+    emulator.step_statement().unwrap();
+    assert_eq!(emulator.registers[15], 8);
+    assert_eq!(emulator.cycles(), 2);
+
+    emulator.step_statement().unwrap();
+    assert_eq!(emulator.registers[15], 12);
+    assert_eq!(emulator.cycles(), 3);
+}
+
+#[test]
+fn test_data_region() {
+    let mut emulator = Emulator::new();
+
+    emulator
+        .assemble("\tPLD\t[R0]\nhello\tDEFB\t\"Hello\", 0\n")
+        .unwrap();
+
+    #[cfg(feature = "disassembler")]
+    assert_eq!(emulator.disassemble(4).unwrap(), "DEFB \"Hell\"");
+    #[cfg(feature = "disassembler")]
+    assert_eq!(emulator.disassemble(8).unwrap(), "DEFB 0x6F, 0x00");
+
+    // execution runs into the data after the PLD, which stops it once faulting on data is turned on
+    emulator.step().unwrap();
+    emulator.set_fault_on_data(true);
+    assert!(matches!(
+        emulator.step(),
+        Err(ExecutionError::ExecutedData {
+            address: 4,
+            jump: None
+        })
+    ));
+    assert_eq!(emulator.registers[15], 4);
+
+    emulator.set_fault_on_data(false);
+    assert!(emulator.step().is_ok());
+}
+
+#[test]
+fn test_jump_diagnostics() {
+    let mut emulator = Emulator::new();
+
+    emulator
+        .assemble("\tPLD\t[R0]\n\tLDMIA\tR0, {PC}\n\tPLD\t[R0]\n\tDEFW\t0\n")
+        .unwrap();
+    emulator.set_fault_on_data(true);
+
+    // the PC is loaded with the address of the data
+    emulator.write_word(0x100, 12);
+    emulator.registers[0] = 0x100;
+
+    emulator.step().unwrap();
+    emulator.step().unwrap();
+    assert!(matches!(
+        emulator.step(),
+        Err(ExecutionError::ExecutedData {
+            address: 12,
+            jump: Some(Jump {
+                address: 4,
+                source_line: Some(SourceLine { module: 0, line: 1 })
+            })
+        })
+    ));
+
+    emulator.set_register(15, 2);
+    assert!(matches!(
+        emulator.step(),
+        Err(ExecutionError::MisalignedPc {
+            address: 2,
+            jump: None
+        })
+    ));
+}
+
+#[test]
+fn test_self_modifying_code() {
+    let mut emulator = Emulator::new();
+
+    emulator
+        .assemble("\tSTMIA\tR0, {R1}\nnext\tPLD\t[R0]\n\tPLD\t[R0]\n")
+        .unwrap();
+
+    // the second instruction is decoded and cached by executing it
+    emulator.set_register(15, 4);
+    emulator.step().unwrap();
+
+    // overwrite it with ADD R2, R2, R3
+    emulator.set_register(15, 0);
+    emulator.registers[0] = 4;
+    emulator.registers[1] = 0xE0822003;
+    emulator.registers[3] = 5;
+
+    emulator.step().unwrap();
+    emulator.step().unwrap();
+    assert_eq!(emulator.registers[2], 5);
+
+    emulator.set_self_modifying_code(SelfModifyingCode::Fault);
+    emulator.set_register(15, 0);
+    assert!(matches!(
+        emulator.step(),
+        Err(ExecutionError::SelfModifyingCode {
+            address: 0,
+            source_line: Some(SourceLine { module: 0, line: 0 }),
+            target: 4,
+            label: Some(label),
+        }) if label == "next"
+    ));
+}
+
+#[test]
+fn test_load_store() {
+    let mut emulator = Emulator::new();
+
+    emulator
+        .assemble(
+            "\tSTR\tR1, [R0], #4
+\tSTRB\tR1, [R0, #1]!
+\tLDR\tR2, [R0, #-5]
+\tLDRB\tR3, [R0]
+\tSTRB\tR1, [R5]
+\tSVC\t2
+loop\tB\tloop
+value\tDEFW\t0, 0
+",
+        )
+        .unwrap();
+
+    emulator.registers[0] = 28;
+    emulator.registers[1] = 0x11223344;
+    emulator.registers[5] = 26;
+
+    // post-indexed, so the word is stored at the base before it moves on
+    emulator.step().unwrap();
+    assert_eq!(emulator.registers[0], 32);
+
+    emulator.step().unwrap();
+    assert_eq!(emulator.registers[0], 33);
+
+    emulator.step().unwrap();
+    assert_eq!(emulator.registers[2], 0x11223344);
+
+    emulator.step().unwrap();
+    assert_eq!(emulator.registers[3], 0x44);
+
+    // the code is read-only, so the stray store names the line and the label it would overwrite
+    emulator.set_self_modifying_code(SelfModifyingCode::Fault);
+    let code = emulator.memory[26];
+    assert!(matches!(
+        emulator.step(),
+        Err(ExecutionError::SelfModifyingCode {
+            address: 16,
+            source_line: Some(SourceLine { module: 0, line: 4 }),
+            target: 26,
+            label: Some(label),
+        }) if label == "loop"
+    ));
+    assert_eq!(emulator.memory[26], code);
+}
+
+#[test]
+fn test_load_report() {
+    let mut emulator = Emulator::new();
+
+    let report = emulator
+        .assemble("\tPLD\t[R0]\nstart\tLDMIA\tR0!, {R0}\n\tORIGIN\t0x100\nvalue\tDEFW\t1\n\tENTRY\n\tSVC\t2\n")
+        .unwrap();
+
+    assert_eq!(report.entry_point, 0x104);
+    assert_eq!(report.regions, [0..8, 0x100..0x108]);
+    assert_eq!(report.symbol_count, 2);
+    assert!(matches!(
+        report.warnings[..],
+        [AssemblyWarning::ParseWarning(_)]
+    ));
+}
+
+#[test]
+fn test_modules() {
+    let mut emulator = Emulator::new();
+
+    emulator
+        .assemble("limit\tEQU\t0x40\n\tORIGIN\t0x1000\ndouble\tADD\tR0, R0, R0\n\tMOV\tPC, LR\n")
+        .unwrap();
+
+    // the student's program can refer to the library's symbols
+    let report = emulator
+        .assemble_module("\tMOV\tR1, #limit\n\tMOV\tR2, #double\n\tSVC\t2\n")
+        .unwrap();
+
+    assert_eq!(report.regions, [0..12]);
+    // the imported symbols aren't counted
+    assert_eq!(report.symbol_count, 0);
+    assert_eq!(emulator.modules().len(), 2);
+    assert_eq!(
+        emulator.modules()[1].statements[..2],
+        Program::assemble("\tMOV\tR1, #0x40\n\tMOV\tR2, #0x1000\n")
+            .unwrap()
+            .statements[..]
+    );
+
+    // the symbols of every module can be used from the REPL
+    emulator.execute_source_line("ADD R3, R1, #double").unwrap();
+    assert_eq!(emulator.registers[3], 0x1000);
+
+    // each module keeps its own source map
+    assert_eq!(
+        emulator.source_line(0x1004),
+        Some(SourceLine { module: 0, line: 3 })
+    );
+    assert_eq!(
+        emulator.source_line(4),
+        Some(SourceLine { module: 1, line: 1 })
+    );
+
+    assert!(matches!(
+        emulator.assemble_module("double\tMOV\tR0, #1\n"),
+        Err(LoadError::SymbolCollision { symbol: Symbol(symbol), module: 0 }) if symbol == "double"
+    ));
+    assert!(matches!(
+        emulator.assemble_module("\tORIGIN\t0x1004\n\tMOV\tR0, #1\n"),
+        Err(LoadError::Overlap {
+            address: 0x1004,
+            module: 0
+        })
+    ));
+    assert_eq!(emulator.modules().len(), 2);
+}
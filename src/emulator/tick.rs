@@ -0,0 +1,62 @@
+use super::{Emulator, ExecutionError, Poll};
+
+/// called once every cycle, after the cycle's instruction, to model a device that runs alongside the processor, see [`Emulator::add_ticker`]
+///
+/// Tickers must be [`Send`], so that the emulator can be moved to a worker thread.
+pub type TickHandler = Box<dyn FnMut(&mut Emulator) + Send>;
+
+impl Emulator {
+    /// calls `ticker` every cycle, e.g. to feed a UART's input at its baud rate or raise an IRQ from a device
+    ///
+    /// Tickers, like the timers of mounted peripherals, are run by every step, so they keep time with the cycle count however the program is run.
+    pub fn add_ticker(&mut self, ticker: impl FnMut(&mut Emulator) + Send + 'static) {
+        self.tickers.push(Box::new(ticker));
+    }
+
+    /// steps the processor and peripherals in lockstep for `cycles` cycles, returning early if the program halts
+    ///
+    /// Unlike [`Emulator::poll_step`], an SVC that waits doesn't stop the ticks, as a peripheral may be what it is waiting for, so time carries on passing while it is retried.
+    /// The result is [`Poll::Waiting`] if the SVC is still waiting at the end.
+    pub fn tick(&mut self, cycles: u64) -> Result<Poll, ExecutionError> {
+        let end = self.cycles + cycles;
+
+        while self.cycles < end {
+            if self.is_halted() {
+                return Ok(Poll::Halted);
+            }
+
+            self.step()?;
+        }
+
+        Ok(match self.waiting {
+            true => Poll::Waiting,
+            false => Poll::Yielded,
+        })
+    }
+
+    /// runs the peripherals for each cycle since `start`, which is the cycle count before the last step
+    pub(super) fn run_peripherals(&mut self, start: u64) {
+        if self.tickers.is_empty() && self.mapped.is_empty() {
+            return;
+        }
+
+        let end = self.cycles;
+
+        for cycle in start + 1..=end {
+            self.cycles = cycle;
+
+            self.tick_timers();
+
+            let mut tickers = std::mem::take(&mut self.tickers);
+            for ticker in &mut tickers {
+                ticker(self);
+            }
+
+            // keep any tickers added by the ones that ran
+            tickers.append(&mut self.tickers);
+            self.tickers = tickers;
+        }
+
+        self.cycles = end;
+    }
+}
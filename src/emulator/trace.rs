@@ -0,0 +1,202 @@
+use std::fmt::Display;
+
+use super::{parse_hex, Emulator, ExecutionError};
+
+/// the first line of a trace file, which includes the version of the format
+const HEADER: &str = "eremius trace 1\n";
+
+/// the steps taken by a run of a program, which can be stored as a golden trace and compared against later runs, see [`Emulator::compare_trace`]
+///
+/// The file is text, with a line for each step like `0x00000008 r0=0x00000005 cpsr=0x60000010`, so it can be read and diffed.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Trace {
+    pub steps: Vec<TraceStep>,
+}
+
+/// an instruction that was stepped, and the registers it changed
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceStep {
+    /// the address of the instruction
+    pub address: u32,
+    /// the registers other than the PC whose values changed, in order, with their new values
+    pub writes: Vec<(u8, u32)>,
+    /// the CPSR, if it changed
+    pub cpsr: Option<u32>,
+}
+
+/// a problem reading a trace file
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceError {
+    /// the file doesn't start with the header of a trace file of this version
+    UnknownFormat,
+    Malformed {
+        line: usize,
+    },
+}
+
+/// where a run first took a different step to a golden trace
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    /// the zero-based index of the step
+    pub step: usize,
+    /// the step in the golden trace, or `None` if the run took more steps
+    pub expected: Option<TraceStep>,
+    /// the step the run took, or `None` if it halted before taking it
+    pub actual: Option<TraceStep>,
+    /// the steps before it that matched, up to [`Divergence::CONTEXT`] of them
+    pub context: Vec<TraceStep>,
+}
+
+impl Trace {
+    /// reads a trace file
+    pub fn parse(text: &str) -> Result<Trace, TraceError> {
+        let rest = text.strip_prefix(HEADER).ok_or(TraceError::UnknownFormat)?;
+
+        let steps = rest
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty())
+            .map(|(index, line)| {
+                // the header is the first line
+                TraceStep::parse(line).ok_or(TraceError::Malformed { line: index + 2 })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Trace { steps })
+    }
+
+    /// the first step where `actual` differs from this trace, or `None` if they are the same
+    pub fn first_divergence(&self, actual: &Trace) -> Option<Divergence> {
+        let step = (0..self.steps.len().max(actual.steps.len()))
+            .find(|&i| self.steps.get(i) != actual.steps.get(i))?;
+
+        Some(Divergence {
+            step,
+            expected: self.steps.get(step).cloned(),
+            actual: actual.steps.get(step).cloned(),
+            context: self.steps[step.saturating_sub(Divergence::CONTEXT)..step].to_vec(),
+        })
+    }
+}
+
+impl TraceStep {
+    fn parse(line: &str) -> Option<TraceStep> {
+        let mut fields = line.split(' ');
+
+        let mut step = TraceStep {
+            address: parse_hex(fields.next()?)?,
+            writes: Vec::new(),
+            cpsr: None,
+        };
+
+        for field in fields {
+            match field.split_once('=')? {
+                ("cpsr", value) => step.cpsr = Some(parse_hex(value)?),
+                (register, value) => {
+                    let register = register.strip_prefix('r')?.parse().ok()?;
+                    step.writes.push((register, parse_hex(value)?));
+                }
+            }
+        }
+
+        Some(step)
+    }
+}
+
+impl Divergence {
+    /// the number of matching steps kept before a divergence
+    pub const CONTEXT: usize = 3;
+}
+
+impl Display for Trace {
+    /// writes the trace file
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{HEADER}")?;
+
+        for step in &self.steps {
+            writeln!(f, "{step}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for TraceStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#010X}", self.address)?;
+
+        for (register, value) in &self.writes {
+            write!(f, " r{register}={value:#010X}")?;
+        }
+
+        if let Some(cpsr) = self.cpsr {
+            write!(f, " cpsr={cpsr:#010X}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for Divergence {
+    /// writes the matching steps before the divergence, then the expected and actual steps
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "trace diverges at step {}", self.step)?;
+
+        for step in &self.context {
+            writeln!(f, "           {step}")?;
+        }
+
+        match &self.expected {
+            Some(step) => writeln!(f, "expected:  {step}")?,
+            None => writeln!(f, "expected:  the program to halt")?,
+        }
+
+        match &self.actual {
+            Some(step) => write!(f, "actual:    {step}"),
+            None => write!(f, "actual:    the program halted"),
+        }
+    }
+}
+
+impl Emulator {
+    /// steps until the program finishes or an SVC has to wait, recording each step, and executing at most `limit` instructions
+    pub fn record_trace(&mut self, limit: usize) -> Result<Trace, ExecutionError> {
+        let mut trace = Trace::default();
+
+        while trace.steps.len() < limit && !self.is_halted() {
+            // edits are made before the snapshot, so they aren't recorded as changes made by the instruction
+            self.replay_edits();
+
+            let address = self.registers[15];
+            let registers = self.registers.0;
+            let cpsr = self.cpsr.bits();
+
+            self.step()?;
+
+            trace.steps.push(TraceStep {
+                address,
+                writes: (0..15)
+                    .filter(|&register| self.registers.0[register] != registers[register])
+                    .map(|register| (register as u8, self.registers.0[register]))
+                    .collect(),
+                cpsr: (self.cpsr.bits() != cpsr).then_some(self.cpsr.bits()),
+            });
+
+            if self.waiting {
+                break;
+            }
+        }
+
+        Ok(trace)
+    }
+
+    /// runs the program and compares its trace against a golden trace, returning where it first diverges, or `None` if it matches
+    ///
+    /// This guards the emulator's behaviour when instructions are changed or added, by storing the traces of known-good runs.
+    /// At most one step more than the golden trace is run, so a program that should have halted is caught without running forever.
+    pub fn compare_trace(&mut self, golden: &Trace) -> Result<Option<Divergence>, ExecutionError> {
+        let actual = self.record_trace(golden.steps.len() + 1)?;
+
+        Ok(golden.first_divergence(&actual))
+    }
+}
@@ -0,0 +1,59 @@
+use std::sync::{
+    atomic::{AtomicU32, AtomicU64, Ordering},
+    Arc,
+};
+
+use super::{Emulator, Psr};
+
+/// a read-only view of the registers and flags, which can be read from another thread while the emulator runs
+///
+/// The view is updated after every step, so a GUI thread can show the registers while a worker thread steps, without locking or copying the emulator.
+/// Registers are published individually, so a reader may see a mix of two consecutive steps.
+#[derive(Debug, Clone)]
+pub struct MachineView(Arc<SharedState>);
+
+#[derive(Debug, Default)]
+struct SharedState {
+    registers: [AtomicU32; 16],
+    cpsr: AtomicU32,
+    cycles: AtomicU64,
+}
+
+impl MachineView {
+    pub(super) fn new() -> MachineView {
+        MachineView(Arc::default())
+    }
+
+    /// the value of a register as of the last step, where 13 is SP, 14 is LR and 15 is PC
+    pub fn register(&self, register: u8) -> u32 {
+        self.0.registers[register as usize].load(Ordering::Relaxed)
+    }
+
+    /// the CPSR as of the last step
+    pub fn cpsr(&self) -> Psr {
+        Psr::from_bits(self.0.cpsr.load(Ordering::Relaxed))
+    }
+
+    pub fn cycles(&self) -> u64 {
+        self.0.cycles.load(Ordering::Relaxed)
+    }
+}
+
+impl Emulator {
+    /// a view of the registers that can be sent to another thread, see [`MachineView`]
+    pub fn view(&self) -> MachineView {
+        self.view.clone()
+    }
+
+    /// updates the view with the current registers and flags
+    pub(super) fn publish(&self) {
+        let state = &self.view.0;
+
+        for (shared, value) in state.registers.iter().zip(self.registers.0) {
+            shared.store(value, Ordering::Relaxed);
+        }
+
+        state.cpsr.store(self.cpsr.bits(), Ordering::Relaxed);
+        state.cycles.store(self.cycles, Ordering::Relaxed);
+    }
+}
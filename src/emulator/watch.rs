@@ -0,0 +1,90 @@
+use super::{Emulator, ExecutionError};
+
+/// handles a watched store, given the emulator and the store
+pub type WatchHandler = Box<dyn FnMut(&mut Emulator, &WatchHit) + Send>;
+
+/// what happens when an instruction stores to a watched word, see [`Emulator::watch`]
+pub enum WatchAction {
+    /// stop with an [`ExecutionError::Watchpoint`] once the storing instruction has finished
+    Halt,
+    /// record the store in [`Emulator::watch_log`] and carry on
+    Log,
+    /// call a handler, e.g. to check the value or change the registers, and carry on
+    Call(WatchHandler),
+    /// store this value instead of the one the program stored, e.g. to fake a device register
+    Inject(u32),
+}
+
+/// a store to a watched word
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WatchHit {
+    /// the address of the word
+    pub address: u32,
+    /// the value the program stored, before any [`WatchAction::Inject`]
+    pub value: u32,
+    /// the address of the storing instruction
+    pub pc: u32,
+    /// the number of instructions executed, including the storing instruction
+    pub cycles: u64,
+}
+
+impl Emulator {
+    /// watches for instructions storing to the word at `address`, replacing any previous watchpoint for it
+    ///
+    /// This makes simple test benches, e.g. logging every value a program writes to its `RESULT` word.
+    pub fn watch(&mut self, address: u32, action: WatchAction) {
+        self.watchpoints.insert(address & !0b11, action);
+    }
+
+    /// stops watching the word at `address`
+    pub fn unwatch(&mut self, address: u32) {
+        self.watchpoints.remove(&(address & !0b11));
+    }
+
+    /// the stores to words watched with [`WatchAction::Log`], in order
+    pub fn watch_log(&self) -> &[WatchHit] {
+        &self.watch_log
+    }
+
+    /// runs the action for a store of `value` to `address`, returning the value to store
+    pub(super) fn watch_store(&mut self, address: u32, value: u32) -> u32 {
+        if !self.watchpoints.contains_key(&address) {
+            return value;
+        }
+
+        let hit = WatchHit {
+            address,
+            value,
+            pc: self.registers[15].wrapping_sub(4),
+            cycles: self.cycles,
+        };
+
+        match self.watchpoints.get(&address) {
+            Some(WatchAction::Halt) => self.watch_halt = Some(hit),
+            Some(WatchAction::Log) => self.watch_log.push(hit),
+            Some(WatchAction::Inject(injected)) => return *injected,
+            Some(WatchAction::Call(_)) => {
+                // the handler is taken out of the emulator while it runs, so that it can borrow the emulator mutably
+                if let Some(WatchAction::Call(mut handler)) = self.watchpoints.remove(&address) {
+                    handler(self, &hit);
+
+                    // keep any replacement the handler set for itself
+                    self.watchpoints
+                        .entry(address)
+                        .or_insert(WatchAction::Call(handler));
+                }
+            }
+            None => {}
+        }
+
+        value
+    }
+
+    /// the error for a watchpoint that halted during the last instruction
+    pub(super) fn watch_halted(&mut self) -> Result<(), ExecutionError> {
+        match self.watch_halt.take() {
+            Some(hit) => Err(ExecutionError::Watchpoint(hit)),
+            None => Ok(()),
+        }
+    }
+}
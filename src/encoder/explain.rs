@@ -0,0 +1,479 @@
+use crate::ir::{
+    register_name, AddressingOffsetValue, BranchKind, Condition, DataProcessingKind, Imm,
+    InstructionKind, LoadStoreAddressCode, LoadStoreKind, LoadStoreQuantity, MoveKind,
+    MultipleAddressingMode, OffsetMode, SetFlags, Shift, ShifterOperandCode, Sign, WriteBack,
+};
+
+use super::bits;
+
+/// a field of an encoded instruction, like a box in the encoding diagrams of the ARM reference manual
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncodingField {
+    /// the name of the field in the reference manual, e.g. `cond` or `Rn`
+    pub name: &'static str,
+    /// the highest bit of the field
+    pub high: u8,
+    /// the lowest bit of the field
+    pub low: u8,
+    /// the bits of the field, shifted down to bit 0
+    pub value: u32,
+    /// what the bits mean, e.g. `AL` for a condition of `0b1110`
+    pub meaning: String,
+}
+
+impl EncodingField {
+    pub fn width(&self) -> u8 {
+        self.high - self.low + 1
+    }
+
+    /// the bits of the field in binary, e.g. `1110`
+    pub fn bits(&self) -> String {
+        format!("{:0width$b}", self.value, width = self.width() as usize)
+    }
+}
+
+/// builds the fields of an encoded word, from the highest bits to the lowest
+struct Fields {
+    word: u32,
+    fields: Vec<EncodingField>,
+}
+
+impl Fields {
+    fn field(mut self, name: &'static str, high: u8, low: u8, meaning: impl Into<String>) -> Self {
+        self.fields.push(EncodingField {
+            name,
+            high,
+            low,
+            value: (self.word >> low) & bits::bottom_n((high - low + 1) as u32),
+            meaning: meaning.into(),
+        });
+
+        self
+    }
+
+    fn bit(self, name: &'static str, bit: u8, meaning: impl Into<String>) -> Self {
+        self.field(name, bit, bit, meaning)
+    }
+
+    fn condition(self, condition: &Condition) -> Self {
+        self.field("cond", 31, 28, format!("{condition:?}"))
+    }
+
+    fn register(self, name: &'static str, low: u8, register: u8) -> Self {
+        self.field(name, low + 3, low, register_name(register))
+    }
+
+    /// the fields of bits `0` to `11` when they hold a shifter operand or a register offset
+    fn shifter(self, shifter: &ShifterOperandCode) -> Self {
+        match shifter {
+            ShifterOperandCode::Immediate(immediate) => self
+                .field(
+                    "rotate_imm",
+                    11,
+                    8,
+                    format!("ROR #{}", immediate.rotate() as u32 * 2),
+                )
+                .field("immed_8", 7, 0, format!("#{}", immediate.value() as u32)),
+            ShifterOperandCode::ImmediateShift(shift) => self
+                .field("shift_imm", 11, 7, format!("#{}", shift.amount.get()))
+                .field("shift", 6, 5, shift_name(&shift.kind))
+                .bit("0", 4, "shift by an immediate")
+                .register("Rm", 0, shift.base.0),
+            ShifterOperandCode::RegisterShift(shift) => self
+                .register("Rs", 8, shift.amount.0)
+                .bit("0", 7, "")
+                .field("shift", 6, 5, shift_name(&shift.kind))
+                .bit("1", 4, "shift by a register")
+                .register("Rm", 0, shift.base.0),
+        }
+    }
+
+    /// the I, P and U bits of a load or store
+    fn addressing(self, address: &LoadStoreAddressCode<Imm<12>, Imm<5>>) -> Self {
+        let offset = &address.offset;
+
+        self.bit(
+            "I",
+            25,
+            match offset.value {
+                AddressingOffsetValue::Immediate(_) => "immediate offset",
+                _ => "register offset",
+            },
+        )
+        .bit(
+            "P",
+            24,
+            match offset.mode {
+                OffsetMode::PostIndexed => "post-indexed",
+                _ => "offset or pre-indexed",
+            },
+        )
+        .bit(
+            "U",
+            23,
+            match offset.sign {
+                Sign::Positive => "add the offset",
+                Sign::Negative => "subtract the offset",
+            },
+        )
+    }
+
+    /// bits `0` to `11` of a load or store
+    fn offset(self, value: &AddressingOffsetValue<Imm<12>, Imm<5>>) -> Self {
+        match value {
+            AddressingOffsetValue::Immediate(immediate) => {
+                self.field("offset_12", 11, 0, format!("#{}", immediate.get()))
+            }
+            AddressingOffsetValue::Register(register) => self
+                .field("shift_imm", 11, 7, "#0")
+                .field("shift", 6, 5, "LSL")
+                .bit("0", 4, "")
+                .register("Rm", 0, register.0),
+            AddressingOffsetValue::ScaledRegister(shift) => self
+                .field("shift_imm", 11, 7, format!("#{}", shift.amount.get()))
+                .field("shift", 6, 5, shift_name(&shift.kind))
+                .bit("0", 4, "")
+                .register("Rm", 0, shift.base.0),
+        }
+    }
+}
+
+impl InstructionKind {
+    /// splits the encoding of the instruction into its fields, from bit 31 down to bit 0, to explain what each part of the word means
+    pub fn explain_encoding(&self) -> Vec<EncodingField> {
+        let fields = Fields {
+            word: self.encode().word(),
+            fields: Vec::new(),
+        };
+
+        let fields = match self {
+            InstructionKind::Branch {
+                condition,
+                kind,
+                target,
+            } => fields
+                .condition(condition)
+                .field("101", 27, 25, "branch")
+                .bit(
+                    "L",
+                    24,
+                    match kind {
+                        BranchKind::Branch => "don't link",
+                        BranchKind::BranchWithLink => "link",
+                    },
+                )
+                .field(
+                    "signed_immed_24",
+                    23,
+                    0,
+                    format!("{} words from PC", target.get()),
+                ),
+
+            InstructionKind::BranchWithLinkAndExchange { target, halfword } => fields
+                .field("1111", 31, 28, "unconditional")
+                .field("101", 27, 25, "branch with link and exchange")
+                .bit(
+                    "H",
+                    24,
+                    if *halfword {
+                        "plus a halfword"
+                    } else {
+                        "no halfword"
+                    },
+                )
+                .field(
+                    "signed_immed_24",
+                    23,
+                    0,
+                    format!("{} words from PC", target.get()),
+                ),
+
+            InstructionKind::BranchAndExchange { condition, target } => fields
+                .condition(condition)
+                .field("00010010", 27, 20, "branch and exchange")
+                .field("SBO", 19, 8, "unused")
+                .field("0001", 7, 4, "branch and exchange")
+                .register("Rm", 0, target.0),
+
+            InstructionKind::DataProcessing { condition, kind } => {
+                let fields = fields
+                    .condition(condition)
+                    .field("00", 27, 26, "data processing");
+
+                let shifter = match kind {
+                    DataProcessingKind::Move { shifter, .. }
+                    | DataProcessingKind::Comparison { shifter, .. }
+                    | DataProcessingKind::Calculation { shifter, .. } => shifter,
+                };
+
+                let fields = fields.bit(
+                    "I",
+                    25,
+                    match shifter {
+                        ShifterOperandCode::Immediate(_) => "immediate",
+                        _ => "register",
+                    },
+                );
+
+                let fields = match kind {
+                    DataProcessingKind::Move {
+                        kind,
+                        set_flags,
+                        destination,
+                        ..
+                    } => fields
+                        .field(
+                            "opcode",
+                            24,
+                            21,
+                            match kind {
+                                MoveKind::Move => "MOV",
+                                MoveKind::MoveNot => "MVN",
+                            },
+                        )
+                        .bit("S", 20, set_flags_meaning(set_flags))
+                        .field("SBZ", 19, 16, "unused")
+                        .register("Rd", 12, destination.0),
+                    DataProcessingKind::Comparison { kind, source, .. } => fields
+                        .field("opcode", 24, 21, format!("{kind:?}"))
+                        .bit("S", 20, "set flags")
+                        .register("Rn", 16, source.0)
+                        .field("SBZ", 15, 12, "unused"),
+                    DataProcessingKind::Calculation {
+                        kind,
+                        set_flags,
+                        destination,
+                        source,
+                        ..
+                    } => fields
+                        .field("opcode", 24, 21, format!("{kind:?}"))
+                        .bit("S", 20, set_flags_meaning(set_flags))
+                        .register("Rn", 16, source.0)
+                        .register("Rd", 12, destination.0),
+                };
+
+                fields.shifter(shifter)
+            }
+
+            InstructionKind::Multiply {
+                condition,
+                set_flags,
+                destination,
+                multiplicand,
+                multiplier,
+                accumulate,
+            } => {
+                let fields = fields
+                    .condition(condition)
+                    .field("000000", 27, 22, "multiply");
+
+                let fields = match accumulate {
+                    Some(accumulate) => fields
+                        .bit("A", 21, "accumulate")
+                        .bit("S", 20, set_flags_meaning(set_flags))
+                        .register("Rd", 16, destination.0)
+                        .register("Rn", 12, accumulate.0),
+                    None => fields
+                        .bit("A", 21, "don't accumulate")
+                        .bit("S", 20, set_flags_meaning(set_flags))
+                        .register("Rd", 16, destination.0)
+                        .field("SBZ", 15, 12, "unused"),
+                };
+
+                fields
+                    .register("Rs", 8, multiplier.0)
+                    .field("1001", 7, 4, "multiply")
+                    .register("Rm", 0, multiplicand.0)
+            }
+
+            InstructionKind::MultiplyLong {
+                condition,
+                kind,
+                set_flags,
+                destination,
+                multiplicand,
+                multiplier,
+            } => fields
+                .condition(condition)
+                .field("00001", 27, 23, "long multiply")
+                .bit("U", 22, if kind.signed() { "signed" } else { "unsigned" })
+                .bit(
+                    "A",
+                    21,
+                    if kind.accumulates() {
+                        "accumulate"
+                    } else {
+                        "don't accumulate"
+                    },
+                )
+                .bit("S", 20, set_flags_meaning(set_flags))
+                .register("RdHi", 16, destination.high.0)
+                .register("RdLo", 12, destination.low.0)
+                .register("Rs", 8, multiplier.0)
+                .field("1001", 7, 4, "multiply")
+                .register("Rm", 0, multiplicand.0),
+
+            InstructionKind::LoadStore {
+                condition,
+                kind,
+                quantity,
+                destination,
+                address,
+            } => fields
+                .condition(condition)
+                .field("01", 27, 26, "load or store")
+                .addressing(address)
+                .bit(
+                    "B",
+                    22,
+                    match quantity {
+                        LoadStoreQuantity::Word => "word",
+                        LoadStoreQuantity::Byte => "byte",
+                    },
+                )
+                .bit("W", 21, write_back_meaning(&address.offset.mode))
+                .bit(
+                    "L",
+                    20,
+                    match kind {
+                        LoadStoreKind::Load => "load",
+                        LoadStoreKind::Store => "store",
+                    },
+                )
+                .register("Rn", 16, address.base.0)
+                .register("Rd", 12, destination.0)
+                .offset(&address.offset.value),
+
+            InstructionKind::Preload { address } => fields
+                .field("1111", 31, 28, "unconditional")
+                .field("01", 27, 26, "load or store")
+                .addressing(address)
+                .bit("1", 22, "")
+                .field("01", 21, 20, "preload")
+                .register("Rn", 16, address.base.0)
+                .field("1111", 15, 12, "")
+                .offset(&address.offset.value),
+
+            InstructionKind::LoadStoreMultiple {
+                condition,
+                kind,
+                mode,
+                base,
+                write_back,
+                register_list,
+            } => {
+                let (before, increment) = match mode {
+                    MultipleAddressingMode::IncrementAfter => ("after", "increment"),
+                    MultipleAddressingMode::IncrementBefore => ("before", "increment"),
+                    MultipleAddressingMode::DecrementAfter => ("after", "decrement"),
+                    MultipleAddressingMode::DecrementBefore => ("before", "decrement"),
+                };
+
+                fields
+                    .condition(condition)
+                    .field("100", 27, 25, "load or store multiple")
+                    .bit("P", 24, before)
+                    .bit("U", 23, increment)
+                    .bit("S", 22, "")
+                    .bit(
+                        "W",
+                        21,
+                        match write_back {
+                            WriteBack::WriteBack => "write back",
+                            WriteBack::NoWriteBack => "don't write back",
+                        },
+                    )
+                    .bit(
+                        "L",
+                        20,
+                        match kind {
+                            LoadStoreKind::Load => "load",
+                            LoadStoreKind::Store => "store",
+                        },
+                    )
+                    .register("Rn", 16, base.0)
+                    .field("register_list", 15, 0, register_list.to_string())
+            }
+
+            InstructionKind::SuperVisorCall {
+                condition,
+                immediate,
+            } => fields
+                .condition(condition)
+                .field("1111", 27, 24, "supervisor call")
+                .field("immed_24", 23, 0, format!("{}", immediate.get())),
+
+            InstructionKind::MoveStatusToRegister {
+                condition,
+                destination,
+            } => fields
+                .condition(condition)
+                .field("00010", 27, 23, "move status register")
+                .bit("R", 22, "CPSR")
+                .field("00", 21, 20, "to a register")
+                .field("SBO", 19, 16, "unused")
+                .register("Rd", 12, destination.0)
+                .field("SBZ", 11, 0, "unused"),
+
+            InstructionKind::MoveToStatusRegister {
+                condition,
+                fields: status_fields,
+                source,
+            } => fields
+                .condition(condition)
+                .field("00", 27, 26, "move to status register")
+                .bit(
+                    "I",
+                    25,
+                    match source {
+                        ShifterOperandCode::Immediate(_) => "immediate",
+                        _ => "register",
+                    },
+                )
+                .field("10", 24, 23, "move to status register")
+                .bit("R", 22, "CPSR")
+                .field("10", 21, 20, "move to status register")
+                .field("field_mask", 19, 16, format!("CPSR_{status_fields}"))
+                .field("SBO", 15, 12, "unused")
+                .shifter(source),
+
+            InstructionKind::CountLeadingZeros {
+                condition,
+                destination,
+                source,
+            } => fields
+                .condition(condition)
+                .field("00010110", 27, 20, "count leading zeros")
+                .field("SBO", 19, 16, "unused")
+                .register("Rd", 12, destination.0)
+                .field("SBO", 11, 8, "unused")
+                .field("0001", 7, 4, "count leading zeros")
+                .register("Rm", 0, source.0),
+        };
+
+        fields.fields
+    }
+}
+
+fn set_flags_meaning(set_flags: &SetFlags) -> &'static str {
+    match set_flags {
+        SetFlags::Set => "set flags",
+        SetFlags::DontSet => "don't set flags",
+    }
+}
+
+fn write_back_meaning(mode: &OffsetMode) -> &'static str {
+    match mode {
+        OffsetMode::PreIndexed => "write back",
+        _ => "don't write back",
+    }
+}
+
+fn shift_name(shift: &Shift) -> &'static str {
+    match shift {
+        Shift::LogicalShiftLeft => "LSL",
+        Shift::LogicalShiftRight => "LSR",
+        Shift::ArithmeticShiftRight => "ASR",
+        Shift::RotateRight => "ROR",
+    }
+}
@@ -1,23 +1,27 @@
-use crate::{
-    ir::{
-        AddressingOffset, BranchKind, Condition, DataProcessingKind, Imm, InstructionKind,
-        LoadStoreAddressCode, LoadStoreKind, LoadStoreQuantity, MoveKind, MultipleAddressingMode,
-        OffsetMode, Rd, RegisterList, Rm, Rn, RotatedImm8, Rs, SetFlags, Shift, ShiftedRegister,
-        ShifterOperandCode, Sign, SignedImm, WriteBack,
-    },
-    parser::AddressingOffsetValue,
+use crate::ir::{
+    AddressingOffset, AddressingOffsetValue, BranchKind, CalculationKind, ComparisonKind,
+    Condition, DataProcessingKind, Imm, InstructionKind, LoadStoreAddressCode, LoadStoreKind,
+    LoadStoreQuantity, MoveKind, MultipleAddressingMode, MultiplyLongKind, OffsetMode, Rd,
+    RegisterList, RegisterPair, Rm, Rn, RotatedImm8, Rs, SetFlags, Shift, ShiftedRegister,
+    ShifterOperandCode, Sign, SignedImm, StatusFields, WriteBack,
 };
 
 mod bits;
+mod explain;
 mod tests;
+mod word;
+
+pub use explain::EncodingField;
+pub use word::EncodedInstruction;
 
 pub trait Encode {
     fn encode(&self) -> u32;
 }
 
-impl Encode for InstructionKind {
-    fn encode(&self) -> u32 {
-        match self {
+impl InstructionKind {
+    /// the instruction's 32-bit word
+    pub fn encode(&self) -> EncodedInstruction {
+        EncodedInstruction(match self {
             InstructionKind::Branch {
                 condition,
                 kind,
@@ -31,6 +35,24 @@ impl Encode for InstructionKind {
                 condition.encode() | 0b101 << 25 | kind.encode() | target.encode()
             }
 
+            InstructionKind::BranchWithLinkAndExchange { target, halfword } => {
+                // ENCODING:
+                //
+                //  3 3 2 2 2 2 2 2 2 2 2 2 1 1 1 1 1 1 1 1 1 1
+                //  1 0 9 8 7 6 5 4 3 2 1 0 9 8 7 6 5 4 3 2 1 0 9 8 7 6 5 4 3 2 1 0
+                // |1 1 1 1|1 0 1|h|target                                         |
+                Condition::NV.encode() | 0b101 << 25 | (*halfword as u32) << 24 | target.encode()
+            }
+
+            InstructionKind::BranchAndExchange { condition, target } => {
+                // ENCODING:
+                //
+                //  3 3 2 2 2 2 2 2 2 2 2 2 1 1 1 1 1 1 1 1 1 1
+                //  1 0 9 8 7 6 5 4 3 2 1 0 9 8 7 6 5 4 3 2 1 0 9 8 7 6 5 4 3 2 1 0
+                // |cond   |0 0 0 1 0 0 1 0|1 1 1 1|1 1 1 1|1 1 1 1|0 0 0 1|Rm     |
+                condition.encode() | 0x012F_FF10 | target.encode()
+            }
+
             InstructionKind::DataProcessing { condition, kind } => {
                 // ENCODING:
                 //
@@ -50,11 +72,14 @@ impl Encode for InstructionKind {
                         //  3 3 2 2 2 2 2 2 2 2 2 2 1 1 1 1 1 1 1 1 1 1
                         //  1 0 9 8 7 6 5 4 3 2 1 0 9 8 7 6 5 4 3 2 1 0 9 8 7 6 5 4 3 2 1 0
                         // |cond   |0 0|I|1 1|N|1|S|SBZ    |Rd     |shifter                |
+                        let immediate = matches!(shifter, ShifterOperandCode::Immediate(_));
 
                         condition.encode()
+                            | (immediate as u32) << 25
                             | 0b11 << 23
                             | kind.encode()
                             | 1 << 21
+                            | set_flags.encode()
                             | destination.encode()
                             | shifter.encode()
                     }
@@ -68,9 +93,12 @@ impl Encode for InstructionKind {
                         //
                         //  3 3 2 2 2 2 2 2 2 2 2 2 1 1 1 1 1 1 1 1 1 1
                         //  1 0 9 8 7 6 5 4 3 2 1 0 9 8 7 6 5 4 3 2 1 0 9 8 7 6 5 4 3 2 1 0
-                        // |cond   |0 0|I|1 0 1 0|1|R n    |SBZ    |shifter                |
+                        // |cond   |0 0|I|1 0|op |1|R n    |SBZ    |shifter                |
+                        let immediate = matches!(shifter, ShifterOperandCode::Immediate(_));
+
                         condition.encode()
-                            | 0b1010 << 21
+                            | (immediate as u32) << 25
+                            | kind.encode()
                             | 1 << 20
                             | source.encode()
                             | shifter.encode()
@@ -87,9 +115,12 @@ impl Encode for InstructionKind {
                         //
                         //  3 3 2 2 2 2 2 2 2 2 2 2 1 1 1 1 1 1 1 1 1 1
                         //  1 0 9 8 7 6 5 4 3 2 1 0 9 8 7 6 5 4 3 2 1 0 9 8 7 6 5 4 3 2 1 0
-                        // |cond   |0 0|I|0 1 0 0|S|Rn     |Rd     |shifter                |
+                        // |cond   |0 0|I|0|op   |S|Rn     |Rd     |shifter                |
+                        let immediate = matches!(shifter, ShifterOperandCode::Immediate(_));
+
                         condition.encode()
-                            | 0b0100 << 21
+                            | (immediate as u32) << 25
+                            | kind.encode()
                             | set_flags.encode()
                             | source.encode()
                             | destination.encode()
@@ -98,6 +129,54 @@ impl Encode for InstructionKind {
                 }
             }
 
+            InstructionKind::Multiply {
+                condition,
+                set_flags,
+                destination,
+                multiplicand,
+                multiplier,
+                accumulate,
+            } => {
+                // ENCODING:
+                //
+                //  3 3 2 2 2 2 2 2 2 2 2 2 1 1 1 1 1 1 1 1 1 1
+                //  1 0 9 8 7 6 5 4 3 2 1 0 9 8 7 6 5 4 3 2 1 0 9 8 7 6 5 4 3 2 1 0
+                // |cond   |0 0 0 0 0 0|A|S|Rd     |Rn     |Rs     |1 0 0 1|Rm     |
+
+                // Rd and Rn are the other way round to data processing
+                condition.encode()
+                    | (accumulate.is_some() as u32) << 21
+                    | set_flags.encode()
+                    | (destination.0 as u32) << 16
+                    | accumulate.map_or(0, |accumulate| (accumulate.0 as u32) << 12)
+                    | multiplier.encode()
+                    | 0b1001 << 4
+                    | multiplicand.encode()
+            }
+
+            InstructionKind::MultiplyLong {
+                condition,
+                kind,
+                set_flags,
+                destination,
+                multiplicand,
+                multiplier,
+            } => {
+                // ENCODING:
+                //
+                //  3 3 2 2 2 2 2 2 2 2 2 2 1 1 1 1 1 1 1 1 1 1
+                //  1 0 9 8 7 6 5 4 3 2 1 0 9 8 7 6 5 4 3 2 1 0 9 8 7 6 5 4 3 2 1 0
+                // |cond   |0 0 0 0 1|U|A|S|RdHi   |RdLo   |Rs     |1 0 0 1|Rm     |
+                condition.encode()
+                    | 1 << 23
+                    | kind.encode()
+                    | set_flags.encode()
+                    | destination.encode()
+                    | multiplier.encode()
+                    | 0b1001 << 4
+                    | multiplicand.encode()
+            }
+
             InstructionKind::LoadStore {
                 condition,
                 kind,
@@ -118,6 +197,20 @@ impl Encode for InstructionKind {
                     | address.encode()
             }
 
+            InstructionKind::Preload { address } => {
+                // ENCODING:
+                //
+                //  3 3 2 2 2 2 2 2 2 2 2 2 1 1 1 1 1 1 1 1 1 1
+                //  1 0 9 8 7 6 5 4 3 2 1 0 9 8 7 6 5 4 3 2 1 0 9 8 7 6 5 4 3 2 1 0
+                // |1 1 1 1|0 1|I|1|U|1|0|1|Rn     |1 1 1 1|offset                 |
+                Condition::NV.encode()
+                    | 1 << 26
+                    | 1 << 22
+                    | 1 << 20
+                    | 0b1111 << 12
+                    | address.encode()
+            }
+
             InstructionKind::LoadStoreMultiple {
                 condition,
                 kind,
@@ -138,6 +231,7 @@ impl Encode for InstructionKind {
                     | 0b100 << 25
                     | mode.encode()
                     | write_back.encode()
+                    | kind.encode()
                     | base.encode()
                     | register_list.encode()
             }
@@ -145,8 +239,60 @@ impl Encode for InstructionKind {
             InstructionKind::SuperVisorCall {
                 condition,
                 immediate,
-            } => condition.encode() | immediate.encode(),
-        }
+            } => {
+                // ENCODING:
+                //
+                //  3 3 2 2 2 2 2 2 2 2 2 2 1 1 1 1 1 1 1 1 1 1
+                //  1 0 9 8 7 6 5 4 3 2 1 0 9 8 7 6 5 4 3 2 1 0 9 8 7 6 5 4 3 2 1 0
+                // |cond   |1 1 1 1|immediate                                      |
+                condition.encode() | 0b1111 << 24 | immediate.encode()
+            }
+
+            InstructionKind::MoveStatusToRegister {
+                condition,
+                destination,
+            } => {
+                // ENCODING:
+                //
+                //  3 3 2 2 2 2 2 2 2 2 2 2 1 1 1 1 1 1 1 1 1 1
+                //  1 0 9 8 7 6 5 4 3 2 1 0 9 8 7 6 5 4 3 2 1 0 9 8 7 6 5 4 3 2 1 0
+                // |cond   |0 0 0 1 0 0 0 0|1 1 1 1|Rd     |SBZ                    |
+                condition.encode() | 0x010F_0000 | destination.encode()
+            }
+
+            InstructionKind::MoveToStatusRegister {
+                condition,
+                fields,
+                source,
+            } => {
+                // ENCODING:
+                //
+                //  3 3 2 2 2 2 2 2 2 2 2 2 1 1 1 1 1 1 1 1 1 1
+                //  1 0 9 8 7 6 5 4 3 2 1 0 9 8 7 6 5 4 3 2 1 0 9 8 7 6 5 4 3 2 1 0
+                // |cond   |0 0|I|1 0 0 1 0|mask   |SBO    |shifter                |
+                let immediate = matches!(source, ShifterOperandCode::Immediate(_));
+
+                condition.encode()
+                    | (immediate as u32) << 25
+                    | 0b10010 << 20
+                    | fields.encode()
+                    | 0b1111 << 12
+                    | source.encode()
+            }
+
+            InstructionKind::CountLeadingZeros {
+                condition,
+                destination,
+                source,
+            } => {
+                // ENCODING:
+                //
+                //  3 3 2 2 2 2 2 2 2 2 2 2 1 1 1 1 1 1 1 1 1 1
+                //  1 0 9 8 7 6 5 4 3 2 1 0 9 8 7 6 5 4 3 2 1 0 9 8 7 6 5 4 3 2 1 0
+                // |cond   |0 0 0 1 0 1 1 0|SBO    |Rd     |SBO    |0 0 0 1|Rm     |
+                condition.encode() | 0x016F_0F10 | destination.encode() | source.encode()
+            }
+        })
     }
 }
 
@@ -191,6 +337,44 @@ impl Encode for MoveKind {
     }
 }
 
+impl Encode for CalculationKind {
+    /// sets bits `21` to `24`
+    fn encode(&self) -> u32 {
+        (match self {
+            CalculationKind::SUB => 0b0010,
+            CalculationKind::RSB => 0b0011,
+            CalculationKind::ADD => 0b0100,
+            CalculationKind::ADC => 0b0101,
+            CalculationKind::SBC => 0b0110,
+            CalculationKind::RSC => 0b0111,
+        }) << 21
+    }
+}
+
+impl Encode for MultiplyLongKind {
+    /// sets bits `21` and `22`
+    fn encode(&self) -> u32 {
+        (match self {
+            MultiplyLongKind::UMULL => 0b00,
+            MultiplyLongKind::UMLAL => 0b01,
+            MultiplyLongKind::SMULL => 0b10,
+            MultiplyLongKind::SMLAL => 0b11,
+        }) << 21
+    }
+}
+
+impl Encode for ComparisonKind {
+    /// sets bits `21` to `24`
+    fn encode(&self) -> u32 {
+        (match self {
+            ComparisonKind::TST => 0b1000,
+            ComparisonKind::TEQ => 0b1001,
+            ComparisonKind::CMP => 0b1010,
+            ComparisonKind::CMN => 0b1011,
+        }) << 21
+    }
+}
+
 impl Encode for SetFlags {
     /// sets bit `20`
     fn encode(&self) -> u32 {
@@ -235,6 +419,13 @@ impl Encode for Rd {
     }
 }
 
+impl Encode for RegisterPair {
+    /// sets bits `12` to `19`
+    fn encode(&self) -> u32 {
+        self.low.encode() | self.high.encode()
+    }
+}
+
 impl Encode for Rm {
     /// sets bits `0` to `3`
     fn encode(&self) -> u32 {
@@ -249,6 +440,16 @@ impl Encode for Rs {
     }
 }
 
+impl Encode for StatusFields {
+    /// sets bits `16` to `19`
+    fn encode(&self) -> u32 {
+        (self.flags as u32) << 19
+            | (self.status as u32) << 18
+            | (self.extension as u32) << 17
+            | (self.control as u32) << 16
+    }
+}
+
 impl Encode for ShifterOperandCode {
     /// sets bits `0` to `11`
     fn encode(&self) -> u32 {
@@ -337,16 +538,16 @@ impl Encode for AddressingOffsetValue<Imm<12>, Imm<5>> {
 }
 
 impl Encode for MultipleAddressingMode {
-    /// sets bits `20`, `23` and `24`
+    /// sets bits `23` and `24`
     fn encode(&self) -> u32 {
-        let (l, p, u) = match self {
-            MultipleAddressingMode::DecrementAfter => (1, 0, 0),
-            MultipleAddressingMode::IncrementAfter => (1, 0, 1),
-            MultipleAddressingMode::DecrementBefore => (1, 1, 0),
-            MultipleAddressingMode::IncrementBefore => (1, 1, 1),
+        let (p, u) = match self {
+            MultipleAddressingMode::DecrementAfter => (0, 0),
+            MultipleAddressingMode::IncrementAfter => (0, 1),
+            MultipleAddressingMode::DecrementBefore => (1, 0),
+            MultipleAddressingMode::IncrementBefore => (1, 1),
         };
 
-        p << 24 | u << 23 | l << 20
+        p << 24 | u << 23
     }
 }
 
@@ -363,11 +564,6 @@ impl Encode for WriteBack {
 impl Encode for RegisterList {
     /// sets bits `0` to `15`
     fn encode(&self) -> u32 {
-        self.registers
-            .iter()
-            .enumerate()
-            .fold(0, |mask, (i, value)| {
-                mask | if *value { 1 } else { 0 } << i as u32
-            })
+        self.iter().fold(0, |mask, register| mask | 1 << register)
     }
 }
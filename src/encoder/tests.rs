@@ -1,11 +1,8 @@
-use crate::{
-    encoder::Encode,
-    ir::{Condition, Imm, Rd, Rm, Rn, ShiftedRegister, ShifterOperandCode, SignedImm},
-    parser::{
-        AddressingOffset, AddressingOffsetValue, BranchKind, CalculationKind, DataProcessingKind,
-        InstructionKind, LoadStoreAddressCode, LoadStoreKind, LoadStoreQuantity, OffsetMode,
-        SetFlags, Shift, Sign,
-    },
+use crate::ir::{
+    AddressingOffset, AddressingOffsetValue, BranchKind, ByteOrder, CalculationKind, Condition,
+    DataProcessingKind, Imm, InstructionKind, LoadStoreAddressCode, LoadStoreKind,
+    LoadStoreQuantity, MoveKind, MultipleAddressingMode, OffsetMode, Rd, RegisterList, Rm, Rn,
+    RotatedImm8, SetFlags, Shift, ShiftedRegister, ShifterOperandCode, Sign, SignedImm, WriteBack,
 };
 
 #[test]
@@ -16,7 +13,10 @@ fn test_branch_encode() {
         target: SignedImm::new(-10),
     };
 
-    assert_eq!(instruction.encode(), 0b11101010111111111111111111110110)
+    assert_eq!(
+        instruction.encode().word(),
+        0b11101010111111111111111111110110
+    )
 }
 
 #[test]
@@ -36,7 +36,10 @@ fn test_data_processing_encode() {
         },
     };
 
-    assert_eq!(instruction.encode(), 0b11100000100000010000000000000010)
+    assert_eq!(
+        instruction.encode().word(),
+        0b11100000100000010000000000000010
+    )
 }
 
 #[test]
@@ -56,5 +59,188 @@ fn test_load_store_encode() {
         },
     };
 
-    assert_eq!(instruction.encode(), 0b11100101001100010000000000001010)
+    assert_eq!(
+        instruction.encode().word(),
+        0b11100101001100010000000000001010
+    )
+}
+
+#[test]
+fn test_load_store_multiple_encode() {
+    // STMFD SP!, {R0-R2, LR}
+    let store = InstructionKind::LoadStoreMultiple {
+        condition: Condition::AL,
+        kind: LoadStoreKind::Store,
+        mode: MultipleAddressingMode::DecrementBefore,
+        base: Rn(13),
+        write_back: WriteBack::WriteBack,
+        register_list: RegisterList::from_slice(&[0, 1, 2, 14]),
+    };
+
+    assert_eq!(store.encode().word(), 0xE92D4007);
+
+    // LDMIA R0, {R4-R7}
+    let load = InstructionKind::LoadStoreMultiple {
+        condition: Condition::AL,
+        kind: LoadStoreKind::Load,
+        mode: MultipleAddressingMode::IncrementAfter,
+        base: Rn(0),
+        write_back: WriteBack::NoWriteBack,
+        register_list: RegisterList::from_range(4..=7),
+    };
+
+    assert_eq!(load.encode().word(), 0xE89000F0);
+}
+
+#[test]
+fn test_branch_with_link_and_exchange_encode() {
+    let instruction = InstructionKind::BranchWithLinkAndExchange {
+        target: SignedImm::new(-2),
+        halfword: true,
+    };
+
+    assert_eq!(
+        instruction.encode().word(),
+        0b11111011111111111111111111111110
+    )
+}
+
+#[test]
+fn test_explain_encoding() {
+    // LDR R0, [R1, #-10]!
+    let instruction = InstructionKind::LoadStore {
+        condition: Condition::AL,
+        kind: LoadStoreKind::Load,
+        quantity: LoadStoreQuantity::Word,
+        destination: Rd(0),
+        address: LoadStoreAddressCode {
+            base: Rn(1),
+            offset: AddressingOffset {
+                sign: Sign::Negative,
+                mode: OffsetMode::PreIndexed,
+                value: AddressingOffsetValue::Immediate(Imm::new(10)),
+            },
+        },
+    };
+
+    let fields = instruction.explain_encoding();
+
+    let explained: Vec<(&str, String, &str)> = fields
+        .iter()
+        .map(|field| (field.name, field.bits(), field.meaning.as_str()))
+        .collect();
+
+    assert_eq!(
+        explained,
+        [
+            ("cond", "1110".to_owned(), "AL"),
+            ("01", "01".to_owned(), "load or store"),
+            ("I", "0".to_owned(), "immediate offset"),
+            ("P", "1".to_owned(), "offset or pre-indexed"),
+            ("U", "0".to_owned(), "subtract the offset"),
+            ("B", "0".to_owned(), "word"),
+            ("W", "1".to_owned(), "write back"),
+            ("L", "1".to_owned(), "load"),
+            ("Rn", "0001".to_owned(), "R1"),
+            ("Rd", "0000".to_owned(), "R0"),
+            ("offset_12", "000000001010".to_owned(), "#10"),
+        ]
+    );
+
+    // MOV R0, #255 sets the I bit for its immediate operand
+    let immediate = InstructionKind::DataProcessing {
+        condition: Condition::AL,
+        kind: DataProcessingKind::Move {
+            kind: MoveKind::Move,
+            set_flags: SetFlags::DontSet,
+            destination: Rd(0),
+            shifter: ShifterOperandCode::Immediate(RotatedImm8::new(255, 0)),
+        },
+    };
+
+    let i = immediate
+        .explain_encoding()
+        .into_iter()
+        .find(|field| field.name == "I")
+        .unwrap();
+    assert_eq!((i.value, i.meaning.as_str()), (1, "immediate"));
+
+    // the fields cover every bit of the word, from the top down
+    for instruction in [
+        instruction,
+        immediate,
+        InstructionKind::SuperVisorCall {
+            condition: Condition::NE,
+            immediate: Imm::new(3),
+        },
+        InstructionKind::DataProcessing {
+            condition: Condition::AL,
+            kind: DataProcessingKind::Calculation {
+                kind: CalculationKind::ADD,
+                set_flags: SetFlags::Set,
+                destination: Rd(0),
+                source: Rn(1),
+                shifter: ShifterOperandCode::ImmediateShift(ShiftedRegister {
+                    kind: Shift::LogicalShiftLeft,
+                    amount: Imm::new(2),
+                    base: Rm(2),
+                }),
+            },
+        },
+    ] {
+        let fields = instruction.explain_encoding();
+        let mut next = 31;
+
+        for field in &fields {
+            assert_eq!(field.high, next);
+            next = field.low.wrapping_sub(1);
+        }
+        assert_eq!(fields.last().unwrap().low, 0);
+
+        let word = fields
+            .iter()
+            .fold(0, |word, field| word | field.value << field.low);
+        assert_eq!(word, instruction.encode().word());
+    }
+}
+
+#[test]
+fn test_encoded_instruction() {
+    let instruction = InstructionKind::DataProcessing {
+        condition: Condition::AL,
+        kind: DataProcessingKind::Calculation {
+            kind: CalculationKind::ADD,
+            set_flags: SetFlags::DontSet,
+            destination: Rd(0),
+            source: Rn(1),
+            shifter: ShifterOperandCode::ImmediateShift(ShiftedRegister {
+                base: Rm(2),
+                kind: Shift::LogicalShiftLeft,
+                amount: Imm::new(0),
+            }),
+        },
+    }
+    .encode();
+
+    assert_eq!(instruction.word(), 0xE0810002);
+    assert_eq!(instruction.to_be_bytes(), [0xE0, 0x81, 0x00, 0x02]);
+    assert_eq!(instruction.to_le_bytes(), [0x02, 0x00, 0x81, 0xE0]);
+    assert_eq!(
+        instruction.to_bytes(ByteOrder::LittleEndian),
+        instruction.to_le_bytes()
+    );
+
+    // the condition, opcode and Rn fields
+    assert_eq!(instruction.bits(31, 28), 0b1110);
+    assert_eq!(instruction.bits(24, 21), 0b0100);
+    assert_eq!(instruction.bits(19, 16), 1);
+    assert_eq!(instruction.bits(31, 0), 0xE0810002);
+    assert!(instruction.bit(23));
+    assert!(!instruction.bit(20));
+
+    assert_eq!(instruction.to_string(), "0xE0810002");
+    assert_eq!(
+        format!("{instruction:032b}"),
+        "11100000100000010000000000000010"
+    );
 }
@@ -0,0 +1,63 @@
+use std::fmt::{Binary, Display, UpperHex};
+
+use crate::ir::ByteOrder;
+
+/// the 32-bit word of an encoded instruction, see [`InstructionKind::encode`](crate::ir::InstructionKind::encode)
+///
+/// Its bytes are written in a chosen order with [`EncodedInstruction::to_bytes`], rather than by converting the word, so every caller says which order it stores words in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EncodedInstruction(pub(super) u32);
+
+impl EncodedInstruction {
+    pub fn word(&self) -> u32 {
+        self.0
+    }
+
+    pub fn to_bytes(&self, order: ByteOrder) -> [u8; 4] {
+        order.word_to_bytes(self.0)
+    }
+
+    pub fn to_be_bytes(&self) -> [u8; 4] {
+        self.to_bytes(ByteOrder::BigEndian)
+    }
+
+    pub fn to_le_bytes(&self) -> [u8; 4] {
+        self.to_bytes(ByteOrder::LittleEndian)
+    }
+
+    /// bit `n` of the word
+    pub fn bit(&self, n: u8) -> bool {
+        self.0 >> n & 1 == 1
+    }
+
+    /// bits `low` to `high` of the word, inclusive, shifted down to bit 0, e.g. `bits(31, 28)` is the condition
+    pub fn bits(&self, high: u8, low: u8) -> u32 {
+        (self.0 >> low) & (u32::MAX >> (31 - (high - low)))
+    }
+}
+
+impl From<EncodedInstruction> for u32 {
+    fn from(value: EncodedInstruction) -> u32 {
+        value.0
+    }
+}
+
+impl Display for EncodedInstruction {
+    /// writes the word in hex, like `0xE0810002`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#010X}", self.0)
+    }
+}
+
+impl UpperHex for EncodedInstruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        UpperHex::fmt(&self.0, f)
+    }
+}
+
+impl Binary for EncodedInstruction {
+    /// writes the word in binary, e.g. `{:032b}` for all 32 bits
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Binary::fmt(&self.0, f)
+    }
+}
@@ -0,0 +1,271 @@
+use super::{
+    AddressingOffsetValue, BranchKind, Condition, DataProcessingKind, Imm, InstructionKind,
+    LoadStoreAddressCode, LoadStoreKind, MultiplyLongKind, OffsetMode, RegisterList, SetFlags,
+    ShifterOperandCode, WriteBack,
+};
+
+impl Condition {
+    /// the condition that passes exactly when this one fails, or `None` for [`Condition::AL`] and [`Condition::NV`]
+    pub fn inverse(&self) -> Option<Condition> {
+        match self {
+            Condition::EQ => Some(Condition::NE),
+            Condition::NE => Some(Condition::EQ),
+            Condition::CS => Some(Condition::CC),
+            Condition::CC => Some(Condition::CS),
+            Condition::MI => Some(Condition::PL),
+            Condition::PL => Some(Condition::MI),
+            Condition::VS => Some(Condition::VC),
+            Condition::VC => Some(Condition::VS),
+            Condition::HI => Some(Condition::LS),
+            Condition::LS => Some(Condition::HI),
+            Condition::GE => Some(Condition::LT),
+            Condition::LT => Some(Condition::GE),
+            Condition::GT => Some(Condition::LE),
+            Condition::LE => Some(Condition::GT),
+            Condition::AL | Condition::NV => None,
+        }
+    }
+}
+
+impl MultiplyLongKind {
+    /// whether the operands are two's complement, which is SMULL and SMLAL
+    pub fn signed(&self) -> bool {
+        matches!(self, MultiplyLongKind::SMULL | MultiplyLongKind::SMLAL)
+    }
+
+    /// whether the product is added to the value already in the destination pair, which is UMLAL and SMLAL
+    pub fn accumulates(&self) -> bool {
+        matches!(self, MultiplyLongKind::UMLAL | MultiplyLongKind::SMLAL)
+    }
+}
+
+impl InstructionKind {
+    /// the registers the instruction reads, including the PC for a branch
+    ///
+    /// The registers an SVC uses depend on its handler, so an SVC doesn't read any.
+    pub fn reads(&self) -> RegisterList {
+        let mut registers = RegisterList::default();
+
+        match self {
+            InstructionKind::Branch { .. } | InstructionKind::BranchWithLinkAndExchange { .. } => {
+                registers.insert(15)
+            }
+
+            InstructionKind::BranchAndExchange { target, .. } => registers.insert(target.0),
+
+            InstructionKind::DataProcessing { kind, .. } => match kind {
+                DataProcessingKind::Move { shifter, .. } => shifter_reads(shifter, &mut registers),
+                DataProcessingKind::Comparison {
+                    source, shifter, ..
+                }
+                | DataProcessingKind::Calculation {
+                    source, shifter, ..
+                } => {
+                    registers.insert(source.0);
+                    shifter_reads(shifter, &mut registers);
+                }
+            },
+
+            InstructionKind::Multiply {
+                multiplicand,
+                multiplier,
+                accumulate,
+                ..
+            } => {
+                registers.insert(multiplicand.0);
+                registers.insert(multiplier.0);
+                if let Some(accumulate) = accumulate {
+                    registers.insert(accumulate.0);
+                }
+            }
+
+            InstructionKind::MultiplyLong {
+                kind,
+                destination,
+                multiplicand,
+                multiplier,
+                ..
+            } => {
+                registers.insert(multiplicand.0);
+                registers.insert(multiplier.0);
+                if kind.accumulates() {
+                    registers.insert(destination.low.0);
+                    registers.insert(destination.high.0);
+                }
+            }
+
+            InstructionKind::LoadStore {
+                kind,
+                destination,
+                address,
+                ..
+            } => {
+                if let LoadStoreKind::Store = kind {
+                    registers.insert(destination.0);
+                }
+                address_reads(address, &mut registers);
+            }
+
+            InstructionKind::Preload { address } => address_reads(address, &mut registers),
+
+            InstructionKind::LoadStoreMultiple {
+                kind,
+                base,
+                register_list,
+                ..
+            } => {
+                registers.insert(base.0);
+                if let LoadStoreKind::Store = kind {
+                    register_list
+                        .iter()
+                        .for_each(|register| registers.insert(register));
+                }
+            }
+
+            InstructionKind::SuperVisorCall { .. }
+            | InstructionKind::MoveStatusToRegister { .. } => {}
+
+            InstructionKind::MoveToStatusRegister { source, .. } => {
+                shifter_reads(source, &mut registers)
+            }
+
+            InstructionKind::CountLeadingZeros { source, .. } => registers.insert(source.0),
+        }
+
+        registers
+    }
+
+    /// the registers the instruction writes, including the PC for a branch, and the base register of a load or store that writes it back
+    ///
+    /// A conditional instruction may not write them, and the registers an SVC changes depend on its handler, so an SVC doesn't write any.
+    pub fn writes(&self) -> RegisterList {
+        let mut registers = RegisterList::default();
+
+        match self {
+            InstructionKind::Branch { kind, .. } => {
+                registers.insert(15);
+                if let BranchKind::BranchWithLink = kind {
+                    registers.insert(14);
+                }
+            }
+
+            InstructionKind::BranchWithLinkAndExchange { .. } => {
+                registers.insert(15);
+                registers.insert(14);
+            }
+
+            InstructionKind::BranchAndExchange { .. } => registers.insert(15),
+
+            InstructionKind::DataProcessing { kind, .. } => match kind {
+                DataProcessingKind::Move { destination, .. }
+                | DataProcessingKind::Calculation { destination, .. } => {
+                    registers.insert(destination.0)
+                }
+                DataProcessingKind::Comparison { .. } => {}
+            },
+
+            InstructionKind::Multiply { destination, .. } => registers.insert(destination.0),
+
+            InstructionKind::MultiplyLong { destination, .. } => {
+                registers.insert(destination.low.0);
+                registers.insert(destination.high.0);
+            }
+
+            InstructionKind::LoadStore {
+                kind,
+                destination,
+                address,
+                ..
+            } => {
+                if let LoadStoreKind::Load = kind {
+                    registers.insert(destination.0);
+                }
+                if !matches!(address.offset.mode, OffsetMode::Offset) {
+                    registers.insert(address.base.0);
+                }
+            }
+
+            InstructionKind::MoveStatusToRegister { destination, .. }
+            | InstructionKind::CountLeadingZeros { destination, .. } => {
+                registers.insert(destination.0)
+            }
+
+            InstructionKind::Preload { .. }
+            | InstructionKind::SuperVisorCall { .. }
+            | InstructionKind::MoveToStatusRegister { .. } => {}
+
+            InstructionKind::LoadStoreMultiple {
+                kind,
+                base,
+                write_back,
+                register_list,
+                ..
+            } => {
+                if let LoadStoreKind::Load = kind {
+                    register_list
+                        .iter()
+                        .for_each(|register| registers.insert(register));
+                }
+                if let WriteBack::WriteBack = write_back {
+                    registers.insert(base.0);
+                }
+            }
+        }
+
+        registers
+    }
+
+    /// whether the instruction writes the condition flags, when its condition passes
+    pub fn sets_flags(&self) -> bool {
+        match self {
+            InstructionKind::DataProcessing { kind, .. } => match kind {
+                DataProcessingKind::Move { set_flags, .. }
+                | DataProcessingKind::Calculation { set_flags, .. } => {
+                    matches!(set_flags, SetFlags::Set)
+                }
+                DataProcessingKind::Comparison { .. } => true,
+            },
+            InstructionKind::Multiply { set_flags, .. }
+            | InstructionKind::MultiplyLong { set_flags, .. } => matches!(set_flags, SetFlags::Set),
+            InstructionKind::MoveToStatusRegister { fields, .. } => fields.flags,
+            _ => false,
+        }
+    }
+
+    /// the address a branch at `address` goes to, or `None` if the instruction isn't a branch
+    pub fn branch_target(&self, address: u32) -> Option<u32> {
+        match self {
+            InstructionKind::Branch { target, .. } => {
+                Some(address.wrapping_add((target.get() << 2) as u32))
+            }
+            InstructionKind::BranchWithLinkAndExchange { target, halfword } => {
+                Some(address.wrapping_add((target.get() << 2 | (*halfword as i32) << 1) as u32))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn shifter_reads(shifter: &ShifterOperandCode, registers: &mut RegisterList) {
+    match shifter {
+        ShifterOperandCode::Immediate(_) => {}
+        ShifterOperandCode::ImmediateShift(shift) => registers.insert(shift.base.0),
+        ShifterOperandCode::RegisterShift(shift) => {
+            registers.insert(shift.base.0);
+            registers.insert(shift.amount.0);
+        }
+    }
+}
+
+fn address_reads(
+    LoadStoreAddressCode { base, offset }: &LoadStoreAddressCode<Imm<12>, Imm<5>>,
+    registers: &mut RegisterList,
+) {
+    registers.insert(base.0);
+
+    match &offset.value {
+        AddressingOffsetValue::Immediate(_) => {}
+        AddressingOffsetValue::Register(register) => registers.insert(register.0),
+        AddressingOffsetValue::ScaledRegister(shift) => registers.insert(shift.base.0),
+    }
+}
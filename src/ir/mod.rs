@@ -1,6 +1,9 @@
+use std::{fmt::Display, ops::RangeInclusive};
+
+mod metadata;
 mod tests;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum InstructionKind<
     BranchAddress = SignedImm<24>,
     LoadStoreAddress = LoadStoreAddressCode<Imm<12>, Imm<5>>,
@@ -13,11 +16,41 @@ pub enum InstructionKind<
         target: BranchAddress,
     },
 
+    /// BLX (immediate), an unconditional branch with link that switches to Thumb state
+    BranchWithLinkAndExchange {
+        target: BranchAddress,
+        /// the H bit, which is bit 1 of the target's offset, as Thumb instructions only need to be halfword aligned
+        halfword: bool,
+    },
+
+    /// BX, a branch to the address in a register, which switches to Thumb state if bit 0 of the address is set
+    BranchAndExchange { condition: Condition, target: Rm },
+
     DataProcessing {
         condition: Condition,
         kind: DataProcessingKind<ShifterOperand>,
     },
 
+    /// MUL, or MLA if it has a register to accumulate, which keep the low word of the product
+    Multiply {
+        condition: Condition,
+        set_flags: SetFlags,
+        destination: Rd,
+        multiplicand: Rm,
+        multiplier: Rs,
+        accumulate: Option<Rn>,
+    },
+
+    /// UMULL, UMLAL, SMULL and SMLAL, which write the whole 64-bit product to a pair of registers
+    MultiplyLong {
+        condition: Condition,
+        kind: MultiplyLongKind,
+        set_flags: SetFlags,
+        destination: RegisterPair,
+        multiplicand: Rm,
+        multiplier: Rs,
+    },
+
     LoadStore {
         condition: Condition,
         kind: LoadStoreKind,
@@ -25,6 +58,9 @@ pub enum InstructionKind<
         destination: Rd,
         address: LoadStoreAddress,
     },
+    /// PLD, a hint that the memory at an address will be loaded soon, which is an unconditional instruction
+    Preload { address: LoadStoreAddress },
+
     LoadStoreMultiple {
         condition: Condition,
         kind: LoadStoreKind,
@@ -38,9 +74,54 @@ pub enum InstructionKind<
         condition: Condition,
         immediate: SuperVisorCallNumber,
     },
+
+    /// MRS, which copies the CPSR to a register
+    MoveStatusToRegister {
+        condition: Condition,
+        destination: Rd,
+    },
+
+    /// MSR, which writes the fields of the CPSR from an immediate or a register, which can't be shifted
+    MoveToStatusRegister {
+        condition: Condition,
+        fields: StatusFields,
+        source: ShifterOperand,
+    },
+
+    /// CLZ, which writes the number of zero bits above the highest set bit of a register, or 32 if it is 0
+    CountLeadingZeros {
+        condition: Condition,
+        destination: Rd,
+        source: Rm,
+    },
 }
 
-#[derive(Debug, PartialEq)]
+impl<BranchAddress, LoadStoreAddress, ShifterOperand, SuperVisorCallNumber>
+    InstructionKind<BranchAddress, LoadStoreAddress, ShifterOperand, SuperVisorCallNumber>
+{
+    /// the condition the instruction is executed under, which is [`Condition::AL`] for unconditional instructions
+    pub fn condition(&self) -> Condition {
+        match self {
+            InstructionKind::BranchWithLinkAndExchange { .. } | InstructionKind::Preload { .. } => {
+                Condition::AL
+            }
+
+            InstructionKind::Branch { condition, .. }
+            | InstructionKind::BranchAndExchange { condition, .. }
+            | InstructionKind::DataProcessing { condition, .. }
+            | InstructionKind::Multiply { condition, .. }
+            | InstructionKind::MultiplyLong { condition, .. }
+            | InstructionKind::LoadStore { condition, .. }
+            | InstructionKind::LoadStoreMultiple { condition, .. }
+            | InstructionKind::SuperVisorCall { condition, .. }
+            | InstructionKind::MoveStatusToRegister { condition, .. }
+            | InstructionKind::MoveToStatusRegister { condition, .. }
+            | InstructionKind::CountLeadingZeros { condition, .. } => *condition,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum DataProcessingKind<ShifterOperand = ShifterOperandCode> {
     Move {
         kind: MoveKind,
@@ -62,61 +143,69 @@ pub enum DataProcessingKind<ShifterOperand = ShifterOperandCode> {
     },
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ComparisonKind {
     CMP,
-    // CMN,
-    // TST,
-    // TEQ,
+    CMN,
+    TST,
+    TEQ,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum CalculationKind {
     ADD,
     SUB,
-    // RSB,
-    // ADC,
-    // SBC,
-    // RSC,
+    RSB,
+    ADC,
+    SBC,
+    RSC,
     // AND,
     // BIC,
     // EOR,
     // ORR,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum MultiplyLongKind {
+    UMULL,
+    UMLAL,
+    SMULL,
+    SMLAL,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum LoadStoreKind {
     Load,
     Store,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LoadStoreAddressCode<Immediate, ShiftImmediate> {
     pub base: Rn,
     pub offset: AddressingOffset<Immediate, ShiftImmediate>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Sign {
     Positive,
     Negative,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct AddressingOffset<Immediate, ShiftImmediate> {
     pub sign: Sign,
     pub value: AddressingOffsetValue<Immediate, ShiftImmediate>,
     pub mode: OffsetMode,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum AddressingOffsetValue<Immediate, ShiftImmediate> {
     Immediate(Immediate),
     Register(Rm),
     ScaledRegister(ShiftedRegister<ShiftImmediate>),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum OffsetMode {
     Offset,
     PreIndexed,
@@ -132,6 +221,29 @@ pub enum Shift {
     RotateRight = 0b11,
 }
 
+impl Shift {
+    /// the largest amount an immediate can shift by, per the ARM ARM
+    ///
+    /// LSR and ASR can shift by 32, which is encoded as 0, but a ROR by 32 is the same as by 0.
+    pub fn max_amount(&self) -> u32 {
+        match self {
+            Shift::LogicalShiftLeft | Shift::RotateRight => 31,
+            Shift::LogicalShiftRight | Shift::ArithmeticShiftRight => 32,
+        }
+    }
+
+    /// the shift and 5-bit amount that encode a shift by `amount`, or `None` if it is more than [`Shift::max_amount`]
+    ///
+    /// A shift by 0 is encoded as `LSL #0`, as an amount of 0 means 32 for LSR and ASR, and RRX for ROR.
+    pub fn encode_amount(self, amount: u32) -> Option<(Shift, Imm<5>)> {
+        match amount {
+            0 => Some((Shift::LogicalShiftLeft, Imm(0))),
+            amount if amount <= self.max_amount() => Some((self, Imm(amount % 32))),
+            _ => None,
+        }
+    }
+}
+
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Condition {
@@ -179,6 +291,11 @@ pub enum Condition {
 
     /// always
     AL = 0b1110,
+
+    /// never, on ARMv4 and earlier
+    ///
+    /// From ARMv5, the encoding is used by the unconditional instructions instead (see [`crate::decoder::EncodingSpace`])
+    NV = 0b1111,
 }
 
 pub trait RegisterIdentifier {
@@ -221,14 +338,23 @@ impl RegisterIdentifier for Rs {
     }
 }
 
-#[derive(Debug, PartialEq)]
+/// the two registers that hold a 64-bit value, e.g. `R0, R1` for the result of `UMULL R0, R1, R2, R3`
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct RegisterPair {
+    /// the register that holds bits `0` to `31`, in the Rd field
+    pub low: Rd,
+    /// the register that holds bits `32` to `63`, in the Rn field
+    pub high: Rn,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum ShifterOperandCode<Immediate = RotatedImm8, ShiftImm = Imm<5>> {
     Immediate(Immediate),
     ImmediateShift(ShiftedRegister<ShiftImm>),
     RegisterShift(ShiftedRegister<Rs>),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ShiftedRegister<Amount, Base = Rm> {
     pub kind: Shift,
     pub amount: Amount,
@@ -243,22 +369,181 @@ pub enum MultipleAddressingMode {
     IncrementBefore,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct RegisterList {
     pub registers: [bool; 16],
 }
 
-#[derive(Debug, PartialEq)]
+impl RegisterList {
+    /// a list of the registers from `range.start()` to `range.end()`, like `{R0-R3}`
+    pub fn from_range(range: RangeInclusive<u8>) -> Self {
+        let mut list = RegisterList::default();
+
+        for register in range {
+            list.insert(register);
+        }
+
+        list
+    }
+
+    /// a list of the given registers, like `{R0, R2, LR}`
+    pub fn from_slice(registers: &[u8]) -> Self {
+        let mut list = RegisterList::default();
+
+        for register in registers {
+            list.insert(*register);
+        }
+
+        list
+    }
+
+    /// adds a register to the list
+    pub fn insert(&mut self, register: u8) {
+        self.registers[register as usize] = true;
+    }
+
+    pub fn contains(&self, register: u8) -> bool {
+        self.registers[register as usize]
+    }
+
+    /// the registers in the list, from lowest to highest, which is the order they are loaded or stored in
+    pub fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..16).filter(|register| self.contains(*register))
+    }
+
+    /// the number of registers in the list
+    pub fn count(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count() == 0
+    }
+}
+
+/// the name a register is written with, which is `SP`, `LR` or `PC` for R13 to R15
+pub fn register_name(register: u8) -> String {
+    match register {
+        13 => "SP".to_owned(),
+        14 => "LR".to_owned(),
+        15 => "PC".to_owned(),
+        n => format!("R{n}"),
+    }
+}
+
+/// the register a name like `R1`, `SP`, `LR` or `PC` refers to, in any case
+pub fn register_number(name: &str) -> Option<u8> {
+    match name.to_ascii_uppercase().as_str() {
+        "SP" => Some(13),
+        "LR" => Some(14),
+        "PC" => Some(15),
+        name => match name.strip_prefix('R')?.as_bytes() {
+            [digit @ b'0'..=b'9'] => Some(digit - b'0'),
+            [b'1', digit @ b'0'..=b'5'] => Some(10 + digit - b'0'),
+            _ => None,
+        },
+    }
+}
+
+impl Display for RegisterList {
+    /// writes the list like `{R0-R3, R5, LR}`, collapsing runs of three or more registers into ranges
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.format(register_name))
+    }
+}
+
+impl RegisterList {
+    /// writes the list like its [`Display`] implementation, but with registers named by `name`
+    pub fn format(&self, name: impl Fn(u8) -> String) -> String {
+        let mut items = Vec::new();
+        let mut registers = self.iter().peekable();
+
+        while let Some(first) = registers.next() {
+            let mut last = first;
+
+            // SP, LR and PC are always written by name, so aren't part of ranges
+            while last < 12 && registers.next_if_eq(&(last + 1)).is_some() {
+                last += 1;
+            }
+
+            match last - first {
+                0 => items.push(name(first)),
+                1 => items.extend([name(first), name(last)]),
+                _ => items.push(format!("{}-{}", name(first), name(last))),
+            }
+        }
+
+        format!("{{{}}}", items.join(", "))
+    }
+}
+
+/// the bytes of the CPSR an MSR writes, written after it like `CPSR_fc`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatusFields {
+    /// bits 24 to 31, with the condition flags
+    pub flags: bool,
+    /// bits 16 to 23
+    pub status: bool,
+    /// bits 8 to 15
+    pub extension: bool,
+    /// bits 0 to 7, with the interrupt masks, the T bit and the mode
+    pub control: bool,
+}
+
+impl StatusFields {
+    /// the fields written by `MSR CPSR, <operand>`, which are the flags and control fields
+    pub const DEFAULT: StatusFields = StatusFields {
+        flags: true,
+        status: false,
+        extension: false,
+        control: true,
+    };
+
+    /// the bits of the flags field
+    pub const FLAGS: u32 = 0xFF00_0000;
+
+    /// the bits of the PSR in the fields
+    pub fn mask(&self) -> u32 {
+        [self.control, self.extension, self.status, self.flags]
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| **field)
+            .fold(0, |mask, (byte, _)| mask | 0xFF << (8 * byte))
+    }
+}
+
+impl Display for StatusFields {
+    /// writes the letters of the fields, like `fc`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (field, letter) in [
+            (self.flags, 'f'),
+            (self.status, 's'),
+            (self.extension, 'x'),
+            (self.control, 'c'),
+        ] {
+            if field {
+                write!(f, "{letter}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Imm<const N: u32>(u32);
 
 impl<const N: u32> TryFrom<u32> for Imm<N> {
     type Error = UnencodableValueError<u32>;
 
     fn try_from(value: u32) -> Result<Imm<N>, Self::Error> {
-        if value < (2 ^ N) {
+        if u64::from(value) < 1 << N {
             Ok(Imm(value))
         } else {
-            Err(UnencodableValueError { value })
+            Err(UnencodableValueError {
+                value,
+                encoding: ImmediateEncoding::Unsigned { bits: N },
+            })
         }
     }
 }
@@ -272,7 +557,7 @@ impl<const N: u32> Imm<N> {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct RotatedImm8 {
     /// 4 bits in instruction
     rotate: u8,
@@ -283,6 +568,69 @@ pub struct RotatedImm8 {
 #[derive(Debug)]
 pub struct UnencodableValueError<N> {
     pub value: N,
+    /// the encoding the value didn't fit
+    pub encoding: ImmediateEncoding,
+}
+
+/// the ways an immediate value can be encoded in an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImmediateEncoding {
+    /// an 8-bit value rotated right by an even amount, see [`RotatedImm8`]
+    Rotated,
+    Unsigned {
+        bits: u32,
+    },
+    /// a two's complement value, e.g. the word offset of a branch
+    Signed {
+        bits: u32,
+    },
+}
+
+impl ImmediateEncoding {
+    /// the nearest encodable values below and above `value`, leaving out the ones that don't exist
+    pub fn nearest(&self, value: i64) -> Vec<i64> {
+        match *self {
+            ImmediateEncoding::Rotated => {
+                let Ok(value) = u32::try_from(value) else {
+                    return Vec::new();
+                };
+
+                // there are only 4096 encodings, so they can all be checked
+                let encodable = (0..16).flat_map(|rotate| {
+                    (0..=u8::MAX).map(move |byte| RotatedImm8::new(byte, rotate).get())
+                });
+
+                let below = encodable.clone().filter(|n| *n < value).max();
+                let above = encodable.filter(|n| *n > value).min();
+
+                below.into_iter().chain(above).map(i64::from).collect()
+            }
+
+            ImmediateEncoding::Unsigned { bits } => {
+                let max = (1 << bits) - 1;
+
+                if value > max {
+                    vec![max]
+                } else if value < 0 {
+                    vec![0]
+                } else {
+                    Vec::new()
+                }
+            }
+
+            ImmediateEncoding::Signed { bits } => {
+                let range = -(1 << (bits - 1))..1 << (bits - 1);
+
+                if value < range.start {
+                    vec![range.start]
+                } else if value >= range.end {
+                    vec![range.end - 1]
+                } else {
+                    Vec::new()
+                }
+            }
+        }
+    }
 }
 
 impl TryFrom<u32> for RotatedImm8 {
@@ -296,7 +644,10 @@ impl TryFrom<u32> for RotatedImm8 {
         if nearest_below.get() == value {
             Ok(nearest_below)
         } else {
-            Err(UnencodableValueError { value })
+            Err(UnencodableValueError {
+                value,
+                encoding: ImmediateEncoding::Rotated,
+            })
         }
     }
 }
@@ -360,6 +711,35 @@ impl RotatedImm8 {
         }
     }
 
+    /// the fewest encodable values whose bits make up `n`, which don't overlap, so they can be added or ORed together
+    pub fn split(n: u32) -> Vec<RotatedImm8> {
+        if n == 0 {
+            return vec![RotatedImm8::new(0, 0)];
+        }
+
+        // a value can wrap around from bit 31 to bit 0, so splitting starts from each even rotation of n
+        (0..16)
+            .map(|rotate| {
+                let mut rest = n.rotate_right(rotate * 2);
+                let mut parts: Vec<RotatedImm8> = Vec::new();
+
+                while rest != 0 {
+                    // the lowest 8-bit window that starts at an even bit
+                    let start = rest.trailing_zeros() & !1;
+
+                    parts.push(RotatedImm8::new(
+                        (rest >> start) as u8,
+                        ((64 - start - rotate * 2) % 32 / 2) as u8,
+                    ));
+                    rest &= !(0xFF << start);
+                }
+
+                parts
+            })
+            .min_by_key(Vec::len)
+            .unwrap()
+    }
+
     /// calculates the next encodable value
     pub fn next(&self) -> RotatedImm8 {
         let value = self.value.wrapping_add(1);
@@ -394,17 +774,20 @@ impl RotatedImm8 {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct SignedImm<const N: u32>(i32);
 
 impl<const N: u32> TryFrom<i32> for SignedImm<N> {
     type Error = UnencodableValueError<i32>;
 
     fn try_from(value: i32) -> Result<Self, Self::Error> {
-        if (-(2 ^ (N as i32 - 1))..(2 ^ (N as i32 - 1))).contains(&value) {
+        if (-(1i64 << (N - 1))..1 << (N - 1)).contains(&i64::from(value)) {
             Ok(SignedImm(value))
         } else {
-            Err(UnencodableValueError { value })
+            Err(UnencodableValueError {
+                value,
+                encoding: ImmediateEncoding::Signed { bits: N },
+            })
         }
     }
 }
@@ -419,32 +802,64 @@ impl<const N: u32> SignedImm<N> {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum BranchKind {
     Branch,
     BranchWithLink,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum MoveKind {
     Move,
     MoveNot,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum SetFlags {
     Set,
     DontSet,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum WriteBack {
     WriteBack,
     NoWriteBack,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum LoadStoreQuantity {
     Byte,
     Word,
 }
+
+/// the order of the bytes of a word in memory
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ByteOrder {
+    /// the most significant byte first, which is how the assembler and emulator store words
+    #[default]
+    BigEndian,
+    LittleEndian,
+}
+
+impl ByteOrder {
+    pub fn word_to_bytes(&self, word: u32) -> [u8; 4] {
+        match self {
+            ByteOrder::BigEndian => word.to_be_bytes(),
+            ByteOrder::LittleEndian => word.to_le_bytes(),
+        }
+    }
+
+    pub fn word_from_bytes(&self, bytes: [u8; 4]) -> u32 {
+        match self {
+            ByteOrder::BigEndian => u32::from_be_bytes(bytes),
+            ByteOrder::LittleEndian => u32::from_le_bytes(bytes),
+        }
+    }
+
+    pub fn halfword_from_bytes(&self, bytes: [u8; 2]) -> u16 {
+        match self {
+            ByteOrder::BigEndian => u16::from_be_bytes(bytes),
+            ByteOrder::LittleEndian => u16::from_le_bytes(bytes),
+        }
+    }
+}
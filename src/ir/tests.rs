@@ -1,4 +1,4 @@
-use crate::ir::RotatedImm8;
+use crate::ir::{RegisterList, RotatedImm8};
 
 #[test]
 fn test_rotated_imm_encoding() {
@@ -28,3 +28,23 @@ fn test_rotated_imm_nearest_with_negative_remainder() {
     assert_eq!(imm.get(), 0b10000000000000000000000000000000);
     assert_eq!(remainder, -1);
 }
+
+#[test]
+fn test_register_list() {
+    let list = RegisterList::from_slice(&[0, 1, 2, 3, 5, 7, 8, 13, 14, 15]);
+
+    assert_eq!(list.count(), 10);
+    assert_eq!(
+        list.iter().collect::<Vec<_>>(),
+        [0, 1, 2, 3, 5, 7, 8, 13, 14, 15]
+    );
+    assert_eq!(list.to_string(), "{R0-R3, R5, R7, R8, SP, LR, PC}");
+
+    assert_eq!(RegisterList::from_range(4..=12).to_string(), "{R4-R12}");
+    assert_eq!(
+        RegisterList::from_range(10..=15).to_string(),
+        "{R10-R12, SP, LR, PC}"
+    );
+    assert!(RegisterList::default().is_empty());
+    assert_eq!(RegisterList::default().to_string(), "{}");
+}
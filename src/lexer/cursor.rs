@@ -1,24 +1,46 @@
 use std::{ops::Range, str::Chars};
 
+use crate::span::Span;
+
 pub struct Cursor<'a> {
     chars: Chars<'a>,
+    /// the byte range of the current token in the input
     current_token: Range<usize>,
+    /// the byte offset of the start of the input in its file
+    offset: usize,
+    /// the line and column of the start of the current token
+    token_start: (usize, usize),
+    /// the line and column of the next character
+    position: (usize, usize),
 }
 
 impl<'a> Cursor<'a> {
-    pub fn new(input: &'a str) -> Cursor<'a> {
+    /// a cursor for input that begins at byte `offset` and the start of line `line` of its file
+    pub fn with_offset(input: &'a str, offset: usize, line: usize) -> Cursor<'a> {
         Cursor {
             chars: input.chars(),
             current_token: 0..0,
+            offset,
+            token_start: (line, 0),
+            position: (line, 0),
         }
     }
 
-    pub fn finish_token(&mut self) -> Range<usize> {
+    /// ends the current token, returning its byte range in the input and its span in the file
+    pub fn finish_token(&mut self) -> (Range<usize>, Span) {
         let range = self.current_token.clone();
+        let (line, column) = self.token_start;
+
+        let span = Span::new(
+            (self.offset + range.start)..(self.offset + range.end),
+            line,
+            column,
+        );
 
         self.current_token = range.end..range.end;
+        self.token_start = self.position;
 
-        range
+        (range, span)
     }
 
     pub fn peek(&mut self) -> Option<char> {
@@ -41,8 +63,16 @@ impl<'a> Iterator for Cursor<'a> {
     type Item = char;
 
     fn next(&mut self) -> Option<char> {
-        self.current_token.end += 1;
+        let char = self.chars.next()?;
+
+        self.current_token.end += char.len_utf8();
+
+        // columns are counted in characters, not bytes
+        self.position = match char {
+            '\n' => (self.position.0 + 1, 0),
+            _ => (self.position.0, self.position.1 + 1),
+        };
 
-        self.chars.next()
+        Some(char)
     }
 }
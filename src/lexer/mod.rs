@@ -1,8 +1,13 @@
-use std::{iter::Peekable, ops::Range};
+use std::collections::VecDeque;
 
 use cursor::Cursor;
 
+use crate::span::Span;
+
+pub use trivia::{TriviaToken, TriviaTokens};
+
 mod cursor;
+mod trivia;
 
 #[cfg(test)]
 mod tests;
@@ -10,19 +15,19 @@ mod tests;
 #[derive(Debug, PartialEq, Clone)]
 pub struct Token<'a> {
     pub kind: TokenKind,
-    pub span: &'a str,
-    pub range: Range<usize>,
+    pub text: &'a str,
+    pub span: Span,
 }
 
 impl<'a> Token<'a> {
     pub fn separate_suffix(&self, n: usize) -> (&'a str, &'a str) {
-        let boundary = self.span.len() - n;
+        let boundary = self.text.len() - n;
 
         if boundary <= 0 {
-            return ("", self.span);
+            return ("", self.text);
         }
 
-        (&self.span[..boundary], &self.span[boundary..])
+        (&self.text[..boundary], &self.text[boundary..])
     }
 }
 
@@ -93,6 +98,8 @@ pub enum TokenKind {
     Plus,
     /// A `-` symbol, used as a minus sign in addressing offsets for load store instructions, or as a hyphen in register lists
     HyphenMinus,
+    /// A `.` symbol, the address of the word being defined in `DEFW label - .`
+    Dot,
 
     /// A Commma, used to delimit instruction arguments
     Comma,
@@ -104,6 +111,16 @@ pub enum TokenKind {
     Whitespace,
 
     Unknown,
+
+    /// The empty end of the input, which is only produced by [`TriviaTokens`] to hold the trivia at the end of the input
+    EndOfInput,
+}
+
+impl TokenKind {
+    /// whether tokens of this kind don't affect the meaning of the source, so can be attached to the tokens around them
+    pub fn is_trivia(&self) -> bool {
+        matches!(self, TokenKind::Whitespace | TokenKind::Comment)
+    }
 }
 
 pub struct Tokens<'a> {
@@ -154,41 +171,135 @@ impl<'a> Iterator for Tokens<'a> {
             '=' => TokenKind::EqualSign,
             '+' => TokenKind::Plus,
             '-' => TokenKind::HyphenMinus,
+            '.' => TokenKind::Dot,
             ',' => TokenKind::Comma,
 
             _ => TokenKind::Unknown,
         };
 
-        let range = self.cursor.finish_token();
-        let span = &self.input[range.clone()];
+        let (range, span) = self.cursor.finish_token();
 
-        Some(Token { kind, span, range })
+        Some(Token {
+            kind,
+            text: &self.input[range],
+            span,
+        })
     }
 }
 
 pub struct Lexer<'a> {
     pub input: &'a str,
-    tokens: Peekable<Tokens<'a>>,
+    /// the byte offset of the start of the input in its file
+    offset: usize,
+    /// the byte offset of the end of the last token consumed, ignoring whitespace and newlines
+    last_end: usize,
+    /// the tokens consumed since recording started, ignoring whitespace and newlines
+    recording: Option<Vec<Token<'a>>>,
+    tokens: Tokens<'a>,
+    /// tokens that have been peeked at but not consumed yet
+    lookahead: VecDeque<Token<'a>>,
 }
 
 impl<'a> Iterator for Lexer<'a> {
     type Item = Token<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.tokens.next()
+        let token = match self.lookahead.pop_front() {
+            Some(token) => token,
+            None => self.tokens.next()?,
+        };
+
+        match token.kind {
+            TokenKind::Whitespace | TokenKind::NewLine => (),
+            _ => {
+                self.last_end = token.span.byte_range.end;
+
+                if let Some(recording) = &mut self.recording {
+                    recording.push(token.clone());
+                }
+            }
+        }
+
+        Some(token)
     }
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Lexer<'a> {
+        Lexer::with_offset(input, 0, 0)
+    }
+
+    /// a lexer for input that begins at byte `offset` and line `line` of its file, so that spans point into the whole file
+    pub fn with_offset(input: &'a str, offset: usize, line: usize) -> Lexer<'a> {
         Lexer {
             input,
-            tokens: Tokens::new(input).peekable(),
+            offset,
+            last_end: offset,
+            recording: None,
+            tokens: Tokens::with_offset(input, offset, line),
+            lookahead: VecDeque::new(),
         }
     }
 
+    /// the byte offset of the end of the last token consumed, ignoring whitespace and newlines
+    pub fn last_end(&self) -> usize {
+        self.last_end
+    }
+
+    /// starts recording the tokens consumed, so that a parser can look back over them
+    pub fn record(&mut self) {
+        self.recording = Some(Vec::new());
+    }
+
+    /// stops recording, returning the tokens consumed since [`Lexer::record`] was called
+    pub fn stop_recording(&mut self) -> Vec<Token<'a>> {
+        self.recording.take().unwrap_or_default()
+    }
+
+    /// the byte offset and text of the line containing the byte at `offset` in the file, excluding its newline character
+    pub fn source_line(&self, offset: usize) -> (usize, &'a str) {
+        let offset = (offset - self.offset).min(self.input.len());
+
+        let start = self.input[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let end = self.input[offset..]
+            .find('\n')
+            .map_or(self.input.len(), |i| offset + i);
+
+        (self.offset + start, &self.input[start..end])
+    }
+
     pub fn peek(&mut self) -> Option<&Token<'a>> {
-        self.tokens.peek()
+        self.peek_raw(0)
+    }
+
+    /// peeks at the `n`th next token that isn't whitespace, counting from 0, without consuming any tokens
+    ///
+    /// `peek_nth(0)` is the token [`Lexer::next_ignore_whitespace`] would return
+    pub fn peek_nth(&mut self, n: usize) -> Option<&Token<'a>> {
+        let mut remaining = n;
+        let mut index = 0;
+
+        loop {
+            match self.peek_raw(index)?.kind {
+                TokenKind::Whitespace => (),
+                _ if remaining == 0 => break,
+                _ => remaining -= 1,
+            }
+
+            index += 1;
+        }
+
+        self.lookahead.get(index)
+    }
+
+    /// peeks at the `index`th next token, including whitespace, buffering the tokens up to it
+    fn peek_raw(&mut self, index: usize) -> Option<&Token<'a>> {
+        while self.lookahead.len() <= index {
+            let token = self.tokens.next()?;
+            self.lookahead.push_back(token);
+        }
+
+        self.lookahead.get(index)
     }
 
     pub fn next_ignore_whitespace(&mut self) -> Option<Token<'a>> {
@@ -222,9 +333,14 @@ impl<'a> Lexer<'a> {
 
 impl<'a> Tokens<'a> {
     pub fn new(input: &'a str) -> Tokens<'a> {
+        Tokens::with_offset(input, 0, 0)
+    }
+
+    /// tokens for input that begins at byte `offset` and line `line` of its file
+    pub fn with_offset(input: &'a str, offset: usize, line: usize) -> Tokens<'a> {
         Tokens {
             input,
-            cursor: Cursor::new(input),
+            cursor: Cursor::with_offset(input, offset, line),
         }
     }
 
@@ -235,7 +351,8 @@ impl<'a> Tokens<'a> {
     }
 
     fn whitespace(&mut self) -> TokenKind {
-        self.cursor.eat_while(char::is_whitespace);
+        // newlines are tokens of their own, as they end statements
+        self.cursor.eat_while(|c| c != '\n' && c.is_whitespace());
 
         TokenKind::Whitespace
     }
@@ -257,15 +374,16 @@ impl<'a> Tokens<'a> {
             }
         }
 
-        self.cursor.eat_while(char::is_numeric);
+        self.cursor.eat_while(|c| c.is_ascii_digit());
 
         match self.cursor.peek() {
             Some('_') => {
                 // eat the _
                 self.cursor.next();
 
-                // eat the number
-                self.cursor.eat_while(char::is_numeric);
+                // eat the number, whose digits can be letters for bases above 10
+                // the parser checks the digits are valid for the base
+                self.cursor.eat_while(|c| c.is_ascii_alphanumeric());
 
                 TokenKind::NBaseNumber
             }
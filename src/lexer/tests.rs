@@ -1,4 +1,7 @@
-use crate::lexer::{Token, TokenKind::*, Tokens};
+use crate::{
+    lexer::{Lexer, Token, TokenKind::*, Tokens, TriviaToken},
+    span::Span,
+};
 
 #[test]
 fn test() {
@@ -11,135 +14,240 @@ fn test() {
     let expected = vec![
         Token {
             kind: Whitespace,
-            span: "   ",
-            range: 0..3,
+            text: "   ",
+            span: Span::new(0..3, 0, 0),
         },
         Token {
             kind: Identifier,
-            span: "B",
-            range: 3..4,
+            text: "B",
+            span: Span::new(3..4, 0, 3),
         },
         Token {
             kind: Whitespace,
-            span: " ",
-            range: 4..5,
+            text: " ",
+            span: Span::new(4..5, 0, 4),
         },
         Token {
             kind: Identifier,
-            span: "loop",
-            range: 5..9,
+            text: "loop",
+            span: Span::new(5..9, 0, 5),
         },
         Token {
             kind: Whitespace,
-            span: " ",
-            range: 9..10,
+            text: " ",
+            span: Span::new(9..10, 0, 9),
         },
         Token {
             kind: Comment,
-            span: "; infinite loop!",
-            range: 10..26,
+            text: "; infinite loop!",
+            span: Span::new(10..26, 0, 10),
         },
         Token {
             kind: NewLine,
-            span: "\n",
-            range: 26..27,
+            text: "\n",
+            span: Span::new(26..27, 0, 26),
         },
         Token {
             kind: Whitespace,
-            span: "    ",
-            range: 27..31,
+            text: "    ",
+            span: Span::new(27..31, 1, 0),
         },
         Token {
             kind: Identifier,
-            span: "SVC",
-            range: 31..34,
+            text: "SVC",
+            span: Span::new(31..34, 1, 4),
         },
         Token {
             kind: Whitespace,
-            span: " ",
-            range: 34..35,
+            text: " ",
+            span: Span::new(34..35, 1, 7),
         },
         Token {
             kind: Decimal,
-            span: "2",
-            range: 35..36,
+            text: "2",
+            span: Span::new(35..36, 1, 8),
         },
         Token {
             kind: NewLine,
-            span: "\n",
-            range: 36..37,
+            text: "\n",
+            span: Span::new(36..37, 1, 9),
         },
         Token {
             kind: Whitespace,
-            span: "    ",
-            range: 37..41,
+            text: "    ",
+            span: Span::new(37..41, 2, 0),
         },
         Token {
             kind: Identifier,
-            span: "LDR",
-            range: 41..44,
+            text: "LDR",
+            span: Span::new(41..44, 2, 4),
         },
         Token {
             kind: Whitespace,
-            span: " ",
-            range: 44..45,
+            text: " ",
+            span: Span::new(44..45, 2, 7),
         },
         Token {
             kind: Identifier,
-            span: "R12",
-            range: 45..48,
+            text: "R12",
+            span: Span::new(45..48, 2, 8),
         },
         Token {
             kind: Comma,
-            span: ",",
-            range: 48..49,
+            text: ",",
+            span: Span::new(48..49, 2, 11),
         },
         Token {
             kind: Whitespace,
-            span: " ",
-            range: 49..50,
+            text: " ",
+            span: Span::new(49..50, 2, 12),
         },
         Token {
             kind: OpenBracket,
-            span: "[",
-            range: 50..51,
+            text: "[",
+            span: Span::new(50..51, 2, 13),
         },
         Token {
             kind: Identifier,
-            span: "R13",
-            range: 51..54,
+            text: "R13",
+            span: Span::new(51..54, 2, 14),
         },
         Token {
             kind: Comma,
-            span: ",",
-            range: 54..55,
+            text: ",",
+            span: Span::new(54..55, 2, 17),
         },
         Token {
             kind: Whitespace,
-            span: " ",
-            range: 55..56,
+            text: " ",
+            span: Span::new(55..56, 2, 18),
         },
         Token {
             kind: LiteralSign,
-            span: "#",
-            range: 56..57,
+            text: "#",
+            span: Span::new(56..57, 2, 19),
         },
         Token {
             kind: HyphenMinus,
-            span: "-",
-            range: 57..58,
+            text: "-",
+            span: Span::new(57..58, 2, 20),
         },
         Token {
             kind: Decimal,
-            span: "4",
-            range: 58..59,
+            text: "4",
+            span: Span::new(58..59, 2, 21),
         },
         Token {
             kind: CloseBracket,
-            span: "]",
-            range: 59..60,
+            text: "]",
+            span: Span::new(59..60, 2, 22),
         },
     ];
 
     assert_eq!(tokens, expected)
 }
+
+#[test]
+fn test_n_base_number() {
+    let tokens: Vec<Token> = Tokens::new("16_FF 36_zz").collect();
+
+    assert_eq!(
+        tokens,
+        vec![
+            Token {
+                kind: NBaseNumber,
+                text: "16_FF",
+                span: Span::new(0..5, 0, 0),
+            },
+            Token {
+                kind: Whitespace,
+                text: " ",
+                span: Span::new(5..6, 0, 5),
+            },
+            Token {
+                kind: NBaseNumber,
+                text: "36_zz",
+                span: Span::new(6..11, 0, 6),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_whitespace_before_newline() {
+    let tokens: Vec<Token> = Tokens::new("B x  \n\tB y").collect();
+
+    assert_eq!(
+        tokens[3..6],
+        [
+            Token {
+                kind: Whitespace,
+                text: "  ",
+                span: Span::new(3..5, 0, 3),
+            },
+            Token {
+                kind: NewLine,
+                text: "\n",
+                span: Span::new(5..6, 0, 5),
+            },
+            Token {
+                kind: Whitespace,
+                text: "\t",
+                span: Span::new(6..7, 1, 0),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_peek_nth() {
+    let mut lexer = Lexer::new("size  EQU 4");
+
+    assert_eq!(lexer.peek_nth(1).map(|token| token.text), Some("EQU"));
+    assert_eq!(lexer.peek_nth(2).map(|token| token.text), Some("4"));
+    assert_eq!(lexer.peek_nth(3), None);
+
+    // peeking doesn't consume any tokens
+    assert_eq!(lexer.next().map(|token| token.text), Some("size"));
+    assert_eq!(lexer.peek_nth(0).map(|token| token.text), Some("EQU"));
+    assert_eq!(lexer.next().map(|token| token.kind), Some(Whitespace));
+}
+
+#[test]
+fn test_trivia() {
+    let input = "  loop B loop ; forever\n\n\t; the end\n  ";
+
+    let tokens: Vec<TriviaToken> = Tokens::new(input).with_trivia().collect();
+
+    // the source can be reproduced exactly
+    assert_eq!(
+        tokens
+            .iter()
+            .map(ToString::to_string)
+            .collect::<std::string::String>(),
+        input
+    );
+
+    fn text<'a>(trivia: &[Token<'a>]) -> Vec<&'a str> {
+        trivia.iter().map(|token| token.text).collect()
+    }
+
+    // indentation leads the first token of a line
+    assert_eq!(tokens[0].token.text, "loop");
+    assert_eq!(text(&tokens[0].leading), ["  "]);
+
+    // comments trail the token before them
+    assert_eq!(tokens[2].token.text, "loop");
+    assert_eq!(text(&tokens[2].trailing), [" ", "; forever"]);
+    assert_eq!(tokens[3].token.kind, NewLine);
+
+    // a comment on its own line leads the newline ending it
+    assert_eq!(tokens[5].token.kind, NewLine);
+    assert_eq!(text(&tokens[5].leading), ["\t", "; the end"]);
+
+    // trivia at the end of the input is held by an empty token
+    let end = tokens.last().unwrap();
+    assert_eq!(end.token.kind, EndOfInput);
+    assert_eq!(end.token.span, Span::new(38..38, 3, 2));
+    assert_eq!(text(&end.leading), ["  "]);
+}
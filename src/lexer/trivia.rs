@@ -0,0 +1,114 @@
+use std::fmt::Display;
+
+use super::{Token, TokenKind, Tokens};
+
+/// a token along with the whitespace and comments around it, so that tools like formatters can reproduce the source exactly
+#[derive(Debug, PartialEq, Clone)]
+pub struct TriviaToken<'a> {
+    /// the whitespace before the token, which is the indentation if the token starts a line
+    pub leading: Vec<Token<'a>>,
+    pub token: Token<'a>,
+    /// the whitespace and comments after the token, up to the next token
+    pub trailing: Vec<Token<'a>>,
+}
+
+impl<'a> Display for TriviaToken<'a> {
+    /// writes the exact source text of the token and its trivia
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for token in self
+            .leading
+            .iter()
+            .chain([&self.token])
+            .chain(&self.trailing)
+        {
+            f.write_str(token.text)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// a lexing mode that attaches whitespace and comments to the tokens around them
+///
+/// Trivia after a newline (or at the start of the input) leads the next token, and any other trivia trails the token before it.
+/// Newlines aren't trivia, as they end statements. The last token is always an empty [`TokenKind::EndOfInput`], which holds any trivia at the end of the input.
+pub struct TriviaTokens<'a> {
+    tokens: Tokens<'a>,
+    /// the next token, if it has already been lexed
+    peeked: Option<Token<'a>>,
+    finished: bool,
+}
+
+impl<'a> Tokens<'a> {
+    /// switches to lexing tokens with their trivia attached
+    pub fn with_trivia(self) -> TriviaTokens<'a> {
+        TriviaTokens {
+            tokens: self,
+            peeked: None,
+            finished: false,
+        }
+    }
+}
+
+impl<'a> TriviaTokens<'a> {
+    fn next_token(&mut self) -> Option<Token<'a>> {
+        self.peeked.take().or_else(|| self.tokens.next())
+    }
+
+    fn peek_token(&mut self) -> Option<&Token<'a>> {
+        if self.peeked.is_none() {
+            self.peeked = self.tokens.next();
+        }
+
+        self.peeked.as_ref()
+    }
+
+    fn end_of_input(&mut self) -> Token<'a> {
+        let (range, span) = self.tokens.cursor.finish_token();
+
+        Token {
+            kind: TokenKind::EndOfInput,
+            text: &self.tokens.input[range],
+            span,
+        }
+    }
+}
+
+impl<'a> Iterator for TriviaTokens<'a> {
+    type Item = TriviaToken<'a>;
+
+    fn next(&mut self) -> Option<TriviaToken<'a>> {
+        if self.finished {
+            return None;
+        }
+
+        let mut leading = Vec::new();
+
+        let token = loop {
+            match self.next_token() {
+                Some(token) if token.kind.is_trivia() => leading.push(token),
+                Some(token) => break token,
+                None => {
+                    self.finished = true;
+
+                    break self.end_of_input();
+                }
+            }
+        };
+
+        let mut trailing = Vec::new();
+
+        // the trivia after a newline leads the first token of the next line
+        if token.kind != TokenKind::NewLine {
+            while self.peek_token().is_some_and(|next| next.kind.is_trivia()) {
+                trailing.extend(self.next_token());
+            }
+        }
+
+        Some(TriviaToken {
+            leading,
+            token,
+            trailing,
+        })
+    }
+}
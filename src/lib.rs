@@ -1,12 +1,22 @@
 #![feature(test)]
-#![feature(bigint_helper_methods)]
 
-mod assembler;
-mod decoder;
-mod emulator;
+#[cfg(feature = "assembler")]
+pub mod assembler;
+#[cfg(any(feature = "emulator", feature = "disassembler"))]
+pub mod decoder;
+#[cfg(feature = "disassembler")]
+pub mod disassembler;
+#[cfg(feature = "emulator")]
+pub mod emulator;
+#[cfg(feature = "assembler")]
 mod encoder;
-mod ir;
-mod lexer;
-mod parser;
-mod preprocessor;
-mod resolver;
+pub mod ir;
+#[cfg(feature = "assembler")]
+pub mod lexer;
+#[cfg(feature = "assembler")]
+pub mod parser;
+#[cfg(feature = "assembler")]
+pub mod preprocessor;
+#[cfg(feature = "assembler")]
+pub mod resolver;
+pub mod span;
@@ -0,0 +1,51 @@
+use std::{env, process::ExitCode};
+
+use eremius::parser::mnemonic_help;
+
+const USAGE: &str = "usage: eremius help <MNEMONIC>";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["help", mnemonic] => help(mnemonic),
+        _ => {
+            eprintln!("{USAGE}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// prints the reference for a mnemonic
+fn help(mnemonic: &str) -> ExitCode {
+    let Some(help) = mnemonic_help(mnemonic) else {
+        eprintln!("unknown mnemonic `{mnemonic}`");
+        return ExitCode::FAILURE;
+    };
+
+    println!("{} - {}", help.mnemonic, help.name);
+    println!();
+    println!("{}", help.description);
+    println!();
+    println!("Syntax: {}", help.syntax);
+    println!();
+
+    let width = help
+        .operands
+        .iter()
+        .map(|(operand, _)| operand.len())
+        .max()
+        .unwrap_or(0);
+
+    for (operand, description) in help.operands {
+        println!("  {operand:width$}  {description}");
+    }
+
+    if !help.operands.is_empty() {
+        println!();
+    }
+
+    println!("Flags: {}", help.flags);
+
+    ExitCode::SUCCESS
+}
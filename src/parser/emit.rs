@@ -0,0 +1,550 @@
+use std::fmt::{Display, Formatter, Result};
+
+use crate::ir::{
+    register_name, AddressingOffset, AddressingOffsetValue, BranchKind, CalculationKind,
+    ComparisonKind, Condition, DataProcessingKind, InstructionKind, LoadStoreAddressCode,
+    LoadStoreKind, LoadStoreQuantity, MoveKind, MultipleAddressingMode, MultiplyLongKind,
+    OffsetMode, RegisterIdentifier, SetFlags, Shift, ShiftedRegister, Sign, WriteBack,
+};
+
+use super::{
+    BytesDefinition, ChecksumKind, DefinitionKind, DiadicOperator, DirectiveKind, Expression,
+    ExpressionKind, Line, LoadStoreAddress, PseudoInstructionKind, Register,
+    ShifterOperandExpression, ShifterOperandShiftAmount, Statement, StatementInstructionKind,
+    StatementKind, WordDefinition,
+};
+
+/// writes lines back into assembly source, with a line of source for each, so a program can be parsed, transformed and written out again
+///
+/// The source parses back into the same statements, but comments and the original spacing aren't kept.
+pub fn emit_source<'a>(lines: impl IntoIterator<Item = &'a Line>) -> String {
+    lines.into_iter().map(|line| format!("{line}\n")).collect()
+}
+
+impl Display for Line {
+    /// writes the line like `label\tstatement`, with a tab before the statement even if there is no label
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        if let Some(label) = &self.label {
+            write!(f, "{label}")?;
+        }
+
+        match &self.statement {
+            Some(statement) => write!(f, "\t{statement}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Display for Statement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl Display for StatementKind {
+    /// writes the statement as source, e.g. `ADDEQS R0, R1, #4`
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            StatementKind::Instruction { kind } => write!(f, "{kind}"),
+            StatementKind::PseudoInstruction { kind } => write!(f, "{kind}"),
+            StatementKind::Directive { kind } => write!(f, "{kind}"),
+        }
+    }
+}
+
+impl Display for StatementInstructionKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            InstructionKind::Branch {
+                condition,
+                kind,
+                target,
+            } => {
+                let link = match kind {
+                    BranchKind::Branch => "",
+                    BranchKind::BranchWithLink => "L",
+                };
+
+                write!(f, "B{link}{} {}", condition_suffix(condition), target.0)
+            }
+
+            InstructionKind::BranchWithLinkAndExchange { target, .. } => {
+                write!(f, "BLX {}", target.0)
+            }
+
+            InstructionKind::BranchAndExchange { condition, target } => {
+                write!(f, "BX{} {}", condition_suffix(condition), register(target))
+            }
+
+            InstructionKind::DataProcessing { condition, kind } => match kind {
+                DataProcessingKind::Move {
+                    kind,
+                    set_flags,
+                    destination,
+                    shifter,
+                } => {
+                    let mnemonic = match kind {
+                        MoveKind::Move => "MOV",
+                        MoveKind::MoveNot => "MVN",
+                    };
+
+                    write!(
+                        f,
+                        "{mnemonic}{}{} {}, {shifter}",
+                        condition_suffix(condition),
+                        set_flags_suffix(set_flags),
+                        register(destination),
+                    )
+                }
+
+                DataProcessingKind::Comparison {
+                    kind,
+                    source,
+                    shifter,
+                } => {
+                    let mnemonic = match kind {
+                        ComparisonKind::CMP => "CMP",
+                        ComparisonKind::CMN => "CMN",
+                        ComparisonKind::TST => "TST",
+                        ComparisonKind::TEQ => "TEQ",
+                    };
+
+                    write!(
+                        f,
+                        "{mnemonic}{} {}, {shifter}",
+                        condition_suffix(condition),
+                        register(source)
+                    )
+                }
+
+                DataProcessingKind::Calculation {
+                    kind,
+                    set_flags,
+                    destination,
+                    source,
+                    shifter,
+                } => {
+                    let mnemonic = match kind {
+                        CalculationKind::ADD => "ADD",
+                        CalculationKind::SUB => "SUB",
+                        CalculationKind::RSB => "RSB",
+                        CalculationKind::ADC => "ADC",
+                        CalculationKind::SBC => "SBC",
+                        CalculationKind::RSC => "RSC",
+                    };
+
+                    write!(
+                        f,
+                        "{mnemonic}{}{} {}, {}, {shifter}",
+                        condition_suffix(condition),
+                        set_flags_suffix(set_flags),
+                        register(destination),
+                        register(source)
+                    )
+                }
+            },
+
+            InstructionKind::Multiply {
+                condition,
+                set_flags,
+                destination,
+                multiplicand,
+                multiplier,
+                accumulate,
+            } => {
+                let mnemonic = match accumulate {
+                    Some(_) => "MLA",
+                    None => "MUL",
+                };
+
+                write!(
+                    f,
+                    "{mnemonic}{}{} {}, {}, {}",
+                    condition_suffix(condition),
+                    set_flags_suffix(set_flags),
+                    register(destination),
+                    register(multiplicand),
+                    register(multiplier)
+                )?;
+
+                match accumulate {
+                    Some(accumulate) => write!(f, ", {}", register(accumulate)),
+                    None => Ok(()),
+                }
+            }
+
+            InstructionKind::MultiplyLong {
+                condition,
+                kind,
+                set_flags,
+                destination,
+                multiplicand,
+                multiplier,
+            } => {
+                let mnemonic = match kind {
+                    MultiplyLongKind::UMULL => "UMULL",
+                    MultiplyLongKind::UMLAL => "UMLAL",
+                    MultiplyLongKind::SMULL => "SMULL",
+                    MultiplyLongKind::SMLAL => "SMLAL",
+                };
+
+                write!(
+                    f,
+                    "{mnemonic}{}{} {}, {}, {}, {}",
+                    condition_suffix(condition),
+                    set_flags_suffix(set_flags),
+                    register(&destination.low),
+                    register(&destination.high),
+                    register(multiplicand),
+                    register(multiplier)
+                )
+            }
+
+            InstructionKind::LoadStore {
+                condition,
+                kind,
+                quantity,
+                destination,
+                address,
+            } => {
+                let mnemonic = match kind {
+                    LoadStoreKind::Load => "LDR",
+                    LoadStoreKind::Store => "STR",
+                };
+
+                let byte = match quantity {
+                    LoadStoreQuantity::Word => "",
+                    LoadStoreQuantity::Byte => "B",
+                };
+
+                write!(
+                    f,
+                    "{mnemonic}{byte}{} {}, {address}",
+                    condition_suffix(condition),
+                    register(destination)
+                )
+            }
+
+            InstructionKind::Preload { address } => write!(f, "PLD {address}"),
+
+            InstructionKind::LoadStoreMultiple {
+                condition,
+                kind,
+                mode,
+                base,
+                write_back,
+                register_list,
+            } => {
+                let mnemonic = match kind {
+                    LoadStoreKind::Load => "LDM",
+                    LoadStoreKind::Store => "STM",
+                };
+
+                let mode = match mode {
+                    MultipleAddressingMode::IncrementAfter => "IA",
+                    MultipleAddressingMode::IncrementBefore => "IB",
+                    MultipleAddressingMode::DecrementAfter => "DA",
+                    MultipleAddressingMode::DecrementBefore => "DB",
+                };
+
+                let write_back = match write_back {
+                    WriteBack::WriteBack => "!",
+                    WriteBack::NoWriteBack => "",
+                };
+
+                write!(
+                    f,
+                    "{mnemonic}{}{mode} {}{write_back}, {register_list}",
+                    condition_suffix(condition),
+                    register(base)
+                )
+            }
+
+            InstructionKind::SuperVisorCall {
+                condition,
+                immediate,
+            } => write!(f, "SVC{} {immediate}", condition_suffix(condition)),
+
+            InstructionKind::MoveStatusToRegister {
+                condition,
+                destination,
+            } => write!(
+                f,
+                "MRS{} {}, CPSR",
+                condition_suffix(condition),
+                register(destination)
+            ),
+
+            InstructionKind::MoveToStatusRegister {
+                condition,
+                fields,
+                source,
+            } => write!(
+                f,
+                "MSR{} CPSR_{fields}, {source}",
+                condition_suffix(condition)
+            ),
+
+            InstructionKind::CountLeadingZeros {
+                condition,
+                destination,
+                source,
+            } => write!(
+                f,
+                "CLZ{} {}, {}",
+                condition_suffix(condition),
+                register(destination),
+                register(source)
+            ),
+        }
+    }
+}
+
+impl Display for PseudoInstructionKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            PseudoInstructionKind::LoadRegisterConstant {
+                condition,
+                destination,
+                value,
+            } => write!(
+                f,
+                "LDR{} {}, ={value}",
+                condition_suffix(condition),
+                register(destination)
+            ),
+
+            PseudoInstructionKind::AddressRegister {
+                condition,
+                long,
+                destination,
+                label,
+            } => write!(
+                f,
+                "ADR{}{} {}, {}",
+                if *long { "L" } else { "" },
+                condition_suffix(condition),
+                destination,
+                label.0
+            ),
+
+            PseudoInstructionKind::MoveConstant {
+                condition,
+                destination,
+                value,
+            } => write!(
+                f,
+                "MOV32{} {}, #{value}",
+                condition_suffix(condition),
+                register(destination)
+            ),
+        }
+    }
+}
+
+impl Display for DirectiveKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            DirectiveKind::Definition { kind } => match kind {
+                DefinitionKind::Space { size, fill: None } => write!(f, "DEFS {size}"),
+                DefinitionKind::Space {
+                    size,
+                    fill: Some(fill),
+                } => write!(f, "DEFS {size}, {fill}"),
+
+                DefinitionKind::Bytes { bytes } => {
+                    let bytes: Vec<String> = bytes
+                        .iter()
+                        .map(|definition| match definition {
+                            BytesDefinition::Byte(byte) => byte.to_string(),
+                            BytesDefinition::String(string) => format!("\"{string}\""),
+                        })
+                        .collect();
+
+                    write!(f, "DEFB {}", bytes.join(", "))
+                }
+
+                DefinitionKind::Words { words } => {
+                    let words: Vec<String> = words
+                        .iter()
+                        .map(|definition| match definition {
+                            WordDefinition::Word(word) => word.to_string(),
+                            WordDefinition::Address(symbol) => symbol.0.clone(),
+                            WordDefinition::Offset(symbol) => format!("{} - .", symbol.0),
+                            WordDefinition::Expression(expression) => expression.to_string(),
+                        })
+                        .collect();
+
+                    write!(f, "DEFW {}", words.join(", "))
+                }
+            },
+            DirectiveKind::Align => write!(f, "ALIGN"),
+            DirectiveKind::Origin { address } => write!(f, "ORIGIN {address}"),
+            DirectiveKind::EntryPoint => write!(f, "ENTRY"),
+            DirectiveKind::Constant { value } => write!(f, "EQU {value}"),
+            DirectiveKind::Section { name } => write!(f, "SECTION {name}"),
+            DirectiveKind::LiteralPool => write!(f, "LTORG"),
+            DirectiveKind::Checksum {
+                kind: ChecksumKind::Sum,
+                start,
+                end,
+            } => write!(f, "CHECKSUM {}, {}", start.0, end.0),
+            DirectiveKind::Checksum {
+                kind: ChecksumKind::Crc32,
+                start,
+                end,
+            } => write!(f, "CRC32 {}, {}", start.0, end.0),
+        }
+    }
+}
+
+impl Display for Expression {
+    /// writes the expression with numbers in the base they were written in, e.g. `table + 0x10`
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match &self.kind {
+            ExpressionKind::Number { base: 10, n } => write!(f, "{n}"),
+            ExpressionKind::Number { base: 16, n } => write!(f, "{n:#X}"),
+            ExpressionKind::Number { base, n } => write!(f, "{base}_{}", in_base(*n, *base)),
+            ExpressionKind::Character(char) => write!(f, "'{char}'"),
+            ExpressionKind::String(string) => write!(f, "\"{string}\""),
+            ExpressionKind::Boolean(true) => write!(f, "{{TRUE}}"),
+            ExpressionKind::Boolean(false) => write!(f, "{{FALSE}}"),
+            ExpressionKind::Symbol(symbol) => write!(f, "{}", symbol.0),
+            ExpressionKind::Diadic(lhs, operator, rhs) => {
+                let operator = match operator {
+                    DiadicOperator::Plus => "+",
+                    DiadicOperator::Minus => "-",
+                };
+
+                write!(f, "{lhs} {operator} {rhs}")
+            }
+        }
+    }
+}
+
+impl Display for Register {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", register_name(self.0))
+    }
+}
+
+impl Display for ShifterOperandExpression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            ShifterOperandExpression::Immediate(expression) => write!(f, "#{expression}"),
+            ShifterOperandExpression::Register(register) => write!(f, "{register}"),
+            ShifterOperandExpression::ShiftedRegister(ShiftedRegister { kind, amount, base }) => {
+                write!(f, "{base}, {} ", shift_name(kind))?;
+
+                match amount {
+                    ShifterOperandShiftAmount::Immediate(amount) => write!(f, "#{amount}"),
+                    ShifterOperandShiftAmount::Register(amount) => write!(f, "{amount}"),
+                }
+            }
+            ShifterOperandExpression::RotateRightWithExtend(register) => {
+                write!(f, "{register}, RRX")
+            }
+        }
+    }
+}
+
+impl Display for LoadStoreAddress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let LoadStoreAddressCode { base, offset } = match self {
+            LoadStoreAddress::Expression(expression) => return write!(f, "{expression}"),
+            LoadStoreAddress::AddressingMode(code) => code,
+        };
+
+        let AddressingOffset { sign, value, mode } = offset;
+        let base = register(base);
+
+        let sign = match sign {
+            Sign::Positive => "",
+            Sign::Negative => "-",
+        };
+
+        let value = match value {
+            // a zero offset is left out, e.g. `[R0]`
+            AddressingOffsetValue::Immediate(Expression {
+                kind: ExpressionKind::Number { n: 0, .. },
+                ..
+            }) if matches!(mode, OffsetMode::Offset) && sign.is_empty() => {
+                return write!(f, "[{base}]")
+            }
+            AddressingOffsetValue::Immediate(expression) => format!("#{sign}{expression}"),
+            AddressingOffsetValue::Register(offset) => format!("{sign}{}", register(offset)),
+            AddressingOffsetValue::ScaledRegister(ShiftedRegister {
+                kind,
+                amount,
+                base: offset,
+            }) => format!("{sign}{}, {} #{amount}", register(offset), shift_name(kind)),
+        };
+
+        match mode {
+            OffsetMode::Offset => write!(f, "[{base}, {value}]"),
+            OffsetMode::PreIndexed => write!(f, "[{base}, {value}]!"),
+            OffsetMode::PostIndexed => write!(f, "[{base}], {value}"),
+        }
+    }
+}
+
+fn register(register: &impl RegisterIdentifier) -> String {
+    register_name(register.number())
+}
+
+fn set_flags_suffix(set_flags: &SetFlags) -> &'static str {
+    match set_flags {
+        SetFlags::Set => "S",
+        SetFlags::DontSet => "",
+    }
+}
+
+fn condition_suffix(condition: &Condition) -> &'static str {
+    match condition {
+        Condition::EQ => "EQ",
+        Condition::NE => "NE",
+        Condition::CS => "CS",
+        Condition::CC => "CC",
+        Condition::MI => "MI",
+        Condition::PL => "PL",
+        Condition::VS => "VS",
+        Condition::VC => "VC",
+        Condition::HI => "HI",
+        Condition::LS => "LS",
+        Condition::GE => "GE",
+        Condition::LT => "LT",
+        Condition::GT => "GT",
+        Condition::LE => "LE",
+        Condition::AL => "",
+        Condition::NV => "NV",
+    }
+}
+
+fn shift_name(shift: &Shift) -> &'static str {
+    match shift {
+        Shift::LogicalShiftLeft => "LSL",
+        Shift::LogicalShiftRight => "LSR",
+        Shift::ArithmeticShiftRight => "ASR",
+        Shift::RotateRight => "ROR",
+    }
+}
+
+/// the digits of `n` in `base`, which is between 2 and 36
+fn in_base(mut n: u32, base: u32) -> String {
+    let mut digits = Vec::new();
+
+    loop {
+        digits.push(
+            std::char::from_digit(n % base, base)
+                .unwrap()
+                .to_ascii_uppercase(),
+        );
+        n /= base;
+
+        if n == 0 {
+            break;
+        }
+    }
+
+    digits.iter().rev().collect()
+}
@@ -4,7 +4,7 @@ use unicase::UniCase;
 
 use crate::ir::{Condition, MultipleAddressingMode};
 
-use super::{statements::Register, Shift};
+use super::Shift;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Mnemonic {
@@ -13,6 +13,10 @@ pub enum Mnemonic {
         l: bool,
         condition: Condition,
     },
+    BLX,
+    BX {
+        condition: Condition,
+    },
 
     // Data Processing Instructions
     ADD {
@@ -23,13 +27,68 @@ pub enum Mnemonic {
         condition: Condition,
         s: bool,
     },
+    RSB {
+        condition: Condition,
+        s: bool,
+    },
+    ADC {
+        condition: Condition,
+        s: bool,
+    },
+    SBC {
+        condition: Condition,
+        s: bool,
+    },
+    RSC {
+        condition: Condition,
+        s: bool,
+    },
     CMP {
         condition: Condition,
     },
+    CMN {
+        condition: Condition,
+    },
+    TST {
+        condition: Condition,
+    },
+    TEQ {
+        condition: Condition,
+    },
     MOV {
         condition: Condition,
         s: bool,
     },
+    MVN {
+        condition: Condition,
+        s: bool,
+    },
+
+    // Multiply Instructions
+    MUL {
+        condition: Condition,
+        s: bool,
+    },
+    MLA {
+        condition: Condition,
+        s: bool,
+    },
+    UMULL {
+        condition: Condition,
+        s: bool,
+    },
+    UMLAL {
+        condition: Condition,
+        s: bool,
+    },
+    SMULL {
+        condition: Condition,
+        s: bool,
+    },
+    SMLAL {
+        condition: Condition,
+        s: bool,
+    },
 
     // Data Transfer Instructions
     LDR {
@@ -38,6 +97,7 @@ pub enum Mnemonic {
     STR {
         condition: Condition,
     },
+    PLD,
     LDRB {
         condition: Condition,
     },
@@ -58,11 +118,27 @@ pub enum Mnemonic {
         condition: Condition,
     },
 
+    // Status Register Access
+    MRS {
+        condition: Condition,
+    },
+    MSR {
+        condition: Condition,
+    },
+
+    // Miscellaneous Arithmetic
+    CLZ {
+        condition: Condition,
+    },
+
     // Pseudo Instructions
     ADR {
         condition: Condition,
         l: bool,
     },
+    MOV32 {
+        condition: Condition,
+    },
 
     // Assembler Directives
     DEFW,
@@ -72,37 +148,43 @@ pub enum Mnemonic {
     ORIGIN,
     ENTRY,
     EQU,
+    SECTION,
+    LTORG,
+    CHECKSUM,
+    CRC32,
 }
 
-// mnemonic and condition perfect hash map
+/// reference information about a mnemonic, for editor hovers and `eremius help`
+#[derive(Debug, PartialEq)]
+pub struct MnemonicHelp {
+    /// the mnemonic without any flags, e.g. `ADD`
+    pub mnemonic: &'static str,
+    /// the full name of the mnemonic, e.g. `Add`
+    pub name: &'static str,
+    pub description: &'static str,
+    /// a template of the syntax, e.g. `ADD{<cond>}{S} <Rd>, <Rn>, <shifter_operand>`
+    pub syntax: &'static str,
+    /// a description of each of the flags and operands in the syntax template
+    pub operands: &'static [(&'static str, &'static str)],
+    /// how the instruction affects the condition flags
+    pub flags: &'static str,
+}
+
+// mnemonic and condition perfect hash map, and the help for each mnemonic
 // generated in build.rs
 include!(concat!(env!("OUT_DIR"), "/codegen.rs"));
 
-// register lookup table
-pub static REGISTERS: phf::Map<UniCase<&'static str>, Register> = phf_map! {
-    UniCase::ascii("R0") => Register(0),
-    UniCase::ascii("R1") => Register(1),
-    UniCase::ascii("R2") => Register(2),
-    UniCase::ascii("R3") => Register(3),
-    UniCase::ascii("R4") => Register(4),
-    UniCase::ascii("R5") => Register(5),
-    UniCase::ascii("R6") => Register(6),
-    UniCase::ascii("R7") => Register(7),
-    UniCase::ascii("R8") => Register(8),
-    UniCase::ascii("R9") => Register(9),
-    UniCase::ascii("R10") => Register(10),
-    UniCase::ascii("R11") => Register(11),
-    UniCase::ascii("R12") => Register(12),
-    // stack pointer
-    UniCase::ascii("SP") => Register(13),
-    UniCase::ascii("R13") => Register(13),
-    // link register
-    UniCase::ascii("LR") => Register(14),
-    UniCase::ascii("R14") => Register(14),
-    // program counter
-    UniCase::ascii("PC") => Register(15),
-    UniCase::ascii("R15") => Register(15),
-};
+/// looks up the help for a mnemonic, which can be written with any flags, e.g. `LDM`, `LDMFD` or `ldmeqfd`
+pub fn mnemonic_help(mnemonic: &str) -> Option<&'static MnemonicHelp> {
+    match MNEMONIC_HELP.get(&UniCase::new(mnemonic)) {
+        Some(help) => Some(help),
+        None => {
+            let name = MNEMONICS.get(&UniCase::new(mnemonic))?.name();
+
+            MNEMONIC_HELP.get(&UniCase::new(name))
+        }
+    }
+}
 
 /// included RRX
 #[derive(Debug, Clone, Copy, PartialEq)]
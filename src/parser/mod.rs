@@ -1,35 +1,65 @@
-use std::{fmt::Display, ops::Range};
+use std::{fmt::Display, num::IntErrorKind};
 
-use crate::{ir::ShiftedRegister, lexer::Tokens};
+use crate::{
+    ir::{register_number, Rn, ShiftedRegister},
+    span::Span,
+};
 pub use crate::{
     ir::{
         AddressingOffset, AddressingOffsetValue, BranchKind, CalculationKind, ComparisonKind,
         DataProcessingKind, InstructionKind, LoadStoreAddressCode, LoadStoreKind,
-        LoadStoreQuantity, MoveKind, OffsetMode, RegisterList, SetFlags, Shift, Sign, WriteBack,
+        LoadStoreQuantity, MoveKind, MultiplyLongKind, OffsetMode, RegisterList, RegisterPair,
+        SetFlags, Shift, Sign, StatusFields, WriteBack,
     },
     lexer::{Lexer, Token, TokenKind},
     parser::{
-        keywords::{Mnemonic, ShiftName, MNEMONICS, REGISTERS, SHIFT_KINDS, SHIFT_NAMES},
+        emit::emit_source,
+        keywords::{
+            mnemonic_help, Mnemonic, MnemonicHelp, ShiftName, MNEMONICS, SHIFT_KINDS, SHIFT_NAMES,
+        },
+        outline::{Outline, OutlineItem, OutlineKind},
         statements::*,
     },
 };
 use unicase::UniCase;
 
+mod emit;
 mod keywords;
+mod outline;
 mod statements;
 
 #[cfg(test)]
 mod tests;
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct Line {
     pub label: Option<String>,
     pub statement: Option<Statement>,
+    /// the span of the line's label, statement and comment, excluding surrounding whitespace
+    pub span: Span,
+    /// problems with the line that don't stop it being assembled
+    pub warnings: Vec<ParseWarning>,
+}
+
+impl Line {
+    /// moves the line's spans, such as when text is inserted or removed before it
+    pub fn shift(&mut self, bytes: isize, lines: isize) {
+        self.span.shift(bytes, lines);
+
+        if let Some(statement) = &mut self.statement {
+            statement.shift(bytes, lines);
+        }
+
+        for warning in &mut self.warnings {
+            warning.span.shift(bytes, lines);
+        }
+    }
 }
 
 pub struct Parser<'a> {
     lexer: Lexer<'a>,
-    line_count: usize,
+    /// the warnings for the line being parsed
+    warnings: Vec<ParseWarning>,
 }
 
 #[derive(Debug)]
@@ -40,32 +70,65 @@ pub struct LineError<'a> {
 
 #[derive(Debug)]
 pub struct ParseError {
-    line_number: usize,
-    line: String,
-    /// The range of the token (or lack of token) causing the error.
+    /// The span of the token (or lack of token) causing the error.
     /// Note: not nexessarily contained within the line - there could be a missing character at the end of a line.
-    bad_token_range: Range<usize>,
+    pub span: Span,
+    line: String,
+    message: &'static str,
+    /// whether the token causing the error isn't a token of the language, e.g. a stray `$`
+    lexical: bool,
+}
+
+impl ParseError {
+    pub fn message(&self) -> &'static str {
+        self.message
+    }
+
+    /// whether the error was caused by text the lexer didn't recognise, rather than by valid tokens in the wrong place
+    pub fn is_lexical(&self) -> bool {
+        self.lexical
+    }
+}
+
+/// a suspicious but valid construct in a line, such as a register list range written backwards
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseWarning {
+    pub span: Span,
     message: &'static str,
 }
 
+impl ParseWarning {
+    pub fn message(&self) -> &'static str {
+        self.message
+    }
+}
+
 impl Display for ParseError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // the byte range of the bad token within the line, which is at least one character long so the arrow is visible
+        let start = self
+            .line
+            .char_indices()
+            .nth(self.span.column)
+            .map_or(self.line.len(), |(i, _)| i);
+        let bad_token_range = start..(start + self.span.len().max(1));
+
         let arrow_line: String = self
             .line
-            .chars()
-            .enumerate()
+            .char_indices()
             .map(|(i, c)| match c {
                 '\t' => '\t',
-                _ if self.bad_token_range.contains(&i) => '^',
+                _ if bad_token_range.contains(&i) => '^',
                 _ => ' ',
             })
+            .chain((start >= self.line.len()).then_some('^'))
             .collect();
 
         write!(
             f,
             "\nError at line {}, token \"{}\": {}\n{}\n{}\n",
-            self.line_number,
-            &self.line[self.bad_token_range.clone()],
+            self.span.line + 1,
+            self.line.get(bad_token_range).unwrap_or_default(),
             self.message,
             self.line,
             arrow_line
@@ -77,43 +140,27 @@ impl<'a> Iterator for Parser<'a> {
     type Item = Result<Line, ParseError>;
 
     fn next(&mut self) -> Option<Result<Line, ParseError>> {
-        let line_start_token = self.lexer.peek()?;
-
-        let line_start = line_start_token.range.start;
-
         let token = self.lexer.next_ignore_whitespace()?;
 
-        self.line_count += 1;
-
-        match self.line(&token) {
-            Ok(line) => Some(Ok(line)),
-
-            // display error helper
-            Err(LineError { token, message }) => {
-                let line = match self.lexer.input[line_start..].split_once('\n') {
-                    Some((line, _)) => line,
-                    None => &self.lexer.input[line_start..],
-                };
-
-                let token_start = match &token {
-                    Some(token) => token.range.start - line_start,
-                    None => line.len(),
-                };
-
-                let token_length = match token {
-                    Some(token) => token.range.len(),
-                    None => 1,
-                };
-
-                let bad_token_range = token_start..(token_start + token_length);
-
-                Some(Err(ParseError {
-                    line_number: self.line_count,
-                    line: line.to_owned(),
-                    bad_token_range,
-                    message,
+        let line = self.line(&token);
+        let warnings = std::mem::take(&mut self.warnings);
+
+        match line {
+            Ok(line) => {
+                let end = self.lexer.last_end().max(token.span.byte_range.start);
+
+                Some(Ok(Line {
+                    span: Span::new(
+                        token.span.byte_range.start..end,
+                        token.span.line,
+                        token.span.column,
+                    ),
+                    warnings,
+                    ..line
                 }))
             }
+
+            Err(error) => Some(Err(self.parse_error(&token.span, error))),
         }
     }
 }
@@ -122,39 +169,121 @@ impl<'a> Lexer<'a> {
     pub fn parse(self) -> Parser<'a> {
         Parser {
             lexer: self,
-            line_count: 0,
+            warnings: Vec::new(),
         }
     }
 }
 
 impl<'a> Parser<'a> {
     pub fn new(input: &'a str) -> Parser<'a> {
-        Parser {
-            lexer: Lexer::new(input),
-            line_count: 0,
+        Lexer::new(input).parse()
+    }
+
+    /// parses a single line of source (excluding its newline character)
+    ///
+    /// `offset` and `line_index` are the zero-based byte offset and index of the line in its file, so that spans point into the whole file
+    pub fn parse_line(
+        input: &'a str,
+        offset: usize,
+        line_index: usize,
+    ) -> Result<Line, ParseError> {
+        let mut parser = Lexer::with_offset(input, offset, line_index).parse();
+
+        parser.next().unwrap_or(Ok(Line {
+            span: Span::empty(offset, line_index, 0),
+            ..Line::default()
+        }))
+    }
+
+    /// parses a single statement, without a label, such as an instruction typed into a debugger or REPL
+    pub fn parse_statement(input: &'a str) -> Result<Statement, ParseError> {
+        let mut parser = Parser::new(input);
+
+        match parser.lexer.next_ignore_whitespace() {
+            Some(token) => parser
+                .mnemonic(&token)
+                .and_then(|mnemonic| parser.statement(&token, mnemonic))
+                .and_then(|statement| parser.input_end().map(|_| statement))
+                .map_err(|error| parser.parse_error(&token.span, error)),
+
+            None => Err(parser.parse_error(
+                &Span::default(),
+                LineError {
+                    token: None,
+                    message: "Expected a statement",
+                },
+            )),
+        }
+    }
+
+    /// parses a single expression, such as a watch expression typed into a debugger
+    pub fn parse_expression(input: &'a str) -> Result<Expression, ParseError> {
+        let mut parser = Parser::new(input);
+
+        let start = parser.next_span();
+
+        parser
+            .expression()
+            .and_then(|expression| parser.input_end().map(|_| expression))
+            .map_err(|error| parser.parse_error(&start, error))
+    }
+
+    /// converts an error into one that can be displayed with the line it occurred on
+    ///
+    /// `start` is the span of the first token of the line or statement, which is used when the error is a missing token
+    fn parse_error(&self, start: &Span, LineError { token, message }: LineError<'a>) -> ParseError {
+        let lexical = token
+            .as_ref()
+            .is_some_and(|token| token.kind == TokenKind::Unknown);
+
+        let span = match token {
+            Some(token) => token.span,
+
+            // point just past the end of the line the statement started on
+            None => {
+                let (line_start, line) = self.lexer.source_line(start.byte_range.start);
+
+                Span::empty(line_start + line.len(), start.line, line.chars().count())
+            }
+        };
+
+        ParseError {
+            line: self.lexer.source_line(span.byte_range.start).1.to_owned(),
+            span,
+            message,
+            lexical,
         }
     }
 
     fn line(&mut self, token: &Token<'a>) -> Result<Line, LineError<'a>> {
         match token.kind {
             TokenKind::Identifier => {
+                // a name followed by EQU is always a label, even if it is also a mnemonic, e.g. `b EQU 4`
+                let mnemonic = match self.next_is_constant() {
+                    true => None,
+                    false => self.mnemonic(&token).ok(),
+                };
+
                 let line =
-                    match self.mnemonic(&token) {
+                    match mnemonic {
                         // if the line starts with a valid mnemonic
-                        Ok(mnemonic) => {
-                            let statement = self.statement(mnemonic)?;
+                        Some(mnemonic) => {
+                            let statement = self.statement(token, mnemonic)?;
 
                             self.line_end()?;
 
                             Line {
                                 label: None,
                                 statement: Some(statement),
+                                ..Line::default()
                             }
                         }
 
                         // otherwise, line must start with a label
-                        Err(_) => Line {
-                            label: Some(token.span.to_owned()),
+                        None => Line {
+                            span: Span::default(),
+                            warnings: Vec::new(),
+                            label: Some(token.text.to_owned()),
                             statement: match self.lexer.next_ignore_whitespace() {
                                 // line end - there is no statement
                                 None
@@ -176,7 +305,7 @@ impl<'a> Parser<'a> {
                                 ) => {
                                     let mnemonic = self.mnemonic(&next)?;
 
-                                    let statement = self.statement(mnemonic)?;
+                                    let statement = self.statement(&next, mnemonic)?;
                                     self.line_end()?;
 
                                     Some(statement)
@@ -214,8 +343,20 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// whether the next token is the EQU mnemonic
+    fn next_is_constant(&mut self) -> bool {
+        match self.lexer.peek_nth(0) {
+            Some(Token {
+                kind: TokenKind::Identifier,
+                text,
+                ..
+            }) => matches!(MNEMONICS.get(&UniCase::new(*text)), Some(Mnemonic::EQU)),
+            _ => false,
+        }
+    }
+
     fn mnemonic(&mut self, identifier: &Token<'a>) -> Result<Mnemonic, LineError<'a>> {
-        match MNEMONICS.get(&UniCase::new(identifier.span)).cloned() {
+        match MNEMONICS.get(&UniCase::new(identifier.text)).cloned() {
             None => Err(LineError {
                 token: Some(identifier.clone()),
                 message: "Invalid Mnemonic",
@@ -224,13 +365,33 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn statement(&mut self, mnemonic: Mnemonic) -> Result<Statement, LineError<'a>> {
+    fn statement(
+        &mut self,
+        mnemonic_token: &Token<'a>,
+        mnemonic: Mnemonic,
+    ) -> Result<Statement, LineError<'a>> {
+        self.lexer.record();
+
+        let kind = self.statement_kind(mnemonic);
+
+        let operands = operand_spans(&self.lexer.stop_recording());
+        let mnemonic = mnemonic_token.span.clone();
+
+        Ok(Statement {
+            kind: kind?,
+            span: self.span_from(&mnemonic),
+            mnemonic,
+            operands,
+        })
+    }
+
+    fn statement_kind(&mut self, mnemonic: Mnemonic) -> Result<StatementKind, LineError<'a>> {
         Ok(match mnemonic {
             // Branch
             Mnemonic::B { condition, l } => {
                 let target = self.label()?;
 
-                Statement::Instruction {
+                StatementKind::Instruction {
                     kind: InstructionKind::Branch {
                         condition,
                         kind: if l {
@@ -243,20 +404,47 @@ impl<'a> Parser<'a> {
                 }
             }
 
+            // Branch with Link and Exchange
+            Mnemonic::BLX => StatementKind::Instruction {
+                kind: InstructionKind::BranchWithLinkAndExchange {
+                    target: self.label()?,
+                    // this depends on the target's address, so is set when it is resolved
+                    halfword: false,
+                },
+            },
+
+            // Branch and Exchange
+            Mnemonic::BX { condition } => StatementKind::Instruction {
+                kind: InstructionKind::BranchAndExchange {
+                    condition,
+                    target: self.register()?.into(),
+                },
+            },
+
             // Data Processing - Move
             Mnemonic::MOV {
                 condition,
                 s: set_flags,
+            }
+            | Mnemonic::MVN {
+                condition,
+                s: set_flags,
             } => {
+                let kind = match mnemonic {
+                    Mnemonic::MOV { .. } => MoveKind::Move,
+                    Mnemonic::MVN { .. } => MoveKind::MoveNot,
+                    _ => unreachable!(),
+                };
+
                 let destination = self.register()?.into();
                 self.comma()?;
                 let shifter = self.shifter()?;
 
-                Statement::Instruction {
+                StatementKind::Instruction {
                     kind: InstructionKind::DataProcessing {
                         condition,
                         kind: DataProcessingKind::Move {
-                            kind: MoveKind::Move,
+                            kind,
                             set_flags: if set_flags {
                                 SetFlags::Set
                             } else {
@@ -270,16 +458,27 @@ impl<'a> Parser<'a> {
             }
 
             // Data Processing - Comparison
-            Mnemonic::CMP { condition } => {
+            Mnemonic::CMP { condition }
+            | Mnemonic::CMN { condition }
+            | Mnemonic::TST { condition }
+            | Mnemonic::TEQ { condition } => {
+                let kind = match mnemonic {
+                    Mnemonic::CMP { .. } => ComparisonKind::CMP,
+                    Mnemonic::CMN { .. } => ComparisonKind::CMN,
+                    Mnemonic::TST { .. } => ComparisonKind::TST,
+                    Mnemonic::TEQ { .. } => ComparisonKind::TEQ,
+                    _ => unreachable!(),
+                };
+
                 let source = self.register()?.into();
                 self.comma()?;
                 let shifter = self.shifter()?;
 
-                Statement::Instruction {
+                StatementKind::Instruction {
                     kind: InstructionKind::DataProcessing {
                         condition,
                         kind: DataProcessingKind::Comparison {
-                            kind: ComparisonKind::CMP,
+                            kind,
                             source,
                             shifter,
                         },
@@ -295,10 +494,30 @@ impl<'a> Parser<'a> {
             | Mnemonic::SUB {
                 condition,
                 s: set_flags,
+            }
+            | Mnemonic::RSB {
+                condition,
+                s: set_flags,
+            }
+            | Mnemonic::ADC {
+                condition,
+                s: set_flags,
+            }
+            | Mnemonic::SBC {
+                condition,
+                s: set_flags,
+            }
+            | Mnemonic::RSC {
+                condition,
+                s: set_flags,
             } => {
                 let kind = match mnemonic {
                     Mnemonic::ADD { .. } => CalculationKind::ADD,
                     Mnemonic::SUB { .. } => CalculationKind::SUB,
+                    Mnemonic::RSB { .. } => CalculationKind::RSB,
+                    Mnemonic::ADC { .. } => CalculationKind::ADC,
+                    Mnemonic::SBC { .. } => CalculationKind::SBC,
+                    Mnemonic::RSC { .. } => CalculationKind::RSC,
                     _ => unreachable!(),
                 };
 
@@ -308,7 +527,7 @@ impl<'a> Parser<'a> {
                 self.comma()?;
                 let shifter = self.shifter()?;
 
-                Statement::Instruction {
+                StatementKind::Instruction {
                     kind: InstructionKind::DataProcessing {
                         condition,
                         kind: DataProcessingKind::Calculation {
@@ -326,6 +545,94 @@ impl<'a> Parser<'a> {
                 }
             }
 
+            // Multiply
+            Mnemonic::MUL {
+                condition,
+                s: set_flags,
+            }
+            | Mnemonic::MLA {
+                condition,
+                s: set_flags,
+            } => {
+                let destination = self.register()?.into();
+                self.comma()?;
+                let multiplicand = self.register()?.into();
+                self.comma()?;
+                let multiplier = self.register()?.into();
+
+                let accumulate = match mnemonic {
+                    Mnemonic::MLA { .. } => {
+                        self.comma()?;
+                        Some(self.register()?.into())
+                    }
+                    _ => None,
+                };
+
+                StatementKind::Instruction {
+                    kind: InstructionKind::Multiply {
+                        condition,
+                        set_flags: if set_flags {
+                            SetFlags::Set
+                        } else {
+                            SetFlags::DontSet
+                        },
+                        destination,
+                        multiplicand,
+                        multiplier,
+                        accumulate,
+                    },
+                }
+            }
+
+            // Multiply - Long
+            Mnemonic::UMULL {
+                condition,
+                s: set_flags,
+            }
+            | Mnemonic::UMLAL {
+                condition,
+                s: set_flags,
+            }
+            | Mnemonic::SMULL {
+                condition,
+                s: set_flags,
+            }
+            | Mnemonic::SMLAL {
+                condition,
+                s: set_flags,
+            } => {
+                let kind = match mnemonic {
+                    Mnemonic::UMULL { .. } => MultiplyLongKind::UMULL,
+                    Mnemonic::UMLAL { .. } => MultiplyLongKind::UMLAL,
+                    Mnemonic::SMULL { .. } => MultiplyLongKind::SMULL,
+                    Mnemonic::SMLAL { .. } => MultiplyLongKind::SMLAL,
+                    _ => unreachable!(),
+                };
+
+                let low = self.register()?.into();
+                self.comma()?;
+                let high = self.register()?.into();
+                self.comma()?;
+                let multiplicand = self.register()?.into();
+                self.comma()?;
+                let multiplier = self.register()?.into();
+
+                StatementKind::Instruction {
+                    kind: InstructionKind::MultiplyLong {
+                        condition,
+                        kind,
+                        set_flags: if set_flags {
+                            SetFlags::Set
+                        } else {
+                            SetFlags::DontSet
+                        },
+                        destination: RegisterPair { low, high },
+                        multiplicand,
+                        multiplier,
+                    },
+                }
+            }
+
             // Load/Store
             // LDR can be either an instruction or pseudo-instruction so handle it separately
             Mnemonic::LDR { condition } => {
@@ -342,7 +649,7 @@ impl<'a> Parser<'a> {
 
                         let value = self.expression()?;
 
-                        Statement::PseudoInstruction {
+                        StatementKind::PseudoInstruction {
                             kind: PseudoInstructionKind::LoadRegisterConstant {
                                 condition,
                                 destination,
@@ -353,7 +660,7 @@ impl<'a> Parser<'a> {
                     _ => {
                         let address = self.load_store_address()?;
 
-                        Statement::Instruction {
+                        StatementKind::Instruction {
                             kind: InstructionKind::LoadStore {
                                 condition,
                                 kind: LoadStoreKind::Load,
@@ -366,6 +673,32 @@ impl<'a> Parser<'a> {
                 }
             }
 
+            // Preload Data
+            Mnemonic::PLD => {
+                let start = self.lexer.peek_ignore_whitespace().cloned();
+                let address = self.load_store_address()?;
+
+                // PLD doesn't write back to the base register
+                if let LoadStoreAddress::AddressingMode(LoadStoreAddressCode {
+                    offset:
+                        AddressingOffset {
+                            mode: OffsetMode::PreIndexed | OffsetMode::PostIndexed,
+                            ..
+                        },
+                    ..
+                }) = address
+                {
+                    return Err(LineError {
+                        token: start,
+                        message: "PLD can't use pre- or post-indexed addressing",
+                    });
+                }
+
+                StatementKind::Instruction {
+                    kind: InstructionKind::Preload { address },
+                }
+            }
+
             // all these instructions have the same structure
             Mnemonic::LDRB { condition }
             | Mnemonic::STR { condition }
@@ -381,7 +714,7 @@ impl<'a> Parser<'a> {
                 self.comma()?;
                 let address = self.load_store_address()?;
 
-                Statement::Instruction {
+                StatementKind::Instruction {
                     kind: InstructionKind::LoadStore {
                         condition,
                         kind,
@@ -400,7 +733,7 @@ impl<'a> Parser<'a> {
                     _ => unreachable!(),
                 };
 
-                let base = self.register()?.into();
+                let base: Rn = self.register()?.into();
 
                 let write_back = match self.lexer.next_ignore_whitespace() {
                     Some(Token {
@@ -422,9 +755,18 @@ impl<'a> Parser<'a> {
                     }
                 };
 
+                let list_start = self.next_span();
                 let register_list = self.register_list()?;
 
-                Statement::Instruction {
+                // the ARM ARM says the final value of the base register is unpredictable
+                if write_back == WriteBack::WriteBack && register_list.contains(base.0) {
+                    self.warnings.push(ParseWarning {
+                        span: self.span_from(&list_start),
+                        message: "The base register is in the Register List with write-back, so its final value is unpredictable",
+                    });
+                }
+
+                StatementKind::Instruction {
                     kind: InstructionKind::LoadStoreMultiple {
                         condition,
                         kind,
@@ -440,7 +782,7 @@ impl<'a> Parser<'a> {
             Mnemonic::SVC { condition } => {
                 let immediate = self.expression()?;
 
-                Statement::Instruction {
+                StatementKind::Instruction {
                     kind: InstructionKind::SuperVisorCall {
                         condition,
                         immediate,
@@ -448,13 +790,69 @@ impl<'a> Parser<'a> {
                 }
             }
 
+            // Status Register Access
+            Mnemonic::MRS { condition } => {
+                let destination = self.register()?.into();
+                self.comma()?;
+                self.status_register(false)?;
+
+                StatementKind::Instruction {
+                    kind: InstructionKind::MoveStatusToRegister {
+                        condition,
+                        destination,
+                    },
+                }
+            }
+
+            Mnemonic::MSR { condition } => {
+                let fields = self.status_register(true)?;
+                self.comma()?;
+
+                // the operand can't be shifted
+                let source = match self.lexer.peek_ignore_whitespace() {
+                    Some(Token {
+                        kind: TokenKind::LiteralSign,
+                        ..
+                    }) => {
+                        // eat the #
+                        self.lexer.next();
+
+                        ShifterOperandExpression::Immediate(self.expression()?)
+                    }
+                    _ => ShifterOperandExpression::Register(self.register()?),
+                };
+
+                StatementKind::Instruction {
+                    kind: InstructionKind::MoveToStatusRegister {
+                        condition,
+                        fields,
+                        source,
+                    },
+                }
+            }
+
+            // Miscellaneous Arithmetic
+            Mnemonic::CLZ { condition } => {
+                let destination = self.register()?.into();
+                self.comma()?;
+                let source = self.register()?.into();
+
+                StatementKind::Instruction {
+                    kind: InstructionKind::CountLeadingZeros {
+                        condition,
+                        destination,
+                        source,
+                    },
+                }
+            }
+
             // Pseudo-Instruction - Address Register
             Mnemonic::ADR { condition, l: long } => {
                 let destination = self.register()?;
                 self.comma()?;
                 let label = self.label()?;
 
-                Statement::PseudoInstruction {
+                StatementKind::PseudoInstruction {
                     kind: PseudoInstructionKind::AddressRegister {
                         condition,
                         long,
@@ -464,9 +862,38 @@ impl<'a> Parser<'a> {
                 }
             }
 
+            // Pseudo-Instruction - Move 32-bit Constant
+            Mnemonic::MOV32 { condition } => {
+                let destination = self.register()?.into();
+                self.comma()?;
+
+                match self.lexer.next_ignore_whitespace() {
+                    Some(Token {
+                        kind: TokenKind::LiteralSign,
+                        ..
+                    }) => (),
+                    token => {
+                        return Err(LineError {
+                            token,
+                            message: "Expected a Literal",
+                        })
+                    }
+                }
+
+                let value = self.expression()?;
+
+                StatementKind::PseudoInstruction {
+                    kind: PseudoInstructionKind::MoveConstant {
+                        condition,
+                        destination,
+                        value,
+                    },
+                }
+            }
+
             // Directive - Define Space
             Mnemonic::DEFS => {
-                let size = self.number()? as usize;
+                let size = self.expression()?;
 
                 let fill = match self.lexer.peek_ignore_whitespace() {
                     Some(Token {
@@ -475,12 +902,12 @@ impl<'a> Parser<'a> {
                     }) => {
                         self.lexer.next();
 
-                        Some(self.number()? as u8) // TODO: check it fits in u8, and allow for characters
+                        Some(self.expression()?)
                     }
                     _ => None,
                 };
 
-                Statement::Directive {
+                StatementKind::Directive {
                     kind: DirectiveKind::Definition {
                         kind: DefinitionKind::Space { size, fill },
                     },
@@ -492,20 +919,27 @@ impl<'a> Parser<'a> {
 
                 loop {
                     match self.lexer.next_ignore_whitespace() {
-                        Some(Token {
-                            kind: TokenKind::Decimal,
-                            span,
-                            ..
-                        }) => {
-                            bytes
-                                .push(BytesDefinition::Byte(u8::from_str_radix(span, 10).unwrap()));
-                        }
+                        Some(
+                            token @ Token {
+                                kind: TokenKind::Decimal,
+                                text,
+                                ..
+                            },
+                        ) => match u8::from_str_radix(text, 10) {
+                            Ok(byte) => bytes.push(BytesDefinition::Byte(byte)),
+                            Err(_) => {
+                                return Err(LineError {
+                                    token: Some(token),
+                                    message: "Number is too large to fit in a byte",
+                                })
+                            }
+                        },
                         Some(Token {
                             kind: TokenKind::String,
-                            span,
+                            text,
                             ..
                         }) => {
-                            bytes.push(BytesDefinition::String(span[1..span.len() - 1].to_owned()))
+                            bytes.push(BytesDefinition::String(text[1..text.len() - 1].to_owned()))
                         }
                         _ => break,
                     }
@@ -522,7 +956,7 @@ impl<'a> Parser<'a> {
                     }
                 }
 
-                Statement::Directive {
+                StatementKind::Directive {
                     kind: DirectiveKind::Definition {
                         kind: DefinitionKind::Bytes { bytes },
                     },
@@ -534,16 +968,7 @@ impl<'a> Parser<'a> {
                 let mut words = Vec::new();
 
                 loop {
-                    match self.lexer.next_ignore_whitespace() {
-                        Some(Token {
-                            kind: TokenKind::Decimal,
-                            span,
-                            ..
-                        }) => {
-                            words.push(u32::from_str_radix(span, 10).unwrap());
-                        }
-                        _ => break,
-                    }
+                    words.push(self.word_definition()?);
 
                     match self.lexer.peek_ignore_whitespace() {
                         Some(Token {
@@ -557,7 +982,7 @@ impl<'a> Parser<'a> {
                     }
                 }
 
-                Statement::Directive {
+                StatementKind::Directive {
                     kind: DirectiveKind::Definition {
                         kind: DefinitionKind::Words { words },
                     },
@@ -565,21 +990,26 @@ impl<'a> Parser<'a> {
             }
 
             // Directive - Align
-            Mnemonic::ALIGN => Statement::Directive {
+            Mnemonic::ALIGN => StatementKind::Directive {
                 kind: DirectiveKind::Align,
             },
 
+            // Directive - Literal Pool
+            Mnemonic::LTORG => StatementKind::Directive {
+                kind: DirectiveKind::LiteralPool,
+            },
+
             // Directive - Origin
             Mnemonic::ORIGIN => {
                 let address = self.expression()?;
 
-                Statement::Directive {
+                StatementKind::Directive {
                     kind: DirectiveKind::Origin { address },
                 }
             }
 
             // Directive - Entry Point
-            Mnemonic::ENTRY => Statement::Directive {
+            Mnemonic::ENTRY => StatementKind::Directive {
                 kind: DirectiveKind::EntryPoint,
             },
 
@@ -587,10 +1017,46 @@ impl<'a> Parser<'a> {
             Mnemonic::EQU => {
                 let value = self.expression()?;
 
-                Statement::Directive {
+                StatementKind::Directive {
                     kind: DirectiveKind::Constant { value },
                 }
             }
+
+            // Directive - Checksum
+            Mnemonic::CHECKSUM | Mnemonic::CRC32 => {
+                let kind = match mnemonic {
+                    Mnemonic::CHECKSUM => ChecksumKind::Sum,
+                    Mnemonic::CRC32 => ChecksumKind::Crc32,
+                    _ => unreachable!(),
+                };
+
+                let start = self.label()?;
+                self.comma()?;
+                let end = self.label()?;
+
+                StatementKind::Directive {
+                    kind: DirectiveKind::Checksum { kind, start, end },
+                }
+            }
+
+            // Directive - Section
+            Mnemonic::SECTION => match self.lexer.next_ignore_whitespace() {
+                Some(Token {
+                    kind: TokenKind::Identifier,
+                    text,
+                    ..
+                }) => StatementKind::Directive {
+                    kind: DirectiveKind::Section {
+                        name: text.to_owned(),
+                    },
+                },
+                token => {
+                    return Err(LineError {
+                        token,
+                        message: "Expected a section name",
+                    })
+                }
+            },
         })
     }
 
@@ -613,6 +1079,27 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// expects nothing but whitespace, comments and newlines before the end of the input
+    fn input_end(&mut self) -> Result<(), LineError<'a>> {
+        loop {
+            match self.lexer.next_ignore_whitespace() {
+                None => return Ok(()),
+
+                Some(Token {
+                    kind: TokenKind::NewLine | TokenKind::Comment,
+                    ..
+                }) => continue,
+
+                token => {
+                    return Err(LineError {
+                        token,
+                        message: "Expected the end of the input",
+                    })
+                }
+            }
+        }
+    }
+
     fn new_line_or_eof(&mut self) -> Result<(), LineError<'a>> {
         match self.lexer.next_ignore_whitespace() {
             None
@@ -628,21 +1115,6 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn number(&mut self) -> Result<u32, LineError<'a>> {
-        match self.lexer.next_ignore_whitespace() {
-            Some(Token {
-                kind: TokenKind::Decimal,
-                span,
-                ..
-            }) => Ok(u32::from_str_radix(span, 10).unwrap()),
-
-            token => Err(LineError {
-                token,
-                message: "Expected a number",
-            }),
-        }
-    }
-
     fn register_list(&mut self) -> Result<RegisterList, LineError<'a>> {
         match self.lexer.next_ignore_whitespace() {
             Some(Token {
@@ -657,9 +1129,22 @@ impl<'a> Parser<'a> {
             }
         }
 
-        let mut registers = [false; 16];
+        // an empty list doesn't transfer anything, and is unpredictable
+        if let Some(Token {
+            kind: TokenKind::CloseBrace,
+            ..
+        }) = self.lexer.peek_ignore_whitespace()
+        {
+            return Err(LineError {
+                token: self.lexer.next_ignore_whitespace(),
+                message: "Register List can't be empty",
+            });
+        }
+
+        let mut registers = RegisterList::default();
 
         loop {
+            let start = self.next_span();
             let register = self.register()?;
 
             match self.lexer.next_ignore_whitespace() {
@@ -670,10 +1155,16 @@ impl<'a> Parser<'a> {
                 }) => {
                     let to_register = self.register()?;
 
-                    let range = register.0..=to_register.0;
+                    // a descending range like `{R5-R2}` is probably a mistake, but it still means the same registers
+                    if to_register.0 < register.0 {
+                        self.warnings.push(ParseWarning {
+                            span: self.span_from(&start),
+                            message: "Register List range is written in descending order",
+                        });
+                    }
 
-                    for i in range {
-                        registers[i as usize] = true;
+                    for n in register.0.min(to_register.0)..=register.0.max(to_register.0) {
+                        registers.insert(n);
                     }
 
                     match self.lexer.next_ignore_whitespace() {
@@ -699,8 +1190,7 @@ impl<'a> Parser<'a> {
                     kind: TokenKind::Comma,
                     ..
                 }) => {
-                    let n = register.0;
-                    registers[n as usize] = true;
+                    registers.insert(register.0);
 
                     continue;
                 }
@@ -710,8 +1200,7 @@ impl<'a> Parser<'a> {
                     kind: TokenKind::CloseBrace,
                     ..
                 }) => {
-                    let n = register.0;
-                    registers[n as usize] = true;
+                    registers.insert(register.0);
 
                     break;
                 }
@@ -725,7 +1214,7 @@ impl<'a> Parser<'a> {
             }
         }
 
-        Ok(RegisterList { registers })
+        Ok(registers)
     }
 
     fn close_brace(&mut self) -> Result<(), LineError<'a>> {
@@ -757,7 +1246,7 @@ impl<'a> Parser<'a> {
                 kind: TokenKind::Identifier,
                 ..
             }) => {
-                let register = self.register()?.into();
+                let register = self.register()?;
 
                 match self.lexer.peek_ignore_whitespace() {
                     Some(Token {
@@ -776,7 +1265,7 @@ impl<'a> Parser<'a> {
                             _ => {
                                 let kind = shift_name.try_into().unwrap();
 
-                                let amount = self.shifter_shift_amount()?;
+                                let amount = self.shifter_shift_amount(kind)?;
 
                                 Ok(ShifterOperandExpression::ShiftedRegister(ShiftedRegister {
                                     kind,
@@ -799,7 +1288,10 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn shifter_shift_amount(&mut self) -> Result<ShifterOperandShiftAmount, LineError<'a>> {
+    fn shifter_shift_amount(
+        &mut self,
+        kind: Shift,
+    ) -> Result<ShifterOperandShiftAmount, LineError<'a>> {
         match self.lexer.peek_ignore_whitespace() {
             Some(Token {
                 kind: TokenKind::LiteralSign,
@@ -808,13 +1300,15 @@ impl<'a> Parser<'a> {
                 // eat the #
                 self.lexer.next();
 
-                Ok(ShifterOperandShiftAmount::Immediate(self.expression()?))
+                Ok(ShifterOperandShiftAmount::Immediate(
+                    self.shift_amount(kind)?,
+                ))
             }
 
             Some(Token {
                 kind: TokenKind::Identifier,
                 ..
-            }) => Ok(ShifterOperandShiftAmount::Register(self.register()?.into())),
+            }) => Ok(ShifterOperandShiftAmount::Register(self.register()?)),
 
             _ => {
                 return Err(LineError {
@@ -878,10 +1372,12 @@ impl<'a> Parser<'a> {
     ) -> Result<AddressingOffset<Expression, Expression>, LineError<'a>> {
         match self.lexer.next_ignore_whitespace() {
             // No offset or Post-Index
-            Some(Token {
-                kind: TokenKind::CloseBracket,
-                ..
-            }) => {
+            Some(
+                close_bracket @ Token {
+                    kind: TokenKind::CloseBracket,
+                    ..
+                },
+            ) => {
                 match self.lexer.peek_ignore_whitespace() {
                     // Post-Index
                     Some(Token {
@@ -903,9 +1399,14 @@ impl<'a> Parser<'a> {
                     // No Offset
                     _ => Ok(AddressingOffset {
                         sign: Sign::Positive,
-                        value: AddressingOffsetValue::Immediate(Expression::Number {
-                            base: 10,
-                            n: 0,
+                        // the implicit zero offset is an empty span just after the bracket
+                        value: AddressingOffsetValue::Immediate(Expression {
+                            kind: ExpressionKind::Number { base: 10, n: 0 },
+                            span: Span::empty(
+                                close_bracket.span.byte_range.end,
+                                close_bracket.span.line,
+                                close_bracket.span.column + 1,
+                            ),
                         }),
                         mode: OffsetMode::Offset,
                     }),
@@ -1012,7 +1513,7 @@ impl<'a> Parser<'a> {
                             }
                         }
 
-                        let amount = self.expression()?;
+                        let amount = self.shift_amount(kind)?;
 
                         AddressingOffsetValue::ScaledRegister(ShiftedRegister {
                             kind,
@@ -1064,10 +1565,10 @@ impl<'a> Parser<'a> {
             Some(
                 token @ Token {
                     kind: TokenKind::Identifier,
-                    span,
+                    text,
                     ..
                 },
-            ) => match SHIFT_NAMES.get(&UniCase::new(span)).cloned() {
+            ) => match SHIFT_NAMES.get(&UniCase::new(text)).cloned() {
                 Some(shift) => Ok(shift),
                 _ => Err(LineError {
                     token: Some(token),
@@ -1081,16 +1582,50 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// an immediate shift amount, which is checked against the range of the shift if it is a number
+    ///
+    /// Amounts given by symbols are checked when they are resolved.
+    fn shift_amount(&mut self, kind: Shift) -> Result<Expression, LineError<'a>> {
+        let token = self.lexer.peek_ignore_whitespace().cloned();
+        let amount = self.expression()?;
+
+        if let ExpressionKind::Number { n, .. } = amount.kind {
+            if n > kind.max_amount() {
+                return Err(LineError {
+                    token,
+                    message: match kind {
+                        Shift::LogicalShiftLeft | Shift::RotateRight => {
+                            "Shift amount is too large, LSL and ROR can shift by at most 31"
+                        }
+                        Shift::LogicalShiftRight | Shift::ArithmeticShiftRight => {
+                            "Shift amount is too large, LSR and ASR can shift by at most 32"
+                        }
+                    },
+                });
+            }
+        }
+
+        Ok(amount)
+    }
+
     fn shift_kind(&mut self) -> Result<Shift, LineError<'a>> {
         match self.lexer.next_ignore_whitespace() {
             Some(
                 token @ Token {
                     kind: TokenKind::Identifier,
-                    span,
+                    text,
                     ..
                 },
-            ) => match SHIFT_KINDS.get(&UniCase::new(span)).cloned() {
+            ) => match SHIFT_KINDS.get(&UniCase::new(text)).cloned() {
                 Some(shift) => Ok(shift),
+                None if SHIFT_NAMES.get(&UniCase::new(text))
+                    == Some(&ShiftName::RotateRightExtended) =>
+                {
+                    Err(LineError {
+                        token: Some(token),
+                        message: "RRX can't be used in a scaled register offset",
+                    })
+                }
                 _ => Err(LineError {
                     token: Some(token),
                     message: "Invalid Shift Operation",
@@ -1121,11 +1656,11 @@ impl<'a> Parser<'a> {
             Some(
                 token @ Token {
                     kind: TokenKind::Identifier,
-                    span,
+                    text,
                     ..
                 },
-            ) => match REGISTERS.get(&UniCase::new(span)).cloned() {
-                Some(register) => Ok(register),
+            ) => match register_number(text) {
+                Some(register) => Ok(Register(register)),
                 _ => Err(LineError {
                     token: Some(token),
                     message: "Invalid Register",
@@ -1138,13 +1673,75 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// parses `CPSR`, or `CPSR_` followed by the letters of the fields to write if `fields` is set, like `CPSR_fc`
+    fn status_register(&mut self, fields: bool) -> Result<StatusFields, LineError<'a>> {
+        let token = self.lexer.next_ignore_whitespace();
+
+        let text = match &token {
+            Some(Token {
+                kind: TokenKind::Identifier,
+                text,
+                ..
+            }) => text.to_ascii_lowercase(),
+            _ => String::new(),
+        };
+
+        let letters = match text.split_once('_') {
+            Some(("cpsr", letters)) if fields && !letters.is_empty() => letters,
+            _ if text == "cpsr" => return Ok(StatusFields::DEFAULT),
+            _ => {
+                return Err(LineError {
+                    token,
+                    message: if fields {
+                        "Expected CPSR, or CPSR_ followed by any of the fields f, s, x and c"
+                    } else {
+                        "Expected CPSR"
+                    },
+                })
+            }
+        };
+
+        let mut status_fields = StatusFields {
+            flags: false,
+            status: false,
+            extension: false,
+            control: false,
+        };
+
+        for letter in letters.chars() {
+            let field = match letter {
+                'f' => &mut status_fields.flags,
+                's' => &mut status_fields.status,
+                'x' => &mut status_fields.extension,
+                'c' => &mut status_fields.control,
+                _ => {
+                    return Err(LineError {
+                        token,
+                        message: "Invalid field. The fields of the CPSR are f, s, x and c",
+                    })
+                }
+            };
+
+            if *field {
+                return Err(LineError {
+                    token,
+                    message: "Each field of the CPSR can only be written once",
+                });
+            }
+
+            *field = true;
+        }
+
+        Ok(status_fields)
+    }
+
     fn label(&mut self) -> Result<Symbol, LineError<'a>> {
         match self.lexer.next_ignore_whitespace() {
             Some(Token {
                 kind: TokenKind::Identifier,
-                span,
+                text,
                 ..
-            }) => Ok(Symbol(span.to_owned())),
+            }) => Ok(Symbol(text.to_owned())),
             token => Err(LineError {
                 token,
                 message: "Expected a label",
@@ -1153,47 +1750,161 @@ impl<'a> Parser<'a> {
     }
 
     fn expression(&mut self) -> Result<Expression, LineError<'a>> {
-        let expression = match self.lexer.next_ignore_whitespace() {
+        let start = self.next_span();
+
+        let term = Expression {
+            kind: self.term()?,
+            span: self.span_from(&start),
+        };
+
+        match self.lexer.peek_ignore_whitespace() {
             Some(Token {
-                kind: TokenKind::Decimal,
-                span,
+                kind: kind @ TokenKind::Plus | kind @ TokenKind::HyphenMinus,
                 ..
-            }) => Ok(Expression::Number {
-                base: 10,
-                n: u32::from_str_radix(span, 10).unwrap(),
-            }),
+            }) => {
+                let operator = match kind {
+                    TokenKind::Plus => DiadicOperator::Plus,
+                    TokenKind::HyphenMinus => DiadicOperator::Minus,
+                    _ => unreachable!(),
+                };
+
+                // eat the operator
+                self.lexer.next();
+
+                let rhs = self.expression()?;
+
+                Ok(Expression {
+                    kind: ExpressionKind::Diadic(Box::new(term), operator, Box::new(rhs)),
+                    span: self.span_from(&start),
+                })
+            }
+
+            _ => Ok(term),
+        }
+    }
 
+    /// parses a word of a DEFW, which is a number, a label, `label - .`, or any other expression
+    fn word_definition(&mut self) -> Result<WordDefinition, LineError<'a>> {
+        let start = self.next_span();
+
+        let symbol = match self.lexer.peek_ignore_whitespace() {
             Some(Token {
-                kind: TokenKind::Hexadecimal,
-                span,
+                kind: TokenKind::Identifier,
+                text,
                 ..
-            }) => Ok(Expression::Number {
-                base: 16,
-                n: u32::from_str_radix(&span[2..], 16).unwrap(),
-            }),
+            }) => Symbol(text.to_string()),
+            _ => {
+                let expression = self.expression()?;
+
+                return Ok(match expression.kind {
+                    ExpressionKind::Number { base: 10, n } => WordDefinition::Word(n),
+                    _ => WordDefinition::Expression(expression),
+                });
+            }
+        };
 
+        // eat the label
+        self.lexer.next_ignore_whitespace();
+
+        let label = Expression {
+            kind: ExpressionKind::Symbol(symbol.clone()),
+            span: self.span_from(&start),
+        };
+
+        let operator = match self.lexer.peek_ignore_whitespace() {
             Some(Token {
-                kind: TokenKind::NBaseNumber,
-                span,
+                kind: TokenKind::Plus,
                 ..
-            }) => {
-                let (base, number) = span.split_once('_').unwrap();
-                let base = base.parse().unwrap();
+            }) => DiadicOperator::Plus,
+            Some(Token {
+                kind: TokenKind::HyphenMinus,
+                ..
+            }) => DiadicOperator::Minus,
+            _ => return Ok(WordDefinition::Address(symbol)),
+        };
+
+        // eat the operator
+        self.lexer.next_ignore_whitespace();
+
+        // `label - .` is the offset of the label from the word
+        if operator == DiadicOperator::Minus {
+            if let Some(Token {
+                kind: TokenKind::Dot,
+                ..
+            }) = self.lexer.peek_ignore_whitespace()
+            {
+                self.lexer.next_ignore_whitespace();
+
+                return Ok(WordDefinition::Offset(symbol));
+            }
+        }
+
+        let rhs = self.expression()?;
+
+        Ok(WordDefinition::Expression(Expression {
+            kind: ExpressionKind::Diadic(Box::new(label), operator, Box::new(rhs)),
+            span: self.span_from(&start),
+        }))
+    }
 
-                Ok(Expression::Number {
+    /// parses a single value, without any operators
+    fn term(&mut self) -> Result<ExpressionKind, LineError<'a>> {
+        match self.lexer.next_ignore_whitespace() {
+            Some(
+                token @ Token {
+                    kind: TokenKind::Decimal,
+                    text,
+                    ..
+                },
+            ) => Ok(ExpressionKind::Number {
+                base: 10,
+                n: number_literal(token, text, 10)?,
+            }),
+
+            Some(
+                token @ Token {
+                    kind: TokenKind::Hexadecimal,
+                    text,
+                    ..
+                },
+            ) => Ok(ExpressionKind::Number {
+                base: 16,
+                n: number_literal(token, &text[2..], 16)?,
+            }),
+
+            Some(
+                token @ Token {
+                    kind: TokenKind::NBaseNumber,
+                    text,
+                    ..
+                },
+            ) => {
+                let (base, number) = text.split_once('_').unwrap();
+
+                let base = match base.parse() {
+                    Ok(base @ 2..=36) => base,
+                    _ => {
+                        return Err(LineError {
+                            token: Some(token),
+                            message: "The base of a number must be between 2 and 36",
+                        })
+                    }
+                };
+
+                Ok(ExpressionKind::Number {
                     base,
-                    n: u32::from_str_radix(number, base).unwrap(),
+                    n: number_literal(token, number, base)?,
                 })
             }
 
             Some(
                 token @ Token {
                     kind: TokenKind::Character,
-                    span,
+                    text,
                     ..
                 },
-            ) => match span[1..span.len() - 1].chars().next() {
-                Some(char) if span.len() == 3 => Ok(Expression::Character(char)),
+            ) => match text[1..text.len() - 1].chars().next() {
+                Some(char) if text.len() == 3 => Ok(ExpressionKind::Character(char)),
                 _ => Err(LineError {
                     token: Some(token),
                     message: "Expected a single character within the single quotes",
@@ -1202,9 +1913,9 @@ impl<'a> Parser<'a> {
 
             Some(Token {
                 kind: TokenKind::String,
-                span,
+                text,
                 ..
-            }) => Ok(Expression::String(span[1..span.len() - 1].to_string())),
+            }) => Ok(ExpressionKind::String(text[1..text.len() - 1].to_string())),
 
             // boolean
             Some(Token {
@@ -1214,12 +1925,12 @@ impl<'a> Parser<'a> {
                 let boolean = match self.lexer.next() {
                     Some(Token {
                         kind: TokenKind::Identifier,
-                        span: "TRUE",
+                        text: "TRUE",
                         ..
                     }) => true,
                     Some(Token {
                         kind: TokenKind::Identifier,
-                        span: "FALSE",
+                        text: "FALSE",
                         ..
                     }) => false,
                     token => {
@@ -1232,43 +1943,79 @@ impl<'a> Parser<'a> {
 
                 self.close_brace()?;
 
-                Ok(Expression::Boolean(boolean))
+                Ok(ExpressionKind::Boolean(boolean))
             }
 
             Some(Token {
                 kind: TokenKind::Identifier,
-                span,
+                text,
                 ..
-            }) => Ok(Expression::Symbol(Symbol(span.to_owned()))),
+            }) => Ok(ExpressionKind::Symbol(Symbol(text.to_owned()))),
 
             token => Err(LineError {
                 token,
                 message: "Expected Number, Character, String, or Boolean",
             }),
-        };
+        }
+    }
 
-        match self.lexer.peek_ignore_whitespace() {
-            Some(Token {
-                kind: kind @ TokenKind::Plus | kind @ TokenKind::HyphenMinus,
-                ..
-            }) => {
-                let operator = match kind {
-                    TokenKind::Plus => DiadicOperator::Plus,
-                    TokenKind::HyphenMinus => DiadicOperator::Minus,
-                    _ => unreachable!(),
-                };
+    /// the span of the next token, ignoring whitespace
+    fn next_span(&mut self) -> Span {
+        self.lexer
+            .peek_ignore_whitespace()
+            .map(|token| token.span.clone())
+            .unwrap_or_default()
+    }
 
-                // eat the operator
-                self.lexer.next();
+    /// a span from the start of `start` to the end of the last token consumed
+    fn span_from(&self, start: &Span) -> Span {
+        Span::new(
+            start.byte_range.start..self.lexer.last_end().max(start.byte_range.start),
+            start.line,
+            start.column,
+        )
+    }
+}
 
-                Ok(Expression::Diadic(
-                    Box::new(expression?),
-                    operator,
-                    Box::new(self.expression()?),
-                ))
-            }
+/// converts the digits of a number literal to a value, which must fit in a word
+fn number_literal<'a>(token: Token<'a>, digits: &str, radix: u32) -> Result<u32, LineError<'a>> {
+    u32::from_str_radix(digits, radix).map_err(|error| LineError {
+        token: Some(token),
+        message: match error.kind() {
+            IntErrorKind::Empty => "Expected the digits of the number",
+            IntErrorKind::InvalidDigit => "Invalid digit for the base of the number",
+            // there are no 64-bit directives, so every literal must fit in 32 bits
+            _ => "Number is too large to fit in 32 bits",
+        },
+    })
+}
+
+/// splits the tokens of a statement at the commas between its operands, returning the span of each operand
+fn operand_spans(tokens: &[Token]) -> Vec<Span> {
+    let mut operands = Vec::new();
+    let mut operand: Option<Span> = None;
 
-            _ => expression,
+    // commas within brackets and braces separate the parts of a single operand
+    let mut depth = 0usize;
+
+    for token in tokens {
+        match token.kind {
+            TokenKind::OpenBrace | TokenKind::OpenBracket => depth += 1,
+            TokenKind::CloseBrace | TokenKind::CloseBracket => depth = depth.saturating_sub(1),
+            TokenKind::Comma if depth == 0 => {
+                operands.extend(operand.take());
+                continue;
+            }
+            _ => (),
         }
+
+        operand = Some(match operand {
+            Some(operand) => operand.to(&token.span),
+            None => token.span.clone(),
+        });
     }
+
+    operands.extend(operand);
+
+    operands
 }
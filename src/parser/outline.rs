@@ -0,0 +1,190 @@
+use crate::span::Span;
+
+use super::{DirectiveKind, Line, Parser, StatementKind};
+
+/// the structure of a source file, for an editor's document symbols and folding ranges
+///
+/// Each label starts an item that runs until the next label or section, and sections contain the items after them.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Outline {
+    pub items: Vec<OutlineItem>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutlineItem {
+    pub name: String,
+    pub kind: OutlineKind,
+    /// the span of the item's name, i.e. its label or the name of its section
+    pub selection: Span,
+    /// the span from the start of the item's first line to the end of its last statement
+    pub span: Span,
+    /// the zero-based line of the item's last statement, so blank lines and comments before the next item aren't part of it
+    pub last_line: usize,
+    /// the items in a section, which is empty for other kinds
+    pub children: Vec<OutlineItem>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutlineKind {
+    /// a `SECTION`
+    Section,
+    /// a label followed by instructions, or by nothing
+    Routine,
+    /// a label followed by data, e.g. `DEFW` or `DEFS`
+    Data,
+    /// a symbol defined by `EQU`
+    Constant,
+}
+
+impl Outline {
+    /// the outline of parsed lines, such as [`crate::assembler::Program::outline`]
+    pub fn new(lines: &[Line]) -> Outline {
+        let mut outline = Outline::default();
+        let mut section: Option<OutlineItem> = None;
+        let mut current: Option<OutlineItem> = None;
+        // whether a statement since the current label has decided its kind
+        let mut decided = false;
+
+        let mut finish = |item: Option<OutlineItem>, section: &mut Option<OutlineItem>| {
+            if let Some(item) = item {
+                match section {
+                    Some(section) => {
+                        section.extend_to(&item.span, item.last_line);
+                        section.children.push(item);
+                    }
+                    None => outline.items.push(item),
+                }
+            }
+        };
+
+        for line in lines {
+            let kind = line.statement.as_ref().map(|statement| &statement.kind);
+
+            if let Some(StatementKind::Directive {
+                kind: DirectiveKind::Section { name },
+            }) = kind
+            {
+                finish(current.take(), &mut section);
+                finish(section.take(), &mut None);
+
+                let statement = line.statement.as_ref().unwrap();
+                let selection = statement
+                    .operands
+                    .first()
+                    .unwrap_or(&statement.span)
+                    .clone();
+
+                section = Some(OutlineItem::new(
+                    name.clone(),
+                    OutlineKind::Section,
+                    line,
+                    selection,
+                ));
+            }
+
+            if let Some(label) = &line.label {
+                finish(current.take(), &mut section);
+
+                let selection = Span::new(
+                    line.span.byte_range.start..line.span.byte_range.start + label.len(),
+                    line.span.line,
+                    line.span.column,
+                );
+
+                // a label is a routine until a statement after it says otherwise
+                current = Some(OutlineItem::new(
+                    label.clone(),
+                    OutlineKind::Routine,
+                    line,
+                    selection,
+                ));
+                decided = false;
+            }
+
+            if let (Some(item), Some(kind)) = (current.as_mut(), kind) {
+                item.extend_to(&line.span, line.span.line);
+
+                match kind {
+                    // a constant is a single line
+                    StatementKind::Directive {
+                        kind: DirectiveKind::Constant { .. },
+                    } if line.label.is_some() => {
+                        item.kind = OutlineKind::Constant;
+                        finish(current.take(), &mut section);
+                    }
+
+                    StatementKind::Directive {
+                        kind: DirectiveKind::Definition { .. } | DirectiveKind::LiteralPool,
+                    } if !decided => {
+                        item.kind = OutlineKind::Data;
+                        decided = true;
+                    }
+
+                    StatementKind::Instruction { .. } | StatementKind::PseudoInstruction { .. } => {
+                        decided = true
+                    }
+
+                    _ => {}
+                }
+            }
+        }
+
+        finish(current.take(), &mut section);
+        finish(section.take(), &mut None);
+
+        outline
+    }
+
+    /// the outline of a source file, which works for a file that doesn't parse, as lines with errors are left out
+    pub fn parse(source: &str) -> Outline {
+        let mut offset = 0;
+
+        let lines: Vec<Line> = source
+            .split('\n')
+            .enumerate()
+            .filter_map(|(index, text)| {
+                let line = Parser::parse_line(text, offset, index);
+                offset += text.len() + 1;
+
+                line.ok()
+            })
+            .collect();
+
+        Outline::new(&lines)
+    }
+
+    /// the zero-based first and last lines of every item that spans more than one line, including those in sections
+    pub fn folding_ranges(&self) -> Vec<(usize, usize)> {
+        fn add(items: &[OutlineItem], ranges: &mut Vec<(usize, usize)>) {
+            for item in items {
+                if item.last_line > item.span.line {
+                    ranges.push((item.span.line, item.last_line));
+                }
+                add(&item.children, ranges);
+            }
+        }
+
+        let mut ranges = Vec::new();
+        add(&self.items, &mut ranges);
+
+        ranges
+    }
+}
+
+impl OutlineItem {
+    fn new(name: String, kind: OutlineKind, line: &Line, selection: Span) -> OutlineItem {
+        OutlineItem {
+            name,
+            kind,
+            selection,
+            span: line.span.clone(),
+            last_line: line.span.line,
+            children: Vec::new(),
+        }
+    }
+
+    fn extend_to(&mut self, span: &Span, line: usize) {
+        self.span.byte_range.end = self.span.byte_range.end.max(span.byte_range.end);
+        self.last_line = self.last_line.max(line);
+    }
+}
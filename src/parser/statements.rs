@@ -1,7 +1,11 @@
 use std::collections::HashMap;
 
-use crate::ir::{
-    Condition, InstructionKind, LoadStoreAddressCode, Rd, Rm, Rn, Rs, ShiftedRegister,
+use crate::{
+    ir::{
+        AddressingOffsetValue, Condition, DataProcessingKind, InstructionKind,
+        LoadStoreAddressCode, Rd, Rm, Rn, Rs, ShiftedRegister,
+    },
+    span::Span,
 };
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
@@ -10,14 +14,36 @@ pub struct Symbol(pub String);
 pub type StatementInstructionKind =
     InstructionKind<Symbol, LoadStoreAddress, ShifterOperandExpression, Expression>;
 
-#[derive(Debug, PartialEq)]
-pub enum Statement {
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub kind: StatementKind,
+    /// the span of the statement, from the start of its mnemonic to the end of its last operand
+    pub span: Span,
+    pub mnemonic: Span,
+    /// the span of each operand, in the order they are written
+    pub operands: Vec<Span>,
+}
+
+/// builds a statement that wasn't parsed from source, so has empty spans
+impl From<StatementKind> for Statement {
+    fn from(kind: StatementKind) -> Statement {
+        Statement {
+            kind,
+            span: Span::default(),
+            mnemonic: Span::default(),
+            operands: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatementKind {
     Instruction { kind: StatementInstructionKind },
     PseudoInstruction { kind: PseudoInstructionKind },
     Directive { kind: DirectiveKind },
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum PseudoInstructionKind {
     LoadRegisterConstant {
         condition: Condition,
@@ -31,31 +57,84 @@ pub enum PseudoInstructionKind {
         destination: Register,
         label: Symbol,
     },
+
+    /// MOV32, which builds the value from a sequence of instructions, so it is resolved by the preprocessor to know its size
+    MoveConstant {
+        condition: Condition,
+        destination: Rd,
+        value: Expression,
+    },
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum DirectiveKind {
-    Definition { kind: DefinitionKind },
+    Definition {
+        kind: DefinitionKind,
+    },
     Align,
-    Origin { address: Expression },
+    Origin {
+        address: Expression,
+    },
     EntryPoint,
-    Constant { value: Expression },
+    Constant {
+        value: Expression,
+    },
+    /// places the following statements in the named section, see [`crate::assembler::Layout`]
+    Section {
+        name: String,
+    },
+    /// places the literal pool of the preceding LDR= pseudo-instructions here
+    LiteralPool,
+    /// a word holding the checksum of the bytes from the `start` label up to the `end` label, which is filled in once the rest of the program is assembled
+    Checksum {
+        kind: ChecksumKind,
+        start: Symbol,
+        end: Symbol,
+    },
 }
 
-#[derive(Debug, PartialEq)]
+/// how a CHECKSUM or CRC32 directive checks its bytes, see [`ChecksumKind::compute`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChecksumKind {
+    /// the sum of the bytes, ignoring overflow
+    Sum,
+    /// the CRC-32 used by zip and Ethernet
+    Crc32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum LoadStoreAddress {
     Expression(Expression),
     AddressingMode(LoadStoreAddressCode<Expression, Expression>),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum DefinitionKind {
-    Space { size: usize, fill: Option<u8> },
-    Bytes { bytes: Vec<BytesDefinition> },
-    Words { words: Vec<u32> },
+    /// the size and fill value are resolved by the preprocessor, so can only refer to symbols defined before the directive
+    Space {
+        size: Expression,
+        fill: Option<Expression>,
+    },
+    Bytes {
+        bytes: Vec<BytesDefinition>,
+    },
+    Words {
+        words: Vec<WordDefinition>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WordDefinition {
+    Word(u32),
+    /// the address of a label, e.g. `DEFW label`
+    Address(Symbol),
+    /// the offset of a label from the word, e.g. `DEFW label - .`
+    Offset(Symbol),
+    /// any other expression, e.g. `DEFW 0x11223344` or `DEFW label + 4`, which is resolved like an operand
+    Expression(Expression),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum BytesDefinition {
     Byte(u8),
     String(String),
@@ -83,7 +162,7 @@ impl Iterator for BytesDefinitionIter {
     type Item = u8;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match &self.bytes {
+        let byte = match &self.bytes {
             BytesDefinition::Byte(byte) => {
                 if self.index == 0 {
                     Some(*byte)
@@ -98,12 +177,32 @@ impl Iterator for BytesDefinitionIter {
                     None
                 }
             }
+        };
+
+        self.index += 1;
+
+        byte
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub span: Span,
+}
+
+/// builds an expression that wasn't parsed from source, so has an empty span
+impl From<ExpressionKind> for Expression {
+    fn from(kind: ExpressionKind) -> Expression {
+        Expression {
+            kind,
+            span: Span::default(),
         }
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum Expression {
+pub enum ExpressionKind {
     Number { base: u32, n: u32 },
     Character(char),
     String(String),
@@ -145,7 +244,7 @@ impl Into<Rs> for Register {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ShifterOperandExpression {
     Immediate(Expression),
     Register(Register),
@@ -153,8 +252,226 @@ pub enum ShifterOperandExpression {
     RotateRightWithExtend(Register),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ShifterOperandShiftAmount {
     Immediate(Expression),
     Register(Register),
 }
+
+impl Statement {
+    /// returns every symbol referenced by the statement, in the order they are written
+    pub fn symbols(&self) -> Vec<&Symbol> {
+        let mut symbols = Vec::new();
+
+        match &self.kind {
+            StatementKind::Instruction { kind } => match kind {
+                InstructionKind::Branch { target, .. }
+                | InstructionKind::BranchWithLinkAndExchange { target, .. } => symbols.push(target),
+                InstructionKind::DataProcessing { kind, .. } => match kind {
+                    DataProcessingKind::Move { shifter, .. }
+                    | DataProcessingKind::Comparison { shifter, .. }
+                    | DataProcessingKind::Calculation { shifter, .. } => {
+                        shifter.collect_symbols(&mut symbols)
+                    }
+                },
+                InstructionKind::MoveToStatusRegister { source, .. } => {
+                    source.collect_symbols(&mut symbols)
+                }
+                InstructionKind::LoadStore { address, .. }
+                | InstructionKind::Preload { address } => match address {
+                    LoadStoreAddress::Expression(expression) => {
+                        expression.collect_symbols(&mut symbols)
+                    }
+                    LoadStoreAddress::AddressingMode(LoadStoreAddressCode { offset, .. }) => {
+                        match &offset.value {
+                            AddressingOffsetValue::Immediate(expression)
+                            | AddressingOffsetValue::ScaledRegister(ShiftedRegister {
+                                amount: expression,
+                                ..
+                            }) => expression.collect_symbols(&mut symbols),
+                            AddressingOffsetValue::Register(_) => (),
+                        }
+                    }
+                },
+                InstructionKind::BranchAndExchange { .. }
+                | InstructionKind::Multiply { .. }
+                | InstructionKind::MultiplyLong { .. }
+                | InstructionKind::LoadStoreMultiple { .. }
+                | InstructionKind::MoveStatusToRegister { .. }
+                | InstructionKind::CountLeadingZeros { .. } => (),
+                InstructionKind::SuperVisorCall { immediate, .. } => {
+                    immediate.collect_symbols(&mut symbols)
+                }
+            },
+            StatementKind::PseudoInstruction { kind } => match kind {
+                PseudoInstructionKind::LoadRegisterConstant { value, .. }
+                | PseudoInstructionKind::MoveConstant { value, .. } => {
+                    value.collect_symbols(&mut symbols)
+                }
+                PseudoInstructionKind::AddressRegister { label, .. } => symbols.push(label),
+            },
+            StatementKind::Directive { kind } => match kind {
+                DirectiveKind::Origin { address } => address.collect_symbols(&mut symbols),
+                DirectiveKind::Constant { value } => value.collect_symbols(&mut symbols),
+                DirectiveKind::Definition {
+                    kind: DefinitionKind::Space { size, fill },
+                } => {
+                    size.collect_symbols(&mut symbols);
+
+                    if let Some(fill) = fill {
+                        fill.collect_symbols(&mut symbols);
+                    }
+                }
+                DirectiveKind::Definition {
+                    kind: DefinitionKind::Words { words },
+                } => {
+                    for word in words {
+                        match word {
+                            WordDefinition::Word(_) => (),
+                            WordDefinition::Address(symbol) | WordDefinition::Offset(symbol) => {
+                                symbols.push(symbol)
+                            }
+                            WordDefinition::Expression(expression) => {
+                                expression.collect_symbols(&mut symbols)
+                            }
+                        }
+                    }
+                }
+                DirectiveKind::Checksum { start, end, .. } => {
+                    symbols.push(start);
+                    symbols.push(end);
+                }
+                DirectiveKind::Definition { .. }
+                | DirectiveKind::Align
+                | DirectiveKind::EntryPoint
+                | DirectiveKind::Section { .. }
+                | DirectiveKind::LiteralPool => (),
+            },
+        }
+
+        symbols
+    }
+
+    /// moves the statement's spans, such as when text is inserted or removed before it
+    pub fn shift(&mut self, bytes: isize, lines: isize) {
+        self.span.shift(bytes, lines);
+        self.mnemonic.shift(bytes, lines);
+
+        for operand in &mut self.operands {
+            operand.shift(bytes, lines);
+        }
+
+        self.kind
+            .for_each_expression_mut(&mut |expression| expression.shift(bytes, lines));
+    }
+}
+
+impl StatementKind {
+    /// calls `f` on every top-level expression in the statement
+    fn for_each_expression_mut(&mut self, f: &mut impl FnMut(&mut Expression)) {
+        match self {
+            StatementKind::Instruction { kind } => match kind {
+                InstructionKind::Branch { .. }
+                | InstructionKind::BranchWithLinkAndExchange { .. }
+                | InstructionKind::BranchAndExchange { .. }
+                | InstructionKind::Multiply { .. }
+                | InstructionKind::MultiplyLong { .. }
+                | InstructionKind::LoadStoreMultiple { .. }
+                | InstructionKind::MoveStatusToRegister { .. }
+                | InstructionKind::CountLeadingZeros { .. } => (),
+                InstructionKind::MoveToStatusRegister {
+                    source: ShifterOperandExpression::Immediate(expression),
+                    ..
+                } => f(expression),
+                InstructionKind::MoveToStatusRegister { .. } => (),
+                InstructionKind::DataProcessing { kind, .. } => match kind {
+                    DataProcessingKind::Move { shifter, .. }
+                    | DataProcessingKind::Comparison { shifter, .. }
+                    | DataProcessingKind::Calculation { shifter, .. } => match shifter {
+                        ShifterOperandExpression::Immediate(expression)
+                        | ShifterOperandExpression::ShiftedRegister(ShiftedRegister {
+                            amount: ShifterOperandShiftAmount::Immediate(expression),
+                            ..
+                        }) => f(expression),
+                        _ => (),
+                    },
+                },
+                InstructionKind::LoadStore { address, .. }
+                | InstructionKind::Preload { address } => match address {
+                    LoadStoreAddress::Expression(expression) => f(expression),
+                    LoadStoreAddress::AddressingMode(LoadStoreAddressCode { offset, .. }) => {
+                        match &mut offset.value {
+                            AddressingOffsetValue::Immediate(expression)
+                            | AddressingOffsetValue::ScaledRegister(ShiftedRegister {
+                                amount: expression,
+                                ..
+                            }) => f(expression),
+                            AddressingOffsetValue::Register(_) => (),
+                        }
+                    }
+                },
+                InstructionKind::SuperVisorCall { immediate, .. } => f(immediate),
+            },
+            StatementKind::PseudoInstruction { kind } => match kind {
+                PseudoInstructionKind::LoadRegisterConstant { value, .. }
+                | PseudoInstructionKind::MoveConstant { value, .. } => f(value),
+                PseudoInstructionKind::AddressRegister { .. } => (),
+            },
+            StatementKind::Directive { kind } => match kind {
+                DirectiveKind::Origin { address } => f(address),
+                DirectiveKind::Constant { value } => f(value),
+                DirectiveKind::Definition {
+                    kind: DefinitionKind::Space { size, fill },
+                } => {
+                    f(size);
+
+                    if let Some(fill) = fill {
+                        f(fill);
+                    }
+                }
+                DirectiveKind::Definition { .. }
+                | DirectiveKind::Align
+                | DirectiveKind::EntryPoint
+                | DirectiveKind::Section { .. }
+                | DirectiveKind::LiteralPool
+                | DirectiveKind::Checksum { .. } => (),
+            },
+        }
+    }
+}
+
+impl ShifterOperandExpression {
+    fn collect_symbols<'a>(&'a self, symbols: &mut Vec<&'a Symbol>) {
+        match self {
+            ShifterOperandExpression::Immediate(expression)
+            | ShifterOperandExpression::ShiftedRegister(ShiftedRegister {
+                amount: ShifterOperandShiftAmount::Immediate(expression),
+                ..
+            }) => expression.collect_symbols(symbols),
+            _ => (),
+        }
+    }
+}
+
+impl Expression {
+    fn collect_symbols<'a>(&'a self, symbols: &mut Vec<&'a Symbol>) {
+        match &self.kind {
+            ExpressionKind::Symbol(symbol) => symbols.push(symbol),
+            ExpressionKind::Diadic(lhs, _, rhs) => {
+                lhs.collect_symbols(symbols);
+                rhs.collect_symbols(symbols);
+            }
+            _ => (),
+        }
+    }
+
+    /// moves the spans of the expression and its subexpressions
+    pub fn shift(&mut self, bytes: isize, lines: isize) {
+        self.span.shift(bytes, lines);
+
+        if let ExpressionKind::Diadic(lhs, _, rhs) = &mut self.kind {
+            lhs.shift(bytes, lines);
+            rhs.shift(bytes, lines);
+        }
+    }
+}
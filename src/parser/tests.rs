@@ -1,6 +1,11 @@
-use crate::ir::Condition;
-use crate::lexer::Lexer;
-use crate::parser::{keywords::MNEMONICS, Line, Parser};
+use crate::ir::{Condition, Rd, Rn};
+use crate::parser::{
+    emit_source, keywords::MNEMONICS, mnemonic_help, AddressingOffsetValue, CalculationKind,
+    DataProcessingKind, DiadicOperator, Expression, ExpressionKind, InstructionKind, Line,
+    LoadStoreAddress, LoadStoreAddressCode, Outline, OutlineItem, OutlineKind, ParseWarning,
+    Parser, SetFlags, ShifterOperandExpression, StatementKind, Symbol,
+};
+use crate::span::Span;
 use std::fs;
 use unicase::UniCase;
 
@@ -41,10 +46,7 @@ fn test_repeated_instructions(b: &mut Bencher) {
 }
 
 fn parse_to_vec(input: &str) -> Vec<Line> {
-    let parser = Parser {
-        lexer: Lexer::new(input),
-        line_count: 0,
-    };
+    let parser = Parser::new(input);
 
     parser
         .map(|x| match x {
@@ -101,10 +103,7 @@ fn bench_load_store_examples(b: &mut Bencher) {
     let input = LOAD_STORE_EXAMPLES;
 
     b.iter(|| {
-        let parser = Parser {
-            lexer: Lexer::new(&input),
-            line_count: 0,
-        };
+        let parser = Parser::new(&input);
 
         for line in parser {
             black_box(line).unwrap();
@@ -621,3 +620,371 @@ fn test_lab4() {
     // from the instruction set specification booklet
     insta::assert_debug_snapshot!(parse_to_vec(LAB4));
 }
+
+#[test]
+fn test_error_span() {
+    let input = "start MOV R0, #1\n\tADD R0, R0, R16\n";
+
+    let error = Parser::new(input)
+        .find_map(Result::err)
+        .expect("R16 should be rejected");
+
+    assert_eq!(error.span, Span::new(30..33, 1, 13));
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "\nError at line 2, token \"R16\": {}\n\tADD R0, R0, R16\n\t            ^^^\n",
+            error.message()
+        )
+    );
+}
+
+#[test]
+fn test_statement_spans() {
+    let input = "loop\tDEFW 4\n\tSTR R0, [R1, #end-loop]!\n";
+
+    let lines = parse_to_vec(input);
+    let statement = lines[1].statement.as_ref().unwrap();
+
+    let text = |span: &Span| &input[span.byte_range.clone()];
+
+    assert_eq!(text(&statement.span), "STR R0, [R1, #end-loop]!");
+    assert_eq!(text(&statement.mnemonic), "STR");
+    assert_eq!(
+        statement.operands.iter().map(text).collect::<Vec<_>>(),
+        ["R0", "[R1, #end-loop]!"]
+    );
+
+    let StatementKind::Instruction {
+        kind:
+            InstructionKind::LoadStore {
+                address: LoadStoreAddress::AddressingMode(LoadStoreAddressCode { offset, .. }),
+                ..
+            },
+    } = &statement.kind
+    else {
+        panic!("expected a load/store instruction");
+    };
+    let AddressingOffsetValue::Immediate(expression) = &offset.value else {
+        panic!("expected an immediate offset");
+    };
+
+    assert_eq!(text(&expression.span), "end-loop");
+    assert_eq!(expression.span.line, 1);
+    assert_eq!(expression.span.column, 15);
+}
+
+#[test]
+fn test_parse_statement() {
+    let statement = Parser::parse_statement("  ADDS R0, R0, #1 ; increment").unwrap();
+
+    assert_eq!(
+        statement.kind,
+        StatementKind::Instruction {
+            kind: InstructionKind::DataProcessing {
+                condition: Condition::AL,
+                kind: DataProcessingKind::Calculation {
+                    kind: CalculationKind::ADD,
+                    set_flags: SetFlags::Set,
+                    destination: Rd(0),
+                    source: Rn(0),
+                    shifter: ShifterOperandExpression::Immediate(Expression {
+                        kind: ExpressionKind::Number { base: 10, n: 1 },
+                        span: Span::new(16..17, 0, 16),
+                    }),
+                },
+            },
+        }
+    );
+
+    assert!(Parser::parse_statement("").is_err());
+    assert!(Parser::parse_statement("label ADD R0, R0, #1").is_err());
+
+    let error = Parser::parse_statement("MOV R0, #1\nMOV R1, #2").unwrap_err();
+    assert_eq!(error.span, Span::new(11..14, 1, 0));
+}
+
+#[test]
+fn test_parse_expression() {
+    let expression = Parser::parse_expression("start + 0x10").unwrap();
+
+    assert_eq!(
+        expression,
+        Expression {
+            kind: ExpressionKind::Diadic(
+                Box::new(Expression {
+                    kind: ExpressionKind::Symbol(Symbol("start".to_owned())),
+                    span: Span::new(0..5, 0, 0),
+                }),
+                DiadicOperator::Plus,
+                Box::new(Expression {
+                    kind: ExpressionKind::Number { base: 16, n: 16 },
+                    span: Span::new(8..12, 0, 8),
+                }),
+            ),
+            span: Span::new(0..12, 0, 0),
+        }
+    );
+
+    let error = Parser::parse_expression("start + R0]").unwrap_err();
+    assert_eq!(error.span, Span::new(10..11, 0, 10));
+}
+
+#[test]
+fn test_mnemonic_help() {
+    // help can be looked up using any spelling of a mnemonic
+    let help = mnemonic_help("addeqs").unwrap();
+
+    assert_eq!(help.mnemonic, "ADD");
+    assert_eq!(help.syntax, "ADD{<cond>}{S} <Rd>, <Rn>, <shifter_operand>");
+    assert_eq!(mnemonic_help("LDMFD"), mnemonic_help("ldm"));
+
+    assert_eq!(mnemonic_help("NOTAMNEMONIC"), None);
+
+    // every mnemonic has help
+    for mnemonic in MNEMONICS.values() {
+        assert!(mnemonic_help(mnemonic.name()).is_some());
+    }
+}
+
+#[test]
+fn test_literal_overflow() {
+    let error = |input| {
+        Parser::new(input)
+            .find_map(Result::err)
+            .expect("the literal should be rejected")
+    };
+
+    let words = error("\tDEFW 1, 99999999999\n");
+    assert_eq!(words.span, Span::new(9..20, 0, 9));
+    assert_eq!(words.message(), "Number is too large to fit in 32 bits");
+
+    let bytes = error("\tDEFB 256\n");
+    assert_eq!(bytes.span, Span::new(6..9, 0, 6));
+    assert_eq!(bytes.message(), "Number is too large to fit in a byte");
+
+    let expression = error("\tMOV R0, #0x100000000\n");
+    assert_eq!(expression.span, Span::new(10..21, 0, 10));
+    assert_eq!(
+        expression.message(),
+        "Number is too large to fit in 32 bits"
+    );
+
+    // the largest word is still accepted
+    assert!(Parser::parse_expression("0xFFFFFFFF").is_ok());
+    assert!(Parser::parse_expression("4294967295").is_ok());
+}
+
+#[test]
+fn test_n_base_number() {
+    let number = |input| Parser::parse_expression(input).map(|expression| expression.kind);
+
+    assert_eq!(
+        number("16_FF").unwrap(),
+        ExpressionKind::Number { base: 16, n: 255 }
+    );
+    assert_eq!(
+        number("2_1010").unwrap(),
+        ExpressionKind::Number { base: 2, n: 10 }
+    );
+    assert_eq!(
+        number("36_Zz").unwrap(),
+        ExpressionKind::Number {
+            base: 36,
+            n: 35 * 36 + 35
+        }
+    );
+
+    let error = |input| number(input).unwrap_err();
+
+    assert_eq!(
+        error("8_19").message(),
+        "Invalid digit for the base of the number"
+    );
+    assert_eq!(error("8_19").span, Span::new(0..4, 0, 0));
+    assert_eq!(
+        error("37_1").message(),
+        "The base of a number must be between 2 and 36"
+    );
+    assert_eq!(
+        error("1_0").message(),
+        "The base of a number must be between 2 and 36"
+    );
+    assert_eq!(error("16_").message(), "Expected the digits of the number");
+}
+
+#[test]
+fn test_error_line() {
+    // trailing whitespace and blank lines still end lines
+    let input = "\tMOV R0, #1  \n   \n\n\tADD R0, R0, R16 ; comment\n";
+
+    let lines: Vec<_> = Parser::new(input).collect();
+
+    assert!(lines[..3].iter().all(Result::is_ok));
+
+    let error = lines[3].as_ref().unwrap_err();
+
+    assert_eq!(error.span, Span::new(32..35, 3, 13));
+    assert!(error.to_string().contains("line 4"));
+}
+
+#[test]
+fn test_constant_named_like_mnemonic() {
+    let lines = parse_to_vec("b EQU 4\n\tB b\n");
+
+    assert_eq!(lines[0].label.as_deref(), Some("b"));
+    assert!(matches!(
+        lines[0].statement.as_ref().unwrap().kind,
+        StatementKind::Directive { .. }
+    ));
+    assert_eq!(lines[1].label, None);
+}
+
+#[test]
+fn test_register_list_validation() {
+    let error = Parser::parse_statement("LDMIA R0, { }").unwrap_err();
+    assert_eq!(error.span, Span::new(12..13, 0, 12));
+    assert_eq!(error.message(), "Register List can't be empty");
+
+    let line = Parser::parse_line("\tSTMFD SP!, {R5-R2, LR}", 0, 0).unwrap();
+    let warnings: Vec<_> = line.warnings.iter().map(ParseWarning::message).collect();
+    assert_eq!(
+        warnings,
+        ["Register List range is written in descending order"]
+    );
+    assert_eq!(line.warnings[0].span, Span::new(13..18, 0, 13));
+
+    // the range still means the registers between its ends
+    match line.statement.unwrap().kind {
+        StatementKind::Instruction {
+            kind: InstructionKind::LoadStoreMultiple { register_list, .. },
+        } => assert_eq!(register_list.to_string(), "{R2-R5, LR}"),
+        kind => panic!("expected an LDM or STM, found {kind:?}"),
+    }
+
+    let line = Parser::parse_line("\tLDMFD R0!, {R0-R3}", 0, 0).unwrap();
+    assert_eq!(line.warnings.len(), 1);
+    assert_eq!(line.warnings[0].span, Span::new(12..19, 0, 12));
+    assert_eq!(
+        line.warnings[0].message(),
+        "The base register is in the Register List with write-back, so its final value is unpredictable"
+    );
+
+    // without write-back, the base can be loaded
+    let line = Parser::parse_line("\tLDMFD R0, {R0-R3}", 0, 0).unwrap();
+    assert_eq!(line.warnings, []);
+}
+
+#[test]
+fn test_outline() {
+    let source = "SIZE\tEQU\t4
+\tSECTION\tcode
+main\tMOV\tR0, #SIZE
+\tBL\tprint
+
+; prints R0
+print\tSVC\t1
+\tMOV\tPC, LR
+\tSECTION\tdata
+table\tDEFW\t1
+\tDEFW\t2
+broken\tMOV\tR0,
+";
+
+    let outline = Outline::parse(source);
+
+    let summary = |items: &[OutlineItem]| {
+        items
+            .iter()
+            .map(|item| (item.name.clone(), item.kind, item.span.line, item.last_line))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        summary(&outline.items),
+        [
+            ("SIZE".to_owned(), OutlineKind::Constant, 0, 0),
+            ("code".to_owned(), OutlineKind::Section, 1, 7),
+            ("data".to_owned(), OutlineKind::Section, 8, 10),
+        ]
+    );
+    assert_eq!(
+        summary(&outline.items[1].children),
+        [
+            ("main".to_owned(), OutlineKind::Routine, 2, 3),
+            ("print".to_owned(), OutlineKind::Routine, 6, 7),
+        ]
+    );
+    assert_eq!(
+        summary(&outline.items[2].children),
+        [("table".to_owned(), OutlineKind::Data, 9, 10)]
+    );
+
+    let print = &outline.items[1].children[1];
+    assert_eq!(&source[print.selection.byte_range.clone()], "print");
+    assert_eq!(
+        &source[print.span.byte_range.clone()],
+        "print\tSVC\t1\n\tMOV\tPC, LR"
+    );
+
+    assert_eq!(
+        outline.folding_ranges(),
+        [(1, 7), (2, 3), (6, 7), (8, 10), (9, 10)]
+    );
+}
+
+#[test]
+fn test_emit_source() {
+    let source = "start\tADDEQS R0, R1, #0x10
+\tMVN R2, R3, LSL #2
+\tCMP R0, R1, ROR R2
+\tMOV R4, R5, RRX
+\tLDRB R0, [R1, #-4]!
+\tSTR R0, [R1], -R2, LSL #2
+\tldr r0, [sp]
+\tLDR R1, table + 4
+\tLDMFD SP!, {R0-R3, LR}
+\tBLNE start
+\tSVC 2_101
+\tLDR R0, =table
+\tADRL R1, start
+\tMOV32 R2, #0x12345678 ; a comment
+table\tDEFW 1, 2
+\tDEFB \"hi\", 0
+\tDEFS 8, 1
+size\tEQU 'a' - 1
+
+\tALIGN
+\tLTORG";
+
+    let emitted = emit_source(&parse_to_vec(source));
+
+    assert_eq!(
+        emitted,
+        "start\tADDEQS R0, R1, #0x10
+\tMVN R2, R3, LSL #2
+\tCMP R0, R1, ROR R2
+\tMOV R4, R5, RRX
+\tLDRB R0, [R1, #-4]!
+\tSTR R0, [R1], -R2, LSL #2
+\tLDR R0, [SP]
+\tLDR R1, table + 4
+\tLDMIA SP!, {R0-R3, LR}
+\tBLNE start
+\tSVC 2_101
+\tLDR R0, =table
+\tADRL R1, start
+\tMOV32 R2, #0x12345678
+table\tDEFW 1, 2
+\tDEFB \"hi\", 0
+\tDEFS 8, 1
+size\tEQU 'a' - 1
+
+\tALIGN
+\tLTORG
+"
+    );
+
+    // the emitted source parses back into the same statements
+    assert_eq!(emit_source(&parse_to_vec(&emitted)), emitted);
+}
@@ -0,0 +1,170 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use crate::parser::{
+    DataProcessingKind, DefinitionKind, DirectiveKind, ExpressionKind, MoveKind,
+    PseudoInstructionKind, SetFlags, ShifterOperandExpression, StatementInstructionKind, Symbol,
+};
+use crate::span::Span;
+
+use super::{PreProcessResult, PreProcessedStatement};
+
+impl PreProcessResult {
+    /// writes the preprocessed statements back into assembly source, which assembles into the same program
+    ///
+    /// Addresses that were decided by the preprocessor are kept: statements that don't follow on from the one before start with an `ORIGIN`,
+    /// LDR= pseudo-instructions load their literals from an `LTORG` where their pool was placed, and a branch over a pool is a `B` to a label after it.
+    /// Data is written as `DEFB`, `DEFS` or `DEFW`, and symbols that aren't labels are defined with `EQU` at the end, so LDR= pseudo-instructions that loaded them from a literal pool still do.
+    pub fn emit_source(&self) -> String {
+        let mut source = String::new();
+
+        let addresses: Vec<usize> = self
+            .statements
+            .iter()
+            .map(|statement| statement.address)
+            .collect();
+        let end = self
+            .statements
+            .last()
+            .map(|statement| statement.address + statement.statement.size());
+
+        // labels are inserted by the preprocessor as plain numbers, without a span
+        let mut labels: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+        let mut constants: Vec<(&Symbol, String)> = Vec::new();
+
+        for (symbol, value) in self.symbol_table.iter() {
+            match value.kind {
+                ExpressionKind::Number { base: 10, n }
+                    if value.span == Span::default()
+                        && (addresses.contains(&(n as usize)) || end == Some(n as usize)) =>
+                {
+                    labels.entry(n as usize).or_default().push(symbol.0.clone())
+                }
+                _ => constants.push((symbol, value.to_string())),
+            }
+        }
+
+        // the values of the literals, by address
+        let mut literals = HashMap::new();
+
+        for statement in &self.statements {
+            if let PreProcessedStatement::LiteralPool(values) = &statement.statement {
+                for (i, value) in values.iter().enumerate() {
+                    literals.insert(statement.address + 4 * i, value);
+                }
+            }
+        }
+
+        // the targets of branches over literal pools, which need a label if they don't have one
+        for statement in &self.statements {
+            if let PreProcessedStatement::Skip { target } = statement.statement {
+                labels
+                    .entry(target)
+                    .or_insert_with(|| vec![format!("skip_{target:X}")]);
+            }
+        }
+
+        let mut next = 0;
+        let first = addresses.first().copied().unwrap_or(0);
+
+        for statement in &self.statements {
+            if statement.address != next {
+                writeln!(source, "\tORIGIN {:#X}", statement.address).unwrap();
+            }
+
+            for label in labels.remove(&statement.address).unwrap_or_default() {
+                writeln!(source, "{label}").unwrap();
+            }
+
+            if statement.address == self.entry_point && self.entry_point != first {
+                writeln!(source, "\tENTRY").unwrap();
+            }
+
+            let text = match &statement.statement {
+                PreProcessedStatement::Instruction { kind } => kind.to_string(),
+                // a known value that fits in an immediate, which is loaded by a MOV
+                PreProcessedStatement::PseudoInstruction {
+                    kind:
+                        PseudoInstructionKind::LoadRegisterConstant {
+                            condition,
+                            destination,
+                            value,
+                        },
+                } => StatementInstructionKind::DataProcessing {
+                    condition: *condition,
+                    kind: DataProcessingKind::Move {
+                        kind: MoveKind::Move,
+                        set_flags: SetFlags::DontSet,
+                        destination: *destination,
+                        shifter: ShifterOperandExpression::Immediate(value.clone()),
+                    },
+                }
+                .to_string(),
+                PreProcessedStatement::PseudoInstruction { kind } => kind.to_string(),
+                PreProcessedStatement::Data(bytes) | PreProcessedStatement::Padding(bytes) => {
+                    data(bytes)
+                }
+                PreProcessedStatement::LoadLiteral {
+                    condition,
+                    destination,
+                    literal,
+                } => PseudoInstructionKind::LoadRegisterConstant {
+                    condition: *condition,
+                    destination: *destination,
+                    value: literals[literal].clone(),
+                }
+                .to_string(),
+                PreProcessedStatement::Words(words) => DirectiveKind::Definition {
+                    kind: DefinitionKind::Words {
+                        words: words.clone(),
+                    },
+                }
+                .to_string(),
+                PreProcessedStatement::LiteralPool(_) => "LTORG".to_owned(),
+                PreProcessedStatement::Skip { target } => format!("B {}", labels[target][0]),
+                PreProcessedStatement::Checksum { kind, start, end } => DirectiveKind::Checksum {
+                    kind: *kind,
+                    start: start.clone(),
+                    end: end.clone(),
+                }
+                .to_string(),
+            };
+
+            if !text.is_empty() {
+                writeln!(source, "\t{text}").unwrap();
+            }
+
+            next = statement.address + statement.statement.size();
+        }
+
+        // labels at the end of the program
+        for label in labels.into_values().flatten() {
+            writeln!(source, "{label}").unwrap();
+        }
+
+        constants.sort_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+
+        for (symbol, value) in constants {
+            writeln!(source, "{}\tEQU {value}", symbol.0).unwrap();
+        }
+
+        source
+    }
+}
+
+/// writes data as a `DEFS` if every byte is the same, or a `DEFB` of its bytes
+fn data(bytes: &[u8]) -> String {
+    match bytes {
+        [] => String::new(),
+        [fill, rest @ ..] if !rest.is_empty() && rest.iter().all(|byte| byte == fill) => match fill
+        {
+            0 => format!("DEFS {}", bytes.len()),
+            fill => format!("DEFS {}, {fill}", bytes.len()),
+        },
+        bytes => {
+            let bytes: Vec<String> = bytes.iter().map(|byte| byte.to_string()).collect();
+
+            format!("DEFB {}", bytes.join(", "))
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use crate::{
+    ir::{Condition, Rd},
+    parser::Expression,
+    span::Span,
+};
+
+use super::{PreProcessError, PreProcessedStatement, PreProcessor};
+
+/// the furthest a literal can be after the LDR that loads it, which is the largest 12-bit load offset
+const RANGE: usize = 4095;
+
+/// a block of words holding the values of LDR= pseudo-instructions that can't be a MOV
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiteralPool {
+    /// the address of the first literal
+    pub address: usize,
+    /// the number of literals, which are a word each
+    pub literals: usize,
+    pub placement: PoolPlacement,
+}
+
+/// why a literal pool was placed where it is
+#[derive(Debug, Clone, PartialEq)]
+pub enum PoolPlacement {
+    /// by the LTORG directive on this line
+    Directive { source_line: usize },
+    /// before the statement on `source_line`, with a branch over it, because the statement would put the literal of an earlier LDR= out of range
+    OutOfRange {
+        source_line: usize,
+        /// the line of the LDR= whose literal would have been out of range
+        instruction_line: usize,
+        /// the span of the LDR=
+        instruction: Span,
+    },
+    /// before the ORIGIN or SECTION directive on this line, which moves to another address
+    Moved { source_line: usize },
+    /// at the end of the program
+    End,
+}
+
+/// an LDR= whose literal hasn't been placed yet
+#[derive(Debug)]
+pub(super) struct PendingLiteral {
+    value: Expression,
+    /// the index of the LDR= in the preprocessed statements
+    statement: usize,
+    address: usize,
+    source_line: usize,
+    span: Span,
+}
+
+impl PreProcessor {
+    /// inserts an LDR= that loads its value from the next literal pool
+    pub(super) fn insert_literal_load(
+        &mut self,
+        condition: Condition,
+        destination: Rd,
+        value: Expression,
+        source_line: usize,
+        span: &Span,
+    ) -> Result<(), PreProcessError> {
+        // the address of the literal is filled in when the pool is placed
+        self.insert_addressed_statement(
+            PreProcessedStatement::LoadLiteral {
+                condition,
+                destination,
+                literal: 0,
+            },
+            4,
+            source_line,
+            span,
+        )?;
+
+        self.literals.push(PendingLiteral {
+            value,
+            statement: self.statements.len() - 1,
+            address: self.address - 4,
+            source_line,
+            span: span.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// places the pending literals before a statement of `size` bytes, if the statement would put any of them out of range
+    pub(super) fn make_room(
+        &mut self,
+        size: usize,
+        source_line: usize,
+        span: &Span,
+    ) -> Result<(), PreProcessError> {
+        let Some(first) = self.literals.first() else {
+            return Ok(());
+        };
+
+        // the pool after the statement would also need a branch over it
+        if (self.address + size).next_multiple_of(4) + 4 - first.address <= RANGE {
+            return Ok(());
+        }
+
+        let placement = PoolPlacement::OutOfRange {
+            source_line,
+            instruction_line: first.source_line,
+            instruction: first.span.clone(),
+        };
+
+        self.place_literals(placement, true, source_line, span)
+    }
+
+    /// places the literals that are still pending at the end of the program, mapping the pool to the last LDR=
+    pub(super) fn place_remaining_literals(&mut self) -> Result<(), PreProcessError> {
+        let Some(last) = self.literals.last() else {
+            return Ok(());
+        };
+        let (source_line, span) = (last.source_line, last.span.clone());
+
+        self.place_literals(PoolPlacement::End, false, source_line, &span)
+    }
+
+    /// places the pending literals in a pool at the current address, optionally with a branch over it, mapping the pool to `source_line`
+    pub(super) fn place_literals(
+        &mut self,
+        placement: PoolPlacement,
+        skip: bool,
+        source_line: usize,
+        span: &Span,
+    ) -> Result<(), PreProcessError> {
+        if self.literals.is_empty() {
+            return Ok(());
+        }
+
+        let start = self.address;
+        let address = self.address.next_multiple_of(4) + if skip { 4 } else { 0 };
+        let literals: Vec<PendingLiteral> = self.literals.drain(..).collect();
+        let end = address + literals.len() * 4;
+
+        self.check_region(end - start, span)?;
+
+        self.source_map.insert_generated(start..end, source_line);
+
+        if !start.is_multiple_of(4) {
+            let padding = (start..start.next_multiple_of(4))
+                .map(|address| self.padding.byte(address))
+                .collect();
+
+            self.append_statement(
+                PreProcessedStatement::Padding(padding),
+                start.next_multiple_of(4) - start,
+                source_line,
+                span,
+            );
+        }
+
+        if skip {
+            self.append_statement(
+                PreProcessedStatement::Skip { target: end },
+                4,
+                source_line,
+                span,
+            );
+        }
+
+        for (index, pending) in literals.iter().enumerate() {
+            if let PreProcessedStatement::LoadLiteral { literal, .. } =
+                &mut self.statements[pending.statement].statement
+            {
+                *literal = address + index * 4;
+            }
+        }
+
+        self.append_statement(
+            PreProcessedStatement::LiteralPool(
+                literals.into_iter().map(|pending| pending.value).collect(),
+            ),
+            end - address,
+            source_line,
+            span,
+        );
+
+        self.literal_pools.push(LiteralPool {
+            address,
+            literals: (end - address) / 4,
+            placement,
+        });
+
+        Ok(())
+    }
+}
@@ -1,29 +1,149 @@
-use std::collections::HashMap;
-use std::iter;
+use std::{collections::HashMap, iter};
 
 use smallvec::SmallVec;
 
-use crate::assembler::AssemblyError;
+use crate::assembler::{AssemblyError, Layout, MemoryRegion, Padding, SourceMap};
+use crate::ir::{Condition, Rd, RotatedImm8};
 use crate::parser::{
-    DefinitionKind, DirectiveKind, Expression, Line, ParseError, Parser, PseudoInstructionKind,
-    Statement, StatementInstructionKind, Symbol,
+    ChecksumKind, DefinitionKind, DirectiveKind, Expression, ExpressionKind, Line, ParseError,
+    Parser, PseudoInstructionKind, StatementInstructionKind, StatementKind, Symbol, WordDefinition,
 };
-use crate::resolver::SymbolTable;
+use crate::resolver::{synthesise_constant, SymbolCase, SymbolTable};
+use crate::span::Span;
 
+pub use literal_pool::{LiteralPool, PoolPlacement};
+
+use literal_pool::PendingLiteral;
+
+mod emit;
+mod literal_pool;
 mod tests;
 
 #[derive(Debug)]
 pub enum PreProcessedStatement {
-    Instruction { kind: StatementInstructionKind },
-    PseudoInstruction { kind: PseudoInstructionKind },
+    Instruction {
+        kind: StatementInstructionKind,
+    },
+    PseudoInstruction {
+        kind: PseudoInstructionKind,
+    },
     // most data definitions will be a single word, so we can increase the performance by using a small vector, which doesn't allocate until its length is greater than 4
     Data(SmallVec<[u8; 4]>),
+    /// the words of a DEFW, which may refer to labels, so are resolved once their addresses are known
+    Words(Vec<WordDefinition>),
+    /// the bytes filling the gap left by an ALIGN directive
+    Padding(SmallVec<[u8; 4]>),
+    /// an LDR= that loads its value from the literal at address `literal`
+    LoadLiteral {
+        condition: Condition,
+        destination: Rd,
+        literal: usize,
+    },
+    /// the values of a literal pool, which are a word each
+    LiteralPool(Vec<Expression>),
+    /// an unconditional branch over a literal pool, to `target`
+    Skip {
+        target: usize,
+    },
+    /// a word to fill with the checksum of the bytes from `start` up to `end`, once they have been assembled
+    Checksum {
+        kind: ChecksumKind,
+        start: Symbol,
+        end: Symbol,
+    },
+}
+
+impl PreProcessedStatement {
+    /// the number of bytes the statement takes up
+    pub fn size(&self) -> usize {
+        match self {
+            PreProcessedStatement::Instruction { .. }
+            | PreProcessedStatement::LoadLiteral { .. }
+            | PreProcessedStatement::Skip { .. }
+            | PreProcessedStatement::Checksum { .. } => 4,
+            PreProcessedStatement::PseudoInstruction { kind } => match kind {
+                PseudoInstructionKind::AddressRegister { long: true, .. } => 8,
+                PseudoInstructionKind::AddressRegister { long: false, .. }
+                | PseudoInstructionKind::LoadRegisterConstant { .. } => 4,
+                // the value of a MOV32 is folded by the preprocessor
+                PseudoInstructionKind::MoveConstant {
+                    condition,
+                    destination,
+                    value,
+                } => match value.kind {
+                    ExpressionKind::Number { n, .. } => {
+                        4 * synthesise_constant(*condition, *destination, n).len()
+                    }
+                    _ => 4,
+                },
+            },
+            PreProcessedStatement::Data(bytes) | PreProcessedStatement::Padding(bytes) => {
+                bytes.len()
+            }
+            PreProcessedStatement::LiteralPool(values) => 4 * values.len(),
+            PreProcessedStatement::Words(words) => 4 * words.len(),
+        }
+    }
+}
+
+/// a preprocessed statement, along with its address and the source line that generated it
+#[derive(Debug)]
+pub struct AddressedStatement {
+    pub address: usize,
+    pub statement: PreProcessedStatement,
+    pub span: Span,
+    pub source_line: usize,
 }
 
 #[derive(Debug)]
 pub enum PreProcessError {
     ParseError(ParseError),
-    OriginAddressError,
+    /// the address of an ORIGIN directive couldn't be resolved from the symbols defined before it
+    OriginAddressError {
+        span: Span,
+    },
+    /// the size or fill value of a DEFS directive couldn't be resolved from the symbols defined before it
+    SpaceExpressionError {
+        span: Span,
+    },
+    /// the value of a MOV32 couldn't be resolved from the symbols defined before it
+    ConstantExpressionError {
+        span: Span,
+    },
+    /// the size of a DEFS directive is negative, or the block would extend past the end of memory
+    SpaceSizeOutOfRange {
+        span: Span,
+        size: u32,
+    },
+    /// the fill value of a DEFS directive doesn't fit in a byte
+    SpaceFillOutOfRange {
+        span: Span,
+        fill: u32,
+    },
+    /// a SECTION directive names a section that the layout doesn't place in a region
+    UnplacedSection {
+        span: Span,
+        section: String,
+    },
+    /// a statement in a section doesn't fit in the region the section is placed in
+    OutsideRegion {
+        span: Span,
+        region: String,
+    },
+    /// there is more than one ENTRY directive
+    MultipleEntryPoints {
+        span: Span,
+        /// the span of the first ENTRY directive
+        previous: Span,
+    },
+    /// an ENTRY directive is followed by data, which can't be executed
+    EntryPointInData {
+        span: Span,
+    },
+    /// an ENTRY directive isn't followed by anything, e.g. it is at the end of the program
+    EntryPointWithoutCode {
+        span: Span,
+    },
 }
 
 impl<'a> Parser<'a> {
@@ -32,25 +152,44 @@ impl<'a> Parser<'a> {
     }
 }
 
-struct PreProcessor {
-    /// a pair of an address and a statement
-    statements: Vec<(usize, PreProcessedStatement)>,
+pub struct PreProcessor {
+    statements: Vec<AddressedStatement>,
     symbol_table: SymbolTable<Expression>,
     entry_point: usize,
-    /// maps the address of a statement to the source line that generated it
-    pub source_map: HashMap<usize, usize>,
+    /// the span of the ENTRY directive, if there is one
+    entry_span: Option<Span>,
+    /// maps addresses to the source lines that generated them
+    pub source_map: SourceMap,
     address: usize,
     /// a queue of labels to be inserted into the symbol table at the next address
     label_queue: Vec<String>,
+    layout: Layout,
+    /// the region of the current section, if a SECTION directive has been used
+    region: Option<MemoryRegion>,
+    /// the next free address of each region that has been left, by name
+    region_addresses: HashMap<String, usize>,
+    padding: Padding,
+    /// the LDR= pseudo-instructions waiting for a literal pool
+    literals: Vec<PendingLiteral>,
+    literal_pools: Vec<LiteralPool>,
+    /// whether LDR= builds known values with instructions, rather than loading them from a literal pool
+    synthesise_constants: bool,
 }
 
 #[derive(Debug)]
 pub struct PreProcessResult {
-    pub statements: Vec<(usize, PreProcessedStatement)>,
+    pub statements: Vec<AddressedStatement>,
     pub symbol_table: SymbolTable<Expression>,
     pub entry_point: usize,
-    /// maps the address of a statement to the source line that generated it
-    pub source_map: HashMap<usize, usize>,
+    /// maps addresses to the source lines that generated them
+    pub source_map: SourceMap,
+    pub literal_pools: Vec<LiteralPool>,
+}
+
+impl Default for PreProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PreProcessor {
@@ -59,17 +198,59 @@ impl PreProcessor {
             statements: Vec::new(),
             symbol_table: SymbolTable::new(),
             entry_point: 0,
-            source_map: HashMap::new(),
+            entry_span: None,
+            source_map: SourceMap::new(),
             address: 0,
             label_queue: Vec::new(),
+            layout: Layout::new(),
+            region: None,
+            region_addresses: HashMap::new(),
+            padding: Padding::default(),
+            literals: Vec::new(),
+            literal_pools: Vec::new(),
+            synthesise_constants: false,
+        }
+    }
+
+    /// a preprocessor whose symbol table matches symbols with the given case sensitivity
+    pub fn with_symbol_case(case: SymbolCase) -> Self {
+        Self {
+            symbol_table: SymbolTable::with_case(case),
+            ..Self::new()
+        }
+    }
+
+    /// places the sections named by SECTION directives using a layout
+    pub fn with_layout(self, layout: Layout) -> Self {
+        Self { layout, ..self }
+    }
+
+    /// fills the gaps left by ALIGN directives with the given padding
+    pub fn with_padding(self, padding: Padding) -> Self {
+        Self { padding, ..self }
+    }
+
+    /// builds the values of LDR= pseudo-instructions that are known where they are used with instructions, like MOV32, rather than loading them from a literal pool
+    pub fn with_constant_synthesis(self, synthesise_constants: bool) -> Self {
+        Self {
+            synthesise_constants,
+            ..self
         }
     }
 
     pub fn run<'a>(
-        mut self,
+        self,
         lines: impl Iterator<Item = Result<Line, ParseError>> + 'a,
     ) -> Result<PreProcessResult, PreProcessError> {
-        for (source_line, line) in lines.enumerate() {
+        self.run_numbered(lines.enumerate())
+    }
+
+    /// like [`PreProcessor::run`], but with the source line number of each line given, so lines can be processed in a different order to their numbers
+    pub fn run_numbered<'a>(
+        mut self,
+        lines: impl Iterator<Item = (usize, Result<Line, ParseError>)> + 'a,
+    ) -> Result<PreProcessResult, PreProcessError> {
+        for (source_line, line) in lines {
             match line {
                 Err(e) => return Err(PreProcessError::ParseError(e)),
 
@@ -83,44 +264,125 @@ impl PreProcessor {
                     match line.statement {
                         None => (),
 
-                        Some(statement) => match statement {
-                            Statement::Instruction { kind } => self.insert_addressed_statement(
-                                PreProcessedStatement::Instruction { kind },
-                                4,
-                                source_line,
-                            ),
+                        Some(statement) => match statement.kind {
+                            StatementKind::Instruction { kind } => self
+                                .insert_addressed_statement(
+                                    PreProcessedStatement::Instruction { kind },
+                                    4,
+                                    source_line,
+                                    &line.span,
+                                )?,
 
-                            Statement::PseudoInstruction { kind } => match kind {
+                            StatementKind::PseudoInstruction { kind } => match kind {
                                 PseudoInstructionKind::AddressRegister { long, .. } => self
                                     .insert_addressed_statement(
                                         PreProcessedStatement::PseudoInstruction { kind },
                                         if long { 8 } else { 4 },
                                         source_line,
-                                    ),
+                                        &line.span,
+                                    )?,
 
-                                PseudoInstructionKind::LoadRegisterConstant { .. } => self
-                                    .insert_addressed_statement(
-                                        PreProcessedStatement::PseudoInstruction { kind },
-                                        4,
+                                PseudoInstructionKind::LoadRegisterConstant {
+                                    condition,
+                                    destination,
+                                    value,
+                                } => match value.backwards_resolve(&self.symbol_table) {
+                                    // a value known here that fits in an immediate is loaded by a MOV
+                                    Ok(n) if RotatedImm8::try_from(n).is_ok() => self
+                                        .insert_addressed_statement(
+                                            PreProcessedStatement::PseudoInstruction {
+                                                kind: PseudoInstructionKind::LoadRegisterConstant {
+                                                    condition,
+                                                    destination,
+                                                    value,
+                                                },
+                                            },
+                                            4,
+                                            source_line,
+                                            &line.span,
+                                        )?,
+
+                                    // and other known values can be built like a MOV32, rather than using a literal pool
+                                    Ok(n) if self.synthesise_constants => self
+                                        .insert_move_constant(
+                                            condition,
+                                            destination,
+                                            n,
+                                            source_line,
+                                            &line.span,
+                                        )?,
+
+                                    _ => self.insert_literal_load(
+                                        condition,
+                                        destination,
+                                        value,
                                         source_line,
-                                    ),
+                                        &line.span,
+                                    )?,
+                                },
+
+                                PseudoInstructionKind::MoveConstant {
+                                    condition,
+                                    destination,
+                                    value,
+                                } => {
+                                    let n = value.backwards_resolve(&self.symbol_table).map_err(
+                                        |_| PreProcessError::ConstantExpressionError {
+                                            span: value.span.clone(),
+                                        },
+                                    )?;
+
+                                    self.insert_move_constant(
+                                        condition,
+                                        destination,
+                                        n,
+                                        source_line,
+                                        &line.span,
+                                    )?;
+                                }
                             },
 
                             // we need to apply assembler directives
-                            Statement::Directive { kind } => match kind {
+                            StatementKind::Directive { kind } => match kind {
+                                DirectiveKind::Definition {
+                                    kind: DefinitionKind::Words { words },
+                                } => {
+                                    let size = 4 * words.len();
+
+                                    self.insert_addressed_statement(
+                                        PreProcessedStatement::Words(words),
+                                        size,
+                                        source_line,
+                                        &line.span,
+                                    )?;
+                                }
+
                                 DirectiveKind::Definition { kind } => {
                                     let bytes: SmallVec<[u8; 4]> = match kind {
                                         DefinitionKind::Space { size, fill } => {
-                                            iter::repeat(fill.unwrap_or(0)).take(size).collect()
+                                            // labels on or just before the directive can be used in its expressions
+                                            let labels = self.label_queue.clone();
+                                            self.insert_labels();
+
+                                            let (size, fill) = self.space(&size, fill.as_ref())?;
+
+                                            // a literal pool placed before the block moves its labels too
+                                            let address = self.address;
+                                            self.make_room(size, source_line, &line.span)?;
+
+                                            if self.address != address {
+                                                self.label_queue = labels;
+                                            }
+
+                                            iter::repeat(fill).take(size).collect()
                                         }
                                         DefinitionKind::Bytes { bytes } => bytes
                                             .into_iter()
                                             // use flat map with an inner iterator to avoid unnecessary allocations
                                             .flat_map(|bytes| bytes.into_iter())
                                             .collect(),
-                                        DefinitionKind::Words { words } => {
-                                            words.into_iter().flat_map(u32::to_be_bytes).collect()
-                                        }
+                                        // DEFW is handled above, as its words are resolved once the labels have addresses
+                                        DefinitionKind::Words { .. } => unreachable!(),
                                     };
 
                                     let size = bytes.len();
@@ -129,11 +391,26 @@ impl PreProcessor {
                                         PreProcessedStatement::Data(bytes),
                                         size,
                                         source_line,
-                                    );
+                                        &line.span,
+                                    )?;
                                 }
 
                                 DirectiveKind::Align => {
-                                    self.address += 4 - (self.address % 4);
+                                    self.make_room(3, source_line, &line.span)?;
+
+                                    let end = self.address.next_multiple_of(4);
+
+                                    let bytes = (self.address..end)
+                                        .map(|address| self.padding.byte(address))
+                                        .collect();
+
+                                    // labels before the directive are for the aligned address, so they stay queued
+                                    self.push_statement(
+                                        PreProcessedStatement::Padding(bytes),
+                                        end - self.address,
+                                        source_line,
+                                        &line.span,
+                                    )?;
                                 }
 
                                 DirectiveKind::Constant { value } => {
@@ -143,15 +420,63 @@ impl PreProcessor {
                                 }
 
                                 DirectiveKind::Origin { address } => {
+                                    self.place_literals(
+                                        PoolPlacement::Moved { source_line },
+                                        false,
+                                        source_line,
+                                        &line.span,
+                                    )?;
+
                                     self.address =
                                         match address.backwards_resolve(&self.symbol_table) {
                                             Ok(n) => n as usize,
-                                            _ => return Err(PreProcessError::OriginAddressError),
+                                            _ => {
+                                                return Err(PreProcessError::OriginAddressError {
+                                                    span: line.span,
+                                                })
+                                            }
                                         };
                                 }
 
                                 DirectiveKind::EntryPoint => {
+                                    if let Some(previous) = self.entry_span.take() {
+                                        return Err(PreProcessError::MultipleEntryPoints {
+                                            span: line.span,
+                                            previous,
+                                        });
+                                    }
+
                                     self.entry_point = self.address;
+                                    self.entry_span = Some(line.span);
+                                }
+
+                                DirectiveKind::Section { name } => {
+                                    self.place_literals(
+                                        PoolPlacement::Moved { source_line },
+                                        false,
+                                        source_line,
+                                        &line.span,
+                                    )?;
+
+                                    self.enter_section(name, line.span)?;
+                                }
+
+                                DirectiveKind::LiteralPool => {
+                                    self.place_literals(
+                                        PoolPlacement::Directive { source_line },
+                                        false,
+                                        source_line,
+                                        &line.span,
+                                    )?;
+                                }
+
+                                DirectiveKind::Checksum { kind, start, end } => {
+                                    self.insert_addressed_statement(
+                                        PreProcessedStatement::Checksum { kind, start, end },
+                                        4,
+                                        source_line,
+                                        &line.span,
+                                    )?;
                                 }
                             },
                         },
@@ -160,33 +485,203 @@ impl PreProcessor {
             }
         }
 
+        self.place_remaining_literals()?;
+        self.check_entry_point()?;
+
         Ok(PreProcessResult {
             statements: self.statements,
             symbol_table: self.symbol_table,
             entry_point: self.entry_point,
             source_map: self.source_map,
+            literal_pools: self.literal_pools,
         })
     }
 
-    fn insert_addressed_statement(
-        &mut self,
-        statement: PreProcessedStatement,
-        size: usize,
-        source_line: usize,
-    ) {
+    /// checks that the ENTRY directive, if there is one, is followed by an instruction
+    fn check_entry_point(&self) -> Result<(), PreProcessError> {
+        let Some(span) = &self.entry_span else {
+            return Ok(());
+        };
+
+        // empty statements, like `DEFS 0`, don't say what is at the address
+        match self.statements.iter().find(|statement| {
+            statement.address == self.entry_point
+                && !matches!(&statement.statement, PreProcessedStatement::Data(bytes) if bytes.is_empty())
+        }) {
+            Some(AddressedStatement {
+                statement:
+                    PreProcessedStatement::Data(_)
+                    | PreProcessedStatement::Words(_)
+                    | PreProcessedStatement::LiteralPool(_)
+                    | PreProcessedStatement::Checksum { .. },
+                ..
+            }) => Err(PreProcessError::EntryPointInData { span: span.clone() }),
+            Some(_) => Ok(()),
+            None => Err(PreProcessError::EntryPointWithoutCode { span: span.clone() }),
+        }
+    }
+
+    /// resolves and checks the size and fill value of a DEFS directive
+    fn space(
+        &self,
+        size: &Expression,
+        fill: Option<&Expression>,
+    ) -> Result<(usize, u8), PreProcessError> {
+        let resolve = |expression: &Expression| {
+            expression
+                .backwards_resolve(&self.symbol_table)
+                .map_err(|_| PreProcessError::SpaceExpressionError {
+                    span: expression.span.clone(),
+                })
+        };
+
+        let size_value = resolve(size)?;
+
+        // a size computed from labels, e.g. `DEFS end - start`, is negative if the labels are the wrong way round
+        // otherwise, the block must end at or before the top of the 32-bit address space
+        if (size_value as i32) < 0 || self.address as u64 + size_value as u64 > 1 << 32 {
+            return Err(PreProcessError::SpaceSizeOutOfRange {
+                span: size.span.clone(),
+                size: size_value,
+            });
+        }
+
+        let fill = match fill {
+            Some(fill) => {
+                let fill_value = resolve(fill)?;
+
+                u8::try_from(fill_value).map_err(|_| PreProcessError::SpaceFillOutOfRange {
+                    span: fill.span.clone(),
+                    fill: fill_value,
+                })?
+            }
+            None => 0,
+        };
+
+        Ok((size_value as usize, fill))
+    }
+
+    /// inserts the queued labels into the symbol table at the current address
+    fn insert_labels(&mut self) {
         for label in self.label_queue.drain(..) {
             self.symbol_table.insert(
                 Symbol(label),
-                Expression::Number {
+                Expression::from(ExpressionKind::Number {
                     base: 10,
                     n: self.address as u32,
-                },
+                }),
             );
         }
+    }
+
+    /// inserts a MOV32 of a known value, whose size depends on the value
+    fn insert_move_constant(
+        &mut self,
+        condition: Condition,
+        destination: Rd,
+        value: u32,
+        source_line: usize,
+        span: &Span,
+    ) -> Result<(), PreProcessError> {
+        let size = 4 * synthesise_constant(condition, destination, value).len();
+
+        // the folded value is kept, so symbols defined later can't change the size
+        self.insert_addressed_statement(
+            PreProcessedStatement::PseudoInstruction {
+                kind: PseudoInstructionKind::MoveConstant {
+                    condition,
+                    destination,
+                    value: Expression::from(ExpressionKind::Number { base: 16, n: value }),
+                },
+            },
+            size,
+            source_line,
+            span,
+        )
+    }
+
+    /// moves to the next free address of the region a section is placed in
+    fn enter_section(&mut self, section: String, span: Span) -> Result<(), PreProcessError> {
+        let region = match self.layout.placement(&section) {
+            Some(region) => region.clone(),
+            None => return Err(PreProcessError::UnplacedSection { span, section }),
+        };
+
+        // remember where the region being left got up to
+        if let Some(previous) = self.region.take() {
+            self.region_addresses.insert(previous.name, self.address);
+        }
 
-        self.source_map.insert(self.address, source_line);
+        self.address = match self.region_addresses.get(&region.name) {
+            Some(address) => *address,
+            None => region.base as usize,
+        };
+        self.region = Some(region);
+
+        Ok(())
+    }
 
-        self.statements.push((self.address, statement));
+    fn insert_addressed_statement(
+        &mut self,
+        statement: PreProcessedStatement,
+        size: usize,
+        source_line: usize,
+        span: &Span,
+    ) -> Result<(), PreProcessError> {
+        self.make_room(size, source_line, span)?;
+        self.insert_labels();
+
+        self.push_statement(statement, size, source_line, span)
+    }
+
+    /// adds a statement at the current address, without inserting the queued labels
+    fn push_statement(
+        &mut self,
+        statement: PreProcessedStatement,
+        size: usize,
+        source_line: usize,
+        span: &Span,
+    ) -> Result<(), PreProcessError> {
+        self.check_region(size, span)?;
+
+        self.source_map
+            .insert(self.address..self.address + size, source_line);
+
+        self.append_statement(statement, size, source_line, span);
+
+        Ok(())
+    }
+
+    /// checks that `size` bytes at the current address fit in the region of the current section
+    fn check_region(&self, size: usize, span: &Span) -> Result<(), PreProcessError> {
+        if let Some(region) = &self.region {
+            let range = region.range();
+
+            if self.address < range.start || self.address + size > range.end {
+                return Err(PreProcessError::OutsideRegion {
+                    span: span.clone(),
+                    region: region.name.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// adds a statement at the current address, without checking it or adding it to the source map
+    fn append_statement(
+        &mut self,
+        statement: PreProcessedStatement,
+        size: usize,
+        source_line: usize,
+        span: &Span,
+    ) {
+        self.statements.push(AddressedStatement {
+            address: self.address,
+            statement,
+            span: span.clone(),
+            source_line,
+        });
 
         self.address += size;
     }
@@ -1,10 +1,13 @@
-use crate::ir::{CalculationKind, Condition, DataProcessingKind, InstructionKind, Rd, Rn};
+use crate::assembler::Program;
+use crate::ir::{
+    ByteOrder, CalculationKind, Condition, DataProcessingKind, InstructionKind, Rd, Rn,
+};
 use crate::parser::{
-    DiadicOperator, DirectiveKind, Expression, Line, PseudoInstructionKind, Register, SetFlags,
-    ShifterOperandExpression, Statement, Symbol,
+    DiadicOperator, DirectiveKind, Expression, ExpressionKind, Line, Parser, PseudoInstructionKind,
+    Register, SetFlags, ShifterOperandExpression, Statement, StatementKind, Symbol,
 };
 
-use crate::preprocessor::PreProcessor;
+use crate::preprocessor::{PreProcessError, PreProcessedStatement, PreProcessor};
 
 #[test]
 fn test_label_adrl_example() {
@@ -12,16 +15,18 @@ fn test_label_adrl_example() {
         Ok(Line {
             label: Some("func".to_owned()),
             statement: None,
+            ..Line::default()
         }),
         Ok(Line {
             label: None,
-            statement: Some(Statement::PseudoInstruction {
+            statement: Some(Statement::from(StatementKind::PseudoInstruction {
                 kind: PseudoInstructionKind::LoadRegisterConstant {
                     condition: Condition::AL,
                     destination: Rd(15),
-                    value: Expression::Symbol(Symbol("func".to_owned())),
+                    value: Expression::from(ExpressionKind::Symbol(Symbol("func".to_owned()))),
                 },
-            }),
+            })),
+            ..Line::default()
         }),
     ];
 
@@ -35,7 +40,7 @@ fn test_origin() {
     let lines = [
         Ok(Line {
             label: Some("func".to_owned()),
-            statement: Some(Statement::Instruction {
+            statement: Some(Statement::from(StatementKind::Instruction {
                 kind: InstructionKind::DataProcessing {
                     condition: Condition::AL,
                     kind: DataProcessingKind::Calculation {
@@ -46,23 +51,25 @@ fn test_origin() {
                         shifter: ShifterOperandExpression::Register(Register(2)),
                     },
                 },
-            }),
+            })),
+            ..Line::default()
         }),
         Ok(Line {
             label: None,
-            statement: Some(Statement::Directive {
+            statement: Some(Statement::from(StatementKind::Directive {
                 kind: DirectiveKind::Origin {
-                    address: Expression::Diadic(
-                        Box::new(Expression::Symbol(Symbol("func".to_owned()))),
+                    address: Expression::from(ExpressionKind::Diadic(
+                        Box::new(ExpressionKind::Symbol(Symbol("func".to_owned())).into()),
                         DiadicOperator::Plus,
-                        Box::new(Expression::Number { base: 10, n: 12 }),
-                    ),
+                        Box::new(ExpressionKind::Number { base: 10, n: 12 }.into()),
+                    )),
                 },
-            }),
+            })),
+            ..Line::default()
         }),
         Ok(Line {
             label: Some("func".to_owned()),
-            statement: Some(Statement::Instruction {
+            statement: Some(Statement::from(StatementKind::Instruction {
                 kind: InstructionKind::DataProcessing {
                     condition: Condition::AL,
                     kind: DataProcessingKind::Calculation {
@@ -73,7 +80,8 @@ fn test_origin() {
                         shifter: ShifterOperandExpression::Register(Register(2)),
                     },
                 },
-            }),
+            })),
+            ..Line::default()
         }),
     ];
 
@@ -81,3 +89,114 @@ fn test_origin() {
 
     insta::assert_debug_snapshot!(result);
 }
+
+#[test]
+fn test_space() {
+    let source = "start\tDEFB 1, 2\nend\tDEFS end - start, 'a'\n\tDEFS 0x3, 0xFF";
+
+    let result = PreProcessor::new().run(Parser::new(source)).unwrap();
+
+    let data: Vec<_> = result
+        .statements
+        .iter()
+        .map(|statement| match &statement.statement {
+            PreProcessedStatement::Data(bytes) => (statement.address, bytes.to_vec()),
+            statement => panic!("expected data, found {statement:?}"),
+        })
+        .collect();
+
+    assert_eq!(
+        data,
+        [
+            (0, vec![1, 2]),
+            (2, vec![b'a', b'a']),
+            (4, vec![0xFF, 0xFF, 0xFF])
+        ]
+    );
+}
+
+#[test]
+fn test_space_errors() {
+    let error = |source| PreProcessor::new().run(Parser::new(source)).unwrap_err();
+
+    // the fill value must fit in a byte
+    assert!(matches!(
+        error("\tDEFS 4, 256"),
+        PreProcessError::SpaceFillOutOfRange { fill: 256, span } if span.byte_range == (9..12)
+    ));
+
+    // the size can't be negative
+    assert!(matches!(
+        error("low\tDEFS 4\nhigh\tDEFS 4\n\tDEFS low - high"),
+        PreProcessError::SpaceSizeOutOfRange {
+            size: 0xFFFFFFFC,
+            ..
+        }
+    ));
+
+    // the size can only use symbols defined before it
+    assert!(matches!(
+        error("\tDEFS end\nend\tDEFB 1"),
+        PreProcessError::SpaceExpressionError { span } if span.byte_range == (6..9)
+    ));
+}
+
+#[test]
+fn test_entry_point_errors() {
+    let run = |source| PreProcessor::new().run(Parser::new(source));
+
+    assert_eq!(run("\tDEFW 1\n\tENTRY\n\tSVC 2").unwrap().entry_point, 4);
+
+    // the conflict is reported with both directives
+    assert!(matches!(
+        run("\tENTRY\n\tSVC 1\n\tENTRY\n\tSVC 2").unwrap_err(),
+        PreProcessError::MultipleEntryPoints { span, previous }
+            if span.byte_range == (15..20) && previous.byte_range == (1..6)
+    ));
+
+    assert!(matches!(
+        run("\tENTRY\nvalue\tDEFW 1").unwrap_err(),
+        PreProcessError::EntryPointInData { span } if span.byte_range == (1..6)
+    ));
+
+    assert!(matches!(
+        run("\tSVC 2\n\tENTRY").unwrap_err(),
+        PreProcessError::EntryPointWithoutCode { .. }
+    ));
+}
+
+#[test]
+fn test_emit_preprocessed_source() {
+    let source = "\tLDR R0, =value\n\tLDR R1, =0x12345678\n\tLTORG\nloop\tADD R0, R0, #1\n\tDEFB 1, 2, 3\n\tALIGN\n\tORIGIN 0x100\n\tENTRY\nmain\tLDR R2, =loop\n\tMOV32 R3, #0x00FF00FF\n\tDEFS 4\nvalue\tEQU 0x1000 + 4\n";
+
+    let result = PreProcessor::new().run(Parser::new(source)).unwrap();
+
+    assert_eq!(
+        result.emit_source(),
+        "\tLDR R0, =value\n\tLDR R1, =0x12345678\n\tLTORG\nloop\n\tADD R0, R0, #1\n\tDEFB 1, 2, 3\n\tDEFB 0\n\tORIGIN 0x100\nmain\n\tENTRY\n\tMOV R2, #loop\n\tMOV32 R3, #0xFF00FF\n\tDEFS 4\nvalue\tEQU 0x1000 + 4\n"
+    );
+
+    // the emitted source assembles into the same program
+    let assemble = |source: &str| {
+        let program = Program::assemble(source).unwrap();
+        let bytes: Vec<(usize, Vec<u8>)> = program
+            .statements
+            .iter()
+            .map(|statement| (statement.address, statement.bytes.to_vec()))
+            .collect();
+
+        (bytes, program.entry_point)
+    };
+
+    assert_eq!(assemble(&result.emit_source()), assemble(source));
+
+    // a pool placed early is branched over
+    let source = "\tLDR R0, =0x12345678\n\tDEFS 5000\n\tSVC 2\n";
+    let emitted = PreProcessor::new()
+        .run(Parser::new(source))
+        .unwrap()
+        .emit_source();
+
+    assert!(emitted.contains("\tB skip_"));
+    assert_eq!(assemble(&emitted), assemble(source));
+}
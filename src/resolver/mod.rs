@@ -1,23 +1,25 @@
 // NOTE: for each statement, lets just return an iterator over a Vec, because there could be an indefinite number of bytes in a data def
 // use a smallvec for optimisations!!
 
-use std::collections::HashMap;
+use std::{collections::HashMap, iter};
 
 use smallvec::{smallvec, SmallVec};
 
 use crate::{
     assembler::AssemblyError,
     ir::{
-        AddressingOffset, Imm, Rm, Rn, RotatedImm8, ShiftedRegister, ShifterOperandCode, SignedImm,
-        UnencodableValueError,
+        AddressingOffset, Condition, Imm, ImmediateEncoding, Rd, Rm, Rn, RotatedImm8,
+        ShiftedRegister, ShifterOperandCode, SignedImm, UnencodableValueError,
     },
     parser::{
-        AddressingOffsetValue, CalculationKind, DataProcessingKind, DiadicOperator, Expression,
-        InstructionKind, LoadStoreAddress, LoadStoreAddressCode, MoveKind, OffsetMode,
-        PseudoInstructionKind, SetFlags, Shift, ShifterOperandExpression,
-        ShifterOperandShiftAmount, Sign, StatementInstructionKind, Symbol,
+        AddressingOffsetValue, BranchKind, CalculationKind, DataProcessingKind, DiadicOperator,
+        Expression, ExpressionKind, InstructionKind, LoadStoreAddress, LoadStoreAddressCode,
+        LoadStoreKind, LoadStoreQuantity, MoveKind, OffsetMode, PseudoInstructionKind, SetFlags,
+        Shift, ShifterOperandExpression, ShifterOperandShiftAmount, Sign, StatementInstructionKind,
+        Symbol, WordDefinition,
     },
     preprocessor::{PreProcessResult, PreProcessedStatement},
+    span::Span,
 };
 
 #[derive(Debug)]
@@ -25,6 +27,61 @@ pub enum ResolveError {
     SymbolNotFound(SymbolNotFoundError),
     UnencodableSignedValue(UnencodableValueError<i32>),
     UnencodableValue(UnencodableValueError<u32>),
+    /// the target of a BLX isn't halfword aligned
+    UnalignedBranchTarget(u32),
+    /// an immediate shift amount is more than the shift allows, see [`Shift::max_amount`]
+    ShiftOutOfRange {
+        kind: Shift,
+        amount: u32,
+    },
+    /// the end label of a CHECKSUM or CRC32 is before its start label
+    ChecksumRange {
+        start: u32,
+        end: u32,
+    },
+}
+
+impl ResolveError {
+    /// the value and the encoding it didn't fit, if the error is an unencodable value
+    pub fn unencodable(&self) -> Option<(i64, ImmediateEncoding)> {
+        match self {
+            ResolveError::UnencodableValue(error) => Some((error.value.into(), error.encoding)),
+            ResolveError::UnencodableSignedValue(error) => {
+                Some((error.value.into(), error.encoding))
+            }
+            ResolveError::SymbolNotFound(_)
+            | ResolveError::UnalignedBranchTarget(_)
+            | ResolveError::ShiftOutOfRange { .. }
+            | ResolveError::ChecksumRange { .. } => None,
+        }
+    }
+}
+
+/// a statement with a value that can't be encoded, one of all the ones found while resolving a program
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnencodableStatement {
+    pub source_line: usize,
+    pub span: Span,
+    /// the value after its expression is folded, in the units it is encoded in, e.g. words for a branch offset
+    pub value: i64,
+    pub encoding: ImmediateEncoding,
+    /// the nearest values that can be encoded, below and above the value
+    pub alternatives: Vec<i64>,
+}
+
+impl UnencodableStatement {
+    /// the unencodable statement for a resolve error, if it is an unencodable value
+    pub fn new(error: &ResolveError, source_line: usize, span: Span) -> Option<Self> {
+        let (value, encoding) = error.unencodable()?;
+
+        Some(Self {
+            source_line,
+            span,
+            value,
+            encoding,
+            alternatives: encoding.nearest(value),
+        })
+    }
 }
 
 impl From<SymbolNotFoundError> for ResolveError {
@@ -46,7 +103,7 @@ impl From<UnencodableValueError<u32>> for ResolveError {
 }
 
 pub enum ResolvedStatement {
-    // currently 2 is the upper limit for the number of instructions in a statement, so this should never allocate
+    // most statements generate at most 2 instructions, so this only allocates for a MOV32 of more than 2
     Instructions(SmallVec<[InstructionKind; 2]>),
     // most data statements are only a word, but this allows for more (at the cost of an allocation)
     // won't allocate in the average case
@@ -56,30 +113,111 @@ pub enum ResolvedStatement {
 #[derive(Debug)]
 pub struct SymbolNotFoundError;
 
-#[derive(Debug)]
+/// whether symbols are matched case-sensitively
+///
+/// Mnemonics and registers are always case-insensitive, but some courses treat labels the same way
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolCase {
+    /// `loop` and `Loop` are different symbols
+    #[default]
+    Sensitive,
+    /// `loop` and `Loop` are the same symbol
+    Insensitive,
+    /// `loop` and `Loop` are the same symbol, but referring to a symbol with different case to its definition is warned about
+    InsensitiveWithWarnings,
+}
+
+#[derive(Debug, Clone)]
 pub struct SymbolTable<E> {
+    /// the symbols, as spelled where they were defined
     table: HashMap<Symbol, E>,
+    /// maps the lowercase form of each symbol to its spelling in `table`
+    folded: HashMap<String, Symbol>,
+    case: SymbolCase,
+}
+
+impl<E> Default for SymbolTable<E> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<E> SymbolTable<E> {
     pub fn new() -> Self {
+        Self::with_case(SymbolCase::default())
+    }
+
+    pub fn with_case(case: SymbolCase) -> Self {
         Self {
             table: HashMap::new(),
+            folded: HashMap::new(),
+            case,
         }
     }
 
+    pub fn case(&self) -> SymbolCase {
+        self.case
+    }
+
+    /// the number of symbols defined
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// the symbols, as spelled where they were defined, and their values
+    pub fn iter(&self) -> impl Iterator<Item = (&Symbol, &E)> {
+        self.table.iter()
+    }
+
     pub fn insert(&mut self, symbol: Symbol, value: E) {
+        let previous = self.folded.insert(symbol.0.to_lowercase(), symbol.clone());
+
+        // when case is ignored, a symbol replaces any definition of it with different case
+        if let Some(previous) = previous {
+            if self.case != SymbolCase::Sensitive && previous != symbol {
+                self.table.remove(&previous);
+            }
+        }
+
         self.table.insert(symbol, value);
     }
 
     pub fn get(&self, symbol: &Symbol) -> Option<&E> {
-        self.table.get(symbol)
+        self.definition(symbol).map(|(_, value)| value)
+    }
+
+    /// the symbol as spelled where it was defined, along with its value
+    pub fn definition(&self, symbol: &Symbol) -> Option<(&Symbol, &E)> {
+        match self.case {
+            SymbolCase::Sensitive => self.table.get_key_value(symbol),
+            SymbolCase::Insensitive | SymbolCase::InsensitiveWithWarnings => self
+                .folded
+                .get(&symbol.0.to_lowercase())
+                .and_then(|definition| self.table.get_key_value(definition)),
+        }
+    }
+
+    /// the spelling of a defined symbol that only differs from `symbol` by case, if there is one
+    ///
+    /// When case is ignored this is a reference that works but is spelled inconsistently, and otherwise it is a likely typo
+    pub fn case_mismatch(&self, symbol: &Symbol) -> Option<&Symbol> {
+        if self.case == SymbolCase::Sensitive && self.table.contains_key(symbol) {
+            return None;
+        }
+
+        self.folded
+            .get(&symbol.0.to_lowercase())
+            .filter(|definition| *definition != symbol)
     }
 }
 
 impl SymbolTable<Expression> {
     pub fn resolve(&self) -> Result<SymbolTable<u32>, ResolveError> {
-        let mut resolved_table = SymbolTable::new();
+        let mut resolved_table = SymbolTable::with_case(self.case);
 
         for (symbol, expression) in self.table.iter() {
             let value = expression.resolve_and_insert(&self, &mut resolved_table)?;
@@ -105,7 +243,89 @@ impl PreProcessedStatement {
             PreProcessedStatement::PseudoInstruction { kind } => Ok(
                 ResolvedStatement::Instructions(kind.resolve(&symbol_table, address)?),
             ),
-            PreProcessedStatement::Data(data) => Ok(ResolvedStatement::Data(data)),
+            PreProcessedStatement::Data(data) | PreProcessedStatement::Padding(data) => {
+                Ok(ResolvedStatement::Data(data))
+            }
+            PreProcessedStatement::Words(words) => Ok(ResolvedStatement::Data(
+                words
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, word)| {
+                        let value = match word {
+                            WordDefinition::Word(word) => word,
+                            WordDefinition::Address(symbol) => {
+                                Expression::from(ExpressionKind::Symbol(symbol))
+                                    .resolve(symbol_table)?
+                            }
+                            WordDefinition::Offset(symbol) => {
+                                Expression::from(ExpressionKind::Symbol(symbol))
+                                    .resolve(symbol_table)?
+                                    .wrapping_sub((address + 4 * index) as u32)
+                            }
+                            WordDefinition::Expression(expression) => {
+                                expression.resolve(symbol_table)?
+                            }
+                        };
+
+                        Ok(value.to_be_bytes())
+                    })
+                    .collect::<Result<Vec<_>, ResolveError>>()?
+                    .into_iter()
+                    .flatten()
+                    .collect(),
+            )),
+            // the checksum is filled in once every other statement has been resolved
+            PreProcessedStatement::Checksum { start, end, .. } => {
+                let start =
+                    Expression::from(ExpressionKind::Symbol(start)).resolve(symbol_table)?;
+                let end = Expression::from(ExpressionKind::Symbol(end)).resolve(symbol_table)?;
+
+                if end < start {
+                    return Err(ResolveError::ChecksumRange { start, end });
+                }
+
+                Ok(ResolvedStatement::Data(smallvec![0; 4]))
+            }
+            PreProcessedStatement::LoadLiteral {
+                condition,
+                destination,
+                literal,
+            } => {
+                let load: StatementInstructionKind = InstructionKind::LoadStore {
+                    condition,
+                    kind: LoadStoreKind::Load,
+                    quantity: LoadStoreQuantity::Word,
+                    destination,
+                    address: LoadStoreAddress::Expression(Expression::from(
+                        ExpressionKind::Number {
+                            base: 16,
+                            n: literal as u32,
+                        },
+                    )),
+                };
+
+                Ok(ResolvedStatement::Instructions(smallvec![
+                    load.resolve(symbol_table, address)?
+                ]))
+            }
+            PreProcessedStatement::LiteralPool(values) => Ok(ResolvedStatement::Data(
+                values
+                    .into_iter()
+                    .map(|value| Ok(value.resolve(symbol_table)?.to_be_bytes()))
+                    .collect::<Result<Vec<_>, ResolveError>>()?
+                    .into_iter()
+                    .flatten()
+                    .collect(),
+            )),
+            PreProcessedStatement::Skip { target } => {
+                Ok(ResolvedStatement::Instructions(smallvec![
+                    InstructionKind::Branch {
+                        condition: Condition::AL,
+                        kind: BranchKind::Branch,
+                        target: SignedImm::try_from((target as i32 - address as i32) >> 2)?,
+                    }
+                ]))
+            }
         }
     }
 }
@@ -135,6 +355,26 @@ impl StatementInstructionKind {
                 })
             }
 
+            InstructionKind::BranchWithLinkAndExchange { target, .. } => {
+                let target_address = target.resolve(symbol_table)?;
+
+                if target_address % 2 != 0 {
+                    return Err(ResolveError::UnalignedBranchTarget(target_address));
+                }
+
+                let offset = target_address as i32 - current_address as i32;
+
+                // Thumb code is halfword-aligned, so bit 1 of the offset is kept in the H bit
+                Ok(InstructionKind::BranchWithLinkAndExchange {
+                    target: SignedImm::try_from(offset >> 2)?,
+                    halfword: offset & 0b10 != 0,
+                })
+            }
+
+            InstructionKind::BranchAndExchange { condition, target } => {
+                Ok(InstructionKind::BranchAndExchange { condition, target })
+            }
+
             InstructionKind::DataProcessing { condition, kind } => {
                 Ok(InstructionKind::DataProcessing {
                     condition,
@@ -142,6 +382,38 @@ impl StatementInstructionKind {
                 })
             }
 
+            InstructionKind::Multiply {
+                condition,
+                set_flags,
+                destination,
+                multiplicand,
+                multiplier,
+                accumulate,
+            } => Ok(InstructionKind::Multiply {
+                condition,
+                set_flags,
+                destination,
+                multiplicand,
+                multiplier,
+                accumulate,
+            }),
+
+            InstructionKind::MultiplyLong {
+                condition,
+                kind,
+                set_flags,
+                destination,
+                multiplicand,
+                multiplier,
+            } => Ok(InstructionKind::MultiplyLong {
+                condition,
+                kind,
+                set_flags,
+                destination,
+                multiplicand,
+                multiplier,
+            }),
+
             InstructionKind::LoadStore {
                 condition,
                 kind,
@@ -156,6 +428,10 @@ impl StatementInstructionKind {
                 address: address.resolve(symbol_table, current_address)?,
             }),
 
+            InstructionKind::Preload { address } => Ok(InstructionKind::Preload {
+                address: address.resolve(symbol_table, current_address)?,
+            }),
+
             InstructionKind::LoadStoreMultiple {
                 condition,
                 kind,
@@ -179,6 +455,34 @@ impl StatementInstructionKind {
                 condition,
                 immediate: Imm::try_from(immediate.resolve(symbol_table)?)?,
             }),
+
+            InstructionKind::MoveStatusToRegister {
+                condition,
+                destination,
+            } => Ok(InstructionKind::MoveStatusToRegister {
+                condition,
+                destination,
+            }),
+
+            InstructionKind::MoveToStatusRegister {
+                condition,
+                fields,
+                source,
+            } => Ok(InstructionKind::MoveToStatusRegister {
+                condition,
+                fields,
+                source: source.resolve(symbol_table)?,
+            }),
+
+            InstructionKind::CountLeadingZeros {
+                condition,
+                destination,
+                source,
+            } => Ok(InstructionKind::CountLeadingZeros {
+                condition,
+                destination,
+                source,
+            }),
         }
     }
 }
@@ -250,9 +554,11 @@ impl ShifterOperandExpression {
             ShifterOperandExpression::ShiftedRegister(ShiftedRegister { kind, amount, base }) => {
                 match amount {
                     ShifterOperandShiftAmount::Immediate(immediate) => {
+                        let (kind, amount) = shift_amount(kind, immediate.resolve(symbol_table)?)?;
+
                         Ok(ShifterOperandCode::ImmediateShift(ShiftedRegister {
                             kind,
-                            amount: Imm::try_from(immediate.resolve(symbol_table)?)?,
+                            amount,
                             base: base.into(),
                         }))
                     }
@@ -344,9 +650,11 @@ impl AddressingOffsetValue<Expression, Expression> {
             }
 
             AddressingOffsetValue::ScaledRegister(ShiftedRegister { kind, amount, base }) => {
+                let (kind, amount) = shift_amount(kind, amount.resolve(symbol_table)?)?;
+
                 Ok(AddressingOffsetValue::ScaledRegister(ShiftedRegister {
                     kind,
-                    amount: Imm::try_from(amount.resolve(symbol_table)?)?,
+                    amount,
                     base,
                 }))
             }
@@ -354,6 +662,12 @@ impl AddressingOffsetValue<Expression, Expression> {
     }
 }
 
+/// encodes an immediate shift amount, see [`Shift::encode_amount`]
+fn shift_amount(kind: Shift, amount: u32) -> Result<(Shift, Imm<5>), ResolveError> {
+    kind.encode_amount(amount)
+        .ok_or(ResolveError::ShiftOutOfRange { kind, amount })
+}
+
 impl PseudoInstructionKind {
     fn resolve(
         self,
@@ -377,6 +691,16 @@ impl PseudoInstructionKind {
                 },
             }]),
 
+            PseudoInstructionKind::MoveConstant {
+                condition,
+                destination,
+                value,
+            } => Ok(synthesise_constant(
+                condition,
+                destination,
+                value.resolve(symbol_table)?,
+            )),
+
             PseudoInstructionKind::AddressRegister {
                 condition,
                 long,
@@ -394,7 +718,10 @@ impl PseudoInstructionKind {
 
                     // if the remainder can't be encoded, then the value in unencodable
                     let remainder_immediate = RotatedImm8::try_from(remainder.abs() as u32)
-                        .map_err(|_| UnencodableValueError { value })?;
+                        .map_err(|_| UnencodableValueError {
+                            value,
+                            encoding: ImmediateEncoding::Rotated,
+                        })?;
 
                     Ok(smallvec![
                         // the first instruction gets us as near as possible
@@ -470,15 +797,59 @@ impl PseudoInstructionKind {
     }
 }
 
+/// the shortest sequence of instructions that builds `value` in a register, for a MOV32
+///
+/// The value is split into 8-bit parts that can each be an immediate. It is built with a MOV of one part and an ADD of each other part,
+/// or, if its inverse has fewer parts, with an MVN of one part of the inverse and a SUB of each other part, as `!(a + b) == !a - b`.
+pub fn synthesise_constant(
+    condition: Condition,
+    destination: Rd,
+    value: u32,
+) -> SmallVec<[InstructionKind; 2]> {
+    let parts = RotatedImm8::split(value);
+    let inverse_parts = RotatedImm8::split(!value);
+
+    let (kind, calculation, parts) = if inverse_parts.len() < parts.len() {
+        (MoveKind::MoveNot, CalculationKind::SUB, inverse_parts)
+    } else {
+        (MoveKind::Move, CalculationKind::ADD, parts)
+    };
+
+    let mut parts = parts.into_iter();
+
+    let first = InstructionKind::DataProcessing {
+        condition,
+        kind: DataProcessingKind::Move {
+            kind,
+            set_flags: SetFlags::DontSet,
+            destination,
+            shifter: ShifterOperandCode::Immediate(parts.next().unwrap()),
+        },
+    };
+
+    iter::once(first)
+        .chain(parts.map(|part| InstructionKind::DataProcessing {
+            condition,
+            kind: DataProcessingKind::Calculation {
+                kind: calculation.clone(),
+                set_flags: SetFlags::DontSet,
+                destination,
+                source: Rn(destination.0),
+                shifter: ShifterOperandCode::Immediate(part),
+            },
+        }))
+        .collect()
+}
+
 impl Expression {
     pub fn resolve(self, symbol_table: &SymbolTable<u32>) -> Result<u32, ResolveError> {
-        match self {
-            Expression::Number { n, base: _ } => Ok(n),
-            Expression::Character(c) => Ok(c as u32),
-            Expression::String(s) => Ok(s.as_bytes()[0] as u32),
-            Expression::Boolean(b) => Ok(b as u32),
-            Expression::Symbol(symbol) => Ok(symbol.resolve(symbol_table)?),
-            Expression::Diadic(lhs, operator, rhs) => {
+        match self.kind {
+            ExpressionKind::Number { n, base: _ } => Ok(n),
+            ExpressionKind::Character(c) => Ok(c as u32),
+            ExpressionKind::String(s) => Ok(s.as_bytes()[0] as u32),
+            ExpressionKind::Boolean(b) => Ok(b as u32),
+            ExpressionKind::Symbol(symbol) => Ok(symbol.resolve(symbol_table)?),
+            ExpressionKind::Diadic(lhs, operator, rhs) => {
                 let lhs = lhs.resolve(symbol_table)?;
                 let rhs = rhs.resolve(symbol_table)?;
 
@@ -495,15 +866,15 @@ impl Expression {
         unresolved_table: &SymbolTable<Expression>,
         resolved_table: &mut SymbolTable<u32>,
     ) -> Result<u32, ResolveError> {
-        match self {
-            Expression::Number { n, base: _ } => Ok(*n),
-            Expression::Character(c) => Ok(*c as u32),
-            Expression::String(s) => Ok(s.as_bytes()[0] as u32),
-            Expression::Boolean(b) => Ok(*b as u32),
-            Expression::Symbol(symbol) => {
+        match &self.kind {
+            ExpressionKind::Number { n, base: _ } => Ok(*n),
+            ExpressionKind::Character(c) => Ok(*c as u32),
+            ExpressionKind::String(s) => Ok(s.as_bytes()[0] as u32),
+            ExpressionKind::Boolean(b) => Ok(*b as u32),
+            ExpressionKind::Symbol(symbol) => {
                 Ok(symbol.resolve_and_insert(unresolved_table, resolved_table)?)
             }
-            Expression::Diadic(lhs, operator, rhs) => {
+            ExpressionKind::Diadic(lhs, operator, rhs) => {
                 let lhs = lhs.resolve_and_insert(unresolved_table, resolved_table)?;
                 let rhs = rhs.resolve_and_insert(unresolved_table, resolved_table)?;
 
@@ -520,19 +891,20 @@ impl Expression {
         &self,
         symbol_table: &SymbolTable<Expression>,
     ) -> Result<u32, ResolveError> {
-        match self {
-            Expression::Number { n, base: _ } => Ok(*n),
-            Expression::Character(c) => Ok(*c as u32),
-            Expression::String(s) => Ok(s.as_bytes()[0] as u32),
-            Expression::Boolean(b) => Ok(*b as u32),
-            Expression::Symbol(symbol) => Ok(symbol.backwards_resolve(symbol_table)?),
-            Expression::Diadic(lhs, operator, rhs) => {
+        match &self.kind {
+            ExpressionKind::Number { n, base: _ } => Ok(*n),
+            ExpressionKind::Character(c) => Ok(*c as u32),
+            ExpressionKind::String(s) => Ok(s.as_bytes()[0] as u32),
+            ExpressionKind::Boolean(b) => Ok(*b as u32),
+            ExpressionKind::Symbol(symbol) => Ok(symbol.backwards_resolve(symbol_table)?),
+            ExpressionKind::Diadic(lhs, operator, rhs) => {
                 let lhs = lhs.backwards_resolve(symbol_table)?;
                 let rhs = rhs.backwards_resolve(symbol_table)?;
 
+                // wrap like 32-bit arithmetic, so e.g. a negative DEFS size is reported as out of range rather than panicking
                 match operator {
-                    DiadicOperator::Plus => Ok(lhs + rhs),
-                    DiadicOperator::Minus => Ok(lhs - rhs),
+                    DiadicOperator::Plus => Ok(lhs.wrapping_add(rhs)),
+                    DiadicOperator::Minus => Ok(lhs.wrapping_sub(rhs)),
                 }
             }
         }
@@ -552,11 +924,11 @@ impl Symbol {
         unresolved_table: &SymbolTable<Expression>,
         resolved_table: &mut SymbolTable<u32>,
     ) -> Result<u32, ResolveError> {
-        match unresolved_table.get(&self) {
-            Some(expression) => {
+        match unresolved_table.definition(&self) {
+            Some((definition, expression)) => {
                 let value = expression.resolve_and_insert(unresolved_table, resolved_table)?;
 
-                resolved_table.insert(self.clone(), value);
+                resolved_table.insert(definition.clone(), value);
 
                 Ok(value)
             }
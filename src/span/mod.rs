@@ -0,0 +1,58 @@
+use std::{fmt::Display, ops::Range};
+
+/// A location in the source, shared by every phase so that tokens, parsed lines, preprocessed statements and errors can all point back at the text that produced them
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Span {
+    /// the byte offsets of the spanned text in its file
+    pub byte_range: Range<usize>,
+    /// the zero-based line the span starts on
+    pub line: usize,
+    /// the zero-based column the span starts at, counted in characters
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(byte_range: Range<usize>, line: usize, column: usize) -> Span {
+        Span {
+            byte_range,
+            line,
+            column,
+        }
+    }
+
+    /// an empty span at a single position
+    pub fn empty(offset: usize, line: usize, column: usize) -> Span {
+        Span::new(offset..offset, line, column)
+    }
+
+    pub fn len(&self) -> usize {
+        self.byte_range.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.byte_range.is_empty()
+    }
+
+    /// a span from the start of this span to the end of `other`
+    pub fn to(&self, other: &Span) -> Span {
+        Span::new(
+            self.byte_range.start..self.byte_range.end.max(other.byte_range.end),
+            self.line,
+            self.column,
+        )
+    }
+
+    /// moves the span by a number of bytes and lines, such as when text is inserted or removed before it
+    pub fn shift(&mut self, bytes: isize, lines: isize) {
+        self.byte_range = self.byte_range.start.wrapping_add_signed(bytes)
+            ..self.byte_range.end.wrapping_add_signed(bytes);
+        self.line = self.line.wrapping_add_signed(lines);
+    }
+}
+
+impl Display for Span {
+    /// formats the span as a one-based `line:column` pair, the way editors display positions
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line + 1, self.column + 1)
+    }
+}